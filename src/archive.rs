@@ -0,0 +1,422 @@
+use xmltree::{Element};
+use error::{ColladaError};
+use inflate;
+use utils;
+
+const LOCAL_HEADER: u32 = 0x0403_4b50;
+const CENTRAL_HEADER: u32 = 0x0201_4b50;
+const END_OF_CENTRAL_DIRECTORY: u32 = 0x0605_4b50;
+
+/// Entry names are utf-8
+const FLAG_UTF8: u16 = 0x0800;
+const FLAG_ENCRYPTED: u16 = 0x0001;
+
+/// 1980-01-01 in MS-DOS format, the earliest date a zip entry can have, so
+/// written archives only depend on their content
+const DOS_DATE: u16 = 0x0021;
+
+/// The files of a zip archive such as a Google Earth KMZ or a COLLADA ZAE
+/// package, with their paths relative to the root of the archive. Only
+/// stored and deflated entries can be read and entries are always written
+/// stored.
+#[derive(Clone, Debug)]
+pub struct Archive {
+    files: Vec<(String, Vec<u8>)>,
+}
+
+impl Archive {
+    pub fn new() -> Archive {
+        Archive {
+            files: Vec::new(),
+        }
+    }
+
+    /// Read the files of a zip archive. Directory entries are skipped.
+    pub fn read(data: &[u8]) -> Result<Archive, ColladaError> {
+        let eocd = try!(find_end_of_central_directory(data));
+        let count = try!(read_u16(data, eocd + 10)) as usize;
+        let offset = try!(read_u32(data, eocd + 16));
+        if count == 0xffff || offset == 0xffff_ffff {
+            return Err(invalid("zip64 archives are not supported"));
+        }
+
+        let mut archive = Archive::new();
+        let mut pos = offset as usize;
+        for _ in 0..count {
+            if try!(read_u32(data, pos)) != CENTRAL_HEADER {
+                return Err(invalid("invalid central directory entry"));
+            }
+            let flags = try!(read_u16(data, pos + 8));
+            let method = try!(read_u16(data, pos + 10));
+            let crc = try!(read_u32(data, pos + 16));
+            let compressed = try!(read_u32(data, pos + 20)) as usize;
+            let size = try!(read_u32(data, pos + 24)) as usize;
+            let name_len = try!(read_u16(data, pos + 28)) as usize;
+            let extra_len = try!(read_u16(data, pos + 30)) as usize;
+            let comment_len = try!(read_u16(data, pos + 32)) as usize;
+            let local = try!(read_u32(data, pos + 42)) as usize;
+            let name = String::from_utf8_lossy(try!(read_bytes(data, pos + 46, name_len))).into_owned();
+            pos += 46 + name_len + extra_len + comment_len;
+
+            if name.ends_with('/') {
+                continue;
+            }
+            if flags & FLAG_ENCRYPTED != 0 {
+                return Err(invalid(&format!("{} is encrypted", name)));
+            }
+
+            if try!(read_u32(data, local)) != LOCAL_HEADER {
+                return Err(invalid(&format!("invalid local header for {}", name)));
+            }
+            let start = local + 30
+                + try!(read_u16(data, local + 26)) as usize
+                + try!(read_u16(data, local + 28)) as usize;
+            let raw = try!(read_bytes(data, start, compressed));
+            let content = match method {
+                0 => raw.to_vec(),
+                8 => match inflate::inflate(raw, size) {
+                    Ok(d) => d,
+                    Err(e) => return Err(invalid(&format!("{}: {}", name, e))),
+                },
+                _ => return Err(invalid(&format!("{} uses unsupported compression method {}", name, method))),
+            };
+            if content.len() != size || inflate::crc32(&content) != crc {
+                return Err(invalid(&format!("{} is corrupt", name)));
+            }
+            archive.insert(&name, content);
+        }
+        Ok(archive)
+    }
+
+    /// Write the files as a zip archive. Fails if the archive needs zip64,
+    /// i.e. it has 65535 files or more or is 4 GiB or larger.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, ColladaError> {
+        let count = try!(checked_u16(self.files.len(), "number of files"));
+        let mut out = Vec::new();
+        let mut central = Vec::new();
+        for &(ref name, ref data) in &self.files {
+            let offset = try!(checked_u32(out.len(), "archive"));
+            let name_len = try!(checked_u16(name.len(), name));
+            let size = try!(checked_u32(data.len(), name));
+            let crc = inflate::crc32(data);
+            write_u32(&mut out, LOCAL_HEADER);
+            write_header(&mut out, name_len, size, crc);
+            out.extend_from_slice(name.as_bytes());
+            out.extend_from_slice(data);
+
+            write_u32(&mut central, CENTRAL_HEADER);
+            write_u16(&mut central, 20);
+            write_header(&mut central, name_len, size, crc);
+            // Comment length, disk number and internal and external attributes
+            write_u16(&mut central, 0);
+            write_u16(&mut central, 0);
+            write_u16(&mut central, 0);
+            write_u32(&mut central, 0);
+            write_u32(&mut central, offset);
+            central.extend_from_slice(name.as_bytes());
+        }
+
+        let offset = try!(checked_u32(out.len(), "archive"));
+        let central_len = try!(checked_u32(central.len(), "central directory"));
+        try!(checked_u32(out.len() + central.len(), "archive"));
+        out.extend_from_slice(&central);
+        write_u32(&mut out, END_OF_CENTRAL_DIRECTORY);
+        write_u16(&mut out, 0);
+        write_u16(&mut out, 0);
+        write_u16(&mut out, count);
+        write_u16(&mut out, count);
+        write_u32(&mut out, central_len);
+        write_u32(&mut out, offset);
+        write_u16(&mut out, 0);
+        Ok(out)
+    }
+
+    /// Get the content of a file
+    pub fn get(&self, path: &str) -> Option<&[u8]> {
+        self.files.iter().find(|f| f.0 == path).map(|f| &f.1[..])
+    }
+
+    /// Add a file, replacing any file with the same path
+    pub fn insert(&mut self, path: &str, data: Vec<u8>) {
+        if let Some(f) = self.files.iter_mut().find(|f| f.0 == path) {
+            f.1 = data;
+            return;
+        }
+        self.files.push((path.to_string(), data));
+    }
+
+    pub fn remove(&mut self, path: &str) -> Option<Vec<u8>> {
+        match self.files.iter().position(|f| f.0 == path) {
+            Some(i) => Some(self.files.remove(i).1),
+            None => None,
+        }
+    }
+
+    /// Paths of all the files in the order they are stored
+    pub fn paths(&self) -> Vec<&str> {
+        self.files.iter().map(|f| f.0.as_str()).collect()
+    }
+
+    /// Path of the root document. This is the `<dae_root>` of a ZAE
+    /// `manifest.xml`, the model link of a KMZ `doc.kml` or otherwise the
+    /// first `.dae` file.
+    pub fn root(&self) -> Option<String> {
+        if let Some(e) = self.get("manifest.xml").and_then(|d| Element::parse(d).ok()) {
+            let root = if e.name == "dae_root" { Some(&e) } else { e.get_child("dae_root") };
+            if let Some(uri) = root.and_then(|r| r.text.as_ref()) {
+                return resolve_path("", uri);
+            }
+        }
+        if let Some(e) = self.get("doc.kml").and_then(|d| Element::parse(d).ok()) {
+            let href = find_element(&e, "Model")
+                .and_then(|m| m.get_child("Link"))
+                .and_then(|l| l.get_child("href"))
+                .and_then(|h| h.text.as_ref());
+            if let Some(uri) = href {
+                return resolve_path("doc.kml", uri);
+            }
+        }
+        self.files.iter()
+            .find(|f| f.0.to_lowercase().ends_with(".dae"))
+            .map(|f| f.0.clone())
+    }
+
+    /// Get the file a URI refers to, relative to the file at `base`, see
+    /// `resolve_path`
+    pub fn resolve(&self, base: &str, uri: &str) -> Option<&[u8]> {
+        resolve_path(base, uri).and_then(|p| self.get(&p))
+    }
+}
+
+/// Resolve a URI to a path in an archive. Relative references are resolved
+/// against the directory of the file at `base` and absolute paths against
+/// the root of the archive. The fragment is ignored and URIs with a scheme,
+/// e.g. `http:`, or that leave the archive give `None`.
+pub fn resolve_path(base: &str, uri: &str) -> Option<String> {
+    let uri = uri.trim();
+    let uri = match uri.find('#') {
+        Some(i) => &uri[..i],
+        None => uri,
+    };
+    if uri.is_empty() || utils::has_scheme(uri) {
+        return None;
+    }
+    // Windows tools write backslashes into KMZ links
+    let path = utils::percent_decode(uri).replace('\\', "/");
+
+    let mut segments: Vec<&str> = Vec::new();
+    if !path.starts_with('/') {
+        segments.extend(base.split('/'));
+        segments.pop();
+    }
+    for s in path.split('/') {
+        match s {
+            "" | "." => {},
+            ".." => {
+                if segments.pop().is_none() {
+                    return None;
+                }
+            },
+            _ => segments.push(s),
+        }
+    }
+    segments.retain(|s| !s.is_empty() && *s != ".");
+    if segments.is_empty() {
+        return None;
+    }
+    Some(segments.join("/"))
+}
+
+/// Find the first element with a name, depth first
+fn find_element<'a>(e: &'a Element, name: &str) -> Option<&'a Element> {
+    if e.name == name {
+        return Some(e);
+    }
+    e.children.iter().filter_map(|c| find_element(c, name)).next()
+}
+
+fn find_end_of_central_directory(data: &[u8]) -> Result<usize, ColladaError> {
+    if data.len() < 22 {
+        return Err(invalid("not a zip archive"));
+    }
+    // The record is at the end, followed by a comment of up to 64k
+    let last = data.len() - 22;
+    let first = last.saturating_sub(0xffff);
+    for pos in (first..last + 1).rev() {
+        if try!(read_u32(data, pos)) == END_OF_CENTRAL_DIRECTORY {
+            return Ok(pos);
+        }
+    }
+    Err(invalid("not a zip archive"))
+}
+
+/// The fields local and central headers share from the version needed
+fn write_header(out: &mut Vec<u8>, name_len: u16, size: u32, crc: u32) {
+    write_u16(out, 20);
+    write_u16(out, FLAG_UTF8);
+    write_u16(out, 0);
+    write_u16(out, 0);
+    write_u16(out, DOS_DATE);
+    write_u32(out, crc);
+    write_u32(out, size);
+    write_u32(out, size);
+    write_u16(out, name_len);
+    write_u16(out, 0);
+}
+
+/// A count or length that fits a 16 bit field. The largest value is left
+/// out, as it marks zip64 archives.
+fn checked_u16(v: usize, what: &str) -> Result<u16, ColladaError> {
+    if v >= 0xffff {
+        return Err(invalid(&format!("{} is too large for a zip archive", what)));
+    }
+    Ok(v as u16)
+}
+
+/// A size or offset that fits a 32 bit field, see `checked_u16`
+fn checked_u32(v: usize, what: &str) -> Result<u32, ColladaError> {
+    if v as u64 >= 0xffff_ffff {
+        return Err(invalid(&format!("{} is too large for a zip archive", what)));
+    }
+    Ok(v as u32)
+}
+
+fn read_bytes(data: &[u8], pos: usize, len: usize) -> Result<&[u8], ColladaError> {
+    match pos.checked_add(len) {
+        Some(end) if end <= data.len() => Ok(&data[pos..end]),
+        _ => Err(invalid("unexpected end of archive")),
+    }
+}
+
+fn read_u16(data: &[u8], pos: usize) -> Result<u16, ColladaError> {
+    let b = try!(read_bytes(data, pos, 2));
+    Ok(b[0] as u16 | (b[1] as u16) << 8)
+}
+
+fn read_u32(data: &[u8], pos: usize) -> Result<u32, ColladaError> {
+    let b = try!(read_bytes(data, pos, 4));
+    Ok(b[0] as u32 | (b[1] as u32) << 8 | (b[2] as u32) << 16 | (b[3] as u32) << 24)
+}
+
+fn write_u16(out: &mut Vec<u8>, v: u16) {
+    out.push(v as u8);
+    out.push((v >> 8) as u8);
+}
+
+fn write_u32(out: &mut Vec<u8>, v: u32) {
+    write_u16(out, v as u16);
+    write_u16(out, (v >> 16) as u16);
+}
+
+fn invalid(msg: &str) -> ColladaError {
+    ColladaError::InvalidArchive{msg: msg.to_string()}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use error::{ColladaError};
+
+    /// Written by python's zipfile, `a.txt` deflated and a `dir/` entry
+    const ZIP: [u8; 199] = [
+        0x50, 0x4b, 0x03, 0x04, 0x14, 0x00, 0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x21, 0x00, 0x34, 0x2a,
+        0x6e, 0x5a, 0x07, 0x00, 0x00, 0x00, 0x0c, 0x00, 0x00, 0x00, 0x05, 0x00, 0x00, 0x00, 0x61, 0x2e,
+        0x74, 0x78, 0x74, 0x4b, 0x4c, 0x4a, 0x4e, 0x84, 0x21, 0x00, 0x50, 0x4b, 0x03, 0x04, 0x14, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x21, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x64, 0x69, 0x72, 0x2f, 0x50, 0x4b, 0x01, 0x02,
+        0x14, 0x03, 0x14, 0x00, 0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x21, 0x00, 0x34, 0x2a, 0x6e, 0x5a,
+        0x07, 0x00, 0x00, 0x00, 0x0c, 0x00, 0x00, 0x00, 0x05, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x80, 0x01, 0x00, 0x00, 0x00, 0x00, 0x61, 0x2e, 0x74, 0x78, 0x74, 0x50,
+        0x4b, 0x01, 0x02, 0x14, 0x03, 0x14, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x21, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x80, 0x01, 0x2a, 0x00, 0x00, 0x00, 0x64, 0x69, 0x72,
+        0x2f, 0x50, 0x4b, 0x05, 0x06, 0x00, 0x00, 0x00, 0x00, 0x02, 0x00, 0x02, 0x00, 0x65, 0x00, 0x00,
+        0x00, 0x4c, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+
+    #[test]
+    fn test_archive_read() {
+        let a = Archive::read(&ZIP).unwrap();
+        assert_eq!(a.paths(), vec!["a.txt"]);
+        assert_eq!(a.get("a.txt"), Some(&b"abcabcabcabc"[..]));
+
+        let mut corrupt = ZIP;
+        corrupt[40] ^= 0xff;
+        assert!(Archive::read(&corrupt).is_err());
+        match Archive::read(&ZIP[..100]) {
+            Err(ColladaError::InvalidArchive{..}) => {},
+            r => panic!("unexpected result {:?}", r),
+        }
+
+        // The central directory declares 4 bytes for the 12 that inflate
+        let mut short = ZIP;
+        assert_eq!(short[0x4c + 24], 12);
+        short[0x4c + 24] = 4;
+        match Archive::read(&short) {
+            Err(ColladaError::InvalidArchive{msg}) => assert!(msg.contains("longer than 4 bytes"), "{}", msg),
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_archive_write_limits() {
+        let mut a = Archive::new();
+        a.insert(&"a".repeat(0x10000), Vec::new());
+        match a.to_bytes() {
+            Err(ColladaError::InvalidArchive{..}) => {},
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_archive_round_trip() {
+        let mut a = Archive::new();
+        a.insert("model.dae", b"<COLLADA/>".to_vec());
+        a.insert("textures/my tex.png", vec![0, 1, 2, 255]);
+        a.insert("model.dae", b"<COLLADA version=\"1.5.0\"/>".to_vec());
+        a.insert("empty", Vec::new());
+
+        let b = Archive::read(&a.to_bytes().unwrap()).unwrap();
+        assert_eq!(b.paths(), vec!["model.dae", "textures/my tex.png", "empty"]);
+        assert_eq!(b.get("model.dae"), Some(&b"<COLLADA version=\"1.5.0\"/>"[..]));
+        assert_eq!(b.get("textures/my tex.png"), Some(&[0, 1, 2, 255][..]));
+        assert_eq!(b.get("empty"), Some(&[][..]));
+        assert_eq!(a.to_bytes().unwrap(), b.to_bytes().unwrap());
+
+        a.remove("empty");
+        assert_eq!(a.get("empty"), None);
+    }
+
+    #[test]
+    fn test_archive_root() {
+        let mut a = Archive::new();
+        assert_eq!(a.root(), None);
+        a.insert("textures/a.png", Vec::new());
+        a.insert("models/b.DAE", Vec::new());
+        assert_eq!(a.root(), Some("models/b.DAE".to_string()));
+
+        a.insert("doc.kml", br##"<kml xmlns="http://www.opengis.net/kml/2.2">
+            <Placemark><Model><Link><href>models\b.DAE</href></Link></Model></Placemark>
+        </kml>"##.to_vec());
+        assert_eq!(a.root(), Some("models/b.DAE".to_string()));
+
+        a.insert("manifest.xml", b"<dae_root>./models/my%20c.dae</dae_root>".to_vec());
+        assert_eq!(a.root(), Some("models/my c.dae".to_string()));
+    }
+
+    #[test]
+    fn test_resolve_path() {
+        assert_eq!(resolve_path("models/m.dae", "tex.png"), Some("models/tex.png".to_string()));
+        assert_eq!(resolve_path("models/m.dae", "./tex.png#x"), Some("models/tex.png".to_string()));
+        assert_eq!(resolve_path("models/m.dae", "../images/my%20tex.png"), Some("images/my tex.png".to_string()));
+        assert_eq!(resolve_path("models/m.dae", "/tex.png"), Some("tex.png".to_string()));
+        assert_eq!(resolve_path("m.dae", "../tex.png"), None);
+        assert_eq!(resolve_path("m.dae", "#node"), None);
+        assert_eq!(resolve_path("m.dae", "http://example.com/tex.png"), None);
+
+        let mut a = Archive::new();
+        a.insert("images/t.png", vec![1]);
+        assert_eq!(a.resolve("models/m.dae", "../images/t.png"), Some(&[1][..]));
+        assert_eq!(a.resolve("models/m.dae", "t.png"), None);
+    }
+}
@@ -0,0 +1,716 @@
+use xmltree::{Element};
+use collada::{Collada};
+use core::{Asset, Transform, TransformKind, UpAxis};
+use kinematics::{Joint, JointRef, Link};
+use mesh::{Animations, Output, animation_outputs};
+use physics::{InstancePhysicsModel, Limit, RigidBodyCommon, RigidConstraint, Shape, ShapeGeometry};
+use traits::{XmlConversion};
+use utils;
+
+/// A rotation between the axes of two up axes, in row-major order
+type Rotation = [[f32; 3]; 3];
+
+/// Semantics of inputs of geometry sources that are directions or positions
+const VECTOR_SEMANTICS: &'static [&'static str] = &[
+    "POSITION", "NORMAL", "TANGENT", "BINORMAL", "TEXTANGENT", "TEXBINORMAL",
+];
+
+/// Rotate a document to an up axis. The up axis of every element is
+/// inherited from the closest `<asset>` with an `<up_axis>`, or the root
+/// asset, and is mapped to the target following the table of `UpAxis`.
+/// Nested up axes are removed, so the whole document has the up axis of the
+/// root asset.
+///
+/// Rotated are geometry positions, normals and tangents, transforms of
+/// nodes, physics and kinematics elements, animation outputs of whole
+/// transforms and their `.X`, `.Y` and `.Z` members, skin bind matrices and
+/// physics vectors. Cameras and lights, which look down their local -Z
+/// axis, get a `<rotate>` to keep their orientation. Array selections of
+/// animation targets, e.g. `(3)(0)`, articulated systems and profile
+/// techniques are kept as they are.
+pub fn convert_up_axis(c: &mut Collada, up: UpAxis) {
+    let converter = Converter {
+        target: up,
+        animations: animation_outputs(&c.other_libraries),
+    };
+    let root = c.asset.up_axis.unwrap_or(UpAxis::YUP);
+    c.asset.up_axis = Some(up);
+
+    for l in &mut c.library_joints {
+        let a = converter.axis(&mut l.asset, root);
+        for j in &mut l.joints {
+            converter.joint(j, a);
+        }
+    }
+    for l in &mut c.library_kinematics_models {
+        let a = converter.axis(&mut l.asset, root);
+        for k in &mut l.kinematics_models {
+            let a = converter.axis(&mut k.asset, a);
+            for j in &mut k.common.joints {
+                if let JointRef::Inline(ref mut j) = *j {
+                    converter.joint(j, a);
+                }
+            }
+            for link in &mut k.common.links {
+                converter.link(link, a);
+            }
+        }
+    }
+    for l in &mut c.library_physics_models {
+        let a = converter.axis(&mut l.asset, root);
+        for p in &mut l.physics_models {
+            let a = converter.axis(&mut p.asset, a);
+            for b in &mut p.rigid_bodies {
+                converter.rigid_body(&mut b.common, a);
+            }
+            for r in &mut p.rigid_constraints {
+                converter.rigid_constraint(r, a);
+            }
+            for i in &mut p.instance_physics_models {
+                converter.instance_physics_model(i, a);
+            }
+        }
+    }
+    for l in &mut c.library_physics_scenes {
+        let a = converter.axis(&mut l.asset, root);
+        for s in &mut l.physics_scenes {
+            let a = converter.axis(&mut s.asset, a);
+            let r = converter.rotation(a);
+            if let Some(ref mut g) = s.common.gravity {
+                *g = rotate(&r, *g);
+            }
+            for i in &mut s.instance_physics_models {
+                converter.instance_physics_model(i, a);
+            }
+        }
+    }
+
+    for l in &mut c.other_libraries {
+        converter.element(l, root);
+    }
+
+    // The rest has no directions, only nested up axes to remove
+    for l in &mut c.library_articulated_systems {
+        clear_up_axis(&mut l.asset);
+        for a in &mut l.articulated_systems {
+            clear_up_axis(&mut a.asset);
+        }
+    }
+    for l in &mut c.library_effects {
+        clear_up_axis(&mut l.asset);
+        for e in &mut l.effects {
+            clear_up_axis(&mut e.asset);
+        }
+    }
+    for l in &mut c.library_formulas {
+        clear_up_axis(&mut l.asset);
+    }
+    for l in &mut c.library_images {
+        clear_up_axis(&mut l.asset);
+        for i in &mut l.images {
+            clear_up_axis(&mut i.asset);
+        }
+    }
+    for l in &mut c.library_kinematics_scenes {
+        clear_up_axis(&mut l.asset);
+        for k in &mut l.kinematics_scenes {
+            clear_up_axis(&mut k.asset);
+        }
+    }
+    for l in &mut c.library_materials {
+        clear_up_axis(&mut l.asset);
+        for m in &mut l.materials {
+            clear_up_axis(&mut m.asset);
+        }
+    }
+    for l in &mut c.library_physics_materials {
+        clear_up_axis(&mut l.asset);
+        for p in &mut l.physics_materials {
+            clear_up_axis(&mut p.asset);
+        }
+    }
+}
+
+fn clear_up_axis(asset: &mut Option<Asset>) {
+    if let Some(ref mut a) = *asset {
+        a.up_axis = None;
+    }
+}
+
+/// Converts the elements of a document to a target up axis
+struct Converter {
+    target: UpAxis,
+    animations: Animations,
+}
+
+impl Converter {
+    /// The up axis of an element with an optional asset within a parent of
+    /// an up axis. The asset's up axis is removed, as it is converted to the
+    /// target.
+    fn axis(&self, asset: &mut Option<Asset>, parent: UpAxis) -> UpAxis {
+        match asset.as_mut().and_then(|a| a.up_axis.take()) {
+            Some(a) => a,
+            None => parent,
+        }
+    }
+
+    /// The rotation from the axes of an up axis to the target's
+    fn rotation(&self, from: UpAxis) -> Rotation {
+        let a = from.axes();
+        let b = self.target.axes();
+        let mut r = [[0.0; 3]; 3];
+        for i in 0..3 {
+            for j in 0..3 {
+                for k in 0..3 {
+                    r[i][j] += b[k][i] * a[k][j];
+                }
+            }
+        }
+        r
+    }
+
+    fn transforms(&self, transforms: &mut [Transform], r: &Rotation) {
+        for t in transforms {
+            rotate_transform(r, t.kind, &mut t.values);
+        }
+    }
+
+    fn joint(&self, j: &mut Joint, axis: UpAxis) {
+        let r = self.rotation(axis);
+        for a in &mut j.axes {
+            a.axis = rotate(&r, a.axis);
+        }
+    }
+
+    fn link(&self, l: &mut Link, axis: UpAxis) {
+        let r = self.rotation(axis);
+        self.transforms(&mut l.transforms, &r);
+        for a in &mut l.attachments {
+            self.transforms(&mut a.transforms, &r);
+            if let Some(ref mut l) = a.link {
+                self.link(l, axis);
+            }
+        }
+    }
+
+    fn shape(&self, s: &mut Shape, r: &Rotation) {
+        self.transforms(&mut s.transforms, r);
+        match s.geometry {
+            ShapeGeometry::InstanceGeometry(_) | ShapeGeometry::Sphere{..} => {},
+            ShapeGeometry::Plane{ref mut equation} => {
+                let n = rotate(r, [equation[0], equation[1], equation[2]]);
+                equation[..3].copy_from_slice(&n);
+            },
+            ShapeGeometry::Box{ref mut half_extents} => *half_extents = permute(r, *half_extents),
+            // Cylinders and capsules are along their Y axis
+            _ => if !is_identity(r) {
+                let a = axis_angle(r);
+                s.transforms.push(Transform::rotate(a[0], a[1], a[2], a[3]));
+            },
+        }
+    }
+
+    fn rigid_body(&self, b: &mut RigidBodyCommon, axis: UpAxis) {
+        let r = self.rotation(axis);
+        if let Some(ref mut i) = b.inertia {
+            *i = permute(&r, *i);
+        }
+        self.transforms(&mut b.mass_frame, &r);
+        for s in &mut b.shapes {
+            self.shape(s, &r);
+        }
+    }
+
+    fn rigid_constraint(&self, c: &mut RigidConstraint, axis: UpAxis) {
+        let r = self.rotation(axis);
+        self.transforms(&mut c.ref_attachment.transforms, &r);
+        self.transforms(&mut c.attachment.transforms, &r);
+        for l in c.common.linear_limit.iter_mut().chain(c.common.swing_cone_and_twist.iter_mut()) {
+            rotate_limit(&r, l);
+        }
+    }
+
+    fn instance_physics_model(&self, i: &mut InstancePhysicsModel, axis: UpAxis) {
+        let r = self.rotation(axis);
+        for b in &mut i.instance_rigid_bodies {
+            if let Some(ref mut v) = b.velocity {
+                *v = rotate(&r, *v);
+            }
+            if let Some(ref mut v) = b.angular_velocity {
+                *v = rotate(&r, *v);
+            }
+            self.rigid_body(&mut b.common, axis);
+        }
+    }
+
+    /// Convert an element of a library that is kept as an element, e.g. a
+    /// `<library_geometries>`
+    fn element(&self, e: &mut Element, axis: UpAxis) {
+        if e.name == "extra" || (e.name == "technique" && e.attributes.contains_key("profile")) {
+            return;
+        }
+        let axis = match e.children.iter_mut().find(|c| c.name == "asset") {
+            Some(a) => {
+                let up = a.children.iter()
+                    .position(|c| c.name == "up_axis")
+                    .map(|i| a.children.remove(i));
+                match up.and_then(|u| u.text).as_ref().map(|t| t.trim()) {
+                    Some("X_UP") => UpAxis::XUP,
+                    Some("Y_UP") => UpAxis::YUP,
+                    Some("Z_UP") => UpAxis::ZUP,
+                    _ => axis,
+                }
+            },
+            None => axis,
+        };
+
+        let r = self.rotation(axis);
+        if !is_identity(&r) {
+            match e.name.as_str() {
+                "bind_shape_matrix" => map_text(e, |v| rotate_transform(&r, TransformKind::Matrix, v)),
+                "channel" => self.channel(e, &r),
+                "geometry" => {
+                    for semantic in VECTOR_SEMANTICS {
+                        map_sources(e, semantic, &|v| rotate_vectors(&r, v, 3, 0));
+                    }
+                },
+                "skin" => map_sources(e, "INV_BIND_MATRIX", &|v| {
+                    for m in v.chunks_mut(16) {
+                        rotate_transform(&r, TransformKind::Matrix, m);
+                    }
+                }),
+                "source" => self.output(e, &r),
+                name => if let Some(kind) = TransformKind::from_name(name) {
+                    map_text(e, |v| rotate_transform(&r, kind, v));
+                },
+            }
+        }
+        for c in &mut e.children {
+            self.element(c, axis);
+        }
+        if e.name == "node" && !is_identity(&r) {
+            orient_instances(e, &r);
+        }
+    }
+
+    /// Move the member a channel targets to the member it is rotated to
+    fn channel(&self, e: &mut Element, r: &Rotation) {
+        let target = match e.attributes.get("target") {
+            Some(t) => t.clone(),
+            None => return,
+        };
+        if let Some(&Output::Member(_, i)) = self.animations.channels.get(&target) {
+            let (j, _) = member(r, i);
+            let new = format!("{}.{}", &target[..target.len() - 2], ["X", "Y", "Z"][j]);
+            e.attributes.insert("target".to_string(), new);
+        }
+    }
+
+    /// Rotate the values of an animation output source
+    fn output(&self, e: &mut Element, r: &Rotation) {
+        let output = match e.attributes.get("id").and_then(|id| self.animations.sources.get(id)) {
+            Some(o) => *o,
+            None => return,
+        };
+        let array = match e.get_mut_child("float_array") {
+            Some(a) => a,
+            None => return,
+        };
+        match output {
+            Output::Transform(kind) => map_text(array, |v| {
+                for t in v.chunks_mut(kind.len()) {
+                    rotate_transform(r, kind, t);
+                }
+            }),
+            Output::Member(TransformKind::Scale, _) => {},
+            Output::Member(_, i) => {
+                let (_, sign) = member(r, i);
+                if sign < 0.0 {
+                    map_text(array, |v| {
+                        for x in v.iter_mut() {
+                            *x = 0.0 - *x;
+                        }
+                    });
+                }
+            },
+        }
+    }
+}
+
+fn is_identity(r: &Rotation) -> bool {
+    (0..3).all(|i| (0..3).all(|j| r[i][j] == if i == j { 1.0 } else { 0.0 }))
+}
+
+fn rotate(r: &Rotation, v: [f32; 3]) -> [f32; 3] {
+    let mut out = [0.0; 3];
+    for i in 0..3 {
+        // Summed from positive zero, so that zeros don't become -0
+        out[i] = (0..3).fold(0.0, |s, j| s + r[i][j] * v[j]);
+    }
+    out
+}
+
+/// Map the components of a vector to the rotated axes without their sign,
+/// e.g. for scales and extents
+fn permute(r: &Rotation, v: [f32; 3]) -> [f32; 3] {
+    let mut out = [0.0; 3];
+    for i in 0..3 {
+        out[i] = (0..3).fold(0.0, |s, j| s + r[i][j].abs() * v[j]);
+    }
+    out
+}
+
+/// The axis a member of a vector is rotated to and the sign it gets
+fn member(r: &Rotation, i: usize) -> (usize, f32) {
+    let j = (0..3).find(|j| r[*j][i] != 0.0).unwrap_or(i);
+    (j, r[j][i])
+}
+
+/// Rotate per axis limits, which are swapped where an axis is negated
+fn rotate_limit(r: &Rotation, l: &mut Limit) {
+    let (min, max) = (l.min, l.max);
+    for i in 0..3 {
+        let (j, sign) = member(r, i);
+        if sign < 0.0 {
+            l.min[j] = 0.0 - max[i];
+            l.max[j] = 0.0 - min[i];
+        } else {
+            l.min[j] = min[i];
+            l.max[j] = max[i];
+        }
+    }
+}
+
+/// The axis and angle in degrees of a rotation
+fn axis_angle(r: &Rotation) -> [f32; 4] {
+    let cos = ((r[0][0] + r[1][1] + r[2][2] - 1.0) / 2.0).max(-1.0).min(1.0);
+    let angle = cos.acos();
+    let axis = [r[2][1] - r[1][2], r[0][2] - r[2][0], r[1][0] - r[0][1]];
+    let len = (axis[0] * axis[0] + axis[1] * axis[1] + axis[2] * axis[2]).sqrt();
+    if len == 0.0 {
+        // Half turns have a symmetric matrix, the axis is the column of the
+        // largest diagonal
+        let i = (0..3).fold(0, |m, i| if r[i][i] > r[m][m] { i } else { m });
+        let mut axis = [0.0; 4];
+        axis[i] = 1.0;
+        axis[3] = if cos < 0.0 { 180.0 } else { 0.0 };
+        return axis;
+    }
+    [axis[0] / len, axis[1] / len, axis[2] / len, angle.to_degrees().round()]
+}
+
+/// Rotate the values of a transform of a kind. Matrices are rotated on both
+/// sides so that they map rotated points to rotated points.
+fn rotate_transform(r: &Rotation, kind: TransformKind, v: &mut [f32]) {
+    if v.len() < kind.len() {
+        return;
+    }
+    match kind {
+        TransformKind::LookAt => rotate_vectors(r, v, 3, 0),
+        TransformKind::Matrix => {
+            let mut m = [[0.0; 3]; 3];
+            for i in 0..3 {
+                for j in 0..3 {
+                    // r * a * r^T
+                    m[i][j] = (0..3).fold(0.0, |s, k| {
+                        s + (0..3).fold(0.0, |t, l| t + r[i][k] * v[k * 4 + l] * r[j][l])
+                    });
+                }
+            }
+            let t = rotate(r, [v[3], v[7], v[11]]);
+            for i in 0..3 {
+                v[i * 4..i * 4 + 3].copy_from_slice(&m[i]);
+                v[i * 4 + 3] = t[i];
+            }
+        },
+        TransformKind::Rotate | TransformKind::Translate => rotate_vectors(r, &mut v[..3], 3, 0),
+        TransformKind::Scale => {
+            let s = permute(r, [v[0], v[1], v[2]]);
+            v.copy_from_slice(&s);
+        },
+        TransformKind::Skew => rotate_vectors(r, &mut v[1..], 3, 0),
+    }
+}
+
+/// Rotate the vectors at an offset within every group of `stride` values
+fn rotate_vectors(r: &Rotation, v: &mut [f32], stride: usize, offset: usize) {
+    for chunk in v.chunks_mut(stride) {
+        if chunk.len() >= offset + 3 {
+            let x = rotate(r, [chunk[offset], chunk[offset + 1], chunk[offset + 2]]);
+            chunk[offset..offset + 3].copy_from_slice(&x);
+        }
+    }
+}
+
+/// Change the values of a list in an element's text. Text that isn't a list
+/// of numbers is kept.
+fn map_text<F: FnOnce(&mut [f32])>(e: &mut Element, f: F) {
+    let mut values: Vec<f32> = match e.text.as_ref().map(|t| t.split_whitespace().map(|x| x.parse()).collect()) {
+        Some(Ok(v)) => v,
+        _ => return,
+    };
+    f(&mut values);
+    let text: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+    e.text = Some(text.join(" "));
+}
+
+/// Change the arrays of the sources an element's inputs of a semantic refer
+/// to, e.g. the positions of a geometry
+fn map_sources(e: &mut Element, semantic: &str, f: &dyn Fn(&mut [f32])) {
+    fn inputs(e: &Element, semantic: &str, ids: &mut Vec<String>) {
+        if e.name == "input" && e.attributes.get("semantic").map(|s| s.as_str()) == Some(semantic) {
+            if let Some(id) = e.attributes.get("source").and_then(|s| utils::url_id(s)) {
+                ids.push(id.to_string());
+            }
+        }
+        for c in &e.children {
+            inputs(c, semantic, ids);
+        }
+    }
+
+    fn sources(e: &mut Element, ids: &[String], f: &dyn Fn(&mut [f32])) {
+        if e.name == "source" && e.attributes.get("id").map(|id| ids.contains(id)) == Some(true) {
+            if let Some(a) = e.get_mut_child("float_array") {
+                map_text(a, |v| f(v));
+            }
+            return;
+        }
+        for c in &mut e.children {
+            sources(c, ids, f);
+        }
+    }
+
+    let mut ids = Vec::new();
+    inputs(e, semantic, &mut ids);
+    ids.dedup();
+    sources(e, &ids, f);
+}
+
+/// Keep the orientation of the cameras and lights of a node, which look down
+/// their local -Z axis whatever the up axis. They get a rotation after the
+/// node's transforms, or a child node of their own with the rotation when
+/// the node has children it would rotate.
+fn orient_instances(e: &mut Element, r: &Rotation) {
+    let is_oriented = |c: &Element| c.name == "instance_camera" || c.name == "instance_light";
+    if !e.children.iter().any(|c| is_oriented(c)) {
+        return;
+    }
+    let a = axis_angle(r);
+    let rotate = Transform::rotate(a[0], a[1], a[2], a[3]).encode();
+
+    let has_children = e.children.iter().any(|c| c.name == "node" || c.name == "instance_node");
+    if !has_children {
+        let i = e.children.iter()
+            .rposition(|c| c.name == "asset" || TransformKind::from_name(&c.name).is_some())
+            .map(|i| i + 1)
+            .unwrap_or(0);
+        e.children.insert(i, rotate);
+        return;
+    }
+
+    let mut node = Element::new("node");
+    node.children.push(rotate);
+    let mut i = 0;
+    while i < e.children.len() {
+        if is_oriented(&e.children[i]) {
+            node.children.push(e.children.remove(i));
+        } else {
+            i += 1;
+        }
+    }
+    let i = e.children.iter().position(|c| c.name == "node" || c.name == "extra").unwrap_or(e.children.len());
+    e.children.insert(i, node);
+}
+
+#[cfg(test)]
+mod tests {
+    use xmltree::{Element};
+    use collada::{Collada};
+    use core::{UpAxis};
+    use physics::{ShapeGeometry};
+
+    const DATA: &'static str = r##"<?xml version="1.0" encoding="utf-8"?>
+<COLLADA xmlns="http://www.collada.org/2008/03/COLLADASchema" version="1.5.0">
+  <asset>
+    <created>2017-01-01T00:00:00Z</created>
+    <modified>2017-01-01T00:00:00Z</modified>
+    <up_axis>Z_UP</up_axis>
+  </asset>
+  <library_animations>
+    <animation id="anim">
+      <source id="anim-input">
+        <float_array id="anim-input-array" count="2">0 1</float_array>
+      </source>
+      <source id="anim-output">
+        <float_array id="anim-output-array" count="6">0 0 0 1 2 3</float_array>
+      </source>
+      <source id="anim-y-output">
+        <float_array id="anim-y-output-array" count="2">0 5</float_array>
+      </source>
+      <sampler id="anim-sampler">
+        <input semantic="INPUT" source="#anim-input"/>
+        <input semantic="OUTPUT" source="#anim-output"/>
+      </sampler>
+      <sampler id="anim-y-sampler">
+        <input semantic="INPUT" source="#anim-input"/>
+        <input semantic="OUTPUT" source="#anim-y-output"/>
+      </sampler>
+      <channel source="#anim-sampler" target="node/location"/>
+      <channel source="#anim-y-sampler" target="child/location.Y"/>
+    </animation>
+  </library_animations>
+  <library_geometries>
+    <geometry id="tri">
+      <mesh>
+        <source id="tri-pos">
+          <float_array id="tri-pos-array" count="6">1 2 3 0 0 1</float_array>
+          <technique_common>
+            <accessor source="#tri-pos-array" count="2" stride="3"/>
+          </technique_common>
+        </source>
+        <source id="tri-normal">
+          <float_array id="tri-normal-array" count="3">0 0 1</float_array>
+        </source>
+        <vertices id="tri-vtx">
+          <input semantic="POSITION" source="#tri-pos"/>
+        </vertices>
+        <triangles count="1">
+          <input semantic="VERTEX" source="#tri-vtx" offset="0"/>
+          <input semantic="NORMAL" source="#tri-normal" offset="1"/>
+          <p>0 0 1 0 1 0</p>
+        </triangles>
+      </mesh>
+    </geometry>
+    <geometry id="yup">
+      <asset>
+        <created>2017-01-01T00:00:00Z</created>
+        <modified>2017-01-01T00:00:00Z</modified>
+        <up_axis>Y_UP</up_axis>
+      </asset>
+      <mesh>
+        <source id="yup-pos">
+          <float_array id="yup-pos-array" count="3">1 2 3</float_array>
+        </source>
+        <vertices id="yup-vtx">
+          <input semantic="POSITION" source="#yup-pos"/>
+        </vertices>
+      </mesh>
+    </geometry>
+  </library_geometries>
+  <library_physics_models>
+    <physics_model id="model">
+      <rigid_body sid="body">
+        <technique_common>
+          <shape>
+            <box>
+              <half_extents>1 2 3</half_extents>
+            </box>
+          </shape>
+          <shape>
+            <cylinder>
+              <height>2</height>
+              <radius>1 1</radius>
+            </cylinder>
+          </shape>
+        </technique_common>
+      </rigid_body>
+    </physics_model>
+  </library_physics_models>
+  <library_physics_scenes>
+    <physics_scene id="physics">
+      <technique_common>
+        <gravity>0 0 -9.8</gravity>
+      </technique_common>
+    </physics_scene>
+  </library_physics_scenes>
+  <library_visual_scenes>
+    <visual_scene id="scene">
+      <node id="node">
+        <translate sid="location">1 2 3</translate>
+        <rotate>0 0 1 90</rotate>
+        <scale>1 2 3</scale>
+        <matrix>1 0 0 1 0 1 0 2 0 0 1 3 0 0 0 1</matrix>
+        <instance_light url="#light"/>
+        <node id="child">
+          <translate sid="location">0 1 0</translate>
+          <instance_camera url="#camera"/>
+        </node>
+      </node>
+    </visual_scene>
+  </library_visual_scenes>
+</COLLADA>"##;
+
+    fn find<'a>(e: &'a Element, name: &str, id: &str) -> Option<&'a Element> {
+        if e.name == name && (id.is_empty() || e.attributes.get("id").map(|x| x.as_str()) == Some(id)) {
+            return Some(e);
+        }
+        e.children.iter().filter_map(|c| find(c, name, id)).next()
+    }
+
+    fn text(c: &Collada, name: &str, id: &str) -> String {
+        let e = c.other_libraries.iter().filter_map(|l| find(l, name, id)).next().unwrap();
+        e.text.clone().unwrap()
+    }
+
+    #[test]
+    fn test_convert_up_axis() {
+        let mut c = Collada::read(DATA.as_bytes()).unwrap();
+        c.convert_up_axis(UpAxis::YUP);
+        assert_eq!(c.asset.up_axis, Some(UpAxis::YUP));
+
+        // Z up (x, y, z) is Y up (x, z, -y)
+        assert_eq!(text(&c, "float_array", "tri-pos-array"), "1 3 -2 0 1 0");
+        assert_eq!(text(&c, "float_array", "tri-normal-array"), "0 1 0");
+        assert_eq!(text(&c, "float_array", "yup-pos-array"), "1 2 3");
+        let g = c.other_libraries.iter().find(|l| l.name == "library_geometries").unwrap();
+        assert!(find(g, "up_axis", "").is_none());
+
+        let scene = c.other_libraries.iter().find(|l| l.name == "library_visual_scenes").unwrap();
+        let node = find(scene, "node", "node").unwrap();
+        let names: Vec<&str> = node.children.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["translate", "rotate", "scale", "matrix", "node", "node"]);
+        assert_eq!(node.children[0].text, Some("1 3 -2".to_string()));
+        assert_eq!(node.children[1].text, Some("0 1 0 90".to_string()));
+        assert_eq!(node.children[2].text, Some("1 3 2".to_string()));
+        assert_eq!(node.children[3].text, Some("1 0 0 1 0 1 0 3 0 0 1 -2 0 0 0 1".to_string()));
+
+        // The light moved to a node of its own, the camera got a rotation
+        let light = &node.children[4];
+        assert_eq!(light.children[0].name, "rotate");
+        assert_eq!(light.children[0].text, Some("-1 0 0 90".to_string()));
+        assert_eq!(light.children[1].name, "instance_light");
+        let camera = find(scene, "node", "child").unwrap();
+        let names: Vec<&str> = camera.children.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["translate", "rotate", "instance_camera"]);
+        assert_eq!(camera.children[0].text, Some("0 0 -1".to_string()));
+
+        assert_eq!(text(&c, "float_array", "anim-output-array"), "0 0 0 1 3 -2");
+        assert_eq!(text(&c, "float_array", "anim-y-output-array"), "0 -5");
+        let channel = c.other_libraries.iter().filter_map(|l| find(l, "channel", "")).next().unwrap();
+        assert_eq!(channel.attributes["target"], "node/location");
+        let a = c.other_libraries.iter().find(|l| l.name == "library_animations").unwrap();
+        assert_eq!(a.children[0].children[6].attributes["target"], "child/location.Z");
+
+        let body = &c.library_physics_models[0].physics_models[0].rigid_bodies[0].common;
+        match body.shapes[0].geometry {
+            ShapeGeometry::Box{half_extents} => assert_eq!(half_extents, [1.0, 3.0, 2.0]),
+            _ => assert!(false),
+        }
+        assert!(body.shapes[0].transforms.is_empty());
+        assert_eq!(body.shapes[1].transforms[0].values, vec![-1.0, 0.0, 0.0, 90.0]);
+        let g = c.library_physics_scenes[0].physics_scenes[0].common.gravity.unwrap();
+        assert_eq!(g, [0.0, -9.8, 0.0]);
+        assert!(c.validate().is_empty());
+    }
+
+    #[test]
+    fn test_convert_up_axis_round_trip() {
+        let mut c = Collada::read(DATA.as_bytes()).unwrap();
+        c.convert_up_axis(UpAxis::XUP);
+        c.convert_up_axis(UpAxis::ZUP);
+        assert_eq!(text(&c, "float_array", "tri-pos-array"), "1 2 3 0 0 1");
+        assert_eq!(text(&c, "translate", ""), "1 2 3");
+        assert_eq!(text(&c, "matrix", ""), "1 0 0 1 0 1 0 2 0 0 1 3 0 0 0 1");
+        assert_eq!(text(&c, "float_array", "anim-y-output-array"), "0 5");
+    }
+}
@@ -1,25 +1,785 @@
-/*
-use std::fs::{File};
+use std::io::{self, Write};
+use xmltree::{Element};
+use archive::{Archive};
+use axis;
+use context::{Diagnostic, ParseContext, ParseOptions};
+use core::{Asset, DateTime, Extra, LibraryFormulas, UnknownContent, UpAxis};
+use error::{ColladaError};
+use lint::{Lint, Linter};
+use gltf::{self, Gltf, GltfOptions};
+use obj::{self, Obj};
+use fx::{LibraryEffects, LibraryImages, LibraryMaterials};
+use kinematics::{LibraryArticulatedSystems, LibraryJoints, LibraryKinematicsModels, LibraryKinematicsScenes};
+use physics::{LibraryPhysicsMaterials, LibraryPhysicsModels, LibraryPhysicsScenes};
+use traits::{XmlConversion};
+use units;
+use utils;
+use validate::{validate_element};
+use version::{Version};
 
-use core::{Asset};
+/// Libraries that are kept as elements until they are implemented
+const OTHER_LIBRARIES: &'static [&'static str] = &[
+    "library_animation_clips",
+    "library_animations",
+    "library_cameras",
+    "library_controllers",
+    "library_force_fields",
+    "library_geometries",
+    "library_lights",
+    "library_nodes",
+    "library_visual_scenes",
+];
 
 /// Represents an entire collada document in memory
+#[derive(Debug)]
 pub struct Collada {
+    pub version: Version,
     pub asset: Asset,
+    pub library_articulated_systems: Vec<LibraryArticulatedSystems>,
+    pub library_effects: Vec<LibraryEffects>,
+    pub library_formulas: Vec<LibraryFormulas>,
+    pub library_images: Vec<LibraryImages>,
+    pub library_joints: Vec<LibraryJoints>,
+    pub library_kinematics_models: Vec<LibraryKinematicsModels>,
+    pub library_kinematics_scenes: Vec<LibraryKinematicsScenes>,
+    pub library_materials: Vec<LibraryMaterials>,
+    pub library_physics_materials: Vec<LibraryPhysicsMaterials>,
+    pub library_physics_models: Vec<LibraryPhysicsModels>,
+    pub library_physics_scenes: Vec<LibraryPhysicsScenes>,
+
+    /// Libraries that aren't implemented yet, e.g. `<library_geometries>`
+    pub other_libraries: Vec<Element>,
+    pub scene: Option<Element>,
+    pub extras: Vec<Extra>,
+
+    /// Namespaces declared on `<COLLADA>` besides the COLLADA schema as
+    /// `(prefix, uri)` pairs, e.g. for prefixed elements in techniques
+    pub namespaces: Vec<(String, String)>,
+    pub unknown: UnknownContent,
 }
 
 impl Collada {
     pub fn new() -> Collada {
         Collada {
+            version: Version::V150,
             asset: Asset::new(),
+            library_articulated_systems: Vec::new(),
+            library_effects: Vec::new(),
+            library_formulas: Vec::new(),
+            library_images: Vec::new(),
+            library_joints: Vec::new(),
+            library_kinematics_models: Vec::new(),
+            library_kinematics_scenes: Vec::new(),
+            library_materials: Vec::new(),
+            library_physics_materials: Vec::new(),
+            library_physics_models: Vec::new(),
+            library_physics_scenes: Vec::new(),
+            other_libraries: Vec::new(),
+            scene: None,
+            extras: Vec::new(),
+            namespaces: Vec::new(),
+            unknown: UnknownContent::new(),
         }
     }
-    
-    /// Read a collada document
-    pub fn read(file: File) -> Result<Collada, ()>{
+
+    /// Read a collada document, failing on the first error
+    pub fn read(data: &[u8]) -> Result<Collada, ColladaError> {
         let mut c = Collada::new();
+        try!(c.parse_source(data));
         Ok(c)
     }
 
+    /// Read a collada document with parse options. In lenient mode the
+    /// problems that were skipped are returned as diagnostics.
+    pub fn read_with(data: &[u8], options: ParseOptions)
+        -> Result<(Collada, Vec<Diagnostic>), ColladaError>
+    {
+        let (root, ctx) = try!(ParseContext::from_source(data));
+        let mut ctx = ctx.with_options(options);
+        let mut c = Collada::new();
+        try!(ctx.parse(&mut c, &root));
+        Ok((c, ctx.take_diagnostics()))
+    }
+
+    /// Read the root document of a KMZ or ZAE archive, see `Archive::root`.
+    /// The archive is returned with it so images and external references
+    /// can be resolved against it.
+    pub fn read_archive(data: &[u8]) -> Result<(Collada, Archive), ColladaError> {
+        let archive = try!(Archive::read(data));
+        let c = {
+            let root = match archive.root() {
+                Some(r) => r,
+                None => return Err(ColladaError::InvalidArchive{
+                    msg: "no COLLADA document".to_string(),
+                }),
+            };
+            match archive.get(&root) {
+                Some(d) => try!(Collada::read(d)),
+                None => return Err(ColladaError::InvalidArchive{
+                    msg: format!("missing root document {}", root),
+                }),
+            }
+        };
+        Ok((c, archive))
+    }
+
+    /// Write the document as xml
+    pub fn write<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        try!(writeln!(w, "<?xml version=\"1.0\" encoding=\"utf-8\"?>"));
+        utils::write_element(w, &self.encode(), 0)
+    }
+
+    /// Write the document into a copy of an archive as a zip. The document
+    /// replaces the root document of the archive, or is added as
+    /// `model.dae` with a ZAE `manifest.xml` if the archive has none. Fails
+    /// if the files are too large for a zip archive, see `Archive::to_bytes`.
+    pub fn write_archive(&self, archive: &Archive) -> Result<Vec<u8>, ColladaError> {
+        let mut archive = archive.clone();
+        let root = match archive.root() {
+            Some(r) => r,
+            None => {
+                archive.insert("manifest.xml", b"<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+                    <dae_root>./model.dae</dae_root>\n".to_vec());
+                "model.dae".to_string()
+            },
+        };
+        let mut data = Vec::new();
+        self.write(&mut data).expect("writing to memory can't fail");
+        archive.insert(&root, data);
+        archive.to_bytes()
+    }
+
+    /// Check the document against the rules of the COLLADA schema for its
+    /// version and return every violation. Documents built in code are
+    /// checked as they would be written. Only part of the schema has rules,
+    /// see `validate_element` for what is covered.
+    pub fn validate(&self) -> Vec<ColladaError> {
+        validate_element(&self.encode())
+    }
+
+    /// Check the document for problems the schema doesn't catch with every
+    /// lint rule, see `Linter`
+    pub fn lint(&self) -> Vec<Lint> {
+        Linter::new().check(self)
+    }
+
+    /// Rescale the document to a unit of `meter_per_unit` meters, e.g. 0.01
+    /// for centimeters, see `convert_units`
+    pub fn convert_units(&mut self, meter_per_unit: f32) -> Result<(), ColladaError> {
+        units::convert_units(self, meter_per_unit)
+    }
+
+    /// Rotate the document to an up axis, see `convert_up_axis`
+    pub fn convert_up_axis(&mut self, up: UpAxis) {
+        axis::convert_up_axis(self, up)
+    }
+
+    /// Convert the document to glTF 2.0, see `export_gltf`
+    pub fn to_gltf(&self, options: &GltfOptions) -> Gltf {
+        gltf::export_gltf(self, options)
+    }
+
+    /// Read a Wavefront OBJ file and its MTL file, see `import_obj`
+    pub fn from_obj(obj: &str, mtl: Option<&str>) -> Result<Collada, ColladaError> {
+        obj::import_obj(obj, mtl)
+    }
+
+    /// Convert the document to Wavefront OBJ, see `export_obj`
+    pub fn to_obj(&self, mtl_uri: &str) -> Obj {
+        obj::export_obj(self, mtl_uri)
+    }
+}
+
+impl XmlConversion for Collada {
+    fn parse_with(&mut self, e: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        if e.name != "COLLADA" {
+            return Err(ColladaError::MissingElement{
+                structure: "document".to_string(),
+                elem: "COLLADA".to_string(),
+            });
+        }
+
+        ctx.unknown_attributes(e, &["version", "xmlns", "base"], &mut self.unknown);
+        self.namespaces = utils::get_namespaces(e).into_iter()
+            .filter(|n| !n.0.is_empty())
+            .collect();
+        match e.attributes.get("version").map(|v| v.as_str()) {
+            Some("1.4.0") | Some("1.4.1") => self.version = Version::V141,
+            Some("1.5.0") => self.version = Version::V150,
+            Some(v) => try!(ctx.recover(e, ColladaError::InvalidAttrData{
+                elem: "COLLADA".to_string(),
+                attr: "version".to_string(),
+                data: v.to_string(),
+            })),
+            None => try!(ctx.recover(e, ColladaError::MissingAttr{
+                elem: "COLLADA".to_string(),
+                attr: "version".to_string(),
+            })),
+        }
+
+        let mut has_asset = false;
+        for c in &e.children {
+            match c.name.as_str() {
+                "asset" => {
+                    try!(ctx.parse(&mut self.asset, c));
+                    has_asset = true;
+                },
+                "library_articulated_systems" => {
+                    let mut l = LibraryArticulatedSystems::new();
+                    try!(ctx.parse(&mut l, c));
+                    self.library_articulated_systems.push(l);
+                },
+                "library_effects" => {
+                    let mut l = LibraryEffects::new();
+                    try!(ctx.parse(&mut l, c));
+                    self.library_effects.push(l);
+                },
+                "library_formulas" => {
+                    let mut l = LibraryFormulas::new();
+                    try!(ctx.parse(&mut l, c));
+                    self.library_formulas.push(l);
+                },
+                "library_images" => {
+                    let mut l = LibraryImages::new();
+                    try!(ctx.parse(&mut l, c));
+                    self.library_images.push(l);
+                },
+                "library_joints" => {
+                    let mut l = LibraryJoints::new();
+                    try!(ctx.parse(&mut l, c));
+                    self.library_joints.push(l);
+                },
+                "library_kinematics_models" => {
+                    let mut l = LibraryKinematicsModels::new();
+                    try!(ctx.parse(&mut l, c));
+                    self.library_kinematics_models.push(l);
+                },
+                "library_kinematics_scenes" => {
+                    let mut l = LibraryKinematicsScenes::new();
+                    try!(ctx.parse(&mut l, c));
+                    self.library_kinematics_scenes.push(l);
+                },
+                "library_materials" => {
+                    let mut l = LibraryMaterials::new();
+                    try!(ctx.parse(&mut l, c));
+                    self.library_materials.push(l);
+                },
+                "library_physics_materials" => {
+                    let mut l = LibraryPhysicsMaterials::new();
+                    try!(ctx.parse(&mut l, c));
+                    self.library_physics_materials.push(l);
+                },
+                "library_physics_models" => {
+                    let mut l = LibraryPhysicsModels::new();
+                    try!(ctx.parse(&mut l, c));
+                    self.library_physics_models.push(l);
+                },
+                "library_physics_scenes" => {
+                    let mut l = LibraryPhysicsScenes::new();
+                    try!(ctx.parse(&mut l, c));
+                    self.library_physics_scenes.push(l);
+                },
+                n if OTHER_LIBRARIES.contains(&n) => self.other_libraries.push(c.clone()),
+                "scene" => self.scene = Some(c.clone()),
+                "extra" => {
+                    let mut x = Extra::new();
+                    try!(ctx.parse(&mut x, c));
+                    self.extras.push(x);
+                },
+                _ => try!(ctx.unknown_child(e, c, &mut self.unknown)),
+            }
+        }
+
+        if !has_asset {
+            try!(ctx.recover(e, ColladaError::MissingElement{
+                structure: "COLLADA".to_string(),
+                elem: "asset".to_string(),
+            }));
+            self.asset.created = DateTime::from_unix(0);
+            self.asset.modified = DateTime::from_unix(0);
+        }
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut d = Element::new("COLLADA");
+        let (version, xmlns) = match self.version {
+            Version::V141 => ("1.4.1", "http://www.collada.org/2005/11/COLLADASchema"),
+            Version::V150 => ("1.5.0", "http://www.collada.org/2008/03/COLLADASchema"),
+        };
+        d.attributes.insert("xmlns".to_string(), xmlns.to_string());
+        d.attributes.insert("version".to_string(), version.to_string());
+        utils::set_namespaces(&mut d, &self.namespaces);
+
+        d.children.push(self.asset.encode());
+        for l in &self.library_articulated_systems {
+            d.children.push(l.encode());
+        }
+        for l in &self.library_effects {
+            d.children.push(l.encode());
+        }
+        for l in &self.library_formulas {
+            d.children.push(l.encode());
+        }
+        for l in &self.library_images {
+            d.children.push(l.encode());
+        }
+        for l in &self.library_joints {
+            d.children.push(l.encode());
+        }
+        for l in &self.library_kinematics_models {
+            d.children.push(l.encode());
+        }
+        for l in &self.library_kinematics_scenes {
+            d.children.push(l.encode());
+        }
+        for l in &self.library_materials {
+            d.children.push(l.encode());
+        }
+        for l in &self.library_physics_materials {
+            d.children.push(l.encode());
+        }
+        for l in &self.library_physics_models {
+            d.children.push(l.encode());
+        }
+        for l in &self.library_physics_scenes {
+            d.children.push(l.encode());
+        }
+        for l in &self.other_libraries {
+            d.children.push(l.clone());
+        }
+        if let Some(ref s) = self.scene {
+            d.children.push(s.clone());
+        }
+        for x in &self.extras {
+            d.children.push(x.encode());
+        }
+        self.unknown.encode_into(&mut d);
+        d
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use archive::{Archive};
+    use context::{ParseContext, ParseOptions, Strictness};
+    use error::{ColladaError};
+    use traits::{XmlConversion};
+
+    const DOC: &'static str = r##"<?xml version="1.0" encoding="utf-8"?>
+<COLLADA xmlns="http://www.collada.org/2005/11/COLLADASchema" version="1.4.1">
+  <asset>
+    <contributor>
+      <author>someone</author>
+      <tool_version>2.1</tool_version>
+    </contributor>
+    <created>2017-01-01T00:00:00Z</created>
+    <modified>2017-01-01T00:00:00Z</modified>
+    <generator>exporter</generator>
+    <up_axis></up_axis>
+  </asset>
+  <library_geometries/>
+  <library_materials>
+    <material id="white">
+      <instance_effect url="#white-fx"/>
+    </material>
+  </library_materials>
+  <scene/>
+</COLLADA>"##;
+
+    #[test]
+    fn test_collada_read() {
+        let data = r##"
+            <COLLADA version="1.5.0">
+                <asset>
+                    <created>2017-01-01T00:00:00Z</created>
+                    <modified>2017-01-01T00:00:00Z</modified>
+                    <up_axis>Z_UP</up_axis>
+                </asset>
+                <library_joints>
+                    <joint id="j"><revolute><axis>0 0 1</axis></revolute></joint>
+                </library_joints>
+                <library_visual_scenes/>
+            </COLLADA>"##;
+        let c = Collada::read(data.as_bytes()).unwrap();
+        assert_eq!(c.version, Version::V150);
+        assert_eq!(c.library_joints.len(), 1);
+        assert!(c.library_joints[0].get("j").is_some());
+        assert_eq!(c.other_libraries.len(), 1);
+
+        let err = Collada::read(DOC.as_bytes()).unwrap_err();
+        let p = err.position().unwrap();
+        assert_eq!(p.path.as_str(), "/COLLADA/asset/contributor/tool_version");
+        assert_eq!(p.line, Some(6));
+
+        assert!(Collada::read(r##"<COLLADA version="1.5.0"/>"##.as_bytes()).is_err());
+        assert!(Collada::read(r##"<asset/>"##.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_collada_read_lenient() {
+        let options = ParseOptions{strictness: Strictness::Lenient, preserve_unknown: false};
+        let (c, diagnostics) = Collada::read_with(DOC.as_bytes(), options).unwrap();
+        assert_eq!(c.version, Version::V141);
+        assert_eq!(c.asset.contributors.len(), 1);
+        assert_eq!(c.asset.contributors[0].author, Some("someone".to_string()));
+        assert!(c.asset.up_axis.is_none());
+        assert_eq!(c.library_materials.len(), 1);
+
+        let paths: Vec<&str> = diagnostics.iter()
+            .map(|d| d.position.as_ref().unwrap().path.as_str())
+            .collect();
+        assert_eq!(paths, vec![
+            "/COLLADA/asset/contributor/tool_version",
+            "/COLLADA/asset/generator",
+            "/COLLADA/asset/up_axis",
+        ]);
+        match diagnostics[2].error {
+            ColladaError::MissingData{ref elem} => assert_eq!(elem.as_str(), "up_axis"),
+            _ => assert!(false),
+        }
+
+        // A missing asset isn't stamped with the time it was read
+        let (c, diagnostics) = Collada::read_with(r##"<COLLADA version="1.4.1"/>"##.as_bytes(), options).unwrap();
+        assert_eq!(c.asset.created, DateTime::from_unix(0));
+        assert_eq!(c.asset.modified, DateTime::from_unix(0));
+        assert_eq!(diagnostics.len(), 1);
+
+        // Errors outside of what lenient parsing skips still fail
+        let data = r##"<COLLADA version="1.4.1"><asset/><library_joints><joint/></library_joints></COLLADA>"##;
+        assert!(Collada::read_with(data.as_bytes(), options).is_err());
+
+        let options = ParseOptions::new();
+        assert_eq!(options.strictness, Strictness::Strict);
+        assert!(Collada::read_with(DOC.as_bytes(), options).is_err());
+    }
+
+    #[test]
+    fn test_collada_encode() {
+        let mut c = Collada::new();
+        c.version = Version::V141;
+        c.library_joints.push(LibraryJoints::new());
+        c.scene = Some(Element::new("scene"));
+        let e = c.encode();
+        assert_eq!(e.name, "COLLADA");
+        assert_eq!(e.attributes.get("version"), Some(&"1.4.1".to_string()));
+        let names: Vec<&str> = e.children.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["asset", "library_joints", "scene"]);
+
+        let mut d = Collada::new();
+        d.parse(&e).unwrap();
+        assert_eq!(d.version, Version::V141);
+        assert_eq!(d.library_joints.len(), 1);
+    }
+
+    const SEED: &'static str = r##"<COLLADA xmlns="http://www.collada.org/2008/03/COLLADASchema" version="1.5.0">
+  <asset>
+    <contributor><author>a</author></contributor>
+    <coverage>
+      <geographic_location>
+        <longitude>-105.2</longitude>
+        <latitude>40.0</latitude>
+        <altitude mode="relativeToGround">1.5</altitude>
+      </geographic_location>
+    </coverage>
+    <created>2017-01-01T00:00:00Z</created>
+    <modified>2017-01-01T00:00:00Z</modified>
+    <unit meter="0.01" name="centimeter"/>
+    <up_axis>Z_UP</up_axis>
+  </asset>
+  <library_images>
+    <image id="tex"><init_from><ref>a%20b.png</ref></init_from></image>
+    <image id="raw"><init_from><hex format="R8G8B8">FF007F</hex></init_from></image>
+  </library_images>
+  <library_effects>
+    <effect id="fx">
+      <newparam sid="tint"><float3>0.9 0.9 1</float3></newparam>
+      <newparam sid="s"><sampler2D><instance_image url="#tex"/></sampler2D></newparam>
+      <profile_COMMON><technique sid="t"><constant/></technique></profile_COMMON>
+    </effect>
+  </library_effects>
+  <library_materials>
+    <material id="m"><instance_effect url="#fx"><setparam ref="tint"><float3>1 0 0</float3></setparam></instance_effect></material>
+  </library_materials>
+  <library_physics_materials>
+    <physics_material id="pm"><technique_common><dynamic_friction>0.2</dynamic_friction><restitution>0.5</restitution></technique_common></physics_material>
+  </library_physics_materials>
+  <library_physics_models>
+    <physics_model id="pmodel">
+      <rigid_body sid="b">
+        <technique_common>
+          <mass>5</mass>
+          <instance_physics_material url="#pm"/>
+          <shape><box><half_extents>1 2 0.1</half_extents></box><translate>0 0 1</translate></shape>
+        </technique_common>
+      </rigid_body>
+      <rigid_constraint sid="c">
+        <ref_attachment rigid_body="b"/>
+        <attachment rigid_body="b"/>
+        <technique_common>
+          <limits><linear><min>0 0 0</min><max>1 1 1</max></linear></limits>
+        </technique_common>
+      </rigid_constraint>
+    </physics_model>
+  </library_physics_models>
+  <library_joints>
+    <joint id="j"><revolute sid="axis0"><axis>0 0 1</axis><limits><min>-90</min><max>90</max></limits></revolute></joint>
+  </library_joints>
+  <library_formulas>
+    <formula id="f">
+      <newparam sid="k"><float>0.5</float></newparam>
+      <target><float>0</float></target>
+      <technique_common>
+        <math><apply><times/><ci>k</ci><apply><factorial/><cn>4</cn></apply></apply></math>
+      </technique_common>
+    </formula>
+  </library_formulas>
+  <library_kinematics_models>
+    <kinematics_model id="km">
+      <technique_common>
+        <instance_joint url="#j" sid="j0"/>
+        <link sid="base">
+          <attachment_full joint="km/j0">
+            <rotate>0 0 1 90</rotate>
+            <link sid="tip"/>
+          </attachment_full>
+        </link>
+        <instance_formula url="#f"><setparam ref="k"><float>2</float></setparam></instance_formula>
+      </technique_common>
+    </kinematics_model>
+  </library_kinematics_models>
+  <library_articulated_systems>
+    <articulated_system id="as">
+      <kinematics>
+        <instance_kinematics_model url="#km" sid="ikm"/>
+        <technique_common>
+          <axis_info sid="a0" axis="ikm/j0/axis0">
+            <limits><min><float>-45</float></min><max><float>45</float></max></limits>
+          </axis_info>
+          <frame_origin link="km/base"/>
+          <frame_tip link="km/tip"/>
+        </technique_common>
+      </kinematics>
+    </articulated_system>
+  </library_articulated_systems>
+  <library_kinematics_scenes>
+    <kinematics_scene id="ks"><instance_articulated_system url="#as"/></kinematics_scene>
+  </library_kinematics_scenes>
+  <scene/>
+</COLLADA>"##;
+
+    /// A small xorshift generator so the fuzz tests are reproducible
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 >> 16) as usize
+        }
+
+        fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+            &items[self.next() % items.len()]
+        }
+    }
+
+    const GARBAGE: &'static [&'static str] = &[
+        "", " ", "abc", "-", "1e999", "-1e999", "NaN", "inf", "1,5", "0x10", "1 2", "1 2 3 4 5",
+        "99999999999999999999", "-0", "%", "%zz", "%e9%", "\u{e9}%\u{e9}", "#", "#missing", "a/b/c",
+        "/", "true", "2", "4294967296", "-2147483649", "Z_UP ", "X_UP",
+    ];
+
+    fn mutate_tree(e: &mut Element, rng: &mut Rng) {
+        match rng.next() % 8 {
+            0 => e.text = Some(rng.pick(GARBAGE).to_string()),
+            1 => e.text = None,
+            2 => {
+                let keys: Vec<String> = e.attributes.keys().cloned().collect();
+                if !keys.is_empty() {
+                    let k = rng.pick(&keys).clone();
+                    e.attributes.insert(k, rng.pick(GARBAGE).to_string());
+                }
+            },
+            3 => {
+                let keys: Vec<String> = e.attributes.keys().cloned().collect();
+                if !keys.is_empty() {
+                    let k = rng.pick(&keys).clone();
+                    e.attributes.remove(&k);
+                }
+            },
+            4 if !e.children.is_empty() => {
+                let i = rng.next() % e.children.len();
+                e.children.remove(i);
+            },
+            5 if !e.children.is_empty() => {
+                let i = rng.next() % e.children.len();
+                let c = e.children[i].clone();
+                e.children.insert(i, c);
+            },
+            _ if !e.children.is_empty() => {
+                let i = rng.next() % e.children.len();
+                mutate_tree(&mut e.children[i], rng);
+            },
+            _ => e.children.clear(),
+        }
+    }
+
+    #[test]
+    fn test_collada_validate() {
+        let c = Collada::read(SEED.as_bytes()).unwrap();
+        let errors: Vec<String> = c.validate().iter().map(|e| e.to_string()).collect();
+        assert_eq!(errors, Vec::<String>::new());
+
+        let mut c = Collada::new();
+        assert!(c.validate().is_empty());
+        c.asset.modified = "2017-01-01T00:00:00Z".parse().unwrap();
+        assert!(c.validate().is_empty());
+    }
+
+    #[test]
+    fn test_collada_archive() {
+        let data = r##"<COLLADA xmlns="http://www.collada.org/2005/11/COLLADASchema" version="1.4.1">
+            <asset>
+                <created>2017-01-01T00:00:00Z</created>
+                <modified>2017-01-01T00:00:00Z</modified>
+            </asset>
+            <library_images>
+                <image id="tex"><init_from>../images/a%20b.png</init_from></image>
+            </library_images>
+        </COLLADA>"##;
+        let mut a = Archive::new();
+        a.insert("doc.kml", b"<kml><Model><Link><href>models/m.dae</href></Link></Model></kml>".to_vec());
+        a.insert("models/m.dae", data.as_bytes().to_vec());
+        a.insert("images/a b.png", vec![1, 2, 3]);
+
+        let (c, archive) = Collada::read_archive(&a.to_bytes().unwrap()).unwrap();
+        let image = &c.library_images[0].images[0];
+        assert_eq!(image.resolve_archive(&archive, "models/m.dae"), vec![&[1, 2, 3][..]]);
+
+        let (d, archive) = Collada::read_archive(&c.write_archive(&archive).unwrap()).unwrap();
+        assert_eq!(archive.paths(), vec!["doc.kml", "models/m.dae", "images/a b.png"]);
+        assert_eq!(d.library_images[0].images[0].uris(), vec!["../images/a%20b.png"]);
+        assert_eq!(d.asset.created, c.asset.created);
+
+        let (_, archive) = Collada::read_archive(&d.write_archive(&Archive::new()).unwrap()).unwrap();
+        assert_eq!(archive.paths(), vec!["manifest.xml", "model.dae"]);
+
+        match Collada::read_archive(&Archive::new().to_bytes().unwrap()) {
+            Err(ColladaError::InvalidArchive{..}) => {},
+            r => panic!("unexpected result {:?}", r.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_collada_namespaces() {
+        let data = r##"<COLLADA xmlns="http://www.collada.org/2005/11/COLLADASchema" xmlns:max="some/max/schema" version="1.4.1">
+            <asset>
+                <created>2017-01-01T00:00:00Z</created>
+                <modified>2017-01-01T00:00:00Z</modified>
+            </asset>
+            <extra><technique profile="MAX3D"><max:frame_rate>30</max:frame_rate></technique></extra>
+        </COLLADA>"##;
+        let c = Collada::read(data.as_bytes()).unwrap();
+        assert_eq!(c.namespaces, vec![("max".to_string(), "some/max/schema".to_string())]);
+        assert_eq!(c.extras[0].techniques[0].data.children[0].name, "max:frame_rate");
+
+        let e = c.encode();
+        assert_eq!(e.attributes.get("xmlns").unwrap(), "http://www.collada.org/2005/11/COLLADASchema");
+        assert_eq!(e.attributes.get("xmlns:max").unwrap(), "some/max/schema");
+        assert!(c.validate().is_empty());
+    }
+
+    #[test]
+    fn test_collada_preserve_unknown() {
+        let data = r##"<COLLADA xmlns="http://www.collada.org/2008/03/COLLADASchema" version="1.5.0" flavor="x">
+            <asset>
+                <contributor><tool_version>3.2</tool_version><author>me</author></contributor>
+                <generator>tool</generator>
+                <created>2017-01-01T00:00:00Z</created>
+                <modified>2017-01-01T00:00:00Z</modified>
+            </asset>
+            <library_materials>
+                <material id="m" tint="red"><note>keep</note><instance_effect url="#e"/></material>
+            </library_materials>
+            <library_future/>
+        </COLLADA>"##;
+
+        let strict = ParseOptions{strictness: Strictness::Strict, preserve_unknown: false};
+        assert!(Collada::read_with(data.as_bytes(), strict).is_err());
+
+        let preserve = ParseOptions{strictness: Strictness::Strict, preserve_unknown: true};
+        let (c, diagnostics) = Collada::read_with(data.as_bytes(), preserve).unwrap();
+        assert!(diagnostics.is_empty());
+        assert_eq!(c.unknown.attributes, vec![("flavor".to_string(), "x".to_string())]);
+
+        let e = c.encode();
+        let names: Vec<&str> = e.children.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["asset", "library_materials", "library_future"]);
+        let asset = &e.children[0];
+        assert_eq!(asset.children[1].name, "generator");
+        assert_eq!(asset.children[0].children[0].name, "tool_version");
+        let m = &e.children[1].children[0];
+        assert_eq!(m.attributes.get("tint").unwrap(), "red");
+        assert_eq!(m.children[0].name, "note");
+        assert_eq!(m.children[1].name, "instance_effect");
+
+        let mut d = Collada::new();
+        let mut ctx = ParseContext::for_element(&e).with_options(preserve);
+        ctx.parse(&mut d, &e).unwrap();
+        assert_eq!(d.encode(), e);
+    }
+
+    #[test]
+    fn test_collada_fuzz_tree() {
+        let seed = Element::parse(SEED.as_bytes()).unwrap();
+        let mut c = Collada::new();
+        match c.parse(&seed) {
+            Ok(_) => assert!(true),
+            Err(e) => panic!("{}", e),
+        }
+
+        let lenient = ParseOptions{strictness: Strictness::Lenient, preserve_unknown: false};
+        let mut rng = Rng(0x2545F4914F6CDD1D);
+        for _ in 0..3000 {
+            let mut e = seed.clone();
+            for _ in 0..(1 + rng.next() % 4) {
+                mutate_tree(&mut e, &mut rng);
+            }
+
+            let mut c = Collada::new();
+            if c.parse(&e).is_ok() {
+                let mut d = Collada::new();
+                let _ = d.parse(&c.encode());
+            }
+            let mut ctx = ParseContext::for_element(&e).with_options(lenient);
+            let _ = ctx.parse(&mut Collada::new(), &e);
+        }
+    }
+
+    #[test]
+    fn test_collada_fuzz_source() {
+        let seed = SEED.as_bytes();
+        let bytes = b"<>/=\"' &;#%-.0123456789eE abcxyz\n";
+        let lenient = ParseOptions{strictness: Strictness::Lenient, preserve_unknown: false};
+        let mut rng = Rng(0x9E3779B97F4A7C15);
+        for _ in 0..1000 {
+            let mut data = seed.to_vec();
+            for _ in 0..(1 + rng.next() % 8) {
+                if data.len() < 2 {
+                    break;
+                }
+                let i = rng.next() % data.len();
+                match rng.next() % 4 {
+                    0 => data[i] = *rng.pick(bytes),
+                    1 => { data.remove(i); },
+                    2 => data.insert(i, *rng.pick(bytes)),
+                    _ => data.truncate(i),
+                }
+            }
+            let _ = Collada::read(&data);
+            let _ = Collada::read_with(&data, lenient);
+        }
+    }
 }
-*/
@@ -0,0 +1,462 @@
+use std::collections::{HashMap};
+use std::fmt;
+use xml::common::{Position};
+use xml::name::{OwnedName};
+use xml::namespace::{Namespace, NS_EMPTY_URI, NS_XML_PREFIX, NS_XMLNS_PREFIX};
+use xml::reader::{EventReader, XmlEvent};
+use xmltree::{Element};
+use core::{UnknownContent};
+use error::{ColladaError};
+use traits::{XmlConversion};
+
+/// Where an element is in a document, e.g. line 12, column 5 and the path
+/// `/COLLADA/library_geometries/geometry[3]/mesh/source[1]`. Elements that
+/// have siblings of the same name get a 1-based index in the path.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SourcePosition {
+    /// Line of the element's start tag, starting at 1. None when the
+    /// element was not read from a source, e.g. when built in code.
+    pub line: Option<u64>,
+
+    /// Column of the element's start tag, starting at 1
+    pub column: Option<u64>,
+    pub path: String,
+}
+
+impl fmt::Display for SourcePosition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.line, self.column) {
+            (Some(l), Some(c)) => write!(f, "{} (line {}, column {})", self.path, l, c),
+            _ => write!(f, "{}", self.path),
+        }
+    }
+}
+
+/// How strictly a document is checked while parsing
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Strictness {
+    /// Fail on the first problem
+    Strict,
+
+    /// Skip problems exporters commonly produce, e.g. unknown elements in
+    /// `<asset>` or `<contributor>` and empty text elements, and report
+    /// them as diagnostics
+    Lenient,
+}
+
+/// Options for parsing a document
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ParseOptions {
+    pub strictness: Strictness,
+
+    /// Keep children and attributes that aren't modelled instead of
+    /// failing on them, so they are written back when encoding
+    pub preserve_unknown: bool,
+}
+
+impl ParseOptions {
+    pub fn new() -> ParseOptions {
+        ParseOptions {
+            strictness: Strictness::Strict,
+            preserve_unknown: false,
+        }
+    }
+}
+
+/// A problem skipped while parsing leniently
+#[derive(Debug)]
+pub struct Diagnostic {
+    pub position: Option<SourcePosition>,
+
+    /// The error strict parsing would have returned
+    pub error: ColladaError,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.position {
+            Some(ref p) => write!(f, "warning: {}: {}", p, self.error),
+            None => write!(f, "warning: {}", self.error),
+        }
+    }
+}
+
+/// State shared by all elements while parsing a document. Errors returned
+/// by `parse` are located at the element that caused them.
+///
+/// Elements are identified by their address, so a context only locates
+/// elements of the tree it was created for.
+pub struct ParseContext {
+    positions: HashMap<*const Element, SourcePosition>,
+    options: ParseOptions,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl ParseContext {
+    /// A context without positions, errors are returned as they are
+    pub fn new() -> ParseContext {
+        ParseContext {
+            positions: HashMap::new(),
+            options: ParseOptions::new(),
+            diagnostics: Vec::new(),
+        }
+    }
+
+    /// Use different parse options
+    pub fn with_options(mut self, options: ParseOptions) -> ParseContext {
+        self.options = options;
+        self
+    }
+
+    pub fn options(&self) -> &ParseOptions {
+        &self.options
+    }
+
+    /// The problems skipped so far
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    /// Remove and return the problems skipped so far
+    pub fn take_diagnostics(&mut self) -> Vec<Diagnostic> {
+        ::std::mem::replace(&mut self.diagnostics, Vec::new())
+    }
+
+    /// Handle a problem with an element that lenient parsing may skip. In
+    /// strict mode the error is returned, located at the element, otherwise
+    /// it is recorded as a diagnostic and parsing continues.
+    pub fn recover(&mut self, e: &Element, error: ColladaError) -> Result<(), ColladaError> {
+        let position = self.position(e).cloned();
+        match self.options.strictness {
+            Strictness::Strict => match position {
+                Some(p) => Err(ColladaError::Located{position: p, error: Box::new(error)}),
+                None => Err(error),
+            },
+            Strictness::Lenient => {
+                self.diagnostics.push(Diagnostic {
+                    position: position,
+                    error: error,
+                });
+                Ok(())
+            },
+        }
+    }
+
+    /// Handle a child an element doesn't model. It is kept in `unknown` when
+    /// preserving unknown content, otherwise it is an invalid child.
+    pub fn unknown_child(&mut self, parent: &Element, c: &Element, unknown: &mut UnknownContent)
+        -> Result<(), ColladaError>
+    {
+        if self.options.preserve_unknown {
+            unknown.add_child(parent, c);
+            Ok(())
+        } else {
+            self.recover(c, ColladaError::InvalidChild{
+                child: c.name.clone(),
+                parent: parent.name.clone(),
+            })
+        }
+    }
+
+    /// Keep the attributes of an element that aren't in a list of known
+    /// attributes when preserving unknown content, otherwise they are ignored
+    pub fn unknown_attributes(&self, e: &Element, known: &[&str], unknown: &mut UnknownContent) {
+        if self.options.preserve_unknown {
+            unknown.add_attributes(e, known);
+        }
+    }
+
+    /// A context with the path of every element of a tree
+    pub fn for_element(root: &Element) -> ParseContext {
+        let mut ctx = ParseContext::new();
+        ctx.add_paths(root, format!("/{}", root.name), &mut Vec::new().into_iter());
+        ctx
+    }
+
+    /// Read the element tree of a document, along with a context holding
+    /// the line and column of every element. The root is boxed so it keeps
+    /// its address when moved.
+    ///
+    /// Elements outside the document's namespace keep their prefix, e.g.
+    /// `max:someElement`, and namespace declarations are kept as `xmlns` and
+    /// `xmlns:<prefix>` attributes of the element declaring them.
+    pub fn from_source(data: &[u8]) -> Result<(Box<Element>, ParseContext), ColladaError> {
+        let mut reader = EventReader::new(data);
+        let mut starts = Vec::new();
+        let mut open: Vec<(Element, Namespace)> = Vec::new();
+        let mut document = None;
+        let mut root = None;
+        loop {
+            let event = reader.next();
+            let p = reader.position();
+            match event {
+                Ok(XmlEvent::StartElement{name, attributes, namespace}) => {
+                    if open.is_empty() {
+                        document = name.namespace.clone();
+                    }
+                    let mut e = Element::new(&qualified_name(&name, &document));
+                    {
+                        let parent = open.last().map(|o| &o.1);
+                        for (prefix, uri) in declared_namespaces(&namespace, parent) {
+                            let attr = match prefix.as_str() {
+                                "" => "xmlns".to_string(),
+                                _ => format!("xmlns:{}", prefix),
+                            };
+                            e.attributes.insert(attr, uri);
+                        }
+                    }
+                    for a in attributes {
+                        e.attributes.insert(qualified_name(&a.name, &None), a.value);
+                    }
+                    open.push((e, namespace));
+                    starts.push((p.row + 1, p.column + 1));
+                },
+                Ok(XmlEvent::EndElement{..}) => {
+                    let e = match open.pop() {
+                        Some((e, _)) => e,
+                        None => return Err(ColladaError::ParseError),
+                    };
+                    match open.last_mut() {
+                        Some(parent) => parent.0.children.push(e),
+                        None => root = Some(e),
+                    }
+                },
+                Ok(XmlEvent::Characters(text)) | Ok(XmlEvent::CData(text)) => {
+                    if let Some(o) = open.last_mut() {
+                        o.0.text = Some(text);
+                    }
+                },
+                Ok(XmlEvent::EndDocument) => break,
+                Ok(_) => {},
+                Err(e) => {
+                    let names: Vec<&str> = open.iter().map(|o| o.0.name.as_str()).collect();
+                    return Err(ColladaError::Located{
+                        position: SourcePosition {
+                            line: Some(e.position().row + 1),
+                            column: Some(e.position().column + 1),
+                            path: format!("/{}", names.join("/")),
+                        },
+                        error: Box::new(ColladaError::ParseError),
+                    });
+                },
+            }
+        }
+
+        let root = match root {
+            Some(e) => Box::new(e),
+            None => return Err(ColladaError::ParseError),
+        };
+        let mut ctx = ParseContext::new();
+        ctx.add_paths(&root, format!("/{}", root.name), &mut starts.into_iter());
+        Ok((root, ctx))
+    }
+
+    /// Record the paths of an element and its descendants, taking their
+    /// start positions in document order
+    fn add_paths<I>(&mut self, e: &Element, path: String, starts: &mut I)
+        where I: Iterator<Item=(u64, u64)>
+    {
+        let start = starts.next();
+        let mut seen = HashMap::new();
+        for c in &e.children {
+            let count = e.children.iter().filter(|x| x.name == c.name).count();
+            let index = seen.entry(c.name.as_str()).or_insert(0);
+            *index += 1;
+            let child = match count {
+                1 => format!("{}/{}", path, c.name),
+                _ => format!("{}/{}[{}]", path, c.name, index),
+            };
+            self.add_paths(c, child, starts);
+        }
+        self.positions.insert(e as *const Element, SourcePosition {
+            line: start.map(|s| s.0),
+            column: start.map(|s| s.1),
+            path: path,
+        });
+    }
+
+    /// The position of an element, if it belongs to the context's tree
+    pub fn position(&self, e: &Element) -> Option<&SourcePosition> {
+        self.positions.get(&(e as *const Element))
+    }
+
+    /// Parse an element into a struct, locating errors within the element
+    pub fn parse<T: XmlConversion + ?Sized>(&mut self, x: &mut T, e: &Element)
+        -> Result<(), ColladaError>
+    {
+        let result = x.parse_with(e, self);
+        self.locate(e, result)
+    }
+
+    /// Attach the position of an element to an error that has none. Errors
+    /// naming a child of the element, e.g. an invalid child or a child with
+    /// invalid data, are located at the child if it is the only one of that
+    /// name, and at the element otherwise.
+    pub fn locate<T>(&self, e: &Element, result: Result<T, ColladaError>)
+        -> Result<T, ColladaError>
+    {
+        let error = match result {
+            Ok(x) => return Ok(x),
+            Err(ColladaError::Located{position, error}) => {
+                return Err(ColladaError::Located{position: position, error: error});
+            },
+            Err(error) => error,
+        };
+
+        let child = match error {
+            ColladaError::InvalidChild{ref child, ref parent} if *parent == e.name => Some(child),
+            ColladaError::InvalidAttr{ref elem, ..} |
+            ColladaError::InvalidAttrData{ref elem, ..} |
+            ColladaError::InvalidData{ref elem, ..} |
+            ColladaError::MissingAttr{ref elem, ..} |
+            ColladaError::MissingData{ref elem} if *elem != e.name => Some(elem),
+            _ => None,
+        };
+        let target = child.and_then(|name| only_child(e, name)).unwrap_or(e);
+
+        match self.position(target) {
+            Some(p) => Err(ColladaError::Located{
+                position: p.clone(),
+                error: Box::new(error),
+            }),
+            None => Err(error),
+        }
+    }
+}
+
+/// The name of an element or attribute with its prefix, unless it is in the
+/// document's namespace
+fn qualified_name(name: &OwnedName, document: &Option<String>) -> String {
+    match name.prefix {
+        Some(ref p) if name.namespace != *document => format!("{}:{}", p, name.local_name),
+        _ => name.local_name.clone(),
+    }
+}
+
+/// The namespaces an element declares, i.e. those that differ from its
+/// parent's, by prefix with the default namespace first
+fn declared_namespaces(namespace: &Namespace, parent: Option<&Namespace>) -> Vec<(String, String)> {
+    let mut declared = Vec::new();
+    for (prefix, uri) in &namespace.0 {
+        if prefix == NS_XML_PREFIX || prefix == NS_XMLNS_PREFIX {
+            continue;
+        }
+        let inherited = match parent {
+            Some(p) => p.get(prefix).unwrap_or(NS_EMPTY_URI),
+            None => NS_EMPTY_URI,
+        };
+        if uri != inherited {
+            declared.push((prefix.clone(), uri.clone()));
+        }
+    }
+    declared
+}
+
+/// The child of an element with a name, if there is exactly one
+fn only_child<'a>(e: &'a Element, name: &str) -> Option<&'a Element> {
+    let mut children = e.children.iter().filter(|c| c.name == name);
+    match (children.next(), children.next()) {
+        (Some(c), None) => Some(c),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use xmltree::{Element};
+    use core::{Asset};
+    use error::{ColladaError};
+    use traits::{XmlConversion};
+
+    const DOC: &'static str = r##"<COLLADA>
+  <library_geometries>
+    <geometry id="a"/>
+    <geometry id="b">
+      <mesh>
+        <source id="p"/>
+        <source id="n"/>
+      </mesh>
+    </geometry>
+  </library_geometries>
+</COLLADA>"##;
+
+    #[test]
+    fn test_context_positions() {
+        let (root, ctx) = ParseContext::from_source(DOC.as_bytes()).unwrap();
+        let g = &root.children[0].children[1];
+        let p = ctx.position(&g.children[0].children[1]).unwrap();
+        assert_eq!(p.path.as_str(), "/COLLADA/library_geometries/geometry[2]/mesh/source[2]");
+        assert_eq!(p.line, Some(7));
+        assert_eq!(p.column, Some(9));
+        assert_eq!(ctx.position(&root).unwrap().path.as_str(), "/COLLADA");
+        assert!(ctx.position(&Element::new("mesh")).is_none());
+
+        let ctx = ParseContext::for_element(&root);
+        let p = ctx.position(g).unwrap();
+        assert_eq!(p.path.as_str(), "/COLLADA/library_geometries/geometry[2]");
+        assert_eq!(p.line, None);
+    }
+
+    #[test]
+    fn test_context_syntax_error() {
+        let data = "<COLLADA>\n  <asset>\n    <unit>\n  </asset>\n</COLLADA>";
+        match ParseContext::from_source(data.as_bytes()) {
+            Err(ColladaError::Located{position, error}) => {
+                assert_eq!(position.line, Some(4));
+                assert_eq!(position.path.as_str(), "/COLLADA/asset/unit");
+                match *error {
+                    ColladaError::ParseError => assert!(true),
+                    _ => assert!(false),
+                }
+            },
+            _ => assert!(false),
+        }
+    }
+
+    #[test]
+    fn test_context_locate_errors() {
+        let data = r##"<COLLADA>
+  <asset>
+    <contributor>
+      <author>someone</author>
+      <bogus>x</bogus>
+    </contributor>
+  </asset>
+</COLLADA>"##;
+        let (root, mut ctx) = ParseContext::from_source(data.as_bytes()).unwrap();
+        let mut a = Asset::new();
+        let err = ctx.parse(&mut a, &root.children[0]).unwrap_err();
+        let p = err.position().unwrap();
+        assert_eq!(p.path.as_str(), "/COLLADA/asset/contributor/bogus");
+        assert_eq!(p.line, Some(5));
+        match *err.inner() {
+            ColladaError::InvalidChild{ref child, ..} => assert_eq!(child.as_str(), "bogus"),
+            _ => assert!(false),
+        }
+        assert!(format!("{}", err).contains("line 5, column 7"));
+
+        // Parsing without a source still reports the element path
+        let e = Element::parse(r##"<asset><revision>x</revision><up_axis>W_UP</up_axis></asset>"##.as_bytes()).unwrap();
+        let err = Asset::new().parse(&e).unwrap_err();
+        assert_eq!(err.position().unwrap().path.as_str(), "/asset/up_axis");
+
+        // Errors that can't be told apart by the child's name stay at the
+        // element being parsed
+        let data = r##"<asset>
+  <contributor><author>a</author></contributor>
+  <contributor><bogus>x</bogus></contributor>
+  <keywords><bogus>x</bogus></keywords>
+</asset>"##;
+        let (root, ctx) = ParseContext::from_source(data.as_bytes()).unwrap();
+        let error = ColladaError::InvalidChild{child: "bogus".to_string(), parent: "asset".to_string()};
+        let err = ctx.locate::<()>(&root, Err(error)).unwrap_err();
+        assert_eq!(err.position().unwrap().path.as_str(), "/asset");
+        let error = ColladaError::MissingData{elem: "contributor".to_string()};
+        let err = ctx.locate::<()>(&root, Err(error)).unwrap_err();
+        assert_eq!(err.position().unwrap().path.as_str(), "/asset");
+        let error = ColladaError::MissingData{elem: "keywords".to_string()};
+        let err = ctx.locate::<()>(&root, Err(error)).unwrap_err();
+        assert_eq!(err.position().unwrap().path.as_str(), "/asset/keywords");
+    }
+}
@@ -1,9 +1,11 @@
 use std::collections::{HashMap};
 use std::fmt;
 use xmltree::{Element};
-use core::{Contributor, Extra, Location};
+use context::{ParseContext};
+use core::{Contributor, DateTime, Extra, GeoLocation, Location, Matrix64, UnknownContent};
 use error::{ColladaError};
 use traits::{XmlConversion};
+use utils;
 
 /// The distance units for the asset. 
 #[derive(Debug)]
@@ -23,6 +25,28 @@ impl Unit {
             meter: Some(1.0),
         }
     }
+
+    /// Create a unit of a number of meters, named when it is a common unit
+    pub fn from_meter(meter: f32) -> Unit {
+        let name = match meter {
+            x if x == 1000.0 => Some("kilometer"),
+            x if x == 1.0 => Some("meter"),
+            x if x == 0.01 => Some("centimeter"),
+            x if x == 0.001 => Some("millimeter"),
+            x if x == 0.3048 => Some("foot"),
+            x if x == 0.0254 => Some("inch"),
+            _ => None,
+        };
+        Unit {
+            name: name.map(|n| n.to_string()),
+            meter: Some(meter),
+        }
+    }
+
+    /// Meters in one distance unit, 1 when not given
+    pub fn meter(&self) -> f32 {
+        self.meter.unwrap_or(1.0)
+    }
 }
 
 /// Which axis represents up for an asset in a right-handed coordinate system
@@ -32,13 +56,24 @@ impl Unit {
 /// | X_UP  | Negative Y | Positive X | Positive Z |
 /// | Y_UP  | Positive X | Positive Y | Positive Z |
 /// | Z_UP  | Positive X | Positive Z | Negative Y |
-#[derive(Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum UpAxis {
     XUP,
     YUP,
     ZUP,
 }
 
+impl UpAxis {
+    /// The right, up and in axes of the table above
+    pub fn axes(&self) -> [[f32; 3]; 3] {
+        match *self {
+            UpAxis::XUP => [[0.0, -1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]],
+            UpAxis::YUP => [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+            UpAxis::ZUP => [[1.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, -1.0, 0.0]],
+        }
+    }
+}
+
 impl fmt::Display for UpAxis {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -50,58 +85,103 @@ impl fmt::Display for UpAxis {
 }
 
 /// Asset information for parent element
+///
+/// `<created>` and `<modified>` are required when parsing. A lenient parse
+/// recovers a missing or invalid one as the Unix epoch, not the current
+/// time, so reading a file twice gives the same asset.
 #[derive(Debug)]
 pub struct Asset {
     pub contributors: Vec<Contributor>,
     pub location: Option<Location>,
-    pub created: String,
+
+    /// The `<coverage>` as parsed, with double precision. It is used
+    /// instead of `location` while `location` is its `f32` copy, see
+    /// `geo_location`.
+    pub coverage: Option<GeoLocation>,
+    pub created: DateTime,
     pub keywords: Vec<String>,
-    pub modified: String,
+    pub modified: DateTime,
     pub revision: Option<String>,
     pub subject: Option<String>,
     pub title: Option<String>,
     pub unit: Option<Unit>,
     pub up_axis: Option<UpAxis>,
     pub extras: Vec<Extra>,
+    pub unknown: UnknownContent,
 }
 
 impl Asset {
+    /// Create a new asset, created and modified now
     pub fn new() -> Asset {
+        let now = DateTime::now();
         Asset {
             contributors: Vec::new(),
             location: None,
-            created: String::from(""), 
+            coverage: None,
+            created: now,
             keywords: Vec::new(),
-            modified: String::from(""),
+            modified: now,
             revision: None,
             subject: None,
             title: None,
             unit: None, 
             up_axis: None, 
             extras: Vec::new(), 
+            unknown: UnknownContent::new(),
+        }
+    }
+
+    /// Set the modified time to now, e.g. before saving changes
+    pub fn touch(&mut self) {
+        self.modified = DateTime::now();
+    }
+
+    /// The location with double precision: the parsed `<coverage>` unless
+    /// `location` has been changed since, otherwise `location` itself
+    pub fn geo_location(&self) -> Option<GeoLocation> {
+        let location = match self.location {
+            Some(ref l) => l,
+            None => return None,
+        };
+        match self.coverage {
+            Some(g) if g.to_f32() == *location => Some(g),
+            _ => Some(location.to_f64()),
         }
     }
+
+    /// Place the asset at its location in earth-centered, earth-fixed
+    /// coordinates with its unit and up axis, see `GeoLocation::georeference`
+    /// and `geo_location`
+    pub fn georeference(&self) -> Option<Matrix64> {
+        let meter = self.unit.as_ref().map(|u| u.meter()).unwrap_or(1.0);
+        let up = self.up_axis.unwrap_or(UpAxis::YUP);
+        self.geo_location().map(|l| l.georeference(meter as f64, up))
+    }
 }
 
 impl XmlConversion for Asset {
-    fn parse(&mut self, e: &Element) -> Result<(), ColladaError> {
+    fn parse_with(&mut self, e: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        ctx.unknown_attributes(e, &[], &mut self.unknown);
+        let mut created = None;
+        let mut modified = None;
         for c in &e.children {
             match c.name.as_str() {
                 "contributor" => {
                     let mut b = Contributor::new();
-                    try!(b.parse(c));
+                    try!(ctx.parse(&mut b, c));
                     self.contributors.push(b);
                     continue;
                 },
                 "coverage" => {
-                    let mut l = Location::new();
-                    try!(l.parse(c));
-                    self.location = Some(l);
+                    let mut l = GeoLocation::new();
+                    try!(ctx.parse(&mut l, c));
+                    self.location = Some(l.to_f32());
+                    self.coverage = Some(l);
                     continue;
                 },
                 "extra" => {
                     let mut x = Extra::new();
-                    try!(x.parse(c));
+                    try!(ctx.parse(&mut x, c));
                     self.extras.push(x);
                     continue;
                 },
@@ -111,31 +191,53 @@ impl XmlConversion for Asset {
                         Some(n) => Some(n.clone()),
                         None => None,
                     };
-                    u.meter = match c.attributes.get("meter") {
-                        Some(m) => Some(m.parse::<f32>().unwrap()),
-                        None => None,
-                    };
+                    u.meter = try!(utils::parse_attr(c, "meter"));
                     self.unit = Some(u);
                     continue;
                 },
                 _ => {}, 
             }
             
+            // A date that is recovered from is there, just not readable
+            match c.name.as_str() {
+                "created" => created = Some(DateTime::from_unix(0)),
+                "modified" => modified = Some(DateTime::from_unix(0)),
+                _ => {},
+            }
+
             let t = match c.text {
                 Some(ref t) => t,
-                None => return Err(ColladaError::MissingData{
-                    elem: c.name.clone(),
-                }),
+                None => {
+                    try!(ctx.recover(c, ColladaError::MissingData{
+                        elem: c.name.clone(),
+                    }));
+                    continue;
+                },
             };
 
             match c.name.as_str(){
-                "created" => self.created = t.clone(),
+                "created" | "modified" => {
+                    let time = match t.trim().parse() {
+                        Ok(x) => x,
+                        Err(_) => {
+                            try!(ctx.recover(c, ColladaError::InvalidData{
+                                elem: c.name.clone(),
+                                data: t.clone(),
+                            }));
+                            continue;
+                        },
+                    };
+                    if c.name == "created" {
+                        created = Some(time);
+                    } else {
+                        modified = Some(time);
+                    }
+                },
                 "keywords" => {
                     for kw in t.split_whitespace() {
                         self.keywords.push(kw.to_string());
                     }
                 },
-                "modified" => self.modified = t.clone(),
                 "revision" => self.revision = Some(t.clone()),
                 "subject" => self.subject = Some(t.clone()),
                 "title" => self.title = Some(t.clone()),
@@ -144,18 +246,26 @@ impl XmlConversion for Asset {
                         "X_UP" => self.up_axis = Some(UpAxis::XUP),
                         "Y_UP" => self.up_axis = Some(UpAxis::YUP),
                         "Z_UP" => self.up_axis = Some(UpAxis::ZUP),
-                        _ => return Err(ColladaError::InvalidData{
+                        _ => try!(ctx.recover(c, ColladaError::InvalidData{
                             elem: "up_axis".to_string(),
                             data: t.clone(),
-                        }),
+                        })),
                     };
                 },
-                _ => return Err(ColladaError::InvalidChild{
-                    child: c.name.clone(),
-                    parent: "asset".to_string(),
-                }),
+                _ => try!(ctx.unknown_child(e, c, &mut self.unknown)),
             }
         }
+
+        for &(name, time) in &[("created", created), ("modified", modified)] {
+            if time.is_none() {
+                try!(ctx.recover(e, ColladaError::MissingElement{
+                    structure: "asset".to_string(),
+                    elem: name.to_string(),
+                }));
+            }
+        }
+        self.created = created.unwrap_or(DateTime::from_unix(0));
+        self.modified = modified.unwrap_or(DateTime::from_unix(0));
         Ok(())
     }
 
@@ -166,37 +276,34 @@ impl XmlConversion for Asset {
             a.children.push(con.encode());
         }
 
-        match self.location {
-            Some(ref x) => a.children.push(x.encode()),
-            None => {},
+        match (self.location.as_ref(), self.coverage) {
+            (Some(l), Some(g)) if g.to_f32() == *l => a.children.push(g.encode()),
+            (Some(l), _) => a.children.push(l.encode()),
+            _ => {},
         }
 
         a.children.push(Element{
             name: "created".to_string(),
             attributes: HashMap::new(),
             children: Vec::new(),
-            text: Some(self.created.clone()),
+            text: Some(self.created.to_string()),
         });
         
-        let mut kw_str = String::new();
-        for kw in &self.keywords {
-            kw_str.push_str(kw.as_str());
-            kw_str.push(' ');
+        // An empty <keywords> has no data and can't be read back
+        if !self.keywords.is_empty() {
+            a.children.push(Element{
+                name: "keywords".to_string(),
+                attributes: HashMap::new(),
+                children: Vec::new(),
+                text: Some(self.keywords.join(" ")),
+            });
         }
-        kw_str.pop(); // Remove extra blank
-
-        a.children.push(Element{
-            name: "keywords".to_string(),
-            attributes: HashMap::new(),
-            children: Vec::new(),
-            text: Some(kw_str),
-        });
 
         a.children.push(Element{
             name: "modified".to_string(),
             attributes: HashMap::new(),
             children: Vec::new(),
-            text: Some(self.modified.clone()),
+            text: Some(self.modified.to_string()),
         });
       
         match self.revision {
@@ -268,6 +375,8 @@ impl XmlConversion for Asset {
             a.children.push(ext.encode());
         }
 
+        self.unknown.encode_into(&mut a);
+
         a
     }
 }
@@ -276,7 +385,9 @@ impl XmlConversion for Asset {
 mod tests {
     use super::*;
     use xmltree::{Element};
-    use core::{AltitudeMode, Contributor, Extra, Location};
+    use context::{ParseContext, ParseOptions, Strictness};
+    use core::{AltitudeMode, Contributor, DateTime, Extra, Location};
+    use error::{ColladaError};
     use traits::{XmlConversion};
 
     #[test]
@@ -323,12 +434,12 @@ mod tests {
             AltitudeMode::Absolute => assert!(true),
             _ => assert!(false),
         };
-        assert_eq!(a.created.as_str(), "2008-01-28T20:51:36Z");
+        assert_eq!(a.created, DateTime::new(2008, 1, 28, 20, 51, 36));
         assert_eq!(a.keywords.len(), 3);
         assert_eq!(a.keywords[0], "foo".to_string());
         assert_eq!(a.keywords[1], "bar".to_string());
         assert_eq!(a.keywords[2], "baz".to_string());
-        assert_eq!(a.modified.as_str(), "2008-01-28T20:51:36Z");
+        assert_eq!(a.modified.to_string(), "2008-01-28T20:51:36Z");
         assert_eq!(a.revision.unwrap().as_str(), "rev_v5");
         assert_eq!(a.title.unwrap().as_str(), "my sweet asset");
         let u = a.unit.unwrap();
@@ -344,6 +455,142 @@ mod tests {
         assert_eq!(a.extras[0].techniques[0].data.name.as_str(), "technique");
     }
 
+    #[test]
+    fn test_asset_parse_invalid_numbers() {
+        let data = r#"<asset><unit meter="1,5" name="foot"/></asset>"#;
+        let e = Element::parse(data.as_bytes()).unwrap();
+        match *Asset::new().parse(&e).unwrap_err().inner() {
+            ColladaError::InvalidAttrData{ref elem, ref attr, ref data} => {
+                assert_eq!(elem.as_str(), "unit");
+                assert_eq!(attr.as_str(), "meter");
+                assert_eq!(data.as_str(), "1,5");
+            },
+            _ => assert!(false),
+        }
+
+        let data = r#"
+            <asset>
+                <coverage>
+                    <geographic_location>
+                        <longitude>north</longitude>
+                        <latitude>0</latitude>
+                        <altitude mode="absolute">0</altitude>
+                    </geographic_location>
+                </coverage>
+            </asset>"#;
+        let e = Element::parse(data.as_bytes()).unwrap();
+        let err = Asset::new().parse(&e).unwrap_err();
+        assert_eq!(err.position().unwrap().path.as_str(), "/asset/coverage/geographic_location/longitude");
+        match *err.inner() {
+            ColladaError::InvalidData{ref data, ..} => assert_eq!(data.as_str(), "north"),
+            _ => assert!(false),
+        }
+    }
+
+    #[test]
+    fn test_asset_parse_invalid_date() {
+        let data = r#"<asset><created>2008-01-28</created></asset>"#;
+        let e = Element::parse(data.as_bytes()).unwrap();
+        match *Asset::new().parse(&e).unwrap_err().inner() {
+            ColladaError::InvalidData{ref elem, ref data} => {
+                assert_eq!(elem.as_str(), "created");
+                assert_eq!(data.as_str(), "2008-01-28");
+            },
+            _ => assert!(false),
+        }
+    }
+
+    #[test]
+    fn test_asset_parse_missing_date() {
+        let data = r#"<asset><modified>2008-01-28T20:51:36Z</modified></asset>"#;
+        let e = Element::parse(data.as_bytes()).unwrap();
+        match *Asset::new().parse(&e).unwrap_err().inner() {
+            ColladaError::MissingElement{ref structure, ref elem} => {
+                assert_eq!(structure.as_str(), "asset");
+                assert_eq!(elem.as_str(), "created");
+            },
+            _ => assert!(false),
+        }
+
+        // Lenient parsing doesn't make up times that change between reads
+        let data = r#"<asset><created>yesterday</created></asset>"#;
+        let e = Element::parse(data.as_bytes()).unwrap();
+        let options = ParseOptions{strictness: Strictness::Lenient, preserve_unknown: false};
+        let mut ctx = ParseContext::new().with_options(options);
+        let mut a = Asset::new();
+        ctx.parse(&mut a, &e).unwrap();
+        assert_eq!(a.created, DateTime::from_unix(0));
+        assert_eq!(a.modified, DateTime::from_unix(0));
+        let errors: Vec<String> = ctx.diagnostics().iter().map(|d| d.error.to_string()).collect();
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].contains("yesterday"), "{}", errors[0]);
+        assert!(errors[1].contains("<modified>"), "{}", errors[1]);
+    }
+
+    #[test]
+    fn test_asset_georeference() {
+        let mut a = Asset::new();
+        assert!(a.georeference().is_none());
+        let mut l = Location::new();
+        l.longitude = 90.0;
+        a.location = Some(l);
+        a.unit = Some(Unit::from_meter(0.01));
+        a.up_axis = Some(UpAxis::ZUP);
+
+        // East is -X and up is +Y at 90° east on the equator
+        let m = a.georeference().unwrap();
+        assert!((m[0] + 0.01).abs() < 1e-9);
+        assert!((m[6] - 0.01).abs() < 1e-9);
+        assert!((m[7] - 6378137.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_asset_geo_location() {
+        // 1e-7 degrees is about a centimetre, which f32 degrees lose
+        let data = r#"
+            <asset>
+                <coverage>
+                    <geographic_location>
+                        <longitude>-105.1234567</longitude>
+                        <latitude>40.1234567</latitude>
+                        <altitude mode="absolute">1609.25</altitude>
+                    </geographic_location>
+                </coverage>
+                <created>2008-01-28T20:51:36Z</created>
+                <modified>2008-01-28T20:51:36Z</modified>
+            </asset>"#;
+        let e = Element::parse(data.as_bytes()).unwrap();
+        let mut a = Asset::new();
+        a.parse(&e).unwrap();
+        let g = a.geo_location().unwrap();
+        assert_eq!(g.longitude, -105.1234567);
+        assert_eq!(g.latitude, 40.1234567);
+        assert_eq!(g.altitude, 1609.25);
+        assert_eq!(a.location.as_ref().unwrap().longitude, -105.1234567f32);
+        assert_eq!(a.georeference().unwrap(), g.georeference(1.0, UpAxis::YUP));
+
+        let geo = a.encode().children[0].children[0].clone();
+        assert_eq!(geo.children[0].text.as_ref().unwrap(), "-105.1234567");
+        assert_eq!(geo.children[2].text.as_ref().unwrap(), "1609.25");
+
+        // A changed location replaces the parsed one
+        a.location.as_mut().unwrap().longitude = 10.0;
+        assert_eq!(a.geo_location().unwrap().longitude, 10.0);
+        assert_eq!(a.encode().children[0].children[0].children[0].text.as_ref().unwrap(), "10");
+        a.location = None;
+        assert!(a.geo_location().is_none());
+        assert!(a.georeference().is_none());
+    }
+
+    #[test]
+    fn test_asset_touch() {
+        let mut a = Asset::new();
+        a.modified = DateTime::new(2008, 1, 28, 20, 51, 36);
+        a.touch();
+        assert!(a.modified.unix_time() >= a.created.unix_time());
+        assert!(a.modified.year >= 2017);
+    }
+
     #[test]
     fn test_asset_encode() {
         let mut asset = Asset::new();
@@ -356,9 +603,9 @@ mod tests {
         location.altitude = 50.0;
         asset.location = Some(location);
 
-        asset.created = "2008-01-28T20:51:36Z".to_string();
+        asset.created = "2008-01-28T20:51:36Z".parse().unwrap();
         asset.keywords = vec!["foo".to_string(), "bar".to_string(), "baz".to_string()];
-        asset.modified = "2008-01-28T20:51:36Z".to_string();
+        asset.modified = DateTime::new(2008, 1, 28, 20, 51, 36);
         asset.revision = Some("rev_v5".to_string());
         asset.title = Some("my sweet asset".to_string());
         
@@ -1,5 +1,7 @@
 use std::collections::{HashMap};
 use xmltree::{Element};
+use context::{ParseContext};
+use core::{UnknownContent};
 use error::{ColladaError};
 use traits::{XmlConversion};
 
@@ -14,6 +16,7 @@ pub struct Contributor {
     pub comments: Option<String>,
     pub copyright: Option<String>,
     pub source_data: Option<String>,
+    pub unknown: UnknownContent,
 }
 
 impl Contributor {
@@ -25,13 +28,14 @@ impl Contributor {
             authoring_tool: None,
             comments: None,
             copyright: None,
-            source_data: None
+            source_data: None,
+            unknown: UnknownContent::new(),
         }
     }
 }
 
 impl XmlConversion for Contributor {
-    fn parse(&mut self, e: &Element) -> Result<(), ColladaError> {
+    fn parse_with(&mut self, e: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError> {
         if e.name != "contributor".to_owned() {
             return Err(ColladaError::MissingElement{
                 structure: "contributor".to_string(),
@@ -39,26 +43,27 @@ impl XmlConversion for Contributor {
             });
         }
         
+        ctx.unknown_attributes(e, &[], &mut self.unknown);
         for c in &e.children {
-            let text = match c.text.clone() {
-                Some(t) => t,
-                None => return Err(ColladaError::MissingData{
-                    elem: c.name.clone(),
-                }),
+            let field = match c.name.as_str() {
+                "author" => &mut self.author,
+                "author_email" => &mut self.author_email,
+                "author_website" => &mut self.author_website,
+                "authoring_tool" => &mut self.authoring_tool,
+                "comments" => &mut self.comments,
+                "copyright" => &mut self.copyright,
+                "source_data" => &mut self.source_data,
+                _ => {
+                    try!(ctx.unknown_child(e, c, &mut self.unknown));
+                    continue;
+                },
             };
 
-            match c.name.as_str() {
-                "author" => self.author = Some(text),
-                "author_email" => self.author_email = Some(text),
-                "author_website" => self.author_website = Some(text),
-                "authoring_tool" => self.authoring_tool = Some(text),
-                "comments" => self.comments = Some(text),
-                "copyright" => self.copyright = Some(text),
-                "source_data" => self.source_data = Some(text),
-                _ => return Err(ColladaError::InvalidChild{
-                    child: c.name.clone(),
-                    parent: "contributor".to_string(),
-                }),
+            match c.text.clone() {
+                Some(t) => *field = Some(t),
+                None => try!(ctx.recover(c, ColladaError::MissingData{
+                    elem: c.name.clone(),
+                })),
             }
         }
         Ok(())
@@ -135,6 +140,7 @@ impl XmlConversion for Contributor {
             });
         }
 
+        self.unknown.encode_into(&mut root);
         return root;
     }
 }
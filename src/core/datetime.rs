@@ -0,0 +1,262 @@
+use std::fmt;
+use std::str::{FromStr};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// An xs:dateTime, e.g. `2008-01-28T20:51:36Z` or `2017-12-31T23:59:59.125-05:30`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct DateTime {
+    /// Year, negative for years before 1 BCE, e.g. -44 for `-0044`
+    pub year: i32,
+
+    /// Month of the year, starting at 1
+    pub month: u32,
+
+    /// Day of the month, starting at 1
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+
+    /// Fraction of the second in nanoseconds
+    pub nanosecond: u32,
+
+    /// Offset from UTC in minutes, e.g. -330 for `-05:30`. None when the
+    /// time has no time zone.
+    pub offset: Option<i32>,
+}
+
+impl DateTime {
+    /// A UTC date and time
+    pub fn new(year: i32, month: u32, day: u32, hour: u32, minute: u32, second: u32) -> DateTime {
+        DateTime {
+            year: year,
+            month: month,
+            day: day,
+            hour: hour,
+            minute: minute,
+            second: second,
+            nanosecond: 0,
+            offset: Some(0),
+        }
+    }
+
+    /// The current UTC time, to the second
+    pub fn now() -> DateTime {
+        let secs = match SystemTime::now().duration_since(UNIX_EPOCH) {
+            Ok(d) => d.as_secs() as i64,
+            Err(e) => -(e.duration().as_secs() as i64),
+        };
+        DateTime::from_unix(secs)
+    }
+
+    /// The UTC time of seconds since 1970-01-01T00:00:00Z
+    pub fn from_unix(secs: i64) -> DateTime {
+        let days = div_floor(secs, 86400);
+        let rem = secs - days * 86400;
+        let (year, month, day) = civil_from_days(days);
+        DateTime::new(year, month, day, (rem / 3600) as u32, (rem % 3600 / 60) as u32, (rem % 60) as u32)
+    }
+
+    /// Seconds since 1970-01-01T00:00:00Z, times without a time zone are
+    /// taken as UTC
+    pub fn unix_time(&self) -> i64 {
+        let days = days_from_civil(self.year, self.month, self.day);
+        days * 86400 + self.hour as i64 * 3600 + self.minute as i64 * 60 + self.second as i64
+            - self.offset.unwrap_or(0) as i64 * 60
+    }
+}
+
+impl FromStr for DateTime {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<DateTime, ()> {
+        let (negative, s) = if s.starts_with('-') { (true, &s[1..]) } else { (false, s) };
+        let digits = s.bytes().take_while(|b| (*b as char).is_digit(10)).count();
+        // Years of more than four digits can't have leading zeros
+        if digits < 4 || (digits > 4 && s.starts_with('0')) {
+            return Err(());
+        }
+        let year: i32 = try!(s[..digits].parse().map_err(|_| ()));
+        if year == 0 {
+            return Err(());
+        }
+
+        let mut t = DateTime::new(if negative { -year } else { year }, 0, 0, 0, 0, 0);
+        let mut s = &s[digits..];
+        let mut fields = [0; 5];
+        for (i, sep) in ['-', '-', 'T', ':', ':'].iter().enumerate() {
+            let (x, rest) = try!(split_field(s, *sep));
+            fields[i] = x;
+            s = rest;
+        }
+        t.month = fields[0];
+        t.day = fields[1];
+        t.hour = fields[2];
+        t.minute = fields[3];
+        t.second = fields[4];
+        if t.month < 1 || t.month > 12 || t.day < 1 || t.day > days_in_month(t.year, t.month)
+            || t.minute > 59 || t.second > 59
+        {
+            return Err(());
+        }
+
+        if s.starts_with('.') {
+            let n = s[1..].bytes().take_while(|b| (*b as char).is_digit(10)).count();
+            if n == 0 {
+                return Err(());
+            }
+            // Digits past nanoseconds are dropped
+            let mut fraction = String::from(&s[1..1 + n.min(9)]);
+            while fraction.len() < 9 {
+                fraction.push('0');
+            }
+            t.nanosecond = try!(fraction.parse().map_err(|_| ()));
+            s = &s[1 + n..];
+        }
+
+        // 24:00:00 is the end of the day
+        if t.hour > 24 || (t.hour == 24 && (t.minute != 0 || t.second != 0 || t.nanosecond != 0)) {
+            return Err(());
+        }
+
+        t.offset = match s.chars().next() {
+            None => None,
+            Some('Z') if s.len() == 1 => Some(0),
+            Some(sign) if sign == '+' || sign == '-' => {
+                let (h, rest) = try!(split_digits(&s[1..]));
+                let (m, rest) = try!(split_field(rest, ':'));
+                if !rest.is_empty() || h > 14 || m > 59 || (h == 14 && m != 0) {
+                    return Err(());
+                }
+                let offset = (h * 60 + m) as i32;
+                Some(if sign == '-' { -offset } else { offset })
+            },
+            _ => return Err(()),
+        };
+        Ok(t)
+    }
+}
+
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.year < 0 {
+            try!(write!(f, "-"));
+        }
+        try!(write!(f, "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}", self.year.abs(), self.month,
+                    self.day, self.hour, self.minute, self.second));
+        if self.nanosecond > 0 {
+            let fraction = format!("{:09}", self.nanosecond);
+            try!(write!(f, ".{}", fraction.trim_end_matches('0')));
+        }
+        match self.offset {
+            None => Ok(()),
+            Some(0) => write!(f, "Z"),
+            Some(m) => write!(f, "{}{:02}:{:02}", if m < 0 { '-' } else { '+' }, m.abs() / 60, m.abs() % 60),
+        }
+    }
+}
+
+/// Split two digits from the start of a string
+fn split_digits(s: &str) -> Result<(u32, &str), ()> {
+    if s.len() < 2 || !s.as_bytes()[..2].iter().all(|b| (*b as char).is_digit(10)) {
+        return Err(());
+    }
+    s[..2].parse().map(|x| (x, &s[2..])).map_err(|_| ())
+}
+
+/// Split a separator and two digits from the start of a string
+fn split_field(s: &str, sep: char) -> Result<(u32, &str), ()> {
+    if !s.starts_with(sep) {
+        return Err(());
+    }
+    split_digits(&s[1..])
+}
+
+fn is_leap_year(year: i32) -> bool {
+    // xs:dateTime has no year 0, so 1 BCE is year -1 and a leap year
+    let y = if year < 0 { year + 1 } else { year };
+    (y % 4 == 0 && y % 100 != 0) || y % 400 == 0
+}
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+fn div_floor(a: i64, b: i64) -> i64 {
+    let d = a / b;
+    if a % b < 0 { d - 1 } else { d }
+}
+
+/// Days since 1970-01-01 of a date in the proleptic Gregorian calendar
+fn days_from_civil(year: i32, month: u32, day: u32) -> i64 {
+    let y = if year < 0 { year as i64 + 1 } else { year as i64 } - if month <= 2 { 1 } else { 0 };
+    let era = div_floor(y, 400);
+    let yoe = y - era * 400;
+    let m = month as i64;
+    let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+/// The date of a count of days since 1970-01-01
+fn civil_from_days(days: i64) -> (i32, u32, u32) {
+    let z = days + 719468;
+    let era = div_floor(z, 146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let y = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    let year = if y <= 0 { y - 1 } else { y } as i32;
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_date_time_parse() {
+        let t: DateTime = "2008-01-28T20:51:36Z".parse().unwrap();
+        assert_eq!(t, DateTime::new(2008, 1, 28, 20, 51, 36));
+
+        let t: DateTime = "2017-12-31T23:59:59.125-05:30".parse().unwrap();
+        assert_eq!(t.nanosecond, 125000000);
+        assert_eq!(t.offset, Some(-330));
+        assert_eq!(t.to_string(), "2017-12-31T23:59:59.125-05:30");
+
+        let t: DateTime = "-0044-03-15T12:00:00".parse().unwrap();
+        assert_eq!(t.year, -44);
+        assert_eq!(t.offset, None);
+        assert_eq!(t.to_string(), "-0044-03-15T12:00:00");
+
+        assert!("2016-02-29T24:00:00+14:00".parse::<DateTime>().is_ok());
+        let invalid = ["", "2017-01-01", "2017-13-01T00:00:00Z", "2017-02-29T00:00:00Z",
+                       "2017-01-01T00:00:00z", "2017-01-01T24:00:01Z", "2017-01-01T00:60:00Z",
+                       "2017-01-01T00:00:00+5", "2017-01-01T00:00:00+15:00", "0000-01-01T00:00:00",
+                       "17-01-01T00:00:00", "2017-01-01T00:00:00.Z", "2017-01-01 00:00:00"];
+        for s in invalid.iter() {
+            assert!(s.parse::<DateTime>().is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn test_date_time_unix() {
+        assert_eq!(DateTime::from_unix(0).to_string(), "1970-01-01T00:00:00Z");
+        assert_eq!(DateTime::from_unix(951782400).to_string(), "2000-02-29T00:00:00Z");
+        assert_eq!(DateTime::from_unix(-1).to_string(), "1969-12-31T23:59:59Z");
+
+        let t: DateTime = "2000-02-29T05:30:00+05:30".parse().unwrap();
+        assert_eq!(t.unix_time(), 951782400);
+        let t: DateTime = "-0001-12-31T00:00:00Z".parse().unwrap();
+        assert_eq!(DateTime::from_unix(t.unix_time()), t);
+        assert!(DateTime::now().year >= 2017);
+    }
+}
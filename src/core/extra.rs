@@ -1,7 +1,9 @@
 use xmltree::{Element};
-use core::{Asset, Technique};
+use context::{ParseContext};
+use core::{Asset, Technique, UnknownContent};
 use error::{ColladaError};
 use traits::{XmlConversion};
+use utils;
 
 /// Provides arbitrary additional information about or related to its parent
 #[derive(Debug)]
@@ -11,6 +13,11 @@ pub struct Extra {
     pub typ: Option<String>,
     pub asset: Option<Asset>,
     pub techniques: Vec<Technique>,
+
+    /// Namespaces declared on the extra as `(prefix, uri)` pairs, e.g. for
+    /// prefixed elements in its techniques
+    pub namespaces: Vec<(String, String)>,
+    pub unknown: UnknownContent,
 }
 
 impl Extra {
@@ -21,12 +28,15 @@ impl Extra {
             typ: None,
             asset: None,
             techniques: Vec::new(),
+            namespaces: Vec::new(),
+            unknown: UnknownContent::new(),
         }
     }
 }
 
 impl XmlConversion for Extra {
-    fn parse(&mut self, e: &Element) -> Result<(), ColladaError> {
+    fn parse_with(&mut self, e: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        ctx.unknown_attributes(e, &["id", "name", "type"], &mut self.unknown);
         self.id = match e.attributes.get("id") {
             Some(v) => Some(v.clone()),
             None => None,
@@ -41,11 +51,13 @@ impl XmlConversion for Extra {
             Some(v) => Some(v.clone()),
             None => None,
         };
+
+        self.namespaces = utils::get_namespaces(e);
         
         match e.get_child("asset") {
             Some(a_node) => {
                 let mut a = Asset::new();
-                try!(a.parse(a_node));
+                try!(ctx.parse(&mut a, a_node));
                 self.asset = Some(a);
             },
             None => {},
@@ -62,14 +74,11 @@ impl XmlConversion for Extra {
             match c.name.as_str() {
                 "technique" => {
                     let mut t = Technique::new();
-                    try!(t.parse(c));
+                    try!(ctx.parse(&mut t, c));
                     self.techniques.push(t);
                 },
                 "asset" => {}, // Skip if asset, this is just to avoid validation error
-                _ => return Err(ColladaError::InvalidChild{
-                    child: c.name.clone(),
-                    parent: "extra".to_string(),
-                }),
+                _ => try!(ctx.unknown_child(e, c, &mut self.unknown)),
             }
         }
 
@@ -88,6 +97,7 @@ impl XmlConversion for Extra {
         if self.typ.is_some() {
             ext.attributes.insert("type".to_string(), self.typ.clone().unwrap());
         }
+        utils::set_namespaces(&mut ext, &self.namespaces);
         
         match self.asset {
             Some(ref x) => ext.children.push(x.encode()),
@@ -98,6 +108,7 @@ impl XmlConversion for Extra {
             ext.children.push(t.encode());
         }
 
+        self.unknown.encode_into(&mut ext);
         ext
     }
 }
@@ -106,6 +117,7 @@ impl XmlConversion for Extra {
 mod tests {
     use super::*;
     use xmltree::{Element};
+    use context::{ParseContext};
     use core::{Asset, Technique};
     use traits::{XmlConversion};
 
@@ -114,8 +126,8 @@ mod tests {
         let data = r#"
             <extra id='some-id' name='some-name' type='basic'>
                 <asset>
-                    <created>2005-06-27T21:00:00z</created>
-                    <modified>2005-06-27T21:00:00z</modified>
+                    <created>2005-06-27T21:00:00Z</created>
+                    <modified>2005-06-27T21:00:00Z</modified>
                 </asset>
                 <technique profile='lolo'>
                     <oh>fo fo fo</oh>
@@ -135,8 +147,8 @@ mod tests {
         assert_eq!(x.typ.unwrap().as_str(), "basic");
         assert_eq!(x.asset.is_some(), true);
         let asset = x.asset.unwrap();
-        assert_eq!(asset.created, "2005-06-27T21:00:00z");
-        assert_eq!(asset.modified, "2005-06-27T21:00:00z");
+        assert_eq!(asset.created.to_string(), "2005-06-27T21:00:00Z");
+        assert_eq!(asset.modified.to_string(), "2005-06-27T21:00:00Z");
         assert_eq!(x.techniques.len(), 2);
         assert_eq!(x.techniques[0].profile, "lolo");
         assert_eq!(x.techniques[1].profile, "lala");
@@ -150,8 +162,8 @@ mod tests {
         x.typ = Some(String::from("basic"));
 
         let mut a = Asset::new();
-        a.created = "2005-06-27T21:00:00z".to_owned();
-        a.modified = "2005-06-27T21:00:00z".to_owned();
+        a.created = "2005-06-27T21:00:00Z".parse().unwrap();
+        a.modified = "2005-06-27T21:00:00Z".parse().unwrap();
         x.asset = Some(a);
         
         let mut t = Technique::new();
@@ -165,8 +177,30 @@ mod tests {
         assert_eq!(e.attributes.get("type"), Some(&String::from("basic")));
         assert_eq!(e.children.len(), 2);
         assert_eq!(e.children[0].name, "asset");
-        assert_eq!(e.children[0].children.len(), 3);
+        assert_eq!(e.children[0].children.len(), 2);
         assert_eq!(e.children[1].name, "technique");
     }
-}
 
+    #[test]
+    fn test_extra_namespaces() {
+        let data = r#"
+            <extra xmlns:max="some/max/schema">
+                <technique profile="MAX3D">
+                    <max:frame_rate>30</max:frame_rate>
+                </technique>
+            </extra>"#;
+        let (e, _) = ParseContext::from_source(data.as_bytes()).unwrap();
+        let mut x = Extra::new();
+        match x.parse(&e) {
+            Ok(_) => assert!(true),
+            Err(_) => assert!(false),
+        }
+        assert_eq!(x.namespaces, vec![("max".to_string(), "some/max/schema".to_string())]);
+        assert!(x.techniques[0].namespaces.is_empty());
+        assert_eq!(x.techniques[0].data.children[0].name, "max:frame_rate");
+
+        let e = x.encode();
+        assert_eq!(e.attributes.get("xmlns:max"), Some(&String::from("some/max/schema")));
+        assert_eq!(e.children[0].children[0].name, "max:frame_rate");
+    }
+}
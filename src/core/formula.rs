@@ -0,0 +1,475 @@
+use std::collections::{HashMap};
+use xmltree::{Element};
+use context::{ParseContext};
+use core::{Asset, Extra, HasTechniques, MathExpr, Technique, UnknownContent};
+use error::{ColladaError};
+use fx::{NewParam, ParamScope, ParamValue, SetParam};
+use traits::{XmlConversion};
+use utils;
+
+/// A mathematical function of its parameters, e.g. the dependency of one
+/// joint value on another
+#[derive(Debug)]
+pub struct Formula {
+    pub id: Option<String>,
+    pub name: Option<String>,
+    pub sid: Option<String>,
+    pub newparams: Vec<NewParam>,
+
+    /// The value the formula computes, a float or a parameter reference
+    pub target: ParamValue,
+    pub math: MathExpr,
+    pub techniques: Vec<Technique>,
+}
+
+impl Formula {
+    pub fn new() -> Formula {
+        Formula {
+            id: None,
+            name: None,
+            sid: None,
+            newparams: Vec::new(),
+            target: ParamValue::Float(0.0),
+            math: MathExpr::Number(0.0),
+            techniques: Vec::new(),
+        }
+    }
+
+    /// Evaluate the formula. Identifiers are looked up in `bindings` first,
+    /// then in the formula's parameters. A parameter holding a SIDREF takes
+    /// the value bound to the referenced element.
+    pub fn evaluate(&self, bindings: &HashMap<String, f32>) -> Result<f32, ColladaError> {
+        self.evaluate_in(&ParamScope::new().with_scope(&self.newparams), bindings)
+    }
+
+    fn evaluate_in(&self, scope: &ParamScope, bindings: &HashMap<String, f32>)
+        -> Result<f32, ColladaError>
+    {
+        self.math.evaluate(&|id: &str| {
+            if let Some(v) = bindings.get(id) {
+                return Some(*v);
+            }
+            match scope.lookup(id) {
+                Some(&ParamValue::SidRef(ref r)) => bindings.get(r).cloned(),
+                Some(&ParamValue::Bool(b)) => Some(if b { 1.0 } else { 0.0 }),
+                Some(v) => v.as_float(),
+                None => None,
+            }
+        })
+    }
+}
+
+impl XmlConversion for Formula {
+    fn parse_with(&mut self, e: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        self.id = utils::get_attr(e, "id");
+        self.name = utils::get_attr(e, "name");
+        self.sid = utils::get_attr(e, "sid");
+
+        let (mut has_target, mut has_math) = (false, false);
+        for c in &e.children {
+            match c.name.as_str() {
+                "newparam" => {
+                    let mut n = NewParam::new();
+                    try!(ctx.parse(&mut n, c));
+                    self.newparams.push(n);
+                },
+                "target" => {
+                    self.target = try!(ParamValue::parse_wrapped(c, ctx));
+                    has_target = true;
+                },
+                "technique_common" => {
+                    match c.get_child("math") {
+                        Some(m) => self.math = try!(MathExpr::parse_math(m)),
+                        None => return Err(ColladaError::MissingElement{
+                            structure: "technique_common".to_string(),
+                            elem: "math".to_string(),
+                        }),
+                    }
+                    has_math = true;
+                },
+                "technique" => {
+                    let mut t = Technique::new();
+                    try!(ctx.parse(&mut t, c));
+                    self.techniques.push(t);
+                },
+                _ => return Err(ColladaError::InvalidChild{
+                    child: c.name.clone(),
+                    parent: "formula".to_string(),
+                }),
+            }
+        }
+
+        if !has_target {
+            return Err(ColladaError::MissingElement{
+                structure: "formula".to_string(),
+                elem: "target".to_string(),
+            });
+        }
+        if !has_math {
+            return Err(ColladaError::MissingElement{
+                structure: "formula".to_string(),
+                elem: "technique_common".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut f = Element::new("formula");
+        utils::set_attr(&mut f, "id", &self.id);
+        utils::set_attr(&mut f, "name", &self.name);
+        utils::set_attr(&mut f, "sid", &self.sid);
+
+        for n in &self.newparams {
+            f.children.push(n.encode());
+        }
+        f.children.push(self.target.encode_wrapped("target"));
+        let mut tc = Element::new("technique_common");
+        tc.children.push(self.math.encode_math());
+        f.children.push(tc);
+        for t in &self.techniques {
+            f.children.push(t.encode());
+        }
+        f
+    }
+}
+
+impl HasTechniques for Formula {
+    type Common = MathExpr;
+
+    fn technique_common(&self) -> &MathExpr {
+        &self.math
+    }
+
+    fn techniques(&self) -> &[Technique] {
+        &self.techniques
+    }
+}
+
+/// Instantiates a formula, overriding its parameters
+#[derive(Debug)]
+pub struct InstanceFormula {
+    /// Url of the formula to instantiate
+    pub url: String,
+    pub sid: Option<String>,
+    pub name: Option<String>,
+    pub setparams: Vec<SetParam>,
+    pub extras: Vec<Extra>,
+    pub unknown: UnknownContent,
+}
+
+impl InstanceFormula {
+    pub fn new() -> InstanceFormula {
+        InstanceFormula {
+            url: String::from(""),
+            sid: None,
+            name: None,
+            setparams: Vec::new(),
+            extras: Vec::new(),
+            unknown: UnknownContent::new(),
+        }
+    }
+
+    /// Find the instantiated formula in the formula libraries
+    pub fn formula<'a>(&self, libraries: &'a [LibraryFormulas]) -> Option<&'a Formula> {
+        utils::url_id(&self.url)
+            .and_then(|id| libraries.iter().filter_map(|l| l.get(id)).next())
+    }
+
+    /// Evaluate the instantiated formula with the parameter overrides
+    pub fn evaluate(&self, libraries: &[LibraryFormulas], bindings: &HashMap<String, f32>)
+        -> Result<f32, ColladaError>
+    {
+        match self.formula(libraries) {
+            Some(f) => {
+                let scope = ParamScope::new()
+                    .with_overrides(&self.setparams)
+                    .with_scope(&f.newparams);
+                f.evaluate_in(&scope, bindings)
+            },
+            None => Err(ColladaError::Invalid{
+                msg: format!("Unable to resolve formula '{}'", self.url),
+            }),
+        }
+    }
+}
+
+impl XmlConversion for InstanceFormula {
+    fn parse_with(&mut self, e: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        ctx.unknown_attributes(e, &["url", "sid", "name"], &mut self.unknown);
+        self.url = try!(utils::require_attr(e, "url"));
+        self.sid = utils::get_attr(e, "sid");
+        self.name = utils::get_attr(e, "name");
+
+        for c in &e.children {
+            match c.name.as_str() {
+                "setparam" => {
+                    let mut s = SetParam::new();
+                    try!(ctx.parse(&mut s, c));
+                    self.setparams.push(s);
+                },
+                "extra" => {
+                    let mut x = Extra::new();
+                    try!(ctx.parse(&mut x, c));
+                    self.extras.push(x);
+                },
+                _ => try!(ctx.unknown_child(e, c, &mut self.unknown)),
+            }
+        }
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut i = Element::new("instance_formula");
+        i.attributes.insert("url".to_string(), self.url.clone());
+        utils::set_attr(&mut i, "sid", &self.sid);
+        utils::set_attr(&mut i, "name", &self.name);
+        for s in &self.setparams {
+            i.children.push(s.encode());
+        }
+        for x in &self.extras {
+            i.children.push(x.encode());
+        }
+        self.unknown.encode_into(&mut i);
+        i
+    }
+}
+
+/// A formula, either instantiated from a library or defined inline
+#[derive(Debug)]
+pub enum FormulaRef {
+    Instance(InstanceFormula),
+    Inline(Formula),
+}
+
+impl FormulaRef {
+    /// Parse an `<instance_formula>` or `<formula>` element
+    pub fn parse(e: &Element, ctx: &mut ParseContext) -> Result<FormulaRef, ColladaError> {
+        if e.name == "instance_formula" {
+            let mut i = InstanceFormula::new();
+            try!(ctx.parse(&mut i, e));
+            Ok(FormulaRef::Instance(i))
+        } else {
+            let mut f = Formula::new();
+            try!(ctx.parse(&mut f, e));
+            Ok(FormulaRef::Inline(f))
+        }
+    }
+
+    pub fn encode(&self) -> Element {
+        match *self {
+            FormulaRef::Instance(ref i) => i.encode(),
+            FormulaRef::Inline(ref f) => f.encode(),
+        }
+    }
+
+    /// Evaluate the formula, looking up instantiated formulas in the libraries
+    pub fn evaluate(&self, libraries: &[LibraryFormulas], bindings: &HashMap<String, f32>)
+        -> Result<f32, ColladaError>
+    {
+        match *self {
+            FormulaRef::Instance(ref i) => i.evaluate(libraries, bindings),
+            FormulaRef::Inline(ref f) => f.evaluate(bindings),
+        }
+    }
+}
+
+/// Library of formulas
+#[derive(Debug)]
+pub struct LibraryFormulas {
+    pub id: Option<String>,
+    pub name: Option<String>,
+    pub asset: Option<Asset>,
+    pub formulas: Vec<Formula>,
+    pub extras: Vec<Extra>,
+    pub unknown: UnknownContent,
+}
+
+impl LibraryFormulas {
+    pub fn new() -> LibraryFormulas {
+        LibraryFormulas {
+            id: None,
+            name: None,
+            asset: None,
+            formulas: Vec::new(),
+            extras: Vec::new(),
+            unknown: UnknownContent::new(),
+        }
+    }
+
+    /// Find a formula by its id
+    pub fn get(&self, id: &str) -> Option<&Formula> {
+        self.formulas.iter().find(|f| f.id.as_ref().map(|x| x.as_str()) == Some(id))
+    }
+}
+
+impl XmlConversion for LibraryFormulas {
+    fn parse_with(&mut self, e: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        ctx.unknown_attributes(e, &["id", "name"], &mut self.unknown);
+        self.id = utils::get_attr(e, "id");
+        self.name = utils::get_attr(e, "name");
+
+        for c in &e.children {
+            match c.name.as_str() {
+                "asset" => {
+                    let mut a = Asset::new();
+                    try!(ctx.parse(&mut a, c));
+                    self.asset = Some(a);
+                },
+                "formula" => {
+                    let mut f = Formula::new();
+                    try!(ctx.parse(&mut f, c));
+                    self.formulas.push(f);
+                },
+                "extra" => {
+                    let mut x = Extra::new();
+                    try!(ctx.parse(&mut x, c));
+                    self.extras.push(x);
+                },
+                _ => try!(ctx.unknown_child(e, c, &mut self.unknown)),
+            }
+        }
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut l = Element::new("library_formulas");
+        utils::set_attr(&mut l, "id", &self.id);
+        utils::set_attr(&mut l, "name", &self.name);
+
+        match self.asset {
+            Some(ref x) => l.children.push(x.encode()),
+            None => {},
+        }
+        for f in &self.formulas {
+            l.children.push(f.encode());
+        }
+        for x in &self.extras {
+            l.children.push(x.encode());
+        }
+        self.unknown.encode_into(&mut l);
+        l
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::{HashMap};
+    use xmltree::{Element};
+    use context::{ParseContext};
+    use traits::{XmlConversion};
+
+    const LIBRARY: &'static str = r##"
+        <library_formulas>
+            <formula id="coupling" sid="f0">
+                <newparam sid="ratio"><float>0.5</float></newparam>
+                <newparam sid="driver"><SIDREF>kmodel/j0/axis0</SIDREF></newparam>
+                <target><param ref="j1_value"/></target>
+                <technique_common>
+                    <math xmlns="http://www.w3.org/1998/Math/MathML">
+                        <apply>
+                            <plus/>
+                            <apply><times/><ci>ratio</ci><ci>driver</ci></apply>
+                            <ci>offset</ci>
+                        </apply>
+                    </math>
+                </technique_common>
+            </formula>
+        </library_formulas>"##;
+
+    #[test]
+    fn test_formula_parse() {
+        let e = Element::parse(LIBRARY.as_bytes()).unwrap();
+        let mut l = LibraryFormulas::new();
+        match l.parse(&e) {
+            Ok(_) => assert!(true),
+            Err(_) => assert!(false),
+        }
+        let f = l.get("coupling").unwrap();
+        assert_eq!(f.sid, Some("f0".to_string()));
+        assert_eq!(f.newparams.len(), 2);
+        match f.target {
+            ParamValue::Param(ref r) => assert_eq!(r.as_str(), "j1_value"),
+            _ => assert!(false),
+        }
+
+        let data = r##"<formula id="f"><target><float>0</float></target></formula>"##;
+        let e = Element::parse(data.as_bytes()).unwrap();
+        assert!(Formula::new().parse(&e).is_err());
+
+        let data = r##"
+            <formula id="f">
+                <technique_common><math><cn>1</cn></math></technique_common>
+            </formula>"##;
+        let e = Element::parse(data.as_bytes()).unwrap();
+        assert!(Formula::new().parse(&e).is_err());
+    }
+
+    #[test]
+    fn test_formula_evaluate() {
+        let e = Element::parse(LIBRARY.as_bytes()).unwrap();
+        let mut l = LibraryFormulas::new();
+        l.parse(&e).unwrap();
+        let f = l.get("coupling").unwrap();
+
+        let mut bindings = HashMap::new();
+        bindings.insert("kmodel/j0/axis0".to_string(), 40.0);
+        bindings.insert("offset".to_string(), 5.0);
+        assert_eq!(f.evaluate(&bindings).unwrap(), 25.0);
+
+        // Bindings take precedence over parameters
+        bindings.insert("ratio".to_string(), 2.0);
+        assert_eq!(f.evaluate(&bindings).unwrap(), 85.0);
+
+        bindings.remove("offset");
+        assert!(f.evaluate(&bindings).is_err());
+    }
+
+    #[test]
+    fn test_instance_formula_evaluate() {
+        let e = Element::parse(LIBRARY.as_bytes()).unwrap();
+        let mut l = LibraryFormulas::new();
+        l.parse(&e).unwrap();
+        let libraries = vec![l];
+
+        let data = r##"
+            <instance_formula url="#coupling" sid="couple">
+                <setparam ref="ratio"><float>-1</float></setparam>
+            </instance_formula>"##;
+        let e = Element::parse(data.as_bytes()).unwrap();
+        let f = FormulaRef::parse(&e, &mut ParseContext::new()).unwrap();
+
+        let mut bindings = HashMap::new();
+        bindings.insert("kmodel/j0/axis0".to_string(), 40.0);
+        bindings.insert("offset".to_string(), 5.0);
+        assert_eq!(f.evaluate(&libraries, &bindings).unwrap(), -35.0);
+
+        let e = Element::parse(r##"<instance_formula url="#missing"/>"##.as_bytes()).unwrap();
+        let f = FormulaRef::parse(&e, &mut ParseContext::new()).unwrap();
+        assert!(f.evaluate(&libraries, &bindings).is_err());
+    }
+
+    #[test]
+    fn test_formula_encode() {
+        let e = Element::parse(LIBRARY.as_bytes()).unwrap();
+        let mut l = LibraryFormulas::new();
+        l.parse(&e).unwrap();
+        let e = l.encode();
+        assert_eq!(e.name, "library_formulas");
+        let f = &e.children[0];
+        let names: Vec<&str> = f.children.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["newparam", "newparam", "target", "technique_common"]);
+        assert_eq!(f.children[3].children[0].name, "math");
+
+        let mut g = Formula::new();
+        g.parse(f).unwrap();
+        assert_eq!(g.math, l.formulas[0].math);
+
+        let mut i = InstanceFormula::new();
+        i.url = "#coupling".to_string();
+        let e = i.encode();
+        assert_eq!(e.name, "instance_formula");
+        assert_eq!(e.attributes.get("url"), Some(&"#coupling".to_string()));
+    }
+}
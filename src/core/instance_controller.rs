@@ -0,0 +1,128 @@
+use xmltree::{Element};
+use context::{ParseContext};
+use core::{Extra, UnknownContent};
+use error::{ColladaError};
+use fx::{BindMaterial};
+use traits::{XmlConversion};
+use utils;
+
+/// Instantiates a controller, binding skeletons and materials
+#[derive(Debug)]
+pub struct InstanceController {
+    pub sid: Option<String>,
+    pub name: Option<String>,
+
+    /// Url of the controller to instantiate
+    pub url: String,
+
+    /// Urls of the nodes where the skin controller starts searching for joints
+    pub skeletons: Vec<String>,
+    pub bind_material: Option<BindMaterial>,
+    pub extras: Vec<Extra>,
+    pub unknown: UnknownContent,
+}
+
+impl InstanceController {
+    pub fn new() -> InstanceController {
+        InstanceController {
+            sid: None,
+            name: None,
+            url: String::from(""),
+            skeletons: Vec::new(),
+            bind_material: None,
+            extras: Vec::new(),
+            unknown: UnknownContent::new(),
+        }
+    }
+}
+
+impl XmlConversion for InstanceController {
+    fn parse_with(&mut self, e: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        ctx.unknown_attributes(e, &["sid", "name", "url"], &mut self.unknown);
+        self.sid = utils::get_attr(e, "sid");
+        self.name = utils::get_attr(e, "name");
+        self.url = try!(utils::require_attr(e, "url"));
+
+        for c in &e.children {
+            match c.name.as_str() {
+                "skeleton" => {
+                    let s = try!(utils::require_text(c));
+                    self.skeletons.push(s.trim().to_string());
+                },
+                "bind_material" => {
+                    let mut b = BindMaterial::new();
+                    try!(ctx.parse(&mut b, c));
+                    self.bind_material = Some(b);
+                },
+                "extra" => {
+                    let mut x = Extra::new();
+                    try!(ctx.parse(&mut x, c));
+                    self.extras.push(x);
+                },
+                _ => try!(ctx.unknown_child(e, c, &mut self.unknown)),
+            }
+        }
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut i = Element::new("instance_controller");
+        utils::set_attr(&mut i, "sid", &self.sid);
+        utils::set_attr(&mut i, "name", &self.name);
+        i.attributes.insert("url".to_string(), self.url.clone());
+
+        for s in &self.skeletons {
+            i.children.push(utils::text_element("skeleton", s.clone()));
+        }
+        match self.bind_material {
+            Some(ref b) => i.children.push(b.encode()),
+            None => {},
+        }
+        for x in &self.extras {
+            i.children.push(x.encode());
+        }
+        self.unknown.encode_into(&mut i);
+        i
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use xmltree::{Element};
+    use traits::{XmlConversion};
+
+    #[test]
+    fn test_instance_controller_parse() {
+        let data = r##"
+            <instance_controller url="#skin">
+                <skeleton>#root</skeleton>
+                <bind_material>
+                    <technique_common>
+                        <instance_material symbol="WHITE" target="#white-mat"/>
+                    </technique_common>
+                </bind_material>
+            </instance_controller>"##;
+        let e = Element::parse(data.as_bytes()).unwrap();
+        let mut i = InstanceController::new();
+        match i.parse(&e) {
+            Ok(_) => assert!(true),
+            Err(_) => assert!(false),
+        }
+        assert_eq!(i.url.as_str(), "#skin");
+        assert_eq!(i.skeletons, vec!["#root".to_string()]);
+        assert!(i.bind_material.is_some());
+    }
+
+    #[test]
+    fn test_instance_controller_encode() {
+        let mut i = InstanceController::new();
+        i.url = "#skin".to_string();
+        i.skeletons.push("#root".to_string());
+        let e = i.encode();
+        assert_eq!(e.name, "instance_controller");
+        assert_eq!(e.children.len(), 1);
+        assert_eq!(e.children[0].name, "skeleton");
+        assert_eq!(e.children[0].text, Some("#root".to_string()));
+    }
+}
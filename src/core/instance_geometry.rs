@@ -0,0 +1,118 @@
+use xmltree::{Element};
+use context::{ParseContext};
+use core::{Extra, UnknownContent};
+use error::{ColladaError};
+use fx::{BindMaterial};
+use traits::{XmlConversion};
+use utils;
+
+/// Instantiates a geometry, binding materials to its primitives
+#[derive(Debug)]
+pub struct InstanceGeometry {
+    pub sid: Option<String>,
+    pub name: Option<String>,
+
+    /// Url of the geometry to instantiate
+    pub url: String,
+    pub bind_material: Option<BindMaterial>,
+    pub extras: Vec<Extra>,
+    pub unknown: UnknownContent,
+}
+
+impl InstanceGeometry {
+    pub fn new() -> InstanceGeometry {
+        InstanceGeometry {
+            sid: None,
+            name: None,
+            url: String::from(""),
+            bind_material: None,
+            extras: Vec::new(),
+            unknown: UnknownContent::new(),
+        }
+    }
+}
+
+impl XmlConversion for InstanceGeometry {
+    fn parse_with(&mut self, e: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        ctx.unknown_attributes(e, &["sid", "name", "url"], &mut self.unknown);
+        self.sid = utils::get_attr(e, "sid");
+        self.name = utils::get_attr(e, "name");
+        self.url = try!(utils::require_attr(e, "url"));
+
+        for c in &e.children {
+            match c.name.as_str() {
+                "bind_material" => {
+                    let mut b = BindMaterial::new();
+                    try!(ctx.parse(&mut b, c));
+                    self.bind_material = Some(b);
+                },
+                "extra" => {
+                    let mut x = Extra::new();
+                    try!(ctx.parse(&mut x, c));
+                    self.extras.push(x);
+                },
+                _ => try!(ctx.unknown_child(e, c, &mut self.unknown)),
+            }
+        }
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut i = Element::new("instance_geometry");
+        utils::set_attr(&mut i, "sid", &self.sid);
+        utils::set_attr(&mut i, "name", &self.name);
+        i.attributes.insert("url".to_string(), self.url.clone());
+
+        match self.bind_material {
+            Some(ref b) => i.children.push(b.encode()),
+            None => {},
+        }
+        for x in &self.extras {
+            i.children.push(x.encode());
+        }
+        self.unknown.encode_into(&mut i);
+        i
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use xmltree::{Element};
+    use traits::{XmlConversion};
+
+    #[test]
+    fn test_instance_geometry_parse() {
+        let data = r##"
+            <instance_geometry url="#cube" sid="geo">
+                <bind_material>
+                    <technique_common>
+                        <instance_material symbol="WHITE" target="#white-mat"/>
+                    </technique_common>
+                </bind_material>
+            </instance_geometry>"##;
+        let e = Element::parse(data.as_bytes()).unwrap();
+        let mut i = InstanceGeometry::new();
+        match i.parse(&e) {
+            Ok(_) => assert!(true),
+            Err(_) => assert!(false),
+        }
+        assert_eq!(i.url.as_str(), "#cube");
+        assert_eq!(i.sid, Some("geo".to_string()));
+        let b = i.bind_material.unwrap();
+        assert_eq!(b.instance_materials.len(), 1);
+        assert_eq!(b.instance_materials[0].symbol.as_str(), "WHITE");
+    }
+
+    #[test]
+    fn test_instance_geometry_encode() {
+        let mut i = InstanceGeometry::new();
+        i.url = "#cube".to_string();
+        i.bind_material = Some(BindMaterial::new());
+        let e = i.encode();
+        assert_eq!(e.name, "instance_geometry");
+        assert_eq!(e.attributes.get("url"), Some(&"#cube".to_string()));
+        assert_eq!(e.children.len(), 1);
+        assert_eq!(e.children[0].name, "bind_material");
+    }
+}
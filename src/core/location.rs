@@ -1,14 +1,18 @@
 use std::fmt;
 use std::collections::{HashMap};
+use std::str::{FromStr};
 use std::string::{String};
 use xmltree::{Element};
+use context::{ParseContext};
+use core::{UpAxis};
 use error::{ColladaError};
 use traits::{XmlConversion};
+use utils;
 
 
 /// Specifies wether the altitude is distance from sea level or distance 
 /// relative to terrain height at that latitude+longitude 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub enum AltitudeMode {
     /// Altitude value should be interpreted as distance in meters relative to 
     /// ground level
@@ -28,11 +32,26 @@ impl fmt::Display for AltitudeMode {
     }
 }
 
+/// Semi-major axis of the WGS84 ellipsoid in meters
+const WGS84_A: f64 = 6378137.0;
+
+/// Flattening of the WGS84 ellipsoid
+const WGS84_F: f64 = 1.0 / 298.257223563;
+
+/// A 4x4 matrix in row-major order with double precision, for positions on
+/// a planetary scale
+pub type Matrix64 = [f64; 16];
+
 /// Geographic location for an asset
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct Location {
+    /// Degrees east of the prime meridian
     pub longitude: f32,
+
+    /// Degrees north of the equator
     pub latitude: f32,
+
+    /// Meters above sea level or the ground, see `mode`
     pub altitude: f32,
     pub mode: AltitudeMode,
 }
@@ -47,10 +66,195 @@ impl Location {
             mode: AltitudeMode::RelativeToGround
         }
     }
+
+    /// Check that the latitude is within ±90 and the longitude within ±180
+    pub fn validate(&self) -> Result<(), ColladaError> {
+        check_range(self.longitude, self.latitude)
+    }
+
+    /// The location with double precision, see `GeoLocation`
+    pub fn to_f64(&self) -> GeoLocation {
+        GeoLocation {
+            longitude: self.longitude as f64,
+            latitude: self.latitude as f64,
+            altitude: self.altitude as f64,
+            mode: self.mode,
+        }
+    }
+
+    /// Earth-centered, earth-fixed coordinates in meters, see
+    /// `GeoLocation::to_ecef`
+    pub fn to_ecef(&self) -> [f64; 3] {
+        self.to_f64().to_ecef()
+    }
+
+    /// The local east-north-up frame at the location, see
+    /// `GeoLocation::enu_to_ecef`
+    pub fn enu_to_ecef(&self) -> Matrix64 {
+        self.to_f64().enu_to_ecef()
+    }
+
+    /// Place a georeferenced model at the location, see
+    /// `GeoLocation::georeference`
+    pub fn georeference(&self, meter: f64, up: UpAxis) -> Matrix64 {
+        self.to_f64().georeference(meter, up)
+    }
 }
 
-impl XmlConversion for Location {
-    fn parse(&mut self, e: &Element) -> Result<(), ColladaError> {
+/// A geographic location with double precision. `f32` degrees can't tell
+/// centimetres apart at planetary scale, so an `Asset` keeps its parsed
+/// `<coverage>` as a `GeoLocation` too, see `Asset::geo_location`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct GeoLocation {
+    /// Degrees east of the prime meridian
+    pub longitude: f64,
+
+    /// Degrees north of the equator
+    pub latitude: f64,
+
+    /// Meters above sea level or the ground, see `mode`
+    pub altitude: f64,
+    pub mode: AltitudeMode,
+}
+
+impl GeoLocation {
+    pub fn new() -> GeoLocation {
+        GeoLocation {
+            longitude: 0.0,
+            latitude: 0.0,
+            altitude: 0.0,
+            mode: AltitudeMode::RelativeToGround
+        }
+    }
+
+    /// The location with single precision, as kept by an `Asset`
+    pub fn to_f32(&self) -> Location {
+        Location {
+            longitude: self.longitude as f32,
+            latitude: self.latitude as f32,
+            altitude: self.altitude as f32,
+            mode: self.mode,
+        }
+    }
+
+    /// Check that the latitude is within ±90 and the longitude within ±180
+    pub fn validate(&self) -> Result<(), ColladaError> {
+        check_range(self.longitude, self.latitude)
+    }
+
+    /// Earth-centered, earth-fixed coordinates in meters on the WGS84
+    /// ellipsoid. The altitude is taken as the height above the ellipsoid,
+    /// whatever its mode, as neither the geoid nor the terrain is known.
+    pub fn to_ecef(&self) -> [f64; 3] {
+        let (sin_lat, cos_lat) = self.latitude.to_radians().sin_cos();
+        let (sin_lon, cos_lon) = self.longitude.to_radians().sin_cos();
+        let e2 = WGS84_F * (2.0 - WGS84_F);
+        // Radius of curvature in the prime vertical
+        let n = WGS84_A / (1.0 - e2 * sin_lat * sin_lat).sqrt();
+        [(n + self.altitude) * cos_lat * cos_lon,
+         (n + self.altitude) * cos_lat * sin_lon,
+         (n * (1.0 - e2) + self.altitude) * sin_lat]
+    }
+
+    /// The location of earth-centered, earth-fixed coordinates in meters,
+    /// with an altitude above the WGS84 ellipsoid
+    pub fn from_ecef(p: [f64; 3]) -> GeoLocation {
+        let e2 = WGS84_F * (2.0 - WGS84_F);
+        let r = (p[0] * p[0] + p[1] * p[1]).sqrt();
+        let mut l = GeoLocation::new();
+        l.mode = AltitudeMode::Absolute;
+        l.longitude = p[1].atan2(p[0]).to_degrees();
+
+        // Converges to well below a millimetre in a few iterations
+        let mut lat = p[2].atan2(r * (1.0 - e2));
+        let mut n = WGS84_A;
+        for _ in 0..8 {
+            n = WGS84_A / (1.0 - e2 * lat.sin() * lat.sin()).sqrt();
+            lat = (p[2] + e2 * n * lat.sin()).atan2(r);
+        }
+        l.latitude = lat.to_degrees();
+        l.altitude = if lat.cos().abs() > 1e-10 {
+            r / lat.cos() - n
+        } else {
+            p[2].abs() - n * (1.0 - e2)
+        };
+        l
+    }
+
+    /// The local east-north-up frame at the location, mapping meters east,
+    /// north and up of it to earth-centered, earth-fixed coordinates
+    pub fn enu_to_ecef(&self) -> Matrix64 {
+        let (sin_lat, cos_lat) = self.latitude.to_radians().sin_cos();
+        let (sin_lon, cos_lon) = self.longitude.to_radians().sin_cos();
+        let o = self.to_ecef();
+        [-sin_lon, -sin_lat * cos_lon, cos_lat * cos_lon, o[0],
+         cos_lon,  -sin_lat * sin_lon, cos_lat * sin_lon, o[1],
+         0.0,      cos_lat,            sin_lat,           o[2],
+         0.0,      0.0,                0.0,               1.0]
+    }
+
+    /// The inverse of `enu_to_ecef`
+    pub fn ecef_to_enu(&self) -> Matrix64 {
+        let m = self.enu_to_ecef();
+        let mut inv = [0.0; 16];
+        for r in 0..3 {
+            for c in 0..3 {
+                inv[r * 4 + c] = m[c * 4 + r];
+            }
+            inv[r * 4 + 3] = -(0..3).map(|k| m[k * 4 + r] * m[k * 4 + 3]).sum::<f64>();
+        }
+        inv[15] = 1.0;
+        inv
+    }
+
+    /// Place a georeferenced model at the location, mapping coordinates of a
+    /// unit of `meter` meters and an up axis to earth-centered, earth-fixed
+    /// coordinates. The right axis of the model points east, its in axis
+    /// south and its up axis up.
+    pub fn georeference(&self, meter: f64, up: UpAxis) -> Matrix64 {
+        let enu = self.enu_to_ecef();
+        let axes = up.axes();
+        // Columns of the model axes in east-north-up coordinates
+        let mut model = [0.0; 16];
+        for c in 0..3 {
+            let v = [axes[0][c], -axes[2][c], axes[1][c]];
+            for r in 0..3 {
+                model[r * 4 + c] = v[r] as f64 * meter;
+            }
+        }
+        model[15] = 1.0;
+
+        let mut m = [0.0; 16];
+        for r in 0..4 {
+            for c in 0..4 {
+                m[r * 4 + c] = (0..4).map(|k| enu[r * 4 + k] * model[k * 4 + c]).sum();
+            }
+        }
+        m
+    }
+}
+
+/// Check that a latitude is within ±90 and a longitude within ±180
+fn check_range<T: Into<f64> + ToString + Copy>(longitude: T, latitude: T) -> Result<(), ColladaError> {
+    if !(longitude.into() >= -180.0 && longitude.into() <= 180.0) {
+        return Err(ColladaError::InvalidData{
+            elem: "longitude".to_string(),
+            data: longitude.to_string(),
+        });
+    }
+    if !(latitude.into() >= -90.0 && latitude.into() <= 90.0) {
+        return Err(ColladaError::InvalidData{
+            elem: "latitude".to_string(),
+            data: latitude.to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// The longitude, latitude, altitude and altitude mode of a `<coverage>`
+fn parse_coverage<T: FromStr + Default>(e: &Element, ctx: &mut ParseContext)
+    -> Result<(T, T, T, AltitudeMode), ColladaError>
+{
         if e.name != "coverage".to_owned() {
             return Err(ColladaError::MissingElement {
                 structure: "location".to_string(),
@@ -58,7 +262,7 @@ impl XmlConversion for Location {
             });
         }
         
-        if e.children.len() != 1 && 
+        if e.children.len() != 1 ||
             e.children[0].name != "geographic_location".to_owned() {
                 return Err(ColladaError::MissingElement{
                     structure: "location".to_string(),
@@ -74,86 +278,113 @@ impl XmlConversion for Location {
             });
         }
          
+        let mut longitude = T::default();
+        let mut latitude = T::default();
+        let mut altitude = T::default();
+        let mut mode = AltitudeMode::RelativeToGround;
         for c in &geo.children {
-            let text = match c.text.clone() {
-                Some(t) => t,
-                None => return Err(ColladaError::MissingData{
-                            elem: c.name.clone()
-                        }),
-            };
-
             match c.name.as_str() {
                 "longitude" => { 
-                    self.longitude = text.parse::<f32>().unwrap();
+                    longitude = try!(ctx.locate(c, utils::parse_text(c)));
                 },
                 "latitude" => {
-                    self.latitude = text.parse::<f32>().unwrap();
+                    latitude = try!(ctx.locate(c, utils::parse_text(c)));
                 },
                 "altitude" => {
-                    self.altitude = text.parse::<f32>().unwrap();
-                    self.mode = match c.attributes.get("mode") {
+                    altitude = try!(ctx.locate(c, utils::parse_text(c)));
+                    mode = match c.attributes.get("mode") {
                         Some(m) => {
                             match m.as_str() {
                                 "absolute" => AltitudeMode::Absolute,
                                 "relativeToGround" => AltitudeMode::RelativeToGround,
-                                _ => return Err(ColladaError::InvalidAttrData{
+                                _ => return ctx.locate(c, Err(ColladaError::InvalidAttrData{
                                         elem: "altitude".to_string(),
                                         attr: "mode".to_string(),
                                         data: m.clone(),
-                                    }),
+                                    })),
                             }
                         },
-                        None => return Err(ColladaError::MissingAttr{
+                        None => return ctx.locate(c, Err(ColladaError::MissingAttr{
                             elem: "altitude".to_string(),
                             attr: "mode".to_string(),
-                        }),
+                        })),
                     }
                 }
-                _ => return Err(ColladaError::InvalidChild{
+                _ => return ctx.locate(c, Err(ColladaError::InvalidChild{
                         child: c.name.clone(),
                         parent: "geographic_location".to_string(),
-                    }),
+                    })),
             }
         }
+        Ok((longitude, latitude, altitude, mode))
+}
+
+fn encode_coverage(longitude: String, latitude: String, altitude: String, mode: AltitudeMode) -> Element {
+    let long = Element {
+        name: String::from("longitude"),
+        attributes: HashMap::new(),
+        children: Vec::new(),
+        text: Some(longitude),
+    };
+
+    let lat = Element {
+        name: String::from("latitude"),
+        attributes: HashMap::new(),
+        children: Vec::new(),
+        text: Some(latitude),
+    };
+    
+    let mut alt = Element {
+        name: String::from("altitude"),
+        attributes: HashMap::new(),
+        children: Vec::new(),
+        text: Some(altitude),
+    };
+    alt.attributes.insert(String::from("mode"), mode.to_string());
+    
+    let geo = Element {
+        name: String::from("geographic_location"),
+        attributes: HashMap::new(),
+        children: vec![long, lat, alt],
+        text: None,
+    };
+
+    Element {
+        name: String::from("coverage"),
+        attributes: HashMap::new(),
+        children: vec![geo],
+        text: None,
+    }
+}
+
+impl XmlConversion for Location {
+    fn parse_with(&mut self, e: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        let (longitude, latitude, altitude, mode) = try!(parse_coverage(e, ctx));
+        self.longitude = longitude;
+        self.latitude = latitude;
+        self.altitude = altitude;
+        self.mode = mode;
         Ok(())
     }
 
     fn encode(&self) -> Element {
-        let long = Element {
-            name: String::from("longitude"),
-            attributes: HashMap::new(),
-            children: Vec::new(),
-            text: Some(self.longitude.to_string()),
-        };
+        encode_coverage(self.longitude.to_string(), self.latitude.to_string(),
+                        (self.altitude as i32).to_string(), self.mode)
+    }
+}
 
-        let lat = Element {
-            name: String::from("latitude"),
-            attributes: HashMap::new(),
-            children: Vec::new(),
-            text: Some(self.latitude.to_string()),
-        };
-        
-        let mut alt = Element {
-            name: String::from("altitude"),
-            attributes: HashMap::new(),
-            children: Vec::new(),
-            text: Some((self.altitude as i32).to_string()),
-        };
-        alt.attributes.insert(String::from("mode"), self.mode.to_string());
-        
-        let geo = Element {
-            name: String::from("geographic_location"),
-            attributes: HashMap::new(),
-            children: vec![long, lat, alt],
-            text: None,
-        };
+impl XmlConversion for GeoLocation {
+    fn parse_with(&mut self, e: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        let (longitude, latitude, altitude, mode) = try!(parse_coverage(e, ctx));
+        self.longitude = longitude;
+        self.latitude = latitude;
+        self.altitude = altitude;
+        self.mode = mode;
+        Ok(())
+    }
 
-        Element {
-            name: String::from("coverage"),
-            attributes: HashMap::new(),
-            children: vec![geo],
-            text: None,
-        }
+    fn encode(&self) -> Element {
+        encode_coverage(self.longitude.to_string(), self.latitude.to_string(), self.altitude.to_string(), self.mode)
     }
 }
 
@@ -161,6 +392,8 @@ impl XmlConversion for Location {
 mod tests {
     use super::*;
     use xmltree::{Element};
+    use core::{UpAxis};
+    use error::{ColladaError};
     use traits::{XmlConversion};
    
     #[test]
@@ -189,6 +422,122 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_location_parse_invalid() {
+        let data = r#"
+        <coverage>
+            <geographic_location>
+                <longitude>1</longitude>
+                <latitude>1e999x</latitude>
+                <altitude mode="absolute">0</altitude>
+            </geographic_location>
+        </coverage>"#;
+        let e = Element::parse(data.as_bytes()).unwrap();
+        match *Location::new().parse(&e).unwrap_err().inner() {
+            ColladaError::InvalidData{ref elem, ref data} => {
+                assert_eq!(elem.as_str(), "latitude");
+                assert_eq!(data.as_str(), "1e999x");
+            },
+            _ => assert!(false),
+        }
+
+        let e = Element::parse(r#"<coverage/>"#.as_bytes()).unwrap();
+        assert!(Location::new().parse(&e).is_err());
+        let e = Element::parse(r#"<coverage><geographic_location/></coverage>"#.as_bytes()).unwrap();
+        assert!(Location::new().parse(&e).is_err());
+        let e = Element::parse(r#"<coverage><geographic_location>
+            <longitude/><latitude>0</latitude><altitude mode="absolute">0</altitude>
+        </geographic_location></coverage>"#.as_bytes()).unwrap();
+        assert!(Location::new().parse(&e).is_err());
+    }
+
+    #[test]
+    fn test_location_validate() {
+        let mut l = Location::new();
+        assert!(l.validate().is_ok());
+        l.latitude = -123.0;
+        match l.validate().unwrap_err() {
+            ColladaError::InvalidData{ref elem, ref data} => {
+                assert_eq!(elem.as_str(), "latitude");
+                assert_eq!(data.as_str(), "-123");
+            },
+            _ => assert!(false),
+        }
+        l.latitude = 0.0;
+        l.longitude = 180.5;
+        assert!(l.validate().is_err());
+        assert!(l.to_f64().validate().is_err());
+    }
+
+    #[test]
+    fn test_location_ecef() {
+        let mut l = GeoLocation::new();
+        let p = l.to_ecef();
+        assert!((p[0] - 6378137.0).abs() < 1e-6 && p[1].abs() < 1e-6 && p[2].abs() < 1e-6);
+
+        // Boulder, CO
+        l.longitude = -105.2705;
+        l.latitude = 40.015;
+        l.altitude = 1655.0;
+        let p = l.to_ecef();
+        assert!((p[0] - -1288675.50).abs() < 0.01, "{:?}", p);
+        assert!((p[1] - -4720151.35).abs() < 0.01, "{:?}", p);
+        assert!((p[2] - 4080325.44).abs() < 0.01, "{:?}", p);
+
+        let back = GeoLocation::from_ecef(p);
+        assert!((back.longitude - l.longitude).abs() < 1e-9);
+        assert!((back.latitude - l.latitude).abs() < 1e-9);
+        assert!((back.altitude - l.altitude).abs() < 1e-4);
+
+        // The frame's origin is the location and its up axis is the normal
+        let m = l.enu_to_ecef();
+        assert_eq!([m[3], m[7], m[11]], p);
+        let inv = l.ecef_to_enu();
+        let up = [p[0] + 10.0 * m[2], p[1] + 10.0 * m[6], p[2] + 10.0 * m[10]];
+        let local: Vec<f64> = (0..3).map(|r| (0..3).map(|k| inv[r * 4 + k] * up[k]).sum::<f64>() + inv[r * 4 + 3]).collect();
+        assert!(local[0].abs() < 1e-6 && local[1].abs() < 1e-6 && (local[2] - 10.0).abs() < 1e-6);
+        assert!((GeoLocation::from_ecef(up).altitude - 1665.0).abs() < 1e-4);
+
+        // A Z up model in centimeters has its Y axis pointing north
+        let g = l.georeference(0.01, UpAxis::ZUP);
+        let north = [m[1], m[5], m[9]];
+        for r in 0..3 {
+            assert!((g[r * 4 + 1] - 0.01 * north[r]).abs() < 1e-12);
+            assert_eq!(g[r * 4 + 3], p[r]);
+        }
+        let g = l.georeference(1.0, UpAxis::YUP);
+        for r in 0..3 {
+            assert!((g[r * 4 + 2] + north[r]).abs() < 1e-12);
+        }
+
+        // Single precision locations take the same path
+        let f = l.to_f32();
+        let q = f.to_ecef();
+        assert!((q[0] - p[0]).abs() < 1.0 && (q[1] - p[1]).abs() < 1.0 && (q[2] - p[2]).abs() < 1.0);
+        assert_eq!(f.georeference(1.0, UpAxis::YUP), f.to_f64().georeference(1.0, UpAxis::YUP));
+    }
+
+    #[test]
+    fn test_geo_location_parse() {
+        let data = r#"
+        <coverage>
+            <geographic_location>
+                <longitude>-105.27050001</longitude>
+                <latitude>40.01500001</latitude>
+                <altitude mode="absolute">1655.25</altitude>
+            </geographic_location>
+        </coverage>"#;
+        let e = Element::parse(data.as_bytes()).unwrap();
+        let mut g = GeoLocation::new();
+        g.parse(&e).unwrap();
+        assert_eq!(g.longitude, -105.27050001);
+        assert_eq!(g.latitude, 40.01500001);
+        assert_eq!(g.altitude, 1655.25);
+        let a = &g.encode().children[0].children[2];
+        assert_eq!(a.text, Some("1655.25".to_string()));
+        assert_eq!(a.attributes.get("mode"), Some(&"absolute".to_string()));
+    }
+
     #[test]
     fn test_location_encode() {
         let mut l = Location::new();
@@ -0,0 +1,539 @@
+use xmltree::{Element};
+use error::{ColladaError};
+use utils;
+
+/// MathML operators of an `<apply>`
+#[derive(Clone, Debug, PartialEq)]
+pub enum MathOp {
+    Plus,
+    Minus,
+    Times,
+    Divide,
+    Power,
+    Root,
+    Abs,
+    Exp,
+    Ln,
+    Log,
+    Floor,
+    Ceiling,
+    Min,
+    Max,
+    Rem,
+    Quotient,
+    Factorial,
+    Sin,
+    Cos,
+    Tan,
+    Sec,
+    Csc,
+    Cot,
+    Arcsin,
+    Arccos,
+    Arctan,
+    Sinh,
+    Cosh,
+    Tanh,
+    Eq,
+    Neq,
+    Gt,
+    Lt,
+    Geq,
+    Leq,
+    And,
+    Or,
+    Xor,
+    Not,
+
+    /// Any other operator, kept by name. Evaluating it is an error.
+    Other(String),
+}
+
+const OPS: &'static [(&'static str, MathOp)] = &[
+    ("plus", MathOp::Plus), ("minus", MathOp::Minus), ("times", MathOp::Times),
+    ("divide", MathOp::Divide), ("power", MathOp::Power), ("root", MathOp::Root),
+    ("abs", MathOp::Abs), ("exp", MathOp::Exp), ("ln", MathOp::Ln), ("log", MathOp::Log),
+    ("floor", MathOp::Floor), ("ceiling", MathOp::Ceiling), ("min", MathOp::Min),
+    ("max", MathOp::Max), ("rem", MathOp::Rem), ("quotient", MathOp::Quotient),
+    ("factorial", MathOp::Factorial), ("sin", MathOp::Sin), ("cos", MathOp::Cos),
+    ("tan", MathOp::Tan), ("sec", MathOp::Sec), ("csc", MathOp::Csc), ("cot", MathOp::Cot),
+    ("arcsin", MathOp::Arcsin), ("arccos", MathOp::Arccos), ("arctan", MathOp::Arctan),
+    ("sinh", MathOp::Sinh), ("cosh", MathOp::Cosh), ("tanh", MathOp::Tanh),
+    ("eq", MathOp::Eq), ("neq", MathOp::Neq), ("gt", MathOp::Gt), ("lt", MathOp::Lt),
+    ("geq", MathOp::Geq), ("leq", MathOp::Leq), ("and", MathOp::And), ("or", MathOp::Or),
+    ("xor", MathOp::Xor), ("not", MathOp::Not),
+];
+
+impl MathOp {
+    pub fn from_name(name: &str) -> MathOp {
+        match OPS.iter().find(|&&(n, _)| n == name) {
+            Some(&(_, ref op)) => op.clone(),
+            None => MathOp::Other(name.to_string()),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match *self {
+            MathOp::Other(ref n) => n,
+            ref op => OPS.iter().find(|&&(_, ref o)| o == op).map(|&(n, _)| n).unwrap_or(""),
+        }
+    }
+}
+
+/// MathML constants
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum MathConstant {
+    Pi,
+    ExponentialE,
+    True,
+    False,
+    Infinity,
+    NotANumber,
+}
+
+impl MathConstant {
+    pub fn from_name(name: &str) -> Option<MathConstant> {
+        match name {
+            "pi" => Some(MathConstant::Pi),
+            "exponentiale" => Some(MathConstant::ExponentialE),
+            "true" => Some(MathConstant::True),
+            "false" => Some(MathConstant::False),
+            "infinity" => Some(MathConstant::Infinity),
+            "notanumber" => Some(MathConstant::NotANumber),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            MathConstant::Pi => "pi",
+            MathConstant::ExponentialE => "exponentiale",
+            MathConstant::True => "true",
+            MathConstant::False => "false",
+            MathConstant::Infinity => "infinity",
+            MathConstant::NotANumber => "notanumber",
+        }
+    }
+
+    pub fn value(&self) -> f32 {
+        match *self {
+            MathConstant::Pi => ::std::f32::consts::PI,
+            MathConstant::ExponentialE => ::std::f32::consts::E,
+            MathConstant::True => 1.0,
+            MathConstant::False => 0.0,
+            MathConstant::Infinity => ::std::f32::INFINITY,
+            MathConstant::NotANumber => ::std::f32::NAN,
+        }
+    }
+}
+
+/// A MathML content expression. Booleans evaluate to 1 and 0, and
+/// trigonometric functions use radians as in MathML.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MathExpr {
+    /// `<cn>`
+    Number(f32),
+
+    /// `<ci>`, or a `<csymbol>` such as a joint's sid reference
+    Identifier(String),
+    Constant(MathConstant),
+    Apply {
+        op: MathOp,
+        args: Vec<MathExpr>,
+
+        /// `<degree>` of a `<root>`
+        degree: Option<Box<MathExpr>>,
+
+        /// `<logbase>` of a `<log>`
+        logbase: Option<Box<MathExpr>>,
+    },
+
+    /// Pairs of value and condition, and the value otherwise
+    Piecewise {
+        pieces: Vec<(MathExpr, MathExpr)>,
+        otherwise: Option<Box<MathExpr>>,
+    },
+}
+
+impl MathExpr {
+    /// Parse the expression of a `<math>` element
+    pub fn parse_math(e: &Element) -> Result<MathExpr, ColladaError> {
+        match e.children.first() {
+            Some(c) => MathExpr::parse(c),
+            None => Err(ColladaError::MissingElement{
+                structure: "math".to_string(),
+                elem: "apply".to_string(),
+            }),
+        }
+    }
+
+    /// Parse a MathML content element, e.g. `<apply>` or `<ci>`
+    pub fn parse(e: &Element) -> Result<MathExpr, ColladaError> {
+        if let Some(c) = MathConstant::from_name(&e.name) {
+            return Ok(MathExpr::Constant(c));
+        }
+
+        Ok(match e.name.as_str() {
+            "cn" => MathExpr::Number(try!(utils::parse_text(e))),
+            "ci" | "csymbol" => MathExpr::Identifier(try!(utils::require_text(e)).trim().to_string()),
+            "apply" => {
+                let op = match e.children.first() {
+                    Some(o) if o.name == "csymbol" => {
+                        MathOp::Other(try!(utils::require_text(o)).trim().to_string())
+                    },
+                    Some(o) => MathOp::from_name(&o.name),
+                    None => return Err(ColladaError::MissingElement{
+                        structure: "apply".to_string(),
+                        elem: "plus".to_string(),
+                    }),
+                };
+                let (mut args, mut degree, mut logbase) = (Vec::new(), None, None);
+                for c in e.children.iter().skip(1) {
+                    match c.name.as_str() {
+                        "degree" => degree = Some(Box::new(try!(MathExpr::parse_math(c)))),
+                        "logbase" => logbase = Some(Box::new(try!(MathExpr::parse_math(c)))),
+                        _ => args.push(try!(MathExpr::parse(c))),
+                    }
+                }
+                MathExpr::Apply{op: op, args: args, degree: degree, logbase: logbase}
+            },
+            "piecewise" => {
+                let (mut pieces, mut otherwise) = (Vec::new(), None);
+                for c in &e.children {
+                    match c.name.as_str() {
+                        "piece" if c.children.len() == 2 => pieces.push((
+                            try!(MathExpr::parse(&c.children[0])),
+                            try!(MathExpr::parse(&c.children[1])),
+                        )),
+                        "otherwise" => otherwise = Some(Box::new(try!(MathExpr::parse_math(c)))),
+                        _ => return Err(ColladaError::InvalidChild{
+                            child: c.name.clone(),
+                            parent: "piecewise".to_string(),
+                        }),
+                    }
+                }
+                MathExpr::Piecewise{pieces: pieces, otherwise: otherwise}
+            },
+            _ => return Err(ColladaError::InvalidChild{
+                child: e.name.clone(),
+                parent: "math".to_string(),
+            }),
+        })
+    }
+
+    /// Encode the expression wrapped in a `<math>` element
+    pub fn encode_math(&self) -> Element {
+        let mut m = Element::new("math");
+        m.attributes.insert("xmlns".to_string(), "http://www.w3.org/1998/Math/MathML".to_string());
+        m.children.push(self.encode());
+        m
+    }
+
+    pub fn encode(&self) -> Element {
+        match *self {
+            MathExpr::Number(x) => utils::text_element("cn", x.to_string()),
+            MathExpr::Identifier(ref x) => {
+                let name = if x.contains('/') { "csymbol" } else { "ci" };
+                utils::text_element(name, x.clone())
+            },
+            MathExpr::Constant(c) => Element::new(c.name()),
+            MathExpr::Apply{ref op, ref args, ref degree, ref logbase} => {
+                let mut a = Element::new("apply");
+                a.children.push(match *op {
+                    MathOp::Other(ref n) => utils::text_element("csymbol", n.clone()),
+                    _ => Element::new(op.name()),
+                });
+                if let Some(ref d) = *degree {
+                    let mut x = Element::new("degree");
+                    x.children.push(d.encode());
+                    a.children.push(x);
+                }
+                if let Some(ref b) = *logbase {
+                    let mut x = Element::new("logbase");
+                    x.children.push(b.encode());
+                    a.children.push(x);
+                }
+                for x in args {
+                    a.children.push(x.encode());
+                }
+                a
+            },
+            MathExpr::Piecewise{ref pieces, ref otherwise} => {
+                let mut p = Element::new("piecewise");
+                for &(ref value, ref condition) in pieces {
+                    let mut x = Element::new("piece");
+                    x.children.push(value.encode());
+                    x.children.push(condition.encode());
+                    p.children.push(x);
+                }
+                if let Some(ref o) = *otherwise {
+                    let mut x = Element::new("otherwise");
+                    x.children.push(o.encode());
+                    p.children.push(x);
+                }
+                p
+            },
+        }
+    }
+
+    /// Evaluate the expression, resolving identifiers with `lookup`
+    pub fn evaluate<F>(&self, lookup: &F) -> Result<f32, ColladaError>
+        where F: Fn(&str) -> Option<f32>
+    {
+        match *self {
+            MathExpr::Number(x) => Ok(x),
+            MathExpr::Identifier(ref x) => match lookup(x) {
+                Some(v) => Ok(v),
+                None => Err(ColladaError::Invalid{
+                    msg: format!("Unbound identifier '{}' in formula", x),
+                }),
+            },
+            MathExpr::Constant(c) => Ok(c.value()),
+            MathExpr::Apply{ref op, ref args, ref degree, ref logbase} => {
+                let mut values = Vec::new();
+                for a in args {
+                    values.push(try!(a.evaluate(lookup)));
+                }
+                let degree = match *degree {
+                    Some(ref d) => try!(d.evaluate(lookup)),
+                    None => 2.0,
+                };
+                let logbase = match *logbase {
+                    Some(ref b) => try!(b.evaluate(lookup)),
+                    None => 10.0,
+                };
+                apply(op, &values, degree, logbase)
+            },
+            MathExpr::Piecewise{ref pieces, ref otherwise} => {
+                for &(ref value, ref condition) in pieces {
+                    if try!(condition.evaluate(lookup)) != 0.0 {
+                        return value.evaluate(lookup);
+                    }
+                }
+                match *otherwise {
+                    Some(ref o) => o.evaluate(lookup),
+                    None => Err(ColladaError::Invalid{
+                        msg: "No piece of piecewise expression applies".to_string(),
+                    }),
+                }
+            },
+        }
+    }
+}
+
+fn truth(b: bool) -> f32 {
+    if b { 1.0 } else { 0.0 }
+}
+
+/// Whether a relation holds between each pair of consecutive values
+fn chain<F: Fn(f32, f32) -> bool>(v: &[f32], f: F) -> bool {
+    v.windows(2).all(|w| f(w[0], w[1]))
+}
+
+fn apply(op: &MathOp, v: &[f32], degree: f32, logbase: f32) -> Result<f32, ColladaError> {
+    let arity = |n: usize| -> Result<(), ColladaError> {
+        if v.len() == n {
+            Ok(())
+        } else {
+            Err(ColladaError::Invalid{
+                msg: format!("<{}> expects {} argument(s) but has {}", op.name(), n, v.len()),
+            })
+        }
+    };
+
+    Ok(match *op {
+        MathOp::Plus => v.iter().fold(0.0, |a, b| a + b),
+        MathOp::Times => v.iter().fold(1.0, |a, b| a * b),
+        MathOp::Minus => match v.len() {
+            1 => -v[0],
+            _ => { try!(arity(2)); v[0] - v[1] },
+        },
+        MathOp::Divide => { try!(arity(2)); v[0] / v[1] },
+        MathOp::Power => { try!(arity(2)); v[0].powf(v[1]) },
+        MathOp::Root => { try!(arity(1)); v[0].powf(1.0 / degree) },
+        MathOp::Log => { try!(arity(1)); v[0].log(logbase) },
+        MathOp::Rem => { try!(arity(2)); v[0] % v[1] },
+        MathOp::Quotient => { try!(arity(2)); (v[0] / v[1]).trunc() },
+        MathOp::Min | MathOp::Max if v.is_empty() => { try!(arity(1)); 0.0 },
+        MathOp::Min => v.iter().cloned().fold(::std::f32::INFINITY, f32::min),
+        MathOp::Max => v.iter().cloned().fold(::std::f32::NEG_INFINITY, f32::max),
+        MathOp::Eq => truth(chain(v, |a, b| a == b)),
+        MathOp::Neq => { try!(arity(2)); truth(v[0] != v[1]) },
+        MathOp::Gt => truth(chain(v, |a, b| a > b)),
+        MathOp::Lt => truth(chain(v, |a, b| a < b)),
+        MathOp::Geq => truth(chain(v, |a, b| a >= b)),
+        MathOp::Leq => truth(chain(v, |a, b| a <= b)),
+        MathOp::And => truth(v.iter().all(|x| *x != 0.0)),
+        MathOp::Or => truth(v.iter().any(|x| *x != 0.0)),
+        MathOp::Xor => truth(v.iter().filter(|x| **x != 0.0).count() % 2 == 1),
+        MathOp::Other(ref n) => return Err(ColladaError::Invalid{
+            msg: format!("Unsupported MathML operator '{}'", n),
+        }),
+        _ => {
+            try!(arity(1));
+            let x = v[0];
+            match *op {
+                MathOp::Abs => x.abs(),
+                MathOp::Exp => x.exp(),
+                MathOp::Ln => x.ln(),
+                MathOp::Floor => x.floor(),
+                MathOp::Ceiling => x.ceil(),
+                // 35! is already beyond the range of f32
+                MathOp::Factorial if x > 34.0 => ::std::f32::INFINITY,
+                MathOp::Factorial => (1..(x.max(0.0) as u32) + 1).fold(1.0, |a, b| a * b as f32),
+                MathOp::Sin => x.sin(),
+                MathOp::Cos => x.cos(),
+                MathOp::Tan => x.tan(),
+                MathOp::Sec => 1.0 / x.cos(),
+                MathOp::Csc => 1.0 / x.sin(),
+                MathOp::Cot => 1.0 / x.tan(),
+                MathOp::Arcsin => x.asin(),
+                MathOp::Arccos => x.acos(),
+                MathOp::Arctan => x.atan(),
+                MathOp::Sinh => x.sinh(),
+                MathOp::Cosh => x.cosh(),
+                MathOp::Tanh => x.tanh(),
+                _ => truth(x == 0.0),
+            }
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::{HashMap};
+    use xmltree::{Element};
+
+    fn eval(data: &str, bindings: &HashMap<String, f32>) -> Result<f32, ColladaError> {
+        let e = Element::parse(data.as_bytes()).unwrap();
+        let x = try!(MathExpr::parse_math(&e));
+        x.evaluate(&|s: &str| bindings.get(s).cloned())
+    }
+
+    fn approx(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-5
+    }
+
+    #[test]
+    fn test_math_parse() {
+        let data = r##"
+            <math xmlns="http://www.w3.org/1998/Math/MathML">
+                <apply>
+                    <plus/>
+                    <apply><times/><cn>2</cn><ci>x</ci></apply>
+                    <csymbol encoding="COLLADA">arm/j0</csymbol>
+                    <pi/>
+                </apply>
+            </math>"##;
+        let e = Element::parse(data.as_bytes()).unwrap();
+        let x = MathExpr::parse_math(&e).unwrap();
+        match x {
+            MathExpr::Apply{ref op, ref args, ..} => {
+                assert_eq!(*op, MathOp::Plus);
+                assert_eq!(args.len(), 3);
+                assert_eq!(args[1], MathExpr::Identifier("arm/j0".to_string()));
+                assert_eq!(args[2], MathExpr::Constant(MathConstant::Pi));
+            },
+            _ => assert!(false),
+        }
+
+        let e = Element::parse(r##"<math><apply/></math>"##.as_bytes()).unwrap();
+        assert!(MathExpr::parse_math(&e).is_err());
+        let e = Element::parse(r##"<math><cn>abc</cn></math>"##.as_bytes()).unwrap();
+        assert!(MathExpr::parse_math(&e).is_err());
+        let e = Element::parse(r##"<math><mrow/></math>"##.as_bytes()).unwrap();
+        assert!(MathExpr::parse_math(&e).is_err());
+    }
+
+    #[test]
+    fn test_math_evaluate() {
+        let mut b = HashMap::new();
+        b.insert("x".to_string(), 3.0);
+        b.insert("arm/j0".to_string(), 0.5);
+
+        let cases = [
+            (r##"<math><apply><plus/><ci>x</ci><cn>1</cn><cn>2</cn></apply></math>"##, 6.0),
+            (r##"<math><apply><minus/><ci>x</ci></apply></math>"##, -3.0),
+            (r##"<math><apply><minus/><cn>10</cn><ci>x</ci></apply></math>"##, 7.0),
+            (r##"<math><apply><times/><csymbol>arm/j0</csymbol><cn>4</cn></apply></math>"##, 2.0),
+            (r##"<math><apply><divide/><cn>1</cn><cn>4</cn></apply></math>"##, 0.25),
+            (r##"<math><apply><power/><ci>x</ci><cn>2</cn></apply></math>"##, 9.0),
+            (r##"<math><apply><root/><degree><cn>3</cn></degree><cn>27</cn></apply></math>"##, 3.0),
+            (r##"<math><apply><root/><cn>16</cn></apply></math>"##, 4.0),
+            (r##"<math><apply><log/><logbase><cn>2</cn></logbase><cn>8</cn></apply></math>"##, 3.0),
+            (r##"<math><apply><log/><cn>100</cn></apply></math>"##, 2.0),
+            (r##"<math><apply><abs/><cn>-2</cn></apply></math>"##, 2.0),
+            (r##"<math><apply><rem/><cn>7</cn><cn>3</cn></apply></math>"##, 1.0),
+            (r##"<math><apply><quotient/><cn>7</cn><cn>2</cn></apply></math>"##, 3.0),
+            (r##"<math><apply><factorial/><cn>4</cn></apply></math>"##, 24.0),
+            (r##"<math><apply><max/><cn>1</cn><ci>x</ci><cn>2</cn></apply></math>"##, 3.0),
+            (r##"<math><apply><min/><cn>1</cn><ci>x</ci><cn>2</cn></apply></math>"##, 1.0),
+            (r##"<math><apply><floor/><cn>1.5</cn></apply></math>"##, 1.0),
+            (r##"<math><apply><ceiling/><cn>1.5</cn></apply></math>"##, 2.0),
+            (r##"<math><apply><sin/><apply><divide/><pi/><cn>2</cn></apply></apply></math>"##, 1.0),
+            (r##"<math><apply><cos/><cn>0</cn></apply></math>"##, 1.0),
+            (r##"<math><apply><arctan/><cn>1</cn></apply></math>"##, ::std::f32::consts::PI / 4.0),
+            (r##"<math><apply><ln/><exponentiale/></apply></math>"##, 1.0),
+            (r##"<math><apply><gt/><ci>x</ci><cn>2</cn><cn>1</cn></apply></math>"##, 1.0),
+            (r##"<math><apply><lt/><ci>x</ci><cn>2</cn></apply></math>"##, 0.0),
+            (r##"<math><apply><eq/><ci>x</ci><cn>3</cn></apply></math>"##, 1.0),
+            (r##"<math><apply><neq/><ci>x</ci><cn>3</cn></apply></math>"##, 0.0),
+            (r##"<math><apply><geq/><ci>x</ci><cn>3</cn></apply></math>"##, 1.0),
+            (r##"<math><apply><leq/><ci>x</ci><cn>2</cn></apply></math>"##, 0.0),
+            (r##"<math><apply><and/><true/><apply><gt/><ci>x</ci><cn>0</cn></apply></apply></math>"##, 1.0),
+            (r##"<math><apply><or/><false/><false/></apply></math>"##, 0.0),
+            (r##"<math><apply><xor/><true/><true/></apply></math>"##, 0.0),
+            (r##"<math><apply><not/><false/></apply></math>"##, 1.0),
+            (r##"<math>
+                    <piecewise>
+                        <piece><cn>-1</cn><apply><lt/><ci>x</ci><cn>0</cn></apply></piece>
+                        <piece><cn>1</cn><apply><gt/><ci>x</ci><cn>0</cn></apply></piece>
+                        <otherwise><cn>0</cn></otherwise>
+                    </piecewise>
+                </math>"##, 1.0),
+        ];
+        for &(data, expected) in cases.iter() {
+            let v = eval(data, &b).unwrap();
+            assert!(approx(v, expected), "{} gave {} instead of {}", data, v, expected);
+        }
+    }
+
+    #[test]
+    fn test_math_evaluate_errors() {
+        let b = HashMap::new();
+        assert!(eval(r##"<math><ci>y</ci></math>"##, &b).is_err());
+        assert!(eval(r##"<math><apply><divide/><cn>1</cn></apply></math>"##, &b).is_err());
+        assert!(eval(r##"<math><apply><csymbol>atan2</csymbol><cn>1</cn><cn>1</cn></apply></math>"##, &b).is_err());
+        assert!(eval(r##"<math><apply><curl/><cn>1</cn></apply></math>"##, &b).is_err());
+        assert!(eval(r##"<math><piecewise><piece><cn>1</cn><false/></piece></piecewise></math>"##, &b).is_err());
+    }
+
+    #[test]
+    fn test_math_encode() {
+        let x = MathExpr::Apply {
+            op: MathOp::Root,
+            args: vec![MathExpr::Identifier("x".to_string())],
+            degree: Some(Box::new(MathExpr::Number(3.0))),
+            logbase: None,
+        };
+        let e = x.encode_math();
+        assert_eq!(e.name, "math");
+        let a = &e.children[0];
+        assert_eq!(a.name, "apply");
+        let names: Vec<&str> = a.children.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["root", "degree", "ci"]);
+        assert_eq!(MathExpr::parse_math(&e).unwrap(), x);
+
+        let e = MathExpr::Identifier("arm/j0".to_string()).encode();
+        assert_eq!(e.name, "csymbol");
+        let e = MathExpr::Apply {
+            op: MathOp::Other("atan2".to_string()),
+            args: Vec::new(),
+            degree: None,
+            logbase: None,
+        }.encode();
+        assert_eq!(e.children[0].name, "csymbol");
+        assert_eq!(e.children[0].text, Some("atan2".to_string()));
+    }
+}
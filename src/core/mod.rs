@@ -1,11 +1,29 @@
 mod asset;
 mod contributor;
+mod datetime;
 mod extra;
+mod formula;
+mod instance_controller;
+mod instance_geometry;
 mod location;
+mod math;
+mod param;
 mod technique;
+mod transform;
+mod unknown;
+mod vendor;
 
 pub use self::asset::*;
 pub use self::extra::*;
+pub use self::formula::*;
+pub use self::instance_controller::*;
+pub use self::instance_geometry::*;
 pub use self::contributor::*;
+pub use self::datetime::*;
 pub use self::location::*;
+pub use self::math::*;
+pub use self::param::*;
 pub use self::technique::*;
+pub use self::transform::*;
+pub use self::unknown::*;
+pub use self::vendor::*;
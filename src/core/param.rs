@@ -0,0 +1,82 @@
+use xmltree::{Element};
+use context::{ParseContext};
+use error::{ColladaError};
+use traits::{XmlConversion};
+use utils;
+
+/// Declares parametric information for its parent element, e.g. the
+/// components of an accessor or a value bound from a material
+#[derive(Debug)]
+pub struct Param {
+    pub name: Option<String>,
+    pub sid: Option<String>,
+    pub semantic: Option<String>,
+    pub typ: String,
+}
+
+impl Param {
+    pub fn new() -> Param {
+        Param {
+            name: None,
+            sid: None,
+            semantic: None,
+            typ: String::from(""),
+        }
+    }
+}
+
+impl XmlConversion for Param {
+    fn parse_with(&mut self, e: &Element, _ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        self.name = utils::get_attr(e, "name");
+        self.sid = utils::get_attr(e, "sid");
+        self.semantic = utils::get_attr(e, "semantic");
+        self.typ = try!(utils::require_attr(e, "type"));
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut p = Element::new("param");
+        utils::set_attr(&mut p, "name", &self.name);
+        utils::set_attr(&mut p, "sid", &self.sid);
+        utils::set_attr(&mut p, "semantic", &self.semantic);
+        p.attributes.insert("type".to_string(), self.typ.clone());
+        p
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use xmltree::{Element};
+    use traits::{XmlConversion};
+
+    #[test]
+    fn test_param_parse() {
+        let data = r#"<param name="X" sid="x" semantic="POSITION" type="float"/>"#;
+        let e = Element::parse(data.as_bytes()).unwrap();
+        let mut p = Param::new();
+        match p.parse(&e) {
+            Ok(_) => assert!(true),
+            Err(_) => assert!(false),
+        }
+        assert_eq!(p.name, Some("X".to_string()));
+        assert_eq!(p.sid, Some("x".to_string()));
+        assert_eq!(p.semantic, Some("POSITION".to_string()));
+        assert_eq!(p.typ.as_str(), "float");
+
+        let e = Element::parse(r#"<param name="X"/>"#.as_bytes()).unwrap();
+        assert!(Param::new().parse(&e).is_err());
+    }
+
+    #[test]
+    fn test_param_encode() {
+        let mut p = Param::new();
+        p.name = Some("S".to_string());
+        p.typ = "float".to_string();
+        let e = p.encode();
+        assert_eq!(e.name, "param");
+        assert_eq!(e.attributes.get("name"), Some(&"S".to_string()));
+        assert_eq!(e.attributes.get("type"), Some(&"float".to_string()));
+        assert_eq!(e.attributes.get("sid"), None);
+    }
+}
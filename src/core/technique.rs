@@ -1,7 +1,9 @@
 use std::collections::{HashMap};
 use xmltree::{Element};
+use context::{ParseContext};
 use error::{ColladaError};
 use traits::{XmlConversion};
+use utils;
 
 /// A technique describes information needed by a specific platform or program. 
 #[derive(Debug)]
@@ -9,10 +11,17 @@ pub struct Technique {
     /// Vendor-defined string that indicates the platform/target for this technique
     pub profile: String,
 
-    /// XML Schema namespace for validation 
+    /// XML Schema namespace for validation. When the technique only declares
+    /// prefixed namespaces it is the first of those, e.g. `some/schema` for
+    /// `xmlns:max="some/schema"`.
     pub xmlns: Option<String>,
 
-    /// Any well-formed XML data stored as an XML Element
+    /// Namespaces declared on the technique as `(prefix, uri)` pairs, the
+    /// default namespace having an empty prefix
+    pub namespaces: Vec<(String, String)>,
+
+    /// Any well-formed XML data stored as an XML Element. Elements of other
+    /// namespaces keep their prefix, e.g. `max:someElement`.
     pub data: Element,
 }
 
@@ -21,6 +30,7 @@ impl Technique {
         Technique {
             profile: String::from(""),
             xmlns: None,
+            namespaces: Vec::new(),
             data: Element {
                 name: String::from("technique"),
                 attributes: HashMap::new(),
@@ -32,7 +42,7 @@ impl Technique {
 }
 
 impl XmlConversion for Technique {
-    fn parse(&mut self, e: &Element) -> Result<(), ColladaError> {
+    fn parse_with(&mut self, e: &Element, _ctx: &mut ParseContext) -> Result<(), ColladaError> {
         if e.name != "technique".to_owned() {
             return Err(ColladaError::MissingElement{
                 structure: "technique".to_string(),
@@ -48,15 +58,110 @@ impl XmlConversion for Technique {
             }),
         };
 
-        // TODO: Parse xmlns attribute once parser is fixed in xmltree-rs
+        self.namespaces = utils::get_namespaces(e);
+        self.xmlns = self.namespaces.first().map(|n| n.1.clone());
         self.data = e.clone();
         Ok(())
     }
 
     fn encode(&self) -> Element {
-        // We only need to clone the data because it already contains the 
-        // entire <technique> tag (including attributes profile/xmlns)
-        self.data.clone()
+        // The data contains the entire <technique> tag, only the attributes
+        // held by fields need to be replaced
+        let mut t = self.data.clone();
+        t.name = "technique".to_string();
+        t.attributes.retain(|a, _| a != "xmlns" && !a.starts_with("xmlns:"));
+        t.attributes.insert("profile".to_string(), self.profile.clone());
+        utils::set_namespaces(&mut t, &self.namespaces);
+        if let Some(ref x) = self.xmlns {
+            if !self.namespaces.iter().any(|n| n.1 == *x) {
+                t.attributes.insert("xmlns".to_string(), x.clone());
+            }
+        }
+        t
+    }
+}
+
+/// The typed content of a `<technique_common>`, e.g. the mass and shapes of
+/// a rigid body
+pub trait TechniqueCommon {
+    /// Parse a child of `<technique_common>`, returns false if the child is
+    /// not part of the common technique
+    fn parse_child(&mut self, c: &Element, ctx: &mut ParseContext) -> Result<bool, ColladaError>;
+
+    /// Append the common technique to a `<technique_common>`
+    fn encode_children(&self, tc: &mut Element);
+
+    /// Parse every child of a `<technique_common>`
+    fn parse_common(&mut self, tc: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        for c in &tc.children {
+            if !try!(self.parse_child(c, ctx)) {
+                return Err(ColladaError::InvalidChild{
+                    child: c.name.clone(),
+                    parent: "technique_common".to_string(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn encode_common(&self) -> Element {
+        let mut tc = Element::new("technique_common");
+        self.encode_children(&mut tc);
+        tc
+    }
+}
+
+/// An element holding a typed `<technique_common>` along with techniques
+/// for specific profiles
+pub trait HasTechniques {
+    type Common: ?Sized;
+
+    fn technique_common(&self) -> &Self::Common;
+    fn techniques(&self) -> &[Technique];
+
+    /// The technique of a profile, if the element has one
+    fn profile_technique(&self, profile: &str) -> Option<&Technique> {
+        self.techniques().iter().find(|t| t.profile == profile)
+    }
+
+    /// The technique of a profile, or the common technique when the element
+    /// has none for the profile
+    fn technique_for<'a>(&'a self, profile: &str) -> TechniqueRef<'a, Self::Common> {
+        self.technique_for_any(&[profile])
+    }
+
+    /// The technique of the first profile of a list the element has one
+    /// for, or the common technique
+    fn technique_for_any<'a>(&'a self, profiles: &[&str]) -> TechniqueRef<'a, Self::Common> {
+        for p in profiles {
+            if let Some(t) = self.profile_technique(p) {
+                return TechniqueRef::Profile(t);
+            }
+        }
+        TechniqueRef::Common(self.technique_common())
+    }
+}
+
+/// A technique chosen by `HasTechniques::technique_for`
+#[derive(Debug)]
+pub enum TechniqueRef<'a, T: 'a + ?Sized> {
+    Profile(&'a Technique),
+    Common(&'a T),
+}
+
+impl<'a, T: 'a + ?Sized> TechniqueRef<'a, T> {
+    pub fn profile(&self) -> Option<&'a Technique> {
+        match *self {
+            TechniqueRef::Profile(t) => Some(t),
+            TechniqueRef::Common(_) => None,
+        }
+    }
+
+    pub fn common(&self) -> Option<&'a T> {
+        match *self {
+            TechniqueRef::Profile(_) => None,
+            TechniqueRef::Common(c) => Some(c),
+        }
     }
 }
 
@@ -65,6 +170,7 @@ mod tests {
     use super::*;
     use std::collections::{HashMap};
     use xmltree::{Element};
+    use context::{ParseContext};
     use traits::{XmlConversion};
 
     #[test]
@@ -78,22 +184,26 @@ mod tests {
                 <uhoh>something well-formed and level, but that can't be validated
                 because there is no schema for it!</uhoh>
             </technique>"#;
-        let e = Element::parse(data.as_bytes()).unwrap();
+        let (e, _) = ParseContext::from_source(data.as_bytes()).unwrap();
         let mut t = Technique::new();
         match t.parse(&e) {
             Ok(_) => assert!(true),
             Err(_) => assert!(false),
         }
         assert_eq!(t.profile.as_str(), "Max");
-        // TODO: Enable this test once namespace parsing is fixed in xmltree
-        //assert_eq!(t.xmlns.unwrap().as_str(), "some/max/schema");
+        assert_eq!(t.xmlns.as_ref().unwrap().as_str(), "some/max/schema");
+        assert_eq!(t.namespaces, vec![("max".to_string(), "some/max/schema".to_string())]);
         let d = &t.data;
         assert_eq!(d.name, "technique");
         assert_eq!(d.children.len(), 3);
         assert_eq!(d.children[0].name.as_str(), "param");
-        // TODO: Enable this test once namespace parsing is fixed in xmltree
-        //assert_eq!(data.children[1].name.as_str(), "max:someElement");
+        assert_eq!(d.children[1].name.as_str(), "max:someElement");
         assert_eq!(d.children[2].name.as_str(), "uhoh");
+
+        let e = t.encode();
+        assert_eq!(e.attributes.get("xmlns:max"), Some(&String::from("some/max/schema")));
+        assert_eq!(e.attributes.get("xmlns"), None);
+        assert_eq!(e.children[1].name.as_str(), "max:someElement");
     }
 
     #[test]
@@ -130,6 +240,14 @@ mod tests {
         assert_eq!(e.name, "technique");
         assert_eq!(e.children.len(), 2);
         assert_eq!(e.attributes.get("profile"), Some(&String::from("max")));
-        // TODO: Write test for namespace attribute
+        assert_eq!(e.attributes.get("xmlns:max"), Some(&String::from("some/max/schema")));
+        assert_eq!(e.children[0].name.as_str(), "max:SomeElement");
+
+        let mut t = Technique::new();
+        t.profile = "blender".to_string();
+        t.xmlns = Some("some/blender/schema".to_string());
+        let e = t.encode();
+        assert_eq!(e.attributes.get("profile"), Some(&String::from("blender")));
+        assert_eq!(e.attributes.get("xmlns"), Some(&String::from("some/blender/schema")));
     }
 }
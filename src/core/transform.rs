@@ -0,0 +1,363 @@
+use xmltree::{Element};
+use context::{ParseContext};
+use error::{ColladaError};
+use traits::{XmlConversion};
+use utils;
+
+/// A 4x4 matrix in row-major order, as in `<matrix>`
+pub type Matrix = [f32; 16];
+
+pub const IDENTITY: Matrix = [1.0, 0.0, 0.0, 0.0,
+                              0.0, 1.0, 0.0, 0.0,
+                              0.0, 0.0, 1.0, 0.0,
+                              0.0, 0.0, 0.0, 1.0];
+
+/// Multiply two matrices, `a * b` applies `b` first
+pub fn multiply(a: &Matrix, b: &Matrix) -> Matrix {
+    let mut m = [0.0; 16];
+    for r in 0..4 {
+        for c in 0..4 {
+            m[r * 4 + c] = (0..4).map(|k| a[r * 4 + k] * b[k * 4 + c]).sum();
+        }
+    }
+    m
+}
+
+/// Transform a point by a matrix
+pub fn transform_point(m: &Matrix, p: [f32; 3]) -> [f32; 3] {
+    let mut out = [0.0; 3];
+    for r in 0..3 {
+        out[r] = m[r * 4] * p[0] + m[r * 4 + 1] * p[1] + m[r * 4 + 2] * p[2] + m[r * 4 + 3];
+    }
+    out
+}
+
+/// Translation by a vector
+pub fn translation(v: [f32; 3]) -> Matrix {
+    let mut m = IDENTITY;
+    m[3] = v[0];
+    m[7] = v[1];
+    m[11] = v[2];
+    m
+}
+
+/// Rotation around an axis by an angle in degrees
+pub fn rotation(axis: [f32; 3], angle: f32) -> Matrix {
+    let a = normalize(axis);
+    let (x, y, z) = (a[0], a[1], a[2]);
+    let (s, c) = angle.to_radians().sin_cos();
+    let t = 1.0 - c;
+    [t * x * x + c,     t * x * y - s * z, t * x * z + s * y, 0.0,
+     t * x * y + s * z, t * y * y + c,     t * y * z - s * x, 0.0,
+     t * x * z - s * y, t * y * z + s * x, t * z * z + c,     0.0,
+     0.0,               0.0,               0.0,               1.0]
+}
+
+/// Compose transformation elements in document order into one matrix
+pub fn compose(transforms: &[Transform]) -> Matrix {
+    transforms.iter().fold(IDENTITY, |m, t| multiply(&m, &t.to_matrix()))
+}
+
+fn normalize(v: [f32; 3]) -> [f32; 3] {
+    let len = (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+    if len == 0.0 {
+        v
+    } else {
+        [v[0] / len, v[1] / len, v[2] / len]
+    }
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+/// The kinds of transformation elements
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TransformKind {
+    /// Eye position, interest point and up vector
+    LookAt,
+
+    /// 4x4 matrix in row-major order
+    Matrix,
+
+    /// Axis and angle in degrees
+    Rotate,
+    Scale,
+
+    /// Angle in degrees, rotation axis and translation axis
+    Skew,
+    Translate,
+}
+
+impl TransformKind {
+    /// Get the kind of a transformation element, e.g. `translate`
+    pub fn from_name(name: &str) -> Option<TransformKind> {
+        match name {
+            "lookat" => Some(TransformKind::LookAt),
+            "matrix" => Some(TransformKind::Matrix),
+            "rotate" => Some(TransformKind::Rotate),
+            "scale" => Some(TransformKind::Scale),
+            "skew" => Some(TransformKind::Skew),
+            "translate" => Some(TransformKind::Translate),
+            _ => None,
+        }
+    }
+
+    /// Name of the transformation element
+    pub fn name(&self) -> &'static str {
+        match *self {
+            TransformKind::LookAt => "lookat",
+            TransformKind::Matrix => "matrix",
+            TransformKind::Rotate => "rotate",
+            TransformKind::Scale => "scale",
+            TransformKind::Skew => "skew",
+            TransformKind::Translate => "translate",
+        }
+    }
+
+    /// Number of values of the transformation
+    pub fn len(&self) -> usize {
+        match *self {
+            TransformKind::LookAt => 9,
+            TransformKind::Matrix => 16,
+            TransformKind::Rotate => 4,
+            TransformKind::Scale => 3,
+            TransformKind::Skew => 7,
+            TransformKind::Translate => 3,
+        }
+    }
+}
+
+/// A transformation element, e.g. `<rotate sid="rotX">1 0 0 90</rotate>`
+#[derive(Clone, Debug)]
+pub struct Transform {
+    pub kind: TransformKind,
+    pub sid: Option<String>,
+    pub values: Vec<f32>,
+}
+
+impl Transform {
+    /// Create an identity transformation of a kind
+    pub fn new(kind: TransformKind) -> Transform {
+        let values = match kind {
+            TransformKind::LookAt => vec![0.0, 0.0, 0.0, 0.0, 0.0, -1.0, 0.0, 1.0, 0.0],
+            TransformKind::Matrix => vec![1.0, 0.0, 0.0, 0.0,
+                                          0.0, 1.0, 0.0, 0.0,
+                                          0.0, 0.0, 1.0, 0.0,
+                                          0.0, 0.0, 0.0, 1.0],
+            TransformKind::Rotate => vec![0.0, 0.0, 1.0, 0.0],
+            TransformKind::Scale => vec![1.0, 1.0, 1.0],
+            TransformKind::Skew => vec![0.0, 0.0, 1.0, 0.0, 1.0, 0.0, 0.0],
+            TransformKind::Translate => vec![0.0, 0.0, 0.0],
+        };
+        Transform {
+            kind: kind,
+            sid: None,
+            values: values,
+        }
+    }
+
+    pub fn translate(x: f32, y: f32, z: f32) -> Transform {
+        Transform {
+            kind: TransformKind::Translate,
+            sid: None,
+            values: vec![x, y, z],
+        }
+    }
+
+    /// Rotation around an axis by an angle in degrees
+    pub fn rotate(x: f32, y: f32, z: f32, angle: f32) -> Transform {
+        Transform {
+            kind: TransformKind::Rotate,
+            sid: None,
+            values: vec![x, y, z, angle],
+        }
+    }
+
+    /// Multiply the distances of the transformation by a factor, e.g. when
+    /// converting units. Angles, axes and scales are unchanged.
+    pub fn scale_units(&mut self, factor: f64) {
+        let indices: &[usize] = match self.kind {
+            TransformKind::LookAt => &[0, 1, 2, 3, 4, 5],
+            TransformKind::Matrix => &[3, 7, 11],
+            TransformKind::Translate => &[0, 1, 2],
+            _ => &[],
+        };
+        for i in indices {
+            if let Some(v) = self.values.get_mut(*i) {
+                *v = (*v as f64 * factor) as f32;
+            }
+        }
+    }
+
+    /// The transformation as a matrix. Missing values, e.g. of a transform
+    /// built in code, are taken as zero.
+    pub fn to_matrix(&self) -> Matrix {
+        let v = |i: usize| self.values.get(i).cloned().unwrap_or(0.0);
+        match self.kind {
+            TransformKind::LookAt => {
+                let eye = [v(0), v(1), v(2)];
+                let f = normalize([v(3) - eye[0], v(4) - eye[1], v(5) - eye[2]]);
+                let x = normalize(cross(f, [v(6), v(7), v(8)]));
+                let y = cross(x, f);
+                [x[0], y[0], -f[0], eye[0],
+                 x[1], y[1], -f[1], eye[1],
+                 x[2], y[2], -f[2], eye[2],
+                 0.0,  0.0,  0.0,   1.0]
+            },
+            TransformKind::Matrix => {
+                let mut m = [0.0; 16];
+                for i in 0..16 {
+                    m[i] = v(i);
+                }
+                m
+            },
+            TransformKind::Rotate => rotation([v(0), v(1), v(2)], v(3)),
+            TransformKind::Scale => {
+                let mut m = IDENTITY;
+                m[0] = v(0);
+                m[5] = v(1);
+                m[10] = v(2);
+                m
+            },
+            TransformKind::Skew => {
+                // Shear along the rotation axis proportional to the distance
+                // along the translation axis
+                let a = normalize([v(1), v(2), v(3)]);
+                let b = normalize([v(4), v(5), v(6)]);
+                let t = v(0).to_radians().tan();
+                let mut m = IDENTITY;
+                for r in 0..3 {
+                    for c in 0..3 {
+                        m[r * 4 + c] += t * a[r] * b[c];
+                    }
+                }
+                m
+            },
+            TransformKind::Translate => translation([v(0), v(1), v(2)]),
+        }
+    }
+}
+
+impl XmlConversion for Transform {
+    fn parse_with(&mut self, e: &Element, _ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        self.kind = match TransformKind::from_name(&e.name) {
+            Some(k) => k,
+            None => return Err(ColladaError::MissingElement{
+                structure: "transform".to_string(),
+                elem: "translate".to_string(),
+            }),
+        };
+        self.sid = utils::get_attr(e, "sid");
+        self.values = try!(utils::parse_array(e, self.kind.len()));
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut t = utils::list_element(self.kind.name(), &self.values);
+        utils::set_attr(&mut t, "sid", &self.sid);
+        t
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use xmltree::{Element};
+    use traits::{XmlConversion};
+
+    #[test]
+    fn test_transform_parse() {
+        let data = r##"
+            <node>
+                <lookat>2 0 3 0 0 0 0 1 0</lookat>
+                <matrix>1 0 0 0 0 1 0 0 0 0 1 0 0 0 0 1</matrix>
+                <rotate sid="rotX">1 0 0 90</rotate>
+                <scale>2 2 2</scale>
+                <skew>45 0 1 0 1 0 0</skew>
+                <translate sid="loc">1 2 3</translate>
+            </node>"##;
+        let e = Element::parse(data.as_bytes()).unwrap();
+        let mut ts = Vec::new();
+        for c in &e.children {
+            let mut t = Transform::new(TransformKind::Translate);
+            match t.parse(c) {
+                Ok(_) => assert!(true),
+                Err(_) => assert!(false),
+            }
+            ts.push(t);
+        }
+        assert_eq!(ts[0].kind, TransformKind::LookAt);
+        assert_eq!(ts[1].kind, TransformKind::Matrix);
+        assert_eq!(ts[1].values.len(), 16);
+        assert_eq!(ts[2].kind, TransformKind::Rotate);
+        assert_eq!(ts[2].sid, Some("rotX".to_string()));
+        assert_eq!(ts[2].values, vec![1.0, 0.0, 0.0, 90.0]);
+        assert_eq!(ts[3].kind, TransformKind::Scale);
+        assert_eq!(ts[4].kind, TransformKind::Skew);
+        assert_eq!(ts[5].kind, TransformKind::Translate);
+        assert_eq!(ts[5].values, vec![1.0, 2.0, 3.0]);
+
+        let e = Element::parse(r##"<translate>1 2</translate>"##.as_bytes()).unwrap();
+        assert!(Transform::new(TransformKind::Translate).parse(&e).is_err());
+        let e = Element::parse(r##"<shear>1 2 3</shear>"##.as_bytes()).unwrap();
+        assert!(Transform::new(TransformKind::Translate).parse(&e).is_err());
+    }
+
+    fn assert_matrix_eq(a: &Matrix, b: &Matrix) {
+        for i in 0..16 {
+            assert!((a[i] - b[i]).abs() < 1e-5, "{:?} != {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn test_transform_matrix() {
+        let t = Transform::translate(1.0, 2.0, 3.0).to_matrix();
+        assert_eq!(transform_point(&t, [1.0, 1.0, 1.0]), [2.0, 3.0, 4.0]);
+
+        let r = Transform::rotate(0.0, 0.0, 1.0, 90.0).to_matrix();
+        let p = transform_point(&r, [1.0, 0.0, 0.0]);
+        assert!(p[0].abs() < 1e-6 && (p[1] - 1.0).abs() < 1e-6);
+
+        // Transforms apply right to left, the translation is rotated
+        let m = compose(&[Transform::rotate(0.0, 0.0, 1.0, 90.0), Transform::translate(1.0, 0.0, 0.0)]);
+        let p = transform_point(&m, [0.0, 0.0, 0.0]);
+        assert!(p[0].abs() < 1e-6 && (p[1] - 1.0).abs() < 1e-6);
+
+        let mut s = Transform::new(TransformKind::Scale);
+        s.values = vec![2.0, 3.0, 4.0];
+        assert_eq!(transform_point(&s.to_matrix(), [1.0, 1.0, 1.0]), [2.0, 3.0, 4.0]);
+
+        let mut m = Transform::new(TransformKind::Matrix);
+        m.values[3] = 5.0;
+        assert_matrix_eq(&m.to_matrix(), &translation([5.0, 0.0, 0.0]));
+
+        let mut k = Transform::new(TransformKind::Skew);
+        k.values = vec![45.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0];
+        let p = transform_point(&k.to_matrix(), [0.0, 2.0, 0.0]);
+        assert!((p[0] - 2.0).abs() < 1e-5 && (p[1] - 2.0).abs() < 1e-5);
+
+        // Camera at +Z looking at the origin is a pure translation
+        let mut l = Transform::new(TransformKind::LookAt);
+        l.values = vec![0.0, 0.0, 5.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0];
+        assert_matrix_eq(&l.to_matrix(), &translation([0.0, 0.0, 5.0]));
+        assert_matrix_eq(&Transform::new(TransformKind::LookAt).to_matrix(), &IDENTITY);
+
+        assert_matrix_eq(&multiply(&IDENTITY, &r), &r);
+    }
+
+    #[test]
+    fn test_transform_encode() {
+        let mut t = Transform::rotate(0.0, 1.0, 0.0, 45.5);
+        t.sid = Some("rotY".to_string());
+        let e = t.encode();
+        assert_eq!(e.name, "rotate");
+        assert_eq!(e.text, Some("0 1 0 45.5".to_string()));
+        assert_eq!(e.attributes.get("sid"), Some(&"rotY".to_string()));
+
+        let e = Transform::new(TransformKind::Scale).encode();
+        assert_eq!(e.name, "scale");
+        assert_eq!(e.text, Some("1 1 1".to_string()));
+        assert_eq!(e.attributes.get("sid"), None);
+    }
+}
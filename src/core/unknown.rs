@@ -0,0 +1,93 @@
+use xmltree::{Element};
+
+/// Children and attributes of an element that aren't modelled, e.g. vendor
+/// additions or elements of a newer schema. They are kept when parsing with
+/// `ParseOptions::preserve_unknown` and written back where they were when
+/// the element is encoded.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UnknownContent {
+    /// Attributes in the order they were found
+    pub attributes: Vec<(String, String)>,
+
+    /// Children with their index among the children of the original element
+    pub children: Vec<(usize, Element)>,
+}
+
+impl UnknownContent {
+    pub fn new() -> UnknownContent {
+        UnknownContent {
+            attributes: Vec::new(),
+            children: Vec::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.attributes.is_empty() && self.children.is_empty()
+    }
+
+    /// Keep a child of an element
+    pub fn add_child(&mut self, parent: &Element, child: &Element) {
+        let index = parent.children.iter()
+            .position(|c| c as *const Element == child as *const Element)
+            .unwrap_or(parent.children.len());
+        self.children.push((index, child.clone()));
+    }
+
+    /// Keep the attributes of an element that aren't in a list of known
+    /// attributes
+    pub fn add_attributes(&mut self, e: &Element, known: &[&str]) {
+        let mut names: Vec<&String> = e.attributes.keys()
+            .filter(|a| !known.contains(&a.as_str()))
+            .collect();
+        names.sort();
+        for name in names {
+            self.attributes.push((name.clone(), e.attributes[name].clone()));
+        }
+    }
+
+    /// Write the content back into an encoded element. Children are
+    /// inserted at their original index, or appended when the element has
+    /// fewer children.
+    pub fn encode_into(&self, e: &mut Element) {
+        for &(ref name, ref value) in &self.attributes {
+            if !e.attributes.contains_key(name) {
+                e.attributes.insert(name.clone(), value.clone());
+            }
+        }
+
+        let mut children = self.children.clone();
+        children.sort_by_key(|c| c.0);
+        for (index, child) in children {
+            let index = if index < e.children.len() { index } else { e.children.len() };
+            e.children.insert(index, child);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use xmltree::{Element};
+
+    #[test]
+    fn test_unknown_content_encode() {
+        let data = r##"<asset flag="1" id="a"><created/><generator/><modified/><tool/></asset>"##;
+        let e = Element::parse(data.as_bytes()).unwrap();
+        let mut u = UnknownContent::new();
+        assert!(u.is_empty());
+        u.add_attributes(&e, &["id"]);
+        u.add_child(&e, &e.children[1]);
+        u.add_child(&e, &e.children[3]);
+        assert_eq!(u.children[0].0, 1);
+        assert_eq!(u.children[1].0, 3);
+
+        let mut a = Element::new("asset");
+        a.children.push(Element::new("created"));
+        a.children.push(Element::new("modified"));
+        u.encode_into(&mut a);
+        let names: Vec<&str> = a.children.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["created", "generator", "modified", "tool"]);
+        assert_eq!(a.attributes.len(), 1);
+        assert!(!a.attributes.contains_key("id"));
+    }
+}
@@ -0,0 +1,514 @@
+use std::any::{Any};
+use std::collections::{HashMap};
+use xmltree::{Element};
+use context::{ParseContext};
+use core::{Technique};
+use error::{ColladaError};
+use traits::{XmlConversion};
+use utils;
+
+/// Vendor profiles of `<technique>` with a built-in decoder
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum VendorProfile {
+    /// FCollada, used by the ColladaMax and ColladaMaya exporters
+    FCollada,
+    Max3d,
+    Maya,
+
+    /// OpenCOLLADA, which names its profiles after the host, e.g.
+    /// `OpenCOLLADA3dsMax`
+    OpenCollada,
+    Blender,
+}
+
+impl VendorProfile {
+    pub fn all() -> &'static [VendorProfile] {
+        const ALL: &'static [VendorProfile] = &[
+            VendorProfile::FCollada,
+            VendorProfile::Max3d,
+            VendorProfile::Maya,
+            VendorProfile::OpenCollada,
+            VendorProfile::Blender,
+        ];
+        ALL
+    }
+
+    /// Every profile name exporters write for the vendor, the first being
+    /// the one new techniques are encoded with
+    pub fn names(&self) -> &'static [&'static str] {
+        match *self {
+            VendorProfile::FCollada => &["FCOLLADA"],
+            VendorProfile::Max3d => &["MAX3D"],
+            VendorProfile::Maya => &["MAYA"],
+            VendorProfile::OpenCollada => &["OpenCOLLADA", "OpenCOLLADA3dsMax", "OpenCOLLADAMaya"],
+            VendorProfile::Blender => &["blender"],
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<VendorProfile> {
+        VendorProfile::all().iter().find(|p| p.names().contains(&name)).cloned()
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.names()[0]
+    }
+
+    /// Name of the element holding a field in the profile
+    fn element(&self, field: VendorField) -> &'static str {
+        match (*self, field) {
+            (VendorProfile::Blender, VendorField::Intensity) => "energy",
+            (_, f) => f.name(),
+        }
+    }
+}
+
+/// Values vendor techniques commonly hold
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum VendorField {
+    DoubleSided,
+    Bump,
+    Intensity,
+    StartTime,
+    EndTime,
+    FrameRate,
+    UserProperties,
+}
+
+impl VendorField {
+    fn all() -> &'static [VendorField] {
+        const ALL: &'static [VendorField] = &[
+            VendorField::DoubleSided,
+            VendorField::Bump,
+            VendorField::Intensity,
+            VendorField::StartTime,
+            VendorField::EndTime,
+            VendorField::FrameRate,
+            VendorField::UserProperties,
+        ];
+        ALL
+    }
+
+    fn name(&self) -> &'static str {
+        match *self {
+            VendorField::DoubleSided => "double_sided",
+            VendorField::Bump => "bump",
+            VendorField::Intensity => "intensity",
+            VendorField::StartTime => "start_time",
+            VendorField::EndTime => "end_time",
+            VendorField::FrameRate => "frame_rate",
+            VendorField::UserProperties => "user_properties",
+        }
+    }
+}
+
+/// A texture a vendor technique refers to, e.g. a bump map
+#[derive(Clone, Debug, PartialEq)]
+pub struct VendorTexture {
+    /// Sid of the sampler, or the image id for older exporters
+    pub texture: String,
+    pub texcoord: Option<String>,
+
+    /// How the texture perturbs the normal, e.g. `HEIGHTFIELD` or
+    /// `NORMALMAP` for OpenCOLLADA
+    pub bump_type: Option<String>,
+}
+
+/// The content of a vendor technique with typed values for what exporters
+/// commonly write, e.g. a double sided flag on an effect, a light intensity
+/// or the time range of a scene
+#[derive(Clone, Debug)]
+pub struct VendorTechnique {
+    pub profile: VendorProfile,
+
+    /// The profile name as written, e.g. `OpenCOLLADA3dsMax`. It is encoded
+    /// when it is one of the names of `profile`, otherwise the first one is.
+    pub profile_name: String,
+    pub double_sided: Option<bool>,
+    pub bump: Option<VendorTexture>,
+    pub intensity: Option<f32>,
+    pub start_time: Option<f32>,
+    pub end_time: Option<f32>,
+    pub frame_rate: Option<f32>,
+
+    /// Free form properties set by the user in the authoring tool
+    pub user_properties: Option<String>,
+
+    /// Children without a typed value
+    pub other: Vec<Element>,
+
+    /// The order of the parsed children, `None` standing for the next one of
+    /// `other`, so encoding writes them back in the same order
+    layout: Vec<Option<VendorField>>,
+}
+
+/// Techniques are equal when their values are, whatever the order of their
+/// children
+impl PartialEq for VendorTechnique {
+    fn eq(&self, other: &VendorTechnique) -> bool {
+        self.profile == other.profile
+            && self.profile_name == other.profile_name
+            && self.double_sided == other.double_sided
+            && self.bump == other.bump
+            && self.intensity == other.intensity
+            && self.start_time == other.start_time
+            && self.end_time == other.end_time
+            && self.frame_rate == other.frame_rate
+            && self.user_properties == other.user_properties
+            && self.other == other.other
+    }
+}
+
+impl VendorTechnique {
+    pub fn new(profile: VendorProfile) -> VendorTechnique {
+        VendorTechnique {
+            profile: profile,
+            profile_name: profile.name().to_string(),
+            double_sided: None,
+            bump: None,
+            intensity: None,
+            start_time: None,
+            end_time: None,
+            frame_rate: None,
+            user_properties: None,
+            other: Vec::new(),
+            layout: Vec::new(),
+        }
+    }
+
+    /// The element of a typed value, if it is set
+    fn field_element(&self, field: VendorField) -> Option<Element> {
+        let name = self.profile.element(field);
+        let number = |x: Option<f32>| x.map(|x| utils::text_element(name, x.to_string()));
+        match field {
+            VendorField::DoubleSided => self.double_sided
+                .map(|x| utils::text_element(name, if x { "1" } else { "0" }.to_string())),
+            VendorField::Bump => self.bump.as_ref().map(|b| {
+                let mut bump = Element::new(name);
+                utils::set_attr(&mut bump, "bumptype", &b.bump_type);
+                let mut texture = Element::new("texture");
+                texture.attributes.insert("texture".to_string(), b.texture.clone());
+                utils::set_attr(&mut texture, "texcoord", &b.texcoord);
+                bump.children.push(texture);
+                bump
+            }),
+            VendorField::Intensity => number(self.intensity),
+            VendorField::StartTime => number(self.start_time),
+            VendorField::EndTime => number(self.end_time),
+            VendorField::FrameRate => number(self.frame_rate),
+            VendorField::UserProperties => self.user_properties.as_ref()
+                .map(|x| utils::text_element(name, x.clone())),
+        }
+    }
+}
+
+impl XmlConversion for VendorTechnique {
+    fn parse_with(&mut self, e: &Element, _ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        let profile = try!(utils::require_attr(e, "profile"));
+        self.profile = match VendorProfile::from_name(&profile) {
+            Some(p) => p,
+            None => return Err(ColladaError::InvalidAttrData{
+                elem: "technique".to_string(),
+                attr: "profile".to_string(),
+                data: profile,
+            }),
+        };
+        self.profile_name = profile;
+        self.layout.clear();
+
+        for c in &e.children {
+            let field = VendorField::all().iter()
+                .find(|f| self.profile.element(**f) == c.name)
+                .cloned();
+            match field {
+                Some(VendorField::DoubleSided) => self.double_sided = Some(try!(utils::parse_bool_text(c))),
+                Some(VendorField::Bump) => {
+                    let t = match c.get_child("texture") {
+                        Some(t) => t,
+                        None => return Err(ColladaError::MissingElement{
+                            structure: c.name.clone(),
+                            elem: "texture".to_string(),
+                        }),
+                    };
+                    self.bump = Some(VendorTexture {
+                        texture: try!(utils::require_attr(t, "texture")),
+                        texcoord: utils::get_attr(t, "texcoord"),
+                        bump_type: utils::get_attr(c, "bumptype"),
+                    });
+                },
+                Some(VendorField::Intensity) => self.intensity = Some(try!(utils::parse_text(c))),
+                Some(VendorField::StartTime) => self.start_time = Some(try!(utils::parse_text(c))),
+                Some(VendorField::EndTime) => self.end_time = Some(try!(utils::parse_text(c))),
+                Some(VendorField::FrameRate) => self.frame_rate = Some(try!(utils::parse_text(c))),
+                Some(VendorField::UserProperties) => self.user_properties = c.text.clone(),
+                None => self.other.push(c.clone()),
+            }
+            self.layout.push(field);
+        }
+
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut t = Element::new("technique");
+        let name = if self.profile.names().contains(&self.profile_name.as_str()) {
+            self.profile_name.as_str()
+        } else {
+            self.profile.name()
+        };
+        t.attributes.insert("profile".to_string(), name.to_string());
+
+        // Children in the order they were parsed in, then any values that
+        // have been set since
+        let mut written = Vec::new();
+        let mut other = self.other.iter();
+        for x in &self.layout {
+            match *x {
+                Some(f) => if !written.contains(&f) {
+                    t.children.extend(self.field_element(f));
+                    written.push(f);
+                },
+                None => t.children.extend(other.next().cloned()),
+            }
+        }
+        for f in VendorField::all() {
+            if !written.contains(f) {
+                t.children.extend(self.field_element(*f));
+            }
+        }
+        t.children.extend(other.cloned());
+        t
+    }
+}
+
+/// Content of a technique decoded by a `ProfileRegistry`
+#[derive(Debug)]
+pub enum ProfileData {
+    /// Content of a vendor profile with a built-in decoder
+    Vendor(VendorTechnique),
+
+    /// Content decoded by a registered decoder, see `ProfileData::custom`
+    Custom(Box<dyn Any>),
+
+    /// The `<technique>` element of a profile without a decoder
+    Raw(Element),
+}
+
+impl ProfileData {
+    /// The value a registered decoder produced, if it is a `T`
+    pub fn custom<T: Any>(&self) -> Option<&T> {
+        match *self {
+            ProfileData::Custom(ref x) => x.downcast_ref::<T>(),
+            _ => None,
+        }
+    }
+}
+
+/// Decodes the content of techniques of a profile
+pub trait ProfileDecoder {
+    fn decode(&self, t: &Technique) -> Result<ProfileData, ColladaError>;
+}
+
+impl ProfileDecoder for VendorProfile {
+    fn decode(&self, t: &Technique) -> Result<ProfileData, ColladaError> {
+        let mut v = VendorTechnique::new(*self);
+        try!(v.parse(&t.encode()));
+        Ok(ProfileData::Vendor(v))
+    }
+}
+
+impl<F> ProfileDecoder for F where F: Fn(&Technique) -> Result<ProfileData, ColladaError> {
+    fn decode(&self, t: &Technique) -> Result<ProfileData, ColladaError> {
+        self(t)
+    }
+}
+
+/// Decoders for technique profiles, keyed by `Technique::profile`
+pub struct ProfileRegistry {
+    decoders: HashMap<String, Box<dyn ProfileDecoder>>,
+}
+
+impl ProfileRegistry {
+    /// A registry with the decoders of every `VendorProfile`
+    pub fn new() -> ProfileRegistry {
+        let mut r = ProfileRegistry::empty();
+        for p in VendorProfile::all() {
+            for name in p.names() {
+                r = r.register(name, *p);
+            }
+        }
+        r
+    }
+
+    /// A registry without decoders
+    pub fn empty() -> ProfileRegistry {
+        ProfileRegistry {
+            decoders: HashMap::new(),
+        }
+    }
+
+    /// Decode techniques of a profile with a decoder, replacing the decoder
+    /// the profile had
+    pub fn register<D: ProfileDecoder + 'static>(mut self, profile: &str, decoder: D) -> ProfileRegistry {
+        self.decoders.insert(profile.to_string(), Box::new(decoder));
+        self
+    }
+
+    /// Keep techniques of a profile raw
+    pub fn unregister(mut self, profile: &str) -> ProfileRegistry {
+        self.decoders.remove(profile);
+        self
+    }
+
+    pub fn has_decoder(&self, profile: &str) -> bool {
+        self.decoders.contains_key(profile)
+    }
+
+    /// Decode a technique with the decoder of its profile, or give its raw
+    /// element when the profile has none
+    pub fn decode(&self, t: &Technique) -> Result<ProfileData, ColladaError> {
+        match self.decoders.get(&t.profile) {
+            Some(d) => d.decode(t),
+            None => Ok(ProfileData::Raw(t.encode())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use xmltree::{Element};
+    use core::{Extra};
+    use error::{ColladaError};
+    use traits::{XmlConversion};
+
+    const DATA: &'static str = r##"
+        <extra>
+            <technique profile="FCOLLADA">
+                <start_time>0</start_time>
+                <end_time>3.5</end_time>
+                <user_properties>mass=2</user_properties>
+            </technique>
+            <technique profile="OpenCOLLADA3dsMax">
+                <double_sided>1</double_sided>
+                <bump bumptype="HEIGHTFIELD"><texture texture="bump-sampler" texcoord="CHANNEL1"/></bump>
+                <extended_shader/>
+            </technique>
+            <technique profile="blender">
+                <energy>2.5</energy>
+            </technique>
+            <technique profile="custom">
+                <speed>4</speed>
+            </technique>
+        </extra>"##;
+
+    #[test]
+    fn test_vendor_decode() {
+        let e = Element::parse(DATA.as_bytes()).unwrap();
+        let mut x = Extra::new();
+        x.parse(&e).unwrap();
+
+        let r = ProfileRegistry::new();
+        match r.decode(&x.techniques[0]).unwrap() {
+            ProfileData::Vendor(v) => {
+                assert_eq!(v.profile, VendorProfile::FCollada);
+                assert_eq!(v.start_time, Some(0.0));
+                assert_eq!(v.end_time, Some(3.5));
+                assert_eq!(v.user_properties, Some("mass=2".to_string()));
+            },
+            d => panic!("{:?}", d),
+        }
+        match r.decode(&x.techniques[1]).unwrap() {
+            ProfileData::Vendor(v) => {
+                assert_eq!(v.profile, VendorProfile::OpenCollada);
+                assert_eq!(v.double_sided, Some(true));
+                assert_eq!(v.bump, Some(VendorTexture {
+                    texture: "bump-sampler".to_string(),
+                    texcoord: Some("CHANNEL1".to_string()),
+                    bump_type: Some("HEIGHTFIELD".to_string()),
+                }));
+                assert_eq!(v.other.len(), 1);
+                assert_eq!(v.other[0].name, "extended_shader");
+            },
+            d => panic!("{:?}", d),
+        }
+        match r.decode(&x.techniques[2]).unwrap() {
+            ProfileData::Vendor(v) => assert_eq!(v.intensity, Some(2.5)),
+            d => panic!("{:?}", d),
+        }
+        match r.decode(&x.techniques[3]).unwrap() {
+            ProfileData::Raw(e) => assert_eq!(e.children[0].name, "speed"),
+            d => panic!("{:?}", d),
+        }
+
+        let r = r.unregister("blender").register("custom", |t: &Technique| {
+            match t.data.get_child("speed") {
+                Some(s) => Ok(ProfileData::Custom(Box::new(try!(utils::parse_text::<u32>(s))))),
+                None => Err(ColladaError::MissingElement{
+                    structure: "technique".to_string(),
+                    elem: "speed".to_string(),
+                }),
+            }
+        });
+        assert!(!r.has_decoder("blender"));
+        match r.decode(&x.techniques[2]).unwrap() {
+            ProfileData::Raw(_) => assert!(true),
+            d => panic!("{:?}", d),
+        }
+        let d = r.decode(&x.techniques[3]).unwrap();
+        assert_eq!(d.custom::<u32>(), Some(&4));
+        assert_eq!(d.custom::<f32>(), None);
+    }
+
+    #[test]
+    fn test_vendor_encode() {
+        let mut v = VendorTechnique::new(VendorProfile::Blender);
+        v.double_sided = Some(false);
+        v.intensity = Some(2.5);
+        v.bump = Some(VendorTexture {
+            texture: "tex".to_string(),
+            texcoord: None,
+            bump_type: None,
+        });
+        let e = v.encode();
+        assert_eq!(e.attributes.get("profile").unwrap(), "blender");
+        let names: Vec<&str> = e.children.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["double_sided", "bump", "energy"]);
+
+        let mut w = VendorTechnique::new(VendorProfile::Maya);
+        w.parse(&e).unwrap();
+        assert_eq!(w, v);
+
+        // Parsed techniques are written back with their profile name and
+        // children as they were
+        let data = r##"<technique profile="OpenCOLLADA3dsMax">
+            <extended_shader/>
+            <bump bumptype="NORMALMAP"><texture texture="bump-sampler"/></bump>
+            <frame_rate>24</frame_rate>
+            <double_sided>1</double_sided>
+        </technique>"##;
+        let e = Element::parse(data.as_bytes()).unwrap();
+        let mut m = VendorTechnique::new(VendorProfile::OpenCollada);
+        m.parse(&e).unwrap();
+        assert_eq!(m.profile, VendorProfile::OpenCollada);
+        assert_eq!(m.profile_name, "OpenCOLLADA3dsMax");
+        let encoded = m.encode();
+        assert_eq!(encoded.attributes.get("profile").unwrap(), "OpenCOLLADA3dsMax");
+        let names: Vec<&str> = encoded.children.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["extended_shader", "bump", "frame_rate", "double_sided"]);
+        let mut n = VendorTechnique::new(VendorProfile::Maya);
+        n.parse(&encoded).unwrap();
+        assert_eq!(n, m);
+        assert_eq!(n.encode(), encoded);
+
+        // Values set after parsing come after the parsed children
+        m.intensity = Some(3.0);
+        m.profile = VendorProfile::Maya;
+        let encoded = m.encode();
+        assert_eq!(encoded.attributes.get("profile").unwrap(), "MAYA");
+        assert_eq!(encoded.children.last().unwrap().name, "intensity");
+
+        let mut bad = Element::new("technique");
+        bad.attributes.insert("profile".to_string(), "other".to_string());
+        assert!(w.parse(&bad).is_err());
+    }
+}
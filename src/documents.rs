@@ -0,0 +1,400 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::{File};
+use std::io::{Read};
+use std::path::{PathBuf};
+use xmltree::{Element};
+use archive::{Archive, resolve_path};
+use collada::{Collada};
+use context::{ParseContext};
+use error::{ColladaError};
+use utils;
+
+/// Fetches the documents of a `DocumentSet` by their path. Paths are
+/// relative to the root of the resolver with `/` separators, see
+/// `resolve_path`.
+pub trait Resolver {
+    fn fetch(&self, path: &str) -> Result<Vec<u8>, ColladaError>;
+}
+
+/// Resolves paths against a directory
+#[derive(Debug)]
+pub struct FileResolver {
+    pub root: PathBuf,
+}
+
+impl FileResolver {
+    pub fn new<P: Into<PathBuf>>(root: P) -> FileResolver {
+        FileResolver {
+            root: root.into(),
+        }
+    }
+}
+
+impl Resolver for FileResolver {
+    fn fetch(&self, path: &str) -> Result<Vec<u8>, ColladaError> {
+        let mut data = Vec::new();
+        match File::open(self.root.join(path)).and_then(|mut f| f.read_to_end(&mut data)) {
+            Ok(_) => Ok(data),
+            Err(_) => Err(ColladaError::MissingDocument{path: path.to_string()}),
+        }
+    }
+}
+
+/// Resolves paths to documents held in memory, e.g. for tests
+#[derive(Debug)]
+pub struct MemoryResolver {
+    pub files: HashMap<String, Vec<u8>>,
+}
+
+impl MemoryResolver {
+    pub fn new() -> MemoryResolver {
+        MemoryResolver {
+            files: HashMap::new(),
+        }
+    }
+
+    pub fn insert(&mut self, path: &str, data: Vec<u8>) {
+        self.files.insert(path.to_string(), data);
+    }
+}
+
+impl Resolver for MemoryResolver {
+    fn fetch(&self, path: &str) -> Result<Vec<u8>, ColladaError> {
+        match self.files.get(path) {
+            Some(d) => Ok(d.clone()),
+            None => Err(ColladaError::MissingDocument{path: path.to_string()}),
+        }
+    }
+}
+
+impl Resolver for Archive {
+    fn fetch(&self, path: &str) -> Result<Vec<u8>, ColladaError> {
+        match self.get(path) {
+            Some(d) => Ok(d.to_vec()),
+            None => Err(ColladaError::MissingDocument{path: path.to_string()}),
+        }
+    }
+}
+
+/// A document of a `DocumentSet`, kept both parsed and as the element tree
+/// urls are resolved in
+#[derive(Debug)]
+pub struct Document {
+    pub path: String,
+    pub collada: Collada,
+    pub root: Element,
+}
+
+/// Documents loaded through a resolver together with every document they
+/// refer to, e.g. by `<instance_geometry url="parts/wheel.dae#wheel">`.
+/// Each document is fetched once and urls resolve the same whether they
+/// point into the same document or another one.
+pub struct DocumentSet {
+    resolver: Box<dyn Resolver>,
+    documents: Vec<Document>,
+}
+
+impl DocumentSet {
+    pub fn new(resolver: Box<dyn Resolver>) -> DocumentSet {
+        DocumentSet {
+            resolver: resolver,
+            documents: Vec::new(),
+        }
+    }
+
+    /// A set loading documents from the files in a directory, see
+    /// `FileResolver`
+    pub fn from_dir<P: Into<PathBuf>>(root: P) -> DocumentSet {
+        DocumentSet::new(Box::new(FileResolver::new(root)))
+    }
+
+    /// Load a document and the documents it refers to. Fails if any of them
+    /// can't be fetched or read, or if instantiating the document would
+    /// never end because a url leads back to an element containing it. The
+    /// set is left as it was when loading fails.
+    pub fn load(&mut self, path: &str) -> Result<&Document, ColladaError> {
+        let loaded = self.documents.len();
+        if let Err(e) = self.fetch(path).and_then(|_| self.check_cycles(path)) {
+            self.documents.truncate(loaded);
+            return Err(e);
+        }
+        Ok(&self.documents[self.index(path).unwrap()])
+    }
+
+    /// A loaded document
+    pub fn get(&self, path: &str) -> Option<&Document> {
+        self.index(path).map(|i| &self.documents[i])
+    }
+
+    /// All loaded documents in the order they were loaded
+    pub fn documents(&self) -> &[Document] {
+        &self.documents
+    }
+
+    /// The element a url in the document at `base` refers to, along with the
+    /// document it is in
+    pub fn resolve(&self, base: &str, url: &str) -> Result<(&Document, &Element), ColladaError> {
+        let unresolved = || ColladaError::UnresolvedReference{url: url.to_string()};
+        let (path, id) = match url.find('#') {
+            Some(i) => (&url[..i], &url[i + 1..]),
+            None => return Err(unresolved()),
+        };
+        let doc = if path.is_empty() {
+            self.get(base)
+        } else {
+            resolve_path(base, path).and_then(|p| self.get(&p))
+        };
+        match doc {
+            Some(d) => match find_id(&d.root, id) {
+                Some(e) => Ok((d, e)),
+                None => Err(unresolved()),
+            },
+            None => Err(unresolved()),
+        }
+    }
+
+    /// Check that following the urls from a loaded document never leads back
+    /// to an element containing the url, e.g. a `<node>` instancing one of
+    /// its parents. Urls that can't be resolved are skipped.
+    pub fn check_cycles(&self, path: &str) -> Result<(), ColladaError> {
+        let doc = match self.get(path) {
+            Some(d) => d,
+            None => return Err(ColladaError::MissingDocument{path: path.to_string()}),
+        };
+        let mut stack = Vec::new();
+        let mut done = HashSet::new();
+        self.visit(doc, &doc.root, &mut stack, &mut done)
+    }
+
+    fn index(&self, path: &str) -> Option<usize> {
+        self.documents.iter().position(|d| d.path == path)
+    }
+
+    /// Fetch a document unless it is loaded and then the documents it
+    /// refers to. Documents are added before their references are followed,
+    /// so documents referring to each other are only fetched once.
+    fn fetch(&mut self, path: &str) -> Result<(), ColladaError> {
+        if self.index(path).is_some() {
+            return Ok(());
+        }
+        let data = try!(self.resolver.fetch(path));
+        let (root, mut ctx) = try!(ParseContext::from_source(&data));
+        let mut collada = Collada::new();
+        try!(ctx.parse(&mut collada, &root));
+
+        let mut paths = Vec::new();
+        external_paths(path, &root, &mut paths);
+        self.documents.push(Document {
+            path: path.to_string(),
+            collada: collada,
+            root: *root,
+        });
+        for p in paths {
+            try!(self.fetch(&p));
+        }
+        Ok(())
+    }
+
+    /// Follow the urls of an element and its descendants, with `stack` the
+    /// elements being followed and `done` the elements without cycles
+    fn visit<'a>(&'a self, doc: &'a Document, e: &'a Element,
+                 stack: &mut Vec<*const Element>, done: &mut HashSet<*const Element>)
+        -> Result<(), ColladaError>
+    {
+        stack.push(e);
+        try!(self.visit_children(doc, e, stack, done));
+        stack.pop();
+        done.insert(e);
+        Ok(())
+    }
+
+    fn visit_children<'a>(&'a self, doc: &'a Document, e: &'a Element,
+                          stack: &mut Vec<*const Element>, done: &mut HashSet<*const Element>)
+        -> Result<(), ColladaError>
+    {
+        for url in instance_urls(e) {
+            let (d, target) = match self.resolve(&doc.path, &url) {
+                Ok(x) => x,
+                Err(_) => continue,
+            };
+            let ptr = target as *const Element;
+            if stack.contains(&ptr) {
+                return Err(ColladaError::CyclicReference{url: url});
+            }
+            if !done.contains(&ptr) {
+                try!(self.visit(d, target, stack, done));
+            }
+        }
+        for c in &e.children {
+            try!(self.visit_children(doc, c, stack, done));
+        }
+        Ok(())
+    }
+}
+
+/// The urls that instantiate other elements, i.e. the `url` of an instance
+/// and the `source` of a skin or morph. Urls like the `source` of an input
+/// only refer to data.
+fn instance_urls(e: &Element) -> Vec<String> {
+    let mut urls: Vec<String> = e.attributes.get("url").into_iter().cloned().collect();
+    if e.name == "skin" || e.name == "morph" {
+        urls.extend(e.attributes.get("source").cloned());
+    }
+    urls
+}
+
+/// Paths of the other documents the urls in an element refer to
+fn external_paths(base: &str, e: &Element, paths: &mut Vec<String>) {
+    for url in utils::element_urls(e) {
+        let path = match url.find('#') {
+            Some(i) => &url[..i],
+            None => continue,
+        };
+        if path.is_empty() {
+            continue;
+        }
+        if let Some(p) = resolve_path(base, path) {
+            if p != base && !paths.contains(&p) {
+                paths.push(p);
+            }
+        }
+    }
+    for c in &e.children {
+        external_paths(base, c, paths);
+    }
+}
+
+/// Find the element with an id, depth first
+fn find_id<'a>(e: &'a Element, id: &str) -> Option<&'a Element> {
+    if e.attributes.get("id").map(|s| s.as_str()) == Some(id) {
+        return Some(e);
+    }
+    e.children.iter().filter_map(|c| find_id(c, id)).next()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs, process};
+    use error::{ColladaError};
+
+    fn doc(body: &str) -> Vec<u8> {
+        format!(r##"<COLLADA xmlns="http://www.collada.org/2008/03/COLLADASchema" version="1.5.0">
+            <asset>
+                <created>2017-01-01T00:00:00Z</created>
+                <modified>2017-01-01T00:00:00Z</modified>
+            </asset>
+            {}
+        </COLLADA>"##, body).into_bytes()
+    }
+
+    #[test]
+    fn test_document_set_load() {
+        let mut r = MemoryResolver::new();
+        r.insert("car.dae", doc(r##"
+            <library_visual_scenes>
+                <visual_scene id="scene">
+                    <node id="car">
+                        <node><instance_node url="parts/wheel.dae#wheel"/></node>
+                        <node><instance_node url="parts/wheel.dae#wheel"/></node>
+                        <node><instance_node url="#body"/></node>
+                    </node>
+                </visual_scene>
+            </library_visual_scenes>
+            <library_nodes><node id="body"/></library_nodes>
+        "##));
+        r.insert("parts/wheel.dae", doc(r##"
+            <library_nodes>
+                <node id="wheel"><instance_geometry url="../shared/geometry.dae#tire"/></node>
+            </library_nodes>
+        "##));
+        r.insert("shared/geometry.dae", doc(r##"<library_geometries><geometry id="tire"/></library_geometries>"##));
+
+        let mut set = DocumentSet::new(Box::new(r));
+        assert_eq!(set.load("car.dae").unwrap().path, "car.dae");
+        let paths: Vec<&str> = set.documents().iter().map(|d| d.path.as_str()).collect();
+        assert_eq!(paths, vec!["car.dae", "parts/wheel.dae", "shared/geometry.dae"]);
+
+        let (d, e) = set.resolve("car.dae", "parts/wheel.dae#wheel").unwrap();
+        assert_eq!(d.path, "parts/wheel.dae");
+        assert_eq!(e.name, "node");
+        let (d, e) = set.resolve("parts/wheel.dae", "../shared/geometry.dae#tire").unwrap();
+        assert_eq!(d.path, "shared/geometry.dae");
+        assert_eq!(e.name, "geometry");
+        let (d, _) = set.resolve("car.dae", "#body").unwrap();
+        assert_eq!(d.path, "car.dae");
+        assert!(set.resolve("car.dae", "#wheel").is_err());
+        assert!(set.resolve("car.dae", "other.dae#body").is_err());
+    }
+
+    #[test]
+    fn test_document_set_missing() {
+        let mut r = MemoryResolver::new();
+        r.insert("a.dae", doc(r##"<library_nodes><node id="a"><instance_node url="b.dae#b"/></node></library_nodes>"##));
+        let mut set = DocumentSet::new(Box::new(r));
+        match set.load("a.dae") {
+            Err(ColladaError::MissingDocument{ref path}) if path == "b.dae" => {},
+            r => panic!("unexpected result {:?}", r.map(|d| d.path.clone())),
+        }
+
+        // Nothing of a failed load is kept, so loading again fails again
+        assert!(set.documents().is_empty());
+        assert!(set.load("a.dae").is_err());
+    }
+
+    #[test]
+    fn test_document_set_from_dir() {
+        let dir = env::temp_dir().join(format!("collada-documents-{}", process::id()));
+        fs::create_dir_all(dir.join("parts")).unwrap();
+        fs::write(dir.join("car.dae"), doc(r##"
+            <library_nodes><node id="car"><instance_node url="parts/wheel.dae#wheel"/></node></library_nodes>
+        "##)).unwrap();
+        fs::write(dir.join("parts/wheel.dae"), doc(r##"<library_nodes><node id="wheel"/></library_nodes>"##)).unwrap();
+
+        let mut set = DocumentSet::from_dir(&dir);
+        let loaded = set.load("car.dae").map(|d| d.path.clone());
+        let resolved = set.resolve("car.dae", "parts/wheel.dae#wheel").map(|(d, _)| d.path.clone());
+        let missing = FileResolver::new(&dir).fetch("parts/tire.dae");
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(loaded.unwrap(), "car.dae");
+        assert_eq!(resolved.unwrap(), "parts/wheel.dae");
+        match missing {
+            Err(ColladaError::MissingDocument{ref path}) => assert_eq!(path, "parts/tire.dae"),
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_document_set_cycles() {
+        // Documents referring to each other are fine as long as the
+        // instances don't
+        let mut r = MemoryResolver::new();
+        r.insert("a.dae", doc(r##"<library_nodes>
+            <node id="a1"><instance_node url="b.dae#b"/></node>
+            <node id="a2"/>
+        </library_nodes>"##));
+        r.insert("b.dae", doc(r##"<library_nodes><node id="b"><instance_node url="a.dae#a2"/></node></library_nodes>"##));
+        let mut set = DocumentSet::new(Box::new(r));
+        assert!(set.load("a.dae").is_ok());
+        assert_eq!(set.documents().len(), 2);
+
+        let mut r = MemoryResolver::new();
+        r.insert("a.dae", doc(r##"<library_nodes><node id="a"><instance_node url="b.dae#b"/></node></library_nodes>"##));
+        r.insert("b.dae", doc(r##"<library_nodes>
+            <node id="b"><node><instance_node url="a.dae#a"/></node></node>
+        </library_nodes>"##));
+        let mut set = DocumentSet::new(Box::new(r));
+        match set.load("a.dae") {
+            Err(ColladaError::CyclicReference{..}) => {},
+            r => panic!("unexpected result {:?}", r.map(|d| d.path.clone())),
+        }
+
+        let mut r = MemoryResolver::new();
+        r.insert("a.dae", doc(r##"<library_nodes><node id="a"><node><instance_node url="#a"/></node></node></library_nodes>"##));
+        let mut set = DocumentSet::new(Box::new(r));
+        assert!(set.load("a.dae").is_err());
+        assert!(set.get("a.dae").is_none());
+        assert!(set.load("a.dae").is_err());
+    }
+}
@@ -1,10 +1,11 @@
 use std::error;
 use std::fmt;
-use std::num;
 use std::string::{String};
+use context::{SourcePosition};
 
 #[derive(Debug)]
 pub enum ColladaError {
+    /// The document is not well-formed xml
     ParseError,
 
     /// General Validation Error
@@ -30,6 +31,42 @@ pub enum ColladaError {
 
     /// Element is missing required data, e.g. <tag><!-- MISSING DATA HERE --></tag>
     MissingData{elem: String},
+
+    /// A KMZ or ZAE archive can't be read
+    InvalidArchive{msg: String},
+
+    /// A line of a Wavefront OBJ or MTL file can't be read
+    InvalidObj{line: usize, msg: String},
+
+    /// A document can't be fetched by its resolver
+    MissingDocument{path: String},
+
+    /// A url doesn't refer to any element
+    UnresolvedReference{url: String},
+
+    /// Following a url leads back to the element it is in
+    CyclicReference{url: String},
+
+    /// An error at a position in the document
+    Located{position: SourcePosition, error: Box<ColladaError>},
+}
+
+impl ColladaError {
+    /// Where in the document the error occurred, if known
+    pub fn position(&self) -> Option<&SourcePosition> {
+        match *self {
+            ColladaError::Located{ref position, ..} => Some(position),
+            _ => None,
+        }
+    }
+
+    /// The error without its position
+    pub fn inner(&self) -> &ColladaError {
+        match *self {
+            ColladaError::Located{ref error, ..} => error.inner(),
+            _ => self,
+        }
+    }
 }
 
 impl fmt::Display for ColladaError {
@@ -53,6 +90,18 @@ impl fmt::Display for ColladaError {
                 write!(f, "Element <{}> is missing required attribute: {}", elem, attr),
             ColladaError::MissingData{ref elem} =>
                 write!(f, "Element <{}> is missing required data", elem),
+            ColladaError::InvalidArchive{ref msg} =>
+                write!(f, "Invalid archive: {}", msg),
+            ColladaError::InvalidObj{line, ref msg} =>
+                write!(f, "Invalid OBJ at line {}: {}", line, msg),
+            ColladaError::MissingDocument{ref path} =>
+                write!(f, "Document '{}' can't be loaded", path),
+            ColladaError::UnresolvedReference{ref url} =>
+                write!(f, "Url '{}' doesn't refer to any element", url),
+            ColladaError::CyclicReference{ref url} =>
+                write!(f, "Url '{}' refers back to an element containing it", url),
+            ColladaError::Located{ref position, ref error} =>
+                write!(f, "{}: {}", position, error),
         }
     }
 }
@@ -69,12 +118,12 @@ impl error::Error for ColladaError {
             ColladaError::MissingElement{..} => "Missing required element",
             ColladaError::MissingAttr{..} => "Missing required attribute",
             ColladaError::MissingData{..} => "Missing required element data",
+            ColladaError::InvalidArchive{..} => "Invalid archive",
+            ColladaError::InvalidObj{..} => "Invalid OBJ",
+            ColladaError::MissingDocument{..} => "Missing document",
+            ColladaError::UnresolvedReference{..} => "Unresolved reference",
+            ColladaError::CyclicReference{..} => "Cyclic reference",
+            ColladaError::Located{..} => "Error in document",
         }
     }
 }
-
-impl From<num::ParseFloatError> for ColladaError {
-    fn from(_: num::ParseFloatError) -> ColladaError {
-        ColladaError::ParseError
-    }
-}
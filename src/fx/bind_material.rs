@@ -0,0 +1,500 @@
+use std::collections::{HashMap};
+use xmltree::{Element};
+use context::{ParseContext};
+use core::{Extra, HasTechniques, Param, Technique, UnknownContent};
+use error::{ColladaError};
+use fx::{Effect, LibraryEffects, LibraryMaterials, Material};
+use traits::{XmlConversion};
+use utils;
+
+/// Binds a value or parameter of the instanced object to a material
+/// parameter
+#[derive(Debug)]
+pub struct Bind {
+    /// Parameter in the material to bind to
+    pub semantic: String,
+
+    /// Sid path of the value that is bound
+    pub target: String,
+}
+
+impl Bind {
+    pub fn new() -> Bind {
+        Bind {
+            semantic: String::from(""),
+            target: String::from(""),
+        }
+    }
+}
+
+impl XmlConversion for Bind {
+    fn parse_with(&mut self, e: &Element, _ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        self.semantic = try!(utils::require_attr(e, "semantic"));
+        self.target = try!(utils::require_attr(e, "target"));
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut b = Element::new("bind");
+        b.attributes.insert("semantic".to_string(), self.semantic.clone());
+        b.attributes.insert("target".to_string(), self.target.clone());
+        b
+    }
+}
+
+/// Binds a geometry input to an effect semantic, e.g. a TEXCOORD set to the
+/// `texcoord` of a texture
+#[derive(Debug)]
+pub struct BindVertexInput {
+    /// Semantic used by the effect, e.g. `UVSET0`
+    pub semantic: String,
+
+    /// Semantic of the geometry input, e.g. `TEXCOORD`
+    pub input_semantic: String,
+
+    /// Set of the geometry input
+    pub input_set: Option<u32>,
+}
+
+impl BindVertexInput {
+    pub fn new() -> BindVertexInput {
+        BindVertexInput {
+            semantic: String::from(""),
+            input_semantic: String::from(""),
+            input_set: None,
+        }
+    }
+}
+
+impl XmlConversion for BindVertexInput {
+    fn parse_with(&mut self, e: &Element, _ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        self.semantic = try!(utils::require_attr(e, "semantic"));
+        self.input_semantic = try!(utils::require_attr(e, "input_semantic"));
+        self.input_set = try!(utils::parse_attr(e, "input_set"));
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut b = Element::new("bind_vertex_input");
+        b.attributes.insert("semantic".to_string(), self.semantic.clone());
+        b.attributes.insert("input_semantic".to_string(), self.input_semantic.clone());
+        if let Some(s) = self.input_set {
+            b.attributes.insert("input_set".to_string(), s.to_string());
+        }
+        b
+    }
+}
+
+/// Binds a material to a material symbol used by the primitives of an
+/// instanced geometry or controller
+#[derive(Debug)]
+pub struct InstanceMaterial {
+    pub sid: Option<String>,
+    pub name: Option<String>,
+
+    /// Url of the material, e.g. `#blue`
+    pub target: String,
+
+    /// Symbol used by the geometry primitives, i.e. their `material` attribute
+    pub symbol: String,
+    pub binds: Vec<Bind>,
+    pub bind_vertex_inputs: Vec<BindVertexInput>,
+    pub extras: Vec<Extra>,
+    pub unknown: UnknownContent,
+}
+
+impl InstanceMaterial {
+    pub fn new() -> InstanceMaterial {
+        InstanceMaterial {
+            sid: None,
+            name: None,
+            target: String::from(""),
+            symbol: String::from(""),
+            binds: Vec::new(),
+            bind_vertex_inputs: Vec::new(),
+            extras: Vec::new(),
+            unknown: UnknownContent::new(),
+        }
+    }
+
+    /// TEXCOORD input set bound to an effect texcoord semantic
+    pub fn texcoord_set(&self, semantic: &str) -> Option<u32> {
+        self.bind_vertex_inputs.iter()
+            .find(|b| b.semantic == semantic && b.input_semantic == "TEXCOORD")
+            .map(|b| b.input_set.unwrap_or(0))
+    }
+}
+
+impl XmlConversion for InstanceMaterial {
+    fn parse_with(&mut self, e: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        ctx.unknown_attributes(e, &["sid", "name", "target", "symbol"], &mut self.unknown);
+        self.sid = utils::get_attr(e, "sid");
+        self.name = utils::get_attr(e, "name");
+        self.target = try!(utils::require_attr(e, "target"));
+        self.symbol = try!(utils::require_attr(e, "symbol"));
+
+        for c in &e.children {
+            match c.name.as_str() {
+                "bind" => {
+                    let mut b = Bind::new();
+                    try!(ctx.parse(&mut b, c));
+                    self.binds.push(b);
+                },
+                "bind_vertex_input" => {
+                    let mut b = BindVertexInput::new();
+                    try!(ctx.parse(&mut b, c));
+                    self.bind_vertex_inputs.push(b);
+                },
+                "extra" => {
+                    let mut x = Extra::new();
+                    try!(ctx.parse(&mut x, c));
+                    self.extras.push(x);
+                },
+                _ => try!(ctx.unknown_child(e, c, &mut self.unknown)),
+            }
+        }
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut i = Element::new("instance_material");
+        utils::set_attr(&mut i, "sid", &self.sid);
+        utils::set_attr(&mut i, "name", &self.name);
+        i.attributes.insert("target".to_string(), self.target.clone());
+        i.attributes.insert("symbol".to_string(), self.symbol.clone());
+
+        for b in &self.binds {
+            i.children.push(b.encode());
+        }
+        for b in &self.bind_vertex_inputs {
+            i.children.push(b.encode());
+        }
+        for x in &self.extras {
+            i.children.push(x.encode());
+        }
+        self.unknown.encode_into(&mut i);
+        i
+    }
+}
+
+/// The material and effect bound to a primitive's material symbol
+#[derive(Debug)]
+pub struct MaterialBinding<'a> {
+    pub instance: &'a InstanceMaterial,
+    pub material: &'a Material,
+
+    /// The effect instantiated by the material
+    pub effect: &'a Effect,
+
+    /// TEXCOORD input set for each texcoord semantic bound by the instance,
+    /// e.g. `UVSET0`
+    pub semantics: HashMap<String, u32>,
+
+    /// TEXCOORD input set for each texture of the effect's `<profile_COMMON>`
+    /// shading, by the texture's `texture` attribute. Textures whose
+    /// `texcoord` isn't bound by the instance are left out.
+    pub texcoords: HashMap<String, u32>,
+}
+
+/// Binds materials to the material symbols of an instanced geometry or
+/// controller
+#[derive(Debug)]
+pub struct BindMaterial {
+    pub params: Vec<Param>,
+
+    /// The `<instance_material>` elements of `<technique_common>`
+    pub instance_materials: Vec<InstanceMaterial>,
+    pub techniques: Vec<Technique>,
+    pub extras: Vec<Extra>,
+    pub unknown: UnknownContent,
+}
+
+impl BindMaterial {
+    pub fn new() -> BindMaterial {
+        BindMaterial {
+            params: Vec::new(),
+            instance_materials: Vec::new(),
+            techniques: Vec::new(),
+            extras: Vec::new(),
+            unknown: UnknownContent::new(),
+        }
+    }
+
+    /// Find the instance material bound to a symbol
+    pub fn instance_material(&self, symbol: &str) -> Option<&InstanceMaterial> {
+        self.instance_materials.iter().find(|i| i.symbol == symbol)
+    }
+
+    /// Resolve the material, effect and TEXCOORD sets bound to a primitive's
+    /// material symbol. Returns `None` if the symbol is unbound or its
+    /// material or effect is not found in the libraries.
+    pub fn resolve<'a>(&'a self, symbol: &str, libraries: &'a [LibraryMaterials], effects: &'a [LibraryEffects])
+        -> Option<MaterialBinding<'a>>
+    {
+        let instance = match self.instance_material(symbol) {
+            Some(i) => i,
+            None => return None,
+        };
+        let id = match utils::url_id(&instance.target) {
+            Some(id) => id,
+            None => return None,
+        };
+        let material = match libraries.iter().filter_map(|l| l.get(id)).next() {
+            Some(m) => m,
+            None => return None,
+        };
+
+        let effect = match utils::url_id(&material.instance_effect.url)
+            .and_then(|id| effects.iter().filter_map(|l| l.get(id)).next())
+        {
+            Some(e) => e,
+            None => return None,
+        };
+
+        let mut semantics = HashMap::new();
+        for b in &instance.bind_vertex_inputs {
+            if b.input_semantic == "TEXCOORD" {
+                semantics.insert(b.semantic.clone(), b.input_set.unwrap_or(0));
+            }
+        }
+        let mut texcoords = HashMap::new();
+        if let Some(common) = effect.common() {
+            for t in common.shading.children.iter().filter_map(|p| p.get_child("texture")) {
+                let texture = t.attributes.get("texture");
+                let set = t.attributes.get("texcoord").and_then(|s| semantics.get(s));
+                if let (Some(texture), Some(set)) = (texture, set) {
+                    texcoords.insert(texture.clone(), *set);
+                }
+            }
+        }
+
+        Some(MaterialBinding {
+            instance: instance,
+            material: material,
+            effect: effect,
+            semantics: semantics,
+            texcoords: texcoords,
+        })
+    }
+}
+
+impl XmlConversion for BindMaterial {
+    fn parse_with(&mut self, e: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        ctx.unknown_attributes(e, &[], &mut self.unknown);
+        if e.get_child("technique_common").is_none() {
+            return Err(ColladaError::MissingElement{
+                structure: "bind_material".to_string(),
+                elem: "technique_common".to_string(),
+            });
+        }
+
+        for c in &e.children {
+            match c.name.as_str() {
+                "param" => {
+                    let mut p = Param::new();
+                    try!(ctx.parse(&mut p, c));
+                    self.params.push(p);
+                },
+                "technique_common" => {
+                    for i in &c.children {
+                        if i.name != "instance_material" {
+                            return Err(ColladaError::InvalidChild{
+                                child: i.name.clone(),
+                                parent: "technique_common".to_string(),
+                            });
+                        }
+                        let mut m = InstanceMaterial::new();
+                        try!(ctx.parse(&mut m, i));
+                        self.instance_materials.push(m);
+                    }
+                },
+                "technique" => {
+                    let mut t = Technique::new();
+                    try!(ctx.parse(&mut t, c));
+                    self.techniques.push(t);
+                },
+                "extra" => {
+                    let mut x = Extra::new();
+                    try!(ctx.parse(&mut x, c));
+                    self.extras.push(x);
+                },
+                _ => try!(ctx.unknown_child(e, c, &mut self.unknown)),
+            }
+        }
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut b = Element::new("bind_material");
+
+        for p in &self.params {
+            b.children.push(p.encode());
+        }
+
+        let mut tc = Element::new("technique_common");
+        for i in &self.instance_materials {
+            tc.children.push(i.encode());
+        }
+        b.children.push(tc);
+
+        for t in &self.techniques {
+            b.children.push(t.encode());
+        }
+        for x in &self.extras {
+            b.children.push(x.encode());
+        }
+        self.unknown.encode_into(&mut b);
+        b
+    }
+}
+
+impl HasTechniques for BindMaterial {
+    type Common = [InstanceMaterial];
+
+    fn technique_common(&self) -> &[InstanceMaterial] {
+        &self.instance_materials
+    }
+
+    fn techniques(&self) -> &[Technique] {
+        &self.techniques
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use xmltree::{Element};
+    use fx::{LibraryMaterials};
+    use traits::{XmlConversion};
+
+    #[test]
+    fn test_bind_material_parse() {
+        let data = r##"
+            <bind_material>
+                <param sid="alpha" type="float"/>
+                <technique_common>
+                    <instance_material symbol="WHITE" target="#white-mat">
+                        <bind semantic="ALPHA" target="node/alpha"/>
+                        <bind_vertex_input semantic="UVSET0" input_semantic="TEXCOORD" input_set="1"/>
+                    </instance_material>
+                    <instance_material symbol="BLUE" target="#blue-mat"/>
+                </technique_common>
+                <technique profile="MAX3D"><instance_material symbol="WHITE" target="#max-mat"/></technique>
+            </bind_material>"##;
+        let e = Element::parse(data.as_bytes()).unwrap();
+        let mut b = BindMaterial::new();
+        match b.parse(&e) {
+            Ok(_) => assert!(true),
+            Err(_) => assert!(false),
+        }
+        assert_eq!(b.params.len(), 1);
+        assert_eq!(b.instance_materials.len(), 2);
+        let i = &b.instance_materials[0];
+        assert_eq!(i.symbol.as_str(), "WHITE");
+        assert_eq!(i.target.as_str(), "#white-mat");
+        assert_eq!(i.binds.len(), 1);
+        assert_eq!(i.binds[0].semantic.as_str(), "ALPHA");
+        assert_eq!(i.binds[0].target.as_str(), "node/alpha");
+        assert_eq!(i.bind_vertex_inputs.len(), 1);
+        assert_eq!(i.bind_vertex_inputs[0].input_set, Some(1));
+        assert_eq!(i.texcoord_set("UVSET0"), Some(1));
+        assert_eq!(i.texcoord_set("UVSET1"), None);
+        assert_eq!(b.instance_material("BLUE").unwrap().target.as_str(), "#blue-mat");
+
+        let t = b.technique_for_any(&["FCOLLADA", "MAX3D"]).profile().unwrap();
+        assert_eq!(t.profile.as_str(), "MAX3D");
+        assert_eq!(b.technique_for("FCOLLADA").common().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_bind_material_missing_technique_common() {
+        let e = Element::parse(r##"<bind_material/>"##.as_bytes()).unwrap();
+        assert!(BindMaterial::new().parse(&e).is_err());
+    }
+
+    #[test]
+    fn test_bind_material_resolve() {
+        let data = r##"
+            <library_materials>
+                <material id="white-mat">
+                    <instance_effect url="#white-fx"/>
+                </material>
+            </library_materials>"##;
+        let e = Element::parse(data.as_bytes()).unwrap();
+        let mut l = LibraryMaterials::new();
+        l.parse(&e).unwrap();
+        let libs = vec![l];
+
+        let data = r##"
+            <library_effects>
+                <effect id="white-fx">
+                    <profile_COMMON>
+                        <technique sid="common">
+                            <lambert>
+                                <emission><texture texture="glow-sampler" texcoord="UVSET1"/></emission>
+                                <diffuse><texture texture="base-sampler" texcoord="UVSET0"/></diffuse>
+                                <transparent><texture texture="mask-sampler" texcoord="UNBOUND"/></transparent>
+                            </lambert>
+                        </technique>
+                    </profile_COMMON>
+                </effect>
+            </library_effects>"##;
+        let e = Element::parse(data.as_bytes()).unwrap();
+        let mut l = LibraryEffects::new();
+        l.parse(&e).unwrap();
+        let effects = vec![l];
+
+        let data = r##"
+            <bind_material>
+                <technique_common>
+                    <instance_material symbol="WHITE" target="#white-mat">
+                        <bind_vertex_input semantic="UVSET0" input_semantic="TEXCOORD" input_set="1"/>
+                        <bind_vertex_input semantic="UVSET1" input_semantic="TEXCOORD"/>
+                        <bind_vertex_input semantic="COL" input_semantic="COLOR"/>
+                    </instance_material>
+                    <instance_material symbol="MISSING" target="#no-such-mat"/>
+                </technique_common>
+            </bind_material>"##;
+        let e = Element::parse(data.as_bytes()).unwrap();
+        let mut b = BindMaterial::new();
+        b.parse(&e).unwrap();
+
+        let r = b.resolve("WHITE", &libs, &effects).unwrap();
+        assert_eq!(r.material.id, Some("white-mat".to_string()));
+        assert_eq!(r.effect.id, "white-fx");
+        assert_eq!(r.semantics.len(), 2);
+        assert_eq!(r.semantics.get("UVSET0"), Some(&1));
+        assert_eq!(r.semantics.get("UVSET1"), Some(&0));
+        assert_eq!(r.texcoords.len(), 2);
+        assert_eq!(r.texcoords.get("base-sampler"), Some(&1));
+        assert_eq!(r.texcoords.get("glow-sampler"), Some(&0));
+        assert!(b.resolve("WHITE", &libs, &[]).is_none());
+        assert!(b.resolve("MISSING", &libs, &effects).is_none());
+        assert!(b.resolve("RED", &libs, &effects).is_none());
+    }
+
+    #[test]
+    fn test_bind_material_encode() {
+        let mut i = InstanceMaterial::new();
+        i.symbol = "WHITE".to_string();
+        i.target = "#white-mat".to_string();
+        let mut v = BindVertexInput::new();
+        v.semantic = "UVSET0".to_string();
+        v.input_semantic = "TEXCOORD".to_string();
+        v.input_set = Some(0);
+        i.bind_vertex_inputs.push(v);
+        let mut b = BindMaterial::new();
+        b.instance_materials.push(i);
+
+        let e = b.encode();
+        assert_eq!(e.name, "bind_material");
+        assert_eq!(e.children.len(), 1);
+        let tc = &e.children[0];
+        assert_eq!(tc.name, "technique_common");
+        assert_eq!(tc.children[0].name, "instance_material");
+        assert_eq!(tc.children[0].attributes.get("symbol"), Some(&"WHITE".to_string()));
+        let v = &tc.children[0].children[0];
+        assert_eq!(v.name, "bind_vertex_input");
+        assert_eq!(v.attributes.get("input_set"), Some(&"0".to_string()));
+    }
+}
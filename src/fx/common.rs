@@ -0,0 +1,224 @@
+use xmltree::{Element};
+use fx::{Effect, EffectProfile, Image, LibraryImages};
+use traits::{XmlConversion};
+use utils;
+
+/// The fixed-function shading of a `<profile_COMMON>` effect, with colors,
+/// floats and textures looked up through the newparams of the profile and
+/// the effect
+#[derive(Debug)]
+pub struct CommonTechnique<'a> {
+    pub effect: &'a Effect,
+    pub profile: &'a Element,
+    pub technique: &'a Element,
+
+    /// `<constant>`, `<lambert>`, `<phong>` or `<blinn>`
+    pub shading: &'a Element,
+    params: Vec<Element>,
+}
+
+impl<'a> CommonTechnique<'a> {
+    /// The technique of the first `<profile_COMMON>` of an effect
+    pub fn new(effect: &'a Effect) -> Option<CommonTechnique<'a>> {
+        let profile = match effect.profiles.iter().filter_map(|p| match *p {
+            EffectProfile::Common(ref e) => Some(e),
+            _ => None,
+        }).next() {
+            Some(p) => p,
+            None => return None,
+        };
+        let technique = match profile.get_child("technique") {
+            Some(t) => t,
+            None => return None,
+        };
+        let shading = match technique.children.iter()
+            .find(|c| ["constant", "lambert", "phong", "blinn"].contains(&c.name.as_str()))
+        {
+            Some(s) => s,
+            None => return None,
+        };
+        let mut params: Vec<Element> = profile.children.iter().filter(|c| c.name == "newparam").cloned().collect();
+        params.extend(effect.newparams.iter().map(|n| n.encode()));
+        Some(CommonTechnique {
+            effect: effect,
+            profile: profile,
+            technique: technique,
+            shading: shading,
+            params: params,
+        })
+    }
+
+    /// The name of the shading, e.g. `phong`
+    pub fn kind(&self) -> &str {
+        &self.shading.name
+    }
+
+    fn param(&self, sid: &str) -> Option<&Element> {
+        self.params.iter().find(|p| p.attributes.get("sid").map(|s| s.as_str()) == Some(sid))
+    }
+
+    /// The value of a `<param ref="...">` child of a shading property
+    fn param_ref(&self, e: &Element) -> Option<&Element> {
+        e.get_child("param").and_then(|p| p.attributes.get("ref")).and_then(|r| self.param(r))
+    }
+
+    /// A color property, e.g. `diffuse`, with alpha 1 if it has only three
+    /// values
+    pub fn color(&self, name: &str) -> Option<[f32; 4]> {
+        let e = match self.shading.get_child(name) {
+            Some(e) => e,
+            None => return None,
+        };
+        let c = e.get_child("color")
+            .or_else(|| self.param_ref(e).and_then(|p| p.get_child("float4").or(p.get_child("float3"))));
+        let v: Vec<f32> = match c.map(|c| utils::parse_list(c)) {
+            Some(Ok(v)) => v,
+            _ => return None,
+        };
+        Some([
+            v.get(0).cloned().unwrap_or(0.0),
+            v.get(1).cloned().unwrap_or(0.0),
+            v.get(2).cloned().unwrap_or(0.0),
+            v.get(3).cloned().unwrap_or(1.0),
+        ])
+    }
+
+    /// A float property, e.g. `shininess`
+    pub fn float(&self, name: &str) -> Option<f32> {
+        let e = match self.shading.get_child(name) {
+            Some(e) => e,
+            None => return None,
+        };
+        e.get_child("float")
+            .or_else(|| self.param_ref(e).and_then(|p| p.get_child("float")))
+            .and_then(|f| utils::parse_text(f).ok())
+    }
+
+    /// The `texture` and `texcoord` attributes of a property's texture
+    pub fn texture(&self, name: &str) -> Option<(&str, &str)> {
+        self.shading.get_child(name)
+            .and_then(|e| e.get_child("texture"))
+            .map(|t| (
+                t.attributes.get("texture").map(|s| s.as_str()).unwrap_or(""),
+                t.attributes.get("texcoord").map(|s| s.as_str()).unwrap_or(""),
+            ))
+    }
+
+    /// The sampler a texture refers to, e.g. `<sampler2D>`
+    pub fn sampler(&self, texture: &str) -> Option<&Element> {
+        self.param(texture).and_then(|p| p.children.iter().find(|c| c.name.starts_with("sampler")))
+    }
+
+    /// The id of the image a texture samples. COLLADA 1.5 samplers instance
+    /// the image, 1.4.1 ones go through a surface and textures without a
+    /// sampler are taken as image ids.
+    pub fn image_id(&self, texture: &str) -> Option<String> {
+        let sampler = match self.sampler(texture) {
+            Some(s) => s,
+            None => return Some(texture.to_string()),
+        };
+        let instanced = sampler.get_child("instance_image")
+            .and_then(|i| i.attributes.get("url"))
+            .and_then(|u| utils::url_id(u))
+            .map(|id| id.to_string());
+        instanced.or_else(|| {
+            sampler.get_child("source")
+                .and_then(|s| s.text.as_ref())
+                .and_then(|surface| self.param(surface.trim()))
+                .and_then(|p| p.get_child("surface"))
+                .and_then(|s| s.get_child("init_from"))
+                .and_then(|i| i.text.as_ref())
+                .map(|t| t.trim().to_string())
+        })
+    }
+
+    /// The image a texture samples, from the image libraries or the images
+    /// of the effect
+    pub fn image<'b>(&self, texture: &str, libraries: &'b [LibraryImages]) -> Option<&'b Image>
+        where 'a: 'b
+    {
+        let id = match self.image_id(texture) {
+            Some(id) => id,
+            None => return None,
+        };
+        libraries.iter().filter_map(|l| l.get(&id)).next()
+            .or_else(|| self.effect.images.iter().find(|i| i.id.as_ref() == Some(&id)))
+    }
+
+    /// The opacity from `<transparent>` and `<transparency>`, where `A_ONE`
+    /// takes it from the alpha and `RGB_ZERO` from the luminance with 0
+    /// being opaque. None if the shading has no transparent color.
+    pub fn opacity(&self) -> Option<f32> {
+        let transparent = match self.shading.get_child("transparent") {
+            Some(t) => t,
+            None => return None,
+        };
+        let c = match self.color("transparent") {
+            Some(c) => c,
+            None => return None,
+        };
+        let transparency = self.float("transparency").unwrap_or(1.0);
+        let opacity = if transparent.attributes.get("opaque").map(|o| o.as_str()) == Some("RGB_ZERO") {
+            1.0 - transparency * (0.2126 * c[0] + 0.7152 * c[1] + 0.0722 * c[2])
+        } else {
+            c[3] * transparency
+        };
+        Some(opacity.max(0.0).min(1.0))
+    }
+
+    /// Whether an exporter marked the effect double sided in the extras of
+    /// the profile or technique
+    pub fn double_sided(&self) -> bool {
+        self.profile.children.iter()
+            .chain(self.technique.children.iter())
+            .filter(|c| c.name == "extra")
+            .flat_map(|x| x.children.iter())
+            .filter_map(|t| t.get_child("double_sided"))
+            .any(|d| d.text.as_ref().map(|t| t.trim() == "1" || t.trim() == "true") == Some(true))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use xmltree::{Element};
+    use fx::{Effect, LibraryImages};
+    use traits::{XmlConversion};
+
+    #[test]
+    fn test_common_technique() {
+        let data = r##"
+            <effect id="fx">
+                <image id="tex"><init_from>tex.png</init_from></image>
+                <newparam sid="tint"><float4>0.5 0.5 0.5 1</float4></newparam>
+                <profile_COMMON>
+                    <newparam sid="tex-surface">
+                        <surface type="2D"><init_from>tex</init_from></surface>
+                    </newparam>
+                    <newparam sid="tex-sampler">
+                        <sampler2D><source>tex-surface</source></sampler2D>
+                    </newparam>
+                    <technique sid="common">
+                        <blinn>
+                            <ambient><param ref="tint"/></ambient>
+                            <diffuse><texture texture="tex-sampler" texcoord="UV"/></diffuse>
+                            <shininess><float>10</float></shininess>
+                            <transparent opaque="RGB_ZERO"><color>0.5 0.5 0.5 1</color></transparent>
+                        </blinn>
+                        <extra><technique profile="MAX3D"><double_sided>1</double_sided></technique></extra>
+                    </technique>
+                </profile_COMMON>
+            </effect>"##;
+        let mut f = Effect::new();
+        f.parse(&Element::parse(data.as_bytes()).unwrap()).unwrap();
+        let c = f.common().unwrap();
+        assert_eq!(c.kind(), "blinn");
+        assert_eq!(c.color("ambient"), Some([0.5, 0.5, 0.5, 1.0]));
+        assert_eq!(c.color("diffuse"), None);
+        assert_eq!(c.float("shininess"), Some(10.0));
+        assert_eq!(c.texture("diffuse"), Some(("tex-sampler", "UV")));
+        assert_eq!(c.image_id("tex-sampler"), Some("tex".to_string()));
+        assert_eq!(c.image("tex-sampler", &[] as &[LibraryImages]).unwrap().uris(), vec!["tex.png"]);
+        assert!((c.opacity().unwrap() - 0.5).abs() < 1e-6);
+        assert!(c.double_sided());
+    }
+}
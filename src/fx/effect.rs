@@ -0,0 +1,297 @@
+use xmltree::{Element};
+use context::{ParseContext};
+use core::{Asset, Extra, UnknownContent};
+use error::{ColladaError};
+use fx::{CommonTechnique, Image, NewParam, Profile, ProfileKind};
+use traits::{XmlConversion};
+use utils;
+
+/// A profile of an effect
+#[derive(Debug)]
+pub enum EffectProfile {
+    /// `<profile_COMMON>`, kept as its XML element
+    Common(Element),
+
+    /// Any of the programmable or platform specific profiles
+    Programmable(Profile),
+}
+
+/// Describes how to render the appearance of an object, for one or more
+/// platforms
+#[derive(Debug)]
+pub struct Effect {
+    pub id: String,
+    pub name: Option<String>,
+    pub asset: Option<Asset>,
+    pub annotations: Vec<Element>,
+
+    /// COLLADA 1.4.1 images of the effect
+    pub images: Vec<Image>,
+    pub newparams: Vec<NewParam>,
+    pub profiles: Vec<EffectProfile>,
+    pub extras: Vec<Extra>,
+    pub unknown: UnknownContent,
+}
+
+impl Effect {
+    pub fn new() -> Effect {
+        Effect {
+            id: String::from(""),
+            name: None,
+            asset: None,
+            annotations: Vec::new(),
+            images: Vec::new(),
+            newparams: Vec::new(),
+            profiles: Vec::new(),
+            extras: Vec::new(),
+            unknown: UnknownContent::new(),
+        }
+    }
+
+    /// Find the first programmable profile of a kind
+    pub fn profile(&self, kind: ProfileKind) -> Option<&Profile> {
+        for p in &self.profiles {
+            if let EffectProfile::Programmable(ref x) = *p {
+                if x.kind == kind {
+                    return Some(x);
+                }
+            }
+        }
+        None
+    }
+
+    /// The shading of the first `<profile_COMMON>`, see `CommonTechnique`
+    pub fn common<'a>(&'a self) -> Option<CommonTechnique<'a>> {
+        CommonTechnique::new(self)
+    }
+}
+
+impl XmlConversion for Effect {
+    fn parse_with(&mut self, e: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        ctx.unknown_attributes(e, &["id", "name"], &mut self.unknown);
+        self.id = try!(utils::require_attr(e, "id"));
+        self.name = utils::get_attr(e, "name");
+
+        for c in &e.children {
+            match c.name.as_str() {
+                "asset" => {
+                    let mut a = Asset::new();
+                    try!(ctx.parse(&mut a, c));
+                    self.asset = Some(a);
+                },
+                "annotate" => self.annotations.push(c.clone()),
+                "image" => {
+                    let mut i = Image::new();
+                    try!(ctx.parse(&mut i, c));
+                    self.images.push(i);
+                },
+                "newparam" => {
+                    let mut n = NewParam::new();
+                    try!(ctx.parse(&mut n, c));
+                    self.newparams.push(n);
+                },
+                "profile_COMMON" => self.profiles.push(EffectProfile::Common(c.clone())),
+                "extra" => {
+                    let mut x = Extra::new();
+                    try!(ctx.parse(&mut x, c));
+                    self.extras.push(x);
+                },
+                _ => match ProfileKind::from_name(&c.name) {
+                    Some(k) => {
+                        let mut p = Profile::new(k);
+                        try!(ctx.parse(&mut p, c));
+                        self.profiles.push(EffectProfile::Programmable(p));
+                    },
+                    None => try!(ctx.unknown_child(e, c, &mut self.unknown)),
+                },
+            }
+        }
+
+        if self.profiles.is_empty() {
+            return Err(ColladaError::MissingElement{
+                structure: "effect".to_string(),
+                elem: "profile_COMMON".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut f = Element::new("effect");
+        f.attributes.insert("id".to_string(), self.id.clone());
+        utils::set_attr(&mut f, "name", &self.name);
+
+        match self.asset {
+            Some(ref x) => f.children.push(x.encode()),
+            None => {},
+        }
+        for a in &self.annotations {
+            f.children.push(a.clone());
+        }
+        for i in &self.images {
+            f.children.push(i.encode());
+        }
+        for n in &self.newparams {
+            f.children.push(n.encode());
+        }
+        for p in &self.profiles {
+            f.children.push(match *p {
+                EffectProfile::Common(ref x) => x.clone(),
+                EffectProfile::Programmable(ref x) => x.encode(),
+            });
+        }
+        for x in &self.extras {
+            f.children.push(x.encode());
+        }
+        self.unknown.encode_into(&mut f);
+        f
+    }
+}
+
+/// Library of effects
+#[derive(Debug)]
+pub struct LibraryEffects {
+    pub id: Option<String>,
+    pub name: Option<String>,
+    pub asset: Option<Asset>,
+    pub effects: Vec<Effect>,
+    pub extras: Vec<Extra>,
+    pub unknown: UnknownContent,
+}
+
+impl LibraryEffects {
+    pub fn new() -> LibraryEffects {
+        LibraryEffects {
+            id: None,
+            name: None,
+            asset: None,
+            effects: Vec::new(),
+            extras: Vec::new(),
+            unknown: UnknownContent::new(),
+        }
+    }
+
+    /// Find an effect by its id
+    pub fn get(&self, id: &str) -> Option<&Effect> {
+        self.effects.iter().find(|f| f.id == id)
+    }
+}
+
+impl XmlConversion for LibraryEffects {
+    fn parse_with(&mut self, e: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        ctx.unknown_attributes(e, &["id", "name"], &mut self.unknown);
+        self.id = utils::get_attr(e, "id");
+        self.name = utils::get_attr(e, "name");
+
+        for c in &e.children {
+            match c.name.as_str() {
+                "asset" => {
+                    let mut a = Asset::new();
+                    try!(ctx.parse(&mut a, c));
+                    self.asset = Some(a);
+                },
+                "effect" => {
+                    let mut f = Effect::new();
+                    try!(ctx.parse(&mut f, c));
+                    self.effects.push(f);
+                },
+                "extra" => {
+                    let mut x = Extra::new();
+                    try!(ctx.parse(&mut x, c));
+                    self.extras.push(x);
+                },
+                _ => try!(ctx.unknown_child(e, c, &mut self.unknown)),
+            }
+        }
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut l = Element::new("library_effects");
+        utils::set_attr(&mut l, "id", &self.id);
+        utils::set_attr(&mut l, "name", &self.name);
+
+        match self.asset {
+            Some(ref x) => l.children.push(x.encode()),
+            None => {},
+        }
+        for f in &self.effects {
+            l.children.push(f.encode());
+        }
+        for x in &self.extras {
+            l.children.push(x.encode());
+        }
+        self.unknown.encode_into(&mut l);
+        l
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use xmltree::{Element};
+    use fx::{ProfileKind};
+    use traits::{XmlConversion};
+
+    #[test]
+    fn test_library_effects_parse() {
+        let data = r##"
+            <library_effects>
+                <effect id="glass" name="Glass">
+                    <newparam sid="tint"><float3>0.9 0.9 1</float3></newparam>
+                    <profile_COMMON>
+                        <technique sid="common"><constant/></technique>
+                    </profile_COMMON>
+                    <profile_CG>
+                        <technique sid="t"><pass/></technique>
+                    </profile_CG>
+                    <profile_BRIDGE url="glass.fx"/>
+                </effect>
+            </library_effects>"##;
+        let e = Element::parse(data.as_bytes()).unwrap();
+        let mut l = LibraryEffects::new();
+        match l.parse(&e) {
+            Ok(_) => assert!(true),
+            Err(_) => assert!(false),
+        }
+        let f = l.get("glass").unwrap();
+        assert_eq!(f.name, Some("Glass".to_string()));
+        assert_eq!(f.newparams.len(), 1);
+        assert_eq!(f.profiles.len(), 3);
+        match f.profiles[0] {
+            EffectProfile::Common(ref x) => assert_eq!(x.name.as_str(), "profile_COMMON"),
+            _ => assert!(false),
+        }
+        assert!(f.profile(ProfileKind::CG).is_some());
+        assert_eq!(f.profile(ProfileKind::Bridge).unwrap().url, Some("glass.fx".to_string()));
+        assert!(f.profile(ProfileKind::GLSL).is_none());
+    }
+
+    #[test]
+    fn test_effect_parse_invalid() {
+        let e = Element::parse(r##"<effect id="a"/>"##.as_bytes()).unwrap();
+        assert!(Effect::new().parse(&e).is_err());
+
+        let e = Element::parse(r##"<effect><profile_BRIDGE url="a.fx"/></effect>"##.as_bytes()).unwrap();
+        assert!(Effect::new().parse(&e).is_err());
+
+        let e = Element::parse(r##"<effect id="a"><profile_HLSL/></effect>"##.as_bytes()).unwrap();
+        assert!(Effect::new().parse(&e).is_err());
+    }
+
+    #[test]
+    fn test_library_effects_encode() {
+        let mut f = Effect::new();
+        f.id = "glass".to_string();
+        f.profiles.push(EffectProfile::Programmable(Profile::new(ProfileKind::GLES2)));
+        let mut l = LibraryEffects::new();
+        l.effects.push(f);
+
+        let e = l.encode();
+        assert_eq!(e.name, "library_effects");
+        let f = &e.children[0];
+        assert_eq!(f.name, "effect");
+        assert_eq!(f.attributes.get("id"), Some(&"glass".to_string()));
+        assert_eq!(f.children[0].name, "profile_GLES2");
+    }
+}
@@ -0,0 +1,1170 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+use xmltree::{Element};
+use archive::{Archive};
+use context::{ParseContext};
+use core::{Asset, Extra, UnknownContent};
+use error::{ColladaError};
+use traits::{XmlConversion};
+use utils;
+
+/// Where the data of an image, or one of its sub-images, comes from
+#[derive(Debug)]
+pub enum ImageSource {
+    /// COLLADA 1.4.1 style `<init_from>` containing the URI as text
+    Uri(String),
+
+    /// COLLADA 1.5 style `<ref>` URI
+    Ref(String),
+
+    /// COLLADA 1.5 style `<hex>` embedded data with the file format, e.g. `PNG`
+    Hex{format: Option<String>, data: Vec<u8>},
+}
+
+/// A face of a cube map
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum CubeFace {
+    PositiveX,
+    NegativeX,
+    PositiveY,
+    NegativeY,
+    PositiveZ,
+    NegativeZ,
+}
+
+impl fmt::Display for CubeFace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &CubeFace::PositiveX => write!(f, "POSITIVE_X"),
+            &CubeFace::NegativeX => write!(f, "NEGATIVE_X"),
+            &CubeFace::PositiveY => write!(f, "POSITIVE_Y"),
+            &CubeFace::NegativeY => write!(f, "NEGATIVE_Y"),
+            &CubeFace::PositiveZ => write!(f, "POSITIVE_Z"),
+            &CubeFace::NegativeZ => write!(f, "NEGATIVE_Z"),
+        }
+    }
+}
+
+/// Initializes an image, or a sub-image of a created image, from a URI or
+/// embedded data
+#[derive(Debug)]
+pub struct InitFrom {
+    /// Whether the mips should be generated after loading (1.5 image only)
+    pub mips_generate: Option<bool>,
+    pub array_index: Option<u32>,
+    pub mip_index: Option<u32>,
+
+    /// Depth slice of a 3D image
+    pub depth: Option<u32>,
+
+    /// Face of a cube image
+    pub face: Option<CubeFace>,
+    pub source: ImageSource,
+}
+
+impl InitFrom {
+    pub fn new() -> InitFrom {
+        InitFrom {
+            mips_generate: None,
+            array_index: None,
+            mip_index: None,
+            depth: None,
+            face: None,
+            source: ImageSource::Uri(String::from("")),
+        }
+    }
+
+    /// URI of the data, if it isn't embedded
+    pub fn uri(&self) -> Option<&str> {
+        match self.source {
+            ImageSource::Uri(ref u) | ImageSource::Ref(ref u) => Some(u.as_str()),
+            ImageSource::Hex{..} => None,
+        }
+    }
+}
+
+impl XmlConversion for InitFrom {
+    fn parse_with(&mut self, e: &Element, _ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        self.mips_generate = match e.attributes.get("mips_generate") {
+            Some(m) => match utils::parse_bool(m) {
+                Some(b) => Some(b),
+                None => return Err(ColladaError::InvalidAttrData{
+                    elem: "init_from".to_string(),
+                    attr: "mips_generate".to_string(),
+                    data: m.clone(),
+                }),
+            },
+            None => None,
+        };
+        self.array_index = try!(utils::parse_attr(e, "array_index"));
+        self.mip_index = try!(utils::parse_attr(e, "mip_index"));
+        self.depth = try!(utils::parse_attr(e, "depth"));
+        self.face = match e.attributes.get("face") {
+            Some(f) => Some(try!(parse_face(f))),
+            None => None,
+        };
+
+        if e.children.is_empty() {
+            let t = try!(utils::require_text(e));
+            self.source = ImageSource::Uri(t.trim().to_string());
+            return Ok(());
+        }
+
+        for c in &e.children {
+            match c.name.as_str() {
+                "ref" => {
+                    let t = try!(utils::require_text(c));
+                    self.source = ImageSource::Ref(t.trim().to_string());
+                },
+                "hex" => {
+                    self.source = ImageSource::Hex{
+                        format: utils::get_attr(c, "format"),
+                        data: try!(utils::parse_hex(c)),
+                    };
+                },
+                _ => return Err(ColladaError::InvalidChild{
+                    child: c.name.clone(),
+                    parent: "init_from".to_string(),
+                }),
+            }
+        }
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut i = Element::new("init_from");
+        if let Some(m) = self.mips_generate {
+            i.attributes.insert("mips_generate".to_string(), m.to_string());
+        }
+        if let Some(x) = self.array_index {
+            i.attributes.insert("array_index".to_string(), x.to_string());
+        }
+        if let Some(x) = self.mip_index {
+            i.attributes.insert("mip_index".to_string(), x.to_string());
+        }
+        if let Some(x) = self.depth {
+            i.attributes.insert("depth".to_string(), x.to_string());
+        }
+        if let Some(x) = self.face {
+            i.attributes.insert("face".to_string(), x.to_string());
+        }
+
+        match self.source {
+            ImageSource::Uri(ref u) => i.text = Some(u.clone()),
+            ImageSource::Ref(ref u) => i.children.push(utils::text_element("ref", u.clone())),
+            ImageSource::Hex{ref format, ref data} => {
+                let mut h = utils::text_element("hex", utils::hex_string(data));
+                utils::set_attr(&mut h, "format", format);
+                i.children.push(h);
+            },
+        }
+        i
+    }
+}
+
+fn parse_face(s: &str) -> Result<CubeFace, ColladaError> {
+    match s {
+        "POSITIVE_X" => Ok(CubeFace::PositiveX),
+        "NEGATIVE_X" => Ok(CubeFace::NegativeX),
+        "POSITIVE_Y" => Ok(CubeFace::PositiveY),
+        "NEGATIVE_Y" => Ok(CubeFace::NegativeY),
+        "POSITIVE_Z" => Ok(CubeFace::PositiveZ),
+        "NEGATIVE_Z" => Ok(CubeFace::NegativeZ),
+        _ => Err(ColladaError::InvalidAttrData{
+            elem: "init_from".to_string(),
+            attr: "face".to_string(),
+            data: s.to_string(),
+        }),
+    }
+}
+
+/// Number of mip levels of a created image
+#[derive(Debug)]
+pub struct Mips {
+    /// Number of levels, 0 means all levels down to 1x1
+    pub levels: u32,
+    pub auto_generate: bool,
+}
+
+impl Mips {
+    pub fn new() -> Mips {
+        Mips {
+            levels: 0,
+            auto_generate: true,
+        }
+    }
+
+    fn parse(e: &Element) -> Result<Mips, ColladaError> {
+        let levels = match try!(utils::parse_attr(e, "levels")) {
+            Some(l) => l,
+            None => return Err(ColladaError::MissingAttr{
+                elem: "mips".to_string(),
+                attr: "levels".to_string(),
+            }),
+        };
+        let auto = try!(utils::require_attr(e, "auto_generate"));
+        let auto_generate = match utils::parse_bool(&auto) {
+            Some(b) => b,
+            None => return Err(ColladaError::InvalidAttrData{
+                elem: "mips".to_string(),
+                attr: "auto_generate".to_string(),
+                data: auto,
+            }),
+        };
+        Ok(Mips {
+            levels: levels,
+            auto_generate: auto_generate,
+        })
+    }
+
+    fn encode(&self) -> Element {
+        let mut m = Element::new("mips");
+        m.attributes.insert("levels".to_string(), self.levels.to_string());
+        m.attributes.insert("auto_generate".to_string(), self.auto_generate.to_string());
+        m
+    }
+}
+
+/// Channels stored in an image format
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum FormatChannels {
+    RGB,
+    RGBA,
+    RGBE,
+    L,
+    LA,
+    D,
+}
+
+impl fmt::Display for FormatChannels {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &FormatChannels::RGB => write!(f, "RGB"),
+            &FormatChannels::RGBA => write!(f, "RGBA"),
+            &FormatChannels::RGBE => write!(f, "RGBE"),
+            &FormatChannels::L => write!(f, "L"),
+            &FormatChannels::LA => write!(f, "LA"),
+            &FormatChannels::D => write!(f, "D"),
+        }
+    }
+}
+
+/// Range of the values stored in an image format
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum FormatRange {
+    SNorm,
+    UNorm,
+    SInt,
+    UInt,
+    Float,
+}
+
+impl fmt::Display for FormatRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &FormatRange::SNorm => write!(f, "SNORM"),
+            &FormatRange::UNorm => write!(f, "UNORM"),
+            &FormatRange::SInt => write!(f, "SINT"),
+            &FormatRange::UInt => write!(f, "UINT"),
+            &FormatRange::Float => write!(f, "FLOAT"),
+        }
+    }
+}
+
+/// Precision of the values stored in an image format
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum FormatPrecision {
+    Default,
+    Low,
+    Mid,
+    High,
+    Max,
+}
+
+impl fmt::Display for FormatPrecision {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &FormatPrecision::Default => write!(f, "DEFAULT"),
+            &FormatPrecision::Low => write!(f, "LOW"),
+            &FormatPrecision::Mid => write!(f, "MID"),
+            &FormatPrecision::High => write!(f, "HIGH"),
+            &FormatPrecision::Max => write!(f, "MAX"),
+        }
+    }
+}
+
+/// Description of a format that the platform may pick a close match for
+#[derive(Debug)]
+pub struct FormatHint {
+    pub channels: FormatChannels,
+    pub range: FormatRange,
+    pub precision: Option<FormatPrecision>,
+
+    /// Color space, e.g. `sRGB`
+    pub space: Option<String>,
+}
+
+/// Format of a created image
+#[derive(Debug)]
+pub struct ImageFormat {
+    pub hint: Option<FormatHint>,
+
+    /// Exact platform specific format name, e.g. `R8G8B8A8`
+    pub exact: Option<String>,
+}
+
+impl ImageFormat {
+    pub fn new() -> ImageFormat {
+        ImageFormat {
+            hint: None,
+            exact: None,
+        }
+    }
+}
+
+impl XmlConversion for ImageFormat {
+    fn parse_with(&mut self, e: &Element, _ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        for c in &e.children {
+            match c.name.as_str() {
+                "hint" => {
+                    let channels = try!(utils::require_attr(c, "channels"));
+                    let range = try!(utils::require_attr(c, "range"));
+                    let invalid = |attr: &str, data: &str| ColladaError::InvalidAttrData{
+                        elem: "hint".to_string(),
+                        attr: attr.to_string(),
+                        data: data.to_string(),
+                    };
+                    let channels = match channels.as_str() {
+                        "RGB" => FormatChannels::RGB,
+                        "RGBA" => FormatChannels::RGBA,
+                        "RGBE" => FormatChannels::RGBE,
+                        "L" => FormatChannels::L,
+                        "LA" => FormatChannels::LA,
+                        "D" => FormatChannels::D,
+                        _ => return Err(invalid("channels", &channels)),
+                    };
+                    let range = match range.as_str() {
+                        "SNORM" => FormatRange::SNorm,
+                        "UNORM" => FormatRange::UNorm,
+                        "SINT" => FormatRange::SInt,
+                        "UINT" => FormatRange::UInt,
+                        "FLOAT" => FormatRange::Float,
+                        _ => return Err(invalid("range", &range)),
+                    };
+                    let precision = match c.attributes.get("precision") {
+                        Some(p) => Some(match p.as_str() {
+                            "DEFAULT" => FormatPrecision::Default,
+                            "LOW" => FormatPrecision::Low,
+                            "MID" => FormatPrecision::Mid,
+                            "HIGH" => FormatPrecision::High,
+                            "MAX" => FormatPrecision::Max,
+                            _ => return Err(invalid("precision", p)),
+                        }),
+                        None => None,
+                    };
+                    self.hint = Some(FormatHint {
+                        channels: channels,
+                        range: range,
+                        precision: precision,
+                        space: utils::get_attr(c, "space"),
+                    });
+                },
+                "exact" => self.exact = Some(try!(utils::require_text(c)).trim().to_string()),
+                _ => return Err(ColladaError::InvalidChild{
+                    child: c.name.clone(),
+                    parent: "format".to_string(),
+                }),
+            }
+        }
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut f = Element::new("format");
+        if let Some(ref h) = self.hint {
+            let mut e = Element::new("hint");
+            e.attributes.insert("channels".to_string(), h.channels.to_string());
+            e.attributes.insert("range".to_string(), h.range.to_string());
+            if let Some(p) = h.precision {
+                e.attributes.insert("precision".to_string(), p.to_string());
+            }
+            utils::set_attr(&mut e, "space", &h.space);
+            f.children.push(e);
+        }
+        if let Some(ref x) = self.exact {
+            f.children.push(utils::text_element("exact", x.clone()));
+        }
+        f
+    }
+}
+
+/// Size of a created 2D image
+#[derive(Debug)]
+pub enum Size2D {
+    /// Exact size in pixels
+    Exact{width: u32, height: u32},
+
+    /// Size relative to the size of the viewport
+    Ratio{width: f32, height: f32},
+}
+
+/// Creates a 2D image, optionally initialized from other images
+#[derive(Debug)]
+pub struct Create2D {
+    pub size: Size2D,
+    pub mips: Option<Mips>,
+
+    /// Whether texel addressing uses unnormalized coordinates, mutually
+    /// exclusive with `mips`
+    pub unnormalized: bool,
+    pub array_length: Option<u32>,
+    pub format: Option<ImageFormat>,
+    pub init_from: Vec<InitFrom>,
+}
+
+/// Creates a 3D image, optionally initialized from other images
+#[derive(Debug)]
+pub struct Create3D {
+    pub width: u32,
+    pub height: u32,
+    pub depth: u32,
+    pub mips: Mips,
+    pub array_length: Option<u32>,
+    pub format: Option<ImageFormat>,
+    pub init_from: Vec<InitFrom>,
+}
+
+/// Creates a cube image, optionally initialized from other images
+#[derive(Debug)]
+pub struct CreateCube {
+    /// Width of each face
+    pub width: u32,
+    pub mips: Mips,
+    pub array_length: Option<u32>,
+    pub format: Option<ImageFormat>,
+    pub init_from: Vec<InitFrom>,
+}
+
+/// Parsed children shared by the `<create_*>` elements
+struct CreateContent {
+    size: Option<Element>,
+    mips: Option<Mips>,
+    unnormalized: bool,
+    array_length: Option<u32>,
+    format: Option<ImageFormat>,
+    init_from: Vec<InitFrom>,
+}
+
+fn parse_create(e: &Element, ctx: &mut ParseContext) -> Result<CreateContent, ColladaError> {
+    let mut content = CreateContent {
+        size: None,
+        mips: None,
+        unnormalized: false,
+        array_length: None,
+        format: None,
+        init_from: Vec::new(),
+    };
+
+    for c in &e.children {
+        match c.name.as_str() {
+            "size" | "size_exact" | "size_ratio" => content.size = Some(c.clone()),
+            "mips" => content.mips = Some(try!(Mips::parse(c))),
+            "unnormalized" => content.unnormalized = true,
+            "array" => {
+                content.array_length = match try!(utils::parse_attr(c, "length")) {
+                    Some(l) => Some(l),
+                    None => return Err(ColladaError::MissingAttr{
+                        elem: "array".to_string(),
+                        attr: "length".to_string(),
+                    }),
+                };
+            },
+            "format" => {
+                let mut f = ImageFormat::new();
+                try!(ctx.parse(&mut f, c));
+                content.format = Some(f);
+            },
+            "init_from" => {
+                let mut i = InitFrom::new();
+                try!(ctx.parse(&mut i, c));
+                content.init_from.push(i);
+            },
+            _ => return Err(ColladaError::InvalidChild{
+                child: c.name.clone(),
+                parent: e.name.clone(),
+            }),
+        }
+    }
+    Ok(content)
+}
+
+fn require_size_attr<T: ::std::str::FromStr>(e: &Element, attr: &str) -> Result<T, ColladaError> {
+    match try!(utils::parse_attr(e, attr)) {
+        Some(x) => Ok(x),
+        None => Err(ColladaError::MissingAttr{
+            elem: e.name.clone(),
+            attr: attr.to_string(),
+        }),
+    }
+}
+
+fn require_size(content: &CreateContent, parent: &str, elem: &str) -> Result<Element, ColladaError> {
+    match content.size {
+        Some(ref s) => Ok(s.clone()),
+        None => Err(ColladaError::MissingElement{
+            structure: parent.to_string(),
+            elem: elem.to_string(),
+        }),
+    }
+}
+
+fn require_mips(content: &mut CreateContent, parent: &str) -> Result<Mips, ColladaError> {
+    match content.mips.take() {
+        Some(m) => Ok(m),
+        None => Err(ColladaError::MissingElement{
+            structure: parent.to_string(),
+            elem: "mips".to_string(),
+        }),
+    }
+}
+
+fn encode_create_tail(e: &mut Element, array_length: &Option<u32>,
+                      format: &Option<ImageFormat>, init_from: &Vec<InitFrom>) {
+    if let Some(l) = *array_length {
+        let mut a = Element::new("array");
+        a.attributes.insert("length".to_string(), l.to_string());
+        e.children.push(a);
+    }
+    if let Some(ref f) = *format {
+        e.children.push(f.encode());
+    }
+    for i in init_from {
+        e.children.push(i.encode());
+    }
+}
+
+impl Create2D {
+    fn parse(e: &Element, ctx: &mut ParseContext) -> Result<Create2D, ColladaError> {
+        let content = try!(parse_create(e, ctx));
+        let s = try!(require_size(&content, "create_2d", "size_exact"));
+        let size = match s.name.as_str() {
+            "size_exact" => Size2D::Exact{
+                width: try!(require_size_attr(&s, "width")),
+                height: try!(require_size_attr(&s, "height")),
+            },
+            "size_ratio" => Size2D::Ratio{
+                width: try!(require_size_attr(&s, "width")),
+                height: try!(require_size_attr(&s, "height")),
+            },
+            _ => return Err(ColladaError::InvalidChild{
+                child: s.name.clone(),
+                parent: "create_2d".to_string(),
+            }),
+        };
+        Ok(Create2D {
+            size: size,
+            mips: content.mips,
+            unnormalized: content.unnormalized,
+            array_length: content.array_length,
+            format: content.format,
+            init_from: content.init_from,
+        })
+    }
+
+    fn encode(&self) -> Element {
+        let mut c = Element::new("create_2d");
+        match self.size {
+            Size2D::Exact{width, height} => {
+                let mut s = Element::new("size_exact");
+                s.attributes.insert("width".to_string(), width.to_string());
+                s.attributes.insert("height".to_string(), height.to_string());
+                c.children.push(s);
+            },
+            Size2D::Ratio{width, height} => {
+                let mut s = Element::new("size_ratio");
+                s.attributes.insert("width".to_string(), width.to_string());
+                s.attributes.insert("height".to_string(), height.to_string());
+                c.children.push(s);
+            },
+        }
+        if let Some(ref m) = self.mips {
+            c.children.push(m.encode());
+        }
+        if self.unnormalized {
+            c.children.push(Element::new("unnormalized"));
+        }
+        encode_create_tail(&mut c, &self.array_length, &self.format, &self.init_from);
+        c
+    }
+}
+
+impl Create3D {
+    fn parse(e: &Element, ctx: &mut ParseContext) -> Result<Create3D, ColladaError> {
+        let mut content = try!(parse_create(e, ctx));
+        let s = try!(require_size(&content, "create_3d", "size"));
+        Ok(Create3D {
+            width: try!(require_size_attr(&s, "width")),
+            height: try!(require_size_attr(&s, "height")),
+            depth: try!(require_size_attr(&s, "depth")),
+            mips: try!(require_mips(&mut content, "create_3d")),
+            array_length: content.array_length,
+            format: content.format,
+            init_from: content.init_from,
+        })
+    }
+
+    fn encode(&self) -> Element {
+        let mut c = Element::new("create_3d");
+        let mut s = Element::new("size");
+        s.attributes.insert("width".to_string(), self.width.to_string());
+        s.attributes.insert("height".to_string(), self.height.to_string());
+        s.attributes.insert("depth".to_string(), self.depth.to_string());
+        c.children.push(s);
+        c.children.push(self.mips.encode());
+        encode_create_tail(&mut c, &self.array_length, &self.format, &self.init_from);
+        c
+    }
+}
+
+impl CreateCube {
+    fn parse(e: &Element, ctx: &mut ParseContext) -> Result<CreateCube, ColladaError> {
+        let mut content = try!(parse_create(e, ctx));
+        let s = try!(require_size(&content, "create_cube", "size"));
+        Ok(CreateCube {
+            width: try!(require_size_attr(&s, "width")),
+            mips: try!(require_mips(&mut content, "create_cube")),
+            array_length: content.array_length,
+            format: content.format,
+            init_from: content.init_from,
+        })
+    }
+
+    fn encode(&self) -> Element {
+        let mut c = Element::new("create_cube");
+        let mut s = Element::new("size");
+        s.attributes.insert("width".to_string(), self.width.to_string());
+        c.children.push(s);
+        c.children.push(self.mips.encode());
+        encode_create_tail(&mut c, &self.array_length, &self.format, &self.init_from);
+        c
+    }
+}
+
+/// How the data of an image is defined
+#[derive(Debug)]
+pub enum ImageData {
+    /// COLLADA 1.4.1 `<data>` embedded hex data
+    Data(Vec<u8>),
+    InitFrom(InitFrom),
+    Create2D(Create2D),
+    Create3D(Create3D),
+    CreateCube(CreateCube),
+}
+
+/// An image used by effects, either loaded from a URI, embedded or created
+/// by the platform
+#[derive(Debug)]
+pub struct Image {
+    pub id: Option<String>,
+    pub sid: Option<String>,
+    pub name: Option<String>,
+
+    /// COLLADA 1.4.1 file format of the image, e.g. `PNG`
+    pub format: Option<String>,
+
+    /// COLLADA 1.4.1 dimensions of the image
+    pub height: Option<u32>,
+    pub width: Option<u32>,
+    pub depth: Option<u32>,
+    pub asset: Option<Asset>,
+
+    /// Whether the image can be rendered to, and whether it is shared
+    /// (COLLADA 1.5 `<renderable share="...">`)
+    pub renderable: Option<bool>,
+    pub data: ImageData,
+    pub extras: Vec<Extra>,
+    pub unknown: UnknownContent,
+}
+
+impl Image {
+    pub fn new() -> Image {
+        Image {
+            id: None,
+            sid: None,
+            name: None,
+            format: None,
+            height: None,
+            width: None,
+            depth: None,
+            asset: None,
+            renderable: None,
+            data: ImageData::InitFrom(InitFrom::new()),
+            extras: Vec::new(),
+            unknown: UnknownContent::new(),
+        }
+    }
+
+    /// URIs of all the external files the image is initialized from
+    pub fn uris(&self) -> Vec<&str> {
+        let init_from = match self.data {
+            ImageData::Data(_) => return Vec::new(),
+            ImageData::InitFrom(ref i) => return i.uri().into_iter().collect(),
+            ImageData::Create2D(ref c) => &c.init_from,
+            ImageData::Create3D(ref c) => &c.init_from,
+            ImageData::CreateCube(ref c) => &c.init_from,
+        };
+        init_from.iter().filter_map(|i| i.uri()).collect()
+    }
+
+    /// Paths of all the external files the image is initialized from, resolved
+    /// against the directory of the document. Non-file URIs are skipped.
+    pub fn resolve_paths(&self, base_dir: &Path) -> Vec<PathBuf> {
+        self.uris().iter().filter_map(|u| resolve_uri(base_dir, u)).collect()
+    }
+
+    /// Data of all the external files the image is initialized from, resolved
+    /// against the path of the document in an archive. URIs outside of the
+    /// archive are skipped.
+    pub fn resolve_archive<'a>(&self, archive: &'a Archive, doc_path: &str) -> Vec<&'a [u8]> {
+        self.uris().iter().filter_map(|u| archive.resolve(doc_path, u)).collect()
+    }
+}
+
+impl XmlConversion for Image {
+    fn parse_with(&mut self, e: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        ctx.unknown_attributes(e, &["id", "sid", "name", "format", "height", "width", "depth"], &mut self.unknown);
+        self.id = utils::get_attr(e, "id");
+        self.sid = utils::get_attr(e, "sid");
+        self.name = utils::get_attr(e, "name");
+        self.format = utils::get_attr(e, "format");
+        self.height = try!(utils::parse_attr(e, "height"));
+        self.width = try!(utils::parse_attr(e, "width"));
+        self.depth = try!(utils::parse_attr(e, "depth"));
+
+        let mut data = None;
+        for c in &e.children {
+            match c.name.as_str() {
+                "asset" => {
+                    let mut a = Asset::new();
+                    try!(ctx.parse(&mut a, c));
+                    self.asset = Some(a);
+                },
+                "renderable" => {
+                    let share = try!(utils::require_attr(c, "share"));
+                    self.renderable = match utils::parse_bool(&share) {
+                        Some(b) => Some(b),
+                        None => return Err(ColladaError::InvalidAttrData{
+                            elem: "renderable".to_string(),
+                            attr: "share".to_string(),
+                            data: share,
+                        }),
+                    };
+                },
+                "data" => data = Some(ImageData::Data(try!(utils::parse_hex(c)))),
+                "init_from" => {
+                    let mut i = InitFrom::new();
+                    try!(ctx.parse(&mut i, c));
+                    data = Some(ImageData::InitFrom(i));
+                },
+                "create_2d" => data = Some(ImageData::Create2D(try!(Create2D::parse(c, ctx)))),
+                "create_3d" => data = Some(ImageData::Create3D(try!(Create3D::parse(c, ctx)))),
+                "create_cube" => data = Some(ImageData::CreateCube(try!(CreateCube::parse(c, ctx)))),
+                "extra" => {
+                    let mut x = Extra::new();
+                    try!(ctx.parse(&mut x, c));
+                    self.extras.push(x);
+                },
+                _ => try!(ctx.unknown_child(e, c, &mut self.unknown)),
+            }
+        }
+
+        self.data = match data {
+            Some(d) => d,
+            None => return Err(ColladaError::MissingElement{
+                structure: "image".to_string(),
+                elem: "init_from".to_string(),
+            }),
+        };
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut i = Element::new("image");
+        utils::set_attr(&mut i, "id", &self.id);
+        utils::set_attr(&mut i, "sid", &self.sid);
+        utils::set_attr(&mut i, "name", &self.name);
+        utils::set_attr(&mut i, "format", &self.format);
+        if let Some(x) = self.height {
+            i.attributes.insert("height".to_string(), x.to_string());
+        }
+        if let Some(x) = self.width {
+            i.attributes.insert("width".to_string(), x.to_string());
+        }
+        if let Some(x) = self.depth {
+            i.attributes.insert("depth".to_string(), x.to_string());
+        }
+
+        match self.asset {
+            Some(ref x) => i.children.push(x.encode()),
+            None => {},
+        }
+        if let Some(share) = self.renderable {
+            let mut r = Element::new("renderable");
+            r.attributes.insert("share".to_string(), share.to_string());
+            i.children.push(r);
+        }
+        i.children.push(match self.data {
+            ImageData::Data(ref d) => utils::text_element("data", utils::hex_string(d)),
+            ImageData::InitFrom(ref x) => x.encode(),
+            ImageData::Create2D(ref x) => x.encode(),
+            ImageData::Create3D(ref x) => x.encode(),
+            ImageData::CreateCube(ref x) => x.encode(),
+        });
+        for x in &self.extras {
+            i.children.push(x.encode());
+        }
+        self.unknown.encode_into(&mut i);
+        i
+    }
+}
+
+/// Library of images
+#[derive(Debug)]
+pub struct LibraryImages {
+    pub id: Option<String>,
+    pub name: Option<String>,
+    pub asset: Option<Asset>,
+    pub images: Vec<Image>,
+    pub extras: Vec<Extra>,
+    pub unknown: UnknownContent,
+}
+
+impl LibraryImages {
+    pub fn new() -> LibraryImages {
+        LibraryImages {
+            id: None,
+            name: None,
+            asset: None,
+            images: Vec::new(),
+            extras: Vec::new(),
+            unknown: UnknownContent::new(),
+        }
+    }
+
+    /// Find an image by its id
+    pub fn get(&self, id: &str) -> Option<&Image> {
+        self.images.iter().find(|i| i.id.as_ref().map(|x| x.as_str()) == Some(id))
+    }
+}
+
+impl XmlConversion for LibraryImages {
+    fn parse_with(&mut self, e: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        ctx.unknown_attributes(e, &["id", "name"], &mut self.unknown);
+        self.id = utils::get_attr(e, "id");
+        self.name = utils::get_attr(e, "name");
+
+        for c in &e.children {
+            match c.name.as_str() {
+                "asset" => {
+                    let mut a = Asset::new();
+                    try!(ctx.parse(&mut a, c));
+                    self.asset = Some(a);
+                },
+                "image" => {
+                    let mut i = Image::new();
+                    try!(ctx.parse(&mut i, c));
+                    self.images.push(i);
+                },
+                "extra" => {
+                    let mut x = Extra::new();
+                    try!(ctx.parse(&mut x, c));
+                    self.extras.push(x);
+                },
+                _ => try!(ctx.unknown_child(e, c, &mut self.unknown)),
+            }
+        }
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut l = Element::new("library_images");
+        utils::set_attr(&mut l, "id", &self.id);
+        utils::set_attr(&mut l, "name", &self.name);
+
+        match self.asset {
+            Some(ref x) => l.children.push(x.encode()),
+            None => {},
+        }
+        for i in &self.images {
+            l.children.push(i.encode());
+        }
+        for x in &self.extras {
+            l.children.push(x.encode());
+        }
+        self.unknown.encode_into(&mut l);
+        l
+    }
+}
+
+/// Resolve an image URI to a file path. Relative references are resolved
+/// against the directory of the document, `file:` URIs are converted to
+/// paths and any other scheme, e.g. `http:`, gives `None`.
+pub fn resolve_uri(base_dir: &Path, uri: &str) -> Option<PathBuf> {
+    let uri = uri.trim();
+    let path = if uri.starts_with("file://") {
+        // file:///C:/foo.png and file:///home/foo.png have an empty host
+        let rest = &uri[7..];
+        if rest.len() > 2 && rest.starts_with('/') && rest.as_bytes()[2] == b':' {
+            utils::percent_decode(&rest[1..])
+        } else {
+            utils::percent_decode(rest)
+        }
+    } else if uri.starts_with("file:") {
+        utils::percent_decode(&uri[5..])
+    } else if utils::has_scheme(uri) {
+        return None;
+    } else {
+        utils::percent_decode(uri)
+    };
+
+    let p = PathBuf::from(&path);
+    if p.is_absolute() || is_drive_path(&path) {
+        Some(p)
+    } else {
+        Some(base_dir.join(p))
+    }
+}
+
+/// Whether a path starts with a windows drive letter, e.g. `C:/foo.png`
+fn is_drive_path(path: &str) -> bool {
+    let b = path.as_bytes();
+    b.len() > 1 && (b[0] as char).is_ascii_alphabetic() && b[1] == b':'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::{Path, PathBuf};
+    use xmltree::{Element};
+    use traits::{XmlConversion};
+
+    #[test]
+    fn test_image_parse_141() {
+        let data = r##"
+            <image id="tex" name="Texture" format="PNG" width="256" height="128">
+                <init_from>./textures/brick%20wall.png</init_from>
+            </image>"##;
+        let e = Element::parse(data.as_bytes()).unwrap();
+        let mut i = Image::new();
+        match i.parse(&e) {
+            Ok(_) => assert!(true),
+            Err(_) => assert!(false),
+        }
+        assert_eq!(i.id, Some("tex".to_string()));
+        assert_eq!(i.format, Some("PNG".to_string()));
+        assert_eq!(i.width, Some(256));
+        assert_eq!(i.height, Some(128));
+        assert_eq!(i.depth, None);
+        match i.data {
+            ImageData::InitFrom(ref x) => match x.source {
+                ImageSource::Uri(ref u) => assert_eq!(u.as_str(), "./textures/brick%20wall.png"),
+                _ => assert!(false),
+            },
+            _ => assert!(false),
+        }
+        assert_eq!(i.resolve_paths(Path::new("/models")),
+                   vec![PathBuf::from("/models/./textures/brick wall.png")]);
+
+        let data = r##"<image id="embedded"><data>FF 00 7F</data></image>"##;
+        let e = Element::parse(data.as_bytes()).unwrap();
+        let mut i = Image::new();
+        i.parse(&e).unwrap();
+        match i.data {
+            ImageData::Data(ref d) => assert_eq!(d, &vec![0xFF, 0x00, 0x7F]),
+            _ => assert!(false),
+        }
+        assert!(i.uris().is_empty());
+    }
+
+    #[test]
+    fn test_image_parse_15() {
+        let data = r##"
+            <library_images>
+                <image id="file" sid="f">
+                    <renderable share="true"/>
+                    <init_from mips_generate="true">
+                        <ref>file:///textures/wood.png</ref>
+                    </init_from>
+                </image>
+                <image id="hex">
+                    <init_from>
+                        <hex format="PNG">0A1b</hex>
+                    </init_from>
+                </image>
+                <image id="rt">
+                    <create_2d>
+                        <size_ratio width="0.5" height="0.5"/>
+                        <unnormalized/>
+                        <format>
+                            <hint channels="RGBA" range="UNORM" precision="HIGH" space="sRGB"/>
+                            <exact>R8G8B8A8</exact>
+                        </format>
+                    </create_2d>
+                </image>
+                <image id="vol">
+                    <create_3d>
+                        <size width="8" height="8" depth="4"/>
+                        <mips levels="0" auto_generate="false"/>
+                        <init_from depth="1" mip_index="0"><ref>slice1.png</ref></init_from>
+                    </create_3d>
+                </image>
+                <image id="sky">
+                    <create_cube>
+                        <size width="512"/>
+                        <mips levels="1" auto_generate="false"/>
+                        <array length="2"/>
+                        <init_from face="NEGATIVE_Z" array_index="1"><ref>sky_nz.png</ref></init_from>
+                    </create_cube>
+                </image>
+            </library_images>"##;
+        let e = Element::parse(data.as_bytes()).unwrap();
+        let mut l = LibraryImages::new();
+        l.parse(&e).unwrap();
+        assert_eq!(l.images.len(), 5);
+
+        let f = l.get("file").unwrap();
+        assert_eq!(f.renderable, Some(true));
+        match f.data {
+            ImageData::InitFrom(ref x) => {
+                assert_eq!(x.mips_generate, Some(true));
+                assert_eq!(x.uri(), Some("file:///textures/wood.png"));
+            },
+            _ => assert!(false),
+        }
+        assert_eq!(f.resolve_paths(Path::new("/models")), vec![PathBuf::from("/textures/wood.png")]);
+
+        match l.get("hex").unwrap().data {
+            ImageData::InitFrom(InitFrom{source: ImageSource::Hex{ref format, ref data}, ..}) => {
+                assert_eq!(format, &Some("PNG".to_string()));
+                assert_eq!(data, &vec![0x0A, 0x1B]);
+            },
+            _ => assert!(false),
+        }
+
+        match l.get("rt").unwrap().data {
+            ImageData::Create2D(ref c) => {
+                match c.size {
+                    Size2D::Ratio{width, height} => {
+                        assert_eq!(width, 0.5);
+                        assert_eq!(height, 0.5);
+                    },
+                    _ => assert!(false),
+                }
+                assert!(c.unnormalized);
+                assert!(c.mips.is_none());
+                let f = c.format.as_ref().unwrap();
+                let h = f.hint.as_ref().unwrap();
+                assert_eq!(h.channels, FormatChannels::RGBA);
+                assert_eq!(h.range, FormatRange::UNorm);
+                assert_eq!(h.precision, Some(FormatPrecision::High));
+                assert_eq!(h.space, Some("sRGB".to_string()));
+                assert_eq!(f.exact, Some("R8G8B8A8".to_string()));
+            },
+            _ => assert!(false),
+        }
+
+        match l.get("vol").unwrap().data {
+            ImageData::Create3D(ref c) => {
+                assert_eq!((c.width, c.height, c.depth), (8, 8, 4));
+                assert_eq!(c.mips.levels, 0);
+                assert!(!c.mips.auto_generate);
+                assert_eq!(c.init_from[0].depth, Some(1));
+                assert_eq!(c.init_from[0].mip_index, Some(0));
+            },
+            _ => assert!(false),
+        }
+
+        let sky = l.get("sky").unwrap();
+        match sky.data {
+            ImageData::CreateCube(ref c) => {
+                assert_eq!(c.width, 512);
+                assert_eq!(c.array_length, Some(2));
+                assert_eq!(c.init_from[0].face, Some(CubeFace::NegativeZ));
+                assert_eq!(c.init_from[0].array_index, Some(1));
+            },
+            _ => assert!(false),
+        }
+        assert_eq!(sky.uris(), vec!["sky_nz.png"]);
+    }
+
+    #[test]
+    fn test_image_parse_invalid() {
+        let e = Element::parse(r##"<image id="a"/>"##.as_bytes()).unwrap();
+        assert!(Image::new().parse(&e).is_err());
+
+        let e = Element::parse(r##"<image id="a" width="wide"><init_from>a.png</init_from></image>"##.as_bytes()).unwrap();
+        assert!(Image::new().parse(&e).is_err());
+
+        let e = Element::parse(r##"<image><create_cube><size width="4"/></create_cube></image>"##.as_bytes()).unwrap();
+        assert!(Image::new().parse(&e).is_err());
+
+        let e = Element::parse(r##"<image><init_from><hex format="PNG">ABC</hex></init_from></image>"##.as_bytes()).unwrap();
+        assert!(Image::new().parse(&e).is_err());
+    }
+
+    #[test]
+    fn test_image_encode() {
+        let mut i = Image::new();
+        i.id = Some("tex".to_string());
+        i.format = Some("PNG".to_string());
+        i.width = Some(64);
+        i.data = ImageData::InitFrom(InitFrom {
+            mips_generate: None,
+            array_index: None,
+            mip_index: None,
+            depth: None,
+            face: None,
+            source: ImageSource::Uri("tex.png".to_string()),
+        });
+        let e = i.encode();
+        assert_eq!(e.name, "image");
+        assert_eq!(e.attributes.get("format"), Some(&"PNG".to_string()));
+        assert_eq!(e.attributes.get("width"), Some(&"64".to_string()));
+        assert_eq!(e.children.len(), 1);
+        assert_eq!(e.children[0].name, "init_from");
+        assert_eq!(e.children[0].text, Some("tex.png".to_string()));
+
+        let mut c = InitFrom::new();
+        c.face = Some(CubeFace::PositiveY);
+        c.source = ImageSource::Hex{format: Some("DDS".to_string()), data: vec![1, 171]};
+        i.data = ImageData::CreateCube(CreateCube {
+            width: 16,
+            mips: Mips::new(),
+            array_length: None,
+            format: None,
+            init_from: vec![c],
+        });
+        let e = i.encode();
+        let cube = &e.children[0];
+        assert_eq!(cube.name, "create_cube");
+        assert_eq!(cube.children[0].name, "size");
+        assert_eq!(cube.children[0].attributes.get("width"), Some(&"16".to_string()));
+        assert_eq!(cube.children[1].name, "mips");
+        assert_eq!(cube.children[1].attributes.get("auto_generate"), Some(&"true".to_string()));
+        let init = &cube.children[2];
+        assert_eq!(init.attributes.get("face"), Some(&"POSITIVE_Y".to_string()));
+        assert_eq!(init.children[0].name, "hex");
+        assert_eq!(init.children[0].text, Some("01AB".to_string()));
+    }
+
+    #[test]
+    fn test_resolve_uri() {
+        let base = Path::new("/data/models");
+        assert_eq!(resolve_uri(base, "tex.png"), Some(PathBuf::from("/data/models/tex.png")));
+        assert_eq!(resolve_uri(base, "../tex.png"), Some(PathBuf::from("/data/models/../tex.png")));
+        assert_eq!(resolve_uri(base, "/abs/tex.png"), Some(PathBuf::from("/abs/tex.png")));
+        assert_eq!(resolve_uri(base, "file:///abs/my%20tex.png"), Some(PathBuf::from("/abs/my tex.png")));
+        assert_eq!(resolve_uri(base, "file:///C:/tex.png"), Some(PathBuf::from("C:/tex.png")));
+        assert_eq!(resolve_uri(base, "http://example.com/tex.png"), None);
+    }
+}
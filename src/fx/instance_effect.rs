@@ -0,0 +1,171 @@
+use xmltree::{Element};
+use context::{ParseContext};
+use core::{Extra, UnknownContent};
+use error::{ColladaError};
+use fx::{SetParam};
+use traits::{XmlConversion};
+use utils;
+
+/// Hint for which effect technique to use on a given platform or profile
+#[derive(Debug)]
+pub struct TechniqueHint {
+    pub platform: Option<String>,
+    pub profile: Option<String>,
+
+    /// Sid of the technique to use
+    pub reference: String,
+}
+
+impl TechniqueHint {
+    pub fn new() -> TechniqueHint {
+        TechniqueHint {
+            platform: None,
+            profile: None,
+            reference: String::from(""),
+        }
+    }
+}
+
+impl XmlConversion for TechniqueHint {
+    fn parse_with(&mut self, e: &Element, _ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        self.platform = utils::get_attr(e, "platform");
+        self.profile = utils::get_attr(e, "profile");
+        self.reference = try!(utils::require_attr(e, "ref"));
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut t = Element::new("technique_hint");
+        utils::set_attr(&mut t, "platform", &self.platform);
+        utils::set_attr(&mut t, "profile", &self.profile);
+        t.attributes.insert("ref".to_string(), self.reference.clone());
+        t
+    }
+}
+
+/// Instantiates an effect, optionally overriding some of its parameters
+#[derive(Debug)]
+pub struct InstanceEffect {
+    pub sid: Option<String>,
+    pub name: Option<String>,
+
+    /// Url of the effect to instantiate, e.g. `#phong-fx`
+    pub url: String,
+    pub technique_hints: Vec<TechniqueHint>,
+    pub setparams: Vec<SetParam>,
+    pub extras: Vec<Extra>,
+    pub unknown: UnknownContent,
+}
+
+impl InstanceEffect {
+    pub fn new() -> InstanceEffect {
+        InstanceEffect {
+            sid: None,
+            name: None,
+            url: String::from(""),
+            technique_hints: Vec::new(),
+            setparams: Vec::new(),
+            extras: Vec::new(),
+            unknown: UnknownContent::new(),
+        }
+    }
+}
+
+impl XmlConversion for InstanceEffect {
+    fn parse_with(&mut self, e: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        ctx.unknown_attributes(e, &["sid", "name", "url"], &mut self.unknown);
+        self.sid = utils::get_attr(e, "sid");
+        self.name = utils::get_attr(e, "name");
+        self.url = try!(utils::require_attr(e, "url"));
+
+        for c in &e.children {
+            match c.name.as_str() {
+                "technique_hint" => {
+                    let mut t = TechniqueHint::new();
+                    try!(ctx.parse(&mut t, c));
+                    self.technique_hints.push(t);
+                },
+                "setparam" => {
+                    let mut s = SetParam::new();
+                    try!(ctx.parse(&mut s, c));
+                    self.setparams.push(s);
+                },
+                "extra" => {
+                    let mut x = Extra::new();
+                    try!(ctx.parse(&mut x, c));
+                    self.extras.push(x);
+                },
+                _ => try!(ctx.unknown_child(e, c, &mut self.unknown)),
+            }
+        }
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut i = Element::new("instance_effect");
+        utils::set_attr(&mut i, "sid", &self.sid);
+        utils::set_attr(&mut i, "name", &self.name);
+        i.attributes.insert("url".to_string(), self.url.clone());
+
+        for t in &self.technique_hints {
+            i.children.push(t.encode());
+        }
+        for s in &self.setparams {
+            i.children.push(s.encode());
+        }
+        for x in &self.extras {
+            i.children.push(x.encode());
+        }
+        self.unknown.encode_into(&mut i);
+        i
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use xmltree::{Element};
+    use traits::{XmlConversion};
+
+    #[test]
+    fn test_instance_effect_parse() {
+        let data = r##"
+            <instance_effect url="#phong-fx" sid="fx">
+                <technique_hint platform="PC-OGL" profile="GLSL" ref="glsl-tech"/>
+                <setparam ref="shininess">
+                    <float>20.0</float>
+                </setparam>
+            </instance_effect>"##;
+        let e = Element::parse(data.as_bytes()).unwrap();
+        let mut i = InstanceEffect::new();
+        match i.parse(&e) {
+            Ok(_) => assert!(true),
+            Err(_) => assert!(false),
+        }
+        assert_eq!(i.url.as_str(), "#phong-fx");
+        assert_eq!(i.sid, Some("fx".to_string()));
+        assert_eq!(i.technique_hints.len(), 1);
+        assert_eq!(i.technique_hints[0].platform, Some("PC-OGL".to_string()));
+        assert_eq!(i.technique_hints[0].profile, Some("GLSL".to_string()));
+        assert_eq!(i.technique_hints[0].reference.as_str(), "glsl-tech");
+        assert_eq!(i.setparams.len(), 1);
+        assert_eq!(i.setparams[0].reference.as_str(), "shininess");
+    }
+
+    #[test]
+    fn test_instance_effect_encode() {
+        let mut i = InstanceEffect::new();
+        i.url = "#phong-fx".to_string();
+        let mut t = TechniqueHint::new();
+        t.reference = "glsl-tech".to_string();
+        i.technique_hints.push(t);
+
+        let e = i.encode();
+        assert_eq!(e.name, "instance_effect");
+        assert_eq!(e.attributes.get("url"), Some(&"#phong-fx".to_string()));
+        assert_eq!(e.children.len(), 1);
+        assert_eq!(e.children[0].name, "technique_hint");
+        assert_eq!(e.children[0].attributes.get("ref"), Some(&"glsl-tech".to_string()));
+        assert_eq!(e.children[0].attributes.get("platform"), None);
+    }
+}
@@ -0,0 +1,221 @@
+use xmltree::{Element};
+use context::{ParseContext};
+use core::{Asset, Extra, UnknownContent};
+use error::{ColladaError};
+use fx::{InstanceEffect};
+use traits::{XmlConversion};
+use utils;
+
+/// Describes the visual appearance of a geometric object by instantiating
+/// an effect
+#[derive(Debug)]
+pub struct Material {
+    pub id: Option<String>,
+    pub name: Option<String>,
+    pub asset: Option<Asset>,
+    pub instance_effect: InstanceEffect,
+    pub extras: Vec<Extra>,
+    pub unknown: UnknownContent,
+}
+
+impl Material {
+    pub fn new() -> Material {
+        Material {
+            id: None,
+            name: None,
+            asset: None,
+            instance_effect: InstanceEffect::new(),
+            extras: Vec::new(),
+            unknown: UnknownContent::new(),
+        }
+    }
+}
+
+impl XmlConversion for Material {
+    fn parse_with(&mut self, e: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        ctx.unknown_attributes(e, &["id", "name"], &mut self.unknown);
+        self.id = utils::get_attr(e, "id");
+        self.name = utils::get_attr(e, "name");
+
+        if e.get_child("instance_effect").is_none() {
+            return Err(ColladaError::MissingElement{
+                structure: "material".to_string(),
+                elem: "instance_effect".to_string(),
+            });
+        }
+
+        for c in &e.children {
+            match c.name.as_str() {
+                "asset" => {
+                    let mut a = Asset::new();
+                    try!(ctx.parse(&mut a, c));
+                    self.asset = Some(a);
+                },
+                "instance_effect" => try!(ctx.parse(&mut self.instance_effect, c)),
+                "extra" => {
+                    let mut x = Extra::new();
+                    try!(ctx.parse(&mut x, c));
+                    self.extras.push(x);
+                },
+                _ => try!(ctx.unknown_child(e, c, &mut self.unknown)),
+            }
+        }
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut m = Element::new("material");
+        utils::set_attr(&mut m, "id", &self.id);
+        utils::set_attr(&mut m, "name", &self.name);
+
+        match self.asset {
+            Some(ref x) => m.children.push(x.encode()),
+            None => {},
+        }
+        m.children.push(self.instance_effect.encode());
+        for x in &self.extras {
+            m.children.push(x.encode());
+        }
+        self.unknown.encode_into(&mut m);
+        m
+    }
+}
+
+/// Library of materials
+#[derive(Debug)]
+pub struct LibraryMaterials {
+    pub id: Option<String>,
+    pub name: Option<String>,
+    pub asset: Option<Asset>,
+    pub materials: Vec<Material>,
+    pub extras: Vec<Extra>,
+    pub unknown: UnknownContent,
+}
+
+impl LibraryMaterials {
+    pub fn new() -> LibraryMaterials {
+        LibraryMaterials {
+            id: None,
+            name: None,
+            asset: None,
+            materials: Vec::new(),
+            extras: Vec::new(),
+            unknown: UnknownContent::new(),
+        }
+    }
+
+    /// Find a material by its id
+    pub fn get(&self, id: &str) -> Option<&Material> {
+        self.materials.iter().find(|m| m.id.as_ref().map(|x| x.as_str()) == Some(id))
+    }
+}
+
+impl XmlConversion for LibraryMaterials {
+    fn parse_with(&mut self, e: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        ctx.unknown_attributes(e, &["id", "name"], &mut self.unknown);
+        self.id = utils::get_attr(e, "id");
+        self.name = utils::get_attr(e, "name");
+
+        for c in &e.children {
+            match c.name.as_str() {
+                "asset" => {
+                    let mut a = Asset::new();
+                    try!(ctx.parse(&mut a, c));
+                    self.asset = Some(a);
+                },
+                "material" => {
+                    let mut m = Material::new();
+                    try!(ctx.parse(&mut m, c));
+                    self.materials.push(m);
+                },
+                "extra" => {
+                    let mut x = Extra::new();
+                    try!(ctx.parse(&mut x, c));
+                    self.extras.push(x);
+                },
+                _ => try!(ctx.unknown_child(e, c, &mut self.unknown)),
+            }
+        }
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut l = Element::new("library_materials");
+        utils::set_attr(&mut l, "id", &self.id);
+        utils::set_attr(&mut l, "name", &self.name);
+
+        match self.asset {
+            Some(ref x) => l.children.push(x.encode()),
+            None => {},
+        }
+        for m in &self.materials {
+            l.children.push(m.encode());
+        }
+        for x in &self.extras {
+            l.children.push(x.encode());
+        }
+        self.unknown.encode_into(&mut l);
+        l
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use xmltree::{Element};
+    use traits::{XmlConversion};
+
+    #[test]
+    fn test_library_materials_parse() {
+        let data = r##"
+            <library_materials id="mats">
+                <material id="blue" name="Blue">
+                    <instance_effect url="#phong-fx">
+                        <setparam ref="diffuse">
+                            <float3>0 0 1</float3>
+                        </setparam>
+                    </instance_effect>
+                </material>
+                <material id="red">
+                    <instance_effect url="#lambert-fx"/>
+                </material>
+            </library_materials>"##;
+        let e = Element::parse(data.as_bytes()).unwrap();
+        let mut l = LibraryMaterials::new();
+        match l.parse(&e) {
+            Ok(_) => assert!(true),
+            Err(_) => assert!(false),
+        }
+        assert_eq!(l.id, Some("mats".to_string()));
+        assert_eq!(l.materials.len(), 2);
+        assert_eq!(l.materials[0].name, Some("Blue".to_string()));
+        assert_eq!(l.materials[0].instance_effect.url.as_str(), "#phong-fx");
+        assert_eq!(l.materials[0].instance_effect.setparams.len(), 1);
+        assert_eq!(l.get("red").unwrap().instance_effect.url.as_str(), "#lambert-fx");
+        assert!(l.get("green").is_none());
+    }
+
+    #[test]
+    fn test_material_missing_effect() {
+        let e = Element::parse(r##"<material id="blue"/>"##.as_bytes()).unwrap();
+        assert!(Material::new().parse(&e).is_err());
+    }
+
+    #[test]
+    fn test_library_materials_encode() {
+        let mut m = Material::new();
+        m.id = Some("blue".to_string());
+        m.instance_effect.url = "#phong-fx".to_string();
+        let mut l = LibraryMaterials::new();
+        l.materials.push(m);
+
+        let e = l.encode();
+        assert_eq!(e.name, "library_materials");
+        assert_eq!(e.children.len(), 1);
+        let m = &e.children[0];
+        assert_eq!(m.name, "material");
+        assert_eq!(m.attributes.get("id"), Some(&"blue".to_string()));
+        assert_eq!(m.children[0].name, "instance_effect");
+        assert_eq!(m.children[0].attributes.get("url"), Some(&"#phong-fx".to_string()));
+    }
+}
@@ -0,0 +1,17 @@
+mod bind_material;
+mod common;
+mod effect;
+mod image;
+mod instance_effect;
+mod material;
+mod param;
+mod profile;
+
+pub use self::bind_material::*;
+pub use self::common::*;
+pub use self::effect::*;
+pub use self::image::*;
+pub use self::instance_effect::*;
+pub use self::material::*;
+pub use self::param::*;
+pub use self::profile::*;
@@ -0,0 +1,843 @@
+use xmltree::{Element};
+use context::{ParseContext};
+use core::{Extra, UnknownContent};
+use error::{ColladaError};
+use fx::{Image, LibraryImages};
+use traits::{XmlConversion};
+use utils;
+
+/// The kind of texture a sampler samples from
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SamplerType {
+    Sampler1D,
+    Sampler2D,
+    Sampler3D,
+    SamplerCube,
+    SamplerRect,
+    SamplerDepth,
+}
+
+impl SamplerType {
+    fn from_name(name: &str) -> Option<SamplerType> {
+        match name {
+            "sampler1D" => Some(SamplerType::Sampler1D),
+            "sampler2D" => Some(SamplerType::Sampler2D),
+            "sampler3D" => Some(SamplerType::Sampler3D),
+            "samplerCUBE" => Some(SamplerType::SamplerCube),
+            "samplerRECT" => Some(SamplerType::SamplerRect),
+            "samplerDEPTH" => Some(SamplerType::SamplerDepth),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match *self {
+            SamplerType::Sampler1D => "sampler1D",
+            SamplerType::Sampler2D => "sampler2D",
+            SamplerType::Sampler3D => "sampler3D",
+            SamplerType::SamplerCube => "samplerCUBE",
+            SamplerType::SamplerRect => "samplerRECT",
+            SamplerType::SamplerDepth => "samplerDEPTH",
+        }
+    }
+}
+
+/// Declares how a texture is sampled
+#[derive(Debug)]
+pub struct Sampler {
+    pub typ: SamplerType,
+
+    /// COLLADA 1.4.1 sid of the `<surface>` parameter to sample
+    pub source: Option<String>,
+
+    /// COLLADA 1.5 url of the image to sample
+    pub instance_image: Option<String>,
+    pub wrap_s: Option<String>,
+    pub wrap_t: Option<String>,
+    pub wrap_p: Option<String>,
+    pub minfilter: Option<String>,
+    pub magfilter: Option<String>,
+    pub mipfilter: Option<String>,
+    pub border_color: Option<Vec<f32>>,
+
+    /// Remaining sampler states, e.g. `<mipmap_bias>`, in document order
+    pub states: Vec<Element>,
+    pub extras: Vec<Extra>,
+    pub unknown: UnknownContent,
+}
+
+impl Sampler {
+    pub fn new(typ: SamplerType) -> Sampler {
+        Sampler {
+            typ: typ,
+            source: None,
+            instance_image: None,
+            wrap_s: None,
+            wrap_t: None,
+            wrap_p: None,
+            minfilter: None,
+            magfilter: None,
+            mipfilter: None,
+            border_color: None,
+            states: Vec::new(),
+            extras: Vec::new(),
+            unknown: UnknownContent::new(),
+        }
+    }
+}
+
+impl XmlConversion for Sampler {
+    fn parse_with(&mut self, e: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        ctx.unknown_attributes(e, &[], &mut self.unknown);
+        for c in &e.children {
+            match c.name.as_str() {
+                "source" => self.source = Some(try!(utils::require_text(c)).trim().to_string()),
+                "instance_image" => self.instance_image = Some(try!(utils::require_attr(c, "url"))),
+                "wrap_s" => self.wrap_s = Some(try!(utils::require_text(c)).trim().to_string()),
+                "wrap_t" => self.wrap_t = Some(try!(utils::require_text(c)).trim().to_string()),
+                "wrap_p" => self.wrap_p = Some(try!(utils::require_text(c)).trim().to_string()),
+                "minfilter" => self.minfilter = Some(try!(utils::require_text(c)).trim().to_string()),
+                "magfilter" => self.magfilter = Some(try!(utils::require_text(c)).trim().to_string()),
+                "mipfilter" => self.mipfilter = Some(try!(utils::require_text(c)).trim().to_string()),
+                "border_color" => self.border_color = Some(try!(utils::parse_array(c, 4))),
+                "extra" => {
+                    let mut x = Extra::new();
+                    try!(ctx.parse(&mut x, c));
+                    self.extras.push(x);
+                },
+                _ => self.states.push(c.clone()),
+            }
+        }
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut s = Element::new(self.typ.name());
+        if let Some(ref x) = self.source {
+            s.children.push(utils::text_element("source", x.clone()));
+        }
+        if let Some(ref x) = self.instance_image {
+            let mut i = Element::new("instance_image");
+            i.attributes.insert("url".to_string(), x.clone());
+            s.children.push(i);
+        }
+        let states = [("wrap_s", &self.wrap_s), ("wrap_t", &self.wrap_t), ("wrap_p", &self.wrap_p),
+                      ("minfilter", &self.minfilter), ("magfilter", &self.magfilter),
+                      ("mipfilter", &self.mipfilter)];
+        for &(name, value) in states.iter() {
+            if let Some(ref x) = *value {
+                s.children.push(utils::text_element(name, x.clone()));
+            }
+        }
+        if let Some(ref x) = self.border_color {
+            s.children.push(utils::list_element("border_color", x));
+        }
+        for x in &self.states {
+            s.children.push(x.clone());
+        }
+        for x in &self.extras {
+            s.children.push(x.encode());
+        }
+        self.unknown.encode_into(&mut s);
+        s
+    }
+}
+
+/// Initializes a surface from an image (COLLADA 1.4.1)
+#[derive(Debug)]
+pub struct SurfaceInitFrom {
+    /// Id of the image
+    pub image: String,
+    pub mip: Option<u32>,
+    pub slice: Option<u32>,
+    pub face: Option<String>,
+}
+
+/// A COLLADA 1.4.1 surface, the intermediate between a sampler and an image
+#[derive(Debug)]
+pub struct Surface {
+    /// Surface type, e.g. `2D` or `CUBE`
+    pub typ: String,
+    pub init_from: Vec<SurfaceInitFrom>,
+    pub format: Option<String>,
+
+    /// Remaining surface content, e.g. `<format_hint>` or `<mip_levels>`,
+    /// in document order
+    pub content: Vec<Element>,
+    pub extras: Vec<Extra>,
+    pub unknown: UnknownContent,
+}
+
+impl Surface {
+    pub fn new() -> Surface {
+        Surface {
+            typ: String::from("2D"),
+            init_from: Vec::new(),
+            format: None,
+            content: Vec::new(),
+            extras: Vec::new(),
+            unknown: UnknownContent::new(),
+        }
+    }
+}
+
+impl XmlConversion for Surface {
+    fn parse_with(&mut self, e: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        ctx.unknown_attributes(e, &["type"], &mut self.unknown);
+        self.typ = try!(utils::require_attr(e, "type"));
+
+        for c in &e.children {
+            match c.name.as_str() {
+                "init_from" => self.init_from.push(SurfaceInitFrom {
+                    image: try!(utils::require_text(c)).trim().to_string(),
+                    mip: try!(utils::parse_attr(c, "mip")),
+                    slice: try!(utils::parse_attr(c, "slice")),
+                    face: utils::get_attr(c, "face"),
+                }),
+                "format" => self.format = Some(try!(utils::require_text(c)).trim().to_string()),
+                "extra" => {
+                    let mut x = Extra::new();
+                    try!(ctx.parse(&mut x, c));
+                    self.extras.push(x);
+                },
+                _ => self.content.push(c.clone()),
+            }
+        }
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut s = Element::new("surface");
+        s.attributes.insert("type".to_string(), self.typ.clone());
+
+        for i in &self.init_from {
+            let mut e = utils::text_element("init_from", i.image.clone());
+            if let Some(x) = i.mip {
+                e.attributes.insert("mip".to_string(), x.to_string());
+            }
+            if let Some(x) = i.slice {
+                e.attributes.insert("slice".to_string(), x.to_string());
+            }
+            utils::set_attr(&mut e, "face", &i.face);
+            s.children.push(e);
+        }
+        if let Some(ref x) = self.format {
+            s.children.push(utils::text_element("format", x.clone()));
+        }
+        for x in &self.content {
+            s.children.push(x.clone());
+        }
+        for x in &self.extras {
+            s.children.push(x.encode());
+        }
+        self.unknown.encode_into(&mut s);
+        s
+    }
+}
+
+/// The typed value of a parameter
+#[derive(Debug)]
+pub enum ParamValue {
+    Bool(bool),
+    Int(i32),
+    Float(f32),
+    Float2([f32; 2]),
+    Float3([f32; 3]),
+    Float4([f32; 4]),
+
+    /// Matrices are stored in row-major order
+    Float2x2([f32; 4]),
+    Float3x3([f32; 9]),
+    Float4x4([f32; 16]),
+    Sampler(Sampler),
+    Surface(Surface),
+
+    /// Reference to the value of another parameter
+    Param(String),
+
+    /// Scoped identifier of an element, e.g. `kscene/kmodel/joint0/axis0`
+    SidRef(String),
+
+    /// Any other value type, e.g. `<int3>` or `<string>`
+    Other(Element),
+}
+
+impl ParamValue {
+    /// Parse a value element, e.g. `<float3>1 0 0</float3>`
+    pub fn parse(e: &Element, ctx: &mut ParseContext) -> Result<ParamValue, ColladaError> {
+        if let Some(typ) = SamplerType::from_name(&e.name) {
+            let mut s = Sampler::new(typ);
+            try!(ctx.parse(&mut s, e));
+            return Ok(ParamValue::Sampler(s));
+        }
+
+        Ok(match e.name.as_str() {
+            "bool" => {
+                let t = try!(utils::require_text(e));
+                match utils::parse_bool(&t) {
+                    Some(b) => ParamValue::Bool(b),
+                    None => return Err(ColladaError::InvalidData{
+                        elem: "bool".to_string(),
+                        data: t,
+                    }),
+                }
+            },
+            "int" => ParamValue::Int(try!(utils::parse_text(e))),
+            "float" => ParamValue::Float(try!(utils::parse_text(e))),
+            "float2" => {
+                let v = try!(utils::parse_array(e, 2));
+                ParamValue::Float2([v[0], v[1]])
+            },
+            "float3" => {
+                let v = try!(utils::parse_array(e, 3));
+                ParamValue::Float3([v[0], v[1], v[2]])
+            },
+            "float4" => {
+                let v = try!(utils::parse_array(e, 4));
+                ParamValue::Float4([v[0], v[1], v[2], v[3]])
+            },
+            "float2x2" => {
+                let v = try!(utils::parse_array(e, 4));
+                ParamValue::Float2x2([v[0], v[1], v[2], v[3]])
+            },
+            "float3x3" => {
+                let v = try!(utils::parse_array::<f32>(e, 9));
+                let mut m = [0.0; 9];
+                m.copy_from_slice(&v);
+                ParamValue::Float3x3(m)
+            },
+            "float4x4" => {
+                let v = try!(utils::parse_array::<f32>(e, 16));
+                let mut m = [0.0; 16];
+                m.copy_from_slice(&v);
+                ParamValue::Float4x4(m)
+            },
+            "surface" => {
+                let mut s = Surface::new();
+                try!(ctx.parse(&mut s, e));
+                ParamValue::Surface(s)
+            },
+            "SIDREF" => ParamValue::SidRef(try!(utils::require_text(e)).trim().to_string()),
+            "param" => match e.attributes.get("ref") {
+                Some(r) => ParamValue::Param(r.clone()),
+                None => ParamValue::Param(try!(utils::require_text(e)).trim().to_string()),
+            },
+            _ => ParamValue::Other(e.clone()),
+        })
+    }
+
+    pub fn encode(&self) -> Element {
+        match *self {
+            ParamValue::Bool(x) => utils::text_element("bool", x.to_string()),
+            ParamValue::Int(x) => utils::text_element("int", x.to_string()),
+            ParamValue::Float(x) => utils::text_element("float", x.to_string()),
+            ParamValue::Float2(ref x) => utils::list_element("float2", x),
+            ParamValue::Float3(ref x) => utils::list_element("float3", x),
+            ParamValue::Float4(ref x) => utils::list_element("float4", x),
+            ParamValue::Float2x2(ref x) => utils::list_element("float2x2", x),
+            ParamValue::Float3x3(ref x) => utils::list_element("float3x3", x),
+            ParamValue::Float4x4(ref x) => utils::list_element("float4x4", x),
+            ParamValue::Sampler(ref x) => x.encode(),
+            ParamValue::Surface(ref x) => x.encode(),
+            ParamValue::SidRef(ref x) => utils::text_element("SIDREF", x.clone()),
+            ParamValue::Param(ref x) => {
+                let mut p = Element::new("param");
+                p.attributes.insert("ref".to_string(), x.clone());
+                p
+            },
+            ParamValue::Other(ref x) => x.clone(),
+        }
+    }
+
+    /// Parse the value wrapped by an element, e.g. `<min><float>0</float></min>`
+    pub fn parse_wrapped(e: &Element, ctx: &mut ParseContext) -> Result<ParamValue, ColladaError> {
+        match e.children.first() {
+            Some(v) => ParamValue::parse(v, ctx),
+            None => Err(ColladaError::MissingElement{
+                structure: e.name.clone(),
+                elem: "param".to_string(),
+            }),
+        }
+    }
+
+    /// Encode the value wrapped by an element named `name`
+    pub fn encode_wrapped(&self, name: &str) -> Element {
+        let mut w = Element::new(name);
+        w.children.push(self.encode());
+        w
+    }
+
+    /// The value as a float, if it is numeric
+    pub fn as_float(&self) -> Option<f32> {
+        match *self {
+            ParamValue::Float(x) => Some(x),
+            ParamValue::Int(x) => Some(x as f32),
+            _ => None,
+        }
+    }
+}
+
+/// Creates a new named parameter in the scope of its parent element
+#[derive(Debug)]
+pub struct NewParam {
+    pub sid: String,
+
+    /// The `<annotate>` elements describing the parameter for tools
+    pub annotations: Vec<Element>,
+    pub semantic: Option<String>,
+    pub modifier: Option<String>,
+    pub value: ParamValue,
+}
+
+impl NewParam {
+    pub fn new() -> NewParam {
+        NewParam {
+            sid: String::from(""),
+            annotations: Vec::new(),
+            semantic: None,
+            modifier: None,
+            value: ParamValue::Float(0.0),
+        }
+    }
+}
+
+impl XmlConversion for NewParam {
+    fn parse_with(&mut self, e: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        self.sid = try!(utils::require_attr(e, "sid"));
+
+        let mut value = None;
+        for c in &e.children {
+            match c.name.as_str() {
+                "annotate" => self.annotations.push(c.clone()),
+                "semantic" => self.semantic = Some(try!(utils::require_text(c)).trim().to_string()),
+                "modifier" => self.modifier = Some(try!(utils::require_text(c)).trim().to_string()),
+                _ => value = Some(try!(ParamValue::parse(c, ctx))),
+            }
+        }
+
+        self.value = match value {
+            Some(v) => v,
+            None => return Err(ColladaError::MissingElement{
+                structure: "newparam".to_string(),
+                elem: "value".to_string(),
+            }),
+        };
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut n = Element::new("newparam");
+        n.attributes.insert("sid".to_string(), self.sid.clone());
+        for a in &self.annotations {
+            n.children.push(a.clone());
+        }
+        if let Some(ref x) = self.semantic {
+            n.children.push(utils::text_element("semantic", x.clone()));
+        }
+        if let Some(ref x) = self.modifier {
+            n.children.push(utils::text_element("modifier", x.clone()));
+        }
+        n.children.push(self.value.encode());
+        n
+    }
+}
+
+/// Assigns a new value to a previously defined parameter
+#[derive(Debug)]
+pub struct SetParam {
+    /// Identifier of the parameter whose value is set
+    pub reference: String,
+    pub value: ParamValue,
+}
+
+impl SetParam {
+    pub fn new() -> SetParam {
+        SetParam {
+            reference: String::from(""),
+            value: ParamValue::Float(0.0),
+        }
+    }
+}
+
+impl XmlConversion for SetParam {
+    fn parse_with(&mut self, e: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        self.reference = try!(utils::require_attr(e, "ref"));
+        self.value = match e.children.iter().filter(|c| c.name != "annotate").last() {
+            Some(v) => try!(ParamValue::parse(v, ctx)),
+            None => return Err(ColladaError::MissingElement{
+                structure: "setparam".to_string(),
+                elem: "value".to_string(),
+            }),
+        };
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut s = Element::new("setparam");
+        s.attributes.insert("ref".to_string(), self.reference.clone());
+        s.children.push(self.value.encode());
+        s
+    }
+}
+
+/// Nested parameter scopes used to resolve parameter references. Overrides
+/// from `<setparam>` take precedence, then the scopes are searched from the
+/// innermost, e.g. a technique, to the outermost, e.g. the effect.
+pub struct ParamScope<'a> {
+    overrides: Vec<&'a SetParam>,
+    scopes: Vec<&'a [NewParam]>,
+}
+
+impl<'a> ParamScope<'a> {
+    pub fn new() -> ParamScope<'a> {
+        ParamScope {
+            overrides: Vec::new(),
+            scopes: Vec::new(),
+        }
+    }
+
+    /// Add `<setparam>` overrides, e.g. from an `<instance_effect>`
+    pub fn with_overrides(mut self, setparams: &'a [SetParam]) -> ParamScope<'a> {
+        self.overrides.extend(setparams.iter());
+        self
+    }
+
+    /// Add a scope that is outside of all the scopes added so far
+    pub fn with_scope(mut self, params: &'a [NewParam]) -> ParamScope<'a> {
+        self.scopes.push(params);
+        self
+    }
+
+    /// Find the value of a parameter, following parameter references
+    pub fn lookup(&self, sid: &str) -> Option<&'a ParamValue> {
+        let mut sid = sid;
+        // Each step follows a reference, so more steps than parameters means
+        // the references form a cycle
+        let limit = self.overrides.len() + self.scopes.iter().map(|s| s.len()).sum::<usize>();
+        for _ in 0..limit + 1 {
+            let value = match self.lookup_direct(sid) {
+                Some(v) => v,
+                None => return None,
+            };
+            match *value {
+                ParamValue::Param(ref r) => sid = r.as_str(),
+                _ => return Some(value),
+            }
+        }
+        None
+    }
+
+    /// Resolve a value, following it if it references a parameter
+    pub fn resolve(&self, value: &'a ParamValue) -> Option<&'a ParamValue> {
+        match *value {
+            ParamValue::Param(ref r) => self.lookup(r),
+            _ => Some(value),
+        }
+    }
+
+    fn lookup_direct(&self, sid: &str) -> Option<&'a ParamValue> {
+        if let Some(s) = self.overrides.iter().rev().find(|s| s.reference == sid) {
+            return Some(&s.value);
+        }
+        for scope in &self.scopes {
+            if let Some(p) = scope.iter().find(|p| p.sid == sid) {
+                return Some(&p.value);
+            }
+        }
+        None
+    }
+
+    /// Resolve the `texture` attribute of a `<texture>` element to an image.
+    /// The texture names a sampler, which uses either an `<instance_image>`
+    /// (1.5) or a `<source>` surface initialized from an image (1.4.1). As
+    /// some exporters write the image id directly, an unknown parameter is
+    /// looked up as an image id.
+    pub fn resolve_texture(&self, texture: &str, libraries: &'a [LibraryImages])
+        -> Option<&'a Image>
+    {
+        let sampler = match self.lookup(texture) {
+            Some(&ParamValue::Sampler(ref s)) => s,
+            Some(_) => return None,
+            None => return find_image(texture, libraries),
+        };
+
+        if let Some(ref url) = sampler.instance_image {
+            return match utils::url_id(url) {
+                Some(id) => find_image(id, libraries),
+                None => None,
+            };
+        }
+
+        let surface = match sampler.source {
+            Some(ref s) => match self.lookup(s) {
+                Some(&ParamValue::Surface(ref x)) => x,
+                _ => return None,
+            },
+            None => return None,
+        };
+        match surface.init_from.first() {
+            Some(i) => find_image(&i.image, libraries),
+            None => None,
+        }
+    }
+}
+
+fn find_image<'a>(id: &str, libraries: &'a [LibraryImages]) -> Option<&'a Image> {
+    libraries.iter().filter_map(|l| l.get(id)).next()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use xmltree::{Element};
+    use fx::{LibraryImages};
+    use traits::{XmlConversion};
+
+    fn parse_newparams(data: &str) -> Vec<NewParam> {
+        let e = Element::parse(data.as_bytes()).unwrap();
+        e.children.iter().map(|c| {
+            let mut n = NewParam::new();
+            n.parse(c).unwrap();
+            n
+        }).collect()
+    }
+
+    fn parse_images() -> Vec<LibraryImages> {
+        let data = r##"
+            <library_images>
+                <image id="brick-img"><init_from>brick.png</init_from></image>
+                <image id="wood-img"><init_from>wood.png</init_from></image>
+                <image id="stone-img"><init_from><ref>stone.png</ref></init_from></image>
+            </library_images>"##;
+        let e = Element::parse(data.as_bytes()).unwrap();
+        let mut l = LibraryImages::new();
+        l.parse(&e).unwrap();
+        vec![l]
+    }
+
+    #[test]
+    fn test_newparam_parse() {
+        let params = parse_newparams(r##"
+            <params>
+                <newparam sid="shininess">
+                    <annotate name="UIName"><string>Shine</string></annotate>
+                    <semantic>SHININESS</semantic>
+                    <modifier>CONST</modifier>
+                    <float>20.0</float>
+                </newparam>
+                <newparam sid="color"><float4>1 0 0 1</float4></newparam>
+                <newparam sid="world"><float4x4>1 0 0 0 0 1 0 0 0 0 1 0 0 0 0 1</float4x4></newparam>
+                <newparam sid="count"><int>-3</int></newparam>
+                <newparam sid="enabled"><bool>true</bool></newparam>
+                <newparam sid="dims"><int3>1 2 3</int3></newparam>
+                <newparam sid="surf">
+                    <surface type="2D">
+                        <init_from mip="0">brick-img</init_from>
+                        <format>A8R8G8B8</format>
+                        <mip_levels>1</mip_levels>
+                    </surface>
+                </newparam>
+                <newparam sid="samp">
+                    <sampler2D>
+                        <source>surf</source>
+                        <wrap_s>WRAP</wrap_s>
+                        <minfilter>LINEAR_MIPMAP_LINEAR</minfilter>
+                        <mipmap_bias>0.5</mipmap_bias>
+                    </sampler2D>
+                </newparam>
+            </params>"##);
+        assert_eq!(params.len(), 8);
+        assert_eq!(params[0].sid.as_str(), "shininess");
+        assert_eq!(params[0].annotations.len(), 1);
+        assert_eq!(params[0].semantic, Some("SHININESS".to_string()));
+        assert_eq!(params[0].modifier, Some("CONST".to_string()));
+        match params[0].value {
+            ParamValue::Float(x) => assert_eq!(x, 20.0),
+            _ => assert!(false),
+        }
+        match params[1].value {
+            ParamValue::Float4(x) => assert_eq!(x, [1.0, 0.0, 0.0, 1.0]),
+            _ => assert!(false),
+        }
+        match params[2].value {
+            ParamValue::Float4x4(x) => assert_eq!(x[15], 1.0),
+            _ => assert!(false),
+        }
+        match params[3].value {
+            ParamValue::Int(x) => assert_eq!(x, -3),
+            _ => assert!(false),
+        }
+        match params[4].value {
+            ParamValue::Bool(x) => assert!(x),
+            _ => assert!(false),
+        }
+        match params[5].value {
+            ParamValue::Other(ref x) => assert_eq!(x.name.as_str(), "int3"),
+            _ => assert!(false),
+        }
+        match params[6].value {
+            ParamValue::Surface(ref s) => {
+                assert_eq!(s.typ.as_str(), "2D");
+                assert_eq!(s.init_from[0].image.as_str(), "brick-img");
+                assert_eq!(s.init_from[0].mip, Some(0));
+                assert_eq!(s.format, Some("A8R8G8B8".to_string()));
+                assert_eq!(s.content.len(), 1);
+            },
+            _ => assert!(false),
+        }
+        match params[7].value {
+            ParamValue::Sampler(ref s) => {
+                assert_eq!(s.typ, SamplerType::Sampler2D);
+                assert_eq!(s.source, Some("surf".to_string()));
+                assert_eq!(s.wrap_s, Some("WRAP".to_string()));
+                assert_eq!(s.minfilter, Some("LINEAR_MIPMAP_LINEAR".to_string()));
+                assert_eq!(s.states.len(), 1);
+            },
+            _ => assert!(false),
+        }
+    }
+
+    #[test]
+    fn test_newparam_parse_invalid() {
+        let e = Element::parse(r##"<newparam sid="a"/>"##.as_bytes()).unwrap();
+        assert!(NewParam::new().parse(&e).is_err());
+
+        let e = Element::parse(r##"<newparam sid="a"><float3>1 2</float3></newparam>"##.as_bytes()).unwrap();
+        assert!(NewParam::new().parse(&e).is_err());
+
+        let e = Element::parse(r##"<newparam><float>1</float></newparam>"##.as_bytes()).unwrap();
+        assert!(NewParam::new().parse(&e).is_err());
+    }
+
+    #[test]
+    fn test_setparam_parse() {
+        let data = r##"
+            <setparam ref="shininess">
+                <float>20.0</float>
+            </setparam>"##;
+        let e = Element::parse(data.as_bytes()).unwrap();
+        let mut s = SetParam::new();
+        match s.parse(&e) {
+            Ok(_) => assert!(true),
+            Err(_) => assert!(false),
+        }
+        assert_eq!(s.reference.as_str(), "shininess");
+        match s.value {
+            ParamValue::Float(x) => assert_eq!(x, 20.0),
+            _ => assert!(false),
+        }
+
+        let e = Element::parse(r##"<setparam ref="foo"/>"##.as_bytes()).unwrap();
+        assert!(SetParam::new().parse(&e).is_err());
+
+        let e = Element::parse(r##"<setparam ref="axis"><SIDREF>kscene/arm/j0/axis</SIDREF></setparam>"##.as_bytes()).unwrap();
+        let mut s = SetParam::new();
+        s.parse(&e).unwrap();
+        match s.value {
+            ParamValue::SidRef(ref x) => assert_eq!(x.as_str(), "kscene/arm/j0/axis"),
+            _ => assert!(false),
+        }
+        assert_eq!(s.value.encode().name, "SIDREF");
+    }
+
+    #[test]
+    fn test_param_encode() {
+        let mut s = SetParam::new();
+        s.reference = "shininess".to_string();
+        s.value = ParamValue::Float(20.0);
+        let e = s.encode();
+        assert_eq!(e.name, "setparam");
+        assert_eq!(e.attributes.get("ref"), Some(&"shininess".to_string()));
+        assert_eq!(e.children.len(), 1);
+        assert_eq!(e.children[0].name, "float");
+        assert_eq!(e.children[0].text, Some("20".to_string()));
+
+        let mut sampler = Sampler::new(SamplerType::Sampler2D);
+        sampler.instance_image = Some("#brick-img".to_string());
+        sampler.wrap_t = Some("CLAMP".to_string());
+        let mut n = NewParam::new();
+        n.sid = "samp".to_string();
+        n.semantic = Some("DIFFUSE".to_string());
+        n.value = ParamValue::Sampler(sampler);
+        let e = n.encode();
+        assert_eq!(e.name, "newparam");
+        assert_eq!(e.attributes.get("sid"), Some(&"samp".to_string()));
+        assert_eq!(e.children[0].name, "semantic");
+        let s = &e.children[1];
+        assert_eq!(s.name, "sampler2D");
+        assert_eq!(s.children[0].name, "instance_image");
+        assert_eq!(s.children[0].attributes.get("url"), Some(&"#brick-img".to_string()));
+        assert_eq!(s.children[1].name, "wrap_t");
+
+        assert_eq!(ParamValue::Float3([1.0, 0.5, 0.0]).encode().text, Some("1 0.5 0".to_string()));
+        assert_eq!(ParamValue::Param("other".to_string()).encode().attributes.get("ref"),
+                   Some(&"other".to_string()));
+    }
+
+    #[test]
+    fn test_resolve_texture_141() {
+        let images = parse_images();
+        let effect = parse_newparams(r##"
+            <params>
+                <newparam sid="brick-surface">
+                    <surface type="2D"><init_from>brick-img</init_from></surface>
+                </newparam>
+            </params>"##);
+        let profile = parse_newparams(r##"
+            <params>
+                <newparam sid="brick-sampler">
+                    <sampler2D><source>brick-surface</source></sampler2D>
+                </newparam>
+                <newparam sid="alias"><param>brick-sampler</param></newparam>
+                <newparam sid="not-a-sampler"><float>1</float></newparam>
+            </params>"##);
+
+        // The profile scope inherits the surface from the effect scope
+        let scope = ParamScope::new().with_scope(&profile).with_scope(&effect);
+        let img = scope.resolve_texture("brick-sampler", &images).unwrap();
+        assert_eq!(img.id, Some("brick-img".to_string()));
+        let img = scope.resolve_texture("alias", &images).unwrap();
+        assert_eq!(img.id, Some("brick-img".to_string()));
+        assert!(scope.resolve_texture("not-a-sampler", &images).is_none());
+
+        // Fallback for exporters that reference the image directly
+        let img = scope.resolve_texture("wood-img", &images).unwrap();
+        assert_eq!(img.id, Some("wood-img".to_string()));
+        assert!(scope.resolve_texture("missing", &images).is_none());
+
+        // Without the effect scope the surface can't be found
+        let scope = ParamScope::new().with_scope(&profile);
+        assert!(scope.resolve_texture("brick-sampler", &images).is_none());
+
+        // A setparam overrides the surface of the effect
+        let e = Element::parse(r##"
+            <setparam ref="brick-surface">
+                <surface type="2D"><init_from>wood-img</init_from></surface>
+            </setparam>"##.as_bytes()).unwrap();
+        let mut s = SetParam::new();
+        s.parse(&e).unwrap();
+        let overrides = vec![s];
+        let scope = ParamScope::new().with_overrides(&overrides)
+            .with_scope(&profile).with_scope(&effect);
+        let img = scope.resolve_texture("brick-sampler", &images).unwrap();
+        assert_eq!(img.id, Some("wood-img".to_string()));
+    }
+
+    #[test]
+    fn test_resolve_texture_15() {
+        let images = parse_images();
+        let params = parse_newparams(r##"
+            <params>
+                <newparam sid="stone-sampler">
+                    <sampler2D><instance_image url="#stone-img"/></sampler2D>
+                </newparam>
+                <newparam sid="loop-a"><param ref="loop-b"/></newparam>
+                <newparam sid="loop-b"><param ref="loop-a"/></newparam>
+            </params>"##);
+        let scope = ParamScope::new().with_scope(&params);
+        let img = scope.resolve_texture("stone-sampler", &images).unwrap();
+        assert_eq!(img.id, Some("stone-img".to_string()));
+        assert!(scope.lookup("loop-a").is_none());
+        assert!(scope.resolve_texture("loop-a", &images).is_none());
+    }
+}
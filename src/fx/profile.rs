@@ -0,0 +1,1220 @@
+use std::collections::{HashMap};
+use xmltree::{Element};
+use context::{ParseContext};
+use core::{Asset, Extra, UnknownContent};
+use error::{ColladaError};
+use fx::{Image, NewParam, ParamValue, SetParam};
+use traits::{XmlConversion};
+use utils;
+use version::{Version};
+
+/// The programmable or platform specific effect profiles
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ProfileKind {
+    /// OpenGL Shading Language
+    GLSL,
+
+    /// NVIDIA Cg
+    CG,
+
+    /// OpenGL ES 1.x fixed function pipeline
+    GLES,
+
+    /// OpenGL ES 2.0 shaders
+    GLES2,
+
+    /// Reference to an effect in an external format (COLLADA 1.5)
+    Bridge,
+}
+
+impl ProfileKind {
+    /// Get the kind of a profile element, e.g. `profile_GLSL`
+    pub fn from_name(name: &str) -> Option<ProfileKind> {
+        match name {
+            "profile_GLSL" => Some(ProfileKind::GLSL),
+            "profile_CG" => Some(ProfileKind::CG),
+            "profile_GLES" => Some(ProfileKind::GLES),
+            "profile_GLES2" => Some(ProfileKind::GLES2),
+            "profile_BRIDGE" => Some(ProfileKind::Bridge),
+            _ => None,
+        }
+    }
+
+    /// Name of the profile element
+    pub fn name(&self) -> &'static str {
+        match *self {
+            ProfileKind::GLSL => "profile_GLSL",
+            ProfileKind::CG => "profile_CG",
+            ProfileKind::GLES => "profile_GLES",
+            ProfileKind::GLES2 => "profile_GLES2",
+            ProfileKind::Bridge => "profile_BRIDGE",
+        }
+    }
+}
+
+/// A block of shader source code, inline or included from a URL
+#[derive(Debug)]
+pub enum CodeBlock {
+    /// `<code>` containing the source text
+    Code{sid: Option<String>, source: String},
+
+    /// `<include>` referencing the source by URL
+    Include{sid: String, url: String},
+}
+
+impl CodeBlock {
+    pub fn sid(&self) -> Option<&str> {
+        match *self {
+            CodeBlock::Code{ref sid, ..} => sid.as_ref().map(|s| s.as_str()),
+            CodeBlock::Include{ref sid, ..} => Some(sid.as_str()),
+        }
+    }
+
+    fn parse(e: &Element) -> Result<CodeBlock, ColladaError> {
+        if e.name == "include" {
+            Ok(CodeBlock::Include{
+                sid: try!(utils::require_attr(e, "sid")),
+                url: try!(utils::require_attr(e, "url")),
+            })
+        } else {
+            Ok(CodeBlock::Code{
+                sid: utils::get_attr(e, "sid"),
+                source: e.text.clone().unwrap_or(String::new()),
+            })
+        }
+    }
+
+    fn encode(&self) -> Element {
+        match *self {
+            CodeBlock::Code{ref sid, ref source} => {
+                let mut c = utils::text_element("code", source.clone());
+                utils::set_attr(&mut c, "sid", sid);
+                c
+            },
+            CodeBlock::Include{ref sid, ref url} => {
+                let mut i = Element::new("include");
+                i.attributes.insert("sid".to_string(), sid.clone());
+                i.attributes.insert("url".to_string(), url.clone());
+                i
+            },
+        }
+    }
+}
+
+/// A render state of a pass, e.g. `<depth_test_enable value="true"/>`
+#[derive(Debug)]
+pub struct RenderState {
+    /// Name of the state, e.g. `blend_func`
+    pub name: String,
+
+    /// Literal value of the state
+    pub value: Option<String>,
+
+    /// Sid of the parameter the state takes its value from
+    pub param: Option<String>,
+
+    /// Index of the state, e.g. the light index of `<light_enable>`
+    pub index: Option<u32>,
+
+    /// Any other attributes, e.g. `operator` of `<texenv>`
+    pub attributes: HashMap<String, String>,
+    pub text: Option<String>,
+
+    /// Sub-states, e.g. `<src>` and `<dest>` of `<blend_func>`
+    pub children: Vec<RenderState>,
+}
+
+impl RenderState {
+    pub fn new(name: &str) -> RenderState {
+        RenderState {
+            name: name.to_string(),
+            value: None,
+            param: None,
+            index: None,
+            attributes: HashMap::new(),
+            text: None,
+            children: Vec::new(),
+        }
+    }
+}
+
+impl XmlConversion for RenderState {
+    fn parse_with(&mut self, e: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        self.name = e.name.clone();
+        self.index = try!(utils::parse_attr(e, "index"));
+        for (k, v) in &e.attributes {
+            match k.as_str() {
+                "value" => self.value = Some(v.clone()),
+                "param" => self.param = Some(v.clone()),
+                "index" => {},
+                _ => { self.attributes.insert(k.clone(), v.clone()); },
+            }
+        }
+        self.text = e.text.clone();
+        for c in &e.children {
+            let mut s = RenderState::new(&c.name);
+            try!(ctx.parse(&mut s, c));
+            self.children.push(s);
+        }
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut s = Element::new(&self.name);
+        utils::set_attr(&mut s, "value", &self.value);
+        utils::set_attr(&mut s, "param", &self.param);
+        if let Some(i) = self.index {
+            s.attributes.insert("index".to_string(), i.to_string());
+        }
+        for (k, v) in &self.attributes {
+            s.attributes.insert(k.clone(), v.clone());
+        }
+        s.text = self.text.clone();
+        for c in &self.children {
+            s.children.push(c.encode());
+        }
+        s
+    }
+}
+
+/// Binds a value or parameter to a uniform of a shader
+#[derive(Debug)]
+pub struct BindUniform {
+    /// Name of the uniform in the shader source
+    pub symbol: String,
+
+    /// The bound value, `ParamValue::Param` for `<param ref="..."/>`
+    pub value: ParamValue,
+}
+
+impl BindUniform {
+    pub fn new() -> BindUniform {
+        BindUniform {
+            symbol: String::from(""),
+            value: ParamValue::Float(0.0),
+        }
+    }
+
+    fn encode_as(&self, name: &str) -> Element {
+        let mut b = Element::new(name);
+        b.attributes.insert("symbol".to_string(), self.symbol.clone());
+        b.children.push(self.value.encode());
+        b
+    }
+}
+
+impl XmlConversion for BindUniform {
+    fn parse_with(&mut self, e: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        self.symbol = try!(utils::require_attr(e, "symbol"));
+        self.value = match e.children.first() {
+            Some(v) => try!(ParamValue::parse(v, ctx)),
+            None => return Err(ColladaError::MissingElement{
+                structure: e.name.clone(),
+                elem: "param".to_string(),
+            }),
+        };
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        self.encode_as("bind_uniform")
+    }
+}
+
+/// Binds a vertex attribute of a shader to a geometry input semantic
+#[derive(Debug)]
+pub struct BindAttribute {
+    /// Name of the attribute in the shader source
+    pub symbol: String,
+    pub semantic: Option<String>,
+}
+
+impl BindAttribute {
+    pub fn new() -> BindAttribute {
+        BindAttribute {
+            symbol: String::from(""),
+            semantic: None,
+        }
+    }
+}
+
+impl XmlConversion for BindAttribute {
+    fn parse_with(&mut self, e: &Element, _ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        self.symbol = try!(utils::require_attr(e, "symbol"));
+        for c in &e.children {
+            match c.name.as_str() {
+                "semantic" => self.semantic = Some(try!(utils::require_text(c)).trim().to_string()),
+                _ => return Err(ColladaError::InvalidChild{
+                    child: c.name.clone(),
+                    parent: "bind_attribute".to_string(),
+                }),
+            }
+        }
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut b = Element::new("bind_attribute");
+        b.attributes.insert("symbol".to_string(), self.symbol.clone());
+        if let Some(ref s) = self.semantic {
+            b.children.push(utils::text_element("semantic", s.clone()));
+        }
+        b
+    }
+}
+
+/// Part of the source of a shader
+#[derive(Debug)]
+pub enum ShaderSource {
+    /// Source text written inline
+    Inline(String),
+
+    /// Sid of a `<code>` or `<include>` block of the profile
+    Import(String),
+}
+
+/// Compiler used to build a shader for a platform (COLLADA 1.5)
+#[derive(Debug)]
+pub struct Compiler {
+    pub platform: String,
+    pub target: Option<String>,
+    pub options: Option<String>,
+
+    /// Precompiled `<binary>` of the shader
+    pub binary: Option<Element>,
+}
+
+/// A shader stage of a pass or program
+#[derive(Debug)]
+pub struct Shader {
+    /// Which form the shader was written in
+    pub version: Version,
+
+    /// Pipeline stage, e.g. `VERTEXPROGRAM` (1.4.1) or `FRAGMENT` (1.5)
+    pub stage: String,
+    pub annotations: Vec<Element>,
+
+    /// Entry point of the shader
+    pub entry: Option<String>,
+    pub sources: Vec<ShaderSource>,
+
+    /// COLLADA 1.4.1 `<compiler_target>`
+    pub compiler_target: Option<String>,
+
+    /// COLLADA 1.4.1 `<compiler_options>`
+    pub compiler_options: Option<String>,
+
+    /// COLLADA 1.5 `<compiler>` elements
+    pub compilers: Vec<Compiler>,
+
+    /// Uniform bindings, `<bind>` in 1.4.1 and `<bind_uniform>` in 1.5
+    pub bind_uniforms: Vec<BindUniform>,
+    pub extras: Vec<Extra>,
+    pub unknown: UnknownContent,
+}
+
+impl Shader {
+    pub fn new() -> Shader {
+        Shader {
+            version: Version::V150,
+            stage: String::from(""),
+            annotations: Vec::new(),
+            entry: None,
+            sources: Vec::new(),
+            compiler_target: None,
+            compiler_options: None,
+            compilers: Vec::new(),
+            bind_uniforms: Vec::new(),
+            extras: Vec::new(),
+            unknown: UnknownContent::new(),
+        }
+    }
+
+    /// The complete source text of the shader, with imported code blocks
+    /// looked up in the profile. Returns `None` if an import isn't found or
+    /// refers to an `<include>` whose source isn't loaded.
+    pub fn source_text(&self, profile: &Profile) -> Option<String> {
+        let mut text = String::new();
+        for s in &self.sources {
+            match *s {
+                ShaderSource::Inline(ref t) => text.push_str(t),
+                ShaderSource::Import(ref sid) => match profile.find_code(sid) {
+                    Some(&CodeBlock::Code{ref source, ..}) => text.push_str(source),
+                    _ => return None,
+                },
+            }
+        }
+        Some(text)
+    }
+}
+
+impl XmlConversion for Shader {
+    fn parse_with(&mut self, e: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        ctx.unknown_attributes(e, &["stage"], &mut self.unknown);
+        self.stage = try!(utils::require_attr(e, "stage"));
+        self.version = Version::V141;
+
+        for c in &e.children {
+            match c.name.as_str() {
+                "annotate" => self.annotations.push(c.clone()),
+                "compiler_target" => self.compiler_target = Some(try!(utils::require_text(c)).trim().to_string()),
+                "name" => {
+                    self.entry = Some(try!(utils::require_text(c)).trim().to_string());
+                    if let Some(s) = utils::get_attr(c, "source") {
+                        self.sources.push(ShaderSource::Import(s));
+                    }
+                },
+                "compiler_options" => self.compiler_options = c.text.clone(),
+                "bind" => {
+                    let mut b = BindUniform::new();
+                    try!(ctx.parse(&mut b, c));
+                    self.bind_uniforms.push(b);
+                },
+                "sources" => {
+                    self.version = Version::V150;
+                    self.entry = utils::get_attr(c, "entry");
+                    for s in &c.children {
+                        match s.name.as_str() {
+                            "inline" => self.sources.push(ShaderSource::Inline(
+                                s.text.clone().unwrap_or(String::new()))),
+                            "import" => self.sources.push(ShaderSource::Import(
+                                try!(utils::require_attr(s, "ref")))),
+                            _ => return Err(ColladaError::InvalidChild{
+                                child: s.name.clone(),
+                                parent: "sources".to_string(),
+                            }),
+                        }
+                    }
+                },
+                "compiler" => {
+                    self.version = Version::V150;
+                    self.compilers.push(Compiler {
+                        platform: try!(utils::require_attr(c, "platform")),
+                        target: utils::get_attr(c, "target"),
+                        options: utils::get_attr(c, "options"),
+                        binary: c.get_child("binary").cloned(),
+                    });
+                },
+                "bind_uniform" => {
+                    self.version = Version::V150;
+                    let mut b = BindUniform::new();
+                    try!(ctx.parse(&mut b, c));
+                    self.bind_uniforms.push(b);
+                },
+                "extra" => {
+                    let mut x = Extra::new();
+                    try!(ctx.parse(&mut x, c));
+                    self.extras.push(x);
+                },
+                _ => try!(ctx.unknown_child(e, c, &mut self.unknown)),
+            }
+        }
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut s = Element::new("shader");
+        s.attributes.insert("stage".to_string(), self.stage.clone());
+        for a in &self.annotations {
+            s.children.push(a.clone());
+        }
+
+        match self.version {
+            Version::V141 => {
+                if let Some(ref t) = self.compiler_target {
+                    s.children.push(utils::text_element("compiler_target", t.clone()));
+                }
+                let mut n = Element::new("name");
+                n.text = self.entry.clone();
+                for src in &self.sources {
+                    if let ShaderSource::Import(ref sid) = *src {
+                        n.attributes.insert("source".to_string(), sid.clone());
+                    }
+                }
+                s.children.push(n);
+                if let Some(ref o) = self.compiler_options {
+                    s.children.push(utils::text_element("compiler_options", o.clone()));
+                }
+                for b in &self.bind_uniforms {
+                    s.children.push(b.encode_as("bind"));
+                }
+            },
+            Version::V150 => {
+                let mut srcs = Element::new("sources");
+                utils::set_attr(&mut srcs, "entry", &self.entry);
+                for src in &self.sources {
+                    srcs.children.push(match *src {
+                        ShaderSource::Inline(ref t) => utils::text_element("inline", t.clone()),
+                        ShaderSource::Import(ref sid) => {
+                            let mut i = Element::new("import");
+                            i.attributes.insert("ref".to_string(), sid.clone());
+                            i
+                        },
+                    });
+                }
+                s.children.push(srcs);
+                for c in &self.compilers {
+                    let mut e = Element::new("compiler");
+                    e.attributes.insert("platform".to_string(), c.platform.clone());
+                    utils::set_attr(&mut e, "target", &c.target);
+                    utils::set_attr(&mut e, "options", &c.options);
+                    if let Some(ref b) = c.binary {
+                        e.children.push(b.clone());
+                    }
+                    s.children.push(e);
+                }
+                for b in &self.bind_uniforms {
+                    s.children.push(b.encode());
+                }
+            },
+        }
+
+        for x in &self.extras {
+            s.children.push(x.encode());
+        }
+        self.unknown.encode_into(&mut s);
+        s
+    }
+}
+
+/// Shaders and bindings linked into a program (COLLADA 1.5)
+#[derive(Debug)]
+pub struct Program {
+    pub shaders: Vec<Shader>,
+
+    /// The `<linker>` elements
+    pub linkers: Vec<Element>,
+    pub bind_attributes: Vec<BindAttribute>,
+    pub bind_uniforms: Vec<BindUniform>,
+}
+
+impl Program {
+    pub fn new() -> Program {
+        Program {
+            shaders: Vec::new(),
+            linkers: Vec::new(),
+            bind_attributes: Vec::new(),
+            bind_uniforms: Vec::new(),
+        }
+    }
+}
+
+impl XmlConversion for Program {
+    fn parse_with(&mut self, e: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        for c in &e.children {
+            match c.name.as_str() {
+                "shader" => {
+                    let mut s = Shader::new();
+                    try!(ctx.parse(&mut s, c));
+                    self.shaders.push(s);
+                },
+                "linker" => self.linkers.push(c.clone()),
+                "bind_attribute" => {
+                    let mut b = BindAttribute::new();
+                    try!(ctx.parse(&mut b, c));
+                    self.bind_attributes.push(b);
+                },
+                "bind_uniform" => {
+                    let mut b = BindUniform::new();
+                    try!(ctx.parse(&mut b, c));
+                    self.bind_uniforms.push(b);
+                },
+                _ => return Err(ColladaError::InvalidChild{
+                    child: c.name.clone(),
+                    parent: "program".to_string(),
+                }),
+            }
+        }
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut p = Element::new("program");
+        for s in &self.shaders {
+            p.children.push(s.encode());
+        }
+        for l in &self.linkers {
+            p.children.push(l.clone());
+        }
+        for b in &self.bind_attributes {
+            p.children.push(b.encode());
+        }
+        for b in &self.bind_uniforms {
+            p.children.push(b.encode());
+        }
+        p
+    }
+}
+
+/// A single rendering pass of a technique
+#[derive(Debug)]
+pub struct Pass {
+    /// Which form the pass was written in. COLLADA 1.5 groups the states,
+    /// program and evaluation elements, COLLADA 1.4.1 lists them directly.
+    pub version: Version,
+    pub sid: Option<String>,
+    pub annotations: Vec<Element>,
+    pub states: Vec<RenderState>,
+
+    /// COLLADA 1.5 program
+    pub program: Option<Program>,
+
+    /// COLLADA 1.4.1 shaders
+    pub shaders: Vec<Shader>,
+
+    /// Render targets, clears and the `<draw>` element
+    pub evaluate: Vec<Element>,
+    pub extras: Vec<Extra>,
+    pub unknown: UnknownContent,
+}
+
+impl Pass {
+    pub fn new() -> Pass {
+        Pass {
+            version: Version::V150,
+            sid: None,
+            annotations: Vec::new(),
+            states: Vec::new(),
+            program: None,
+            shaders: Vec::new(),
+            evaluate: Vec::new(),
+            extras: Vec::new(),
+            unknown: UnknownContent::new(),
+        }
+    }
+
+    /// All the shaders of the pass, whichever form it was written in
+    pub fn all_shaders(&self) -> Vec<&Shader> {
+        let mut v: Vec<&Shader> = self.shaders.iter().collect();
+        if let Some(ref p) = self.program {
+            v.extend(p.shaders.iter());
+        }
+        v
+    }
+}
+
+impl XmlConversion for Pass {
+    fn parse_with(&mut self, e: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        ctx.unknown_attributes(e, &["sid"], &mut self.unknown);
+        self.sid = utils::get_attr(e, "sid");
+        self.version = Version::V141;
+
+        for c in &e.children {
+            match c.name.as_str() {
+                "annotate" => self.annotations.push(c.clone()),
+                "states" => {
+                    self.version = Version::V150;
+                    for s in &c.children {
+                        let mut r = RenderState::new(&s.name);
+                        try!(ctx.parse(&mut r, s));
+                        self.states.push(r);
+                    }
+                },
+                "program" => {
+                    self.version = Version::V150;
+                    let mut p = Program::new();
+                    try!(ctx.parse(&mut p, c));
+                    self.program = Some(p);
+                },
+                "evaluate" => {
+                    self.version = Version::V150;
+                    self.evaluate.extend(c.children.iter().cloned());
+                },
+                "color_target" | "depth_target" | "stencil_target" |
+                "color_clear" | "depth_clear" | "stencil_clear" | "draw" => {
+                    self.evaluate.push(c.clone());
+                },
+                "shader" => {
+                    let mut s = Shader::new();
+                    try!(ctx.parse(&mut s, c));
+                    self.shaders.push(s);
+                },
+                "extra" => {
+                    let mut x = Extra::new();
+                    try!(ctx.parse(&mut x, c));
+                    self.extras.push(x);
+                },
+                _ => {
+                    let mut r = RenderState::new(&c.name);
+                    try!(ctx.parse(&mut r, c));
+                    self.states.push(r);
+                },
+            }
+        }
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut p = Element::new("pass");
+        utils::set_attr(&mut p, "sid", &self.sid);
+        for a in &self.annotations {
+            p.children.push(a.clone());
+        }
+
+        match self.version {
+            Version::V141 => {
+                p.children.extend(self.evaluate.iter().cloned());
+                for s in &self.states {
+                    p.children.push(s.encode());
+                }
+                for s in &self.shaders {
+                    p.children.push(s.encode());
+                }
+            },
+            Version::V150 => {
+                if !self.states.is_empty() {
+                    let mut st = Element::new("states");
+                    for s in &self.states {
+                        st.children.push(s.encode());
+                    }
+                    p.children.push(st);
+                }
+                if let Some(ref x) = self.program {
+                    p.children.push(x.encode());
+                }
+                if !self.evaluate.is_empty() {
+                    let mut ev = Element::new("evaluate");
+                    ev.children.extend(self.evaluate.iter().cloned());
+                    p.children.push(ev);
+                }
+            },
+        }
+
+        for x in &self.extras {
+            p.children.push(x.encode());
+        }
+        self.unknown.encode_into(&mut p);
+        p
+    }
+}
+
+/// A technique of an effect profile, one way of rendering the effect
+#[derive(Debug)]
+pub struct FxTechnique {
+    pub id: Option<String>,
+    pub sid: Option<String>,
+    pub asset: Option<Asset>,
+    pub annotations: Vec<Element>,
+
+    /// COLLADA 1.4.1 code blocks of the technique
+    pub code: Vec<CodeBlock>,
+
+    /// COLLADA 1.4.1 images of the technique
+    pub images: Vec<Image>,
+    pub newparams: Vec<NewParam>,
+
+    /// COLLADA 1.4.1 parameter overrides of the technique
+    pub setparams: Vec<SetParam>,
+    pub passes: Vec<Pass>,
+    pub extras: Vec<Extra>,
+    pub unknown: UnknownContent,
+}
+
+impl FxTechnique {
+    pub fn new() -> FxTechnique {
+        FxTechnique {
+            id: None,
+            sid: None,
+            asset: None,
+            annotations: Vec::new(),
+            code: Vec::new(),
+            images: Vec::new(),
+            newparams: Vec::new(),
+            setparams: Vec::new(),
+            passes: Vec::new(),
+            extras: Vec::new(),
+            unknown: UnknownContent::new(),
+        }
+    }
+}
+
+impl XmlConversion for FxTechnique {
+    fn parse_with(&mut self, e: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        ctx.unknown_attributes(e, &["id", "sid"], &mut self.unknown);
+        self.id = utils::get_attr(e, "id");
+        self.sid = utils::get_attr(e, "sid");
+
+        for c in &e.children {
+            match c.name.as_str() {
+                "asset" => {
+                    let mut a = Asset::new();
+                    try!(ctx.parse(&mut a, c));
+                    self.asset = Some(a);
+                },
+                "annotate" => self.annotations.push(c.clone()),
+                "code" | "include" => self.code.push(try!(CodeBlock::parse(c))),
+                "image" => {
+                    let mut i = Image::new();
+                    try!(ctx.parse(&mut i, c));
+                    self.images.push(i);
+                },
+                "newparam" => {
+                    let mut n = NewParam::new();
+                    try!(ctx.parse(&mut n, c));
+                    self.newparams.push(n);
+                },
+                "setparam" => {
+                    let mut s = SetParam::new();
+                    try!(ctx.parse(&mut s, c));
+                    self.setparams.push(s);
+                },
+                "pass" => {
+                    let mut p = Pass::new();
+                    try!(ctx.parse(&mut p, c));
+                    self.passes.push(p);
+                },
+                "extra" => {
+                    let mut x = Extra::new();
+                    try!(ctx.parse(&mut x, c));
+                    self.extras.push(x);
+                },
+                _ => try!(ctx.unknown_child(e, c, &mut self.unknown)),
+            }
+        }
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut t = Element::new("technique");
+        utils::set_attr(&mut t, "id", &self.id);
+        utils::set_attr(&mut t, "sid", &self.sid);
+
+        match self.asset {
+            Some(ref x) => t.children.push(x.encode()),
+            None => {},
+        }
+        for a in &self.annotations {
+            t.children.push(a.clone());
+        }
+        for c in &self.code {
+            t.children.push(c.encode());
+        }
+        for i in &self.images {
+            t.children.push(i.encode());
+        }
+        for n in &self.newparams {
+            t.children.push(n.encode());
+        }
+        for s in &self.setparams {
+            t.children.push(s.encode());
+        }
+        for p in &self.passes {
+            t.children.push(p.encode());
+        }
+        for x in &self.extras {
+            t.children.push(x.encode());
+        }
+        self.unknown.encode_into(&mut t);
+        t
+    }
+}
+
+/// A programmable or platform specific profile of an effect, i.e.
+/// `<profile_GLSL>`, `<profile_CG>`, `<profile_GLES>`, `<profile_GLES2>` or
+/// `<profile_BRIDGE>`
+#[derive(Debug)]
+pub struct Profile {
+    pub kind: ProfileKind,
+    pub id: Option<String>,
+    pub platform: Option<String>,
+
+    /// Platforms of a `<profile_GLES2>`
+    pub platforms: Option<String>,
+
+    /// Shader language of a `<profile_GLES2>`
+    pub language: Option<String>,
+
+    /// Url of the external effect of a `<profile_BRIDGE>`
+    pub url: Option<String>,
+    pub asset: Option<Asset>,
+    pub code: Vec<CodeBlock>,
+
+    /// COLLADA 1.4.1 images of the profile
+    pub images: Vec<Image>,
+    pub newparams: Vec<NewParam>,
+    pub techniques: Vec<FxTechnique>,
+    pub extras: Vec<Extra>,
+    pub unknown: UnknownContent,
+}
+
+impl Profile {
+    pub fn new(kind: ProfileKind) -> Profile {
+        Profile {
+            kind: kind,
+            id: None,
+            platform: None,
+            platforms: None,
+            language: None,
+            url: None,
+            asset: None,
+            code: Vec::new(),
+            images: Vec::new(),
+            newparams: Vec::new(),
+            techniques: Vec::new(),
+            extras: Vec::new(),
+            unknown: UnknownContent::new(),
+        }
+    }
+
+    /// Find a code block by sid in the profile or any of its techniques
+    pub fn find_code(&self, sid: &str) -> Option<&CodeBlock> {
+        self.code.iter()
+            .chain(self.techniques.iter().flat_map(|t| t.code.iter()))
+            .find(|c| c.sid() == Some(sid))
+    }
+
+    /// Find the source text of a `<code>` block by sid for rewriting
+    pub fn code_mut(&mut self, sid: &str) -> Option<&mut String> {
+        let blocks = self.code.iter_mut()
+            .chain(self.techniques.iter_mut().flat_map(|t| t.code.iter_mut()));
+        for c in blocks {
+            if let CodeBlock::Code{sid: Some(ref s), ref mut source} = *c {
+                if s == sid {
+                    return Some(source);
+                }
+            }
+        }
+        None
+    }
+
+    /// Find a technique by sid
+    pub fn technique(&self, sid: &str) -> Option<&FxTechnique> {
+        self.techniques.iter().find(|t| t.sid.as_ref().map(|s| s.as_str()) == Some(sid))
+    }
+}
+
+impl XmlConversion for Profile {
+    fn parse_with(&mut self, e: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        ctx.unknown_attributes(e, &["id", "platform", "platforms", "language", "url"], &mut self.unknown);
+        self.kind = match ProfileKind::from_name(&e.name) {
+            Some(k) => k,
+            None => return Err(ColladaError::InvalidChild{
+                child: e.name.clone(),
+                parent: "effect".to_string(),
+            }),
+        };
+        self.id = utils::get_attr(e, "id");
+        self.platform = utils::get_attr(e, "platform");
+        self.platforms = utils::get_attr(e, "platforms");
+        self.language = utils::get_attr(e, "language");
+        self.url = utils::get_attr(e, "url");
+
+        for c in &e.children {
+            match c.name.as_str() {
+                "asset" => {
+                    let mut a = Asset::new();
+                    try!(ctx.parse(&mut a, c));
+                    self.asset = Some(a);
+                },
+                "code" | "include" => self.code.push(try!(CodeBlock::parse(c))),
+                "image" => {
+                    let mut i = Image::new();
+                    try!(ctx.parse(&mut i, c));
+                    self.images.push(i);
+                },
+                "newparam" => {
+                    let mut n = NewParam::new();
+                    try!(ctx.parse(&mut n, c));
+                    self.newparams.push(n);
+                },
+                "technique" => {
+                    let mut t = FxTechnique::new();
+                    try!(ctx.parse(&mut t, c));
+                    self.techniques.push(t);
+                },
+                "extra" => {
+                    let mut x = Extra::new();
+                    try!(ctx.parse(&mut x, c));
+                    self.extras.push(x);
+                },
+                _ => try!(ctx.unknown_child(e, c, &mut self.unknown)),
+            }
+        }
+
+        if self.kind != ProfileKind::Bridge && self.techniques.is_empty() {
+            return Err(ColladaError::MissingElement{
+                structure: e.name.clone(),
+                elem: "technique".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut p = Element::new(self.kind.name());
+        utils::set_attr(&mut p, "id", &self.id);
+        utils::set_attr(&mut p, "platform", &self.platform);
+        utils::set_attr(&mut p, "platforms", &self.platforms);
+        utils::set_attr(&mut p, "language", &self.language);
+        utils::set_attr(&mut p, "url", &self.url);
+
+        match self.asset {
+            Some(ref x) => p.children.push(x.encode()),
+            None => {},
+        }
+        for c in &self.code {
+            p.children.push(c.encode());
+        }
+        for i in &self.images {
+            p.children.push(i.encode());
+        }
+        for n in &self.newparams {
+            p.children.push(n.encode());
+        }
+        for t in &self.techniques {
+            p.children.push(t.encode());
+        }
+        for x in &self.extras {
+            p.children.push(x.encode());
+        }
+        self.unknown.encode_into(&mut p);
+        p
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use xmltree::{Element};
+    use fx::{ParamValue};
+    use traits::{XmlConversion};
+    use version::{Version};
+
+    fn parse_profile(data: &str) -> Profile {
+        let e = Element::parse(data.as_bytes()).unwrap();
+        let mut p = Profile::new(ProfileKind::GLSL);
+        match p.parse(&e) {
+            Ok(_) => {},
+            Err(e) => panic!("{}", e),
+        }
+        p
+    }
+
+    #[test]
+    fn test_profile_glsl_141_parse() {
+        let p = parse_profile(r##"
+            <profile_GLSL>
+                <code sid="vs">void main() { gl_Position = ftransform(); }</code>
+                <include sid="common" url="common.glsl"/>
+                <newparam sid="color"><float4>1 0 0 1</float4></newparam>
+                <technique sid="default">
+                    <pass sid="p0">
+                        <depth_test_enable value="true"/>
+                        <blend_func>
+                            <src value="SRC_ALPHA"/>
+                            <dest value="ONE_MINUS_SRC_ALPHA"/>
+                        </blend_func>
+                        <light_enable index="1" param="light1"/>
+                        <shader stage="VERTEXPROGRAM">
+                            <name source="vs">main</name>
+                            <bind symbol="color"><param ref="color"/></bind>
+                        </shader>
+                    </pass>
+                </technique>
+            </profile_GLSL>"##);
+        assert_eq!(p.kind, ProfileKind::GLSL);
+        assert_eq!(p.code.len(), 2);
+        match p.code[1] {
+            CodeBlock::Include{ref sid, ref url} => {
+                assert_eq!(sid.as_str(), "common");
+                assert_eq!(url.as_str(), "common.glsl");
+            },
+            _ => assert!(false),
+        }
+        assert_eq!(p.newparams.len(), 1);
+
+        let t = p.technique("default").unwrap();
+        let pass = &t.passes[0];
+        assert_eq!(pass.version, Version::V141);
+        assert_eq!(pass.sid, Some("p0".to_string()));
+        assert_eq!(pass.states.len(), 3);
+        assert_eq!(pass.states[0].name.as_str(), "depth_test_enable");
+        assert_eq!(pass.states[0].value, Some("true".to_string()));
+        assert_eq!(pass.states[1].children.len(), 2);
+        assert_eq!(pass.states[1].children[1].value, Some("ONE_MINUS_SRC_ALPHA".to_string()));
+        assert_eq!(pass.states[2].index, Some(1));
+        assert_eq!(pass.states[2].param, Some("light1".to_string()));
+
+        let s = &pass.shaders[0];
+        assert_eq!(s.version, Version::V141);
+        assert_eq!(s.stage.as_str(), "VERTEXPROGRAM");
+        assert_eq!(s.entry, Some("main".to_string()));
+        assert_eq!(s.bind_uniforms.len(), 1);
+        assert_eq!(s.bind_uniforms[0].symbol.as_str(), "color");
+        match s.bind_uniforms[0].value {
+            ParamValue::Param(ref r) => assert_eq!(r.as_str(), "color"),
+            _ => assert!(false),
+        }
+        assert_eq!(s.source_text(&p), Some("void main() { gl_Position = ftransform(); }".to_string()));
+    }
+
+    #[test]
+    fn test_profile_glsl_15_parse() {
+        let p = parse_profile(r##"
+            <profile_GLSL platform="PC">
+                <code sid="fs">uniform vec4 tint;</code>
+                <technique sid="t0">
+                    <pass>
+                        <states>
+                            <cull_face value="BACK"/>
+                        </states>
+                        <program>
+                            <shader stage="FRAGMENT">
+                                <sources entry="main">
+                                    <import ref="fs"/>
+                                    <inline>void main() { gl_FragColor = tint; }</inline>
+                                </sources>
+                                <compiler platform="PC" target="glsl120" options="-O3"/>
+                                <bind_uniform symbol="tint"><float4>1 1 1 1</float4></bind_uniform>
+                            </shader>
+                            <linker platform="PC"/>
+                            <bind_attribute symbol="pos"><semantic>POSITION</semantic></bind_attribute>
+                            <bind_uniform symbol="mvp"><param ref="mvp"/></bind_uniform>
+                        </program>
+                        <evaluate>
+                            <draw>GEOMETRY</draw>
+                        </evaluate>
+                    </pass>
+                </technique>
+            </profile_GLSL>"##);
+        assert_eq!(p.platform, Some("PC".to_string()));
+        let pass = &p.techniques[0].passes[0];
+        assert_eq!(pass.version, Version::V150);
+        assert_eq!(pass.states.len(), 1);
+        assert_eq!(pass.evaluate.len(), 1);
+        let prog = pass.program.as_ref().unwrap();
+        assert_eq!(prog.linkers.len(), 1);
+        assert_eq!(prog.bind_attributes[0].symbol.as_str(), "pos");
+        assert_eq!(prog.bind_attributes[0].semantic, Some("POSITION".to_string()));
+        assert_eq!(prog.bind_uniforms[0].symbol.as_str(), "mvp");
+
+        let s = &prog.shaders[0];
+        assert_eq!(s.version, Version::V150);
+        assert_eq!(s.entry, Some("main".to_string()));
+        assert_eq!(s.compilers[0].target, Some("glsl120".to_string()));
+        assert_eq!(s.compilers[0].options, Some("-O3".to_string()));
+        match s.bind_uniforms[0].value {
+            ParamValue::Float4(x) => assert_eq!(x, [1.0, 1.0, 1.0, 1.0]),
+            _ => assert!(false),
+        }
+        assert_eq!(s.source_text(&p),
+                   Some("uniform vec4 tint;void main() { gl_FragColor = tint; }".to_string()));
+        assert_eq!(pass.all_shaders().len(), 1);
+    }
+
+    #[test]
+    fn test_profile_other_kinds_parse() {
+        let p = parse_profile(r##"
+            <profile_CG platform="PC-OGL">
+                <code sid="cg">float4 main() : COLOR { return 1; }</code>
+                <technique sid="t">
+                    <pass>
+                        <shader stage="FRAGMENTPROGRAM">
+                            <compiler_target>arbfp1</compiler_target>
+                            <name source="cg">main</name>
+                            <compiler_options>-fastmath</compiler_options>
+                        </shader>
+                    </pass>
+                </technique>
+            </profile_CG>"##);
+        assert_eq!(p.kind, ProfileKind::CG);
+        let s = &p.techniques[0].passes[0].shaders[0];
+        assert_eq!(s.compiler_target, Some("arbfp1".to_string()));
+        assert_eq!(s.compiler_options, Some("-fastmath".to_string()));
+
+        let p = parse_profile(r##"
+            <profile_GLES platform="PS3">
+                <technique sid="t">
+                    <pass>
+                        <states><texture_pipeline_enable value="true"/></states>
+                    </pass>
+                </technique>
+            </profile_GLES>"##);
+        assert_eq!(p.kind, ProfileKind::GLES);
+
+        let p = parse_profile(r##"
+            <profile_GLES2 language="GLSL" platforms="OpenGLES2">
+                <include sid="inc" url="shaders/common.glsl"/>
+                <technique sid="t"><pass/></technique>
+            </profile_GLES2>"##);
+        assert_eq!(p.kind, ProfileKind::GLES2);
+        assert_eq!(p.language, Some("GLSL".to_string()));
+        assert_eq!(p.platforms, Some("OpenGLES2".to_string()));
+
+        let p = parse_profile(r##"<profile_BRIDGE platform="fx" url="effects.fx#glass"/>"##);
+        assert_eq!(p.kind, ProfileKind::Bridge);
+        assert_eq!(p.url, Some("effects.fx#glass".to_string()));
+
+        let e = Element::parse(r##"<profile_GLSL/>"##.as_bytes()).unwrap();
+        assert!(Profile::new(ProfileKind::GLSL).parse(&e).is_err());
+    }
+
+    #[test]
+    fn test_profile_rewrite_source() {
+        let mut p = parse_profile(r##"
+            <profile_GLSL>
+                <code sid="fs">old</code>
+                <technique sid="t"><pass/></technique>
+            </profile_GLSL>"##);
+        p.code_mut("fs").unwrap().push_str(" new");
+        assert!(p.code_mut("missing").is_none());
+        match *p.find_code("fs").unwrap() {
+            CodeBlock::Code{ref source, ..} => assert_eq!(source.as_str(), "old new"),
+            _ => assert!(false),
+        }
+    }
+
+    #[test]
+    fn test_profile_encode_round_trip() {
+        let data = r##"
+            <profile_GLSL>
+                <code sid="vs">void main() {}</code>
+                <technique sid="t141">
+                    <pass>
+                        <depth_func value="LEQUAL"/>
+                        <shader stage="VERTEXPROGRAM">
+                            <name source="vs">main</name>
+                            <bind symbol="k"><float>2</float></bind>
+                        </shader>
+                    </pass>
+                </technique>
+                <technique sid="t150">
+                    <pass>
+                        <states><depth_func value="LESS"/></states>
+                        <program>
+                            <shader stage="VERTEX">
+                                <sources entry="main"><import ref="vs"/></sources>
+                            </shader>
+                        </program>
+                        <evaluate><draw>GEOMETRY</draw></evaluate>
+                    </pass>
+                </technique>
+            </profile_GLSL>"##;
+        let p = parse_profile(data);
+        let e = p.encode();
+        assert_eq!(e.name, "profile_GLSL");
+        assert_eq!(e.children[0].name, "code");
+
+        let p141 = &e.children[1].children[0];
+        assert_eq!(p141.name, "pass");
+        assert_eq!(p141.children[0].name, "depth_func");
+        let s = &p141.children[1];
+        assert_eq!(s.name, "shader");
+        assert_eq!(s.children[0].name, "name");
+        assert_eq!(s.children[0].attributes.get("source"), Some(&"vs".to_string()));
+        assert_eq!(s.children[1].name, "bind");
+
+        let p150 = &e.children[2].children[0];
+        let names: Vec<&str> = p150.children.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["states", "program", "evaluate"]);
+        let s = &p150.children[1].children[0];
+        assert_eq!(s.children[0].name, "sources");
+        assert_eq!(s.children[0].attributes.get("entry"), Some(&"main".to_string()));
+        assert_eq!(s.children[0].children[0].name, "import");
+
+        // Parsing the encoded profile gives the same structure
+        let mut again = Profile::new(ProfileKind::CG);
+        again.parse(&e).unwrap();
+        assert_eq!(again.encode(), e);
+    }
+}
@@ -0,0 +1,2074 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::ptr;
+use std::ops::{Index};
+use xmltree::{Element};
+use collada::{Collada};
+use core::{IDENTITY, InstanceController, InstanceGeometry, Matrix, TransformKind, UpAxis, compose,
+           multiply, transform_point};
+use fx::{BindMaterial, CommonTechnique, Image, ImageData, ImageSource};
+use traits::{XmlConversion};
+use utils;
+use mesh::{Lookup, Primitive, Source, inputs, node_transforms, normalize};
+
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+const FLOAT: u32 = 5126;
+const UNSIGNED_SHORT: u32 = 5123;
+const UNSIGNED_INT: u32 = 5125;
+
+/// Instances of nodes can nest this deep, which also stops cycles
+const MAX_DEPTH: usize = 64;
+
+/// Resampled animations of a node have at most this many samples besides
+/// the key times, however long they run
+const MAX_SAMPLES: usize = 1 << 16;
+
+static NULL: Json = Json::Null;
+
+/// A JSON value, as a glTF document is made of
+#[derive(Clone, Debug, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+
+    /// Members in the order they are written
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    /// An object with members
+    pub fn object(members: Vec<(&str, Json)>) -> Json {
+        Json::Object(members.into_iter().map(|(k, v)| (k.to_string(), v)).collect())
+    }
+
+    /// A member of an object
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match *self {
+            Json::Object(ref m) => m.iter().find(|x| x.0 == key).map(|x| &x.1),
+            _ => None,
+        }
+    }
+
+    /// Set a member of an object, replacing any member with the same key.
+    /// Other values are left as they are.
+    pub fn set(&mut self, key: &str, value: Json) {
+        if let Json::Object(ref mut m) = *self {
+            match m.iter().position(|x| x.0 == key) {
+                Some(i) => m[i].1 = value,
+                None => m.push((key.to_string(), value)),
+            }
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Json::Number(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match *self {
+            Json::String(ref s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The number of items of an array or members of an object
+    pub fn len(&self) -> usize {
+        match *self {
+            Json::Array(ref a) => a.len(),
+            Json::Object(ref m) => m.len(),
+            _ => 0,
+        }
+    }
+}
+
+impl<'a> Index<&'a str> for Json {
+    type Output = Json;
+
+    /// A member of an object, or null
+    fn index(&self, key: &str) -> &Json {
+        self.get(key).unwrap_or(&NULL)
+    }
+}
+
+impl Index<usize> for Json {
+    type Output = Json;
+
+    /// An item of an array, or null
+    fn index(&self, i: usize) -> &Json {
+        match *self {
+            Json::Array(ref a) => a.get(i).unwrap_or(&NULL),
+            _ => &NULL,
+        }
+    }
+}
+
+impl From<bool> for Json {
+    fn from(b: bool) -> Json {
+        Json::Bool(b)
+    }
+}
+
+impl From<f32> for Json {
+    /// The shortest decimal of the float, so 0.1 isn't written as
+    /// 0.10000000149011612
+    fn from(x: f32) -> Json {
+        Json::Number(x.to_string().parse().unwrap_or(0.0))
+    }
+}
+
+impl From<usize> for Json {
+    fn from(x: usize) -> Json {
+        Json::Number(x as f64)
+    }
+}
+
+impl From<u32> for Json {
+    fn from(x: u32) -> Json {
+        Json::Number(x as f64)
+    }
+}
+
+impl<'a> From<&'a str> for Json {
+    fn from(s: &str) -> Json {
+        Json::String(s.to_string())
+    }
+}
+
+impl From<String> for Json {
+    fn from(s: String) -> Json {
+        Json::String(s)
+    }
+}
+
+impl<'a> From<&'a [f32]> for Json {
+    fn from(v: &[f32]) -> Json {
+        Json::Array(v.iter().map(|x| Json::from(*x)).collect())
+    }
+}
+
+impl From<Vec<usize>> for Json {
+    fn from(v: Vec<usize>) -> Json {
+        Json::Array(v.into_iter().map(Json::from).collect())
+    }
+}
+
+impl fmt::Display for Json {
+    /// Compact JSON. Numbers that aren't finite are written as null.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(b) => write!(f, "{}", b),
+            Json::Number(n) if n.is_finite() => write!(f, "{}", n),
+            Json::Number(_) => write!(f, "null"),
+            Json::String(ref s) => write_string(f, s),
+            Json::Array(ref a) => {
+                try!(write!(f, "["));
+                for (i, x) in a.iter().enumerate() {
+                    if i > 0 {
+                        try!(write!(f, ","));
+                    }
+                    try!(write!(f, "{}", x));
+                }
+                write!(f, "]")
+            },
+            Json::Object(ref m) => {
+                try!(write!(f, "{{"));
+                for (i, &(ref k, ref v)) in m.iter().enumerate() {
+                    if i > 0 {
+                        try!(write!(f, ","));
+                    }
+                    try!(write_string(f, k));
+                    try!(write!(f, ":{}", v));
+                }
+                write!(f, "}}")
+            },
+        }
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter, s: &str) -> fmt::Result {
+    try!(write!(f, "\""));
+    for c in s.chars() {
+        match c {
+            '"' => try!(write!(f, "\\\"")),
+            '\\' => try!(write!(f, "\\\\")),
+            '\n' => try!(write!(f, "\\n")),
+            '\r' => try!(write!(f, "\\r")),
+            '\t' => try!(write!(f, "\\t")),
+            c if (c as u32) < 0x20 => try!(write!(f, "\\u{:04x}", c as u32)),
+            c => try!(write!(f, "{}", c)),
+        }
+    }
+    write!(f, "\"")
+}
+
+/// How resampled node animations interpolate between their samples
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum GltfInterpolation {
+    Linear,
+    Step,
+
+    /// Cubic splines with tangents estimated from the neighbouring samples
+    CubicSpline,
+}
+
+impl GltfInterpolation {
+    fn name(&self) -> &'static str {
+        match *self {
+            GltfInterpolation::Linear => "LINEAR",
+            GltfInterpolation::Step => "STEP",
+            GltfInterpolation::CubicSpline => "CUBICSPLINE",
+        }
+    }
+}
+
+/// Options of a glTF export
+#[derive(Clone, Debug)]
+pub struct GltfOptions {
+    pub interpolation: GltfInterpolation,
+
+    /// Samples per second of resampled animations, besides the key times.
+    /// Step interpolation only samples the key times, and long animations
+    /// are sampled less often to keep the number of samples bounded.
+    pub sample_rate: f32,
+}
+
+impl GltfOptions {
+    pub fn new() -> GltfOptions {
+        GltfOptions {
+            interpolation: GltfInterpolation::Linear,
+            sample_rate: 30.0,
+        }
+    }
+}
+
+/// A glTF 2.0 asset and its binary buffer
+#[derive(Debug)]
+pub struct Gltf {
+    /// The JSON document, without the uri of the buffer
+    pub json: Json,
+    pub bin: Vec<u8>,
+}
+
+impl Gltf {
+    /// The JSON of a `.gltf` file with the binary buffer in a `.bin` file at
+    /// `bin_uri`, relative to the `.gltf`
+    pub fn to_json(&self, bin_uri: &str) -> String {
+        let mut json = self.json.clone();
+        if let Some(&mut Json::Array(ref mut buffers)) = buffer_list(&mut json) {
+            if let Some(b) = buffers.get_mut(0) {
+                b.set("uri", bin_uri.into());
+            }
+        }
+        json.to_string()
+    }
+
+    /// A binary `.glb` file with the JSON and the buffer
+    pub fn to_glb(&self) -> Vec<u8> {
+        let mut json = self.json.to_string().into_bytes();
+        while json.len() % 4 != 0 {
+            json.push(b' ');
+        }
+        let mut bin = self.bin.clone();
+        while bin.len() % 4 != 0 {
+            bin.push(0);
+        }
+
+        let mut out = Vec::new();
+        let total = 12 + 8 + json.len() + if bin.is_empty() { 0 } else { 8 + bin.len() };
+        push_u32(&mut out, 0x4654_6c67);
+        push_u32(&mut out, 2);
+        push_u32(&mut out, total as u32);
+        push_u32(&mut out, json.len() as u32);
+        push_u32(&mut out, 0x4e4f_534a);
+        out.extend_from_slice(&json);
+        if !bin.is_empty() {
+            push_u32(&mut out, bin.len() as u32);
+            push_u32(&mut out, 0x004e_4942);
+            out.extend_from_slice(&bin);
+        }
+        out
+    }
+}
+
+fn buffer_list(json: &mut Json) -> Option<&mut Json> {
+    match *json {
+        Json::Object(ref mut m) => m.iter_mut().find(|x| x.0 == "buffers").map(|x| &mut x.1),
+        _ => None,
+    }
+}
+
+/// Convert a document to glTF 2.0. The visual scene of `<scene>`, or the
+/// first one, becomes the scene with its node hierarchy and instances:
+///
+/// * Mesh primitives are triangulated, lines and line strips become lines
+///   and vertices are split where their inputs differ.
+/// * `profile_COMMON` effects become metallic-roughness materials with a
+///   base color from the diffuse, or the emission of `<constant>` as an
+///   unlit material, a roughness from the shininess and an alpha from the
+///   transparency.
+/// * Skins get their bind shape applied to the mesh and up to four joints
+///   per vertex, morph controllers become morph targets.
+/// * Animations of node transforms are resampled into translation, rotation
+///   and scale channels.
+/// * Cameras are exported and lights, except ambient ones, become
+///   `KHR_lights_punctual` lights.
+///
+/// The unit and up axis of the root asset are converted to meters and Y up
+/// with a root node.
+pub fn export_gltf(c: &Collada, options: &GltfOptions) -> Gltf {
+    let mut x = Exporter {
+        c: c,
+        options: options,
+        lookup: Lookup::new(c),
+        animations: Vec::new(),
+        bin: Vec::new(),
+        buffer_views: Vec::new(),
+        accessors: Vec::new(),
+        nodes: Vec::new(),
+        node_map: HashMap::new(),
+        meshes: Vec::new(),
+        mesh_cache: HashMap::new(),
+        materials: Vec::new(),
+        material_cache: HashMap::new(),
+        textures: Vec::new(),
+        images: Vec::new(),
+        image_cache: HashMap::new(),
+        samplers: Vec::new(),
+        cameras: Vec::new(),
+        camera_cache: HashMap::new(),
+        lights: Vec::new(),
+        light_cache: HashMap::new(),
+        skins: Vec::new(),
+        pending_skins: Vec::new(),
+        extensions: Vec::new(),
+    };
+    x.animations = x.animation_channels();
+    x.export()
+}
+
+fn float_child(e: &Element, name: &str) -> Option<f32> {
+    e.get_child(name).and_then(|c| utils::parse_text(c).ok())
+}
+
+/// The data of a `<skin>` the meshes of an instance need
+struct Skin {
+    bind_shape: Matrix,
+
+    /// Joint sids, or ids for an `IDREF_array`
+    joints: Vec<String>,
+    idref: bool,
+    inverse_binds: Vec<f32>,
+
+    /// Up to four joints and weights of each position, the weights summing
+    /// to one
+    weights: Vec<Vec<(u16, f32)>>,
+}
+
+/// A skin waiting for the nodes of its joints
+struct PendingSkin<'a> {
+    skin: usize,
+    node: usize,
+    joints: Vec<String>,
+    idref: bool,
+    skeletons: Vec<String>,
+    scene: &'a Element,
+}
+
+/// Node transforms targeted by an animation channel
+struct Channel<'a> {
+    node: &'a Element,
+    sid: String,
+
+    /// The value targeted, e.g. 3 for `.ANGLE`, or all values of the
+    /// transform
+    index: Option<usize>,
+    sampler: Sampler,
+}
+
+/// The keys of an animation `<sampler>`
+struct Sampler {
+    times: Vec<f32>,
+    values: Source,
+    interpolations: Vec<String>,
+    in_tangents: Option<Source>,
+    out_tangents: Option<Source>,
+}
+
+struct Exporter<'a> {
+    c: &'a Collada,
+    options: &'a GltfOptions,
+    lookup: Lookup<'a>,
+    animations: Vec<Channel<'a>>,
+    bin: Vec<u8>,
+    buffer_views: Vec<Json>,
+    accessors: Vec<Json>,
+    nodes: Vec<Json>,
+
+    /// The glTF nodes of each `<node>`, which has several when it is
+    /// instanced more than once
+    node_map: HashMap<*const Element, Vec<usize>>,
+    meshes: Vec<Json>,
+    mesh_cache: HashMap<String, usize>,
+    materials: Vec<Json>,
+    material_cache: HashMap<String, usize>,
+    textures: Vec<Json>,
+    images: Vec<Json>,
+    image_cache: HashMap<String, usize>,
+    samplers: Vec<Json>,
+    cameras: Vec<Json>,
+    camera_cache: HashMap<*const Element, Option<usize>>,
+    lights: Vec<Json>,
+    light_cache: HashMap<*const Element, Option<usize>>,
+    skins: Vec<Json>,
+    pending_skins: Vec<PendingSkin<'a>>,
+    extensions: Vec<&'static str>,
+}
+
+impl<'a> Exporter<'a> {
+    fn export(mut self) -> Gltf {
+        let mut roots = Vec::new();
+        if let Some(scene) = self.lookup.visual_scene(self.c) {
+            for n in scene.children.iter().filter(|n| n.name == "node") {
+                roots.push(self.node(n, scene, 0));
+            }
+            let pending: Vec<PendingSkin> = self.pending_skins.drain(..).collect();
+            for p in pending {
+                self.resolve_skin(p);
+            }
+        }
+
+        // glTF is in meters with Y up
+        let meter = self.c.asset.unit.as_ref().map(|u| u.meter()).unwrap_or(1.0);
+        let up = self.c.asset.up_axis.unwrap_or(UpAxis::YUP);
+        if meter != 1.0 || up != UpAxis::YUP {
+            let m = multiply(&scaling(meter), &axis_rotation(up));
+            let root = Json::object(vec![
+                ("name", "root".into()),
+                ("matrix", Json::from(&transpose(&m)[..])),
+                ("children", roots.into()),
+            ]);
+            self.nodes.push(root);
+            roots = vec![self.nodes.len() - 1];
+        }
+
+        let animation = self.export_animations();
+
+        let mut json = Json::object(vec![
+            ("asset", Json::object(vec![
+                ("version", "2.0".into()),
+                ("generator", "collada-rs".into()),
+            ])),
+            ("scene", 0usize.into()),
+            ("scenes", Json::Array(vec![Json::object(vec![("nodes", roots.into())])])),
+        ]);
+        let lists = vec![
+            ("nodes", self.nodes),
+            ("meshes", self.meshes),
+            ("materials", self.materials),
+            ("textures", self.textures),
+            ("images", self.images),
+            ("samplers", self.samplers),
+            ("skins", self.skins),
+            ("cameras", self.cameras),
+            ("animations", animation.into_iter().collect()),
+            ("accessors", self.accessors),
+            ("bufferViews", self.buffer_views),
+        ];
+        for (name, list) in lists {
+            if !list.is_empty() {
+                json.set(name, Json::Array(list));
+            }
+        }
+        if !self.bin.is_empty() {
+            json.set("buffers", Json::Array(vec![Json::object(vec![("byteLength", self.bin.len().into())])]));
+        }
+        if !self.lights.is_empty() {
+            json.set("extensions", Json::object(vec![
+                ("KHR_lights_punctual", Json::object(vec![("lights", Json::Array(self.lights))])),
+            ]));
+        }
+        if !self.extensions.is_empty() {
+            json.set("extensionsUsed", Json::Array(self.extensions.iter().map(|e| Json::from(*e)).collect()));
+        }
+        Gltf {
+            json: json,
+            bin: self.bin,
+        }
+    }
+
+    fn is_animated(&self, node: &Element) -> bool {
+        self.animations.iter().any(|a| ptr::eq(a.node, node))
+    }
+
+    /// Export a node and its children, returning its index
+    fn node(&mut self, e: &'a Element, scene: &'a Element, depth: usize) -> usize {
+        let index = self.nodes.len();
+        self.nodes.push(Json::Null);
+        self.node_map.entry(e).or_insert(Vec::new()).push(index);
+
+        let mut json = Json::object(vec![]);
+        if let Some(name) = e.attributes.get("name").or(e.attributes.get("id")) {
+            json.set("name", name.as_str().into());
+        }
+        let m = compose(&node_transforms(e));
+        if self.is_animated(e) {
+            let (t, r, s) = decompose(&m);
+            json.set("translation", Json::from(&t[..]));
+            json.set("rotation", Json::from(&r[..]));
+            json.set("scale", Json::from(&s[..]));
+        } else if m != IDENTITY {
+            json.set("matrix", Json::from(&transpose(&m)[..]));
+        }
+
+        let mut children = Vec::new();
+        let mut attached = Vec::new();
+        for c in &e.children {
+            match c.name.as_str() {
+                "node" if depth < MAX_DEPTH => children.push(self.node(c, scene, depth + 1)),
+                "instance_node" if depth < MAX_DEPTH => {
+                    match c.attributes.get("url").and_then(|u| self.lookup.resolve(u)) {
+                        Some(n) if n.name == "node" => children.push(self.node(n, scene, depth + 1)),
+                        _ => {},
+                    }
+                },
+                "instance_geometry" => {
+                    let mut i = InstanceGeometry::new();
+                    if i.parse(c).is_ok() {
+                        if let Some(mesh) = self.mesh(&i.url, None, i.bind_material.as_ref()) {
+                            attached.push(("mesh", mesh, None));
+                        }
+                    }
+                },
+                "instance_controller" => {
+                    let mut i = InstanceController::new();
+                    if i.parse(c).is_err() {
+                        continue;
+                    }
+                    let controller = match self.lookup.resolve(&i.url) {
+                        Some(c) if c.name == "controller" => c,
+                        _ => continue,
+                    };
+                    let (geometry, skin, morph) = self.controller(controller);
+                    if let Some(mesh) = self.mesh(&geometry, Some((controller, skin.as_ref(), morph)),
+                                                  i.bind_material.as_ref()) {
+                        let skin = skin.map(|s| (s, i.skeletons.clone()));
+                        attached.push(("mesh", mesh, skin));
+                    }
+                },
+                "instance_camera" => {
+                    if let Some(camera) = c.attributes.get("url").and_then(|u| self.lookup.resolve(u)) {
+                        if let Some(i) = self.camera(camera) {
+                            attached.push(("camera", i, None));
+                        }
+                    }
+                },
+                "instance_light" => {
+                    if let Some(light) = c.attributes.get("url").and_then(|u| self.lookup.resolve(u)) {
+                        if let Some(i) = self.light(light) {
+                            attached.push(("light", i, None));
+                        }
+                    }
+                },
+                _ => {},
+            }
+        }
+
+        // A glTF node has one of each, so more instances get child nodes
+        let mut used = HashSet::new();
+        for (kind, i, skin) in attached {
+            let target = if used.insert(kind) {
+                index
+            } else {
+                self.nodes.push(Json::object(vec![]));
+                children.push(self.nodes.len() - 1);
+                self.nodes.len() - 1
+            };
+            let skin = skin.map(|(skin, skeletons)| {
+                let inverse = self.accessor(&column_major(&skin.inverse_binds), "MAT4", None, false);
+                self.skins.push(Json::object(vec![("inverseBindMatrices", inverse.into())]));
+                self.pending_skins.push(PendingSkin {
+                    skin: self.skins.len() - 1,
+                    node: target,
+                    joints: skin.joints,
+                    idref: skin.idref,
+                    skeletons: skeletons,
+                    scene: scene,
+                });
+                self.skins.len() - 1
+            });
+            let json = if target == index { &mut json } else { &mut self.nodes[target] };
+            match kind {
+                "light" => json.set("extensions", Json::object(vec![
+                    ("KHR_lights_punctual", Json::object(vec![("light", i.into())])),
+                ])),
+                _ => json.set(kind, i.into()),
+            }
+            if let Some(s) = skin {
+                json.set("skin", s.into());
+            }
+        }
+
+        if !children.is_empty() {
+            json.set("children", children.into());
+        }
+        self.nodes[index] = json;
+        index
+    }
+
+    /// Find the nodes of a skin's joints, after every node is exported
+    fn resolve_skin(&mut self, p: PendingSkin<'a>) {
+        let roots: Vec<&Element> = p.skeletons.iter().filter_map(|u| self.lookup.resolve(u)).collect();
+        let mut joints = Vec::new();
+        let mut skeleton = None;
+        for name in &p.joints {
+            let node = if p.idref {
+                self.lookup.ids.get(name.as_str()).cloned()
+            } else {
+                roots.iter().filter_map(|r| find_sid(r, name)).next()
+                    .or_else(|| find_sid(p.scene, name))
+                    .or_else(|| self.lookup.ids.get(name.as_str()).cloned())
+            };
+            let index = node.and_then(|n| self.node_map.get(&(n as *const Element))).map(|v| v[0]);
+            // Joints without a node follow the skinned node instead
+            joints.push(index.unwrap_or(p.node));
+            if skeleton.is_none() {
+                skeleton = index;
+            }
+        }
+        let root = roots.first().and_then(|r| self.node_map.get(&(*r as *const Element))).map(|v| v[0]);
+        let skin = &mut self.skins[p.skin];
+        skin.set("joints", joints.into());
+        if let Some(r) = root {
+            skin.set("skeleton", r.into());
+        }
+    }
+
+    /// The geometry a controller deforms, with its skin and morph
+    fn controller(&self, controller: &'a Element) -> (String, Option<Skin>, Option<&'a Element>) {
+        let mut skin = None;
+        let mut morph = None;
+        let mut source = String::new();
+        let mut e = controller;
+        for _ in 0..2 {
+            if let Some(s) = e.get_child("skin") {
+                if skin.is_none() {
+                    skin = self.skin(s);
+                }
+                source = s.attributes.get("source").cloned().unwrap_or(String::new());
+            } else if let Some(m) = e.get_child("morph") {
+                if morph.is_none() {
+                    morph = Some(m);
+                }
+                source = m.attributes.get("source").cloned().unwrap_or(String::new());
+            }
+            // A skin can deform the result of a morph
+            match self.lookup.resolve(&source) {
+                Some(c) if c.name == "controller" => e = c,
+                _ => break,
+            }
+        }
+        (source, skin, morph)
+    }
+
+    fn skin(&self, skin: &Element) -> Option<Skin> {
+        let bind_shape = skin.get_child("bind_shape_matrix")
+            .and_then(|m| utils::parse_array::<f32>(m, 16).ok())
+            .map(|v| to_matrix(&v))
+            .unwrap_or(IDENTITY);
+
+        let joint_inputs = match skin.get_child("joints") {
+            Some(j) => inputs(j),
+            None => return None,
+        };
+        let joint_source = match joint_inputs.iter().find(|i| i.semantic == "JOINT") {
+            Some(i) => i.source,
+            None => return None,
+        };
+        let (joints, idref) = match self.lookup.names(joint_source) {
+            Some(x) => x,
+            None => return None,
+        };
+        let inverse_binds = joint_inputs.iter()
+            .find(|i| i.semantic == "INV_BIND_MATRIX")
+            .and_then(|i| self.lookup.source(i.source))
+            .map(|s| s.values)
+            .unwrap_or(Vec::new());
+        if inverse_binds.len() != joints.len() * 16 {
+            return None;
+        }
+
+        let vw = match skin.get_child("vertex_weights") {
+            Some(v) => v,
+            None => return None,
+        };
+        let vw_inputs = inputs(vw);
+        let stride = vw_inputs.iter().map(|i| i.offset + 1).max().unwrap_or(1);
+        let joint = vw_inputs.iter().find(|i| i.semantic == "JOINT").map(|i| i.offset);
+        let weight = vw_inputs.iter().find(|i| i.semantic == "WEIGHT");
+        let (joint, weight) = match (joint, weight) {
+            (Some(j), Some(w)) => (j, w),
+            _ => return None,
+        };
+        let weight_values = match self.lookup.source(weight.source) {
+            Some(s) => s,
+            None => return None,
+        };
+        let vcount: Vec<usize> = vw.get_child("vcount").and_then(|c| utils::parse_list(c).ok()).unwrap_or(Vec::new());
+        let v: Vec<i64> = vw.get_child("v").and_then(|c| utils::parse_list(c).ok()).unwrap_or(Vec::new());
+
+        let mut weights = Vec::new();
+        let mut next = 0;
+        for n in vcount {
+            let mut w: Vec<(u16, f32)> = Vec::new();
+            for k in 0..n {
+                let base = next + k * stride;
+                let j = v.get(base + joint).cloned().unwrap_or(-1);
+                let x = v.get(base + weight.offset)
+                    .and_then(|i| weight_values.item(*i as usize).first().cloned())
+                    .unwrap_or(0.0);
+                // Joint -1 is the bind shape, which glTF doesn't have
+                if j >= 0 && (j as usize) < joints.len() && x > 0.0 {
+                    w.push((j as u16, x));
+                }
+            }
+            next += n * stride;
+            w.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(::std::cmp::Ordering::Equal));
+            w.truncate(4);
+            let sum: f32 = w.iter().map(|x| x.1).sum();
+            for x in &mut w {
+                x.1 /= sum;
+            }
+            weights.push(w);
+        }
+
+        Some(Skin {
+            bind_shape: bind_shape,
+            joints: joints,
+            idref: idref,
+            inverse_binds: inverse_binds,
+            weights: weights,
+        })
+    }
+
+    /// Export the mesh of a geometry for the materials bound by an instance
+    fn mesh(&mut self, url: &str, controller: Option<(&'a Element, Option<&Skin>, Option<&'a Element>)>,
+            bind: Option<&BindMaterial>) -> Option<usize>
+    {
+        let mut key = match controller {
+            Some((c, _, _)) => format!("{:p}", c),
+            None => url.to_string(),
+        };
+        if let Some(b) = bind {
+            for i in &b.instance_materials {
+                key.push_str(&format!("|{}={}", i.symbol, i.target));
+            }
+        }
+        if let Some(i) = self.mesh_cache.get(&key) {
+            return Some(*i);
+        }
+
+        let geometry = match self.lookup.resolve(url) {
+            Some(g) if g.name == "geometry" => g,
+            _ => return None,
+        };
+        let mesh = match geometry.get_child("mesh") {
+            Some(m) => m,
+            None => return None,
+        };
+        let (skin, morph) = match controller {
+            Some((_, s, m)) => (s, m),
+            None => (None, None),
+        };
+        let targets = morph.map(|m| self.morph_targets(url, m)).unwrap_or((Vec::new(), Vec::new()));
+
+        let mut primitives = Vec::new();
+        for p in &mesh.children {
+            let mut prim = match self.lookup.primitive(p) {
+                Some(x) => x,
+                None => continue,
+            };
+            if let Some(s) = skin {
+                skin_primitive(&mut prim, s);
+            }
+
+            let mut json = Json::object(vec![]);
+            let mut attributes = Json::object(vec![]);
+            for &(ref name, width, ref data) in &prim.attributes {
+                let typ = match width {
+                    2 => "VEC2",
+                    3 => "VEC3",
+                    _ => "VEC4",
+                };
+                let a = if name == "JOINTS_0" {
+                    let joints: Vec<u16> = data.iter().map(|j| *j as u16).collect();
+                    self.accessor_u16(&joints, "VEC4")
+                } else if name.starts_with("TEXCOORD") {
+                    // glTF texture coordinates start at the top
+                    let flipped: Vec<f32> = data.chunks(2).flat_map(|t| vec![t[0], 1.0 - t[1]]).collect();
+                    self.accessor(&flipped, typ, Some(ARRAY_BUFFER), false)
+                } else {
+                    self.accessor(data, typ, Some(ARRAY_BUFFER), name == "POSITION")
+                };
+                attributes.set(name, a.into());
+            }
+            json.set("attributes", attributes);
+            let indices = self.accessor_u32(&prim.indices);
+            json.set("indices", indices.into());
+            json.set("mode", if prim.lines { 1usize } else { 4 }.into());
+
+            if let (Some(symbol), Some(b)) = (prim.material.as_ref(), bind) {
+                if let Some(m) = self.material(b, symbol, &prim.sets) {
+                    json.set("material", m.into());
+                }
+            }
+
+            if !targets.0.is_empty() {
+                let linear = skin.map(|s| s.bind_shape).unwrap_or(IDENTITY);
+                let mut list = Vec::new();
+                for t in &targets.0 {
+                    let mut data = Vec::with_capacity(prim.positions.len() * 3);
+                    for p in &prim.positions {
+                        let d = t.get(*p).cloned().unwrap_or([0.0; 3]);
+                        data.extend_from_slice(&transform_vector(&linear, d));
+                    }
+                    let a = self.accessor(&data, "VEC3", Some(ARRAY_BUFFER), true);
+                    list.push(Json::object(vec![("POSITION", a.into())]));
+                }
+                json.set("targets", Json::Array(list));
+            }
+            primitives.push(json);
+        }
+        if primitives.is_empty() {
+            return None;
+        }
+
+        let mut json = Json::object(vec![("primitives", Json::Array(primitives))]);
+        if let Some(name) = geometry.attributes.get("name").or(geometry.attributes.get("id")) {
+            json.set("name", name.as_str().into());
+        }
+        if !targets.1.is_empty() {
+            json.set("weights", Json::from(&targets.1[..]));
+        }
+        self.meshes.push(json);
+        let index = self.meshes.len() - 1;
+        self.mesh_cache.insert(key, index);
+        Some(index)
+    }
+
+    /// The displacement of each position of a geometry by each target of a
+    /// morph, and the weights of the targets
+    fn morph_targets(&self, url: &str, morph: &Element) -> (Vec<Vec<[f32; 3]>>, Vec<f32>) {
+        let positions = |url: &str| -> Option<Source> {
+            let mesh = match self.lookup.resolve(url).and_then(|g| g.get_child("mesh")) {
+                Some(m) => m,
+                None => return None,
+            };
+            let vertices = match mesh.get_child("vertices") {
+                Some(v) => v,
+                None => return None,
+            };
+            inputs(vertices).iter()
+                .find(|i| i.semantic == "POSITION")
+                .and_then(|i| self.lookup.source(i.source))
+        };
+        let base = match positions(url) {
+            Some(b) => b,
+            None => return (Vec::new(), Vec::new()),
+        };
+        let relative = morph.attributes.get("method").map(|m| m.as_str()) == Some("RELATIVE");
+
+        let inputs = morph.get_child("targets").map(|t| inputs(t)).unwrap_or(Vec::new());
+        let names = inputs.iter()
+            .find(|i| i.semantic == "MORPH_TARGET")
+            .and_then(|i| self.lookup.names(i.source))
+            .map(|n| n.0)
+            .unwrap_or(Vec::new());
+        let weights = inputs.iter()
+            .find(|i| i.semantic == "MORPH_WEIGHT")
+            .and_then(|i| self.lookup.source(i.source))
+            .map(|s| s.values)
+            .unwrap_or(Vec::new());
+
+        let mut targets = Vec::new();
+        let mut target_weights = Vec::new();
+        for (i, name) in names.iter().enumerate() {
+            let target = match positions(&format!("#{}", name)) {
+                Some(t) => t,
+                None => continue,
+            };
+            let displacements = (0..base.count()).map(|p| {
+                let b = base.item(p);
+                let t = target.item(p);
+                if b.len() < 3 || t.len() < 3 {
+                    [0.0; 3]
+                } else if relative {
+                    [t[0], t[1], t[2]]
+                } else {
+                    [t[0] - b[0], t[1] - b[1], t[2] - b[2]]
+                }
+            }).collect();
+            targets.push(displacements);
+            target_weights.push(weights.get(i).cloned().unwrap_or(0.0));
+        }
+        (targets, target_weights)
+    }
+
+    /// Export the material bound to a symbol, with texture coordinates from
+    /// the TEXCOORD sets of a primitive
+    fn material(&mut self, bind: &BindMaterial, symbol: &str, sets: &[u32]) -> Option<usize> {
+        let c = self.c;
+        let binding = match bind.resolve(symbol, &c.library_materials, &c.library_effects) {
+            Some(b) => b,
+            None => return None,
+        };
+        let common = match binding.effect.common() {
+            Some(t) => t,
+            None => return None,
+        };
+        let texcoord = |texture: &str| -> usize {
+            binding.texcoords.get(texture)
+                .and_then(|set| sets.iter().position(|s| s == set))
+                .unwrap_or(0)
+        };
+        let key = format!("{}|{:?}", binding.instance.target, sets);
+        if let Some(i) = self.material_cache.get(&key) {
+            return Some(*i);
+        }
+
+        let constant = common.kind() == "constant";
+        let base_name = if constant { "emission" } else { "diffuse" };
+        let mut base = common.color(base_name).unwrap_or([1.0, 1.0, 1.0, 1.0]);
+
+        let mut pbr = Json::object(vec![]);
+        let mut json = Json::object(vec![]);
+        if let Some(name) = binding.material.name.as_ref().or(binding.material.id.as_ref()) {
+            json.set("name", name.as_str().into());
+        }
+
+        if let Some(opacity) = common.opacity() {
+            base[3] *= opacity;
+            if base[3] < 1.0 || common.texture("transparent").is_some() {
+                json.set("alphaMode", "BLEND".into());
+            }
+        }
+        pbr.set("baseColorFactor", Json::from(&base[..]));
+
+        if let Some((tex, _)) = common.texture(base_name) {
+            if let Some(t) = self.texture(&common, tex) {
+                pbr.set("baseColorTexture", Json::object(vec![
+                    ("index", t.into()),
+                    ("texCoord", texcoord(tex).into()),
+                ]));
+            }
+        }
+        pbr.set("metallicFactor", 0usize.into());
+        let roughness = match common.kind() {
+            "phong" | "blinn" => {
+                let s = common.float("shininess").unwrap_or(0.0).max(0.0);
+                (2.0 / (s + 2.0)).sqrt()
+            },
+            _ => 1.0,
+        };
+        pbr.set("roughnessFactor", roughness.into());
+        json.set("pbrMetallicRoughness", pbr);
+
+        if constant {
+            json.set("extensions", Json::object(vec![("KHR_materials_unlit", Json::object(vec![]))]));
+            self.use_extension("KHR_materials_unlit");
+        } else {
+            if let Some(e) = common.color("emission") {
+                if e[0] > 0.0 || e[1] > 0.0 || e[2] > 0.0 {
+                    json.set("emissiveFactor", Json::from(&[e[0].min(1.0), e[1].min(1.0), e[2].min(1.0)][..]));
+                }
+            }
+            if let Some((tex, _)) = common.texture("emission") {
+                if let Some(t) = self.texture(&common, tex) {
+                    json.set("emissiveTexture", Json::object(vec![
+                        ("index", t.into()),
+                        ("texCoord", texcoord(tex).into()),
+                    ]));
+                    json.set("emissiveFactor", Json::from(&[1.0f32, 1.0, 1.0][..]));
+                }
+            }
+        }
+        if common.double_sided() {
+            json.set("doubleSided", true.into());
+        }
+
+        self.materials.push(json);
+        let index = self.materials.len() - 1;
+        self.material_cache.insert(key, index);
+        Some(index)
+    }
+
+    /// Export the texture a `<texture texture="...">` refers to
+    fn texture(&mut self, common: &CommonTechnique, texture: &str) -> Option<usize> {
+        let c = self.c;
+        let (id, image) = match (common.image_id(texture), common.image(texture, &c.library_images)) {
+            (Some(id), Some(image)) => (id, image),
+            _ => return None,
+        };
+        let source = match self.image(&id, image) {
+            Some(i) => i,
+            None => return None,
+        };
+
+        let mut json = Json::object(vec![("source", source.into())]);
+        if let Some(s) = common.sampler(texture) {
+            let wrap = |name: &str| match s.get_child(name).and_then(|w| w.text.as_ref()).map(|t| t.trim()) {
+                Some("MIRROR") => 33648usize,
+                Some("CLAMP") | Some("BORDER") | Some("MIRROR_ONCE") => 33071,
+                _ => 10497,
+            };
+            let (wrap_s, wrap_t) = (wrap("wrap_s"), wrap("wrap_t"));
+            if wrap_s != 10497 || wrap_t != 10497 {
+                self.samplers.push(Json::object(vec![("wrapS", wrap_s.into()), ("wrapT", wrap_t.into())]));
+                json.set("sampler", (self.samplers.len() - 1).into());
+            }
+        }
+        self.textures.push(json);
+        Some(self.textures.len() - 1)
+    }
+
+    /// Export an image by its uri, or its data if it is embedded
+    fn image(&mut self, id: &str, image: &Image) -> Option<usize> {
+        if let Some(i) = self.image_cache.get(id) {
+            return Some(*i);
+        }
+        let mut json = Json::object(vec![]);
+        if let Some(name) = image.name.as_ref() {
+            json.set("name", name.as_str().into());
+        }
+        if let Some(uri) = image.uris().first() {
+            json.set("uri", (*uri).into());
+        } else {
+            let (format, data) = match image.data {
+                ImageData::Data(ref d) => (image.format.clone(), d),
+                ImageData::InitFrom(ref i) => match i.source {
+                    ImageSource::Hex{ref format, ref data} => (format.clone(), data),
+                    _ => return None,
+                },
+                _ => return None,
+            };
+            let mime = match format.map(|f| f.to_uppercase()) {
+                Some(ref f) if f == "PNG" => "image/png",
+                Some(ref f) if f == "JPG" || f == "JPEG" => "image/jpeg",
+                _ => return None,
+            };
+            let view = self.buffer_view(data, None);
+            json.set("bufferView", view.into());
+            json.set("mimeType", mime.into());
+        }
+        self.images.push(json);
+        let index = self.images.len() - 1;
+        self.image_cache.insert(id.to_string(), index);
+        Some(index)
+    }
+
+    fn camera(&mut self, camera: &Element) -> Option<usize> {
+        if let Some(i) = self.camera_cache.get(&(camera as *const Element)) {
+            return *i;
+        }
+        let common = camera.get_child("optics").and_then(|o| o.get_child("technique_common"));
+        let json = common.and_then(|c| {
+            if let Some(p) = c.get_child("perspective") {
+                let aspect = float_child(p, "aspect_ratio");
+                let xfov = float_child(p, "xfov").map(|x| x.to_radians());
+                let yfov = float_child(p, "yfov").map(|y| y.to_radians());
+                let (yfov, aspect) = match (xfov, yfov, aspect) {
+                    (_, Some(y), Some(a)) => (y, Some(a)),
+                    (Some(x), Some(y), None) => (y, Some((x / 2.0).tan() / (y / 2.0).tan())),
+                    (Some(x), None, Some(a)) => (2.0 * ((x / 2.0).tan() / a).atan(), Some(a)),
+                    (Some(x), None, None) => (x, None),
+                    (None, Some(y), None) => (y, None),
+                    _ => return None,
+                };
+                let mut json = Json::object(vec![
+                    ("yfov", yfov.into()),
+                    ("znear", float_child(p, "znear").unwrap_or(0.01).max(1e-6).into()),
+                ]);
+                if let Some(a) = aspect {
+                    json.set("aspectRatio", a.into());
+                }
+                if let Some(z) = float_child(p, "zfar") {
+                    json.set("zfar", z.into());
+                }
+                Some(Json::object(vec![("type", "perspective".into()), ("perspective", json)]))
+            } else if let Some(o) = c.get_child("orthographic") {
+                let aspect = float_child(o, "aspect_ratio").unwrap_or(1.0);
+                let (xmag, ymag) = match (float_child(o, "xmag"), float_child(o, "ymag")) {
+                    (Some(x), Some(y)) => (x, y),
+                    (Some(x), None) => (x, x / aspect),
+                    (None, Some(y)) => (y * aspect, y),
+                    _ => return None,
+                };
+                let znear = float_child(o, "znear").unwrap_or(0.0);
+                let json = Json::object(vec![
+                    ("xmag", xmag.into()),
+                    ("ymag", ymag.into()),
+                    ("znear", znear.into()),
+                    ("zfar", float_child(o, "zfar").unwrap_or(znear + 1000.0).into()),
+                ]);
+                Some(Json::object(vec![("type", "orthographic".into()), ("orthographic", json)]))
+            } else {
+                None
+            }
+        });
+        let index = json.map(|mut j| {
+            if let Some(name) = camera.attributes.get("name").or(camera.attributes.get("id")) {
+                j.set("name", name.as_str().into());
+            }
+            self.cameras.push(j);
+            self.cameras.len() - 1
+        });
+        self.camera_cache.insert(camera, index);
+        index
+    }
+
+    fn light(&mut self, light: &Element) -> Option<usize> {
+        if let Some(i) = self.light_cache.get(&(light as *const Element)) {
+            return *i;
+        }
+        let kind = light.get_child("technique_common")
+            .and_then(|c| c.children.iter().find(|k| ["directional", "point", "spot"].contains(&k.name.as_str())));
+        let index = kind.map(|k| {
+            let c: Vec<f32> = k.get_child("color").and_then(|c| utils::parse_list(c).ok()).unwrap_or(vec![1.0; 3]);
+            let c = [
+                c.get(0).cloned().unwrap_or(1.0),
+                c.get(1).cloned().unwrap_or(1.0),
+                c.get(2).cloned().unwrap_or(1.0),
+            ];
+            // Colors brighter than white become the intensity
+            let intensity = c[0].max(c[1]).max(c[2]).max(1.0);
+            let mut json = Json::object(vec![
+                ("type", k.name.as_str().into()),
+                ("color", Json::from(&[c[0] / intensity, c[1] / intensity, c[2] / intensity][..])),
+                ("intensity", intensity.into()),
+            ]);
+            if let Some(name) = light.attributes.get("name").or(light.attributes.get("id")) {
+                json.set("name", name.as_str().into());
+            }
+            if k.name == "spot" {
+                // The falloff angle is the whole cone
+                let falloff = float_child(k, "falloff_angle").unwrap_or(180.0);
+                let outer = (falloff / 2.0).min(90.0).max(0.01).to_radians();
+                json.set("spot", Json::object(vec![
+                    ("innerConeAngle", 0usize.into()),
+                    ("outerConeAngle", outer.into()),
+                ]));
+            }
+            self.lights.push(json);
+            self.lights.len() - 1
+        });
+        if index.is_some() {
+            self.use_extension("KHR_lights_punctual");
+        }
+        self.light_cache.insert(light, index);
+        index
+    }
+
+    fn use_extension(&mut self, name: &'static str) {
+        if !self.extensions.contains(&name) {
+            self.extensions.push(name);
+        }
+    }
+
+    /// The channels of every animation that target node transforms
+    fn animation_channels(&self) -> Vec<Channel<'a>> {
+        fn collect<'b>(e: &'b Element, channels: &mut Vec<&'b Element>) {
+            if e.name == "channel" {
+                channels.push(e);
+            }
+            for c in &e.children {
+                collect(c, channels);
+            }
+        }
+        let mut elements = Vec::new();
+        for l in self.c.other_libraries.iter().filter(|l| l.name == "library_animations") {
+            collect(l, &mut elements);
+        }
+
+        let mut channels = Vec::new();
+        for e in elements {
+            let target = match e.attributes.get("target") {
+                Some(t) => t,
+                None => continue,
+            };
+            let slash = match target.find('/') {
+                Some(i) => i,
+                None => continue,
+            };
+            let node = match self.lookup.ids.get(&target[..slash]) {
+                Some(n) if n.name == "node" => *n,
+                _ => continue,
+            };
+            let rest = &target[slash + 1..];
+            let (sid, selector) = match rest.find(|c| c == '.' || c == '(') {
+                Some(i) => (&rest[..i], &rest[i..]),
+                None => (rest, ""),
+            };
+            let kind = match node.children.iter()
+                .find(|c| c.attributes.get("sid").map(|s| s.as_str()) == Some(sid))
+                .and_then(|c| TransformKind::from_name(&c.name))
+            {
+                Some(k) => k,
+                None => continue,
+            };
+            let index = match selector {
+                "" => None,
+                ".X" => Some(0),
+                ".Y" => Some(1),
+                ".Z" => Some(2),
+                ".ANGLE" if kind == TransformKind::Rotate => Some(3),
+                _ if selector.starts_with('(') => {
+                    let parts: Vec<usize> = selector.split(|c| c == '(' || c == ')')
+                        .filter(|s| !s.is_empty())
+                        .filter_map(|s| s.parse().ok())
+                        .collect();
+                    match parts.len() {
+                        1 => Some(parts[0]),
+                        2 if kind == TransformKind::Matrix => Some(parts[0] * 4 + parts[1]),
+                        _ => continue,
+                    }
+                },
+                _ => continue,
+            };
+            let sampler = match e.attributes.get("source").and_then(|s| self.lookup.resolve(s)).and_then(|s| self.sampler(s)) {
+                Some(s) => s,
+                None => continue,
+            };
+            channels.push(Channel {
+                node: node,
+                sid: sid.to_string(),
+                index: index,
+                sampler: sampler,
+            });
+        }
+        channels
+    }
+
+    fn sampler(&self, e: &Element) -> Option<Sampler> {
+        let inputs = inputs(e);
+        let find = |semantic: &str| inputs.iter().find(|i| i.semantic == semantic).map(|i| i.source);
+        let times = match find("INPUT").and_then(|s| self.lookup.source(s)) {
+            Some(s) => s.values,
+            None => return None,
+        };
+        let values = match find("OUTPUT").and_then(|s| self.lookup.source(s)) {
+            Some(s) => s,
+            None => return None,
+        };
+        let interpolations = find("INTERPOLATION")
+            .and_then(|s| self.lookup.names(s))
+            .map(|n| n.0)
+            .unwrap_or(Vec::new());
+        if times.is_empty() || values.count() < times.len() || times.iter().any(|t| !t.is_finite()) {
+            return None;
+        }
+        Some(Sampler {
+            times: times,
+            values: values,
+            interpolations: interpolations,
+            in_tangents: find("IN_TANGENT").and_then(|s| self.lookup.source(s)),
+            out_tangents: find("OUT_TANGENT").and_then(|s| self.lookup.source(s)),
+        })
+    }
+
+    /// Resample the animated nodes into translation, rotation and scale
+    /// channels of one animation
+    fn export_animations(&mut self) -> Option<Json> {
+        let mut nodes: Vec<&'a Element> = Vec::new();
+        for a in &self.animations {
+            if !nodes.iter().any(|n| ptr::eq(*n, a.node)) {
+                nodes.push(a.node);
+            }
+        }
+
+        let interpolation = self.options.interpolation;
+        let mut samplers = Vec::new();
+        let mut channels = Vec::new();
+        for node in nodes {
+            let targets = match self.node_map.get(&(node as *const Element)) {
+                Some(t) => t.clone(),
+                None => continue,
+            };
+            let times = self.sample_times(node);
+            let transforms = node_transforms(node);
+            let mut t = Vec::new();
+            let mut r = Vec::new();
+            let mut s = Vec::new();
+            let mut previous = [0.0, 0.0, 0.0, 1.0];
+            for time in &times {
+                let mut animated = transforms.clone();
+                for a in self.animations.iter().filter(|a| ptr::eq(a.node, node)) {
+                    let value = a.sampler.evaluate(*time);
+                    let transform = match animated.iter_mut().find(|x| x.sid.as_ref() == Some(&a.sid)) {
+                        Some(x) => x,
+                        None => continue,
+                    };
+                    match a.index {
+                        Some(i) => if let (Some(v), Some(x)) = (transform.values.get_mut(i), value.first()) {
+                            *v = *x;
+                        },
+                        None => if value.len() == transform.values.len() {
+                            transform.values = value;
+                        },
+                    }
+                }
+                let (translation, mut rotation, scale) = decompose(&compose(&animated));
+                // Keep quaternions in one hemisphere so they interpolate the
+                // short way
+                if (0..4).map(|i| rotation[i] * previous[i]).sum::<f32>() < 0.0 {
+                    for x in &mut rotation {
+                        *x = -*x;
+                    }
+                }
+                previous = rotation;
+                t.push(translation.to_vec());
+                r.push(rotation.to_vec());
+                s.push(scale.to_vec());
+            }
+
+            let input = self.accessor(&times, "SCALAR", None, true);
+            for (path, values, typ) in [("translation", t, "VEC3"), ("rotation", r, "VEC4"), ("scale", s, "VEC3")] {
+                let data = match interpolation {
+                    GltfInterpolation::CubicSpline => cubic_spline(&times, &values),
+                    _ => values.concat(),
+                };
+                let output = self.accessor(&data, typ, None, false);
+                samplers.push(Json::object(vec![
+                    ("input", input.into()),
+                    ("output", output.into()),
+                    ("interpolation", interpolation.name().into()),
+                ]));
+                for n in &targets {
+                    channels.push(Json::object(vec![
+                        ("sampler", (samplers.len() - 1).into()),
+                        ("target", Json::object(vec![("node", (*n).into()), ("path", path.into())])),
+                    ]));
+                }
+            }
+        }
+        if channels.is_empty() {
+            return None;
+        }
+        Some(Json::object(vec![
+            ("name", "animation".into()),
+            ("channels", Json::Array(channels)),
+            ("samplers", Json::Array(samplers)),
+        ]))
+    }
+
+    /// The key times of a node's channels, and samples in between at the
+    /// sample rate unless stepping
+    fn sample_times(&self, node: &Element) -> Vec<f32> {
+        let mut times: Vec<f32> = self.animations.iter()
+            .filter(|a| ptr::eq(a.node, node))
+            .flat_map(|a| a.sampler.times.iter().cloned())
+            .collect();
+        times.sort_by(|a, b| a.partial_cmp(b).unwrap_or(::std::cmp::Ordering::Equal));
+        if let (Some(first), Some(last)) = (times.first().cloned(), times.last().cloned()) {
+            let mut rate = self.options.sample_rate;
+            if self.options.interpolation != GltfInterpolation::Step && rate > 0.0 {
+                let span = last - first;
+                if span * rate > MAX_SAMPLES as f32 {
+                    rate = MAX_SAMPLES as f32 / span;
+                }
+                let n = (span * rate).floor() as usize;
+                times.extend((1..n + 1).map(|i| first + i as f32 / rate).filter(|t| *t < last));
+                times.sort_by(|a, b| a.partial_cmp(b).unwrap_or(::std::cmp::Ordering::Equal));
+            }
+        }
+        times.dedup_by(|a, b| (*a - *b).abs() < 1e-5);
+        times
+    }
+
+    /// Add data to the buffer as a view, aligned to four bytes
+    fn buffer_view(&mut self, data: &[u8], target: Option<u32>) -> usize {
+        while self.bin.len() % 4 != 0 {
+            self.bin.push(0);
+        }
+        let mut json = Json::object(vec![
+            ("buffer", 0usize.into()),
+            ("byteOffset", self.bin.len().into()),
+            ("byteLength", data.len().into()),
+        ]);
+        if let Some(t) = target {
+            json.set("target", t.into());
+        }
+        self.bin.extend_from_slice(data);
+        self.buffer_views.push(json);
+        self.buffer_views.len() - 1
+    }
+
+    /// Add floats as an accessor of a type, e.g. `VEC3`, with their bounds if
+    /// `bounds` is set
+    fn accessor(&mut self, data: &[f32], typ: &str, target: Option<u32>, bounds: bool) -> usize {
+        let width = components(typ);
+        let mut bytes = Vec::with_capacity(data.len() * 4);
+        for x in data {
+            push_u32(&mut bytes, x.to_bits());
+        }
+        let view = self.buffer_view(&bytes, target);
+        let mut json = Json::object(vec![
+            ("bufferView", view.into()),
+            ("componentType", FLOAT.into()),
+            ("count", (data.len() / width).into()),
+            ("type", typ.into()),
+        ]);
+        if bounds && !data.is_empty() {
+            let mut min = data[..width].to_vec();
+            let mut max = data[..width].to_vec();
+            for item in data.chunks(width) {
+                for i in 0..width {
+                    min[i] = min[i].min(item[i]);
+                    max[i] = max[i].max(item[i]);
+                }
+            }
+            json.set("min", Json::from(&min[..]));
+            json.set("max", Json::from(&max[..]));
+        }
+        self.accessors.push(json);
+        self.accessors.len() - 1
+    }
+
+    fn accessor_u16(&mut self, data: &[u16], typ: &str) -> usize {
+        let mut bytes = Vec::with_capacity(data.len() * 2);
+        for x in data {
+            bytes.push(*x as u8);
+            bytes.push((*x >> 8) as u8);
+        }
+        let view = self.buffer_view(&bytes, Some(ARRAY_BUFFER));
+        self.accessors.push(Json::object(vec![
+            ("bufferView", view.into()),
+            ("componentType", UNSIGNED_SHORT.into()),
+            ("count", (data.len() / components(typ)).into()),
+            ("type", typ.into()),
+        ]));
+        self.accessors.len() - 1
+    }
+
+    fn accessor_u32(&mut self, data: &[u32]) -> usize {
+        let mut bytes = Vec::with_capacity(data.len() * 4);
+        for x in data {
+            push_u32(&mut bytes, *x);
+        }
+        let view = self.buffer_view(&bytes, Some(ELEMENT_ARRAY_BUFFER));
+        self.accessors.push(Json::object(vec![
+            ("bufferView", view.into()),
+            ("componentType", UNSIGNED_INT.into()),
+            ("count", data.len().into()),
+            ("type", "SCALAR".into()),
+        ]));
+        self.accessors.len() - 1
+    }
+}
+
+impl Sampler {
+    /// The output at a time, holding the first and last keys outside of the
+    /// keys
+    fn evaluate(&self, t: f32) -> Vec<f32> {
+        let n = self.times.len();
+        if t <= self.times[0] {
+            return self.values.item(0).to_vec();
+        }
+        if t >= self.times[n - 1] {
+            return self.values.item(n - 1).to_vec();
+        }
+        let k = self.times.iter().rposition(|x| *x <= t).unwrap_or(0);
+        let (t0, t1) = (self.times[k], self.times[k + 1]);
+        let (v0, v1) = (self.values.item(k), self.values.item(k + 1));
+        let s = if t1 > t0 { (t - t0) / (t1 - t0) } else { 0.0 };
+        let width = self.values.width;
+
+        let interpolation = self.interpolations.get(k).map(|i| i.as_str()).unwrap_or("LINEAR");
+        let tangents = match (self.out_tangents.as_ref(), self.in_tangents.as_ref()) {
+            (Some(o), Some(i)) => Some((o.item(k), i.item(k + 1))),
+            _ => None,
+        };
+        (0..width).map(|c| {
+            match (interpolation, tangents) {
+                ("STEP", _) => v0[c],
+                ("BEZIER", Some((out, inn))) if out.len() == 2 * width && inn.len() == 2 * width => {
+                    // 2D control points of time and value
+                    let p1 = [out[2 * c], out[2 * c + 1]];
+                    let p2 = [inn[2 * c], inn[2 * c + 1]];
+                    bezier(t, [t0, v0[c]], p1, p2, [t1, v1[c]])
+                },
+                ("BEZIER", Some((out, inn))) | ("HERMITE", Some((out, inn)))
+                    if out.len() == width && inn.len() == width => {
+                    // Value tangents, as control points a third of the way
+                    // for bezier and slopes over the span for hermite
+                    let (m0, m1) = if interpolation == "BEZIER" {
+                        (3.0 * (out[c] - v0[c]), 3.0 * (v1[c] - inn[c]))
+                    } else {
+                        (out[c], inn[c])
+                    };
+                    let s2 = s * s;
+                    let s3 = s2 * s;
+                    (2.0 * s3 - 3.0 * s2 + 1.0) * v0[c] + (s3 - 2.0 * s2 + s) * m0
+                        + (-2.0 * s3 + 3.0 * s2) * v1[c] + (s3 - s2) * m1
+                },
+                _ => v0[c] + (v1[c] - v0[c]) * s,
+            }
+        }).collect()
+    }
+}
+
+/// The value of a 2D cubic bezier curve at time `t`, finding the curve
+/// parameter by bisection since time is monotonic along the curve
+fn bezier(t: f32, p0: [f32; 2], p1: [f32; 2], p2: [f32; 2], p3: [f32; 2]) -> f32 {
+    let at = |s: f32, i: usize| {
+        let r = 1.0 - s;
+        r * r * r * p0[i] + 3.0 * r * r * s * p1[i] + 3.0 * r * s * s * p2[i] + s * s * s * p3[i]
+    };
+    let (mut lo, mut hi) = (0.0f32, 1.0f32);
+    for _ in 0..32 {
+        let mid = (lo + hi) / 2.0;
+        if at(mid, 0) < t {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    at((lo + hi) / 2.0, 1)
+}
+
+/// Samples as glTF cubic spline keys of in-tangent, value and out-tangent,
+/// with tangents from the neighbouring samples
+fn cubic_spline(times: &[f32], values: &[Vec<f32>]) -> Vec<f32> {
+    let n = values.len();
+    let mut out = Vec::new();
+    for k in 0..n {
+        let (a, b) = (if k > 0 { k - 1 } else { k }, if k + 1 < n { k + 1 } else { k });
+        let dt = times[b] - times[a];
+        let tangent: Vec<f32> = (0..values[k].len())
+            .map(|c| if dt > 0.0 { (values[b][c] - values[a][c]) / dt } else { 0.0 })
+            .collect();
+        out.extend_from_slice(&tangent);
+        out.extend_from_slice(&values[k]);
+        out.extend_from_slice(&tangent);
+    }
+    out
+}
+
+/// Add the bind shape and the joints and weights of a skin to a primitive
+fn skin_primitive(p: &mut Primitive, skin: &Skin) {
+    for &mut (ref name, _, ref mut data) in &mut p.attributes {
+        match name.as_str() {
+            "POSITION" => for v in data.chunks_mut(3) {
+                let x = transform_point(&skin.bind_shape, [v[0], v[1], v[2]]);
+                v.copy_from_slice(&x);
+            },
+            "NORMAL" => for v in data.chunks_mut(3) {
+                let x = normalize(transform_vector(&skin.bind_shape, [v[0], v[1], v[2]]));
+                v.copy_from_slice(&x);
+            },
+            _ => {},
+        }
+    }
+    let mut joints = Vec::with_capacity(p.positions.len() * 4);
+    let mut weights = Vec::with_capacity(p.positions.len() * 4);
+    for i in &p.positions {
+        let w = skin.weights.get(*i).map(|w| w.as_slice()).unwrap_or(&[]);
+        for k in 0..4 {
+            let (j, x) = w.get(k).cloned().unwrap_or((0, 0.0));
+            joints.push(j as f32);
+            weights.push(x);
+        }
+    }
+    p.attributes.push(("JOINTS_0".to_string(), 4, joints));
+    p.attributes.push(("WEIGHTS_0".to_string(), 4, weights));
+}
+
+/// Find a descendant with a sid, depth first
+fn find_sid<'a>(e: &'a Element, sid: &str) -> Option<&'a Element> {
+    if e.attributes.get("sid").map(|s| s.as_str()) == Some(sid) {
+        return Some(e);
+    }
+    e.children.iter().filter_map(|c| find_sid(c, sid)).next()
+}
+
+fn components(typ: &str) -> usize {
+    match typ {
+        "VEC2" => 2,
+        "VEC3" => 3,
+        "VEC4" => 4,
+        "MAT4" => 16,
+        _ => 1,
+    }
+}
+
+fn push_u32(out: &mut Vec<u8>, v: u32) {
+    out.push(v as u8);
+    out.push((v >> 8) as u8);
+    out.push((v >> 16) as u8);
+    out.push((v >> 24) as u8);
+}
+
+fn to_matrix(v: &[f32]) -> Matrix {
+    let mut m = [0.0; 16];
+    m.copy_from_slice(&v[..16]);
+    m
+}
+
+/// glTF matrices are column-major
+fn transpose(m: &Matrix) -> Matrix {
+    let mut t = [0.0; 16];
+    for r in 0..4 {
+        for c in 0..4 {
+            t[c * 4 + r] = m[r * 4 + c];
+        }
+    }
+    t
+}
+
+/// Transpose a list of row-major matrices
+fn column_major(v: &[f32]) -> Vec<f32> {
+    v.chunks(16).flat_map(|m| transpose(&to_matrix(m)).to_vec()).collect()
+}
+
+fn scaling(s: f32) -> Matrix {
+    let mut m = IDENTITY;
+    m[0] = s;
+    m[5] = s;
+    m[10] = s;
+    m
+}
+
+/// Rotation from an up axis to Y up
+fn axis_rotation(up: UpAxis) -> Matrix {
+    let source = up.axes();
+    let target = UpAxis::YUP.axes();
+    let mut m = IDENTITY;
+    for i in 0..3 {
+        for j in 0..3 {
+            m[i * 4 + j] = (0..3).map(|k| target[k][i] * source[k][j]).sum();
+        }
+    }
+    m
+}
+
+/// Transform a direction, ignoring the translation
+fn transform_vector(m: &Matrix, v: [f32; 3]) -> [f32; 3] {
+    let mut out = [0.0; 3];
+    for r in 0..3 {
+        out[r] = m[r * 4] * v[0] + m[r * 4 + 1] * v[1] + m[r * 4 + 2] * v[2];
+    }
+    out
+}
+
+/// Split a matrix into a translation, a rotation quaternion and a scale.
+/// Shear is lost.
+fn decompose(m: &Matrix) -> ([f32; 3], [f32; 4], [f32; 3]) {
+    let t = [m[3], m[7], m[11]];
+    let column = |c: usize| [m[c], m[4 + c], m[8 + c]];
+    let length = |v: [f32; 3]| (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+    let mut s = [length(column(0)), length(column(1)), length(column(2))];
+    let det = m[0] * (m[5] * m[10] - m[6] * m[9]) - m[1] * (m[4] * m[10] - m[6] * m[8])
+        + m[2] * (m[4] * m[9] - m[5] * m[8]);
+    if det < 0.0 {
+        s[0] = -s[0];
+    }
+
+    // Rotation matrix, by rows
+    let mut r = [[0.0f32; 3]; 3];
+    for c in 0..3 {
+        let col = column(c);
+        let d = if s[c] == 0.0 { 1.0 } else { s[c] };
+        for i in 0..3 {
+            r[i][c] = col[i] / d;
+        }
+    }
+    let trace = r[0][0] + r[1][1] + r[2][2];
+    let q = if trace > 0.0 {
+        let w = (trace + 1.0).sqrt() * 2.0;
+        [(r[2][1] - r[1][2]) / w, (r[0][2] - r[2][0]) / w, (r[1][0] - r[0][1]) / w, w / 4.0]
+    } else if r[0][0] > r[1][1] && r[0][0] > r[2][2] {
+        let w = (1.0 + r[0][0] - r[1][1] - r[2][2]).sqrt() * 2.0;
+        [w / 4.0, (r[0][1] + r[1][0]) / w, (r[0][2] + r[2][0]) / w, (r[2][1] - r[1][2]) / w]
+    } else if r[1][1] > r[2][2] {
+        let w = (1.0 + r[1][1] - r[0][0] - r[2][2]).sqrt() * 2.0;
+        [(r[0][1] + r[1][0]) / w, w / 4.0, (r[1][2] + r[2][1]) / w, (r[0][2] - r[2][0]) / w]
+    } else {
+        let w = (1.0 + r[2][2] - r[0][0] - r[1][1]).sqrt() * 2.0;
+        [(r[0][2] + r[2][0]) / w, (r[1][2] + r[2][1]) / w, w / 4.0, (r[1][0] - r[0][1]) / w]
+    };
+    let len = (q[0] * q[0] + q[1] * q[1] + q[2] * q[2] + q[3] * q[3]).sqrt();
+    (t, [q[0] / len, q[1] / len, q[2] / len, q[3] / len], s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DATA: &'static str = r##"<?xml version="1.0" encoding="utf-8"?>
+<COLLADA xmlns="http://www.collada.org/2005/11/COLLADASchema" version="1.4.1">
+  <asset>
+    <created>2017-01-01T00:00:00Z</created>
+    <modified>2017-01-01T00:00:00Z</modified>
+    <up_axis>Z_UP</up_axis>
+  </asset>
+  <library_animations>
+    <animation id="spin">
+      <source id="spin-input">
+        <float_array id="spin-input-array" count="2">0 1</float_array>
+      </source>
+      <source id="spin-output">
+        <float_array id="spin-output-array" count="2">0 90</float_array>
+      </source>
+      <sampler id="spin-sampler">
+        <input semantic="INPUT" source="#spin-input"/>
+        <input semantic="OUTPUT" source="#spin-output"/>
+      </sampler>
+      <channel source="#spin-sampler" target="bone/rotZ.ANGLE"/>
+    </animation>
+  </library_animations>
+  <library_cameras>
+    <camera id="cam">
+      <optics>
+        <technique_common>
+          <perspective>
+            <yfov>45</yfov>
+            <aspect_ratio>1.5</aspect_ratio>
+            <znear>0.1</znear>
+            <zfar>100</zfar>
+          </perspective>
+        </technique_common>
+      </optics>
+    </camera>
+  </library_cameras>
+  <library_lights>
+    <light id="spot">
+      <technique_common>
+        <spot>
+          <color>2 2 1</color>
+          <falloff_angle>60</falloff_angle>
+        </spot>
+      </technique_common>
+    </light>
+    <light id="ambient">
+      <technique_common>
+        <ambient>
+          <color>1 1 1</color>
+        </ambient>
+      </technique_common>
+    </light>
+  </library_lights>
+  <library_images>
+    <image id="tex-image">
+      <init_from>tex.png</init_from>
+    </image>
+  </library_images>
+  <library_effects>
+    <effect id="fx">
+      <profile_COMMON>
+        <newparam sid="tex-surface">
+          <surface type="2D">
+            <init_from>tex-image</init_from>
+          </surface>
+        </newparam>
+        <newparam sid="tex-sampler">
+          <sampler2D>
+            <source>tex-surface</source>
+            <wrap_s>CLAMP</wrap_s>
+          </sampler2D>
+        </newparam>
+        <technique sid="common">
+          <phong>
+            <diffuse>
+              <texture texture="tex-sampler" texcoord="UV"/>
+            </diffuse>
+            <shininess>
+              <float>20</float>
+            </shininess>
+          </phong>
+        </technique>
+      </profile_COMMON>
+    </effect>
+  </library_effects>
+  <library_materials>
+    <material id="mat" name="Material">
+      <instance_effect url="#fx"/>
+    </material>
+  </library_materials>
+  <library_geometries>
+    <geometry id="quad" name="Quad">
+      <mesh>
+        <source id="quad-pos">
+          <float_array id="quad-pos-array" count="12">0 0 0 1 0 0 1 1 0 0 1 0</float_array>
+          <technique_common>
+            <accessor source="#quad-pos-array" count="4" stride="3">
+              <param name="X" type="float"/>
+              <param name="Y" type="float"/>
+              <param name="Z" type="float"/>
+            </accessor>
+          </technique_common>
+        </source>
+        <source id="quad-uv">
+          <float_array id="quad-uv-array" count="8">0 0 1 0 1 1 0 1</float_array>
+          <technique_common>
+            <accessor source="#quad-uv-array" count="4" stride="2">
+              <param name="S" type="float"/>
+              <param name="T" type="float"/>
+            </accessor>
+          </technique_common>
+        </source>
+        <vertices id="quad-vtx">
+          <input semantic="POSITION" source="#quad-pos"/>
+        </vertices>
+        <polylist count="1" material="m">
+          <input semantic="VERTEX" source="#quad-vtx" offset="0"/>
+          <input semantic="TEXCOORD" source="#quad-uv" offset="1" set="0"/>
+          <vcount>4</vcount>
+          <p>0 0 1 1 2 2 3 3</p>
+        </polylist>
+      </mesh>
+    </geometry>
+    <geometry id="quad-raised">
+      <mesh>
+        <source id="quad-raised-pos">
+          <float_array id="quad-raised-pos-array" count="12">0 0 1 1 0 1 1 1 1 0 1 1</float_array>
+          <technique_common>
+            <accessor source="#quad-raised-pos-array" count="4" stride="3"/>
+          </technique_common>
+        </source>
+        <vertices id="quad-raised-vtx">
+          <input semantic="POSITION" source="#quad-raised-pos"/>
+        </vertices>
+      </mesh>
+    </geometry>
+  </library_geometries>
+  <library_controllers>
+    <controller id="morph">
+      <morph source="#quad" method="NORMALIZED">
+        <source id="morph-targets">
+          <IDREF_array id="morph-targets-array" count="1">quad-raised</IDREF_array>
+        </source>
+        <source id="morph-weights">
+          <float_array id="morph-weights-array" count="1">0.5</float_array>
+        </source>
+        <targets>
+          <input semantic="MORPH_TARGET" source="#morph-targets"/>
+          <input semantic="MORPH_WEIGHT" source="#morph-weights"/>
+        </targets>
+      </morph>
+    </controller>
+    <controller id="skin">
+      <skin source="#morph">
+        <source id="skin-joints">
+          <Name_array id="skin-joints-array" count="1">bone</Name_array>
+        </source>
+        <source id="skin-binds">
+          <float_array id="skin-binds-array" count="16">1 0 0 0 0 1 0 0 0 0 1 0 0 0 0 1</float_array>
+          <technique_common>
+            <accessor source="#skin-binds-array" count="1" stride="16">
+              <param name="TRANSFORM" type="float4x4"/>
+            </accessor>
+          </technique_common>
+        </source>
+        <source id="skin-weights">
+          <float_array id="skin-weights-array" count="1">1</float_array>
+        </source>
+        <joints>
+          <input semantic="JOINT" source="#skin-joints"/>
+          <input semantic="INV_BIND_MATRIX" source="#skin-binds"/>
+        </joints>
+        <vertex_weights count="4">
+          <input semantic="JOINT" source="#skin-joints" offset="0"/>
+          <input semantic="WEIGHT" source="#skin-weights" offset="1"/>
+          <vcount>1 1 1 1</vcount>
+          <v>0 0 0 0 0 0 0 0</v>
+        </vertex_weights>
+      </skin>
+    </controller>
+  </library_controllers>
+  <library_visual_scenes>
+    <visual_scene id="scene">
+      <node id="bone" sid="bone" name="Bone" type="JOINT">
+        <rotate sid="rotZ">0 0 1 0</rotate>
+      </node>
+      <node id="model" name="Model">
+        <instance_controller url="#skin">
+          <skeleton>#bone</skeleton>
+          <bind_material>
+            <technique_common>
+              <instance_material symbol="m" target="#mat">
+                <bind_vertex_input semantic="UV" input_semantic="TEXCOORD" input_set="0"/>
+              </instance_material>
+            </technique_common>
+          </bind_material>
+        </instance_controller>
+      </node>
+      <node id="eye" name="Eye">
+        <translate>0 -5 0</translate>
+        <instance_camera url="#cam"/>
+        <instance_light url="#spot"/>
+        <instance_light url="#ambient"/>
+      </node>
+    </visual_scene>
+  </library_visual_scenes>
+  <scene>
+    <instance_visual_scene url="#scene"/>
+  </scene>
+</COLLADA>
+"##;
+
+    /// The floats of an accessor
+    fn floats(gltf: &Gltf, accessor: usize) -> Vec<f32> {
+        let a = &gltf.json["accessors"][accessor];
+        let view = &gltf.json["bufferViews"][a["bufferView"].as_f64().unwrap() as usize];
+        let start = view["byteOffset"].as_f64().unwrap() as usize;
+        let len = view["byteLength"].as_f64().unwrap() as usize;
+        gltf.bin[start..start + len].chunks(4)
+            .map(|b| f32::from_bits(b[0] as u32 | (b[1] as u32) << 8 | (b[2] as u32) << 16 | (b[3] as u32) << 24))
+            .collect()
+    }
+
+    fn index(j: &Json) -> usize {
+        j.as_f64().unwrap() as usize
+    }
+
+    #[test]
+    fn test_json() {
+        let mut j = Json::object(vec![("a", 1usize.into()), ("b", "x\"\n".into())]);
+        j.set("a", Json::Array(vec![true.into(), Json::Null, 0.1f32.into(), Json::Number(f64::NAN)]));
+        assert_eq!(j.to_string(), r#"{"a":[true,null,0.1,null],"b":"x\"\n"}"#);
+        assert_eq!(j["b"].as_str(), Some("x\"\n"));
+        assert_eq!(j["a"][2].as_f64(), Some(0.1));
+        assert_eq!(j["c"][0], Json::Null);
+    }
+
+    #[test]
+    fn test_export_gltf() {
+        let c = Collada::read(DATA.as_bytes()).unwrap();
+        let gltf = c.to_gltf(&GltfOptions::new());
+        let json = &gltf.json;
+        assert_eq!(json["asset"]["version"].as_str(), Some("2.0"));
+
+        // Z up gets a root node turning it to Y up
+        let roots = &json["scenes"][0]["nodes"];
+        assert_eq!(roots.len(), 1);
+        let root = &json["nodes"][index(&roots[0])];
+        assert_eq!(root["name"].as_str(), Some("root"));
+        assert_eq!(root["matrix"][6].as_f64(), Some(-1.0));
+        assert_eq!(root["children"].len(), 3);
+
+        let nodes = &json["nodes"];
+        let bone = index(&root["children"][0]);
+        assert_eq!(nodes[bone]["name"].as_str(), Some("Bone"));
+        assert!(nodes[bone]["matrix"] == Json::Null && nodes[bone]["rotation"].len() == 4);
+
+        // The skinned and morphed quad, with its polygon triangulated
+        let model = &nodes[index(&root["children"][1])];
+        let mesh = &json["meshes"][index(&model["mesh"])];
+        assert_eq!(mesh["name"].as_str(), Some("Quad"));
+        assert_eq!(mesh["weights"][0].as_f64(), Some(0.5));
+        let primitive = &mesh["primitives"][0];
+        assert_eq!(primitive["mode"].as_f64(), Some(4.0));
+        assert_eq!(json["accessors"][index(&primitive["indices"])]["count"].as_f64(), Some(6.0));
+        let attributes = &primitive["attributes"];
+        let position = &json["accessors"][index(&attributes["POSITION"])];
+        assert_eq!(position["count"].as_f64(), Some(4.0));
+        assert_eq!(position["max"][1].as_f64(), Some(1.0));
+        assert_eq!(&floats(&gltf, index(&attributes["TEXCOORD_0"]))[..4], &[0.0, 1.0, 1.0, 1.0]);
+        assert_eq!(&floats(&gltf, index(&attributes["WEIGHTS_0"]))[..4], &[1.0, 0.0, 0.0, 0.0]);
+        assert!(attributes["JOINTS_0"] != Json::Null);
+        let target = floats(&gltf, index(&primitive["targets"][0]["POSITION"]));
+        assert_eq!(target, vec![0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0]);
+
+        let skin = &json["skins"][index(&model["skin"])];
+        assert_eq!(index(&skin["joints"][0]), bone);
+        assert_eq!(index(&skin["skeleton"]), bone);
+        assert_eq!(floats(&gltf, index(&skin["inverseBindMatrices"]))[15], 1.0);
+
+        let material = &json["materials"][index(&primitive["material"])];
+        assert_eq!(material["name"].as_str(), Some("Material"));
+        let pbr = &material["pbrMetallicRoughness"];
+        assert!((pbr["roughnessFactor"].as_f64().unwrap() - (2.0f64 / 22.0).sqrt()).abs() < 1e-6);
+        let texture = &json["textures"][index(&pbr["baseColorTexture"]["index"])];
+        assert_eq!(json["images"][index(&texture["source"])]["uri"].as_str(), Some("tex.png"));
+        assert_eq!(json["samplers"][index(&texture["sampler"])]["wrapS"].as_f64(), Some(33071.0));
+
+        // The ambient light is left out
+        let eye = &nodes[index(&root["children"][2])];
+        assert_eq!(eye["matrix"][13].as_f64(), Some(-5.0));
+        let camera = &json["cameras"][index(&eye["camera"])];
+        assert_eq!(camera["type"].as_str(), Some("perspective"));
+        assert!((camera["perspective"]["yfov"].as_f64().unwrap() - 0.785398).abs() < 1e-5);
+        let lights = &json["extensions"]["KHR_lights_punctual"]["lights"];
+        assert_eq!(lights.len(), 1);
+        let light = &lights[index(&eye["extensions"]["KHR_lights_punctual"]["light"])];
+        assert_eq!(light["intensity"].as_f64(), Some(2.0));
+        assert_eq!(light["color"][2].as_f64(), Some(0.5));
+        assert!((light["spot"]["outerConeAngle"].as_f64().unwrap() - 0.523599).abs() < 1e-5);
+        assert_eq!(json["extensionsUsed"][0].as_str(), Some("KHR_lights_punctual"));
+
+        // Resampled at 30 samples a second, ending a quarter turn about Z
+        let animation = &json["animations"][0];
+        assert_eq!(animation["channels"].len(), 3);
+        let rotation = &animation["channels"][1];
+        assert_eq!(rotation["target"]["path"].as_str(), Some("rotation"));
+        assert_eq!(index(&rotation["target"]["node"]), bone);
+        let sampler = &animation["samplers"][index(&rotation["sampler"])];
+        assert_eq!(sampler["interpolation"].as_str(), Some("LINEAR"));
+        assert_eq!(json["accessors"][index(&sampler["input"])]["count"].as_f64(), Some(31.0));
+        let q = floats(&gltf, index(&sampler["output"]));
+        assert_eq!(q.len(), 31 * 4);
+        let half = 0.5f32.sqrt();
+        for (a, b) in q[120..].iter().zip(&[0.0, 0.0, half, half]) {
+            assert!((a - b).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_export_gltf_key_times() {
+        // Key times that aren't finite leave the animation out
+        let data = DATA.replace(">0 1</float_array>", ">0 INF</float_array>");
+        let gltf = Collada::read(data.as_bytes()).unwrap().to_gltf(&GltfOptions::new());
+        assert!(gltf.json["animations"] == Json::Null);
+
+        // Long animations are sampled less often than the sample rate
+        let data = DATA.replace(">0 1</float_array>", ">0 1e9</float_array>");
+        let gltf = Collada::read(data.as_bytes()).unwrap().to_gltf(&GltfOptions::new());
+        let json = &gltf.json;
+        let sampler = &json["animations"][0]["samplers"][0];
+        let count = json["accessors"][index(&sampler["input"])]["count"].as_f64().unwrap() as usize;
+        assert!(count > 2 && count <= MAX_SAMPLES + 2, "{}", count);
+    }
+
+    #[test]
+    fn test_gltf_files() {
+        let c = Collada::read(DATA.as_bytes()).unwrap();
+        let mut options = GltfOptions::new();
+        options.interpolation = GltfInterpolation::CubicSpline;
+        let gltf = c.to_gltf(&options);
+        assert_eq!(gltf.json["buffers"][0]["byteLength"].as_f64(), Some(gltf.bin.len() as f64));
+        assert!(gltf.to_json("model.bin").contains(r#""buffers":[{"byteLength":"#));
+        assert!(gltf.to_json("model.bin").contains(r#""uri":"model.bin""#));
+        assert!(!gltf.json.to_string().contains("model.bin"));
+
+        // Cubic splines have tangents around every value
+        let sampler = &gltf.json["animations"][0]["samplers"][1];
+        assert_eq!(sampler["interpolation"].as_str(), Some("CUBICSPLINE"));
+        assert_eq!(gltf.json["accessors"][index(&sampler["output"])]["count"].as_f64(), Some(93.0));
+
+        let glb = gltf.to_glb();
+        assert_eq!(&glb[..4], b"glTF");
+        assert_eq!(glb[8] as usize | (glb[9] as usize) << 8 | (glb[10] as usize) << 16, glb.len());
+        assert_eq!(&glb[16..20], b"JSON");
+        assert_eq!(glb.len() % 4, 0);
+    }
+}
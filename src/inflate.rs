@@ -0,0 +1,315 @@
+//! A decompressor for raw deflate streams (RFC 1951), as used by zip
+//! archives
+
+/// Base lengths of the length codes 257 to 285
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31,
+    35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2,
+    3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+
+/// Base distances of the distance codes 0 to 29
+const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193,
+    257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DIST_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6,
+    7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13,
+];
+
+/// Order of the code length code lengths of a dynamic block
+const CLEN_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+/// Reads bits least significant first
+struct Bits<'a> {
+    data: &'a [u8],
+    pos: usize,
+    buf: u32,
+    count: u32,
+}
+
+impl<'a> Bits<'a> {
+    fn need(&mut self, n: u32) -> Result<u32, String> {
+        while self.count < n {
+            let b = match self.data.get(self.pos) {
+                Some(b) => *b,
+                None => return Err("unexpected end of deflate data".to_string()),
+            };
+            self.pos += 1;
+            self.buf |= (b as u32) << self.count;
+            self.count += 8;
+        }
+        let v = self.buf & ((1u64 << n) - 1) as u32;
+        self.buf = if n == 32 { 0 } else { self.buf >> n };
+        self.count -= n;
+        Ok(v)
+    }
+
+    /// Skip to the next byte boundary
+    fn align(&mut self) {
+        self.buf = 0;
+        self.count = 0;
+    }
+}
+
+/// A canonical huffman code, as the number of codes of each length and the
+/// symbols ordered by code
+struct Huffman {
+    counts: [u16; 16],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Result<Huffman, String> {
+        let mut counts = [0u16; 16];
+        for l in lengths {
+            counts[*l as usize] += 1;
+        }
+        counts[0] = 0;
+
+        // Over-subscribed codes can't be decoded, incomplete ones can
+        let mut left: i32 = 1;
+        for len in 1..16 {
+            left = left * 2 - counts[len] as i32;
+            if left < 0 {
+                return Err("invalid huffman code lengths".to_string());
+            }
+        }
+
+        let mut offsets = [0u16; 16];
+        for len in 1..15 {
+            offsets[len + 1] = offsets[len] + counts[len];
+        }
+        let mut symbols = vec![0; lengths.len()];
+        for (s, l) in lengths.iter().enumerate() {
+            if *l != 0 {
+                symbols[offsets[*l as usize] as usize] = s as u16;
+                offsets[*l as usize] += 1;
+            }
+        }
+        Ok(Huffman {
+            counts: counts,
+            symbols: symbols,
+        })
+    }
+
+    fn decode(&self, bits: &mut Bits) -> Result<u16, String> {
+        let mut code: i32 = 0;
+        let mut first: i32 = 0;
+        let mut index: i32 = 0;
+        for len in 1..16 {
+            code |= try!(bits.need(1)) as i32;
+            let count = self.counts[len] as i32;
+            if code - count < first {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err("invalid huffman code".to_string())
+    }
+}
+
+/// Decompress a raw deflate stream of at most `limit` bytes, failing as soon
+/// as the output would grow past it
+pub fn inflate(data: &[u8], limit: usize) -> Result<Vec<u8>, String> {
+    let mut bits = Bits {
+        data: data,
+        pos: 0,
+        buf: 0,
+        count: 0,
+    };
+    let mut out = Vec::with_capacity(limit.min(data.len().saturating_mul(3)));
+    loop {
+        let last = try!(bits.need(1)) == 1;
+        match try!(bits.need(2)) {
+            0 => {
+                bits.align();
+                if bits.pos + 4 > data.len() {
+                    return Err("unexpected end of deflate data".to_string());
+                }
+                let len = data[bits.pos] as usize | (data[bits.pos + 1] as usize) << 8;
+                let nlen = data[bits.pos + 2] as usize | (data[bits.pos + 3] as usize) << 8;
+                if len != !nlen & 0xffff {
+                    return Err("invalid stored block length".to_string());
+                }
+                bits.pos += 4;
+                if bits.pos + len > data.len() {
+                    return Err("unexpected end of deflate data".to_string());
+                }
+                if out.len() + len > limit {
+                    return Err(too_long(limit));
+                }
+                out.extend_from_slice(&data[bits.pos..bits.pos + len]);
+                bits.pos += len;
+            },
+            1 => {
+                let mut lengths = [0u8; 288];
+                for (i, l) in lengths.iter_mut().enumerate() {
+                    *l = if i < 144 {
+                        8
+                    } else if i < 256 {
+                        9
+                    } else if i < 280 {
+                        7
+                    } else {
+                        8
+                    };
+                }
+                let lit = try!(Huffman::new(&lengths));
+                let dist = try!(Huffman::new(&[5; 30]));
+                try!(inflate_block(&mut bits, &mut out, limit, &lit, &dist));
+            },
+            2 => {
+                let (lit, dist) = try!(dynamic_tables(&mut bits));
+                try!(inflate_block(&mut bits, &mut out, limit, &lit, &dist));
+            },
+            _ => return Err("invalid deflate block type".to_string()),
+        }
+        if last {
+            return Ok(out);
+        }
+    }
+}
+
+/// Read the huffman codes of a dynamic block
+fn dynamic_tables(bits: &mut Bits) -> Result<(Huffman, Huffman), String> {
+    let nlen = try!(bits.need(5)) as usize + 257;
+    let ndist = try!(bits.need(5)) as usize + 1;
+    let ncode = try!(bits.need(4)) as usize + 4;
+    if nlen > 286 || ndist > 30 {
+        return Err("invalid dynamic block code counts".to_string());
+    }
+
+    let mut clens = [0u8; 19];
+    for i in 0..ncode {
+        clens[CLEN_ORDER[i]] = try!(bits.need(3)) as u8;
+    }
+    let clen = try!(Huffman::new(&clens));
+
+    let mut lengths = vec![0u8; nlen + ndist];
+    let mut i = 0;
+    while i < nlen + ndist {
+        let sym = try!(clen.decode(bits));
+        let (value, repeat) = match sym {
+            _ if sym < 16 => (sym as u8, 1),
+            16 => {
+                if i == 0 {
+                    return Err("repeated code length without a previous length".to_string());
+                }
+                (lengths[i - 1], 3 + try!(bits.need(2)) as usize)
+            },
+            17 => (0, 3 + try!(bits.need(3)) as usize),
+            _ => (0, 11 + try!(bits.need(7)) as usize),
+        };
+        if i + repeat > nlen + ndist {
+            return Err("too many code lengths".to_string());
+        }
+        for l in &mut lengths[i..i + repeat] {
+            *l = value;
+        }
+        i += repeat;
+    }
+    if lengths[256] == 0 {
+        return Err("missing end of block code".to_string());
+    }
+    Ok((try!(Huffman::new(&lengths[..nlen])), try!(Huffman::new(&lengths[nlen..]))))
+}
+
+fn too_long(limit: usize) -> String {
+    format!("deflate data is longer than {} bytes", limit)
+}
+
+/// Decode the symbols of a compressed block up to its end
+fn inflate_block(bits: &mut Bits, out: &mut Vec<u8>, limit: usize, lit: &Huffman, dist: &Huffman)
+    -> Result<(), String>
+{
+    loop {
+        let sym = try!(lit.decode(bits)) as usize;
+        if sym < 256 {
+            if out.len() >= limit {
+                return Err(too_long(limit));
+            }
+            out.push(sym as u8);
+            continue;
+        }
+        if sym == 256 {
+            return Ok(());
+        }
+
+        let sym = sym - 257;
+        if sym >= 29 {
+            return Err("invalid length code".to_string());
+        }
+        let len = LENGTH_BASE[sym] as usize + try!(bits.need(LENGTH_EXTRA[sym] as u32)) as usize;
+        let d = try!(dist.decode(bits)) as usize;
+        if d >= 30 {
+            return Err("invalid distance code".to_string());
+        }
+        let distance = DIST_BASE[d] as usize + try!(bits.need(DIST_EXTRA[d] as u32)) as usize;
+        if distance > out.len() {
+            return Err("distance past the start of the data".to_string());
+        }
+        if out.len() + len > limit {
+            return Err(too_long(limit));
+        }
+        // Copies may overlap what they write, e.g. runs of one byte
+        let start = out.len() - distance;
+        for i in 0..len {
+            let b = out[start + i];
+            out.push(b);
+        }
+    }
+}
+
+/// The CRC-32 checksum zip archives store for each entry
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for b in data {
+        crc ^= *b as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { crc >> 1 ^ 0xedb8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inflate() {
+        // Stored block
+        let stored = [0x01, 0x03, 0x00, 0xfc, 0xff, b'a', b'b', b'c'];
+        assert_eq!(inflate(&stored, 3).unwrap(), &b"abc"[..]);
+        assert!(inflate(&stored, 2).is_err());
+
+        // Fixed huffman codes with a back reference, from zlib
+        let data = [0x4b, 0x4c, 0x4a, 0x4e, 0x84, 0x21, 0x00];
+        assert_eq!(inflate(&data, 12).unwrap(), &b"abcabcabcabc"[..]);
+        assert!(inflate(&data, 11).is_err());
+        assert!(inflate(&data, 2).is_err());
+
+        // Dynamic huffman codes, from zlib
+        let data = [
+            0x15, 0xc7, 0xc1, 0x09, 0x00, 0x30, 0x10, 0xc3, 0xb0, 0x59, 0x1d, 0xf0, 0x06, 0xde,
+            0x9f, 0x6b, 0x5f, 0x42, 0x28, 0x85, 0xe8, 0x96, 0x5f, 0xc1, 0x68, 0xf5, 0x76,
+        ];
+        assert_eq!(inflate(&data, 32).unwrap(), &b"aeeattaeaeebbteeaeeeaaetatbtteae"[..]);
+
+        assert!(inflate(&[0x4b, 0x4c], 100).is_err());
+        assert!(inflate(&[0x07], 100).is_err());
+    }
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+    }
+}
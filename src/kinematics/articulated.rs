@@ -0,0 +1,1094 @@
+use xmltree::{Element};
+use context::{ParseContext};
+use core::{Asset, Extra, FormulaRef, HasTechniques, Technique, TechniqueCommon, Transform, TransformKind, UnknownContent};
+use error::{ColladaError};
+use fx::{NewParam, ParamValue, SetParam};
+use kinematics::{KinematicsModel, LibraryKinematicsModels};
+use traits::{XmlConversion};
+use utils;
+
+/// Binds a symbol of an instantiated kinematics model or articulated system
+/// to a value or parameter
+#[derive(Debug)]
+pub struct KinematicsBind {
+    pub symbol: String,
+    pub value: ParamValue,
+}
+
+impl KinematicsBind {
+    pub fn new() -> KinematicsBind {
+        KinematicsBind {
+            symbol: String::from(""),
+            value: ParamValue::Float(0.0),
+        }
+    }
+}
+
+impl XmlConversion for KinematicsBind {
+    fn parse_with(&mut self, e: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        self.symbol = try!(utils::require_attr(e, "symbol"));
+        self.value = try!(ParamValue::parse_wrapped(e, ctx));
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut b = self.value.encode_wrapped("bind");
+        b.attributes.insert("symbol".to_string(), self.symbol.clone());
+        b
+    }
+}
+
+/// Parse the `<bind>`, `<newparam>` and `<setparam>` children shared by
+/// instances and motion infos, returns false for any other child
+fn parse_param_child(c: &Element, ctx: &mut ParseContext, binds: &mut Vec<KinematicsBind>,
+                     newparams: &mut Vec<NewParam>, setparams: &mut Vec<SetParam>)
+                     -> Result<bool, ColladaError> {
+    match c.name.as_str() {
+        "bind" => {
+            let mut b = KinematicsBind::new();
+            try!(ctx.parse(&mut b, c));
+            binds.push(b);
+        },
+        "newparam" => {
+            let mut n = NewParam::new();
+            try!(ctx.parse(&mut n, c));
+            newparams.push(n);
+        },
+        "setparam" => {
+            let mut s = SetParam::new();
+            try!(ctx.parse(&mut s, c));
+            setparams.push(s);
+        },
+        _ => return Ok(false),
+    }
+    Ok(true)
+}
+
+fn encode_param_children(e: &mut Element, binds: &[KinematicsBind], newparams: &[NewParam],
+                         setparams: &[SetParam]) {
+    for b in binds {
+        e.children.push(b.encode());
+    }
+    for n in newparams {
+        e.children.push(n.encode());
+    }
+    for s in setparams {
+        e.children.push(s.encode());
+    }
+}
+
+/// Instantiates a kinematics model or an articulated system
+#[derive(Debug)]
+pub struct KinematicsInstance {
+    /// Element name, `instance_kinematics_model` or `instance_articulated_system`
+    pub element: String,
+    pub url: String,
+    pub sid: Option<String>,
+    pub name: Option<String>,
+    pub binds: Vec<KinematicsBind>,
+    pub newparams: Vec<NewParam>,
+    pub setparams: Vec<SetParam>,
+    pub extras: Vec<Extra>,
+    pub unknown: UnknownContent,
+}
+
+impl KinematicsInstance {
+    pub fn new(element: &str) -> KinematicsInstance {
+        KinematicsInstance {
+            element: element.to_string(),
+            url: String::from(""),
+            sid: None,
+            name: None,
+            binds: Vec::new(),
+            newparams: Vec::new(),
+            setparams: Vec::new(),
+            extras: Vec::new(),
+            unknown: UnknownContent::new(),
+        }
+    }
+
+    /// Find the instantiated kinematics model in the libraries
+    pub fn kinematics_model<'a>(&self, libraries: &'a [LibraryKinematicsModels])
+        -> Option<&'a KinematicsModel>
+    {
+        let id = match utils::url_id(&self.url) {
+            Some(x) => x,
+            None => return None,
+        };
+        libraries.iter().filter_map(|l| l.get(id)).next()
+    }
+
+    /// Find the instantiated articulated system in the libraries
+    pub fn articulated_system<'a>(&self, libraries: &'a [LibraryArticulatedSystems])
+        -> Option<&'a ArticulatedSystem>
+    {
+        let id = match utils::url_id(&self.url) {
+            Some(x) => x,
+            None => return None,
+        };
+        libraries.iter().filter_map(|l| l.get(id)).next()
+    }
+}
+
+impl XmlConversion for KinematicsInstance {
+    fn parse_with(&mut self, e: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        ctx.unknown_attributes(e, &["url", "sid", "name"], &mut self.unknown);
+        self.element = e.name.clone();
+        self.url = try!(utils::require_attr(e, "url"));
+        self.sid = utils::get_attr(e, "sid");
+        self.name = utils::get_attr(e, "name");
+
+        for c in &e.children {
+            if try!(parse_param_child(c, ctx, &mut self.binds, &mut self.newparams, &mut self.setparams)) {
+                continue;
+            }
+            match c.name.as_str() {
+                "extra" => {
+                    let mut x = Extra::new();
+                    try!(ctx.parse(&mut x, c));
+                    self.extras.push(x);
+                },
+                _ => try!(ctx.unknown_child(e, c, &mut self.unknown)),
+            }
+        }
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut i = Element::new(&self.element);
+        i.attributes.insert("url".to_string(), self.url.clone());
+        utils::set_attr(&mut i, "sid", &self.sid);
+        utils::set_attr(&mut i, "name", &self.name);
+
+        encode_param_children(&mut i, &self.binds, &self.newparams, &self.setparams);
+        for x in &self.extras {
+            i.children.push(x.encode());
+        }
+        self.unknown.encode_into(&mut i);
+        i
+    }
+}
+
+/// Limits of a kinematics axis, which may reference parameters
+#[derive(Debug)]
+pub struct AxisLimits {
+    pub min: ParamValue,
+    pub max: ParamValue,
+}
+
+/// An index of a kinematics axis, e.g. for a controller
+#[derive(Debug)]
+pub struct AxisIndex {
+    pub semantic: Option<String>,
+    pub value: ParamValue,
+}
+
+/// Kinematic information of a joint axis of an articulated system
+#[derive(Debug)]
+pub struct KinematicsAxisInfo {
+    pub sid: Option<String>,
+    pub name: Option<String>,
+
+    /// Sid reference of the joint axis, e.g. `kmodel/joint0/axis0`
+    pub axis: String,
+    pub newparams: Vec<NewParam>,
+    pub active: Option<ParamValue>,
+    pub locked: Option<ParamValue>,
+    pub indices: Vec<AxisIndex>,
+    pub limits: Option<AxisLimits>,
+
+    pub formulas: Vec<FormulaRef>,
+}
+
+impl KinematicsAxisInfo {
+    pub fn new() -> KinematicsAxisInfo {
+        KinematicsAxisInfo {
+            sid: None,
+            name: None,
+            axis: String::from(""),
+            newparams: Vec::new(),
+            active: None,
+            locked: None,
+            indices: Vec::new(),
+            limits: None,
+            formulas: Vec::new(),
+        }
+    }
+}
+
+impl XmlConversion for KinematicsAxisInfo {
+    fn parse_with(&mut self, e: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        self.sid = utils::get_attr(e, "sid");
+        self.name = utils::get_attr(e, "name");
+        self.axis = try!(utils::require_attr(e, "axis"));
+
+        for c in &e.children {
+            match c.name.as_str() {
+                "newparam" => {
+                    let mut n = NewParam::new();
+                    try!(ctx.parse(&mut n, c));
+                    self.newparams.push(n);
+                },
+                "active" => self.active = Some(try!(ParamValue::parse_wrapped(c, ctx))),
+                "locked" => self.locked = Some(try!(ParamValue::parse_wrapped(c, ctx))),
+                "index" => self.indices.push(AxisIndex {
+                    semantic: utils::get_attr(c, "semantic"),
+                    value: try!(ParamValue::parse_wrapped(c, ctx)),
+                }),
+                "limits" => {
+                    let (mut min, mut max) = (None, None);
+                    for l in &c.children {
+                        match l.name.as_str() {
+                            "min" => min = Some(try!(ParamValue::parse_wrapped(l, ctx))),
+                            "max" => max = Some(try!(ParamValue::parse_wrapped(l, ctx))),
+                            _ => return Err(ColladaError::InvalidChild{
+                                child: l.name.clone(),
+                                parent: "limits".to_string(),
+                            }),
+                        }
+                    }
+                    match (min, max) {
+                        (Some(min), Some(max)) => self.limits = Some(AxisLimits{min: min, max: max}),
+                        (min, _) => return Err(ColladaError::MissingElement{
+                            structure: "limits".to_string(),
+                            elem: if min.is_none() { "min" } else { "max" }.to_string(),
+                        }),
+                    }
+                },
+                "formula" | "instance_formula" => self.formulas.push(try!(FormulaRef::parse(c, ctx))),
+                _ => return Err(ColladaError::InvalidChild{
+                    child: c.name.clone(),
+                    parent: "axis_info".to_string(),
+                }),
+            }
+        }
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut a = Element::new("axis_info");
+        utils::set_attr(&mut a, "sid", &self.sid);
+        utils::set_attr(&mut a, "name", &self.name);
+        a.attributes.insert("axis".to_string(), self.axis.clone());
+
+        for n in &self.newparams {
+            a.children.push(n.encode());
+        }
+        if let Some(ref x) = self.active {
+            a.children.push(x.encode_wrapped("active"));
+        }
+        if let Some(ref x) = self.locked {
+            a.children.push(x.encode_wrapped("locked"));
+        }
+        for i in &self.indices {
+            let mut x = i.value.encode_wrapped("index");
+            utils::set_attr(&mut x, "semantic", &i.semantic);
+            a.children.push(x);
+        }
+        if let Some(ref l) = self.limits {
+            let mut x = Element::new("limits");
+            x.children.push(l.min.encode_wrapped("min"));
+            x.children.push(l.max.encode_wrapped("max"));
+            a.children.push(x);
+        }
+        for f in &self.formulas {
+            a.children.push(f.encode());
+        }
+        a
+    }
+}
+
+/// A coordinate frame of an articulated system relative to a link, e.g.
+/// `<frame_tip link="arm/wrist">`
+#[derive(Debug)]
+pub struct Frame {
+    /// Element name, e.g. `frame_origin` or `frame_tcp`
+    pub element: String,
+    pub link: Option<String>,
+    pub transforms: Vec<Transform>,
+}
+
+impl Frame {
+    pub fn new(element: &str) -> Frame {
+        Frame {
+            element: element.to_string(),
+            link: None,
+            transforms: Vec::new(),
+        }
+    }
+}
+
+impl XmlConversion for Frame {
+    fn parse_with(&mut self, e: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        self.element = e.name.clone();
+        self.link = match e.name.as_str() {
+            "frame_origin" | "frame_tip" => Some(try!(utils::require_attr(e, "link"))),
+            _ => utils::get_attr(e, "link"),
+        };
+
+        for c in &e.children {
+            match c.name.as_str() {
+                "translate" | "rotate" => {
+                    let mut t = Transform::new(TransformKind::Translate);
+                    try!(ctx.parse(&mut t, c));
+                    self.transforms.push(t);
+                },
+                _ => return Err(ColladaError::InvalidChild{
+                    child: c.name.clone(),
+                    parent: e.name.clone(),
+                }),
+            }
+        }
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut f = Element::new(&self.element);
+        utils::set_attr(&mut f, "link", &self.link);
+        for t in &self.transforms {
+            f.children.push(t.encode());
+        }
+        f
+    }
+}
+
+/// The common technique of a kinematics system: its axes and frames
+#[derive(Debug)]
+pub struct KinematicsCommon {
+    pub axis_info: Vec<KinematicsAxisInfo>,
+    pub frame_origin: Frame,
+    pub frame_tip: Frame,
+
+    /// Tool center point
+    pub frame_tcp: Option<Frame>,
+    pub frame_object: Option<Frame>,
+}
+
+impl KinematicsCommon {
+    pub fn new() -> KinematicsCommon {
+        KinematicsCommon {
+            axis_info: Vec::new(),
+            frame_origin: Frame::new("frame_origin"),
+            frame_tip: Frame::new("frame_tip"),
+            frame_tcp: None,
+            frame_object: None,
+        }
+    }
+}
+
+impl TechniqueCommon for KinematicsCommon {
+    fn parse_child(&mut self, c: &Element, ctx: &mut ParseContext) -> Result<bool, ColladaError> {
+        match c.name.as_str() {
+            "axis_info" => {
+                let mut a = KinematicsAxisInfo::new();
+                try!(ctx.parse(&mut a, c));
+                self.axis_info.push(a);
+            },
+            "frame_origin" => try!(ctx.parse(&mut self.frame_origin, c)),
+            "frame_tip" => try!(ctx.parse(&mut self.frame_tip, c)),
+            "frame_tcp" | "frame_object" => {
+                let mut f = Frame::new(&c.name);
+                try!(ctx.parse(&mut f, c));
+                if c.name == "frame_tcp" {
+                    self.frame_tcp = Some(f);
+                } else {
+                    self.frame_object = Some(f);
+                }
+            },
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    fn encode_children(&self, tc: &mut Element) {
+        for a in &self.axis_info {
+            tc.children.push(a.encode());
+        }
+        tc.children.push(self.frame_origin.encode());
+        tc.children.push(self.frame_tip.encode());
+        if let Some(ref f) = self.frame_tcp {
+            tc.children.push(f.encode());
+        }
+        if let Some(ref f) = self.frame_object {
+            tc.children.push(f.encode());
+        }
+    }
+}
+
+/// The kinematics of an articulated system: its kinematics models, axes
+/// and frames
+#[derive(Debug)]
+pub struct Kinematics {
+    pub instance_kinematics_models: Vec<KinematicsInstance>,
+    pub common: KinematicsCommon,
+    pub techniques: Vec<Technique>,
+    pub extras: Vec<Extra>,
+    pub unknown: UnknownContent,
+}
+
+impl Kinematics {
+    pub fn new() -> Kinematics {
+        Kinematics {
+            instance_kinematics_models: Vec::new(),
+            common: KinematicsCommon::new(),
+            techniques: Vec::new(),
+            extras: Vec::new(),
+            unknown: UnknownContent::new(),
+        }
+    }
+}
+
+impl XmlConversion for Kinematics {
+    fn parse_with(&mut self, e: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        ctx.unknown_attributes(e, &[], &mut self.unknown);
+        let mut frames = (false, false);
+        for c in &e.children {
+            match c.name.as_str() {
+                "instance_kinematics_model" => {
+                    let mut i = KinematicsInstance::new("instance_kinematics_model");
+                    try!(ctx.parse(&mut i, c));
+                    self.instance_kinematics_models.push(i);
+                },
+                "technique_common" => {
+                    try!(self.common.parse_common(c, ctx));
+                    frames = (c.get_child("frame_origin").is_some(), c.get_child("frame_tip").is_some());
+                },
+                "technique" => {
+                    let mut t = Technique::new();
+                    try!(ctx.parse(&mut t, c));
+                    self.techniques.push(t);
+                },
+                "extra" => {
+                    let mut x = Extra::new();
+                    try!(ctx.parse(&mut x, c));
+                    self.extras.push(x);
+                },
+                _ => try!(ctx.unknown_child(e, c, &mut self.unknown)),
+            }
+        }
+
+        if !frames.0 || !frames.1 {
+            return Err(ColladaError::MissingElement{
+                structure: "kinematics".to_string(),
+                elem: if frames.0 { "frame_tip" } else { "frame_origin" }.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut k = Element::new("kinematics");
+        for i in &self.instance_kinematics_models {
+            k.children.push(i.encode());
+        }
+        k.children.push(self.common.encode_common());
+        for t in &self.techniques {
+            k.children.push(t.encode());
+        }
+        for x in &self.extras {
+            k.children.push(x.encode());
+        }
+        self.unknown.encode_into(&mut k);
+        k
+    }
+}
+
+impl HasTechniques for Kinematics {
+    type Common = KinematicsCommon;
+
+    fn technique_common(&self) -> &KinematicsCommon {
+        &self.common
+    }
+
+    fn techniques(&self) -> &[Technique] {
+        &self.techniques
+    }
+}
+
+/// Dynamic limits of a joint axis (`<axis_info>`) or of the end effector
+/// (`<effector_info>`) of a motion system
+#[derive(Debug)]
+pub struct MotionInfo {
+    /// Element name, `axis_info` or `effector_info`
+    pub element: String,
+    pub sid: Option<String>,
+    pub name: Option<String>,
+
+    /// Sid reference of the kinematics axis, only for `<axis_info>`
+    pub axis: Option<String>,
+    pub binds: Vec<KinematicsBind>,
+    pub newparams: Vec<NewParam>,
+    pub setparams: Vec<SetParam>,
+    pub speed: Option<ParamValue>,
+    pub acceleration: Option<ParamValue>,
+    pub deceleration: Option<ParamValue>,
+    pub jerk: Option<ParamValue>,
+}
+
+impl MotionInfo {
+    pub fn new(element: &str) -> MotionInfo {
+        MotionInfo {
+            element: element.to_string(),
+            sid: None,
+            name: None,
+            axis: None,
+            binds: Vec::new(),
+            newparams: Vec::new(),
+            setparams: Vec::new(),
+            speed: None,
+            acceleration: None,
+            deceleration: None,
+            jerk: None,
+        }
+    }
+}
+
+impl XmlConversion for MotionInfo {
+    fn parse_with(&mut self, e: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        self.element = e.name.clone();
+        self.sid = utils::get_attr(e, "sid");
+        self.name = utils::get_attr(e, "name");
+        if e.name == "axis_info" {
+            self.axis = Some(try!(utils::require_attr(e, "axis")));
+        }
+
+        for c in &e.children {
+            if try!(parse_param_child(c, ctx, &mut self.binds, &mut self.newparams, &mut self.setparams)) {
+                continue;
+            }
+            match c.name.as_str() {
+                "speed" => self.speed = Some(try!(ParamValue::parse_wrapped(c, ctx))),
+                "acceleration" => self.acceleration = Some(try!(ParamValue::parse_wrapped(c, ctx))),
+                "deceleration" => self.deceleration = Some(try!(ParamValue::parse_wrapped(c, ctx))),
+                "jerk" => self.jerk = Some(try!(ParamValue::parse_wrapped(c, ctx))),
+                _ => return Err(ColladaError::InvalidChild{
+                    child: c.name.clone(),
+                    parent: e.name.clone(),
+                }),
+            }
+        }
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut m = Element::new(&self.element);
+        utils::set_attr(&mut m, "sid", &self.sid);
+        utils::set_attr(&mut m, "name", &self.name);
+        utils::set_attr(&mut m, "axis", &self.axis);
+
+        encode_param_children(&mut m, &self.binds, &self.newparams, &self.setparams);
+        let values = [("speed", &self.speed), ("acceleration", &self.acceleration),
+                      ("deceleration", &self.deceleration), ("jerk", &self.jerk)];
+        for &(name, value) in values.iter() {
+            if let Some(ref x) = *value {
+                m.children.push(x.encode_wrapped(name));
+            }
+        }
+        m
+    }
+}
+
+/// The common technique of a motion system: the dynamic limits of its axes
+/// and end effector
+#[derive(Debug)]
+pub struct MotionCommon {
+    pub axis_info: Vec<MotionInfo>,
+    pub effector_info: Option<MotionInfo>,
+}
+
+impl MotionCommon {
+    pub fn new() -> MotionCommon {
+        MotionCommon {
+            axis_info: Vec::new(),
+            effector_info: None,
+        }
+    }
+}
+
+impl TechniqueCommon for MotionCommon {
+    fn parse_child(&mut self, c: &Element, ctx: &mut ParseContext) -> Result<bool, ColladaError> {
+        match c.name.as_str() {
+            "axis_info" | "effector_info" => {
+                let mut m = MotionInfo::new(&c.name);
+                try!(ctx.parse(&mut m, c));
+                if c.name == "axis_info" {
+                    self.axis_info.push(m);
+                } else {
+                    self.effector_info = Some(m);
+                }
+            },
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    fn encode_children(&self, tc: &mut Element) {
+        for a in &self.axis_info {
+            tc.children.push(a.encode());
+        }
+        if let Some(ref x) = self.effector_info {
+            tc.children.push(x.encode());
+        }
+    }
+}
+
+/// The motion of an articulated system: dynamic limits on top of another
+/// articulated system's kinematics
+#[derive(Debug)]
+pub struct Motion {
+    pub instance_articulated_system: KinematicsInstance,
+    pub common: MotionCommon,
+    pub techniques: Vec<Technique>,
+    pub extras: Vec<Extra>,
+    pub unknown: UnknownContent,
+}
+
+impl Motion {
+    pub fn new() -> Motion {
+        Motion {
+            instance_articulated_system: KinematicsInstance::new("instance_articulated_system"),
+            common: MotionCommon::new(),
+            techniques: Vec::new(),
+            extras: Vec::new(),
+            unknown: UnknownContent::new(),
+        }
+    }
+}
+
+impl XmlConversion for Motion {
+    fn parse_with(&mut self, e: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        ctx.unknown_attributes(e, &[], &mut self.unknown);
+        let mut has_instance = false;
+        for c in &e.children {
+            match c.name.as_str() {
+                "instance_articulated_system" => {
+                    try!(ctx.parse(&mut self.instance_articulated_system, c));
+                    has_instance = true;
+                },
+                "technique_common" => try!(self.common.parse_common(c, ctx)),
+                "technique" => {
+                    let mut t = Technique::new();
+                    try!(ctx.parse(&mut t, c));
+                    self.techniques.push(t);
+                },
+                "extra" => {
+                    let mut x = Extra::new();
+                    try!(ctx.parse(&mut x, c));
+                    self.extras.push(x);
+                },
+                _ => try!(ctx.unknown_child(e, c, &mut self.unknown)),
+            }
+        }
+
+        if !has_instance {
+            return Err(ColladaError::MissingElement{
+                structure: "motion".to_string(),
+                elem: "instance_articulated_system".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut m = Element::new("motion");
+        m.children.push(self.instance_articulated_system.encode());
+        m.children.push(self.common.encode_common());
+        for t in &self.techniques {
+            m.children.push(t.encode());
+        }
+        for x in &self.extras {
+            m.children.push(x.encode());
+        }
+        self.unknown.encode_into(&mut m);
+        m
+    }
+}
+
+impl HasTechniques for Motion {
+    type Common = MotionCommon;
+
+    fn technique_common(&self) -> &MotionCommon {
+        &self.common
+    }
+
+    fn techniques(&self) -> &[Technique] {
+        &self.techniques
+    }
+}
+
+/// The content of an articulated system
+#[derive(Debug)]
+pub enum ArticulatedSystemContent {
+    Kinematics(Kinematics),
+    Motion(Motion),
+}
+
+/// A kinematics or motion system, e.g. a robot arm and its controller
+#[derive(Debug)]
+pub struct ArticulatedSystem {
+    pub id: Option<String>,
+    pub name: Option<String>,
+    pub asset: Option<Asset>,
+    pub content: ArticulatedSystemContent,
+    pub extras: Vec<Extra>,
+    pub unknown: UnknownContent,
+}
+
+impl ArticulatedSystem {
+    pub fn new() -> ArticulatedSystem {
+        ArticulatedSystem {
+            id: None,
+            name: None,
+            asset: None,
+            content: ArticulatedSystemContent::Kinematics(Kinematics::new()),
+            extras: Vec::new(),
+            unknown: UnknownContent::new(),
+        }
+    }
+
+    /// Find the kinematics of the system, following motion systems to the
+    /// articulated systems they instantiate
+    pub fn kinematics<'a>(&'a self, libraries: &'a [LibraryArticulatedSystems]) -> Option<&'a Kinematics> {
+        let mut system = self;
+        let limit = libraries.iter().map(|l| l.articulated_systems.len()).sum::<usize>();
+        for _ in 0..limit + 1 {
+            match system.content {
+                ArticulatedSystemContent::Kinematics(ref k) => return Some(k),
+                ArticulatedSystemContent::Motion(ref m) => {
+                    system = match m.instance_articulated_system.articulated_system(libraries) {
+                        Some(s) => s,
+                        None => return None,
+                    };
+                },
+            }
+        }
+        None
+    }
+}
+
+impl XmlConversion for ArticulatedSystem {
+    fn parse_with(&mut self, e: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        ctx.unknown_attributes(e, &["id", "name"], &mut self.unknown);
+        self.id = utils::get_attr(e, "id");
+        self.name = utils::get_attr(e, "name");
+
+        let mut content = None;
+        for c in &e.children {
+            match c.name.as_str() {
+                "asset" => {
+                    let mut a = Asset::new();
+                    try!(ctx.parse(&mut a, c));
+                    self.asset = Some(a);
+                },
+                "kinematics" => {
+                    let mut k = Kinematics::new();
+                    try!(ctx.parse(&mut k, c));
+                    content = Some(ArticulatedSystemContent::Kinematics(k));
+                },
+                "motion" => {
+                    let mut m = Motion::new();
+                    try!(ctx.parse(&mut m, c));
+                    content = Some(ArticulatedSystemContent::Motion(m));
+                },
+                "extra" => {
+                    let mut x = Extra::new();
+                    try!(ctx.parse(&mut x, c));
+                    self.extras.push(x);
+                },
+                _ => try!(ctx.unknown_child(e, c, &mut self.unknown)),
+            }
+        }
+
+        self.content = match content {
+            Some(x) => x,
+            None => return Err(ColladaError::MissingElement{
+                structure: "articulated_system".to_string(),
+                elem: "kinematics".to_string(),
+            }),
+        };
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut a = Element::new("articulated_system");
+        utils::set_attr(&mut a, "id", &self.id);
+        utils::set_attr(&mut a, "name", &self.name);
+
+        match self.asset {
+            Some(ref x) => a.children.push(x.encode()),
+            None => {},
+        }
+        a.children.push(match self.content {
+            ArticulatedSystemContent::Kinematics(ref k) => k.encode(),
+            ArticulatedSystemContent::Motion(ref m) => m.encode(),
+        });
+        for x in &self.extras {
+            a.children.push(x.encode());
+        }
+        self.unknown.encode_into(&mut a);
+        a
+    }
+}
+
+/// Library of articulated systems
+#[derive(Debug)]
+pub struct LibraryArticulatedSystems {
+    pub id: Option<String>,
+    pub name: Option<String>,
+    pub asset: Option<Asset>,
+    pub articulated_systems: Vec<ArticulatedSystem>,
+    pub extras: Vec<Extra>,
+    pub unknown: UnknownContent,
+}
+
+impl LibraryArticulatedSystems {
+    pub fn new() -> LibraryArticulatedSystems {
+        LibraryArticulatedSystems {
+            id: None,
+            name: None,
+            asset: None,
+            articulated_systems: Vec::new(),
+            extras: Vec::new(),
+            unknown: UnknownContent::new(),
+        }
+    }
+
+    /// Find an articulated system by its id
+    pub fn get(&self, id: &str) -> Option<&ArticulatedSystem> {
+        self.articulated_systems.iter().find(|a| a.id.as_ref().map(|x| x.as_str()) == Some(id))
+    }
+}
+
+impl XmlConversion for LibraryArticulatedSystems {
+    fn parse_with(&mut self, e: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        ctx.unknown_attributes(e, &["id", "name"], &mut self.unknown);
+        self.id = utils::get_attr(e, "id");
+        self.name = utils::get_attr(e, "name");
+
+        for c in &e.children {
+            match c.name.as_str() {
+                "asset" => {
+                    let mut a = Asset::new();
+                    try!(ctx.parse(&mut a, c));
+                    self.asset = Some(a);
+                },
+                "articulated_system" => {
+                    let mut a = ArticulatedSystem::new();
+                    try!(ctx.parse(&mut a, c));
+                    self.articulated_systems.push(a);
+                },
+                "extra" => {
+                    let mut x = Extra::new();
+                    try!(ctx.parse(&mut x, c));
+                    self.extras.push(x);
+                },
+                _ => try!(ctx.unknown_child(e, c, &mut self.unknown)),
+            }
+        }
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut l = Element::new("library_articulated_systems");
+        utils::set_attr(&mut l, "id", &self.id);
+        utils::set_attr(&mut l, "name", &self.name);
+
+        match self.asset {
+            Some(ref x) => l.children.push(x.encode()),
+            None => {},
+        }
+        for a in &self.articulated_systems {
+            l.children.push(a.encode());
+        }
+        for x in &self.extras {
+            l.children.push(x.encode());
+        }
+        self.unknown.encode_into(&mut l);
+        l
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use xmltree::{Element};
+    use fx::{ParamValue};
+    use traits::{XmlConversion};
+
+    const SYSTEMS: &'static str = r##"
+        <library_articulated_systems>
+            <articulated_system id="arm_kinematics">
+                <kinematics>
+                    <instance_kinematics_model url="#arm" sid="inst_arm">
+                        <newparam sid="j0_axis"><SIDREF>arm/j0/axis0</SIDREF></newparam>
+                        <bind symbol="reach"><float>2</float></bind>
+                    </instance_kinematics_model>
+                    <technique_common>
+                        <axis_info sid="a0" axis="inst_arm/j0/axis0">
+                            <active><bool>true</bool></active>
+                            <locked><bool>false</bool></locked>
+                            <index semantic="controller"><int>0</int></index>
+                            <limits>
+                                <min><float>-45</float></min>
+                                <max><param ref="max_angle"/></max>
+                            </limits>
+                        </axis_info>
+                        <frame_origin link="arm/base"/>
+                        <frame_tip link="arm/tip"><translate>0 0 0.1</translate></frame_tip>
+                        <frame_tcp link="arm/tip"/>
+                    </technique_common>
+                </kinematics>
+            </articulated_system>
+            <articulated_system id="arm_motion">
+                <motion>
+                    <instance_articulated_system url="#arm_kinematics">
+                        <setparam ref="max_angle"><float>45</float></setparam>
+                    </instance_articulated_system>
+                    <technique_common>
+                        <axis_info axis="arm_kinematics/a0">
+                            <speed><float>30</float></speed>
+                            <acceleration><float>60</float></acceleration>
+                            <deceleration><float>60</float></deceleration>
+                            <jerk><float>120</float></jerk>
+                        </axis_info>
+                        <effector_info sid="effector">
+                            <speed><float2>1 90</float2></speed>
+                        </effector_info>
+                    </technique_common>
+                </motion>
+            </articulated_system>
+        </library_articulated_systems>"##;
+
+    #[test]
+    fn test_library_articulated_systems_parse() {
+        let e = Element::parse(SYSTEMS.as_bytes()).unwrap();
+        let mut l = LibraryArticulatedSystems::new();
+        match l.parse(&e) {
+            Ok(_) => assert!(true),
+            Err(_) => assert!(false),
+        }
+
+        let k = match l.get("arm_kinematics").unwrap().content {
+            ArticulatedSystemContent::Kinematics(ref k) => k,
+            _ => panic!("expected kinematics"),
+        };
+        let i = &k.instance_kinematics_models[0];
+        assert_eq!(i.element.as_str(), "instance_kinematics_model");
+        assert_eq!(i.url.as_str(), "#arm");
+        assert_eq!(i.newparams.len(), 1);
+        assert_eq!(i.binds[0].symbol.as_str(), "reach");
+        let a = &k.common.axis_info[0];
+        assert_eq!(a.axis.as_str(), "inst_arm/j0/axis0");
+        match a.active {
+            Some(ParamValue::Bool(x)) => assert!(x),
+            _ => assert!(false),
+        }
+        assert_eq!(a.indices[0].semantic, Some("controller".to_string()));
+        let limits = a.limits.as_ref().unwrap();
+        assert_eq!(limits.min.as_float(), Some(-45.0));
+        match limits.max {
+            ParamValue::Param(ref r) => assert_eq!(r.as_str(), "max_angle"),
+            _ => assert!(false),
+        }
+        assert_eq!(k.common.frame_origin.link, Some("arm/base".to_string()));
+        assert_eq!(k.common.frame_tip.transforms.len(), 1);
+        assert!(k.common.frame_tcp.is_some());
+        assert!(k.common.frame_object.is_none());
+
+        let m = match l.get("arm_motion").unwrap().content {
+            ArticulatedSystemContent::Motion(ref m) => m,
+            _ => panic!("expected motion"),
+        };
+        assert_eq!(m.instance_articulated_system.setparams.len(), 1);
+        let a = &m.common.axis_info[0];
+        assert_eq!(a.axis, Some("arm_kinematics/a0".to_string()));
+        assert_eq!(a.speed.as_ref().unwrap().as_float(), Some(30.0));
+        assert_eq!(a.jerk.as_ref().unwrap().as_float(), Some(120.0));
+        let effector = m.common.effector_info.as_ref().unwrap();
+        assert!(effector.axis.is_none());
+        match effector.speed {
+            Some(ParamValue::Float2(x)) => assert_eq!(x, [1.0, 90.0]),
+            _ => assert!(false),
+        }
+
+        let libraries = vec![l];
+        let k = libraries[0].get("arm_motion").unwrap().kinematics(&libraries).unwrap();
+        assert_eq!(k.common.axis_info.len(), 1);
+    }
+
+    #[test]
+    fn test_articulated_system_parse_invalid() {
+        let e = Element::parse(r##"<articulated_system id="a"/>"##.as_bytes()).unwrap();
+        assert!(ArticulatedSystem::new().parse(&e).is_err());
+
+        let e = Element::parse(r##"<articulated_system><kinematics><technique_common><frame_origin link="a"/></technique_common></kinematics></articulated_system>"##.as_bytes()).unwrap();
+        assert!(ArticulatedSystem::new().parse(&e).is_err());
+
+        let e = Element::parse(r##"<articulated_system><motion><technique_common/></motion></articulated_system>"##.as_bytes()).unwrap();
+        assert!(ArticulatedSystem::new().parse(&e).is_err());
+
+        let e = Element::parse(r##"<axis_info axis="a"><limits><min><float>0</float></min></limits></axis_info>"##.as_bytes()).unwrap();
+        assert!(KinematicsAxisInfo::new().parse(&e).is_err());
+
+        let e = Element::parse(r##"<axis_info sid="a"/>"##.as_bytes()).unwrap();
+        assert!(MotionInfo::new("axis_info").parse(&e).is_err());
+    }
+
+    #[test]
+    fn test_motion_cycle() {
+        let data = r##"
+            <library_articulated_systems>
+                <articulated_system id="a">
+                    <motion><instance_articulated_system url="#b"/></motion>
+                </articulated_system>
+                <articulated_system id="b">
+                    <motion><instance_articulated_system url="#a"/></motion>
+                </articulated_system>
+            </library_articulated_systems>"##;
+        let e = Element::parse(data.as_bytes()).unwrap();
+        let mut l = LibraryArticulatedSystems::new();
+        l.parse(&e).unwrap();
+        let libraries = vec![l];
+        assert!(libraries[0].get("a").unwrap().kinematics(&libraries).is_none());
+    }
+
+    #[test]
+    fn test_library_articulated_systems_encode() {
+        let mut k = Kinematics::new();
+        k.common.frame_origin.link = Some("arm/base".to_string());
+        k.common.frame_tip.link = Some("arm/tip".to_string());
+        let mut i = KinematicsInstance::new("instance_kinematics_model");
+        i.url = "#arm".to_string();
+        k.instance_kinematics_models.push(i);
+        let mut a = KinematicsAxisInfo::new();
+        a.axis = "arm/j0/axis0".to_string();
+        a.limits = Some(AxisLimits{min: ParamValue::Float(-1.0), max: ParamValue::Float(1.0)});
+        k.common.axis_info.push(a);
+        let mut s = ArticulatedSystem::new();
+        s.id = Some("sys".to_string());
+        s.content = ArticulatedSystemContent::Kinematics(k);
+        let mut l = LibraryArticulatedSystems::new();
+        l.articulated_systems.push(s);
+
+        let e = l.encode();
+        assert_eq!(e.name, "library_articulated_systems");
+        let k = &e.children[0].children[0];
+        assert_eq!(k.name, "kinematics");
+        assert_eq!(k.children[0].name, "instance_kinematics_model");
+        let tc = &k.children[1];
+        let names: Vec<&str> = tc.children.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["axis_info", "frame_origin", "frame_tip"]);
+        let limits = &tc.children[0].children[0];
+        assert_eq!(limits.name, "limits");
+        assert_eq!(limits.children[0].children[0].name, "float");
+        assert_eq!(limits.children[0].children[0].text, Some("-1".to_string()));
+
+        let mut m = MotionInfo::new("effector_info");
+        m.speed = Some(ParamValue::Float2([1.0, 2.0]));
+        let e = m.encode();
+        assert_eq!(e.name, "effector_info");
+        assert_eq!(e.attributes.get("axis"), None);
+        assert_eq!(e.children[0].name, "speed");
+        assert_eq!(e.children[0].children[0].name, "float2");
+    }
+}
@@ -0,0 +1,286 @@
+use std::collections::{HashMap};
+use core::{self, Matrix};
+use error::{ColladaError};
+use kinematics::{AttachmentKind, Joint, JointLimits, JointRef, KinematicsModel, LibraryJoints, Link};
+use utils;
+
+/// Joint values by joint sid, e.g. `elbow`, or for joints with several
+/// axes by joint and axis sid, e.g. `wrist/axis1`. Missing values are zero.
+pub type JointValues = HashMap<String, f32>;
+
+/// World transform of a link
+#[derive(Debug)]
+pub struct LinkPose {
+    pub sid: Option<String>,
+    pub transform: Matrix,
+}
+
+/// World transform of a joint's frame on a link
+#[derive(Debug)]
+pub struct AttachmentPose {
+    pub kind: AttachmentKind,
+
+    /// Sid reference of the joint
+    pub joint: String,
+
+    /// Sid of the link the attachment belongs to
+    pub link: Option<String>,
+    pub transform: Matrix,
+}
+
+/// A joint value outside of the joint's limits
+#[derive(Debug, PartialEq)]
+pub struct LimitViolation {
+    /// Sid reference of the joint
+    pub joint: String,
+
+    /// Index of the axis within the joint
+    pub axis: usize,
+    pub value: f32,
+    pub limits: JointLimits,
+}
+
+/// The result of forward kinematics. Transforms are relative to the frame
+/// of the kinematics model, i.e. of the node it is bound to.
+#[derive(Debug)]
+pub struct Pose {
+    pub links: Vec<LinkPose>,
+    pub attachments: Vec<AttachmentPose>,
+
+    /// Joint values outside of their limits, the pose uses the values as given
+    pub violations: Vec<LimitViolation>,
+}
+
+impl Pose {
+    /// Find the transform of a link by its sid
+    pub fn link(&self, sid: &str) -> Option<&Matrix> {
+        self.links.iter()
+            .find(|l| l.sid.as_ref().map(|x| x.as_str()) == Some(sid))
+            .map(|l| &l.transform)
+    }
+
+    /// Whether all joint values are within their limits
+    pub fn within_limits(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+impl KinematicsModel {
+    /// Compute the transform of every link and attachment frame for a set
+    /// of joint values. Joints instantiated with `<instance_joint>` are
+    /// looked up in the joint libraries.
+    pub fn forward(&self, joint_values: &JointValues, libraries: &[LibraryJoints])
+        -> Result<Pose, ColladaError>
+    {
+        let mut pose = Pose {
+            links: Vec::new(),
+            attachments: Vec::new(),
+            violations: Vec::new(),
+        };
+        for l in &self.common.links {
+            try!(self.forward_link(l, &core::IDENTITY, joint_values, libraries, &mut pose));
+        }
+        Ok(pose)
+    }
+
+    fn forward_link(&self, link: &Link, parent: &Matrix, values: &JointValues,
+                    libraries: &[LibraryJoints], pose: &mut Pose) -> Result<(), ColladaError> {
+        let world = core::multiply(parent, &core::compose(&link.transforms));
+        pose.links.push(LinkPose {
+            sid: link.sid.clone(),
+            transform: world,
+        });
+
+        for a in &link.attachments {
+            let frame = core::multiply(&world, &core::compose(&a.transforms));
+            pose.attachments.push(AttachmentPose {
+                kind: a.kind,
+                joint: a.joint.clone(),
+                link: link.sid.clone(),
+                transform: frame,
+            });
+
+            if let Some(ref child) = a.link {
+                let (sid, joint) = try!(self.resolve_joint(&a.joint, libraries));
+                let mut motion = core::IDENTITY;
+                for (i, axis) in joint.axes.iter().enumerate() {
+                    let value = joint_value(values, &a.joint, sid, axis.sid.as_ref(), i);
+                    if let Some(ref l) = axis.limits {
+                        if !l.contains(value) {
+                            pose.violations.push(LimitViolation {
+                                joint: a.joint.clone(),
+                                axis: i,
+                                value: value,
+                                limits: l.clone(),
+                            });
+                        }
+                    }
+                    motion = core::multiply(&motion, &axis.motion(value));
+                }
+                try!(self.forward_link(child, &core::multiply(&frame, &motion), values, libraries, pose));
+            }
+        }
+        Ok(())
+    }
+
+    /// Resolve a joint reference, e.g. `kmodel/joint0`, to the joint's sid
+    /// within the model and its definition
+    fn resolve_joint<'a>(&'a self, reference: &'a str, libraries: &'a [LibraryJoints])
+        -> Result<(&'a str, &'a Joint), ColladaError>
+    {
+        let sid = reference.rsplit('/').next().unwrap_or(reference);
+        let joint = match self.joint(sid) {
+            Some(&JointRef::Inline(ref j)) => Some(j),
+            Some(&JointRef::Instance(ref i)) => utils::url_id(&i.url)
+                .and_then(|id| libraries.iter().filter_map(|l| l.get(id)).next()),
+            None => None,
+        };
+        match joint {
+            Some(j) => Ok((sid, j)),
+            None => Err(ColladaError::Invalid{
+                msg: format!("Unable to resolve joint '{}'", reference),
+            }),
+        }
+    }
+}
+
+fn joint_value(values: &JointValues, reference: &str, sid: &str, axis: Option<&String>, index: usize) -> f32 {
+    let mut keys = Vec::new();
+    if let Some(a) = axis {
+        keys.push(format!("{}/{}", reference, a));
+        keys.push(format!("{}/{}", sid, a));
+    }
+    if index == 0 {
+        keys.push(reference.to_string());
+        keys.push(sid.to_string());
+    }
+    keys.iter().filter_map(|k| values.get(k)).next().cloned().unwrap_or(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use xmltree::{Element};
+    use core::{transform_point};
+    use kinematics::{LibraryKinematicsModels};
+    use traits::{XmlConversion};
+
+    const ARM: &'static str = r##"
+        <library_kinematics_models>
+            <kinematics_model id="arm">
+                <technique_common>
+                    <instance_joint url="#shoulder" sid="j0"/>
+                    <joint sid="j1">
+                        <revolute sid="axis0">
+                            <axis>0 0 1</axis>
+                            <limits><min>-90</min><max>90</max></limits>
+                        </revolute>
+                    </joint>
+                    <joint sid="slide">
+                        <prismatic sid="axis0"><axis>0 0 2</axis></prismatic>
+                    </joint>
+                    <link sid="base">
+                        <attachment_full joint="arm/j0">
+                            <translate>0 0 1</translate>
+                            <link sid="upper">
+                                <attachment_full joint="arm/j1">
+                                    <translate>1 0 0</translate>
+                                    <link sid="lower">
+                                        <attachment_full joint="arm/slide">
+                                            <translate>1 0 0</translate>
+                                            <link sid="tip"/>
+                                        </attachment_full>
+                                        <attachment_end joint="arm/j0">
+                                            <translate>0.5 0 0</translate>
+                                        </attachment_end>
+                                    </link>
+                                </attachment_full>
+                            </link>
+                        </attachment_full>
+                    </link>
+                </technique_common>
+            </kinematics_model>
+        </library_kinematics_models>"##;
+
+    const JOINTS: &'static str = r##"
+        <library_joints>
+            <joint id="shoulder">
+                <revolute sid="axis0"><axis>0 0 1</axis></revolute>
+            </joint>
+        </library_joints>"##;
+
+    fn load() -> (LibraryKinematicsModels, Vec<LibraryJoints>) {
+        let mut models = LibraryKinematicsModels::new();
+        models.parse(&Element::parse(ARM.as_bytes()).unwrap()).unwrap();
+        let mut joints = LibraryJoints::new();
+        joints.parse(&Element::parse(JOINTS.as_bytes()).unwrap()).unwrap();
+        (models, vec![joints])
+    }
+
+    fn assert_point(m: &Matrix, expected: [f32; 3]) {
+        let p = transform_point(m, [0.0, 0.0, 0.0]);
+        for i in 0..3 {
+            assert!((p[i] - expected[i]).abs() < 1e-5, "{:?} != {:?}", p, expected);
+        }
+    }
+
+    #[test]
+    fn test_forward_zero() {
+        let (models, joints) = load();
+        let m = models.get("arm").unwrap();
+        let pose = m.forward(&JointValues::new(), &joints).unwrap();
+        assert_eq!(pose.links.len(), 4);
+        assert_point(pose.link("base").unwrap(), [0.0, 0.0, 0.0]);
+        assert_point(pose.link("upper").unwrap(), [0.0, 0.0, 1.0]);
+        assert_point(pose.link("lower").unwrap(), [1.0, 0.0, 1.0]);
+        assert_point(pose.link("tip").unwrap(), [2.0, 0.0, 1.0]);
+        assert!(pose.within_limits());
+    }
+
+    #[test]
+    fn test_forward_values() {
+        let (models, joints) = load();
+        let m = models.get("arm").unwrap();
+        let mut values = JointValues::new();
+        values.insert("j0".to_string(), 90.0);
+        values.insert("arm/j1".to_string(), -90.0);
+        values.insert("slide/axis0".to_string(), 0.5);
+        let pose = m.forward(&values, &joints).unwrap();
+
+        // Shoulder turns the arm to +Y, the elbow turns it back to +X
+        assert_point(pose.link("lower").unwrap(), [0.0, 1.0, 1.0]);
+        assert_point(pose.link("tip").unwrap(), [1.0, 1.0, 1.5]);
+
+        assert_eq!(pose.attachments.len(), 4);
+        let end = pose.attachments.iter().find(|a| a.kind == AttachmentKind::End).unwrap();
+        assert_eq!(end.link, Some("lower".to_string()));
+        assert_point(&end.transform, [0.5, 1.0, 1.0]);
+        let full = &pose.attachments[0];
+        assert_eq!(full.joint.as_str(), "arm/j0");
+        assert_point(&full.transform, [0.0, 0.0, 1.0]);
+        assert!(pose.within_limits());
+    }
+
+    #[test]
+    fn test_forward_limits() {
+        let (models, joints) = load();
+        let m = models.get("arm").unwrap();
+        let mut values = JointValues::new();
+        values.insert("j1".to_string(), 120.0);
+        let pose = m.forward(&values, &joints).unwrap();
+        assert!(!pose.within_limits());
+        assert_eq!(pose.violations, vec![LimitViolation {
+            joint: "arm/j1".to_string(),
+            axis: 0,
+            value: 120.0,
+            limits: JointLimits{min: -90.0, max: 90.0},
+        }]);
+    }
+
+    #[test]
+    fn test_forward_unresolved_joint() {
+        let (models, _) = load();
+        let m = models.get("arm").unwrap();
+        assert!(m.forward(&JointValues::new(), &[]).is_err());
+    }
+}
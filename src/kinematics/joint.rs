@@ -0,0 +1,407 @@
+use xmltree::{Element};
+use context::{ParseContext};
+use core::{self, Asset, Extra, Matrix, UnknownContent};
+use error::{ColladaError};
+use traits::{XmlConversion};
+use utils;
+
+/// The kinds of joint primitives
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum JointKind {
+    /// Translation along the axis
+    Prismatic,
+
+    /// Rotation around the axis, in degrees
+    Revolute,
+}
+
+impl JointKind {
+    pub fn from_name(name: &str) -> Option<JointKind> {
+        match name {
+            "prismatic" => Some(JointKind::Prismatic),
+            "revolute" => Some(JointKind::Revolute),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            JointKind::Prismatic => "prismatic",
+            JointKind::Revolute => "revolute",
+        }
+    }
+}
+
+/// Range of motion of a joint axis, in degrees for revolute and in distance
+/// units for prismatic axes
+#[derive(Clone, Debug, PartialEq)]
+pub struct JointLimits {
+    pub min: f32,
+    pub max: f32,
+}
+
+impl JointLimits {
+    /// Whether a joint value is within the limits
+    pub fn contains(&self, value: f32) -> bool {
+        value >= self.min && value <= self.max
+    }
+}
+
+/// A single degree of freedom of a joint
+#[derive(Clone, Debug)]
+pub struct JointAxis {
+    pub kind: JointKind,
+    pub sid: Option<String>,
+    pub axis: [f32; 3],
+
+    /// Sid of the `<axis>` element, used to reference the axis
+    pub axis_sid: Option<String>,
+    pub limits: Option<JointLimits>,
+}
+
+impl JointAxis {
+    pub fn new(kind: JointKind) -> JointAxis {
+        JointAxis {
+            kind: kind,
+            sid: None,
+            axis: [0.0, 0.0, 1.0],
+            axis_sid: None,
+            limits: None,
+        }
+    }
+
+    /// The motion of the axis for a joint value, a rotation in degrees for
+    /// revolute and a translation for prismatic axes
+    pub fn motion(&self, value: f32) -> Matrix {
+        match self.kind {
+            JointKind::Revolute => core::rotation(self.axis, value),
+            JointKind::Prismatic => {
+                let len = (self.axis[0] * self.axis[0] + self.axis[1] * self.axis[1] +
+                           self.axis[2] * self.axis[2]).sqrt();
+                let scale = if len == 0.0 { 0.0 } else { value / len };
+                core::translation([self.axis[0] * scale, self.axis[1] * scale, self.axis[2] * scale])
+            },
+        }
+    }
+}
+
+impl XmlConversion for JointAxis {
+    fn parse_with(&mut self, e: &Element, _ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        self.kind = match JointKind::from_name(&e.name) {
+            Some(k) => k,
+            None => return Err(ColladaError::InvalidChild{
+                child: e.name.clone(),
+                parent: "joint".to_string(),
+            }),
+        };
+        self.sid = utils::get_attr(e, "sid");
+
+        let mut has_axis = false;
+        for c in &e.children {
+            match c.name.as_str() {
+                "axis" => {
+                    self.axis = try!(utils::parse_float3(c));
+                    self.axis_sid = utils::get_attr(c, "sid");
+                    has_axis = true;
+                },
+                "limits" => {
+                    let (mut min, mut max) = (None, None);
+                    for l in &c.children {
+                        match l.name.as_str() {
+                            "min" => min = Some(try!(utils::parse_text(l))),
+                            "max" => max = Some(try!(utils::parse_text(l))),
+                            _ => return Err(ColladaError::InvalidChild{
+                                child: l.name.clone(),
+                                parent: "limits".to_string(),
+                            }),
+                        }
+                    }
+                    match (min, max) {
+                        (Some(min), Some(max)) => self.limits = Some(JointLimits{min: min, max: max}),
+                        _ => return Err(ColladaError::MissingElement{
+                            structure: "limits".to_string(),
+                            elem: if min.is_none() { "min" } else { "max" }.to_string(),
+                        }),
+                    }
+                },
+                _ => return Err(ColladaError::InvalidChild{
+                    child: c.name.clone(),
+                    parent: e.name.clone(),
+                }),
+            }
+        }
+
+        if !has_axis {
+            return Err(ColladaError::MissingElement{
+                structure: e.name.clone(),
+                elem: "axis".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut j = Element::new(self.kind.name());
+        utils::set_attr(&mut j, "sid", &self.sid);
+
+        let mut a = utils::list_element("axis", &self.axis);
+        utils::set_attr(&mut a, "sid", &self.axis_sid);
+        j.children.push(a);
+        if let Some(ref l) = self.limits {
+            let mut e = Element::new("limits");
+            e.children.push(utils::text_element("min", l.min.to_string()));
+            e.children.push(utils::text_element("max", l.max.to_string()));
+            j.children.push(e);
+        }
+        j
+    }
+}
+
+/// A joint with one or more degrees of freedom
+#[derive(Debug)]
+pub struct Joint {
+    pub id: Option<String>,
+    pub name: Option<String>,
+    pub sid: Option<String>,
+    pub axes: Vec<JointAxis>,
+    pub extras: Vec<Extra>,
+    pub unknown: UnknownContent,
+}
+
+impl Joint {
+    pub fn new() -> Joint {
+        Joint {
+            id: None,
+            name: None,
+            sid: None,
+            axes: Vec::new(),
+            extras: Vec::new(),
+            unknown: UnknownContent::new(),
+        }
+    }
+
+    /// Find an axis by the sid of the primitive or of its `<axis>`
+    pub fn axis(&self, sid: &str) -> Option<&JointAxis> {
+        self.axes.iter().find(|a| {
+            a.sid.as_ref().map(|x| x.as_str()) == Some(sid) ||
+            a.axis_sid.as_ref().map(|x| x.as_str()) == Some(sid)
+        })
+    }
+}
+
+impl XmlConversion for Joint {
+    fn parse_with(&mut self, e: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        ctx.unknown_attributes(e, &["id", "name", "sid"], &mut self.unknown);
+        self.id = utils::get_attr(e, "id");
+        self.name = utils::get_attr(e, "name");
+        self.sid = utils::get_attr(e, "sid");
+
+        for c in &e.children {
+            match c.name.as_str() {
+                "prismatic" | "revolute" => {
+                    let mut a = JointAxis::new(JointKind::Revolute);
+                    try!(ctx.parse(&mut a, c));
+                    self.axes.push(a);
+                },
+                "extra" => {
+                    let mut x = Extra::new();
+                    try!(ctx.parse(&mut x, c));
+                    self.extras.push(x);
+                },
+                _ => try!(ctx.unknown_child(e, c, &mut self.unknown)),
+            }
+        }
+
+        if self.axes.is_empty() {
+            return Err(ColladaError::MissingElement{
+                structure: "joint".to_string(),
+                elem: "revolute".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut j = Element::new("joint");
+        utils::set_attr(&mut j, "id", &self.id);
+        utils::set_attr(&mut j, "name", &self.name);
+        utils::set_attr(&mut j, "sid", &self.sid);
+
+        for a in &self.axes {
+            j.children.push(a.encode());
+        }
+        for x in &self.extras {
+            j.children.push(x.encode());
+        }
+        self.unknown.encode_into(&mut j);
+        j
+    }
+}
+
+/// Library of joints
+#[derive(Debug)]
+pub struct LibraryJoints {
+    pub id: Option<String>,
+    pub name: Option<String>,
+    pub asset: Option<Asset>,
+    pub joints: Vec<Joint>,
+    pub extras: Vec<Extra>,
+    pub unknown: UnknownContent,
+}
+
+impl LibraryJoints {
+    pub fn new() -> LibraryJoints {
+        LibraryJoints {
+            id: None,
+            name: None,
+            asset: None,
+            joints: Vec::new(),
+            extras: Vec::new(),
+            unknown: UnknownContent::new(),
+        }
+    }
+
+    /// Find a joint by its id
+    pub fn get(&self, id: &str) -> Option<&Joint> {
+        self.joints.iter().find(|j| j.id.as_ref().map(|x| x.as_str()) == Some(id))
+    }
+}
+
+impl XmlConversion for LibraryJoints {
+    fn parse_with(&mut self, e: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        ctx.unknown_attributes(e, &["id", "name"], &mut self.unknown);
+        self.id = utils::get_attr(e, "id");
+        self.name = utils::get_attr(e, "name");
+
+        for c in &e.children {
+            match c.name.as_str() {
+                "asset" => {
+                    let mut a = Asset::new();
+                    try!(ctx.parse(&mut a, c));
+                    self.asset = Some(a);
+                },
+                "joint" => {
+                    let mut j = Joint::new();
+                    try!(ctx.parse(&mut j, c));
+                    self.joints.push(j);
+                },
+                "extra" => {
+                    let mut x = Extra::new();
+                    try!(ctx.parse(&mut x, c));
+                    self.extras.push(x);
+                },
+                _ => try!(ctx.unknown_child(e, c, &mut self.unknown)),
+            }
+        }
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut l = Element::new("library_joints");
+        utils::set_attr(&mut l, "id", &self.id);
+        utils::set_attr(&mut l, "name", &self.name);
+
+        match self.asset {
+            Some(ref x) => l.children.push(x.encode()),
+            None => {},
+        }
+        for j in &self.joints {
+            l.children.push(j.encode());
+        }
+        for x in &self.extras {
+            l.children.push(x.encode());
+        }
+        self.unknown.encode_into(&mut l);
+        l
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use xmltree::{Element};
+    use traits::{XmlConversion};
+
+    #[test]
+    fn test_library_joints_parse() {
+        let data = r##"
+            <library_joints>
+                <joint id="shoulder" name="Shoulder">
+                    <revolute sid="axis0">
+                        <axis>0 0 1</axis>
+                        <limits>
+                            <min>-90</min>
+                            <max>90</max>
+                        </limits>
+                    </revolute>
+                </joint>
+                <joint id="slider">
+                    <prismatic sid="axis0">
+                        <axis sid="dir">1 0 0</axis>
+                    </prismatic>
+                    <revolute sid="axis1"><axis>1 0 0</axis></revolute>
+                </joint>
+            </library_joints>"##;
+        let e = Element::parse(data.as_bytes()).unwrap();
+        let mut l = LibraryJoints::new();
+        match l.parse(&e) {
+            Ok(_) => assert!(true),
+            Err(_) => assert!(false),
+        }
+        let j = l.get("shoulder").unwrap();
+        assert_eq!(j.name, Some("Shoulder".to_string()));
+        assert_eq!(j.axes.len(), 1);
+        assert_eq!(j.axes[0].kind, JointKind::Revolute);
+        assert_eq!(j.axes[0].axis, [0.0, 0.0, 1.0]);
+        let limits = j.axes[0].limits.clone().unwrap();
+        assert_eq!(limits, JointLimits{min: -90.0, max: 90.0});
+        assert!(limits.contains(45.0));
+        assert!(!limits.contains(91.0));
+
+        let j = l.get("slider").unwrap();
+        assert_eq!(j.axes.len(), 2);
+        assert_eq!(j.axes[0].kind, JointKind::Prismatic);
+        assert_eq!(j.axis("dir").unwrap().axis, [1.0, 0.0, 0.0]);
+        assert_eq!(j.axis("axis1").unwrap().kind, JointKind::Revolute);
+        assert!(j.axes[1].limits.is_none());
+    }
+
+    #[test]
+    fn test_joint_parse_invalid() {
+        let e = Element::parse(r##"<joint id="a"/>"##.as_bytes()).unwrap();
+        assert!(Joint::new().parse(&e).is_err());
+
+        let e = Element::parse(r##"<joint><revolute/></joint>"##.as_bytes()).unwrap();
+        assert!(Joint::new().parse(&e).is_err());
+
+        let e = Element::parse(r##"<joint><revolute><axis>0 0 1</axis><limits><min>0</min></limits></revolute></joint>"##.as_bytes()).unwrap();
+        assert!(Joint::new().parse(&e).is_err());
+
+        let e = Element::parse(r##"<joint><spherical><axis>0 0 1</axis></spherical></joint>"##.as_bytes()).unwrap();
+        assert!(Joint::new().parse(&e).is_err());
+    }
+
+    #[test]
+    fn test_library_joints_encode() {
+        let mut a = JointAxis::new(JointKind::Prismatic);
+        a.sid = Some("axis0".to_string());
+        a.axis = [0.0, 1.0, 0.0];
+        a.limits = Some(JointLimits{min: 0.0, max: 0.5});
+        let mut j = Joint::new();
+        j.id = Some("lift".to_string());
+        j.axes.push(a);
+        let mut l = LibraryJoints::new();
+        l.joints.push(j);
+
+        let e = l.encode();
+        assert_eq!(e.name, "library_joints");
+        let j = &e.children[0];
+        assert_eq!(j.attributes.get("id"), Some(&"lift".to_string()));
+        let p = &j.children[0];
+        assert_eq!(p.name, "prismatic");
+        assert_eq!(p.children[0].name, "axis");
+        assert_eq!(p.children[0].text, Some("0 1 0".to_string()));
+        assert_eq!(p.children[1].name, "limits");
+        assert_eq!(p.children[1].children[1].text, Some("0.5".to_string()));
+    }
+}
@@ -0,0 +1,11 @@
+mod articulated;
+mod forward;
+mod joint;
+mod model;
+mod scene;
+
+pub use self::articulated::*;
+pub use self::forward::*;
+pub use self::joint::*;
+pub use self::model::*;
+pub use self::scene::*;
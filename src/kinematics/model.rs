@@ -0,0 +1,632 @@
+use xmltree::{Element};
+use context::{ParseContext};
+use core::{Asset, Extra, FormulaRef, HasTechniques, Technique, TechniqueCommon, Transform, TransformKind, UnknownContent};
+use error::{ColladaError};
+use fx::{NewParam};
+use kinematics::{Joint};
+use traits::{XmlConversion};
+use utils;
+
+/// Instantiates a joint from a joint library
+#[derive(Debug)]
+pub struct InstanceJoint {
+    pub url: String,
+    pub sid: Option<String>,
+    pub name: Option<String>,
+    pub extras: Vec<Extra>,
+    pub unknown: UnknownContent,
+}
+
+impl InstanceJoint {
+    pub fn new() -> InstanceJoint {
+        InstanceJoint {
+            url: String::from(""),
+            sid: None,
+            name: None,
+            extras: Vec::new(),
+            unknown: UnknownContent::new(),
+        }
+    }
+}
+
+impl XmlConversion for InstanceJoint {
+    fn parse_with(&mut self, e: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        ctx.unknown_attributes(e, &["url", "sid", "name"], &mut self.unknown);
+        self.url = try!(utils::require_attr(e, "url"));
+        self.sid = utils::get_attr(e, "sid");
+        self.name = utils::get_attr(e, "name");
+
+        for c in &e.children {
+            match c.name.as_str() {
+                "extra" => {
+                    let mut x = Extra::new();
+                    try!(ctx.parse(&mut x, c));
+                    self.extras.push(x);
+                },
+                _ => try!(ctx.unknown_child(e, c, &mut self.unknown)),
+            }
+        }
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut i = Element::new("instance_joint");
+        i.attributes.insert("url".to_string(), self.url.clone());
+        utils::set_attr(&mut i, "sid", &self.sid);
+        utils::set_attr(&mut i, "name", &self.name);
+        for x in &self.extras {
+            i.children.push(x.encode());
+        }
+        self.unknown.encode_into(&mut i);
+        i
+    }
+}
+
+/// A joint of a kinematics model, either instantiated from a library or
+/// defined inline
+#[derive(Debug)]
+pub enum JointRef {
+    Instance(InstanceJoint),
+    Inline(Joint),
+}
+
+impl JointRef {
+    /// Parse an `<instance_joint>` or `<joint>` element
+    pub fn parse(e: &Element, ctx: &mut ParseContext) -> Result<JointRef, ColladaError> {
+        if e.name == "instance_joint" {
+            let mut i = InstanceJoint::new();
+            try!(ctx.parse(&mut i, e));
+            Ok(JointRef::Instance(i))
+        } else {
+            let mut j = Joint::new();
+            try!(ctx.parse(&mut j, e));
+            Ok(JointRef::Inline(j))
+        }
+    }
+
+    pub fn encode(&self) -> Element {
+        match *self {
+            JointRef::Instance(ref i) => i.encode(),
+            JointRef::Inline(ref j) => j.encode(),
+        }
+    }
+
+    /// The sid the joint is referenced by within the kinematics model
+    pub fn sid(&self) -> Option<&str> {
+        match *self {
+            JointRef::Instance(ref i) => i.sid.as_ref().map(|x| x.as_str()),
+            JointRef::Inline(ref j) => j.sid.as_ref().map(|x| x.as_str()),
+        }
+    }
+}
+
+/// How a link attachment relates to its joint
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum AttachmentKind {
+    /// The joint connects the link to a child link
+    Full,
+
+    /// Start and end frames of a joint in a closed loop
+    Start,
+    End,
+}
+
+impl AttachmentKind {
+    pub fn from_name(name: &str) -> Option<AttachmentKind> {
+        match name {
+            "attachment_full" => Some(AttachmentKind::Full),
+            "attachment_start" => Some(AttachmentKind::Start),
+            "attachment_end" => Some(AttachmentKind::End),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            AttachmentKind::Full => "attachment_full",
+            AttachmentKind::Start => "attachment_start",
+            AttachmentKind::End => "attachment_end",
+        }
+    }
+}
+
+/// Attaches a joint to a link
+#[derive(Debug)]
+pub struct LinkAttachment {
+    pub kind: AttachmentKind,
+
+    /// Sid reference of the joint, e.g. `kmodel/joint0`
+    pub joint: String,
+
+    /// Frame of the joint relative to the link
+    pub transforms: Vec<Transform>,
+
+    /// Child link of an `<attachment_full>`
+    pub link: Option<Box<Link>>,
+}
+
+impl LinkAttachment {
+    pub fn new(kind: AttachmentKind) -> LinkAttachment {
+        LinkAttachment {
+            kind: kind,
+            joint: String::from(""),
+            transforms: Vec::new(),
+            link: None,
+        }
+    }
+}
+
+impl XmlConversion for LinkAttachment {
+    fn parse_with(&mut self, e: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        self.kind = match AttachmentKind::from_name(&e.name) {
+            Some(k) => k,
+            None => return Err(ColladaError::InvalidChild{
+                child: e.name.clone(),
+                parent: "link".to_string(),
+            }),
+        };
+        self.joint = try!(utils::require_attr(e, "joint"));
+
+        for c in &e.children {
+            match c.name.as_str() {
+                "translate" | "rotate" => {
+                    let mut t = Transform::new(TransformKind::Translate);
+                    try!(ctx.parse(&mut t, c));
+                    self.transforms.push(t);
+                },
+                "link" if self.kind == AttachmentKind::Full => {
+                    let mut l = Link::new();
+                    try!(ctx.parse(&mut l, c));
+                    self.link = Some(Box::new(l));
+                },
+                _ => return Err(ColladaError::InvalidChild{
+                    child: c.name.clone(),
+                    parent: e.name.clone(),
+                }),
+            }
+        }
+
+        if self.kind == AttachmentKind::Full && self.link.is_none() {
+            return Err(ColladaError::MissingElement{
+                structure: e.name.clone(),
+                elem: "link".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut a = Element::new(self.kind.name());
+        a.attributes.insert("joint".to_string(), self.joint.clone());
+        for t in &self.transforms {
+            a.children.push(t.encode());
+        }
+        if let Some(ref l) = self.link {
+            a.children.push(l.encode());
+        }
+        a
+    }
+}
+
+/// A rigid link of a kinematic chain
+#[derive(Debug)]
+pub struct Link {
+    pub sid: Option<String>,
+    pub name: Option<String>,
+
+    /// Frame of the link relative to its parent
+    pub transforms: Vec<Transform>,
+    pub attachments: Vec<LinkAttachment>,
+}
+
+impl Link {
+    pub fn new() -> Link {
+        Link {
+            sid: None,
+            name: None,
+            transforms: Vec::new(),
+            attachments: Vec::new(),
+        }
+    }
+}
+
+impl XmlConversion for Link {
+    fn parse_with(&mut self, e: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        self.sid = utils::get_attr(e, "sid");
+        self.name = utils::get_attr(e, "name");
+
+        for c in &e.children {
+            match c.name.as_str() {
+                "translate" | "rotate" => {
+                    let mut t = Transform::new(TransformKind::Translate);
+                    try!(ctx.parse(&mut t, c));
+                    self.transforms.push(t);
+                },
+                "attachment_full" | "attachment_start" | "attachment_end" => {
+                    let mut a = LinkAttachment::new(AttachmentKind::Full);
+                    try!(ctx.parse(&mut a, c));
+                    self.attachments.push(a);
+                },
+                _ => return Err(ColladaError::InvalidChild{
+                    child: c.name.clone(),
+                    parent: "link".to_string(),
+                }),
+            }
+        }
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut l = Element::new("link");
+        utils::set_attr(&mut l, "sid", &self.sid);
+        utils::set_attr(&mut l, "name", &self.name);
+
+        for t in &self.transforms {
+            l.children.push(t.encode());
+        }
+        for a in &self.attachments {
+            l.children.push(a.encode());
+        }
+        l
+    }
+}
+
+/// The common technique of a kinematics model
+#[derive(Debug)]
+pub struct KinematicsModelCommon {
+    pub newparams: Vec<NewParam>,
+    pub joints: Vec<JointRef>,
+
+    /// Root links of the model
+    pub links: Vec<Link>,
+
+    pub formulas: Vec<FormulaRef>,
+}
+
+impl KinematicsModelCommon {
+    pub fn new() -> KinematicsModelCommon {
+        KinematicsModelCommon {
+            newparams: Vec::new(),
+            joints: Vec::new(),
+            links: Vec::new(),
+            formulas: Vec::new(),
+        }
+    }
+}
+
+impl TechniqueCommon for KinematicsModelCommon {
+    fn parse_child(&mut self, c: &Element, ctx: &mut ParseContext) -> Result<bool, ColladaError> {
+        match c.name.as_str() {
+            "newparam" => {
+                let mut n = NewParam::new();
+                try!(ctx.parse(&mut n, c));
+                self.newparams.push(n);
+            },
+            "instance_joint" | "joint" => self.joints.push(try!(JointRef::parse(c, ctx))),
+            "link" => {
+                let mut l = Link::new();
+                try!(ctx.parse(&mut l, c));
+                self.links.push(l);
+            },
+            "formula" | "instance_formula" => self.formulas.push(try!(FormulaRef::parse(c, ctx))),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    fn encode_children(&self, tc: &mut Element) {
+        for n in &self.newparams {
+            tc.children.push(n.encode());
+        }
+        for j in &self.joints {
+            tc.children.push(j.encode());
+        }
+        for l in &self.links {
+            tc.children.push(l.encode());
+        }
+        for f in &self.formulas {
+            tc.children.push(f.encode());
+        }
+    }
+}
+
+/// A kinematic chain of links connected by joints
+#[derive(Debug)]
+pub struct KinematicsModel {
+    pub id: Option<String>,
+    pub name: Option<String>,
+    pub asset: Option<Asset>,
+    pub common: KinematicsModelCommon,
+    pub techniques: Vec<Technique>,
+    pub extras: Vec<Extra>,
+    pub unknown: UnknownContent,
+}
+
+impl KinematicsModel {
+    pub fn new() -> KinematicsModel {
+        KinematicsModel {
+            id: None,
+            name: None,
+            asset: None,
+            common: KinematicsModelCommon::new(),
+            techniques: Vec::new(),
+            extras: Vec::new(),
+            unknown: UnknownContent::new(),
+        }
+    }
+
+    /// Find a joint by its sid within the model
+    pub fn joint(&self, sid: &str) -> Option<&JointRef> {
+        self.common.joints.iter().find(|j| j.sid() == Some(sid))
+    }
+}
+
+impl XmlConversion for KinematicsModel {
+    fn parse_with(&mut self, e: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        ctx.unknown_attributes(e, &["id", "name"], &mut self.unknown);
+        self.id = utils::get_attr(e, "id");
+        self.name = utils::get_attr(e, "name");
+
+        let mut has_common = false;
+        for c in &e.children {
+            match c.name.as_str() {
+                "asset" => {
+                    let mut a = Asset::new();
+                    try!(ctx.parse(&mut a, c));
+                    self.asset = Some(a);
+                },
+                "technique_common" => {
+                    has_common = true;
+                    try!(self.common.parse_common(c, ctx));
+                },
+                "technique" => {
+                    let mut t = Technique::new();
+                    try!(ctx.parse(&mut t, c));
+                    self.techniques.push(t);
+                },
+                "extra" => {
+                    let mut x = Extra::new();
+                    try!(ctx.parse(&mut x, c));
+                    self.extras.push(x);
+                },
+                _ => try!(ctx.unknown_child(e, c, &mut self.unknown)),
+            }
+        }
+
+        if !has_common {
+            return Err(ColladaError::MissingElement{
+                structure: "kinematics_model".to_string(),
+                elem: "technique_common".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut m = Element::new("kinematics_model");
+        utils::set_attr(&mut m, "id", &self.id);
+        utils::set_attr(&mut m, "name", &self.name);
+
+        match self.asset {
+            Some(ref x) => m.children.push(x.encode()),
+            None => {},
+        }
+        m.children.push(self.common.encode_common());
+        for t in &self.techniques {
+            m.children.push(t.encode());
+        }
+        for x in &self.extras {
+            m.children.push(x.encode());
+        }
+        self.unknown.encode_into(&mut m);
+        m
+    }
+}
+
+impl HasTechniques for KinematicsModel {
+    type Common = KinematicsModelCommon;
+
+    fn technique_common(&self) -> &KinematicsModelCommon {
+        &self.common
+    }
+
+    fn techniques(&self) -> &[Technique] {
+        &self.techniques
+    }
+}
+
+/// Library of kinematics models
+#[derive(Debug)]
+pub struct LibraryKinematicsModels {
+    pub id: Option<String>,
+    pub name: Option<String>,
+    pub asset: Option<Asset>,
+    pub kinematics_models: Vec<KinematicsModel>,
+    pub extras: Vec<Extra>,
+    pub unknown: UnknownContent,
+}
+
+impl LibraryKinematicsModels {
+    pub fn new() -> LibraryKinematicsModels {
+        LibraryKinematicsModels {
+            id: None,
+            name: None,
+            asset: None,
+            kinematics_models: Vec::new(),
+            extras: Vec::new(),
+            unknown: UnknownContent::new(),
+        }
+    }
+
+    /// Find a kinematics model by its id
+    pub fn get(&self, id: &str) -> Option<&KinematicsModel> {
+        self.kinematics_models.iter().find(|m| m.id.as_ref().map(|x| x.as_str()) == Some(id))
+    }
+}
+
+impl XmlConversion for LibraryKinematicsModels {
+    fn parse_with(&mut self, e: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        ctx.unknown_attributes(e, &["id", "name"], &mut self.unknown);
+        self.id = utils::get_attr(e, "id");
+        self.name = utils::get_attr(e, "name");
+
+        for c in &e.children {
+            match c.name.as_str() {
+                "asset" => {
+                    let mut a = Asset::new();
+                    try!(ctx.parse(&mut a, c));
+                    self.asset = Some(a);
+                },
+                "kinematics_model" => {
+                    let mut m = KinematicsModel::new();
+                    try!(ctx.parse(&mut m, c));
+                    self.kinematics_models.push(m);
+                },
+                "extra" => {
+                    let mut x = Extra::new();
+                    try!(ctx.parse(&mut x, c));
+                    self.extras.push(x);
+                },
+                _ => try!(ctx.unknown_child(e, c, &mut self.unknown)),
+            }
+        }
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut l = Element::new("library_kinematics_models");
+        utils::set_attr(&mut l, "id", &self.id);
+        utils::set_attr(&mut l, "name", &self.name);
+
+        match self.asset {
+            Some(ref x) => l.children.push(x.encode()),
+            None => {},
+        }
+        for m in &self.kinematics_models {
+            l.children.push(m.encode());
+        }
+        for x in &self.extras {
+            l.children.push(x.encode());
+        }
+        self.unknown.encode_into(&mut l);
+        l
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use xmltree::{Element};
+    use traits::{XmlConversion};
+
+    #[test]
+    fn test_library_kinematics_models_parse() {
+        let data = r##"
+            <library_kinematics_models>
+                <kinematics_model id="arm" name="Arm">
+                    <technique_common>
+                        <newparam sid="reach"><float>1.5</float></newparam>
+                        <instance_joint url="#shoulder" sid="j0"/>
+                        <joint sid="j1">
+                            <revolute sid="axis0"><axis>0 1 0</axis></revolute>
+                        </joint>
+                        <link sid="base">
+                            <translate>0 0 0.5</translate>
+                            <attachment_full joint="arm/j0">
+                                <rotate>0 0 1 0</rotate>
+                                <link sid="upper">
+                                    <attachment_full joint="arm/j1">
+                                        <translate>0 0 1</translate>
+                                        <link sid="lower">
+                                            <attachment_end joint="arm/j0"/>
+                                        </link>
+                                    </attachment_full>
+                                </link>
+                            </attachment_full>
+                            <attachment_start joint="arm/j1"/>
+                        </link>
+                    </technique_common>
+                </kinematics_model>
+            </library_kinematics_models>"##;
+        let e = Element::parse(data.as_bytes()).unwrap();
+        let mut l = LibraryKinematicsModels::new();
+        match l.parse(&e) {
+            Ok(_) => assert!(true),
+            Err(_) => assert!(false),
+        }
+        let m = l.get("arm").unwrap();
+        assert_eq!(m.name, Some("Arm".to_string()));
+        assert_eq!(m.common.newparams.len(), 1);
+        assert_eq!(m.common.joints.len(), 2);
+        match *m.joint("j0").unwrap() {
+            JointRef::Instance(ref i) => assert_eq!(i.url.as_str(), "#shoulder"),
+            _ => assert!(false),
+        }
+        match *m.joint("j1").unwrap() {
+            JointRef::Inline(ref j) => assert_eq!(j.axes.len(), 1),
+            _ => assert!(false),
+        }
+
+        let base = &m.common.links[0];
+        assert_eq!(base.sid, Some("base".to_string()));
+        assert_eq!(base.transforms.len(), 1);
+        assert_eq!(base.attachments.len(), 2);
+        assert_eq!(base.attachments[0].kind, AttachmentKind::Full);
+        assert_eq!(base.attachments[0].joint.as_str(), "arm/j0");
+        assert_eq!(base.attachments[1].kind, AttachmentKind::Start);
+        let upper = base.attachments[0].link.as_ref().unwrap();
+        assert_eq!(upper.sid, Some("upper".to_string()));
+        let lower = upper.attachments[0].link.as_ref().unwrap();
+        assert_eq!(lower.attachments[0].kind, AttachmentKind::End);
+    }
+
+    #[test]
+    fn test_kinematics_model_parse_invalid() {
+        let e = Element::parse(r##"<kinematics_model id="a"/>"##.as_bytes()).unwrap();
+        assert!(KinematicsModel::new().parse(&e).is_err());
+
+        let e = Element::parse(r##"<kinematics_model><technique_common><link><attachment_full joint="j"/></link></technique_common></kinematics_model>"##.as_bytes()).unwrap();
+        assert!(KinematicsModel::new().parse(&e).is_err());
+
+        let e = Element::parse(r##"<kinematics_model><technique_common><link><attachment_end/></link></technique_common></kinematics_model>"##.as_bytes()).unwrap();
+        assert!(KinematicsModel::new().parse(&e).is_err());
+
+        let e = Element::parse(r##"<kinematics_model><technique_common><instance_joint/></technique_common></kinematics_model>"##.as_bytes()).unwrap();
+        assert!(KinematicsModel::new().parse(&e).is_err());
+    }
+
+    #[test]
+    fn test_library_kinematics_models_encode() {
+        let mut child = Link::new();
+        child.sid = Some("tip".to_string());
+        let mut a = LinkAttachment::new(AttachmentKind::Full);
+        a.joint = "arm/j0".to_string();
+        a.transforms.push(Transform::translate(0.0, 0.0, 1.0));
+        a.link = Some(Box::new(child));
+        let mut base = Link::new();
+        base.sid = Some("base".to_string());
+        base.attachments.push(a);
+
+        let mut i = InstanceJoint::new();
+        i.url = "#shoulder".to_string();
+        i.sid = Some("j0".to_string());
+        let mut m = KinematicsModel::new();
+        m.id = Some("arm".to_string());
+        m.common.joints.push(JointRef::Instance(i));
+        m.common.links.push(base);
+        let mut l = LibraryKinematicsModels::new();
+        l.kinematics_models.push(m);
+
+        let e = l.encode();
+        assert_eq!(e.name, "library_kinematics_models");
+        let tc = &e.children[0].children[0];
+        assert_eq!(tc.name, "technique_common");
+        let names: Vec<&str> = tc.children.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["instance_joint", "link"]);
+        let a = &tc.children[1].children[0];
+        assert_eq!(a.name, "attachment_full");
+        assert_eq!(a.attributes.get("joint"), Some(&"arm/j0".to_string()));
+        assert_eq!(a.children[0].name, "translate");
+        assert_eq!(a.children[1].name, "link");
+    }
+}
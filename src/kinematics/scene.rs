@@ -0,0 +1,509 @@
+use xmltree::{Element};
+use context::{ParseContext};
+use core::{Asset, Extra, UnknownContent};
+use error::{ColladaError};
+use fx::{NewParam, ParamScope, ParamValue, SetParam};
+use kinematics::{KinematicsInstance};
+use traits::{XmlConversion};
+use utils;
+
+/// A kinematics environment of kinematics models and articulated systems
+#[derive(Debug)]
+pub struct KinematicsScene {
+    pub id: Option<String>,
+    pub name: Option<String>,
+    pub asset: Option<Asset>,
+    pub instance_kinematics_models: Vec<KinematicsInstance>,
+    pub instance_articulated_systems: Vec<KinematicsInstance>,
+    pub extras: Vec<Extra>,
+    pub unknown: UnknownContent,
+}
+
+impl KinematicsScene {
+    pub fn new() -> KinematicsScene {
+        KinematicsScene {
+            id: None,
+            name: None,
+            asset: None,
+            instance_kinematics_models: Vec::new(),
+            instance_articulated_systems: Vec::new(),
+            extras: Vec::new(),
+            unknown: UnknownContent::new(),
+        }
+    }
+}
+
+impl XmlConversion for KinematicsScene {
+    fn parse_with(&mut self, e: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        ctx.unknown_attributes(e, &["id", "name"], &mut self.unknown);
+        self.id = utils::get_attr(e, "id");
+        self.name = utils::get_attr(e, "name");
+
+        for c in &e.children {
+            match c.name.as_str() {
+                "asset" => {
+                    let mut a = Asset::new();
+                    try!(ctx.parse(&mut a, c));
+                    self.asset = Some(a);
+                },
+                "instance_kinematics_model" => {
+                    let mut i = KinematicsInstance::new("instance_kinematics_model");
+                    try!(ctx.parse(&mut i, c));
+                    self.instance_kinematics_models.push(i);
+                },
+                "instance_articulated_system" => {
+                    let mut i = KinematicsInstance::new("instance_articulated_system");
+                    try!(ctx.parse(&mut i, c));
+                    self.instance_articulated_systems.push(i);
+                },
+                "extra" => {
+                    let mut x = Extra::new();
+                    try!(ctx.parse(&mut x, c));
+                    self.extras.push(x);
+                },
+                _ => try!(ctx.unknown_child(e, c, &mut self.unknown)),
+            }
+        }
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut s = Element::new("kinematics_scene");
+        utils::set_attr(&mut s, "id", &self.id);
+        utils::set_attr(&mut s, "name", &self.name);
+
+        match self.asset {
+            Some(ref x) => s.children.push(x.encode()),
+            None => {},
+        }
+        for i in &self.instance_kinematics_models {
+            s.children.push(i.encode());
+        }
+        for i in &self.instance_articulated_systems {
+            s.children.push(i.encode());
+        }
+        for x in &self.extras {
+            s.children.push(x.encode());
+        }
+        self.unknown.encode_into(&mut s);
+        s
+    }
+}
+
+/// Library of kinematics scenes
+#[derive(Debug)]
+pub struct LibraryKinematicsScenes {
+    pub id: Option<String>,
+    pub name: Option<String>,
+    pub asset: Option<Asset>,
+    pub kinematics_scenes: Vec<KinematicsScene>,
+    pub extras: Vec<Extra>,
+    pub unknown: UnknownContent,
+}
+
+impl LibraryKinematicsScenes {
+    pub fn new() -> LibraryKinematicsScenes {
+        LibraryKinematicsScenes {
+            id: None,
+            name: None,
+            asset: None,
+            kinematics_scenes: Vec::new(),
+            extras: Vec::new(),
+            unknown: UnknownContent::new(),
+        }
+    }
+
+    /// Find a kinematics scene by its id
+    pub fn get(&self, id: &str) -> Option<&KinematicsScene> {
+        self.kinematics_scenes.iter().find(|s| s.id.as_ref().map(|x| x.as_str()) == Some(id))
+    }
+}
+
+impl XmlConversion for LibraryKinematicsScenes {
+    fn parse_with(&mut self, e: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        ctx.unknown_attributes(e, &["id", "name"], &mut self.unknown);
+        self.id = utils::get_attr(e, "id");
+        self.name = utils::get_attr(e, "name");
+
+        for c in &e.children {
+            match c.name.as_str() {
+                "asset" => {
+                    let mut a = Asset::new();
+                    try!(ctx.parse(&mut a, c));
+                    self.asset = Some(a);
+                },
+                "kinematics_scene" => {
+                    let mut s = KinematicsScene::new();
+                    try!(ctx.parse(&mut s, c));
+                    self.kinematics_scenes.push(s);
+                },
+                "extra" => {
+                    let mut x = Extra::new();
+                    try!(ctx.parse(&mut x, c));
+                    self.extras.push(x);
+                },
+                _ => try!(ctx.unknown_child(e, c, &mut self.unknown)),
+            }
+        }
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut l = Element::new("library_kinematics_scenes");
+        utils::set_attr(&mut l, "id", &self.id);
+        utils::set_attr(&mut l, "name", &self.name);
+
+        match self.asset {
+            Some(ref x) => l.children.push(x.encode()),
+            None => {},
+        }
+        for s in &self.kinematics_scenes {
+            l.children.push(s.encode());
+        }
+        for x in &self.extras {
+            l.children.push(x.encode());
+        }
+        self.unknown.encode_into(&mut l);
+        l
+    }
+}
+
+/// Binds a kinematics model to the visual node it moves
+#[derive(Debug)]
+pub struct BindKinematicsModel {
+    /// Id of the node
+    pub node: String,
+
+    /// Sid reference of the instantiated kinematics model, or a parameter
+    /// holding it
+    pub value: ParamValue,
+}
+
+/// Binds a joint axis to the transformation of a visual node it drives
+#[derive(Debug)]
+pub struct BindJointAxis {
+    /// Sid path of the transformation, e.g. `elbow/rotateZ`
+    pub target: String,
+
+    /// Sid reference of the axis, or a parameter holding it
+    pub axis: ParamValue,
+
+    /// Joint value, or a parameter holding it
+    pub value: ParamValue,
+}
+
+/// A kinematics model binding resolved against the scene's parameters
+#[derive(Debug, PartialEq)]
+pub struct ModelBinding<'a> {
+    pub node: &'a str,
+
+    /// Sid reference of the instantiated kinematics model
+    pub model: &'a str,
+}
+
+/// A joint axis binding resolved against the scene's parameters
+#[derive(Debug, PartialEq)]
+pub struct JointAxisBinding<'a> {
+    /// Id of the node
+    pub node: &'a str,
+
+    /// Sid of the transformation within the node
+    pub transform: &'a str,
+
+    /// Sid reference of the joint axis
+    pub axis: &'a str,
+    pub value: Option<f32>,
+}
+
+/// Instantiates a kinematics scene in the `<scene>` and binds it to the
+/// visual scene
+#[derive(Debug)]
+pub struct InstanceKinematicsScene {
+    pub url: String,
+    pub sid: Option<String>,
+    pub name: Option<String>,
+    pub asset: Option<Asset>,
+    pub newparams: Vec<NewParam>,
+    pub setparams: Vec<SetParam>,
+    pub bind_kinematics_models: Vec<BindKinematicsModel>,
+    pub bind_joint_axes: Vec<BindJointAxis>,
+    pub extras: Vec<Extra>,
+    pub unknown: UnknownContent,
+}
+
+impl InstanceKinematicsScene {
+    pub fn new() -> InstanceKinematicsScene {
+        InstanceKinematicsScene {
+            url: String::from(""),
+            sid: None,
+            name: None,
+            asset: None,
+            newparams: Vec::new(),
+            setparams: Vec::new(),
+            bind_kinematics_models: Vec::new(),
+            bind_joint_axes: Vec::new(),
+            extras: Vec::new(),
+            unknown: UnknownContent::new(),
+        }
+    }
+
+    fn scope<'a>(&'a self) -> ParamScope<'a> {
+        ParamScope::new().with_overrides(&self.setparams).with_scope(&self.newparams)
+    }
+
+    /// Resolve the kinematics models bound to nodes, bindings whose model
+    /// can't be resolved are skipped
+    pub fn model_bindings<'a>(&'a self) -> Vec<ModelBinding<'a>> {
+        let scope = self.scope();
+        self.bind_kinematics_models.iter().filter_map(|b| {
+            match scope.resolve(&b.value) {
+                Some(&ParamValue::SidRef(ref m)) => Some(ModelBinding {
+                    node: b.node.as_str(),
+                    model: m.as_str(),
+                }),
+                _ => None,
+            }
+        }).collect()
+    }
+
+    /// Resolve the joint axes bound to node transformations, bindings whose
+    /// target or axis can't be resolved are skipped
+    pub fn joint_axis_bindings<'a>(&'a self) -> Vec<JointAxisBinding<'a>> {
+        let scope = self.scope();
+        self.bind_joint_axes.iter().filter_map(|b| {
+            let mut target = b.target.splitn(2, '/');
+            let (node, transform) = match (target.next(), target.next()) {
+                (Some(n), Some(t)) => (n, t),
+                _ => return None,
+            };
+            match scope.resolve(&b.axis) {
+                Some(&ParamValue::SidRef(ref a)) => Some(JointAxisBinding {
+                    node: node,
+                    transform: transform,
+                    axis: a.as_str(),
+                    value: scope.resolve(&b.value).and_then(|v| v.as_float()),
+                }),
+                _ => None,
+            }
+        }).collect()
+    }
+}
+
+impl XmlConversion for InstanceKinematicsScene {
+    fn parse_with(&mut self, e: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        ctx.unknown_attributes(e, &["url", "sid", "name"], &mut self.unknown);
+        self.url = try!(utils::require_attr(e, "url"));
+        self.sid = utils::get_attr(e, "sid");
+        self.name = utils::get_attr(e, "name");
+
+        for c in &e.children {
+            match c.name.as_str() {
+                "asset" => {
+                    let mut a = Asset::new();
+                    try!(ctx.parse(&mut a, c));
+                    self.asset = Some(a);
+                },
+                "newparam" => {
+                    let mut n = NewParam::new();
+                    try!(ctx.parse(&mut n, c));
+                    self.newparams.push(n);
+                },
+                "setparam" => {
+                    let mut s = SetParam::new();
+                    try!(ctx.parse(&mut s, c));
+                    self.setparams.push(s);
+                },
+                "bind_kinematics_model" => self.bind_kinematics_models.push(BindKinematicsModel {
+                    node: try!(utils::require_attr(c, "node")),
+                    value: try!(ParamValue::parse_wrapped(c, ctx)),
+                }),
+                "bind_joint_axis" => {
+                    let (mut axis, mut value) = (None, None);
+                    for x in &c.children {
+                        match x.name.as_str() {
+                            "axis" => axis = Some(try!(ParamValue::parse_wrapped(x, ctx))),
+                            "value" => value = Some(try!(ParamValue::parse_wrapped(x, ctx))),
+                            _ => return Err(ColladaError::InvalidChild{
+                                child: x.name.clone(),
+                                parent: "bind_joint_axis".to_string(),
+                            }),
+                        }
+                    }
+                    match (axis, value) {
+                        (Some(axis), Some(value)) => self.bind_joint_axes.push(BindJointAxis {
+                            target: try!(utils::require_attr(c, "target")),
+                            axis: axis,
+                            value: value,
+                        }),
+                        (axis, _) => return Err(ColladaError::MissingElement{
+                            structure: "bind_joint_axis".to_string(),
+                            elem: if axis.is_none() { "axis" } else { "value" }.to_string(),
+                        }),
+                    }
+                },
+                "extra" => {
+                    let mut x = Extra::new();
+                    try!(ctx.parse(&mut x, c));
+                    self.extras.push(x);
+                },
+                _ => try!(ctx.unknown_child(e, c, &mut self.unknown)),
+            }
+        }
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut i = Element::new("instance_kinematics_scene");
+        i.attributes.insert("url".to_string(), self.url.clone());
+        utils::set_attr(&mut i, "sid", &self.sid);
+        utils::set_attr(&mut i, "name", &self.name);
+
+        match self.asset {
+            Some(ref x) => i.children.push(x.encode()),
+            None => {},
+        }
+        for n in &self.newparams {
+            i.children.push(n.encode());
+        }
+        for s in &self.setparams {
+            i.children.push(s.encode());
+        }
+        for b in &self.bind_kinematics_models {
+            let mut x = b.value.encode_wrapped("bind_kinematics_model");
+            x.attributes.insert("node".to_string(), b.node.clone());
+            i.children.push(x);
+        }
+        for b in &self.bind_joint_axes {
+            let mut x = Element::new("bind_joint_axis");
+            x.attributes.insert("target".to_string(), b.target.clone());
+            x.children.push(b.axis.encode_wrapped("axis"));
+            x.children.push(b.value.encode_wrapped("value"));
+            i.children.push(x);
+        }
+        for x in &self.extras {
+            i.children.push(x.encode());
+        }
+        self.unknown.encode_into(&mut i);
+        i
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use xmltree::{Element};
+    use fx::{ParamValue};
+    use traits::{XmlConversion};
+
+    #[test]
+    fn test_library_kinematics_scenes_parse() {
+        let data = r##"
+            <library_kinematics_scenes>
+                <kinematics_scene id="kscene">
+                    <instance_kinematics_model url="#arm" sid="inst_arm"/>
+                    <instance_articulated_system url="#arm_motion" sid="inst_motion"/>
+                </kinematics_scene>
+            </library_kinematics_scenes>"##;
+        let e = Element::parse(data.as_bytes()).unwrap();
+        let mut l = LibraryKinematicsScenes::new();
+        match l.parse(&e) {
+            Ok(_) => assert!(true),
+            Err(_) => assert!(false),
+        }
+        let s = l.get("kscene").unwrap();
+        assert_eq!(s.instance_kinematics_models[0].url.as_str(), "#arm");
+        assert_eq!(s.instance_articulated_systems[0].sid, Some("inst_motion".to_string()));
+
+        let e = Element::parse(r##"<kinematics_scene><instance_kinematics_model/></kinematics_scene>"##.as_bytes()).unwrap();
+        assert!(KinematicsScene::new().parse(&e).is_err());
+    }
+
+    #[test]
+    fn test_instance_kinematics_scene_bindings() {
+        let data = r##"
+            <instance_kinematics_scene url="#kscene">
+                <newparam sid="model_ref"><SIDREF>kscene/inst_arm</SIDREF></newparam>
+                <newparam sid="axis0_ref"><SIDREF>kscene/inst_arm/j0/axis0</SIDREF></newparam>
+                <newparam sid="axis0_value"><float>0</float></newparam>
+                <setparam ref="axis0_value"><float>30</float></setparam>
+                <bind_kinematics_model node="arm_root">
+                    <param ref="model_ref"/>
+                </bind_kinematics_model>
+                <bind_joint_axis target="shoulder/rotateZ">
+                    <axis><param ref="axis0_ref"/></axis>
+                    <value><param ref="axis0_value"/></value>
+                </bind_joint_axis>
+                <bind_joint_axis target="elbow/rotateY">
+                    <axis><SIDREF>kscene/inst_arm/j1/axis0</SIDREF></axis>
+                    <value><float>-15</float></value>
+                </bind_joint_axis>
+                <bind_joint_axis target="wrist">
+                    <axis><SIDREF>kscene/inst_arm/j2/axis0</SIDREF></axis>
+                    <value><float>0</float></value>
+                </bind_joint_axis>
+            </instance_kinematics_scene>"##;
+        let e = Element::parse(data.as_bytes()).unwrap();
+        let mut i = InstanceKinematicsScene::new();
+        match i.parse(&e) {
+            Ok(_) => assert!(true),
+            Err(_) => assert!(false),
+        }
+        assert_eq!(i.url.as_str(), "#kscene");
+        assert_eq!(i.bind_kinematics_models.len(), 1);
+        assert_eq!(i.bind_joint_axes.len(), 3);
+
+        assert_eq!(i.model_bindings(), vec![ModelBinding{node: "arm_root", model: "kscene/inst_arm"}]);
+        let axes = i.joint_axis_bindings();
+        assert_eq!(axes.len(), 2);
+        assert_eq!(axes[0], JointAxisBinding {
+            node: "shoulder",
+            transform: "rotateZ",
+            axis: "kscene/inst_arm/j0/axis0",
+            value: Some(30.0),
+        });
+        assert_eq!(axes[1].node, "elbow");
+        assert_eq!(axes[1].value, Some(-15.0));
+
+        let e = Element::parse(r##"<instance_kinematics_scene url="#k"><bind_joint_axis target="a/b"><axis><SIDREF>x</SIDREF></axis></bind_joint_axis></instance_kinematics_scene>"##.as_bytes()).unwrap();
+        assert!(InstanceKinematicsScene::new().parse(&e).is_err());
+        let e = Element::parse(r##"<instance_kinematics_scene url="#k"><bind_kinematics_model><SIDREF>x</SIDREF></bind_kinematics_model></instance_kinematics_scene>"##.as_bytes()).unwrap();
+        assert!(InstanceKinematicsScene::new().parse(&e).is_err());
+    }
+
+    #[test]
+    fn test_kinematics_scene_encode() {
+        let mut s = KinematicsScene::new();
+        s.id = Some("kscene".to_string());
+        let mut i = KinematicsInstance::new("instance_kinematics_model");
+        i.url = "#arm".to_string();
+        s.instance_kinematics_models.push(i);
+        let mut l = LibraryKinematicsScenes::new();
+        l.kinematics_scenes.push(s);
+        let e = l.encode();
+        assert_eq!(e.name, "library_kinematics_scenes");
+        assert_eq!(e.children[0].children[0].name, "instance_kinematics_model");
+
+        let mut i = InstanceKinematicsScene::new();
+        i.url = "#kscene".to_string();
+        i.bind_kinematics_models.push(BindKinematicsModel {
+            node: "root".to_string(),
+            value: ParamValue::SidRef("kscene/arm".to_string()),
+        });
+        i.bind_joint_axes.push(BindJointAxis {
+            target: "shoulder/rotateZ".to_string(),
+            axis: ParamValue::SidRef("kscene/arm/j0/axis0".to_string()),
+            value: ParamValue::Float(10.0),
+        });
+        let e = i.encode();
+        assert_eq!(e.name, "instance_kinematics_scene");
+        let b = &e.children[0];
+        assert_eq!(b.name, "bind_kinematics_model");
+        assert_eq!(b.attributes.get("node"), Some(&"root".to_string()));
+        assert_eq!(b.children[0].name, "SIDREF");
+        let b = &e.children[1];
+        assert_eq!(b.attributes.get("target"), Some(&"shoulder/rotateZ".to_string()));
+        assert_eq!(b.children[0].name, "axis");
+        assert_eq!(b.children[1].name, "value");
+        assert_eq!(b.children[1].children[0].text, Some("10".to_string()));
+    }
+}
@@ -1,9 +1,37 @@
+extern crate xml;
 extern crate xmltree;
 
 pub mod core;
+pub mod fx;
+pub mod kinematics;
+pub mod physics;
+mod archive;
+mod axis;
 mod collada;
+mod context;
+mod documents;
 mod error;
+mod gltf;
+mod inflate;
+mod lint;
+mod mesh;
+mod obj;
 mod traits;
+mod units;
+mod utils;
+mod validate;
+mod version;
 
+pub use self::archive::*;
+pub use self::axis::*;
 pub use self::collada::*;
+pub use self::context::*;
+pub use self::documents::*;
+pub use self::error::*;
+pub use self::gltf::*;
+pub use self::lint::*;
+pub use self::obj::*;
 pub use self::traits::*;
+pub use self::units::*;
+pub use self::validate::*;
+pub use self::version::*;
@@ -0,0 +1,798 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use xmltree::{Element};
+use collada::{Collada};
+use context::{ParseContext, SourcePosition};
+use traits::{XmlConversion};
+use utils;
+
+/// The consistency checks made by a `Linter`
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum LintRule {
+    /// An `<accessor>` reads past the end of its array
+    AccessorOverrun,
+
+    /// An index in `<p>` or `<v>` is larger than the count of its source
+    IndexOutOfRange,
+
+    /// The weights of a skinned vertex don't sum to 1
+    SkinWeights,
+
+    /// A `#id` url refers to an id that isn't in the document
+    DanglingUrl,
+
+    /// An id is used by more than one element
+    DuplicateId,
+
+    /// An `<instance_material>` binds a symbol no primitive of the geometry
+    /// uses
+    UnusedMaterialSymbol,
+
+    /// A `<channel>` targets an id or sid path that doesn't exist
+    AnimationTarget,
+}
+
+impl LintRule {
+    /// All rules, in the order they are checked for each element
+    pub fn all() -> &'static [LintRule] {
+        const ALL: &'static [LintRule] = &[
+            LintRule::AccessorOverrun,
+            LintRule::IndexOutOfRange,
+            LintRule::SkinWeights,
+            LintRule::DanglingUrl,
+            LintRule::DuplicateId,
+            LintRule::UnusedMaterialSymbol,
+            LintRule::AnimationTarget,
+        ];
+        ALL
+    }
+
+    pub fn from_name(name: &str) -> Option<LintRule> {
+        LintRule::all().iter().find(|r| r.name() == name).cloned()
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            LintRule::AccessorOverrun => "accessor-overrun",
+            LintRule::IndexOutOfRange => "index-out-of-range",
+            LintRule::SkinWeights => "skin-weights",
+            LintRule::DanglingUrl => "dangling-url",
+            LintRule::DuplicateId => "duplicate-id",
+            LintRule::UnusedMaterialSymbol => "unused-material-symbol",
+            LintRule::AnimationTarget => "animation-target",
+        }
+    }
+
+    /// Severity of the rule unless it is changed
+    pub fn default_severity(&self) -> Severity {
+        match *self {
+            LintRule::SkinWeights | LintRule::UnusedMaterialSymbol => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+}
+
+/// How serious a lint is
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    pub fn from_name(name: &str) -> Option<Severity> {
+        match name {
+            "info" => Some(Severity::Info),
+            "warning" => Some(Severity::Warning),
+            "error" => Some(Severity::Error),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+}
+
+/// A problem found by a `Linter`
+#[derive(Clone, Debug)]
+pub struct Lint {
+    pub rule: LintRule,
+    pub severity: Severity,
+
+    /// Element the problem was found at, in the encoded document
+    pub position: Option<SourcePosition>,
+    pub message: String,
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.position {
+            Some(ref p) => write!(f, "{}[{}]: {}: {}", self.severity.name(), self.rule.name(), p, self.message),
+            None => write!(f, "{}[{}]: {}", self.severity.name(), self.rule.name(), self.message),
+        }
+    }
+}
+
+/// Checks documents for problems the schema doesn't catch, e.g. indices
+/// past the end of a source or urls to missing ids. Every rule is enabled
+/// with its default severity unless changed.
+///
+/// The rules run on XML elements, not on the parsed structs: a `Collada`
+/// is encoded first and every element, typed or not, is read again from its
+/// attributes and text. A lint may therefore read a value differently from
+/// the parser, e.g. a count the parser rejects is taken as missing, and
+/// checks only what is written out, not fields that `encode` leaves out.
+#[derive(Clone, Debug)]
+pub struct Linter {
+    rules: HashMap<LintRule, Severity>,
+}
+
+impl Linter {
+    pub fn new() -> Linter {
+        Linter {
+            rules: LintRule::all().iter().map(|r| (*r, r.default_severity())).collect(),
+        }
+    }
+
+    /// Enable a rule with its default severity
+    pub fn enable(mut self, rule: LintRule) -> Linter {
+        self.rules.insert(rule, rule.default_severity());
+        self
+    }
+
+    /// Don't check a rule
+    pub fn disable(mut self, rule: LintRule) -> Linter {
+        self.rules.remove(&rule);
+        self
+    }
+
+    /// Enable a rule with a different severity
+    pub fn with_severity(mut self, rule: LintRule, severity: Severity) -> Linter {
+        self.rules.insert(rule, severity);
+        self
+    }
+
+    /// The severity of a rule, None when it is disabled
+    pub fn severity(&self, rule: LintRule) -> Option<Severity> {
+        self.rules.get(&rule).cloned()
+    }
+
+    /// Check a document with the enabled rules, as the elements it encodes
+    /// to
+    pub fn check(&self, c: &Collada) -> Vec<Lint> {
+        self.check_element(&c.encode())
+    }
+
+    /// Check a `<COLLADA>` element tree with the enabled rules
+    pub fn check_element(&self, root: &Element) -> Vec<Lint> {
+        let mut d = Document {
+            ctx: ParseContext::for_element(root),
+            ids: HashMap::new(),
+            elements: Vec::new(),
+            linter: self,
+            lints: Vec::new(),
+        };
+        d.collect(root);
+
+        let elements = d.elements.clone();
+        for e in &elements {
+            for rule in LintRule::all() {
+                if self.rules.contains_key(rule) {
+                    d.check(*rule, e);
+                }
+            }
+        }
+        d.lints
+    }
+}
+
+const PRIMITIVES: &'static [&'static str] = &[
+    "lines", "linestrips", "polygons", "polylist", "triangles", "trifans", "tristrips",
+];
+
+/// Elements of a document being linted, indexed by id
+struct Document<'a> {
+    ctx: ParseContext,
+    ids: HashMap<&'a str, Vec<&'a Element>>,
+
+    /// Every element outside of profile techniques, in document order
+    elements: Vec<&'a Element>,
+    linter: &'a Linter,
+    lints: Vec<Lint>,
+}
+
+impl<'a> Document<'a> {
+    fn collect(&mut self, e: &'a Element) {
+        self.elements.push(e);
+        if let Some(id) = e.attributes.get("id") {
+            self.ids.entry(id.as_str()).or_insert(Vec::new()).push(e);
+        }
+        if e.name == "technique" && e.attributes.contains_key("profile") {
+            return;
+        }
+        for c in &e.children {
+            self.collect(c);
+        }
+    }
+
+    fn lint(&mut self, rule: LintRule, e: &Element, message: String) {
+        let severity = match self.linter.severity(rule) {
+            Some(s) => s,
+            None => return,
+        };
+        self.lints.push(Lint {
+            rule: rule,
+            severity: severity,
+            position: self.ctx.position(e).cloned(),
+            message: message,
+        });
+    }
+
+    /// The element a `#id` url refers to
+    fn resolve(&self, url: &str) -> Option<&'a Element> {
+        utils::url_id(url)
+            .and_then(|id| self.ids.get(id))
+            .map(|v| v[0])
+    }
+
+    /// The number of values of an array element, from its text
+    fn array_len(e: &Element) -> usize {
+        e.text.as_ref().map(|t| t.split_whitespace().count()).unwrap_or(0)
+    }
+
+    /// The accessor of a `<source>`
+    fn accessor(source: &Element) -> Option<&Element> {
+        source.get_child("technique_common").and_then(|t| t.get_child("accessor"))
+    }
+
+    /// The number of items a `<source>` or `<vertices>` url refers to,
+    /// following `<vertices>` that refer to other `<vertices>`
+    fn source_count(&self, url: &str, depth: usize) -> Option<usize> {
+        let e = match self.resolve(url) {
+            Some(e) => e,
+            None => return None,
+        };
+        match e.name.as_str() {
+            "source" => Document::accessor(e)
+                .and_then(|a| a.attributes.get("count"))
+                .and_then(|c| c.trim().parse().ok()),
+            "vertices" if depth < 16 => e.children.iter()
+                .find(|i| i.name == "input" && i.attributes.get("semantic").map(|s| s.as_str()) == Some("POSITION"))
+                .and_then(|i| i.attributes.get("source"))
+                .and_then(|s| self.source_count(s, depth + 1)),
+            _ => None,
+        }
+    }
+
+    /// The float values of a `<source>`, in accessor order
+    fn source_values(&self, url: &str) -> Option<Vec<f32>> {
+        let source = match self.resolve(url) {
+            Some(e) if e.name == "source" => e,
+            _ => return None,
+        };
+        let a = match Document::accessor(source) {
+            Some(a) => a,
+            None => return None,
+        };
+        let array = match a.attributes.get("source").and_then(|s| self.resolve(s)) {
+            Some(e) => e,
+            None => return None,
+        };
+        let values: Vec<f32> = match utils::parse_list(array) {
+            Ok(v) => v,
+            Err(_) => return None,
+        };
+        let count = uint_attr(a, "count").unwrap_or(0);
+        let offset = uint_attr(a, "offset").unwrap_or(0);
+        let stride = uint_attr(a, "stride").unwrap_or(1);
+
+        // Each item reads a different value, so no more items than values
+        // can be read
+        Some((0..count.min(values.len()))
+            .filter_map(|i| i.checked_mul(stride).and_then(|n| n.checked_add(offset)))
+            .filter_map(|n| values.get(n).cloned())
+            .collect())
+    }
+
+    fn check(&mut self, rule: LintRule, e: &Element) {
+        match rule {
+            LintRule::AccessorOverrun => self.check_accessor(e),
+            LintRule::IndexOutOfRange => self.check_indices(e),
+            LintRule::SkinWeights => self.check_weights(e),
+            LintRule::DanglingUrl => self.check_urls(e),
+            LintRule::DuplicateId => self.check_id(e),
+            LintRule::UnusedMaterialSymbol => self.check_symbols(e),
+            LintRule::AnimationTarget => self.check_target(e),
+        }
+    }
+
+    fn check_accessor(&mut self, e: &Element) {
+        if e.name != "accessor" {
+            return;
+        }
+        let array = match e.attributes.get("source").and_then(|s| self.resolve(s)) {
+            Some(a) => a,
+            None => return,
+        };
+        let count = uint_attr(e, "count").unwrap_or(0);
+        let offset = uint_attr(e, "offset").unwrap_or(0);
+        let stride = uint_attr(e, "stride").unwrap_or(1);
+        let params = e.children.iter().filter(|c| c.name == "param").count();
+        let len = uint_attr(array, "count").unwrap_or(Document::array_len(array));
+        let end = count.checked_mul(stride).and_then(|n| n.checked_add(offset));
+        if count > 0 && end.map(|n| n > len).unwrap_or(true) {
+            let msg = format!("accessor reads {} items with a stride of {} from offset {} but <{}> has {} values",
+                count, stride, offset, array.name, len);
+            self.lint(LintRule::AccessorOverrun, e, msg);
+        } else if params > stride {
+            let msg = format!("accessor has {} params but a stride of {}", params, stride);
+            self.lint(LintRule::AccessorOverrun, e, msg);
+        }
+    }
+
+    /// The inputs of a primitive with their offset and source url
+    fn inputs(e: &Element) -> Vec<(usize, String, String)> {
+        e.children.iter()
+            .filter(|c| c.name == "input")
+            .map(|c| (
+                uint_attr(c, "offset").unwrap_or(0),
+                utils::get_attr(c, "semantic").unwrap_or(String::new()),
+                utils::get_attr(c, "source").unwrap_or(String::new()),
+            ))
+            .collect()
+    }
+
+    /// Check the indices of a list against the inputs that read them
+    fn check_list(&mut self, e: &Element, list: &Element, inputs: &[(usize, String, String)]) {
+        let stride = inputs.iter().map(|i| i.0.saturating_add(1)).max().unwrap_or(1);
+        let values: Vec<i64> = match utils::parse_list(list) {
+            Ok(v) => v,
+            Err(_) => return,
+        };
+        let counts: Vec<Option<usize>> = inputs.iter().map(|i| self.source_count(&i.2, 0)).collect();
+        let mut reported = HashSet::new();
+        for (n, v) in values.iter().enumerate() {
+            for (input, count) in inputs.iter().zip(counts.iter()) {
+                let count = match *count {
+                    Some(c) if input.0 == n % stride => c as i64,
+                    _ => continue,
+                };
+
+                // Joint index -1 refers to the bind shape
+                let bind_shape = *v == -1 && input.1 == "JOINT";
+                if (*v < 0 || *v >= count) && !bind_shape && reported.insert(input.0) {
+                    let msg = format!("index {} in <{}> is past the {} values of {} ({})",
+                        v, list.name, count, input.2, input.1);
+                    self.lint(LintRule::IndexOutOfRange, e, msg);
+                }
+            }
+        }
+    }
+
+    fn check_indices(&mut self, e: &Element) {
+        if PRIMITIVES.contains(&e.name.as_str()) {
+            let inputs = Document::inputs(e);
+            for c in &e.children {
+                match c.name.as_str() {
+                    "p" => self.check_list(e, c, &inputs),
+                    "ph" => for l in &c.children {
+                        self.check_list(e, l, &inputs);
+                    },
+                    _ => {},
+                }
+            }
+        } else if e.name == "vertex_weights" {
+            let inputs = Document::inputs(e);
+            if let Some(v) = e.get_child("v") {
+                self.check_list(e, v, &inputs);
+            }
+        }
+    }
+
+    fn check_weights(&mut self, e: &Element) {
+        if e.name != "vertex_weights" {
+            return;
+        }
+        let inputs = Document::inputs(e);
+        let stride = inputs.iter().map(|i| i.0.saturating_add(1)).max().unwrap_or(1);
+        let weight = match inputs.iter().find(|i| i.1 == "WEIGHT") {
+            Some(i) => i,
+            None => return,
+        };
+        let weights = match self.source_values(&weight.2) {
+            Some(w) => w,
+            None => return,
+        };
+        let vcount: Vec<usize> = match e.get_child("vcount").map(|c| utils::parse_list(c)) {
+            Some(Ok(v)) => v,
+            _ => return,
+        };
+        let v: Vec<i64> = match e.get_child("v").map(|c| utils::parse_list(c)) {
+            Some(Ok(v)) => v,
+            _ => return,
+        };
+
+        let mut bad = Vec::new();
+        let mut next = 0;
+        for (vertex, n) in vcount.iter().enumerate() {
+            let end = match n.checked_mul(stride).and_then(|x| x.checked_add(next)) {
+                Some(end) if end <= v.len() => end,
+                _ => {
+                    let msg = format!("<vcount> of vertex {} reads past the {} indices of <v>", vertex, v.len());
+                    self.lint(LintRule::SkinWeights, e, msg);
+                    return;
+                },
+            };
+            let mut sum = 0.0;
+            for i in 0..*n {
+                let index = v[next + i * stride + weight.0];
+                if index >= 0 {
+                    sum += weights.get(index as usize).cloned().unwrap_or(0.0);
+                }
+            }
+            next = end;
+            if *n > 0 && (sum - 1.0f32).abs() > 1e-3 {
+                bad.push((vertex, sum));
+            }
+        }
+        if let Some(&(vertex, sum)) = bad.first() {
+            let msg = format!("weights of {} of {} vertices don't sum to 1, e.g. vertex {} sums to {}",
+                bad.len(), vcount.len(), vertex, sum);
+            self.lint(LintRule::SkinWeights, e, msg);
+        }
+    }
+
+    fn check_urls(&mut self, e: &Element) {
+        for url in utils::element_urls(e) {
+            if let Some(id) = utils::url_id(&url) {
+                if !self.ids.contains_key(id) {
+                    self.lint(LintRule::DanglingUrl, e, format!("no element has the id of {}", url));
+                }
+            }
+        }
+    }
+
+    fn check_id(&mut self, e: &Element) {
+        let id = match e.attributes.get("id") {
+            Some(id) => id,
+            None => return,
+        };
+        let first = self.ids.get(id.as_str()).map(|v| v[0] as *const Element);
+        if first.is_some() && first != Some(e as *const Element) {
+            self.lint(LintRule::DuplicateId, e, format!("id '{}' is already used", id));
+        }
+    }
+
+    /// The geometry a `<instance_geometry>` or `<instance_controller>` url
+    /// refers to, following controller sources
+    fn geometry(&self, url: &str, depth: usize) -> Option<&'a Element> {
+        let e = match self.resolve(url) {
+            Some(e) => e,
+            None => return None,
+        };
+        match e.name.as_str() {
+            "geometry" => Some(e),
+            "controller" if depth < 16 => e.children.iter()
+                .find(|c| c.name == "skin" || c.name == "morph")
+                .and_then(|c| c.attributes.get("source"))
+                .and_then(|s| self.geometry(s, depth + 1)),
+            _ => None,
+        }
+    }
+
+    fn check_symbols(&mut self, e: &Element) {
+        if e.name != "instance_geometry" && e.name != "instance_controller" {
+            return;
+        }
+        let geometry = match e.attributes.get("url").and_then(|u| self.geometry(u, 0)) {
+            Some(g) => g,
+            None => return,
+        };
+        let mut used = HashSet::new();
+        let mut stack = vec![geometry];
+        while let Some(x) = stack.pop() {
+            if PRIMITIVES.contains(&x.name.as_str()) {
+                used.extend(x.attributes.get("material").cloned());
+            }
+            stack.extend(x.children.iter());
+        }
+
+        let common = e.get_child("bind_material").and_then(|b| b.get_child("technique_common"));
+        for m in common.iter().flat_map(|c| c.children.iter()) {
+            if let Some(symbol) = m.attributes.get("symbol") {
+                if m.name == "instance_material" && !used.contains(symbol) {
+                    let msg = format!("no primitive of {} uses the material symbol '{}'", e.attributes["url"], symbol);
+                    self.lint(LintRule::UnusedMaterialSymbol, m, msg);
+                }
+            }
+        }
+    }
+
+    fn check_target(&mut self, e: &Element) {
+        if e.name != "channel" {
+            return;
+        }
+        let target = match e.attributes.get("target") {
+            Some(t) => t,
+            None => return,
+        };
+
+        // Drop the member selection, e.g. `.ANGLE` or `(3)(1)`
+        let path = match target.find('(') {
+            Some(i) => &target[..i],
+            None => target.as_str(),
+        };
+        let last = path.rfind('/').map(|i| i + 1).unwrap_or(0);
+        let path = match path[last..].find('.') {
+            Some(i) => &path[..last + i],
+            None => path,
+        };
+
+        let mut segments = path.split('/');
+        let id = segments.next().unwrap_or("");
+        if id == "." {
+            return;
+        }
+        let mut e_target = match self.ids.get(id) {
+            Some(v) => v[0],
+            None => {
+                self.lint(LintRule::AnimationTarget, e, format!("no element has the id of '{}' in {}", id, target));
+                return;
+            },
+        };
+        for sid in segments {
+            e_target = match find_sid(e_target, sid) {
+                Some(x) => x,
+                None => {
+                    self.lint(LintRule::AnimationTarget, e, format!("no element has the sid '{}' in {}", sid, target));
+                    return;
+                },
+            };
+        }
+    }
+}
+
+/// Find the closest descendant with a sid, searching breadth first
+fn find_sid<'a>(e: &'a Element, sid: &str) -> Option<&'a Element> {
+    let mut level: Vec<&Element> = e.children.iter().collect();
+    while !level.is_empty() {
+        if let Some(x) = level.iter().find(|c| c.attributes.get("sid").map(|s| s.as_str()) == Some(sid)) {
+            return Some(x);
+        }
+        level = level.iter().flat_map(|c| c.children.iter()).collect();
+    }
+    None
+}
+
+fn uint_attr(e: &Element, attr: &str) -> Option<usize> {
+    e.attributes.get(attr).and_then(|v| v.trim().parse().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use collada::{Collada};
+
+    const DOC: &'static str = r##"<COLLADA version="1.5.0">
+  <asset>
+    <created>2017-01-01T00:00:00Z</created>
+    <modified>2017-01-01T00:00:00Z</modified>
+  </asset>
+  <library_animations>
+    <animation id="spin">
+      <source id="spin-input">
+        <float_array id="spin-input-array" count="2">0 1</float_array>
+        <technique_common><accessor source="#spin-input-array" count="2"><param type="float"/></accessor></technique_common>
+      </source>
+      <sampler id="spin-sampler"><input semantic="INPUT" source="#spin-input"/></sampler>
+      <channel source="#spin-sampler" target="tri-node/rot.ANGLE"/>
+      <channel source="#spin-sampler" target="tri-node/twist.ANGLE"/>
+      <channel source="#spin-sampler" target="nowhere/rot(3)"/>
+    </animation>
+  </library_animations>
+  <library_controllers>
+    <controller id="tri-skin">
+      <skin source="#tri">
+        <source id="tri-joints">
+          <Name_array id="tri-joints-array" count="2">a b</Name_array>
+          <technique_common><accessor source="#tri-joints-array" count="2"><param type="name"/></accessor></technique_common>
+        </source>
+        <source id="tri-weights">
+          <float_array id="tri-weights-array" count="3">1 0.5 0.25</float_array>
+          <technique_common><accessor source="#tri-weights-array" count="3"><param type="float"/></accessor></technique_common>
+        </source>
+        <source id="tri-matrices">
+          <float_array id="tri-matrices-array" count="0"/>
+          <technique_common><accessor source="#tri-matrices-array" count="2" stride="16"><param type="float4x4"/></accessor></technique_common>
+        </source>
+        <joints>
+          <input semantic="JOINT" source="#tri-joints"/>
+          <input semantic="INV_BIND_MATRIX" source="#tri-matrices"/>
+        </joints>
+        <vertex_weights count="3">
+          <input semantic="JOINT" source="#tri-joints" offset="0"/>
+          <input semantic="WEIGHT" source="#tri-weights" offset="1"/>
+          <vcount>1 2 1</vcount>
+          <v>0 0 0 1 1 2 -1 0</v>
+        </vertex_weights>
+      </skin>
+    </controller>
+  </library_controllers>
+  <library_geometries>
+    <geometry id="tri">
+      <mesh>
+        <source id="tri-positions">
+          <float_array id="tri-positions-array" count="9">0 0 0 1 0 0 0 1 0</float_array>
+          <technique_common>
+            <accessor source="#tri-positions-array" count="3" stride="3">
+              <param name="X" type="float"/><param name="Y" type="float"/><param name="Z" type="float"/>
+            </accessor>
+          </technique_common>
+        </source>
+        <vertices id="tri-vertices">
+          <input semantic="POSITION" source="#tri-positions"/>
+        </vertices>
+        <triangles count="2" material="white">
+          <input semantic="VERTEX" source="#tri-vertices" offset="0"/>
+          <p>0 1 2 2 1 3</p>
+        </triangles>
+      </mesh>
+    </geometry>
+  </library_geometries>
+  <library_visual_scenes>
+    <visual_scene id="scene">
+      <node id="tri-node">
+        <rotate sid="rot">0 0 1 0</rotate>
+        <instance_controller url="#tri-skin">
+          <skeleton>#root</skeleton>
+          <bind_material>
+            <technique_common>
+              <instance_material symbol="white" target="#white"/>
+              <instance_material symbol="red" target="#white"/>
+            </technique_common>
+          </bind_material>
+        </instance_controller>
+      </node>
+      <node id="tri-node"/>
+    </visual_scene>
+  </library_visual_scenes>
+</COLLADA>"##;
+
+    fn rules(lints: &[Lint]) -> Vec<&'static str> {
+        lints.iter().map(|l| l.rule.name()).collect()
+    }
+
+    #[test]
+    fn test_lint_rules() {
+        let c = Collada::read(DOC.as_bytes()).unwrap();
+        let lints = c.lint();
+        assert_eq!(rules(&lints), vec![
+            "animation-target",
+            "animation-target",
+            "accessor-overrun",
+            "skin-weights",
+            "index-out-of-range",
+            "unused-material-symbol",
+            "dangling-url",
+            "dangling-url",
+            "dangling-url",
+            "duplicate-id",
+        ]);
+        assert!(lints[0].message.contains("'twist'"));
+        assert!(lints[1].message.contains("'nowhere'"));
+        assert!(lints[3].message.contains("1 of 3 vertices don't sum to 1, e.g. vertex 1 sums to 0.75"));
+        assert!(lints[4].message.contains("index 3"));
+        assert!(lints[5].message.contains("'red'"));
+        assert!(lints[6].message.contains("#root"));
+        assert!(lints[7].message.contains("#white"));
+
+        assert_eq!(lints[3].severity, Severity::Warning);
+        assert_eq!(lints[4].severity, Severity::Error);
+        let p = lints[4].position.as_ref().unwrap();
+        assert_eq!(p.path.as_str(), "/COLLADA/library_geometries/geometry/mesh/triangles");
+        assert!(lints[4].to_string().starts_with("error[index-out-of-range]: /COLLADA/"));
+    }
+
+    #[test]
+    fn test_lint_toggle() {
+        let c = Collada::read(DOC.as_bytes()).unwrap();
+        let linter = Linter::new()
+            .disable(LintRule::DanglingUrl)
+            .disable(LintRule::AnimationTarget)
+            .with_severity(LintRule::UnusedMaterialSymbol, Severity::Info);
+        assert_eq!(linter.severity(LintRule::DanglingUrl), None);
+        let lints = linter.check(&c);
+        assert_eq!(rules(&lints), vec![
+            "accessor-overrun",
+            "skin-weights",
+            "index-out-of-range",
+            "unused-material-symbol",
+            "duplicate-id",
+        ]);
+        assert_eq!(lints[3].severity, Severity::Info);
+
+        let linter = linter.enable(LintRule::DanglingUrl);
+        assert_eq!(linter.severity(LintRule::DanglingUrl), Some(Severity::Error));
+        assert_eq!(LintRule::from_name("skin-weights"), Some(LintRule::SkinWeights));
+        assert_eq!(Severity::from_name("warning"), Some(Severity::Warning));
+        assert!(Severity::Error > Severity::Warning);
+    }
+
+    #[test]
+    fn test_lint_overflow() {
+        let data = r##"<COLLADA version="1.5.0">
+  <asset>
+    <created>2017-01-01T00:00:00Z</created>
+    <modified>2017-01-01T00:00:00Z</modified>
+  </asset>
+  <library_controllers>
+    <controller id="skin">
+      <skin source="#mesh">
+        <source id="joints">
+          <Name_array id="joints-array" count="1">a</Name_array>
+          <technique_common><accessor source="#joints-array" count="1"><param type="name"/></accessor></technique_common>
+        </source>
+        <source id="weights">
+          <float_array id="weights-array" count="1">1</float_array>
+          <technique_common>
+            <accessor source="#weights-array" count="6148914691236517206" stride="3"><param type="float"/></accessor>
+          </technique_common>
+        </source>
+        <joints><input semantic="JOINT" source="#joints"/></joints>
+        <vertex_weights count="1">
+          <input semantic="JOINT" source="#joints" offset="0"/>
+          <input semantic="WEIGHT" source="#weights" offset="1"/>
+          <vcount>9223372036854775807</vcount>
+          <v>0 0</v>
+        </vertex_weights>
+      </skin>
+    </controller>
+  </library_controllers>
+</COLLADA>"##;
+        let c = Collada::read(data.as_bytes()).unwrap();
+        let lints = c.lint();
+        assert_eq!(rules(&lints), vec!["dangling-url", "accessor-overrun", "skin-weights"]);
+        assert!(lints[1].message.contains("6148914691236517206 items with a stride of 3"));
+        assert!(lints[2].message.contains("reads past the 2 indices"));
+    }
+
+    #[test]
+    fn test_lint_vertices_cycle() {
+        let data = r##"<COLLADA version="1.5.0">
+  <asset>
+    <created>2017-01-01T00:00:00Z</created>
+    <modified>2017-01-01T00:00:00Z</modified>
+  </asset>
+  <library_geometries>
+    <geometry id="mesh">
+      <mesh>
+        <vertices id="a">
+          <input semantic="POSITION" source="#b"/>
+        </vertices>
+        <vertices id="b">
+          <input semantic="POSITION" source="#a"/>
+        </vertices>
+        <triangles count="1">
+          <input semantic="VERTEX" source="#a" offset="0"/>
+          <p>0 1 2</p>
+        </triangles>
+      </mesh>
+    </geometry>
+  </library_geometries>
+</COLLADA>"##;
+        let c = Collada::read(data.as_bytes()).unwrap();
+        assert!(c.lint().is_empty(), "{:?}", c.lint());
+    }
+
+    #[test]
+    fn test_lint_clean() {
+        let c = Collada::new();
+        assert!(c.lint().is_empty());
+    }
+}
@@ -0,0 +1,482 @@
+//! Reading of the raw geometry, scene and animation elements that
+//! converters and exporters share
+
+use std::collections::{HashMap, HashSet};
+use xmltree::{Element};
+use collada::{Collada};
+use core::{Transform, TransformKind};
+use traits::{XmlConversion};
+use utils;
+
+/// The float values of a `<source>`: the named params of each item of its
+/// accessor
+pub struct Source {
+    pub values: Vec<f32>,
+    pub width: usize,
+}
+
+impl Source {
+    pub fn item(&self, i: usize) -> &[f32] {
+        match i.checked_mul(self.width) {
+            Some(start) if start + self.width <= self.values.len() => &self.values[start..start + self.width],
+            _ => &[],
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        if self.width == 0 { 0 } else { self.values.len() / self.width }
+    }
+}
+
+/// An `<input>` of a primitive or of vertex weights
+pub struct Input<'a> {
+    pub offset: usize,
+    pub semantic: &'a str,
+    pub source: &'a str,
+    pub set: u32,
+}
+
+pub fn inputs<'a>(e: &'a Element) -> Vec<Input<'a>> {
+    e.children.iter()
+        .filter(|c| c.name == "input")
+        .map(|c| Input {
+            offset: uint_attr(c, "offset").unwrap_or(0),
+            semantic: c.attributes.get("semantic").map(|s| s.as_str()).unwrap_or(""),
+            source: c.attributes.get("source").map(|s| s.as_str()).unwrap_or(""),
+            set: uint_attr(c, "set").unwrap_or(0) as u32,
+        })
+        .collect()
+}
+
+pub fn uint_attr(e: &Element, attr: &str) -> Option<usize> {
+    e.attributes.get(attr).and_then(|v| v.trim().parse().ok())
+}
+
+/// A primitive with one index per vertex, triangulated or as line segments
+pub struct Primitive {
+    pub lines: bool,
+    pub material: Option<String>,
+
+    /// The TEXCOORD sets, in the order of the TEXCOORD_n attributes
+    pub sets: Vec<u32>,
+
+    /// POSITION, NORMAL, TEXCOORD_n and COLOR_0 attributes with their
+    /// number of components
+    pub attributes: Vec<(String, usize, Vec<f32>)>,
+    pub indices: Vec<u32>,
+
+    /// The index of each vertex's position, for skin weights and morph
+    /// targets
+    pub positions: Vec<usize>,
+}
+
+/// The elements of the libraries that aren't implemented yet, by id
+pub struct Lookup<'a> {
+    pub ids: HashMap<&'a str, &'a Element>,
+}
+
+impl<'a> Lookup<'a> {
+    pub fn new(c: &'a Collada) -> Lookup<'a> {
+        let mut ids = HashMap::new();
+        for l in &c.other_libraries {
+            collect_ids(l, &mut ids);
+        }
+        Lookup {
+            ids: ids,
+        }
+    }
+
+    pub fn resolve(&self, url: &str) -> Option<&'a Element> {
+        utils::url_id(url).and_then(|id| self.ids.get(id)).cloned()
+    }
+
+    /// The visual scene instanced by `<scene>`, or the first one
+    pub fn visual_scene(&self, c: &'a Collada) -> Option<&'a Element> {
+        let instanced = c.scene.as_ref()
+            .and_then(|s| s.get_child("instance_visual_scene"))
+            .and_then(|i| i.attributes.get("url"))
+            .and_then(|u| self.resolve(u));
+        if instanced.is_some() {
+            return instanced;
+        }
+        c.other_libraries.iter()
+            .filter(|l| l.name == "library_visual_scenes")
+            .flat_map(|l| l.children.iter())
+            .find(|v| v.name == "visual_scene")
+    }
+
+    /// The float values of a `<source>`, without the items of its accessor
+    /// that are past the end of the array
+    pub fn source(&self, url: &str) -> Option<Source> {
+        let source = match self.resolve(url) {
+            Some(s) if s.name == "source" => s,
+            _ => return None,
+        };
+        let accessor = source.get_child("technique_common").and_then(|t| t.get_child("accessor"));
+        let array = match accessor.and_then(|a| a.attributes.get("source")).and_then(|s| self.resolve(s))
+            .or(source.get_child("float_array"))
+        {
+            Some(a) => a,
+            None => return None,
+        };
+        let values: Vec<f32> = match utils::parse_list(array) {
+            Ok(v) => v,
+            Err(_) => return None,
+        };
+        let accessor = match accessor {
+            Some(a) => a,
+            None => return Some(Source {
+                values: values,
+                width: 1,
+            }),
+        };
+
+        let count = uint_attr(accessor, "count").unwrap_or(0);
+        let offset = uint_attr(accessor, "offset").unwrap_or(0);
+        let stride = uint_attr(accessor, "stride").unwrap_or(1);
+        // Params without a name are skipped, matrix params take all their
+        // values
+        let mut params = Vec::new();
+        let mut next = 0;
+        for p in accessor.children.iter().filter(|p| p.name == "param") {
+            let size = match p.attributes.get("type").map(|t| t.as_str()) {
+                Some("float2") => 2,
+                Some("float3") => 3,
+                Some("float4") | Some("float2x2") => 4,
+                Some("float3x3") => 9,
+                Some("float4x4") => 16,
+                _ => 1,
+            };
+            if p.attributes.contains_key("name") {
+                params.extend(next..next + size);
+            }
+            next += size;
+        }
+        let params = if params.is_empty() { (0..stride.min(values.len())).collect() } else { params };
+        if stride == 0 || params.is_empty() {
+            return None;
+        }
+
+        // Only read the items the array has values for, whatever the count
+        let last = params.iter().cloned().max().unwrap_or(0);
+        let count = match offset.checked_add(last) {
+            Some(end) if end < values.len() => count.min((values.len() - end - 1) / stride + 1),
+            _ => 0,
+        };
+        let mut out = Vec::with_capacity(count * params.len());
+        for i in 0..count {
+            let start = offset + i * stride;
+            for p in &params {
+                out.push(values[start + p]);
+            }
+        }
+        Some(Source {
+            values: out,
+            width: params.len(),
+        })
+    }
+
+    /// The names of a `<source>` with a `Name_array` or `IDREF_array`, and
+    /// whether they are ids
+    pub fn names(&self, url: &str) -> Option<(Vec<String>, bool)> {
+        let source = match self.resolve(url) {
+            Some(s) if s.name == "source" => s,
+            _ => return None,
+        };
+        let (array, idref) = match source.get_child("Name_array") {
+            Some(a) => (a, false),
+            None => match source.get_child("IDREF_array") {
+                Some(a) => (a, true),
+                None => return None,
+            },
+        };
+        let names = array.text.as_ref().map(|t| t.split_whitespace().map(|s| s.to_string()).collect());
+        names.map(|n| (n, idref))
+    }
+
+    /// Read a primitive of a mesh, triangulated, with its vertices split
+    /// where their inputs differ
+    pub fn primitive(&self, p: &'a Element) -> Option<Primitive> {
+        let lines = match p.name.as_str() {
+            "triangles" | "polylist" | "polygons" | "trifans" | "tristrips" => false,
+            "lines" | "linestrips" => true,
+            _ => return None,
+        };
+        let own = inputs(p);
+        let stride = own.iter().map(|i| i.offset + 1).max().unwrap_or(1);
+
+        // The inputs of <vertices> are read with the VERTEX offset
+        let mut all = Vec::new();
+        let mut vertex_offset = None;
+        for i in own {
+            if i.semantic != "VERTEX" {
+                all.push(i);
+                continue;
+            }
+            vertex_offset = Some(i.offset);
+            let vertices = match self.resolve(i.source) {
+                Some(v) => v,
+                None => continue,
+            };
+            for mut v in inputs(vertices) {
+                v.offset = i.offset;
+                v.set = i.set;
+                all.push(v);
+            }
+        }
+        let vertex_offset = match vertex_offset {
+            Some(o) => o,
+            None => return None,
+        };
+
+        // Every list of indices, and the corners to output from them
+        let lists: Vec<Vec<u32>> = p.children.iter()
+            .flat_map(|c| match c.name.as_str() {
+                "p" => vec![c],
+                "ph" => c.children.iter().filter(|x| x.name == "p").take(1).collect(),
+                _ => Vec::new(),
+            })
+            .map(|c| utils::parse_list(c).unwrap_or(Vec::new()))
+            .collect();
+        let mut values = Vec::new();
+        let mut corners = Vec::new();
+        let vcount: Vec<usize> = p.get_child("vcount").and_then(|c| utils::parse_list(c).ok()).unwrap_or(Vec::new());
+        for list in &lists {
+            let first = values.len() / stride;
+            let n = list.len() / stride;
+            values.extend_from_slice(&list[..n * stride]);
+            match p.name.as_str() {
+                "triangles" => corners.extend(first..first + n / 3 * 3),
+                "lines" => corners.extend(first..first + n / 2 * 2),
+                "polylist" => {
+                    let mut start = first;
+                    for count in &vcount {
+                        if start + count > first + n {
+                            break;
+                        }
+                        fan(&mut corners, start, *count);
+                        start += count;
+                    }
+                },
+                "polygons" | "trifans" => fan(&mut corners, first, n),
+                "tristrips" => {
+                    for k in 2..n {
+                        let (a, b) = if k % 2 == 0 { (k - 2, k - 1) } else { (k - 1, k - 2) };
+                        corners.extend_from_slice(&[first + a, first + b, first + k]);
+                    }
+                },
+                _ => {
+                    for k in 1..n {
+                        corners.extend_from_slice(&[first + k - 1, first + k]);
+                    }
+                },
+            }
+        }
+
+        // One vertex for each distinct combination of indices
+        let mut vertices: HashMap<&[u32], u32> = HashMap::new();
+        let mut order: Vec<&[u32]> = Vec::new();
+        let mut indices = Vec::with_capacity(corners.len());
+        for c in corners {
+            let key = &values[c * stride..(c + 1) * stride];
+            let next = order.len() as u32;
+            let i = *vertices.entry(key).or_insert(next);
+            if i == next {
+                order.push(key);
+            }
+            indices.push(i);
+        }
+
+        let mut sets: Vec<u32> = all.iter().filter(|i| i.semantic == "TEXCOORD").map(|i| i.set).collect();
+        sets.sort();
+        sets.dedup();
+        let mut attributes = Vec::new();
+        let mut seen = HashSet::new();
+        for input in &all {
+            let (name, width) = match input.semantic {
+                "POSITION" => ("POSITION".to_string(), 3),
+                "NORMAL" => ("NORMAL".to_string(), 3),
+                "TEXCOORD" => {
+                    let k = sets.iter().position(|s| *s == input.set).unwrap_or(0);
+                    (format!("TEXCOORD_{}", k), 2)
+                },
+                "COLOR" => ("COLOR_0".to_string(), 4),
+                _ => continue,
+            };
+            if !seen.insert(name.clone()) {
+                continue;
+            }
+            let source = match self.source(input.source) {
+                Some(s) => s,
+                None => continue,
+            };
+            let mut data = Vec::with_capacity(order.len() * width);
+            for v in &order {
+                let item = source.item(v[input.offset] as usize);
+                let x = |i: usize, default: f32| item.get(i).cloned().unwrap_or(default);
+                match input.semantic {
+                    "TEXCOORD" => data.extend_from_slice(&[x(0, 0.0), x(1, 0.0)]),
+                    "COLOR" => data.extend_from_slice(&[x(0, 0.0), x(1, 0.0), x(2, 0.0), x(3, 1.0)]),
+                    "NORMAL" => data.extend_from_slice(&normalize([x(0, 0.0), x(1, 0.0), x(2, 0.0)])),
+                    _ => data.extend_from_slice(&[x(0, 0.0), x(1, 0.0), x(2, 0.0)]),
+                }
+            }
+            attributes.push((name, width, data));
+        }
+        if !seen.contains("POSITION") {
+            return None;
+        }
+
+        Some(Primitive {
+            lines: lines,
+            material: p.attributes.get("material").cloned(),
+            sets: sets,
+            attributes: attributes,
+            indices: indices,
+            positions: order.iter().map(|v| v[vertex_offset] as usize).collect(),
+        })
+    }
+}
+
+fn collect_ids<'a>(e: &'a Element, ids: &mut HashMap<&'a str, &'a Element>) {
+    if let Some(id) = e.attributes.get("id") {
+        ids.entry(id.as_str()).or_insert(e);
+    }
+    for c in &e.children {
+        collect_ids(c, ids);
+    }
+}
+
+/// The transformation elements of a node
+pub fn node_transforms(e: &Element) -> Vec<Transform> {
+    e.children.iter()
+        .filter_map(|c| TransformKind::from_name(&c.name).map(|k| (k, c)))
+        .filter_map(|(k, c)| {
+            let mut t = Transform::new(k);
+            t.parse(c).ok().map(|_| t)
+        })
+        .collect()
+}
+
+/// Add the triangles of a fan of `n` corners from `first`
+fn fan(corners: &mut Vec<usize>, first: usize, n: usize) {
+    for k in 2..n {
+        corners.extend_from_slice(&[first, first + k - 1, first + k]);
+    }
+}
+
+pub fn normalize(v: [f32; 3]) -> [f32; 3] {
+    let len = (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+    if len == 0.0 { v } else { [v[0] / len, v[1] / len, v[2] / len] }
+}
+
+/// The values an animation output animates
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Output {
+    /// Whole transforms of a kind
+    Transform(TransformKind),
+
+    /// The `.X`, `.Y` or `.Z` member of a transform's vector
+    Member(TransformKind, usize),
+}
+
+/// The outputs of animation channels that target transforms, by the id of
+/// the output `<source>` and by the target of the `<channel>`
+pub struct Animations {
+    pub sources: HashMap<String, Output>,
+    pub channels: HashMap<String, Output>,
+}
+
+/// Find the transforms animation channels of raw libraries target
+pub fn animation_outputs(libraries: &[Element]) -> Animations {
+    fn collect<'a>(e: &'a Element, ids: &mut HashMap<&'a str, &'a Element>, channels: &mut Vec<&'a Element>) {
+        if let Some(id) = e.attributes.get("id") {
+            ids.entry(id.as_str()).or_insert(e);
+        }
+        if e.name == "channel" {
+            channels.push(e);
+        }
+        for c in &e.children {
+            collect(c, ids, channels);
+        }
+    }
+
+    let mut ids = HashMap::new();
+    let mut channels = Vec::new();
+    for l in libraries {
+        collect(l, &mut ids, &mut channels);
+    }
+
+    let mut a = Animations {
+        sources: HashMap::new(),
+        channels: HashMap::new(),
+    };
+    for c in channels {
+        let target = match c.attributes.get("target") {
+            Some(t) if !t.contains('(') => t,
+            _ => continue,
+        };
+        let (path, member) = match target.rfind('.') {
+            Some(i) if i > target.rfind('/').unwrap_or(0) => (&target[..i], Some(&target[i + 1..])),
+            _ => (target.as_str(), None),
+        };
+        let mut segments = path.split('/');
+        let mut e = match segments.next().and_then(|id| ids.get(id)) {
+            Some(e) => *e,
+            None => continue,
+        };
+        let mut found = true;
+        for sid in segments {
+            match find_sid(e, sid) {
+                Some(x) => e = x,
+                None => {
+                    found = false;
+                    break;
+                },
+            }
+        }
+        let kind = match TransformKind::from_name(&e.name) {
+            Some(k) if found => k,
+            _ => continue,
+        };
+
+        let output = match member {
+            None => Output::Transform(kind),
+            Some("X") => Output::Member(kind, 0),
+            Some("Y") => Output::Member(kind, 1),
+            Some("Z") => Output::Member(kind, 2),
+            Some(_) => continue,
+        };
+        match output {
+            Output::Member(TransformKind::Rotate, _) | Output::Member(TransformKind::Scale, _) |
+            Output::Member(TransformKind::Translate, _) | Output::Transform(_) => {},
+            Output::Member(..) => continue,
+        }
+
+        let sampler = match c.attributes.get("source").and_then(|s| utils::url_id(s)).and_then(|id| ids.get(id)) {
+            Some(s) => *s,
+            None => continue,
+        };
+        let source = sampler.children.iter()
+            .find(|i| i.name == "input" && i.attributes.get("semantic").map(|s| s.as_str()) == Some("OUTPUT"))
+            .and_then(|i| i.attributes.get("source"))
+            .and_then(|s| utils::url_id(s));
+        if let Some(id) = source {
+            a.sources.insert(id.to_string(), output);
+            a.channels.insert(target.clone(), output);
+        }
+    }
+    a
+}
+
+/// Find the closest descendant with a sid, searching breadth first
+pub fn find_sid<'a>(e: &'a Element, sid: &str) -> Option<&'a Element> {
+    let mut level: Vec<&Element> = e.children.iter().collect();
+    while !level.is_empty() {
+        if let Some(x) = level.iter().find(|c| c.attributes.get("sid").map(|s| s.as_str()) == Some(sid)) {
+            return Some(x);
+        }
+        level = level.iter().flat_map(|c| c.children.iter()).collect();
+    }
+    None
+}
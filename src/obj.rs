@@ -0,0 +1,1264 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Write};
+use xmltree::{Element};
+use collada::{Collada};
+use core::{IDENTITY, InstanceController, InstanceGeometry, Matrix, UpAxis, compose, multiply, transform_point};
+use error::{ColladaError};
+use fx::{BindMaterial, Effect, EffectProfile, Image, ImageData, ImageSource, InitFrom, LibraryEffects,
+         LibraryImages, LibraryMaterials, Material};
+use mesh::{Lookup, node_transforms, normalize};
+use traits::{XmlConversion};
+use utils;
+
+/// Instances of nodes can nest this deep, which also stops cycles
+const MAX_DEPTH: usize = 64;
+
+/// A Wavefront OBJ file and the MTL file of its materials
+#[derive(Debug)]
+pub struct Obj {
+    pub obj: String,
+    pub mtl: String,
+}
+
+/// A corner of a face: indices of the position, texture coordinate and
+/// normal, from 0
+type Corner = (usize, Option<usize>, Option<usize>);
+
+/// An `o` or `g` of an OBJ file
+struct Object {
+    name: String,
+
+    /// Faces with the material they use
+    faces: Vec<(Option<String>, Vec<Corner>)>,
+
+    /// Line strips of positions
+    lines: Vec<Vec<usize>>,
+}
+
+impl Object {
+    fn new(name: &str) -> Object {
+        Object {
+            name: name.to_string(),
+            faces: Vec::new(),
+            lines: Vec::new(),
+        }
+    }
+}
+
+/// A `newmtl` of an MTL file
+struct ObjMaterial {
+    name: String,
+    ambient: Option<[f32; 3]>,
+    diffuse: Option<[f32; 3]>,
+    specular: Option<[f32; 3]>,
+    emission: Option<[f32; 3]>,
+    shininess: Option<f32>,
+    opacity: Option<f32>,
+    illum: Option<u32>,
+    diffuse_map: Option<String>,
+}
+
+impl ObjMaterial {
+    fn new(name: &str) -> ObjMaterial {
+        ObjMaterial {
+            name: name.to_string(),
+            ambient: None,
+            diffuse: None,
+            specular: None,
+            emission: None,
+            shininess: None,
+            opacity: None,
+            illum: None,
+            diffuse_map: None,
+        }
+    }
+}
+
+fn invalid(line: usize, msg: &str) -> ColladaError {
+    ColladaError::InvalidObj {
+        line: line,
+        msg: msg.to_string(),
+    }
+}
+
+/// The keyword and arguments of each line that isn't empty or a comment,
+/// with lines ending in `\` joined to the next
+fn statements(data: &str) -> Vec<(usize, String)> {
+    let mut out: Vec<(usize, String)> = Vec::new();
+    let mut continued = false;
+    for (i, line) in data.lines().enumerate() {
+        let line = match line.find('#') {
+            Some(c) => &line[..c],
+            None => line,
+        };
+        let trimmed = line.trim_end();
+        let (text, next) = if trimmed.ends_with('\\') {
+            (&trimmed[..trimmed.len() - 1], true)
+        } else {
+            (trimmed, false)
+        };
+        if continued {
+            if let Some(last) = out.last_mut() {
+                last.1.push(' ');
+                last.1.push_str(text);
+            }
+        } else if !text.trim().is_empty() {
+            out.push((i + 1, text.trim().to_string()));
+        }
+        continued = next;
+    }
+    out
+}
+
+fn parse_floats(line: usize, args: &[&str], min: usize, max: usize) -> Result<Vec<f32>, ColladaError> {
+    if args.len() < min {
+        return Err(invalid(line, "too few values"));
+    }
+    let mut out = Vec::new();
+    for a in args.iter().take(max) {
+        match a.parse() {
+            Ok(x) => out.push(x),
+            Err(_) => return Err(invalid(line, &format!("invalid number '{}'", a))),
+        }
+    }
+    Ok(out)
+}
+
+/// A color of one grey value or three values. Spectral and CIEXYZ colors
+/// are ignored.
+fn parse_color(line: usize, args: &[&str]) -> Result<Option<[f32; 3]>, ColladaError> {
+    if args.first().map(|a| *a == "spectral" || *a == "xyz") == Some(true) {
+        return Ok(None);
+    }
+    let v = try!(parse_floats(line, args, 1, 3));
+    Ok(Some(if v.len() < 3 { [v[0], v[0], v[0]] } else { [v[0], v[1], v[2]] }))
+}
+
+/// An index of a vertex element, counting from 1 or back from the end
+fn parse_index(line: usize, s: &str, len: usize) -> Result<usize, ColladaError> {
+    let i: i64 = match s.parse() {
+        Ok(i) => i,
+        Err(_) => return Err(invalid(line, &format!("invalid index '{}'", s))),
+    };
+    let index = if i < 0 { len as i64 + i } else { i - 1 };
+    if index < 0 || index >= len as i64 {
+        return Err(invalid(line, &format!("index {} out of range", i)));
+    }
+    Ok(index as usize)
+}
+
+fn parse_mtl(data: &str) -> Result<Vec<ObjMaterial>, ColladaError> {
+    let mut materials: Vec<ObjMaterial> = Vec::new();
+    for (line, text) in statements(data) {
+        let parts: Vec<&str> = text.split_whitespace().collect();
+        let (keyword, args) = (parts[0], &parts[1..]);
+        if keyword == "newmtl" {
+            materials.push(ObjMaterial::new(&args.join(" ")));
+            continue;
+        }
+        let m = match materials.last_mut() {
+            Some(m) => m,
+            None => return Err(invalid(line, "material statement before newmtl")),
+        };
+        match keyword {
+            "Ka" => m.ambient = try!(parse_color(line, args)),
+            "Kd" => m.diffuse = try!(parse_color(line, args)),
+            "Ks" => m.specular = try!(parse_color(line, args)),
+            "Ke" => m.emission = try!(parse_color(line, args)),
+            "Ns" => m.shininess = Some(try!(parse_floats(line, args, 1, 1))[0]),
+            "d" => {
+                // `d -halo 0.5` fades with the viewing angle, keep the value
+                let v = args.iter().filter(|a| !a.starts_with('-')).cloned().collect::<Vec<&str>>();
+                m.opacity = Some(try!(parse_floats(line, &v, 1, 1))[0]);
+            },
+            "Tr" => m.opacity = Some(1.0 - try!(parse_floats(line, args, 1, 1))[0]),
+            "illum" => m.illum = match args.first().and_then(|a| a.parse().ok()) {
+                Some(i) => Some(i),
+                None => return Err(invalid(line, "invalid illumination model")),
+            },
+            // Options come before the file name
+            "map_Kd" => m.diffuse_map = args.last().map(|a| a.to_string()),
+            _ => {},
+        }
+    }
+    Ok(materials)
+}
+
+/// An id made of the characters allowed in ids, different from the ids
+/// already used
+fn unique_id(name: &str, used: &mut HashSet<String>) -> String {
+    let mut id: String = name.chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' || c == '.' { c } else { '_' })
+        .collect();
+    if !id.starts_with(|c: char| c.is_alphabetic() || c == '_') {
+        id.insert(0, '_');
+    }
+    let mut unique = id.clone();
+    let mut n = 2;
+    while used.contains(&unique) {
+        unique = format!("{}-{}", id, n);
+        n += 1;
+    }
+    used.insert(unique.clone());
+    unique
+}
+
+fn element(name: &str, attributes: &[(&str, &str)], children: Vec<Element>) -> Element {
+    let mut e = Element::new(name);
+    for &(k, v) in attributes {
+        e.attributes.insert(k.to_string(), v.to_string());
+    }
+    e.children = children;
+    e
+}
+
+fn color_element(name: &str, c: [f32; 3]) -> Element {
+    element(name, &[], vec![utils::list_element("color", &[c[0], c[1], c[2], 1.0])])
+}
+
+fn float_element(name: &str, x: f32) -> Element {
+    element(name, &[], vec![utils::text_element("float", x.to_string())])
+}
+
+/// A `<source>` of floats with named params
+fn float_source(id: &str, values: &[f32], params: &[&str]) -> Element {
+    let array_id = format!("{}-array", id);
+    let mut array = utils::list_element("float_array", values);
+    array.attributes.insert("id".to_string(), array_id.clone());
+    array.attributes.insert("count".to_string(), values.len().to_string());
+    let mut accessor = element("accessor", &[
+        ("source", &format!("#{}", array_id)),
+        ("count", &(values.len() / params.len()).to_string()),
+        ("stride", &params.len().to_string()),
+    ], Vec::new());
+    for p in params {
+        accessor.children.push(element("param", &[("name", p), ("type", "float")], Vec::new()));
+    }
+    element("source", &[("id", id)], vec![
+        array,
+        element("technique_common", &[], vec![accessor]),
+    ])
+}
+
+/// Read a Wavefront OBJ file and the MTL file of its materials, if any.
+/// Each `o` or `g` becomes a geometry with a polylist for each material,
+/// instanced by a node of one visual scene. MTL materials become `lambert`
+/// effects, or `phong` ones when they have a specular color or an
+/// illumination model with highlights.
+pub fn import_obj(obj: &str, mtl: Option<&str>) -> Result<Collada, ColladaError> {
+    let mut positions: Vec<f32> = Vec::new();
+    let mut texcoords: Vec<f32> = Vec::new();
+    let mut normals: Vec<f32> = Vec::new();
+    let mut objects = vec![Object::new("default")];
+    let mut material: Option<String> = None;
+    let mut used_materials: Vec<String> = Vec::new();
+
+    for (line, text) in statements(obj) {
+        let parts: Vec<&str> = text.split_whitespace().collect();
+        let (keyword, args) = (parts[0], &parts[1..]);
+        match keyword {
+            "v" => positions.extend(try!(parse_floats(line, args, 3, 3))),
+            "vt" => {
+                let v = try!(parse_floats(line, args, 1, 2));
+                texcoords.extend_from_slice(&[v[0], v.get(1).cloned().unwrap_or(0.0)]);
+            },
+            "vn" => normals.extend(try!(parse_floats(line, args, 3, 3))),
+            "f" => {
+                if args.len() < 3 {
+                    return Err(invalid(line, "face with fewer than 3 vertices"));
+                }
+                let mut corners = Vec::new();
+                for a in args {
+                    let mut it = a.split('/');
+                    let v = try!(parse_index(line, it.next().unwrap_or(""), positions.len() / 3));
+                    let vt = match it.next() {
+                        Some(t) if !t.is_empty() => Some(try!(parse_index(line, t, texcoords.len() / 2))),
+                        _ => None,
+                    };
+                    let vn = match it.next() {
+                        Some(n) if !n.is_empty() => Some(try!(parse_index(line, n, normals.len() / 3))),
+                        _ => None,
+                    };
+                    corners.push((v, vt, vn));
+                }
+                if let Some(ref m) = material {
+                    if !used_materials.contains(m) {
+                        used_materials.push(m.clone());
+                    }
+                }
+                objects.last_mut().unwrap().faces.push((material.clone(), corners));
+            },
+            "l" => {
+                if args.len() < 2 {
+                    return Err(invalid(line, "line with fewer than 2 vertices"));
+                }
+                let mut strip = Vec::new();
+                for a in args {
+                    strip.push(try!(parse_index(line, a.split('/').next().unwrap_or(""), positions.len() / 3)));
+                }
+                objects.last_mut().unwrap().lines.push(strip);
+            },
+            "o" | "g" => {
+                let name = if args.is_empty() { "default".to_string() } else { args.join(" ") };
+                let last = objects.last_mut().unwrap();
+                if last.faces.is_empty() && last.lines.is_empty() {
+                    last.name = name;
+                } else {
+                    objects.push(Object::new(&name));
+                }
+            },
+            "usemtl" => material = Some(args.join(" ")),
+            _ => {},
+        }
+    }
+
+    let mut c = Collada::new();
+    c.asset.up_axis = Some(UpAxis::YUP);
+    let mut ids = HashSet::new();
+
+    // Materials of the MTL file, then defaults for any others used
+    let mut materials = match mtl {
+        Some(m) => try!(parse_mtl(m)),
+        None => Vec::new(),
+    };
+    for m in &used_materials {
+        if !materials.iter().any(|x| &x.name == m) {
+            let mut d = ObjMaterial::new(m);
+            d.diffuse = Some([0.8, 0.8, 0.8]);
+            materials.push(d);
+        }
+    }
+    let mut material_ids = HashMap::new();
+    let mut library_effects = LibraryEffects::new();
+    let mut library_materials = LibraryMaterials::new();
+    let mut library_images = LibraryImages::new();
+    for m in &materials {
+        let id = unique_id(&m.name, &mut ids);
+        let (effect, image) = common_effect(m, &id, &mut ids);
+        let mut material = Material::new();
+        material.id = Some(id.clone());
+        material.name = Some(m.name.clone());
+        material.instance_effect.url = format!("#{}", effect.id);
+        library_effects.effects.push(effect);
+        library_materials.materials.push(material);
+        if let Some(i) = image {
+            library_images.images.push(i);
+        }
+        material_ids.insert(m.name.clone(), id);
+    }
+    if !library_images.images.is_empty() {
+        c.library_images.push(library_images);
+    }
+    if !library_effects.effects.is_empty() {
+        c.library_effects.push(library_effects);
+        c.library_materials.push(library_materials);
+    }
+
+    let mut geometries = Vec::new();
+    let mut nodes = Vec::new();
+    for o in objects.iter().filter(|o| !o.faces.is_empty() || !o.lines.is_empty()) {
+        let id = unique_id(&o.name, &mut ids);
+        let (geometry, symbols) = geometry(o, &id, &positions, &texcoords, &normals);
+        geometries.push(geometry);
+
+        let mut instance = element("instance_geometry", &[("url", &format!("#{}", id))], Vec::new());
+        if !symbols.is_empty() {
+            let mut technique = element("technique_common", &[], Vec::new());
+            for s in symbols {
+                let target = format!("#{}", material_ids[&s]);
+                technique.children.push(element("instance_material", &[("symbol", &s), ("target", &target)], vec![
+                    element("bind_vertex_input", &[
+                        ("semantic", "UV"),
+                        ("input_semantic", "TEXCOORD"),
+                        ("input_set", "0"),
+                    ], Vec::new()),
+                ]));
+            }
+            instance.children.push(element("bind_material", &[], vec![technique]));
+        }
+        let node_id = unique_id(&format!("{}-node", id), &mut ids);
+        nodes.push(element("node", &[("id", &node_id), ("name", &o.name)], vec![instance]));
+    }
+
+    let scene_id = unique_id("scene", &mut ids);
+    c.other_libraries.push(element("library_geometries", &[], geometries));
+    c.other_libraries.push(element("library_visual_scenes", &[], vec![
+        element("visual_scene", &[("id", &scene_id)], nodes),
+    ]));
+    c.scene = Some(element("scene", &[], vec![
+        element("instance_visual_scene", &[("url", &format!("#{}", scene_id))], Vec::new()),
+    ]));
+    Ok(c)
+}
+
+/// The `profile_COMMON` effect of an MTL material, and the image of its
+/// diffuse map
+fn common_effect(m: &ObjMaterial, id: &str, ids: &mut HashSet<String>) -> (Effect, Option<Image>) {
+    let phong = m.specular.map(|s| s != [0.0; 3]).unwrap_or(false) || m.illum.map(|i| i >= 2).unwrap_or(false);
+    let mut shading = element(if phong { "phong" } else { "lambert" }, &[], Vec::new());
+    let mut profile = element("profile_COMMON", &[], Vec::new());
+
+    if let Some(e) = m.emission {
+        shading.children.push(color_element("emission", e));
+    }
+    if let Some(a) = m.ambient {
+        shading.children.push(color_element("ambient", a));
+    }
+    let mut image = None;
+    match m.diffuse_map {
+        Some(ref path) => {
+            let image_id = unique_id(&format!("{}-image", id), ids);
+            let sampler = format!("{}-sampler", image_id);
+            profile.children.push(element("newparam", &[("sid", &sampler)], vec![
+                element("sampler2D", &[], vec![
+                    element("instance_image", &[("url", &format!("#{}", image_id))], Vec::new()),
+                ]),
+            ]));
+            shading.children.push(element("diffuse", &[], vec![
+                element("texture", &[("texture", &sampler), ("texcoord", "UV")], Vec::new()),
+            ]));
+            let mut init_from = InitFrom::new();
+            init_from.source = ImageSource::Ref(path.replace('\\', "/"));
+            let mut i = Image::new();
+            i.id = Some(image_id);
+            i.data = ImageData::InitFrom(init_from);
+            image = Some(i);
+        },
+        None => if let Some(d) = m.diffuse {
+            shading.children.push(color_element("diffuse", d));
+        },
+    }
+    if phong {
+        shading.children.push(color_element("specular", m.specular.unwrap_or([0.0; 3])));
+        shading.children.push(float_element("shininess", m.shininess.unwrap_or(0.0)));
+    }
+    if let Some(d) = m.opacity {
+        if d < 1.0 {
+            shading.children.push(element("transparent", &[("opaque", "A_ONE")], vec![
+                utils::list_element("color", &[1.0, 1.0, 1.0, 1.0]),
+            ]));
+            shading.children.push(float_element("transparency", d));
+        }
+    }
+    profile.children.push(element("technique", &[("sid", "common")], vec![shading]));
+
+    let mut effect = Effect::new();
+    effect.id = unique_id(&format!("{}-effect", id), ids);
+    effect.name = Some(m.name.clone());
+    effect.profiles.push(EffectProfile::Common(profile));
+    (effect, image)
+}
+
+/// The geometry of an object with the positions, texture coordinates and
+/// normals it uses, and the material symbols of its primitives
+fn geometry(o: &Object, id: &str, positions: &[f32], texcoords: &[f32], normals: &[f32])
+    -> (Element, Vec<String>)
+{
+    // Indices of the OBJ file to indices of the geometry, in order of use
+    fn remap(map: &mut HashMap<usize, usize>, order: &mut Vec<usize>, i: usize) -> usize {
+        let next = order.len();
+        let local = *map.entry(i).or_insert(next);
+        if local == next {
+            order.push(i);
+        }
+        local
+    }
+    let mut maps = (HashMap::new(), HashMap::new(), HashMap::new());
+    let mut orders = (Vec::new(), Vec::new(), Vec::new());
+
+    // Faces by material and the inputs they have, in order of use
+    let mut groups: Vec<((Option<String>, bool, bool), Vec<Vec<usize>>)> = Vec::new();
+    for &(ref material, ref corners) in &o.faces {
+        let uv = corners.iter().all(|c| c.1.is_some());
+        let normal = corners.iter().all(|c| c.2.is_some());
+        let key = (material.clone(), uv, normal);
+        let mut face = Vec::new();
+        for &(v, vt, vn) in corners {
+            face.push(remap(&mut maps.0, &mut orders.0, v));
+            if let (true, Some(t)) = (uv, vt) {
+                face.push(remap(&mut maps.1, &mut orders.1, t));
+            }
+            if let (true, Some(n)) = (normal, vn) {
+                face.push(remap(&mut maps.2, &mut orders.2, n));
+            }
+        }
+        match groups.iter().position(|g| g.0 == key) {
+            Some(i) => groups[i].1.push(face),
+            None => groups.push((key, vec![face])),
+        }
+    }
+    let strips: Vec<Vec<usize>> = o.lines.iter()
+        .map(|l| l.iter().map(|v| remap(&mut maps.0, &mut orders.0, *v)).collect())
+        .collect();
+
+    let gather = |order: &[usize], values: &[f32], width: usize| -> Vec<f32> {
+        order.iter().flat_map(|i| values[i * width..(i + 1) * width].to_vec()).collect()
+    };
+    let vertices_id = format!("{}-vertices", id);
+    let mut mesh = element("mesh", &[], vec![
+        float_source(&format!("{}-positions", id), &gather(&orders.0, positions, 3), &["X", "Y", "Z"]),
+    ]);
+    if !orders.1.is_empty() {
+        mesh.children.push(float_source(&format!("{}-texcoords", id), &gather(&orders.1, texcoords, 2), &["S", "T"]));
+    }
+    if !orders.2.is_empty() {
+        mesh.children.push(float_source(&format!("{}-normals", id), &gather(&orders.2, normals, 3), &["X", "Y", "Z"]));
+    }
+    mesh.children.push(element("vertices", &[("id", &vertices_id)], vec![
+        element("input", &[("semantic", "POSITION"), ("source", &format!("#{}-positions", id))], Vec::new()),
+    ]));
+
+    let mut symbols = Vec::new();
+    for ((material, uv, normal), faces) in groups {
+        let mut inputs = vec![
+            element("input", &[("semantic", "VERTEX"), ("source", &format!("#{}", vertices_id)), ("offset", "0")],
+                    Vec::new()),
+        ];
+        let mut offset = 1;
+        if uv {
+            inputs.push(element("input", &[
+                ("semantic", "TEXCOORD"),
+                ("source", &format!("#{}-texcoords", id)),
+                ("offset", &offset.to_string()),
+                ("set", "0"),
+            ], Vec::new()));
+            offset += 1;
+        }
+        if normal {
+            inputs.push(element("input", &[
+                ("semantic", "NORMAL"),
+                ("source", &format!("#{}-normals", id)),
+                ("offset", &offset.to_string()),
+            ], Vec::new()));
+            offset += 1;
+        }
+        let vcount: Vec<usize> = faces.iter().map(|f| f.len() / offset).collect();
+        let p: Vec<usize> = faces.concat();
+        let mut polylist = element("polylist", &[("count", &faces.len().to_string())], inputs);
+        if let Some(m) = material {
+            polylist.attributes.insert("material".to_string(), m.clone());
+            if !symbols.contains(&m) {
+                symbols.push(m);
+            }
+        }
+        polylist.children.push(utils::list_element("vcount", &vcount));
+        polylist.children.push(utils::list_element("p", &p));
+        mesh.children.push(polylist);
+    }
+    if !strips.is_empty() {
+        let p: Vec<usize> = strips.iter()
+            .flat_map(|s| s.windows(2).flat_map(|w| w.to_vec()).collect::<Vec<usize>>())
+            .collect();
+        mesh.children.push(element("lines", &[("count", &(p.len() / 2).to_string())], vec![
+            element("input", &[("semantic", "VERTEX"), ("source", &format!("#{}", vertices_id)), ("offset", "0")],
+                    Vec::new()),
+            utils::list_element("p", &p),
+        ]));
+    }
+
+    let geometry = element("geometry", &[("id", id), ("name", &o.name)], vec![mesh]);
+    (geometry, symbols)
+}
+
+/// Write the meshes of the visual scene of `<scene>`, or the first one, as
+/// Wavefront OBJ with the world transforms of their nodes applied and
+/// faces triangulated. Skinned and morphed meshes are written in their bind
+/// pose. Materials come from `profile_COMMON` effects, and the OBJ refers
+/// to the MTL file as `mtl_uri`. The unit and up axis are kept as they are,
+/// see `convert_units` and `convert_up_axis`.
+pub fn export_obj(c: &Collada, mtl_uri: &str) -> Obj {
+    let mut x = ObjExporter {
+        c: c,
+        lookup: Lookup::new(c),
+        obj: String::new(),
+        mtl: String::new(),
+        counts: (0, 0, 0),
+        materials: HashMap::new(),
+        names: HashSet::new(),
+    };
+    if let Some(scene) = x.lookup.visual_scene(c) {
+        for n in scene.children.iter().filter(|n| n.name == "node") {
+            x.node(n, &IDENTITY, 0);
+        }
+    }
+    let mut obj = String::new();
+    if !x.mtl.is_empty() {
+        obj.push_str(&format!("mtllib {}\n", mtl_uri));
+    }
+    obj.push_str(&x.obj);
+    Obj {
+        obj: obj,
+        mtl: x.mtl,
+    }
+}
+
+struct ObjExporter<'a> {
+    c: &'a Collada,
+    lookup: Lookup<'a>,
+    obj: String,
+    mtl: String,
+
+    /// The number of positions, texture coordinates and normals written
+    counts: (usize, usize, usize),
+
+    /// The MTL names of materials by id
+    materials: HashMap<String, String>,
+    names: HashSet<String>,
+}
+
+impl<'a> ObjExporter<'a> {
+    fn node(&mut self, e: &'a Element, parent: &Matrix, depth: usize) {
+        let world = multiply(parent, &compose(&node_transforms(e)));
+        let name = e.attributes.get("name").or(e.attributes.get("id")).cloned();
+        for c in &e.children {
+            match c.name.as_str() {
+                "node" if depth < MAX_DEPTH => self.node(c, &world, depth + 1),
+                "instance_node" if depth < MAX_DEPTH => {
+                    match c.attributes.get("url").and_then(|u| self.lookup.resolve(u)) {
+                        Some(n) if n.name == "node" => self.node(n, &world, depth + 1),
+                        _ => {},
+                    }
+                },
+                "instance_geometry" => {
+                    let mut i = InstanceGeometry::new();
+                    if i.parse(c).is_ok() {
+                        self.mesh(&i.url, name.as_ref(), &world, i.bind_material.as_ref());
+                    }
+                },
+                "instance_controller" => {
+                    let mut i = InstanceController::new();
+                    if i.parse(c).is_ok() {
+                        if let Some((url, bind_shape)) = self.controller_geometry(&i.url) {
+                            let m = multiply(&world, &bind_shape);
+                            self.mesh(&url, name.as_ref(), &m, i.bind_material.as_ref());
+                        }
+                    }
+                },
+                _ => {},
+            }
+        }
+    }
+
+    /// The geometry a controller deforms and the bind shape of its skin
+    fn controller_geometry(&self, url: &str) -> Option<(String, Matrix)> {
+        let mut url = url.to_string();
+        let mut bind_shape = IDENTITY;
+        for _ in 0..MAX_DEPTH {
+            let e = match self.lookup.resolve(&url) {
+                Some(e) => e,
+                None => return None,
+            };
+            if e.name != "controller" {
+                return Some((url, bind_shape));
+            }
+            let deformer = match e.get_child("skin").or(e.get_child("morph")) {
+                Some(d) => d,
+                None => return None,
+            };
+            if let Some(m) = deformer.get_child("bind_shape_matrix").and_then(|m| utils::parse_array(m, 16).ok()) {
+                let mut matrix = [0.0; 16];
+                matrix.copy_from_slice(&m);
+                bind_shape = multiply(&bind_shape, &matrix);
+            }
+            url = match deformer.attributes.get("source") {
+                Some(s) => s.clone(),
+                None => return None,
+            };
+        }
+        None
+    }
+
+    fn mesh(&mut self, url: &str, name: Option<&String>, m: &Matrix, bind: Option<&BindMaterial>) {
+        let mesh = match self.lookup.resolve(url).filter(|g| g.name == "geometry").and_then(|g| g.get_child("mesh")) {
+            Some(m) => m,
+            None => return,
+        };
+        let normal_matrix = cofactors(m);
+        // Mirroring turns faces inside out unless their order is reversed
+        let mirrored = determinant(m) < 0.0;
+
+        let _ = writeln!(self.obj, "o {}", name.map(|n| n.as_str()).unwrap_or("mesh"));
+        for p in &mesh.children {
+            let prim = match self.lookup.primitive(p) {
+                Some(x) => x,
+                None => continue,
+            };
+            let attribute = |name: &str| prim.attributes.iter().find(|a| a.0 == name).map(|a| &a.2);
+            let count = prim.positions.len();
+            if let Some(v) = attribute("POSITION") {
+                for p in v.chunks(3) {
+                    let x = transform_point(m, [p[0], p[1], p[2]]);
+                    let _ = writeln!(self.obj, "v {} {} {}", x[0], x[1], x[2]);
+                }
+            }
+            let uv = attribute("TEXCOORD_0");
+            if let Some(t) = uv {
+                for t in t.chunks(2) {
+                    let _ = writeln!(self.obj, "vt {} {}", t[0], t[1]);
+                }
+            }
+            let normal = attribute("NORMAL");
+            if let Some(n) = normal {
+                for n in n.chunks(3) {
+                    let x = normalize(transform_direction(&normal_matrix, [n[0], n[1], n[2]]));
+                    let _ = writeln!(self.obj, "vn {} {} {}", x[0], x[1], x[2]);
+                }
+            }
+
+            let material = match (prim.material.as_ref(), bind) {
+                (Some(s), Some(b)) => self.material(b, s),
+                _ => None,
+            };
+            if let Some(m) = material {
+                let _ = writeln!(self.obj, "usemtl {}", m);
+            }
+
+            let (v, t, n) = self.counts;
+            let corner = |i: u32| {
+                let i = i as usize + 1;
+                match (uv.is_some(), normal.is_some()) {
+                    (true, true) => format!("{}/{}/{}", v + i, t + i, n + i),
+                    (true, false) => format!("{}/{}", v + i, t + i),
+                    (false, true) => format!("{}//{}", v + i, n + i),
+                    (false, false) => format!("{}", v + i),
+                }
+            };
+            let size = if prim.lines { 2 } else { 3 };
+            for f in prim.indices.chunks(size) {
+                let mut corners: Vec<String> = f.iter().map(|i| corner(*i)).collect();
+                if mirrored && !prim.lines {
+                    corners.reverse();
+                }
+                let _ = writeln!(self.obj, "{} {}", if prim.lines { "l" } else { "f" }, corners.join(" "));
+            }
+
+            self.counts.0 += count;
+            if uv.is_some() {
+                self.counts.1 += count;
+            }
+            if normal.is_some() {
+                self.counts.2 += count;
+            }
+        }
+    }
+
+    /// The MTL name of the material bound to a symbol, writing the material
+    /// the first time
+    fn material(&mut self, bind: &BindMaterial, symbol: &str) -> Option<String> {
+        let c = self.c;
+        let binding = match bind.resolve(symbol, &c.library_materials, &c.library_effects) {
+            Some(b) => b,
+            None => return None,
+        };
+        let id = binding.material.id.clone().unwrap_or(binding.instance.target.clone());
+        if let Some(name) = self.materials.get(&id) {
+            return Some(name.clone());
+        }
+
+        // MTL names can't have spaces and must be unique
+        let base: String = binding.material.name.as_ref().unwrap_or(&id).split_whitespace().collect::<Vec<_>>()
+            .join("_");
+        let mut name = base.clone();
+        let mut n = 2;
+        while self.names.contains(&name) {
+            name = format!("{}_{}", base, n);
+            n += 1;
+        }
+        self.names.insert(name.clone());
+        self.materials.insert(id, name.clone());
+
+        let _ = writeln!(self.mtl, "newmtl {}", name);
+        let common = match binding.effect.common() {
+            Some(t) => t,
+            None => {
+                let _ = writeln!(self.mtl, "Kd 0.8 0.8 0.8\n");
+                return Some(name);
+            },
+        };
+        let rgb = |c: [f32; 4]| format!("{} {} {}", c[0], c[1], c[2]);
+
+        // Constant shading has only an emission color, which OBJ's color
+        // without lighting takes from the diffuse
+        let constant = common.kind() == "constant";
+        let base = if constant { "emission" } else { "diffuse" };
+        if !constant {
+            if let Some(a) = common.color("ambient") {
+                let _ = writeln!(self.mtl, "Ka {}", rgb(a));
+            }
+        }
+        let texture = common.texture(base).and_then(|t| common.image(t.0, &c.library_images));
+        match (common.color(base), texture) {
+            (Some(d), _) => { let _ = writeln!(self.mtl, "Kd {}", rgb(d)); },
+            (None, Some(_)) => { let _ = writeln!(self.mtl, "Kd 1 1 1"); },
+            _ => {},
+        }
+        let specular = common.kind() == "phong" || common.kind() == "blinn";
+        if specular {
+            if let Some(s) = common.color("specular") {
+                let _ = writeln!(self.mtl, "Ks {}", rgb(s));
+            }
+            if let Some(s) = common.float("shininess") {
+                let _ = writeln!(self.mtl, "Ns {}", s);
+            }
+        }
+        if !constant {
+            if let Some(e) = common.color("emission") {
+                let _ = writeln!(self.mtl, "Ke {}", rgb(e));
+            }
+        }
+        if let Some(d) = common.opacity() {
+            let _ = writeln!(self.mtl, "d {}", d);
+        }
+        let _ = writeln!(self.mtl, "illum {}", if constant { 0 } else if specular { 2 } else { 1 });
+        if let Some(uri) = texture.and_then(|i| i.uris().first().cloned()) {
+            let _ = writeln!(self.mtl, "map_Kd {}", uri);
+        }
+        self.mtl.push('\n');
+        Some(name)
+    }
+}
+
+fn determinant(m: &Matrix) -> f32 {
+    m[0] * (m[5] * m[10] - m[6] * m[9]) - m[1] * (m[4] * m[10] - m[6] * m[8]) + m[2] * (m[4] * m[9] - m[5] * m[8])
+}
+
+/// The cofactors of the linear part of a matrix, which transform normals
+/// like the inverse transpose up to scale
+fn cofactors(m: &Matrix) -> [[f32; 3]; 3] {
+    let a = |r: usize, c: usize| m[r * 4 + c];
+    let mut out = [[0.0; 3]; 3];
+    for r in 0..3 {
+        for c in 0..3 {
+            let (r1, r2) = ((r + 1) % 3, (r + 2) % 3);
+            let (c1, c2) = ((c + 1) % 3, (c + 2) % 3);
+            out[r][c] = a(r1, c1) * a(r2, c2) - a(r1, c2) * a(r2, c1);
+        }
+    }
+    // Keep normals pointing out of mirrored faces
+    if determinant(m) < 0.0 {
+        for row in &mut out {
+            for x in row.iter_mut() {
+                *x = -*x;
+            }
+        }
+    }
+    out
+}
+
+fn transform_direction(m: &[[f32; 3]; 3], v: [f32; 3]) -> [f32; 3] {
+    let mut out = [0.0; 3];
+    for r in 0..3 {
+        out[r] = m[r][0] * v[0] + m[r][1] * v[1] + m[r][2] * v[2];
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gltf::{GltfOptions};
+
+    const OBJ: &'static str = "
+# A quad and a triangle
+mtllib box.mtl
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vt 0 0
+vt 1 0
+vt 1 1
+vt 0 1
+vn 0 0 1
+o quad
+usemtl red
+f 1/1/1 2/2/1 3/3/1 4/4/1
+o tri
+usemtl missing
+f -4//-1 -3//-1 \\
+  -2//-1
+l 1 2 3
+";
+
+    const MTL: &'static str = "
+newmtl red
+Ka 0.1
+Kd 1 0 0
+Ks 0.5 0.5 0.5
+Ns 20
+d 0.5
+map_Kd -bm 1 textures\\red.png
+";
+
+    fn child<'a>(e: &'a Element, name: &str) -> &'a Element {
+        e.get_child(name).unwrap()
+    }
+
+    #[test]
+    fn test_import_obj() {
+        let c = import_obj(OBJ, Some(MTL)).unwrap();
+        assert!(c.validate().is_empty(), "{:?}", c.validate());
+        assert_eq!(c.asset.up_axis, Some(UpAxis::YUP));
+
+        let geometries = c.other_libraries.iter().find(|l| l.name == "library_geometries").unwrap();
+        assert_eq!(geometries.children.len(), 2);
+        let quad = child(&geometries.children[0], "mesh");
+        assert_eq!(geometries.children[0].attributes["id"], "quad");
+        let polylist = child(quad, "polylist");
+        assert_eq!(polylist.attributes["material"], "red");
+        assert_eq!(polylist.children.iter().filter(|i| i.name == "input").count(), 3);
+        assert_eq!(child(polylist, "vcount").text.as_ref().unwrap(), "4");
+        assert_eq!(child(polylist, "p").text.as_ref().unwrap(), "0 0 0 1 1 0 2 2 0 3 3 0");
+
+        // Indices are local to each geometry, lines are split into segments
+        let tri = child(&geometries.children[1], "mesh");
+        assert_eq!(tri.children.iter().filter(|s| s.name == "source").count(), 2);
+        let polylist = child(tri, "polylist");
+        assert_eq!(polylist.attributes["material"], "missing");
+        assert_eq!(child(polylist, "p").text.as_ref().unwrap(), "0 0 1 0 2 0");
+        assert_eq!(child(child(tri, "lines"), "p").text.as_ref().unwrap(), "0 1 1 2");
+
+        let effect = c.library_effects[0].get("red-effect").unwrap();
+        let common = effect.common().unwrap();
+        assert_eq!(common.kind(), "phong");
+        assert_eq!(common.color("ambient"), Some([0.1, 0.1, 0.1, 1.0]));
+        assert_eq!(common.float("shininess"), Some(20.0));
+        assert_eq!(common.opacity(), Some(0.5));
+        let (texture, texcoord) = common.texture("diffuse").unwrap();
+        assert_eq!(texcoord, "UV");
+        assert_eq!(common.image(texture, &c.library_images).unwrap().uris(), vec!["textures/red.png"]);
+        assert_eq!(c.library_effects[0].get("missing-effect").unwrap().common().unwrap().kind(), "lambert");
+
+        let scene = c.other_libraries.iter().find(|l| l.name == "library_visual_scenes").unwrap();
+        let node = &child(scene, "visual_scene").children[0];
+        let instance = child(child(child(node, "instance_geometry"), "bind_material"), "technique_common");
+        assert_eq!(instance.children[0].attributes["symbol"], "red");
+        assert_eq!(instance.children[0].attributes["target"], "#red");
+
+        assert!(import_obj("v 0 0\n", None).is_err());
+        match import_obj("v 0 0 0\nf 1 2 3\n", None) {
+            Err(ColladaError::InvalidObj { line, .. }) => assert_eq!(line, 2),
+            _ => panic!("expected an invalid index"),
+        }
+        assert!(import_obj("Kd 1 1 1\n", None).is_ok());
+        assert!(import_obj("", Some("Kd 1 1 1\n")).is_err());
+    }
+
+    #[test]
+    fn test_export_obj() {
+        let data = r##"<?xml version="1.0" encoding="utf-8"?>
+<COLLADA xmlns="http://www.collada.org/2005/11/COLLADASchema" version="1.4.1">
+  <asset>
+    <created>2017-01-01T00:00:00Z</created>
+    <modified>2017-01-01T00:00:00Z</modified>
+  </asset>
+  <library_effects>
+    <effect id="fx">
+      <profile_COMMON>
+        <technique sid="common">
+          <lambert>
+            <diffuse><color>0 1 0 1</color></diffuse>
+          </lambert>
+        </technique>
+      </profile_COMMON>
+    </effect>
+  </library_effects>
+  <library_materials>
+    <material id="green" name="Green Paint">
+      <instance_effect url="#fx"/>
+    </material>
+  </library_materials>
+  <library_geometries>
+    <geometry id="tri">
+      <mesh>
+        <source id="tri-positions">
+          <float_array id="tri-positions-array" count="9">0 0 0 1 0 0 0 1 0</float_array>
+          <technique_common>
+            <accessor source="#tri-positions-array" count="3" stride="3">
+              <param name="X" type="float"/>
+              <param name="Y" type="float"/>
+              <param name="Z" type="float"/>
+            </accessor>
+          </technique_common>
+        </source>
+        <vertices id="tri-vertices">
+          <input semantic="POSITION" source="#tri-positions"/>
+        </vertices>
+        <triangles count="1" material="mat">
+          <input semantic="VERTEX" source="#tri-vertices" offset="0"/>
+          <p>0 1 2</p>
+        </triangles>
+      </mesh>
+    </geometry>
+  </library_geometries>
+  <library_visual_scenes>
+    <visual_scene id="scene">
+      <node id="a" name="moved">
+        <translate>0 0 5</translate>
+        <scale>-1 1 1</scale>
+        <instance_geometry url="#tri">
+          <bind_material>
+            <technique_common>
+              <instance_material symbol="mat" target="#green"/>
+            </technique_common>
+          </bind_material>
+        </instance_geometry>
+      </node>
+    </visual_scene>
+  </library_visual_scenes>
+  <scene>
+    <instance_visual_scene url="#scene"/>
+  </scene>
+</COLLADA>"##;
+        let c = Collada::read(data.as_bytes()).unwrap();
+        let o = c.to_obj("tri.mtl");
+        assert_eq!(o.obj, "mtllib tri.mtl\no moved\nv 0 0 5\nv -1 0 5\nv 0 1 5\nusemtl Green_Paint\nf 3 2 1\n");
+        assert_eq!(o.mtl, "newmtl Green_Paint\nKd 0 1 0\nillum 1\n\n");
+
+        // Importing the export gives the same faces, with the vertices in
+        // the order the faces use them
+        let c = Collada::from_obj(&o.obj, Some(&o.mtl)).unwrap();
+        assert!(c.validate().is_empty(), "{:?}", c.validate());
+        let o2 = c.to_obj("tri.mtl");
+        assert_eq!(o2.obj, "mtllib tri.mtl\no moved\nv 0 1 5\nv -1 0 5\nv 0 0 5\nusemtl Green_Paint\nf 1 2 3\n");
+        assert_eq!(o2.mtl, o.mtl);
+
+        // OBJ round trips through COLLADA
+        let c = import_obj(OBJ, Some(MTL)).unwrap();
+        let o = c.to_obj("box.mtl");
+        assert!(o.obj.starts_with("mtllib box.mtl\no quad\n"));
+        assert!(o.obj.contains("f 1/1/1 2/2/2 3/3/3\nf 1/1/1 3/3/3 4/4/4\n"));
+        assert!(o.obj.contains("\nl "));
+        assert!(o.mtl.contains("newmtl red\nKa 0.1 0.1 0.1\nKd 1 1 1\nKs 0.5 0.5 0.5\nNs 20\nd 0.5\nillum 2\nmap_Kd textures/red.png\n"));
+    }
+
+    #[test]
+    fn test_export_obj_accessor_overrun() {
+        // Accessors that claim more values than their arrays have
+        let data = r##"<?xml version="1.0" encoding="utf-8"?>
+<COLLADA xmlns="http://www.collada.org/2005/11/COLLADASchema" version="1.4.1">
+  <asset>
+    <created>2017-01-01T00:00:00Z</created>
+    <modified>2017-01-01T00:00:00Z</modified>
+  </asset>
+  <library_geometries>
+    <geometry id="tri">
+      <mesh>
+        <source id="tri-positions">
+          <float_array id="tri-positions-array" count="10">0 0 0 1 0 0 0 1 0 7</float_array>
+          <technique_common>
+            <accessor source="#tri-positions-array" count="6148914691236517206" stride="3">
+              <param name="X" type="float"/>
+              <param name="Y" type="float"/>
+              <param name="Z" type="float"/>
+            </accessor>
+          </technique_common>
+        </source>
+        <source id="tri-normals">
+          <float_array id="tri-normals-array" count="3">0 0 1</float_array>
+          <technique_common>
+            <accessor source="#tri-normals-array" count="3" stride="18446744073709551615"/>
+          </technique_common>
+        </source>
+        <source id="tri-uvs">
+          <float_array id="tri-uvs-array" count="2">0 0</float_array>
+          <technique_common>
+            <accessor source="#tri-uvs-array" count="1" offset="18446744073709551615" stride="2">
+              <param name="S" type="float"/>
+              <param name="T" type="float"/>
+            </accessor>
+          </technique_common>
+        </source>
+        <vertices id="tri-vertices">
+          <input semantic="POSITION" source="#tri-positions"/>
+        </vertices>
+        <triangles count="1">
+          <input semantic="VERTEX" source="#tri-vertices" offset="0"/>
+          <input semantic="NORMAL" source="#tri-normals" offset="0"/>
+          <input semantic="TEXCOORD" source="#tri-uvs" offset="0"/>
+          <p>0 1 2</p>
+        </triangles>
+      </mesh>
+    </geometry>
+  </library_geometries>
+  <library_visual_scenes>
+    <visual_scene id="scene">
+      <node id="a">
+        <instance_geometry url="#tri"/>
+      </node>
+    </visual_scene>
+  </library_visual_scenes>
+</COLLADA>"##;
+        let c = Collada::read(data.as_bytes()).unwrap();
+        let lookup = Lookup::new(&c);
+        assert_eq!(lookup.source("#tri-positions").unwrap().values, vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0]);
+        assert_eq!(lookup.source("#tri-normals").unwrap().values, vec![0.0, 0.0, 1.0]);
+        assert_eq!(lookup.source("#tri-uvs").unwrap().count(), 0);
+
+        let o = c.to_obj("tri.mtl");
+        assert!(o.obj.contains("v 0 0 0\nv 1 0 0\nv 0 1 0\n"), "{}", o.obj);
+        c.to_gltf(&GltfOptions::new());
+    }
+
+
+    #[test]
+    fn test_import_obj_negative_indices() {
+        // Negative indices count back from the last vertex read so far
+        let c = import_obj("v 0 0 0\nv 1 0 0\nv 0 1 0\nf -3 -2 -1\nv 1 1 0\nf 2 -1 -2\n", None).unwrap();
+        let geometries = c.other_libraries.iter().find(|l| l.name == "library_geometries").unwrap();
+        let mesh = child(&geometries.children[0], "mesh");
+        assert_eq!(child(child(mesh, "source"), "float_array").text.as_ref().unwrap(), "0 0 0 1 0 0 0 1 0 1 1 0");
+        assert_eq!(child(child(mesh, "polylist"), "p").text.as_ref().unwrap(), "0 1 2 1 3 2");
+
+        for &(data, line) in &[
+            ("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 0 1 2\n", 4),
+            ("v 0 0 0\nv 1 0 0\nv 0 1 0\nf -4 1 2\n", 4),
+            ("v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0 0\nf 1/-2 2/1 3/1\n", 5),
+            ("v 0 0 0\nv 1 0 0\nl -1 -2\nl 1 -3\n", 4),
+        ] {
+            match import_obj(data, None) {
+                Err(ColladaError::InvalidObj { line: l, .. }) => assert_eq!(l, line, "{}", data),
+                _ => panic!("expected an invalid index in {}", data),
+            }
+        }
+    }
+
+    #[test]
+    fn test_obj_material_groups() {
+        // Faces of one object with the same material and inputs share a
+        // polylist, in order of first use
+        let data = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nvt 0 0\n\
+                    usemtl a\nf 1 2 3\nusemtl b\nf 1 3 4\nusemtl a\nf 2 3 4\nf 1/1 2/1 3/1\n";
+        let c = import_obj(data, None).unwrap();
+        assert!(c.validate().is_empty(), "{:?}", c.validate());
+        let geometries = c.other_libraries.iter().find(|l| l.name == "library_geometries").unwrap();
+        assert_eq!(geometries.children.len(), 1);
+        let polylists: Vec<&Element> = child(&geometries.children[0], "mesh").children.iter()
+            .filter(|p| p.name == "polylist")
+            .collect();
+        let summary: Vec<(&str, &str, &str)> = polylists.iter()
+            .map(|p| (p.attributes["material"].as_str(), p.attributes["count"].as_str(),
+                      child(p, "p").text.as_ref().unwrap().as_str()))
+            .collect();
+        assert_eq!(summary, vec![("a", "2", "0 1 2 1 2 3"), ("b", "1", "0 2 3"), ("a", "1", "0 0 1 0 2 0")]);
+
+        let scene = c.other_libraries.iter().find(|l| l.name == "library_visual_scenes").unwrap();
+        let node = &child(scene, "visual_scene").children[0];
+        let technique = child(child(child(node, "instance_geometry"), "bind_material"), "technique_common");
+        let symbols: Vec<&str> = technique.children.iter().map(|i| i.attributes["symbol"].as_str()).collect();
+        assert_eq!(symbols, vec!["a", "b"]);
+
+        let o = c.to_obj("groups.mtl");
+        assert_eq!(o.obj, "mtllib groups.mtl\no default\n\
+                           v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nusemtl a\nf 1 2 3\nf 2 3 4\n\
+                           v 0 0 0\nv 1 1 0\nv 0 1 0\nusemtl b\nf 5 6 7\n\
+                           v 0 0 0\nv 1 0 0\nv 1 1 0\nvt 0 0\nvt 0 0\nvt 0 0\nusemtl a\nf 8/1 9/2 10/3\n");
+        assert_eq!(o.mtl, "newmtl a\nKd 0.8 0.8 0.8\nillum 1\n\nnewmtl b\nKd 0.8 0.8 0.8\nillum 1\n\n");
+    }
+
+    #[test]
+    fn test_obj_texture_maps() {
+        let mtl = "newmtl wood\nmap_Kd -o 0.5 0.5 0 -s 2 2 1 -clamp on textures\\oak.png\n\
+                   newmtl metal\nKd 0.5 0.5 0.5\nmap_Kd metal.png\n\
+                   newmtl plain\nKd 1 0 0\n";
+        let obj = "mtllib maps.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0 0\nvt 1 0\nvt 0 1\n\
+                   usemtl wood\nf 1/1 2/2 3/3\nusemtl metal\nf 1/1 2/2 3/3\nusemtl plain\nf 1 2 3\n";
+        let c = import_obj(obj, Some(mtl)).unwrap();
+        assert!(c.validate().is_empty(), "{:?}", c.validate());
+        assert_eq!(c.library_images[0].images.len(), 2);
+        for &(id, uri) in &[("wood", "textures/oak.png"), ("metal", "metal.png")] {
+            let common = c.library_effects[0].get(&format!("{}-effect", id)).unwrap().common().unwrap();
+            let (texture, texcoord) = common.texture("diffuse").unwrap();
+            assert_eq!(texcoord, "UV");
+            assert_eq!(common.image(texture, &c.library_images).unwrap().uris(), vec![uri]);
+        }
+        assert!(c.library_effects[0].get("plain-effect").unwrap().common().unwrap().texture("diffuse").is_none());
+
+        // Textured materials export a white diffuse color for the map to
+        // multiply
+        let o = c.to_obj("maps.mtl");
+        assert_eq!(o.mtl, "newmtl wood\nKd 1 1 1\nillum 1\nmap_Kd textures/oak.png\n\n\
+                           newmtl metal\nKd 1 1 1\nillum 1\nmap_Kd metal.png\n\n\
+                           newmtl plain\nKd 1 0 0\nillum 1\n\n");
+    }
+
+    #[test]
+    fn test_export_obj_polygons() {
+        let data = r##"<?xml version="1.0" encoding="utf-8"?>
+<COLLADA xmlns="http://www.collada.org/2005/11/COLLADASchema" version="1.4.1">
+  <asset>
+    <created>2017-01-01T00:00:00Z</created>
+    <modified>2017-01-01T00:00:00Z</modified>
+  </asset>
+  <library_geometries>
+    <geometry id="house">
+      <mesh>
+        <source id="house-positions">
+          <float_array id="house-positions-array" count="15">0 0 0 1 0 0 1 1 0 0 1 0 0.5 2 0</float_array>
+          <technique_common>
+            <accessor source="#house-positions-array" count="5" stride="3">
+              <param name="X" type="float"/>
+              <param name="Y" type="float"/>
+              <param name="Z" type="float"/>
+            </accessor>
+          </technique_common>
+        </source>
+        <vertices id="house-vertices">
+          <input semantic="POSITION" source="#house-positions"/>
+        </vertices>
+        <polylist count="2">
+          <input semantic="VERTEX" source="#house-vertices" offset="0"/>
+          <vcount>4 3</vcount>
+          <p>0 1 2 3 3 2 4</p>
+        </polylist>
+        <polygons count="2">
+          <input semantic="VERTEX" source="#house-vertices" offset="0"/>
+          <p>0 1 2 3</p>
+          <ph>
+            <p>1 2 4</p>
+            <h>0 1 2</h>
+          </ph>
+        </polygons>
+      </mesh>
+    </geometry>
+  </library_geometries>
+  <library_visual_scenes>
+    <visual_scene id="scene">
+      <node id="a" name="house">
+        <instance_geometry url="#house"/>
+      </node>
+    </visual_scene>
+  </library_visual_scenes>
+</COLLADA>"##;
+        // Polygons are fanned from their first vertex, holes are dropped
+        let c = Collada::read(data.as_bytes()).unwrap();
+        let o = c.to_obj("house.mtl");
+        let positions = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nv 0.5 2 0\n";
+        assert_eq!(o.obj, format!("o house\n{}f 1 2 3\nf 1 3 4\nf 4 3 5\n{}f 6 7 8\nf 6 8 9\nf 7 8 10\n",
+                                  positions, positions));
+        assert_eq!(o.mtl, "");
+    }
+
+    #[test]
+    fn test_obj_round_trip() {
+        // The first export triangulates and splits vertices, after which
+        // exporting an import gives the same files
+        let c = import_obj(OBJ, Some(MTL)).unwrap();
+        let o = c.to_obj("box.mtl");
+        let c2 = Collada::from_obj(&o.obj, Some(&o.mtl)).unwrap();
+        assert!(c2.validate().is_empty(), "{:?}", c2.validate());
+        let o2 = c2.to_obj("box.mtl");
+        assert_eq!(o2.mtl, o.mtl);
+        let o3 = Collada::from_obj(&o2.obj, Some(&o2.mtl)).unwrap().to_obj("box.mtl");
+        assert_eq!(o3.obj, o2.obj);
+        assert_eq!(o3.mtl, o2.mtl);
+
+        let geometries = c2.other_libraries.iter().find(|l| l.name == "library_geometries").unwrap();
+        let names: Vec<&str> = geometries.children.iter().map(|g| g.attributes["name"].as_str()).collect();
+        assert_eq!(names, vec!["quad", "tri"]);
+        let count = |o: &str, prefix: &str| o.lines().filter(|l| l.starts_with(prefix)).count();
+        assert_eq!(count(&o2.obj, "f "), count(&o.obj, "f "));
+        assert_eq!(count(&o2.obj, "l "), count(&o.obj, "l "));
+    }
+
+}
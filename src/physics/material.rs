@@ -0,0 +1,312 @@
+use xmltree::{Element};
+use context::{ParseContext};
+use core::{Asset, Extra, HasTechniques, Technique, TechniqueCommon, UnknownContent};
+use error::{ColladaError};
+use traits::{XmlConversion};
+use utils;
+
+/// The common technique of a physics material
+#[derive(Debug)]
+pub struct PhysicsMaterialCommon {
+    pub dynamic_friction: Option<f32>,
+    pub restitution: Option<f32>,
+    pub static_friction: Option<f32>,
+}
+
+impl PhysicsMaterialCommon {
+    pub fn new() -> PhysicsMaterialCommon {
+        PhysicsMaterialCommon {
+            dynamic_friction: None,
+            restitution: None,
+            static_friction: None,
+        }
+    }
+}
+
+impl TechniqueCommon for PhysicsMaterialCommon {
+    fn parse_child(&mut self, c: &Element, _ctx: &mut ParseContext) -> Result<bool, ColladaError> {
+        match c.name.as_str() {
+            "dynamic_friction" => self.dynamic_friction = Some(try!(utils::parse_text(c))),
+            "restitution" => self.restitution = Some(try!(utils::parse_text(c))),
+            "static_friction" => self.static_friction = Some(try!(utils::parse_text(c))),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    fn encode_children(&self, tc: &mut Element) {
+        let values = [("dynamic_friction", self.dynamic_friction),
+                      ("restitution", self.restitution),
+                      ("static_friction", self.static_friction)];
+        for &(name, value) in values.iter() {
+            if let Some(v) = value {
+                tc.children.push(utils::text_element(name, v.to_string()));
+            }
+        }
+    }
+}
+
+/// Physical properties of an object's surface
+#[derive(Debug)]
+pub struct PhysicsMaterial {
+    pub id: Option<String>,
+    pub name: Option<String>,
+    pub asset: Option<Asset>,
+    pub common: PhysicsMaterialCommon,
+    pub techniques: Vec<Technique>,
+    pub extras: Vec<Extra>,
+    pub unknown: UnknownContent,
+}
+
+impl PhysicsMaterial {
+    pub fn new() -> PhysicsMaterial {
+        PhysicsMaterial {
+            id: None,
+            name: None,
+            asset: None,
+            common: PhysicsMaterialCommon::new(),
+            techniques: Vec::new(),
+            extras: Vec::new(),
+            unknown: UnknownContent::new(),
+        }
+    }
+}
+
+impl XmlConversion for PhysicsMaterial {
+    fn parse_with(&mut self, e: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        ctx.unknown_attributes(e, &["id", "name"], &mut self.unknown);
+        self.id = utils::get_attr(e, "id");
+        self.name = utils::get_attr(e, "name");
+
+        for c in &e.children {
+            match c.name.as_str() {
+                "asset" => {
+                    let mut a = Asset::new();
+                    try!(ctx.parse(&mut a, c));
+                    self.asset = Some(a);
+                },
+                "technique_common" => try!(self.common.parse_common(c, ctx)),
+                "technique" => {
+                    let mut t = Technique::new();
+                    try!(ctx.parse(&mut t, c));
+                    self.techniques.push(t);
+                },
+                "extra" => {
+                    let mut x = Extra::new();
+                    try!(ctx.parse(&mut x, c));
+                    self.extras.push(x);
+                },
+                _ => try!(ctx.unknown_child(e, c, &mut self.unknown)),
+            }
+        }
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut m = Element::new("physics_material");
+        utils::set_attr(&mut m, "id", &self.id);
+        utils::set_attr(&mut m, "name", &self.name);
+
+        match self.asset {
+            Some(ref x) => m.children.push(x.encode()),
+            None => {},
+        }
+        m.children.push(self.common.encode_common());
+        for t in &self.techniques {
+            m.children.push(t.encode());
+        }
+        for x in &self.extras {
+            m.children.push(x.encode());
+        }
+        self.unknown.encode_into(&mut m);
+        m
+    }
+}
+
+impl HasTechniques for PhysicsMaterial {
+    type Common = PhysicsMaterialCommon;
+
+    fn technique_common(&self) -> &PhysicsMaterialCommon {
+        &self.common
+    }
+
+    fn techniques(&self) -> &[Technique] {
+        &self.techniques
+    }
+}
+
+/// The physics material of a rigid body or shape, either instantiated from
+/// a library or defined inline
+#[derive(Debug)]
+pub enum PhysicsMaterialRef {
+    /// Url of an `<instance_physics_material>`
+    Instance(String),
+    Inline(PhysicsMaterial),
+}
+
+impl PhysicsMaterialRef {
+    /// Parse an `<instance_physics_material>` or `<physics_material>` element
+    pub fn parse(e: &Element, ctx: &mut ParseContext) -> Result<PhysicsMaterialRef, ColladaError> {
+        if e.name == "instance_physics_material" {
+            Ok(PhysicsMaterialRef::Instance(try!(utils::require_attr(e, "url"))))
+        } else {
+            let mut m = PhysicsMaterial::new();
+            try!(ctx.parse(&mut m, e));
+            Ok(PhysicsMaterialRef::Inline(m))
+        }
+    }
+
+    pub fn encode(&self) -> Element {
+        match *self {
+            PhysicsMaterialRef::Instance(ref url) => {
+                let mut i = Element::new("instance_physics_material");
+                i.attributes.insert("url".to_string(), url.clone());
+                i
+            },
+            PhysicsMaterialRef::Inline(ref m) => m.encode(),
+        }
+    }
+}
+
+/// Library of physics materials
+#[derive(Debug)]
+pub struct LibraryPhysicsMaterials {
+    pub id: Option<String>,
+    pub name: Option<String>,
+    pub asset: Option<Asset>,
+    pub physics_materials: Vec<PhysicsMaterial>,
+    pub extras: Vec<Extra>,
+    pub unknown: UnknownContent,
+}
+
+impl LibraryPhysicsMaterials {
+    pub fn new() -> LibraryPhysicsMaterials {
+        LibraryPhysicsMaterials {
+            id: None,
+            name: None,
+            asset: None,
+            physics_materials: Vec::new(),
+            extras: Vec::new(),
+            unknown: UnknownContent::new(),
+        }
+    }
+
+    /// Find a physics material by its id
+    pub fn get(&self, id: &str) -> Option<&PhysicsMaterial> {
+        self.physics_materials.iter().find(|m| m.id.as_ref().map(|x| x.as_str()) == Some(id))
+    }
+}
+
+impl XmlConversion for LibraryPhysicsMaterials {
+    fn parse_with(&mut self, e: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        ctx.unknown_attributes(e, &["id", "name"], &mut self.unknown);
+        self.id = utils::get_attr(e, "id");
+        self.name = utils::get_attr(e, "name");
+
+        for c in &e.children {
+            match c.name.as_str() {
+                "asset" => {
+                    let mut a = Asset::new();
+                    try!(ctx.parse(&mut a, c));
+                    self.asset = Some(a);
+                },
+                "physics_material" => {
+                    let mut m = PhysicsMaterial::new();
+                    try!(ctx.parse(&mut m, c));
+                    self.physics_materials.push(m);
+                },
+                "extra" => {
+                    let mut x = Extra::new();
+                    try!(ctx.parse(&mut x, c));
+                    self.extras.push(x);
+                },
+                _ => try!(ctx.unknown_child(e, c, &mut self.unknown)),
+            }
+        }
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut l = Element::new("library_physics_materials");
+        utils::set_attr(&mut l, "id", &self.id);
+        utils::set_attr(&mut l, "name", &self.name);
+
+        match self.asset {
+            Some(ref x) => l.children.push(x.encode()),
+            None => {},
+        }
+        for m in &self.physics_materials {
+            l.children.push(m.encode());
+        }
+        for x in &self.extras {
+            l.children.push(x.encode());
+        }
+        self.unknown.encode_into(&mut l);
+        l
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::{TechniqueRef};
+    use xmltree::{Element};
+    use traits::{XmlConversion};
+
+    #[test]
+    fn test_library_physics_materials_parse() {
+        let data = r##"
+            <library_physics_materials>
+                <physics_material id="rubber" name="Rubber">
+                    <technique_common>
+                        <dynamic_friction>0.8</dynamic_friction>
+                        <restitution>0.9</restitution>
+                        <static_friction>1.0</static_friction>
+                    </technique_common>
+                    <technique profile="PhysX"><skin_width>0.01</skin_width></technique>
+                </physics_material>
+            </library_physics_materials>"##;
+        let e = Element::parse(data.as_bytes()).unwrap();
+        let mut l = LibraryPhysicsMaterials::new();
+        match l.parse(&e) {
+            Ok(_) => assert!(true),
+            Err(_) => assert!(false),
+        }
+        let m = l.get("rubber").unwrap();
+        assert_eq!(m.name, Some("Rubber".to_string()));
+        assert_eq!(m.common.dynamic_friction, Some(0.8));
+        assert_eq!(m.common.restitution, Some(0.9));
+        assert_eq!(m.common.static_friction, Some(1.0));
+        assert_eq!(m.techniques.len(), 1);
+        match m.technique_for("PhysX") {
+            TechniqueRef::Profile(t) => assert_eq!(t.data.children[0].name, "skin_width"),
+            TechniqueRef::Common(_) => assert!(false),
+        }
+        assert_eq!(m.technique_for("Bullet").common().unwrap().restitution, Some(0.9));
+
+        let e = Element::parse(r##"<physics_material><technique_common><friction>1</friction></technique_common></physics_material>"##.as_bytes()).unwrap();
+        assert!(PhysicsMaterial::new().parse(&e).is_err());
+    }
+
+    #[test]
+    fn test_library_physics_materials_encode() {
+        let mut m = PhysicsMaterial::new();
+        m.id = Some("rubber".to_string());
+        m.common.restitution = Some(0.5);
+        let mut l = LibraryPhysicsMaterials::new();
+        l.physics_materials.push(m);
+
+        let e = l.encode();
+        assert_eq!(e.name, "library_physics_materials");
+        let m = &e.children[0];
+        assert_eq!(m.name, "physics_material");
+        assert_eq!(m.children[0].name, "technique_common");
+        assert_eq!(m.children[0].children.len(), 1);
+        assert_eq!(m.children[0].children[0].name, "restitution");
+        assert_eq!(m.children[0].children[0].text, Some("0.5".to_string()));
+
+        let r = PhysicsMaterialRef::Instance("#rubber".to_string()).encode();
+        assert_eq!(r.name, "instance_physics_material");
+        assert_eq!(r.attributes.get("url"), Some(&"#rubber".to_string()));
+    }
+}
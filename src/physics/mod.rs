@@ -0,0 +1,13 @@
+mod material;
+mod model;
+mod rigid_body;
+mod rigid_constraint;
+mod scene;
+mod shape;
+
+pub use self::material::*;
+pub use self::model::*;
+pub use self::rigid_body::*;
+pub use self::rigid_constraint::*;
+pub use self::scene::*;
+pub use self::shape::*;
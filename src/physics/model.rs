@@ -0,0 +1,428 @@
+use xmltree::{Element};
+use context::{ParseContext};
+use core::{Asset, Extra, UnknownContent};
+use error::{ColladaError};
+use physics::{InstanceRigidBody, InstanceRigidConstraint, RigidBody, RigidConstraint};
+use traits::{XmlConversion};
+use utils;
+
+/// A set of rigid bodies and the constraints between them
+#[derive(Debug)]
+pub struct PhysicsModel {
+    pub id: Option<String>,
+    pub name: Option<String>,
+    pub asset: Option<Asset>,
+    pub rigid_bodies: Vec<RigidBody>,
+    pub rigid_constraints: Vec<RigidConstraint>,
+
+    /// Nested physics models
+    pub instance_physics_models: Vec<InstancePhysicsModel>,
+    pub extras: Vec<Extra>,
+    pub unknown: UnknownContent,
+}
+
+impl PhysicsModel {
+    pub fn new() -> PhysicsModel {
+        PhysicsModel {
+            id: None,
+            name: None,
+            asset: None,
+            rigid_bodies: Vec::new(),
+            rigid_constraints: Vec::new(),
+            instance_physics_models: Vec::new(),
+            extras: Vec::new(),
+            unknown: UnknownContent::new(),
+        }
+    }
+
+    /// Find a rigid body by its sid
+    pub fn rigid_body(&self, sid: &str) -> Option<&RigidBody> {
+        self.rigid_bodies.iter().find(|b| b.sid == sid)
+    }
+
+    /// Find a rigid constraint by its sid
+    pub fn rigid_constraint(&self, sid: &str) -> Option<&RigidConstraint> {
+        self.rigid_constraints.iter().find(|r| r.sid == sid)
+    }
+}
+
+impl XmlConversion for PhysicsModel {
+    fn parse_with(&mut self, e: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        ctx.unknown_attributes(e, &["id", "name"], &mut self.unknown);
+        self.id = utils::get_attr(e, "id");
+        self.name = utils::get_attr(e, "name");
+
+        for c in &e.children {
+            match c.name.as_str() {
+                "asset" => {
+                    let mut a = Asset::new();
+                    try!(ctx.parse(&mut a, c));
+                    self.asset = Some(a);
+                },
+                "rigid_body" => {
+                    let mut b = RigidBody::new();
+                    try!(ctx.parse(&mut b, c));
+                    self.rigid_bodies.push(b);
+                },
+                "rigid_constraint" => {
+                    let mut r = RigidConstraint::new();
+                    try!(ctx.parse(&mut r, c));
+                    self.rigid_constraints.push(r);
+                },
+                "instance_physics_model" => {
+                    let mut i = InstancePhysicsModel::new();
+                    try!(ctx.parse(&mut i, c));
+                    self.instance_physics_models.push(i);
+                },
+                "extra" => {
+                    let mut x = Extra::new();
+                    try!(ctx.parse(&mut x, c));
+                    self.extras.push(x);
+                },
+                _ => try!(ctx.unknown_child(e, c, &mut self.unknown)),
+            }
+        }
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut m = Element::new("physics_model");
+        utils::set_attr(&mut m, "id", &self.id);
+        utils::set_attr(&mut m, "name", &self.name);
+
+        match self.asset {
+            Some(ref x) => m.children.push(x.encode()),
+            None => {},
+        }
+        for b in &self.rigid_bodies {
+            m.children.push(b.encode());
+        }
+        for r in &self.rigid_constraints {
+            m.children.push(r.encode());
+        }
+        for i in &self.instance_physics_models {
+            m.children.push(i.encode());
+        }
+        for x in &self.extras {
+            m.children.push(x.encode());
+        }
+        self.unknown.encode_into(&mut m);
+        m
+    }
+}
+
+/// A rigid body instance resolved against its physics model
+#[derive(Debug)]
+pub struct BodyBinding<'a> {
+    pub instance: &'a InstanceRigidBody,
+    pub body: &'a RigidBody,
+
+    /// Id of the node moved by the rigid body
+    pub target: &'a str,
+}
+
+/// Instantiates a physics model
+#[derive(Debug)]
+pub struct InstancePhysicsModel {
+    pub url: String,
+    pub sid: Option<String>,
+    pub name: Option<String>,
+
+    /// Url of the node the instance is relative to
+    pub parent: Option<String>,
+
+    /// Urls of the force fields affecting the instance
+    pub instance_force_fields: Vec<String>,
+    pub instance_rigid_bodies: Vec<InstanceRigidBody>,
+    pub instance_rigid_constraints: Vec<InstanceRigidConstraint>,
+    pub extras: Vec<Extra>,
+    pub unknown: UnknownContent,
+}
+
+impl InstancePhysicsModel {
+    pub fn new() -> InstancePhysicsModel {
+        InstancePhysicsModel {
+            url: String::from(""),
+            sid: None,
+            name: None,
+            parent: None,
+            instance_force_fields: Vec::new(),
+            instance_rigid_bodies: Vec::new(),
+            instance_rigid_constraints: Vec::new(),
+            extras: Vec::new(),
+            unknown: UnknownContent::new(),
+        }
+    }
+
+    /// Find the instantiated physics model in the libraries
+    pub fn model<'a>(&self, libraries: &'a [LibraryPhysicsModels]) -> Option<&'a PhysicsModel> {
+        let id = match utils::url_id(&self.url) {
+            Some(x) => x,
+            None => return None,
+        };
+        libraries.iter().filter_map(|l| l.get(id)).next()
+    }
+
+    /// Resolve each rigid body instance to its rigid body and target node,
+    /// instances that can't be resolved are skipped
+    pub fn body_bindings<'a>(&'a self, libraries: &'a [LibraryPhysicsModels]) -> Vec<BodyBinding<'a>> {
+        let model = match self.model(libraries) {
+            Some(x) => x,
+            None => return Vec::new(),
+        };
+        self.instance_rigid_bodies.iter().filter_map(|i| {
+            match (model.rigid_body(&i.body), i.target_node()) {
+                (Some(b), Some(t)) => Some(BodyBinding{instance: i, body: b, target: t}),
+                _ => None,
+            }
+        }).collect()
+    }
+}
+
+impl XmlConversion for InstancePhysicsModel {
+    fn parse_with(&mut self, e: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        ctx.unknown_attributes(e, &["url", "sid", "name", "parent"], &mut self.unknown);
+        self.url = try!(utils::require_attr(e, "url"));
+        self.sid = utils::get_attr(e, "sid");
+        self.name = utils::get_attr(e, "name");
+        self.parent = utils::get_attr(e, "parent");
+
+        for c in &e.children {
+            match c.name.as_str() {
+                "instance_force_field" => {
+                    self.instance_force_fields.push(try!(utils::require_attr(c, "url")));
+                },
+                "instance_rigid_body" => {
+                    let mut i = InstanceRigidBody::new();
+                    try!(ctx.parse(&mut i, c));
+                    self.instance_rigid_bodies.push(i);
+                },
+                "instance_rigid_constraint" => {
+                    let mut i = InstanceRigidConstraint::new();
+                    try!(ctx.parse(&mut i, c));
+                    self.instance_rigid_constraints.push(i);
+                },
+                "extra" => {
+                    let mut x = Extra::new();
+                    try!(ctx.parse(&mut x, c));
+                    self.extras.push(x);
+                },
+                _ => try!(ctx.unknown_child(e, c, &mut self.unknown)),
+            }
+        }
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut i = Element::new("instance_physics_model");
+        i.attributes.insert("url".to_string(), self.url.clone());
+        utils::set_attr(&mut i, "sid", &self.sid);
+        utils::set_attr(&mut i, "name", &self.name);
+        utils::set_attr(&mut i, "parent", &self.parent);
+
+        for f in &self.instance_force_fields {
+            let mut x = Element::new("instance_force_field");
+            x.attributes.insert("url".to_string(), f.clone());
+            i.children.push(x);
+        }
+        for b in &self.instance_rigid_bodies {
+            i.children.push(b.encode());
+        }
+        for r in &self.instance_rigid_constraints {
+            i.children.push(r.encode());
+        }
+        for x in &self.extras {
+            i.children.push(x.encode());
+        }
+        self.unknown.encode_into(&mut i);
+        i
+    }
+}
+
+/// Library of physics models
+#[derive(Debug)]
+pub struct LibraryPhysicsModels {
+    pub id: Option<String>,
+    pub name: Option<String>,
+    pub asset: Option<Asset>,
+    pub physics_models: Vec<PhysicsModel>,
+    pub extras: Vec<Extra>,
+    pub unknown: UnknownContent,
+}
+
+impl LibraryPhysicsModels {
+    pub fn new() -> LibraryPhysicsModels {
+        LibraryPhysicsModels {
+            id: None,
+            name: None,
+            asset: None,
+            physics_models: Vec::new(),
+            extras: Vec::new(),
+            unknown: UnknownContent::new(),
+        }
+    }
+
+    /// Find a physics model by its id
+    pub fn get(&self, id: &str) -> Option<&PhysicsModel> {
+        self.physics_models.iter().find(|m| m.id.as_ref().map(|x| x.as_str()) == Some(id))
+    }
+}
+
+impl XmlConversion for LibraryPhysicsModels {
+    fn parse_with(&mut self, e: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        ctx.unknown_attributes(e, &["id", "name"], &mut self.unknown);
+        self.id = utils::get_attr(e, "id");
+        self.name = utils::get_attr(e, "name");
+
+        for c in &e.children {
+            match c.name.as_str() {
+                "asset" => {
+                    let mut a = Asset::new();
+                    try!(ctx.parse(&mut a, c));
+                    self.asset = Some(a);
+                },
+                "physics_model" => {
+                    let mut m = PhysicsModel::new();
+                    try!(ctx.parse(&mut m, c));
+                    self.physics_models.push(m);
+                },
+                "extra" => {
+                    let mut x = Extra::new();
+                    try!(ctx.parse(&mut x, c));
+                    self.extras.push(x);
+                },
+                _ => try!(ctx.unknown_child(e, c, &mut self.unknown)),
+            }
+        }
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut l = Element::new("library_physics_models");
+        utils::set_attr(&mut l, "id", &self.id);
+        utils::set_attr(&mut l, "name", &self.name);
+
+        match self.asset {
+            Some(ref x) => l.children.push(x.encode()),
+            None => {},
+        }
+        for m in &self.physics_models {
+            l.children.push(m.encode());
+        }
+        for x in &self.extras {
+            l.children.push(x.encode());
+        }
+        self.unknown.encode_into(&mut l);
+        l
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use xmltree::{Element};
+    use traits::{XmlConversion};
+
+    #[test]
+    fn test_library_physics_models_parse() {
+        let data = r##"
+            <library_physics_models>
+                <physics_model id="door_model">
+                    <rigid_body sid="frame">
+                        <technique_common>
+                            <dynamic>false</dynamic>
+                            <shape><box><half_extents>1 2 0.1</half_extents></box></shape>
+                        </technique_common>
+                    </rigid_body>
+                    <rigid_body sid="door">
+                        <technique_common>
+                            <mass>5</mass>
+                            <shape><box><half_extents>0.9 1.9 0.05</half_extents></box></shape>
+                        </technique_common>
+                    </rigid_body>
+                    <rigid_constraint sid="hinge">
+                        <ref_attachment rigid_body="frame"/>
+                        <attachment rigid_body="door"/>
+                    </rigid_constraint>
+                </physics_model>
+            </library_physics_models>"##;
+        let e = Element::parse(data.as_bytes()).unwrap();
+        let mut l = LibraryPhysicsModels::new();
+        match l.parse(&e) {
+            Ok(_) => assert!(true),
+            Err(_) => assert!(false),
+        }
+        let m = l.get("door_model").unwrap();
+        assert_eq!(m.rigid_bodies.len(), 2);
+        assert_eq!(m.rigid_body("door").unwrap().common.mass, Some(5.0));
+        assert!(m.rigid_body("window").is_none());
+        assert_eq!(m.rigid_constraint("hinge").unwrap().attachment.rigid_body, "door");
+    }
+
+    #[test]
+    fn test_instance_physics_model_body_bindings() {
+        let data = r##"
+            <library_physics_models>
+                <physics_model id="m">
+                    <rigid_body sid="ball">
+                        <technique_common>
+                            <shape><sphere><radius>1</radius></sphere></shape>
+                        </technique_common>
+                    </rigid_body>
+                </physics_model>
+            </library_physics_models>"##;
+        let e = Element::parse(data.as_bytes()).unwrap();
+        let mut l = LibraryPhysicsModels::new();
+        l.parse(&e).unwrap();
+        let libraries = vec![l];
+
+        let data = r##"
+            <instance_physics_model url="#m" parent="#root">
+                <instance_force_field url="#wind"/>
+                <instance_rigid_body body="ball" target="#ball_node"/>
+                <instance_rigid_body body="missing" target="#other"/>
+            </instance_physics_model>"##;
+        let e = Element::parse(data.as_bytes()).unwrap();
+        let mut i = InstancePhysicsModel::new();
+        match i.parse(&e) {
+            Ok(_) => assert!(true),
+            Err(_) => assert!(false),
+        }
+        assert_eq!(i.parent, Some("#root".to_string()));
+        assert_eq!(i.instance_force_fields, vec!["#wind".to_string()]);
+
+        let bindings = i.body_bindings(&libraries);
+        assert_eq!(bindings.len(), 1);
+        assert_eq!(bindings[0].body.sid, "ball");
+        assert_eq!(bindings[0].target, "ball_node");
+
+        i.url = "#other_model".to_string();
+        assert!(i.model(&libraries).is_none());
+        assert!(i.body_bindings(&libraries).is_empty());
+
+        let e = Element::parse(r##"<instance_physics_model/>"##.as_bytes()).unwrap();
+        assert!(InstancePhysicsModel::new().parse(&e).is_err());
+    }
+
+    #[test]
+    fn test_library_physics_models_encode() {
+        let mut i = InstancePhysicsModel::new();
+        i.url = "#m".to_string();
+        i.instance_force_fields.push("#wind".to_string());
+        let mut m = PhysicsModel::new();
+        m.id = Some("outer".to_string());
+        m.instance_physics_models.push(i);
+        let mut l = LibraryPhysicsModels::new();
+        l.physics_models.push(m);
+
+        let e = l.encode();
+        assert_eq!(e.name, "library_physics_models");
+        let m = &e.children[0];
+        assert_eq!(m.name, "physics_model");
+        assert_eq!(m.attributes.get("id"), Some(&"outer".to_string()));
+        let i = &m.children[0];
+        assert_eq!(i.name, "instance_physics_model");
+        assert_eq!(i.attributes.get("url"), Some(&"#m".to_string()));
+        assert_eq!(i.children[0].name, "instance_force_field");
+    }
+}
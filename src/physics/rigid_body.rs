@@ -0,0 +1,407 @@
+use xmltree::{Element};
+use context::{ParseContext};
+use core::{Extra, HasTechniques, Technique, TechniqueCommon, Transform, TransformKind, UnknownContent};
+use error::{ColladaError};
+use physics::{PhysicsMaterialRef, Shape};
+use traits::{XmlConversion};
+use utils;
+
+/// The common technique of a rigid body, shared by `<rigid_body>` and
+/// `<instance_rigid_body>`
+#[derive(Debug)]
+pub struct RigidBodyCommon {
+    pub dynamic: Option<bool>,
+    pub mass: Option<f32>,
+
+    /// Center of mass and principal axes of inertia
+    pub mass_frame: Vec<Transform>,
+    pub inertia: Option<[f32; 3]>,
+    pub physics_material: Option<PhysicsMaterialRef>,
+    pub shapes: Vec<Shape>,
+}
+
+impl RigidBodyCommon {
+    pub fn new() -> RigidBodyCommon {
+        RigidBodyCommon {
+            dynamic: None,
+            mass: None,
+            mass_frame: Vec::new(),
+            inertia: None,
+            physics_material: None,
+            shapes: Vec::new(),
+        }
+    }
+}
+
+impl TechniqueCommon for RigidBodyCommon {
+    fn parse_child(&mut self, c: &Element, ctx: &mut ParseContext) -> Result<bool, ColladaError> {
+        match c.name.as_str() {
+            "dynamic" => self.dynamic = Some(try!(utils::parse_bool_text(c))),
+            "mass" => self.mass = Some(try!(utils::parse_text(c))),
+            "mass_frame" => {
+                for t in &c.children {
+                    match t.name.as_str() {
+                        "translate" | "rotate" => {
+                            let mut x = Transform::new(TransformKind::Translate);
+                            try!(ctx.parse(&mut x, t));
+                            self.mass_frame.push(x);
+                        },
+                        _ => return Err(ColladaError::InvalidChild{
+                            child: t.name.clone(),
+                            parent: "mass_frame".to_string(),
+                        }),
+                    }
+                }
+            },
+            "inertia" => self.inertia = Some(try!(utils::parse_float3(c))),
+            "instance_physics_material" | "physics_material" => {
+                self.physics_material = Some(try!(PhysicsMaterialRef::parse(c, ctx)));
+            },
+            "shape" => {
+                let mut s = Shape::new();
+                try!(ctx.parse(&mut s, c));
+                self.shapes.push(s);
+            },
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    fn encode_children(&self, tc: &mut Element) {
+        if let Some(d) = self.dynamic {
+            tc.children.push(utils::text_element("dynamic", d.to_string()));
+        }
+        if let Some(m) = self.mass {
+            tc.children.push(utils::text_element("mass", m.to_string()));
+        }
+        if !self.mass_frame.is_empty() {
+            let mut f = Element::new("mass_frame");
+            for t in &self.mass_frame {
+                f.children.push(t.encode());
+            }
+            tc.children.push(f);
+        }
+        if let Some(ref i) = self.inertia {
+            tc.children.push(utils::list_element("inertia", i));
+        }
+        if let Some(ref m) = self.physics_material {
+            tc.children.push(m.encode());
+        }
+        for s in &self.shapes {
+            tc.children.push(s.encode());
+        }
+    }
+}
+
+/// A rigid body of a physics model
+#[derive(Debug)]
+pub struct RigidBody {
+    pub sid: String,
+    pub name: Option<String>,
+    pub common: RigidBodyCommon,
+    pub techniques: Vec<Technique>,
+    pub extras: Vec<Extra>,
+    pub unknown: UnknownContent,
+}
+
+impl RigidBody {
+    pub fn new() -> RigidBody {
+        RigidBody {
+            sid: String::from(""),
+            name: None,
+            common: RigidBodyCommon::new(),
+            techniques: Vec::new(),
+            extras: Vec::new(),
+            unknown: UnknownContent::new(),
+        }
+    }
+}
+
+impl XmlConversion for RigidBody {
+    fn parse_with(&mut self, e: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        ctx.unknown_attributes(e, &["sid", "name"], &mut self.unknown);
+        self.sid = try!(utils::require_attr(e, "sid"));
+        self.name = utils::get_attr(e, "name");
+
+        let mut has_common = false;
+        for c in &e.children {
+            match c.name.as_str() {
+                "technique_common" => {
+                    has_common = true;
+                    try!(self.common.parse_common(c, ctx));
+                },
+                "technique" => {
+                    let mut t = Technique::new();
+                    try!(ctx.parse(&mut t, c));
+                    self.techniques.push(t);
+                },
+                "extra" => {
+                    let mut x = Extra::new();
+                    try!(ctx.parse(&mut x, c));
+                    self.extras.push(x);
+                },
+                _ => try!(ctx.unknown_child(e, c, &mut self.unknown)),
+            }
+        }
+
+        if !has_common {
+            return Err(ColladaError::MissingElement{
+                structure: "rigid_body".to_string(),
+                elem: "technique_common".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut b = Element::new("rigid_body");
+        b.attributes.insert("sid".to_string(), self.sid.clone());
+        utils::set_attr(&mut b, "name", &self.name);
+
+        b.children.push(self.common.encode_common());
+        for t in &self.techniques {
+            b.children.push(t.encode());
+        }
+        for x in &self.extras {
+            b.children.push(x.encode());
+        }
+        self.unknown.encode_into(&mut b);
+        b
+    }
+}
+
+impl HasTechniques for RigidBody {
+    type Common = RigidBodyCommon;
+
+    fn technique_common(&self) -> &RigidBodyCommon {
+        &self.common
+    }
+
+    fn techniques(&self) -> &[Technique] {
+        &self.techniques
+    }
+}
+
+/// Instantiates a rigid body of a physics model and binds it to a node of
+/// the visual scene
+#[derive(Debug)]
+pub struct InstanceRigidBody {
+    /// Sid of the rigid body within the instantiated physics model
+    pub body: String,
+    pub sid: Option<String>,
+    pub name: Option<String>,
+
+    /// Url of the node the rigid body moves
+    pub target: String,
+    pub angular_velocity: Option<[f32; 3]>,
+    pub velocity: Option<[f32; 3]>,
+
+    /// Overrides of the rigid body's common technique
+    pub common: RigidBodyCommon,
+    pub techniques: Vec<Technique>,
+    pub extras: Vec<Extra>,
+    pub unknown: UnknownContent,
+}
+
+impl InstanceRigidBody {
+    pub fn new() -> InstanceRigidBody {
+        InstanceRigidBody {
+            body: String::from(""),
+            sid: None,
+            name: None,
+            target: String::from(""),
+            angular_velocity: None,
+            velocity: None,
+            common: RigidBodyCommon::new(),
+            techniques: Vec::new(),
+            extras: Vec::new(),
+            unknown: UnknownContent::new(),
+        }
+    }
+
+    /// Id of the target node
+    pub fn target_node(&self) -> Option<&str> {
+        utils::url_id(&self.target)
+    }
+}
+
+impl XmlConversion for InstanceRigidBody {
+    fn parse_with(&mut self, e: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        ctx.unknown_attributes(e, &["body", "sid", "name", "target"], &mut self.unknown);
+        self.body = try!(utils::require_attr(e, "body"));
+        self.sid = utils::get_attr(e, "sid");
+        self.name = utils::get_attr(e, "name");
+        self.target = try!(utils::require_attr(e, "target"));
+
+        for c in &e.children {
+            match c.name.as_str() {
+                "technique_common" => {
+                    for t in &c.children {
+                        match t.name.as_str() {
+                            "angular_velocity" => self.angular_velocity = Some(try!(utils::parse_float3(t))),
+                            "velocity" => self.velocity = Some(try!(utils::parse_float3(t))),
+                            _ => if !try!(self.common.parse_child(t, ctx)) {
+                                return Err(ColladaError::InvalidChild{
+                                    child: t.name.clone(),
+                                    parent: "technique_common".to_string(),
+                                });
+                            },
+                        }
+                    }
+                },
+                "technique" => {
+                    let mut t = Technique::new();
+                    try!(ctx.parse(&mut t, c));
+                    self.techniques.push(t);
+                },
+                "extra" => {
+                    let mut x = Extra::new();
+                    try!(ctx.parse(&mut x, c));
+                    self.extras.push(x);
+                },
+                _ => try!(ctx.unknown_child(e, c, &mut self.unknown)),
+            }
+        }
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut i = Element::new("instance_rigid_body");
+        i.attributes.insert("body".to_string(), self.body.clone());
+        utils::set_attr(&mut i, "sid", &self.sid);
+        utils::set_attr(&mut i, "name", &self.name);
+        i.attributes.insert("target".to_string(), self.target.clone());
+
+        let mut tc = Element::new("technique_common");
+        if let Some(ref v) = self.angular_velocity {
+            tc.children.push(utils::list_element("angular_velocity", v));
+        }
+        if let Some(ref v) = self.velocity {
+            tc.children.push(utils::list_element("velocity", v));
+        }
+        self.common.encode_children(&mut tc);
+        i.children.push(tc);
+        for t in &self.techniques {
+            i.children.push(t.encode());
+        }
+        for x in &self.extras {
+            i.children.push(x.encode());
+        }
+        self.unknown.encode_into(&mut i);
+        i
+    }
+}
+
+impl HasTechniques for InstanceRigidBody {
+    type Common = RigidBodyCommon;
+
+    fn technique_common(&self) -> &RigidBodyCommon {
+        &self.common
+    }
+
+    fn techniques(&self) -> &[Technique] {
+        &self.techniques
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use xmltree::{Element};
+    use traits::{XmlConversion};
+
+    #[test]
+    fn test_rigid_body_parse() {
+        let data = r##"
+            <rigid_body sid="box" name="Box">
+                <technique_common>
+                    <dynamic>true</dynamic>
+                    <mass>10</mass>
+                    <mass_frame>
+                        <translate>0 0.5 0</translate>
+                        <rotate>0 1 0 0</rotate>
+                    </mass_frame>
+                    <inertia>1 1 1</inertia>
+                    <instance_physics_material url="#wood"/>
+                    <shape><box><half_extents>1 1 1</half_extents></box></shape>
+                    <shape><sphere><radius>0.5</radius></sphere></shape>
+                </technique_common>
+            </rigid_body>"##;
+        let e = Element::parse(data.as_bytes()).unwrap();
+        let mut b = RigidBody::new();
+        match b.parse(&e) {
+            Ok(_) => assert!(true),
+            Err(_) => assert!(false),
+        }
+        assert_eq!(b.sid, "box");
+        assert_eq!(b.name, Some("Box".to_string()));
+        assert_eq!(b.common.dynamic, Some(true));
+        assert_eq!(b.common.mass, Some(10.0));
+        assert_eq!(b.common.mass_frame.len(), 2);
+        assert_eq!(b.common.inertia, Some([1.0, 1.0, 1.0]));
+        assert!(b.common.physics_material.is_some());
+        assert_eq!(b.common.shapes.len(), 2);
+
+        let e = Element::parse(r##"<rigid_body sid="a"/>"##.as_bytes()).unwrap();
+        assert!(RigidBody::new().parse(&e).is_err());
+        let e = Element::parse(r##"<rigid_body><technique_common/></rigid_body>"##.as_bytes()).unwrap();
+        assert!(RigidBody::new().parse(&e).is_err());
+        let e = Element::parse(r##"<rigid_body sid="a"><technique_common><velocity>0 0 0</velocity></technique_common></rigid_body>"##.as_bytes()).unwrap();
+        assert!(RigidBody::new().parse(&e).is_err());
+    }
+
+    #[test]
+    fn test_instance_rigid_body_parse() {
+        let data = r##"
+            <instance_rigid_body body="box" target="#box_node">
+                <technique_common>
+                    <angular_velocity>0 1 0</angular_velocity>
+                    <velocity>0 0 -2</velocity>
+                    <mass>20</mass>
+                </technique_common>
+            </instance_rigid_body>"##;
+        let e = Element::parse(data.as_bytes()).unwrap();
+        let mut i = InstanceRigidBody::new();
+        match i.parse(&e) {
+            Ok(_) => assert!(true),
+            Err(_) => assert!(false),
+        }
+        assert_eq!(i.body, "box");
+        assert_eq!(i.target_node(), Some("box_node"));
+        assert_eq!(i.angular_velocity, Some([0.0, 1.0, 0.0]));
+        assert_eq!(i.velocity, Some([0.0, 0.0, -2.0]));
+        assert_eq!(i.common.mass, Some(20.0));
+
+        let e = Element::parse(r##"<instance_rigid_body body="box"/>"##.as_bytes()).unwrap();
+        assert!(InstanceRigidBody::new().parse(&e).is_err());
+    }
+
+    #[test]
+    fn test_rigid_body_encode() {
+        let mut b = RigidBody::new();
+        b.sid = "box".to_string();
+        b.common.mass = Some(2.0);
+        b.common.mass_frame.push(Transform::translate(0.0, 1.0, 0.0));
+        let e = b.encode();
+        assert_eq!(e.name, "rigid_body");
+        assert_eq!(e.attributes.get("sid"), Some(&"box".to_string()));
+        let tc = &e.children[0];
+        assert_eq!(tc.name, "technique_common");
+        assert_eq!(tc.children[0].name, "mass");
+        assert_eq!(tc.children[1].name, "mass_frame");
+        assert_eq!(tc.children[1].children[0].name, "translate");
+
+        let mut i = InstanceRigidBody::new();
+        i.body = "box".to_string();
+        i.target = "#n".to_string();
+        i.velocity = Some([1.0, 0.0, 0.0]);
+        i.common.dynamic = Some(false);
+        let e = i.encode();
+        assert_eq!(e.attributes.get("target"), Some(&"#n".to_string()));
+        let tc = &e.children[0];
+        assert_eq!(tc.children[0].name, "velocity");
+        assert_eq!(tc.children[0].text, Some("1 0 0".to_string()));
+        assert_eq!(tc.children[1].name, "dynamic");
+        assert_eq!(tc.children[1].text, Some("false".to_string()));
+    }
+}
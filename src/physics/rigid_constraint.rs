@@ -0,0 +1,479 @@
+use xmltree::{Element};
+use context::{ParseContext};
+use core::{Extra, HasTechniques, Technique, TechniqueCommon, Transform, TransformKind, UnknownContent};
+use error::{ColladaError};
+use traits::{XmlConversion};
+use utils;
+
+/// Frame of a rigid body, or node, that a constraint is attached to
+#[derive(Debug)]
+pub struct Attachment {
+    /// Sid of a rigid body or url of a node
+    pub rigid_body: String,
+    pub transforms: Vec<Transform>,
+    pub extras: Vec<Extra>,
+    pub unknown: UnknownContent,
+}
+
+impl Attachment {
+    pub fn new() -> Attachment {
+        Attachment {
+            rigid_body: String::from(""),
+            transforms: Vec::new(),
+            extras: Vec::new(),
+            unknown: UnknownContent::new(),
+        }
+    }
+
+    fn encode_as(&self, name: &str) -> Element {
+        let mut a = Element::new(name);
+        a.attributes.insert("rigid_body".to_string(), self.rigid_body.clone());
+        for t in &self.transforms {
+            a.children.push(t.encode());
+        }
+        for x in &self.extras {
+            a.children.push(x.encode());
+        }
+        self.unknown.encode_into(&mut a);
+        a
+    }
+}
+
+impl XmlConversion for Attachment {
+    fn parse_with(&mut self, e: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        ctx.unknown_attributes(e, &["rigid_body"], &mut self.unknown);
+        self.rigid_body = try!(utils::require_attr(e, "rigid_body"));
+
+        for c in &e.children {
+            match c.name.as_str() {
+                "translate" | "rotate" => {
+                    let mut t = Transform::new(TransformKind::Translate);
+                    try!(ctx.parse(&mut t, c));
+                    self.transforms.push(t);
+                },
+                "extra" => {
+                    let mut x = Extra::new();
+                    try!(ctx.parse(&mut x, c));
+                    self.extras.push(x);
+                },
+                _ => try!(ctx.unknown_child(e, c, &mut self.unknown)),
+            }
+        }
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        self.encode_as("attachment")
+    }
+}
+
+/// Minimum and maximum of a constraint's degrees of freedom
+#[derive(Clone, Debug, PartialEq)]
+pub struct Limit {
+    pub min: [f32; 3],
+    pub max: [f32; 3],
+}
+
+impl Limit {
+    pub fn new() -> Limit {
+        Limit {
+            min: [0.0, 0.0, 0.0],
+            max: [0.0, 0.0, 0.0],
+        }
+    }
+
+    fn parse(e: &Element) -> Result<Limit, ColladaError> {
+        let mut l = Limit::new();
+        for c in &e.children {
+            match c.name.as_str() {
+                "min" => l.min = try!(utils::parse_float3(c)),
+                "max" => l.max = try!(utils::parse_float3(c)),
+                _ => return Err(ColladaError::InvalidChild{
+                    child: c.name.clone(),
+                    parent: e.name.clone(),
+                }),
+            }
+        }
+        Ok(l)
+    }
+
+    fn encode(&self, name: &str) -> Element {
+        let mut l = Element::new(name);
+        l.children.push(utils::list_element("min", &self.min));
+        l.children.push(utils::list_element("max", &self.max));
+        l
+    }
+}
+
+/// A spring of a constraint
+#[derive(Clone, Debug, PartialEq)]
+pub struct Spring {
+    pub stiffness: f32,
+    pub damping: f32,
+    pub target_value: f32,
+}
+
+impl Spring {
+    pub fn new() -> Spring {
+        Spring {
+            stiffness: 1.0,
+            damping: 0.0,
+            target_value: 0.0,
+        }
+    }
+
+    fn parse(e: &Element) -> Result<Spring, ColladaError> {
+        let mut s = Spring::new();
+        for c in &e.children {
+            match c.name.as_str() {
+                "stiffness" => s.stiffness = try!(utils::parse_text(c)),
+                "damping" => s.damping = try!(utils::parse_text(c)),
+                "target_value" => s.target_value = try!(utils::parse_text(c)),
+                _ => return Err(ColladaError::InvalidChild{
+                    child: c.name.clone(),
+                    parent: e.name.clone(),
+                }),
+            }
+        }
+        Ok(s)
+    }
+
+    fn encode(&self, name: &str) -> Element {
+        let mut s = Element::new(name);
+        s.children.push(utils::text_element("stiffness", self.stiffness.to_string()));
+        s.children.push(utils::text_element("damping", self.damping.to_string()));
+        s.children.push(utils::text_element("target_value", self.target_value.to_string()));
+        s
+    }
+}
+
+/// The common technique of a rigid constraint
+#[derive(Debug)]
+pub struct RigidConstraintCommon {
+    pub enabled: Option<bool>,
+    pub interpenetrate: Option<bool>,
+
+    /// Angular limits in degrees
+    pub swing_cone_and_twist: Option<Limit>,
+    pub linear_limit: Option<Limit>,
+    pub angular_spring: Option<Spring>,
+    pub linear_spring: Option<Spring>,
+}
+
+impl RigidConstraintCommon {
+    pub fn new() -> RigidConstraintCommon {
+        RigidConstraintCommon {
+            enabled: None,
+            interpenetrate: None,
+            swing_cone_and_twist: None,
+            linear_limit: None,
+            angular_spring: None,
+            linear_spring: None,
+        }
+    }
+}
+
+impl TechniqueCommon for RigidConstraintCommon {
+    fn parse_child(&mut self, c: &Element, _ctx: &mut ParseContext) -> Result<bool, ColladaError> {
+        match c.name.as_str() {
+            "enabled" => self.enabled = Some(try!(utils::parse_bool_text(c))),
+            "interpenetrate" => self.interpenetrate = Some(try!(utils::parse_bool_text(c))),
+            "limits" => for l in &c.children {
+                match l.name.as_str() {
+                    "swing_cone_and_twist" => self.swing_cone_and_twist = Some(try!(Limit::parse(l))),
+                    "linear" => self.linear_limit = Some(try!(Limit::parse(l))),
+                    _ => return Err(ColladaError::InvalidChild{
+                        child: l.name.clone(),
+                        parent: "limits".to_string(),
+                    }),
+                }
+            },
+            "spring" => for s in &c.children {
+                match s.name.as_str() {
+                    "angular" => self.angular_spring = Some(try!(Spring::parse(s))),
+                    "linear" => self.linear_spring = Some(try!(Spring::parse(s))),
+                    _ => return Err(ColladaError::InvalidChild{
+                        child: s.name.clone(),
+                        parent: "spring".to_string(),
+                    }),
+                }
+            },
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    fn encode_children(&self, tc: &mut Element) {
+        if let Some(x) = self.enabled {
+            tc.children.push(utils::text_element("enabled", x.to_string()));
+        }
+        if let Some(x) = self.interpenetrate {
+            tc.children.push(utils::text_element("interpenetrate", x.to_string()));
+        }
+        if self.swing_cone_and_twist.is_some() || self.linear_limit.is_some() {
+            let mut l = Element::new("limits");
+            if let Some(ref x) = self.swing_cone_and_twist {
+                l.children.push(x.encode("swing_cone_and_twist"));
+            }
+            if let Some(ref x) = self.linear_limit {
+                l.children.push(x.encode("linear"));
+            }
+            tc.children.push(l);
+        }
+        if self.angular_spring.is_some() || self.linear_spring.is_some() {
+            let mut s = Element::new("spring");
+            if let Some(ref x) = self.angular_spring {
+                s.children.push(x.encode("angular"));
+            }
+            if let Some(ref x) = self.linear_spring {
+                s.children.push(x.encode("linear"));
+            }
+            tc.children.push(s);
+        }
+    }
+}
+
+/// Connects two rigid bodies, or a rigid body and a node, and limits their
+/// relative motion
+#[derive(Debug)]
+pub struct RigidConstraint {
+    pub sid: String,
+    pub name: Option<String>,
+    pub ref_attachment: Attachment,
+    pub attachment: Attachment,
+    pub common: RigidConstraintCommon,
+    pub techniques: Vec<Technique>,
+    pub extras: Vec<Extra>,
+    pub unknown: UnknownContent,
+}
+
+impl RigidConstraint {
+    pub fn new() -> RigidConstraint {
+        RigidConstraint {
+            sid: String::from(""),
+            name: None,
+            ref_attachment: Attachment::new(),
+            attachment: Attachment::new(),
+            common: RigidConstraintCommon::new(),
+            techniques: Vec::new(),
+            extras: Vec::new(),
+            unknown: UnknownContent::new(),
+        }
+    }
+}
+
+impl XmlConversion for RigidConstraint {
+    fn parse_with(&mut self, e: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        ctx.unknown_attributes(e, &["sid", "name"], &mut self.unknown);
+        self.sid = try!(utils::require_attr(e, "sid"));
+        self.name = utils::get_attr(e, "name");
+
+        let mut attachments = (false, false);
+        for c in &e.children {
+            match c.name.as_str() {
+                "ref_attachment" => {
+                    try!(ctx.parse(&mut self.ref_attachment, c));
+                    attachments.0 = true;
+                },
+                "attachment" => {
+                    try!(ctx.parse(&mut self.attachment, c));
+                    attachments.1 = true;
+                },
+                "technique_common" => try!(self.common.parse_common(c, ctx)),
+                "technique" => {
+                    let mut t = Technique::new();
+                    try!(ctx.parse(&mut t, c));
+                    self.techniques.push(t);
+                },
+                "extra" => {
+                    let mut x = Extra::new();
+                    try!(ctx.parse(&mut x, c));
+                    self.extras.push(x);
+                },
+                _ => try!(ctx.unknown_child(e, c, &mut self.unknown)),
+            }
+        }
+
+        if !attachments.0 || !attachments.1 {
+            return Err(ColladaError::MissingElement{
+                structure: "rigid_constraint".to_string(),
+                elem: if attachments.0 { "attachment" } else { "ref_attachment" }.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut r = Element::new("rigid_constraint");
+        r.attributes.insert("sid".to_string(), self.sid.clone());
+        utils::set_attr(&mut r, "name", &self.name);
+
+        r.children.push(self.ref_attachment.encode_as("ref_attachment"));
+        r.children.push(self.attachment.encode());
+
+        r.children.push(self.common.encode_common());
+        for t in &self.techniques {
+            r.children.push(t.encode());
+        }
+        for x in &self.extras {
+            r.children.push(x.encode());
+        }
+        self.unknown.encode_into(&mut r);
+        r
+    }
+}
+
+impl HasTechniques for RigidConstraint {
+    type Common = RigidConstraintCommon;
+
+    fn technique_common(&self) -> &RigidConstraintCommon {
+        &self.common
+    }
+
+    fn techniques(&self) -> &[Technique] {
+        &self.techniques
+    }
+}
+
+/// Instantiates a rigid constraint of a physics model
+#[derive(Debug)]
+pub struct InstanceRigidConstraint {
+    /// Sid of the rigid constraint within the instantiated physics model
+    pub constraint: String,
+    pub sid: Option<String>,
+    pub name: Option<String>,
+    pub extras: Vec<Extra>,
+    pub unknown: UnknownContent,
+}
+
+impl InstanceRigidConstraint {
+    pub fn new() -> InstanceRigidConstraint {
+        InstanceRigidConstraint {
+            constraint: String::from(""),
+            sid: None,
+            name: None,
+            extras: Vec::new(),
+            unknown: UnknownContent::new(),
+        }
+    }
+}
+
+impl XmlConversion for InstanceRigidConstraint {
+    fn parse_with(&mut self, e: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        ctx.unknown_attributes(e, &["constraint", "sid", "name"], &mut self.unknown);
+        self.constraint = try!(utils::require_attr(e, "constraint"));
+        self.sid = utils::get_attr(e, "sid");
+        self.name = utils::get_attr(e, "name");
+
+        for c in &e.children {
+            match c.name.as_str() {
+                "extra" => {
+                    let mut x = Extra::new();
+                    try!(ctx.parse(&mut x, c));
+                    self.extras.push(x);
+                },
+                _ => try!(ctx.unknown_child(e, c, &mut self.unknown)),
+            }
+        }
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut i = Element::new("instance_rigid_constraint");
+        i.attributes.insert("constraint".to_string(), self.constraint.clone());
+        utils::set_attr(&mut i, "sid", &self.sid);
+        utils::set_attr(&mut i, "name", &self.name);
+        for x in &self.extras {
+            i.children.push(x.encode());
+        }
+        self.unknown.encode_into(&mut i);
+        i
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use xmltree::{Element};
+    use traits::{XmlConversion};
+
+    #[test]
+    fn test_rigid_constraint_parse() {
+        let data = r##"
+            <rigid_constraint sid="hinge" name="Hinge">
+                <ref_attachment rigid_body="frame">
+                    <translate>0 1 0</translate>
+                </ref_attachment>
+                <attachment rigid_body="door">
+                    <translate>0 1 0</translate>
+                    <rotate>1 0 0 0</rotate>
+                </attachment>
+                <technique_common>
+                    <enabled>true</enabled>
+                    <interpenetrate>false</interpenetrate>
+                    <limits>
+                        <swing_cone_and_twist>
+                            <min>0 -90 0</min>
+                            <max>0 90 0</max>
+                        </swing_cone_and_twist>
+                        <linear><min>0 0 0</min><max>0 0 0</max></linear>
+                    </limits>
+                    <spring>
+                        <angular>
+                            <stiffness>10</stiffness>
+                            <damping>0.5</damping>
+                            <target_value>0</target_value>
+                        </angular>
+                    </spring>
+                </technique_common>
+            </rigid_constraint>"##;
+        let e = Element::parse(data.as_bytes()).unwrap();
+        let mut r = RigidConstraint::new();
+        match r.parse(&e) {
+            Ok(_) => assert!(true),
+            Err(_) => assert!(false),
+        }
+        assert_eq!(r.sid, "hinge");
+        assert_eq!(r.ref_attachment.rigid_body, "frame");
+        assert_eq!(r.attachment.rigid_body, "door");
+        assert_eq!(r.attachment.transforms.len(), 2);
+        assert_eq!(r.common.enabled, Some(true));
+        assert_eq!(r.common.interpenetrate, Some(false));
+        let l = r.common.swing_cone_and_twist.unwrap();
+        assert_eq!(l.min, [0.0, -90.0, 0.0]);
+        assert_eq!(l.max, [0.0, 90.0, 0.0]);
+        assert!(r.common.linear_limit.is_some());
+        assert_eq!(r.common.angular_spring, Some(Spring{stiffness: 10.0, damping: 0.5, target_value: 0.0}));
+        assert!(r.common.linear_spring.is_none());
+
+        let e = Element::parse(r##"<rigid_constraint sid="a"><attachment rigid_body="b"/></rigid_constraint>"##.as_bytes()).unwrap();
+        assert!(RigidConstraint::new().parse(&e).is_err());
+        let e = Element::parse(r##"<rigid_constraint sid="a"><ref_attachment/><attachment rigid_body="b"/></rigid_constraint>"##.as_bytes()).unwrap();
+        assert!(RigidConstraint::new().parse(&e).is_err());
+    }
+
+    #[test]
+    fn test_rigid_constraint_encode() {
+        let mut r = RigidConstraint::new();
+        r.sid = "hinge".to_string();
+        r.ref_attachment.rigid_body = "frame".to_string();
+        r.attachment.rigid_body = "door".to_string();
+        r.common.linear_spring = Some(Spring::new());
+
+        let e = r.encode();
+        assert_eq!(e.name, "rigid_constraint");
+        let names: Vec<&str> = e.children.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["ref_attachment", "attachment", "technique_common"]);
+        assert_eq!(e.children[0].attributes.get("rigid_body"), Some(&"frame".to_string()));
+        let s = &e.children[2].children[0];
+        assert_eq!(s.name, "spring");
+        assert_eq!(s.children[0].name, "linear");
+        assert_eq!(s.children[0].children[0].text, Some("1".to_string()));
+
+        let mut i = InstanceRigidConstraint::new();
+        i.constraint = "hinge".to_string();
+        let e = i.encode();
+        assert_eq!(e.name, "instance_rigid_constraint");
+        assert_eq!(e.attributes.get("constraint"), Some(&"hinge".to_string()));
+    }
+}
@@ -0,0 +1,316 @@
+use xmltree::{Element};
+use context::{ParseContext};
+use core::{Asset, Extra, HasTechniques, Technique, TechniqueCommon, UnknownContent};
+use error::{ColladaError};
+use physics::{InstancePhysicsModel, LibraryPhysicsModels, BodyBinding};
+use traits::{XmlConversion};
+use utils;
+
+/// The common technique of a physics scene
+#[derive(Debug)]
+pub struct PhysicsSceneCommon {
+    pub gravity: Option<[f32; 3]>,
+
+    /// Integration time step in seconds
+    pub time_step: Option<f32>,
+}
+
+impl PhysicsSceneCommon {
+    pub fn new() -> PhysicsSceneCommon {
+        PhysicsSceneCommon {
+            gravity: None,
+            time_step: None,
+        }
+    }
+}
+
+impl TechniqueCommon for PhysicsSceneCommon {
+    fn parse_child(&mut self, c: &Element, _ctx: &mut ParseContext) -> Result<bool, ColladaError> {
+        match c.name.as_str() {
+            "gravity" => self.gravity = Some(try!(utils::parse_float3(c))),
+            "time_step" => self.time_step = Some(try!(utils::parse_text(c))),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    fn encode_children(&self, tc: &mut Element) {
+        if let Some(ref g) = self.gravity {
+            tc.children.push(utils::list_element("gravity", g));
+        }
+        if let Some(t) = self.time_step {
+            tc.children.push(utils::text_element("time_step", t.to_string()));
+        }
+    }
+}
+
+/// An environment in which physical objects are simulated
+#[derive(Debug)]
+pub struct PhysicsScene {
+    pub id: Option<String>,
+    pub name: Option<String>,
+    pub asset: Option<Asset>,
+
+    /// Urls of the force fields affecting the whole scene
+    pub instance_force_fields: Vec<String>,
+    pub instance_physics_models: Vec<InstancePhysicsModel>,
+    pub common: PhysicsSceneCommon,
+    pub techniques: Vec<Technique>,
+    pub extras: Vec<Extra>,
+    pub unknown: UnknownContent,
+}
+
+impl PhysicsScene {
+    pub fn new() -> PhysicsScene {
+        PhysicsScene {
+            id: None,
+            name: None,
+            asset: None,
+            instance_force_fields: Vec::new(),
+            instance_physics_models: Vec::new(),
+            common: PhysicsSceneCommon::new(),
+            techniques: Vec::new(),
+            extras: Vec::new(),
+            unknown: UnknownContent::new(),
+        }
+    }
+
+    /// Resolve the rigid body instances of all physics models in the scene
+    pub fn body_bindings<'a>(&'a self, libraries: &'a [LibraryPhysicsModels]) -> Vec<BodyBinding<'a>> {
+        let mut bindings = Vec::new();
+        for i in &self.instance_physics_models {
+            bindings.extend(i.body_bindings(libraries));
+        }
+        bindings
+    }
+}
+
+impl XmlConversion for PhysicsScene {
+    fn parse_with(&mut self, e: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        ctx.unknown_attributes(e, &["id", "name"], &mut self.unknown);
+        self.id = utils::get_attr(e, "id");
+        self.name = utils::get_attr(e, "name");
+
+        for c in &e.children {
+            match c.name.as_str() {
+                "asset" => {
+                    let mut a = Asset::new();
+                    try!(ctx.parse(&mut a, c));
+                    self.asset = Some(a);
+                },
+                "instance_force_field" => {
+                    self.instance_force_fields.push(try!(utils::require_attr(c, "url")));
+                },
+                "instance_physics_model" => {
+                    let mut i = InstancePhysicsModel::new();
+                    try!(ctx.parse(&mut i, c));
+                    self.instance_physics_models.push(i);
+                },
+                "technique_common" => try!(self.common.parse_common(c, ctx)),
+                "technique" => {
+                    let mut t = Technique::new();
+                    try!(ctx.parse(&mut t, c));
+                    self.techniques.push(t);
+                },
+                "extra" => {
+                    let mut x = Extra::new();
+                    try!(ctx.parse(&mut x, c));
+                    self.extras.push(x);
+                },
+                _ => try!(ctx.unknown_child(e, c, &mut self.unknown)),
+            }
+        }
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut s = Element::new("physics_scene");
+        utils::set_attr(&mut s, "id", &self.id);
+        utils::set_attr(&mut s, "name", &self.name);
+
+        match self.asset {
+            Some(ref x) => s.children.push(x.encode()),
+            None => {},
+        }
+        for f in &self.instance_force_fields {
+            let mut x = Element::new("instance_force_field");
+            x.attributes.insert("url".to_string(), f.clone());
+            s.children.push(x);
+        }
+        for i in &self.instance_physics_models {
+            s.children.push(i.encode());
+        }
+        s.children.push(self.common.encode_common());
+        for t in &self.techniques {
+            s.children.push(t.encode());
+        }
+        for x in &self.extras {
+            s.children.push(x.encode());
+        }
+        self.unknown.encode_into(&mut s);
+        s
+    }
+}
+
+impl HasTechniques for PhysicsScene {
+    type Common = PhysicsSceneCommon;
+
+    fn technique_common(&self) -> &PhysicsSceneCommon {
+        &self.common
+    }
+
+    fn techniques(&self) -> &[Technique] {
+        &self.techniques
+    }
+}
+
+/// Library of physics scenes
+#[derive(Debug)]
+pub struct LibraryPhysicsScenes {
+    pub id: Option<String>,
+    pub name: Option<String>,
+    pub asset: Option<Asset>,
+    pub physics_scenes: Vec<PhysicsScene>,
+    pub extras: Vec<Extra>,
+    pub unknown: UnknownContent,
+}
+
+impl LibraryPhysicsScenes {
+    pub fn new() -> LibraryPhysicsScenes {
+        LibraryPhysicsScenes {
+            id: None,
+            name: None,
+            asset: None,
+            physics_scenes: Vec::new(),
+            extras: Vec::new(),
+            unknown: UnknownContent::new(),
+        }
+    }
+
+    /// Find a physics scene by its id
+    pub fn get(&self, id: &str) -> Option<&PhysicsScene> {
+        self.physics_scenes.iter().find(|s| s.id.as_ref().map(|x| x.as_str()) == Some(id))
+    }
+}
+
+impl XmlConversion for LibraryPhysicsScenes {
+    fn parse_with(&mut self, e: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        ctx.unknown_attributes(e, &["id", "name"], &mut self.unknown);
+        self.id = utils::get_attr(e, "id");
+        self.name = utils::get_attr(e, "name");
+
+        for c in &e.children {
+            match c.name.as_str() {
+                "asset" => {
+                    let mut a = Asset::new();
+                    try!(ctx.parse(&mut a, c));
+                    self.asset = Some(a);
+                },
+                "physics_scene" => {
+                    let mut s = PhysicsScene::new();
+                    try!(ctx.parse(&mut s, c));
+                    self.physics_scenes.push(s);
+                },
+                "extra" => {
+                    let mut x = Extra::new();
+                    try!(ctx.parse(&mut x, c));
+                    self.extras.push(x);
+                },
+                _ => try!(ctx.unknown_child(e, c, &mut self.unknown)),
+            }
+        }
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut l = Element::new("library_physics_scenes");
+        utils::set_attr(&mut l, "id", &self.id);
+        utils::set_attr(&mut l, "name", &self.name);
+
+        match self.asset {
+            Some(ref x) => l.children.push(x.encode()),
+            None => {},
+        }
+        for s in &self.physics_scenes {
+            l.children.push(s.encode());
+        }
+        for x in &self.extras {
+            l.children.push(x.encode());
+        }
+        self.unknown.encode_into(&mut l);
+        l
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use xmltree::{Element};
+    use physics::{LibraryPhysicsModels};
+    use traits::{XmlConversion};
+
+    #[test]
+    fn test_library_physics_scenes_parse() {
+        let data = r##"
+            <library_physics_scenes>
+                <physics_scene id="world">
+                    <instance_force_field url="#wind"/>
+                    <instance_physics_model url="#m">
+                        <instance_rigid_body body="ball" target="#ball_node"/>
+                    </instance_physics_model>
+                    <technique_common>
+                        <gravity>0 -9.8 0</gravity>
+                        <time_step>0.0166</time_step>
+                    </technique_common>
+                </physics_scene>
+            </library_physics_scenes>"##;
+        let e = Element::parse(data.as_bytes()).unwrap();
+        let mut l = LibraryPhysicsScenes::new();
+        match l.parse(&e) {
+            Ok(_) => assert!(true),
+            Err(_) => assert!(false),
+        }
+        let s = l.get("world").unwrap();
+        assert_eq!(s.instance_force_fields.len(), 1);
+        assert_eq!(s.instance_physics_models.len(), 1);
+        assert_eq!(s.common.gravity, Some([0.0, -9.8, 0.0]));
+        assert_eq!(s.common.time_step, Some(0.0166));
+
+        let data = r##"
+            <library_physics_models>
+                <physics_model id="m">
+                    <rigid_body sid="ball">
+                        <technique_common><shape><sphere><radius>1</radius></sphere></shape></technique_common>
+                    </rigid_body>
+                </physics_model>
+            </library_physics_models>"##;
+        let e = Element::parse(data.as_bytes()).unwrap();
+        let mut m = LibraryPhysicsModels::new();
+        m.parse(&e).unwrap();
+        let models = vec![m];
+        let bindings = s.body_bindings(&models);
+        assert_eq!(bindings.len(), 1);
+        assert_eq!(bindings[0].target, "ball_node");
+
+        let e = Element::parse(r##"<physics_scene><technique_common><wind>1</wind></technique_common></physics_scene>"##.as_bytes()).unwrap();
+        assert!(PhysicsScene::new().parse(&e).is_err());
+    }
+
+    #[test]
+    fn test_library_physics_scenes_encode() {
+        let mut s = PhysicsScene::new();
+        s.id = Some("world".to_string());
+        s.common.gravity = Some([0.0, 0.0, -9.8]);
+        let mut l = LibraryPhysicsScenes::new();
+        l.physics_scenes.push(s);
+
+        let e = l.encode();
+        assert_eq!(e.name, "library_physics_scenes");
+        let s = &e.children[0];
+        assert_eq!(s.name, "physics_scene");
+        let tc = &s.children[0];
+        assert_eq!(tc.name, "technique_common");
+        assert_eq!(tc.children[0].name, "gravity");
+        assert_eq!(tc.children[0].text, Some("0 0 -9.8".to_string()));
+    }
+}
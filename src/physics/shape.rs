@@ -0,0 +1,314 @@
+use xmltree::{Element};
+use context::{ParseContext};
+use core::{Extra, InstanceGeometry, Transform, TransformKind, UnknownContent};
+use error::{ColladaError};
+use physics::{PhysicsMaterialRef};
+use traits::{XmlConversion};
+use utils;
+
+/// The geometry of a collision shape
+#[derive(Debug)]
+pub enum ShapeGeometry {
+    /// A geometry used as the (convex) hull of the shape
+    InstanceGeometry(InstanceGeometry),
+
+    /// Infinite plane `ax + by + cz + d = 0`
+    Plane{equation: [f32; 4]},
+    Box{half_extents: [f32; 3]},
+    Sphere{radius: f32},
+
+    /// Cylinder along the Y axis with elliptical radii
+    Cylinder{height: f32, radius: Vec<f32>},
+    TaperedCylinder{height: f32, radius1: Vec<f32>, radius2: Vec<f32>},
+
+    /// Capsule along the Y axis with elliptical radii
+    Capsule{height: f32, radius: Vec<f32>},
+    TaperedCapsule{height: f32, radius1: Vec<f32>, radius2: Vec<f32>},
+}
+
+impl ShapeGeometry {
+    /// Whether an element is a shape geometry
+    pub fn is_geometry(name: &str) -> bool {
+        match name {
+            "instance_geometry" | "plane" | "box" | "sphere" | "cylinder" |
+            "tapered_cylinder" | "capsule" | "tapered_capsule" => true,
+            _ => false,
+        }
+    }
+
+    /// Parse a shape geometry element, e.g. `<box>`
+    pub fn parse(e: &Element, ctx: &mut ParseContext) -> Result<ShapeGeometry, ColladaError> {
+        if e.name == "instance_geometry" {
+            let mut i = InstanceGeometry::new();
+            try!(ctx.parse(&mut i, e));
+            return Ok(ShapeGeometry::InstanceGeometry(i));
+        }
+
+        let child = |name: &str| -> Result<&Element, ColladaError> {
+            match e.get_child(name) {
+                Some(c) => Ok(c),
+                None => Err(ColladaError::MissingElement{
+                    structure: e.name.clone(),
+                    elem: name.to_string(),
+                }),
+            }
+        };
+
+        Ok(match e.name.as_str() {
+            "plane" => ShapeGeometry::Plane{
+                equation: try!(utils::parse_float4(try!(child("equation")))),
+            },
+            "box" => ShapeGeometry::Box{
+                half_extents: try!(utils::parse_float3(try!(child("half_extents")))),
+            },
+            "sphere" => ShapeGeometry::Sphere{
+                radius: try!(utils::parse_text(try!(child("radius")))),
+            },
+            "cylinder" => ShapeGeometry::Cylinder{
+                height: try!(utils::parse_text(try!(child("height")))),
+                radius: try!(utils::parse_list(try!(child("radius")))),
+            },
+            "tapered_cylinder" => ShapeGeometry::TaperedCylinder{
+                height: try!(utils::parse_text(try!(child("height")))),
+                radius1: try!(utils::parse_list(try!(child("radius1")))),
+                radius2: try!(utils::parse_list(try!(child("radius2")))),
+            },
+            "capsule" => ShapeGeometry::Capsule{
+                height: try!(utils::parse_text(try!(child("height")))),
+                radius: try!(utils::parse_list(try!(child("radius")))),
+            },
+            "tapered_capsule" => ShapeGeometry::TaperedCapsule{
+                height: try!(utils::parse_text(try!(child("height")))),
+                radius1: try!(utils::parse_list(try!(child("radius1")))),
+                radius2: try!(utils::parse_list(try!(child("radius2")))),
+            },
+            _ => return Err(ColladaError::InvalidChild{
+                child: e.name.clone(),
+                parent: "shape".to_string(),
+            }),
+        })
+    }
+
+    pub fn encode(&self) -> Element {
+        match *self {
+            ShapeGeometry::InstanceGeometry(ref i) => i.encode(),
+            ShapeGeometry::Plane{ref equation} => {
+                let mut p = Element::new("plane");
+                p.children.push(utils::list_element("equation", equation));
+                p
+            },
+            ShapeGeometry::Box{ref half_extents} => {
+                let mut b = Element::new("box");
+                b.children.push(utils::list_element("half_extents", half_extents));
+                b
+            },
+            ShapeGeometry::Sphere{radius} => {
+                let mut s = Element::new("sphere");
+                s.children.push(utils::text_element("radius", radius.to_string()));
+                s
+            },
+            ShapeGeometry::Cylinder{height, ref radius} |
+            ShapeGeometry::Capsule{height, ref radius} => {
+                let name = match *self {
+                    ShapeGeometry::Cylinder{..} => "cylinder",
+                    _ => "capsule",
+                };
+                let mut c = Element::new(name);
+                c.children.push(utils::text_element("height", height.to_string()));
+                c.children.push(utils::list_element("radius", radius));
+                c
+            },
+            ShapeGeometry::TaperedCylinder{height, ref radius1, ref radius2} |
+            ShapeGeometry::TaperedCapsule{height, ref radius1, ref radius2} => {
+                let name = match *self {
+                    ShapeGeometry::TaperedCylinder{..} => "tapered_cylinder",
+                    _ => "tapered_capsule",
+                };
+                let mut c = Element::new(name);
+                c.children.push(utils::text_element("height", height.to_string()));
+                c.children.push(utils::list_element("radius1", radius1));
+                c.children.push(utils::list_element("radius2", radius2));
+                c
+            },
+        }
+    }
+}
+
+/// A collision shape of a rigid body
+#[derive(Debug)]
+pub struct Shape {
+    pub hollow: Option<bool>,
+    pub mass: Option<f32>,
+    pub density: Option<f32>,
+    pub physics_material: Option<PhysicsMaterialRef>,
+    pub geometry: ShapeGeometry,
+
+    /// Position and orientation of the shape relative to the rigid body
+    pub transforms: Vec<Transform>,
+    pub extras: Vec<Extra>,
+    pub unknown: UnknownContent,
+}
+
+impl Shape {
+    pub fn new() -> Shape {
+        Shape {
+            hollow: None,
+            mass: None,
+            density: None,
+            physics_material: None,
+            geometry: ShapeGeometry::Sphere{radius: 1.0},
+            transforms: Vec::new(),
+            extras: Vec::new(),
+            unknown: UnknownContent::new(),
+        }
+    }
+}
+
+impl XmlConversion for Shape {
+    fn parse_with(&mut self, e: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError> {
+        ctx.unknown_attributes(e, &[], &mut self.unknown);
+        let mut geometry = None;
+        for c in &e.children {
+            match c.name.as_str() {
+                "hollow" => self.hollow = Some(try!(utils::parse_bool_text(c))),
+                "mass" => self.mass = Some(try!(utils::parse_text(c))),
+                "density" => self.density = Some(try!(utils::parse_text(c))),
+                "instance_physics_material" | "physics_material" => {
+                    self.physics_material = Some(try!(PhysicsMaterialRef::parse(c, ctx)));
+                },
+                "translate" | "rotate" => {
+                    let mut t = Transform::new(TransformKind::Translate);
+                    try!(ctx.parse(&mut t, c));
+                    self.transforms.push(t);
+                },
+                "extra" => {
+                    let mut x = Extra::new();
+                    try!(ctx.parse(&mut x, c));
+                    self.extras.push(x);
+                },
+                n if ShapeGeometry::is_geometry(n) => geometry = Some(try!(ShapeGeometry::parse(c, ctx))),
+                _ => try!(ctx.unknown_child(e, c, &mut self.unknown)),
+            }
+        }
+
+        self.geometry = match geometry {
+            Some(g) => g,
+            None => return Err(ColladaError::MissingElement{
+                structure: "shape".to_string(),
+                elem: "box".to_string(),
+            }),
+        };
+        Ok(())
+    }
+
+    fn encode(&self) -> Element {
+        let mut s = Element::new("shape");
+        if let Some(h) = self.hollow {
+            s.children.push(utils::text_element("hollow", h.to_string()));
+        }
+        if let Some(m) = self.mass {
+            s.children.push(utils::text_element("mass", m.to_string()));
+        }
+        if let Some(d) = self.density {
+            s.children.push(utils::text_element("density", d.to_string()));
+        }
+        if let Some(ref m) = self.physics_material {
+            s.children.push(m.encode());
+        }
+        s.children.push(self.geometry.encode());
+        for t in &self.transforms {
+            s.children.push(t.encode());
+        }
+        for x in &self.extras {
+            s.children.push(x.encode());
+        }
+        self.unknown.encode_into(&mut s);
+        s
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use xmltree::{Element};
+    use traits::{XmlConversion};
+
+    fn parse_shape(data: &str) -> Result<Shape, ColladaError> {
+        let e = Element::parse(data.as_bytes()).unwrap();
+        let mut s = Shape::new();
+        try!(s.parse(&e));
+        Ok(s)
+    }
+
+    #[test]
+    fn test_shape_parse() {
+        let s = parse_shape(r##"
+            <shape>
+                <hollow>false</hollow>
+                <mass>2.5</mass>
+                <density>1000</density>
+                <instance_physics_material url="#rubber"/>
+                <box><half_extents>1 2 3</half_extents></box>
+                <translate>0 1 0</translate>
+                <rotate>0 0 1 45</rotate>
+            </shape>"##).unwrap();
+        assert_eq!(s.hollow, Some(false));
+        assert_eq!(s.mass, Some(2.5));
+        assert_eq!(s.density, Some(1000.0));
+        match s.physics_material {
+            Some(PhysicsMaterialRef::Instance(ref u)) => assert_eq!(u.as_str(), "#rubber"),
+            _ => assert!(false),
+        }
+        match s.geometry {
+            ShapeGeometry::Box{half_extents} => assert_eq!(half_extents, [1.0, 2.0, 3.0]),
+            _ => assert!(false),
+        }
+        assert_eq!(s.transforms.len(), 2);
+        assert_eq!(s.transforms[1].kind, TransformKind::Rotate);
+
+        let geometries = [
+            (r##"<shape><sphere><radius>0.5</radius></sphere></shape>"##, "sphere"),
+            (r##"<shape><plane><equation>0 1 0 0</equation></plane></shape>"##, "plane"),
+            (r##"<shape><cylinder><height>2</height><radius>1 1</radius></cylinder></shape>"##, "cylinder"),
+            (r##"<shape><capsule><height>2</height><radius>1 1</radius></capsule></shape>"##, "capsule"),
+            (r##"<shape><tapered_cylinder><height>2</height><radius1>1 1</radius1><radius2>0.5 0.5</radius2></tapered_cylinder></shape>"##, "tapered_cylinder"),
+            (r##"<shape><tapered_capsule><height>2</height><radius1>1 1</radius1><radius2>0.5 0.5</radius2></tapered_capsule></shape>"##, "tapered_capsule"),
+            (r##"<shape><instance_geometry url="#hull"/></shape>"##, "instance_geometry"),
+        ];
+        for &(data, name) in geometries.iter() {
+            let s = parse_shape(data).unwrap();
+            assert_eq!(s.geometry.encode().name.as_str(), name);
+        }
+        match parse_shape(geometries[0].0).unwrap().geometry {
+            ShapeGeometry::Sphere{radius} => assert_eq!(radius, 0.5),
+            _ => assert!(false),
+        }
+    }
+
+    #[test]
+    fn test_shape_parse_invalid() {
+        assert!(parse_shape(r##"<shape><mass>1</mass></shape>"##).is_err());
+        assert!(parse_shape(r##"<shape><box/></shape>"##).is_err());
+        assert!(parse_shape(r##"<shape><box><half_extents>1 2</half_extents></box></shape>"##).is_err());
+        assert!(parse_shape(r##"<shape><cone/></shape>"##).is_err());
+        assert!(parse_shape(r##"<shape><hollow>maybe</hollow><sphere><radius>1</radius></sphere></shape>"##).is_err());
+    }
+
+    #[test]
+    fn test_shape_encode() {
+        let mut s = Shape::new();
+        s.mass = Some(1.0);
+        s.geometry = ShapeGeometry::Capsule{height: 2.0, radius: vec![0.5, 0.5]};
+        s.transforms.push(Transform::translate(0.0, 1.0, 0.0));
+
+        let e = s.encode();
+        assert_eq!(e.name, "shape");
+        let names: Vec<&str> = e.children.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["mass", "capsule", "translate"]);
+        let c = &e.children[1];
+        assert_eq!(c.children[0].name, "height");
+        assert_eq!(c.children[0].text, Some("2".to_string()));
+        assert_eq!(c.children[1].name, "radius");
+        assert_eq!(c.children[1].text, Some("0.5 0.5".to_string()));
+    }
+}
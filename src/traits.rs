@@ -1,12 +1,24 @@
 use xmltree::{Element};
+use context::{ParseContext};
 use error::{ColladaError};
 
 pub trait XmlConversion {
-    /// Parse data from an xml element into struct.
-    fn parse(&mut self, e: &Element) -> Result<(), ColladaError>;
+    /// Parse data from an xml element into struct. Errors are located by
+    /// their element path within `e`.
+    fn parse(&mut self, e: &Element) -> Result<(), ColladaError> {
+        ParseContext::for_element(e).parse(self, e)
+    }
+
+    /// Parse data from an xml element into struct, parsing children
+    /// through the context so errors carry their position
+    fn parse_with(&mut self, e: &Element, ctx: &mut ParseContext) -> Result<(), ColladaError>;
+
+    /// Parse an xml document into struct, errors carry their line and column
+    fn parse_source(&mut self, data: &[u8]) -> Result<(), ColladaError> {
+        let (root, mut ctx) = try!(ParseContext::from_source(data));
+        ctx.parse(self, &root)
+    }
 
     /// Encode struct data as an xml element 
     fn encode(&self) -> Element;
 }
-
-
@@ -0,0 +1,686 @@
+use std::collections::{HashSet};
+use xmltree::{Element};
+use collada::{Collada};
+use core::{Asset, Transform, TransformKind, Unit};
+use error::{ColladaError};
+use kinematics::{Joint, JointKind, JointRef, Link};
+use mesh::{Animations, Output, animation_outputs};
+use physics::{InstancePhysicsModel, RigidBodyCommon, RigidConstraint, Shape, ShapeGeometry};
+use utils;
+
+/// Rescale a document to a unit of `meter` meters. The unit of every element
+/// is inherited from the closest `<asset>` with a `<unit>`, or the root
+/// asset. Distances are converted from that unit and nested units are
+/// removed, so the whole document is in the unit of the root asset.
+///
+/// Converted are geometry positions, translations of transforms and skin
+/// bind matrices, camera clip planes and magnifications, light attenuation
+/// and the dimensions, distances and derived quantities of physics elements,
+/// kinematics links and prismatic joint limits, as well as the animation
+/// outputs of translations, matrices and lookats and their `.X`, `.Y` and
+/// `.Z` members. Array selections of animation targets, e.g. `(3)(0)`,
+/// articulated systems and profile techniques are kept as they are.
+///
+/// Returns an error, leaving the document unchanged, unless `meter` and the
+/// units of the document are positive numbers.
+pub fn convert_units(c: &mut Collada, meter: f32) -> Result<(), ColladaError> {
+    if !(meter > 0.0) || meter.is_infinite() {
+        return Err(ColladaError::Invalid{
+            msg: format!("unit of {} meters is not a positive number", meter),
+        });
+    }
+    try!(check_units(c));
+    let converter = Converter {
+        target: meter,
+        animations: animation_outputs(&c.other_libraries),
+    };
+    let root = c.asset.unit.as_ref().map(|u| u.meter()).unwrap_or(1.0);
+    c.asset.unit = Some(Unit::from_meter(meter));
+
+    for l in &mut c.library_joints {
+        let m = converter.meter(&mut l.asset, root);
+        for j in &mut l.joints {
+            converter.joint(j, m);
+        }
+    }
+    for l in &mut c.library_kinematics_models {
+        let m = converter.meter(&mut l.asset, root);
+        for k in &mut l.kinematics_models {
+            let m = converter.meter(&mut k.asset, m);
+            for j in &mut k.common.joints {
+                if let JointRef::Inline(ref mut j) = *j {
+                    converter.joint(j, m);
+                }
+            }
+            for link in &mut k.common.links {
+                converter.link(link, m);
+            }
+        }
+    }
+    for l in &mut c.library_physics_models {
+        let m = converter.meter(&mut l.asset, root);
+        for p in &mut l.physics_models {
+            let m = converter.meter(&mut p.asset, m);
+            for b in &mut p.rigid_bodies {
+                converter.rigid_body(&mut b.common, m);
+            }
+            for r in &mut p.rigid_constraints {
+                converter.rigid_constraint(r, m);
+            }
+            for i in &mut p.instance_physics_models {
+                converter.instance_physics_model(i, m);
+            }
+        }
+    }
+    for l in &mut c.library_physics_scenes {
+        let m = converter.meter(&mut l.asset, root);
+        for s in &mut l.physics_scenes {
+            let m = converter.meter(&mut s.asset, m);
+            let f = converter.factor(m);
+            if let Some(ref mut g) = s.common.gravity {
+                scale3(g, f);
+            }
+            for i in &mut s.instance_physics_models {
+                converter.instance_physics_model(i, m);
+            }
+        }
+    }
+
+    for l in &mut c.other_libraries {
+        converter.element(l, root);
+    }
+
+    // The rest has no distances, only nested units to remove
+    for l in &mut c.library_articulated_systems {
+        clear_unit(&mut l.asset);
+        for a in &mut l.articulated_systems {
+            clear_unit(&mut a.asset);
+        }
+    }
+    for l in &mut c.library_effects {
+        clear_unit(&mut l.asset);
+        for e in &mut l.effects {
+            clear_unit(&mut e.asset);
+        }
+    }
+    for l in &mut c.library_formulas {
+        clear_unit(&mut l.asset);
+    }
+    for l in &mut c.library_images {
+        clear_unit(&mut l.asset);
+        for i in &mut l.images {
+            clear_unit(&mut i.asset);
+        }
+    }
+    for l in &mut c.library_kinematics_scenes {
+        clear_unit(&mut l.asset);
+        for k in &mut l.kinematics_scenes {
+            clear_unit(&mut k.asset);
+        }
+    }
+    for l in &mut c.library_materials {
+        clear_unit(&mut l.asset);
+        for m in &mut l.materials {
+            clear_unit(&mut m.asset);
+        }
+    }
+    for l in &mut c.library_physics_materials {
+        clear_unit(&mut l.asset);
+        for p in &mut l.physics_materials {
+            clear_unit(&mut p.asset);
+        }
+    }
+    Ok(())
+}
+
+/// Check that the units distances are converted from are positive numbers
+fn check_units(c: &Collada) -> Result<(), ColladaError> {
+    let mut assets = vec![Some(&c.asset)];
+    for l in &c.library_joints {
+        assets.push(l.asset.as_ref());
+    }
+    for l in &c.library_kinematics_models {
+        assets.push(l.asset.as_ref());
+        assets.extend(l.kinematics_models.iter().map(|k| k.asset.as_ref()));
+    }
+    for l in &c.library_physics_models {
+        assets.push(l.asset.as_ref());
+        assets.extend(l.physics_models.iter().map(|p| p.asset.as_ref()));
+    }
+    for l in &c.library_physics_scenes {
+        assets.push(l.asset.as_ref());
+        assets.extend(l.physics_scenes.iter().map(|p| p.asset.as_ref()));
+    }
+    for a in assets.into_iter().filter_map(|a| a) {
+        if let Some(m) = a.unit.as_ref().and_then(|u| u.meter) {
+            try!(check_meter(&m.to_string(), m));
+        }
+    }
+
+    let mut stack: Vec<&Element> = c.other_libraries.iter().collect();
+    while let Some(e) = stack.pop() {
+        if e.name == "extra" || (e.name == "technique" && e.attributes.contains_key("profile")) {
+            continue;
+        }
+        if let Some(u) = e.get_child("asset").and_then(|a| a.get_child("unit")) {
+            try!(unit_meter(u));
+        }
+        stack.extend(e.children.iter());
+    }
+    Ok(())
+}
+
+/// The meters of a `<unit>` element, 1 when it has none
+fn unit_meter(u: &Element) -> Result<f32, ColladaError> {
+    match u.attributes.get("meter") {
+        Some(m) => match m.trim().parse() {
+            Ok(x) => check_meter(m, x),
+            Err(_) => check_meter(m, ::std::f32::NAN),
+        },
+        None => Ok(1.0),
+    }
+}
+
+fn check_meter(data: &str, meter: f32) -> Result<f32, ColladaError> {
+    if !(meter > 0.0) || meter.is_infinite() {
+        return Err(ColladaError::InvalidAttrData{
+            elem: "unit".to_string(),
+            attr: "meter".to_string(),
+            data: data.to_string(),
+        });
+    }
+    Ok(meter)
+}
+
+fn clear_unit(asset: &mut Option<Asset>) {
+    if let Some(ref mut a) = *asset {
+        a.unit = None;
+    }
+}
+
+/// Converts the elements of a document to a target unit
+struct Converter {
+    /// Meters in one unit of the converted document
+    target: f32,
+    animations: Animations,
+}
+
+impl Converter {
+    /// The unit of an element with an optional asset within a parent of a
+    /// unit. The asset's unit is removed, as it is converted to the target.
+    fn meter(&self, asset: &mut Option<Asset>, parent: f32) -> f32 {
+        match asset.as_mut().and_then(|a| a.unit.take()) {
+            Some(u) => u.meter(),
+            None => parent,
+        }
+    }
+
+    /// The factor to convert distances of a unit to the target unit. Units
+    /// are taken as the decimals they are written as, so that e.g. 0.01 and
+    /// 1 are exactly 100 times apart.
+    fn factor(&self, meter: f32) -> f64 {
+        decimal(meter) / decimal(self.target)
+    }
+
+    fn transforms(&self, transforms: &mut [Transform], meter: f32) {
+        let f = self.factor(meter);
+        for t in transforms {
+            t.scale_units(f);
+        }
+    }
+
+    fn joint(&self, j: &mut Joint, meter: f32) {
+        let f = self.factor(meter);
+        for a in &mut j.axes {
+            if a.kind != JointKind::Prismatic {
+                continue;
+            }
+            if let Some(ref mut l) = a.limits {
+                scale(&mut l.min, f);
+                scale(&mut l.max, f);
+            }
+        }
+    }
+
+    fn link(&self, l: &mut Link, meter: f32) {
+        self.transforms(&mut l.transforms, meter);
+        for a in &mut l.attachments {
+            self.transforms(&mut a.transforms, meter);
+            if let Some(ref mut l) = a.link {
+                self.link(l, meter);
+            }
+        }
+    }
+
+    fn shape(&self, s: &mut Shape, meter: f32) {
+        let f = self.factor(meter);
+        // Density is mass per volume
+        if let Some(ref mut d) = s.density {
+            scale(d, 1.0 / (f * f * f));
+        }
+        match s.geometry {
+            ShapeGeometry::InstanceGeometry(_) => {},
+            ShapeGeometry::Plane{ref mut equation} => scale(&mut equation[3], f),
+            ShapeGeometry::Box{ref mut half_extents} => scale3(half_extents, f),
+            ShapeGeometry::Sphere{ref mut radius} => scale(radius, f),
+            ShapeGeometry::Cylinder{ref mut height, ref mut radius} |
+            ShapeGeometry::Capsule{ref mut height, ref mut radius} => {
+                scale(height, f);
+                for r in radius {
+                    scale(r, f);
+                }
+            },
+            ShapeGeometry::TaperedCylinder{ref mut height, ref mut radius1, ref mut radius2} |
+            ShapeGeometry::TaperedCapsule{ref mut height, ref mut radius1, ref mut radius2} => {
+                scale(height, f);
+                for r in radius1.iter_mut().chain(radius2.iter_mut()) {
+                    scale(r, f);
+                }
+            },
+        }
+        self.transforms(&mut s.transforms, meter);
+    }
+
+    fn rigid_body(&self, b: &mut RigidBodyCommon, meter: f32) {
+        let f = self.factor(meter);
+        // Inertia is mass times squared distance
+        if let Some(ref mut i) = b.inertia {
+            scale3(i, f * f);
+        }
+        self.transforms(&mut b.mass_frame, meter);
+        for s in &mut b.shapes {
+            self.shape(s, meter);
+        }
+    }
+
+    fn rigid_constraint(&self, r: &mut RigidConstraint, meter: f32) {
+        let f = self.factor(meter);
+        self.transforms(&mut r.ref_attachment.transforms, meter);
+        self.transforms(&mut r.attachment.transforms, meter);
+        if let Some(ref mut l) = r.common.linear_limit {
+            scale3(&mut l.min, f);
+            scale3(&mut l.max, f);
+        }
+        if let Some(ref mut s) = r.common.linear_spring {
+            scale(&mut s.target_value, f);
+        }
+    }
+
+    fn instance_physics_model(&self, i: &mut InstancePhysicsModel, meter: f32) {
+        let f = self.factor(meter);
+        for b in &mut i.instance_rigid_bodies {
+            if let Some(ref mut v) = b.velocity {
+                scale3(v, f);
+            }
+            self.rigid_body(&mut b.common, meter);
+        }
+    }
+
+    /// Convert an element of a library that is kept as an element, e.g. a
+    /// `<library_geometries>`
+    fn element(&self, e: &mut Element, meter: f32) {
+        if e.name == "extra" || (e.name == "technique" && e.attributes.contains_key("profile")) {
+            return;
+        }
+        let meter = match e.children.iter_mut().find(|c| c.name == "asset") {
+            Some(a) => {
+                let unit = a.children.iter()
+                    .position(|c| c.name == "unit")
+                    .map(|i| a.children.remove(i));
+                // Checked by `check_units` before converting
+                unit.and_then(|u| unit_meter(&u).ok()).unwrap_or(meter)
+            },
+            None => meter,
+        };
+
+        let f = self.factor(meter);
+        match e.name.as_str() {
+            "translate" => scale_text(e, 3, &[0, 1, 2], f),
+            "lookat" => scale_text(e, 9, &[0, 1, 2, 3, 4, 5], f),
+            "matrix" | "bind_shape_matrix" => scale_text(e, 16, &[3, 7, 11], f),
+            "znear" | "zfar" | "xmag" | "ymag" => scale_text(e, 1, &[0], f),
+            "linear_attenuation" => scale_text(e, 1, &[0], 1.0 / f),
+            "quadratic_attenuation" => scale_text(e, 1, &[0], 1.0 / (f * f)),
+            "geometry" => scale_sources(e, "POSITION", 1, &[0], f),
+            "skin" => scale_sources(e, "INV_BIND_MATRIX", 16, &[3, 7, 11], f),
+            "source" => self.output(e, f),
+            _ => {},
+        }
+        for c in &mut e.children {
+            self.element(c, meter);
+        }
+    }
+
+    /// Scale the distances of an animation output source
+    fn output(&self, e: &mut Element, f: f64) {
+        let output = match e.attributes.get("id").and_then(|id| self.animations.sources.get(id)) {
+            Some(o) => *o,
+            None => return,
+        };
+        let array = match e.get_mut_child("float_array") {
+            Some(a) => a,
+            None => return,
+        };
+        match output {
+            Output::Transform(kind) => scale_text(array, kind.len(), distances(kind), f),
+            Output::Member(TransformKind::Translate, _) => scale_text(array, 1, &[0], f),
+            Output::Member(..) => {},
+        }
+    }
+}
+
+/// The indices of the values of a transform that are distances
+fn distances(kind: TransformKind) -> &'static [usize] {
+    match kind {
+        TransformKind::LookAt => &[0, 1, 2, 3, 4, 5],
+        TransformKind::Matrix => &[3, 7, 11],
+        TransformKind::Translate => &[0, 1, 2],
+        _ => &[],
+    }
+}
+
+/// A unit as the decimal it is written as
+fn decimal(meter: f32) -> f64 {
+    meter.to_string().parse().unwrap_or(meter as f64)
+}
+
+fn scale(x: &mut f32, f: f64) {
+    *x = (*x as f64 * f) as f32;
+}
+
+fn scale3(v: &mut [f32; 3], f: f64) {
+    for x in v.iter_mut() {
+        scale(x, f);
+    }
+}
+
+/// Multiply values of a list in an element's text by a factor, the values at
+/// indices within every group of `stride` values. Text that isn't a list of
+/// numbers is kept.
+fn scale_text(e: &mut Element, stride: usize, indices: &[usize], f: f64) {
+    if f == 1.0 {
+        return;
+    }
+    let mut values: Vec<f32> = match e.text.as_ref().map(|t| t.split_whitespace().map(|x| x.parse()).collect()) {
+        Some(Ok(v)) => v,
+        _ => return,
+    };
+    for (i, v) in values.iter_mut().enumerate() {
+        if indices.contains(&(i % stride)) {
+            scale(v, f);
+        }
+    }
+    let text: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+    e.text = Some(text.join(" "));
+}
+
+/// Scale the arrays of the sources an element's inputs of a semantic refer to
+fn scale_sources(e: &mut Element, semantic: &str, stride: usize, indices: &[usize], f: f64) {
+    fn inputs(e: &Element, semantic: &str, ids: &mut HashSet<String>) {
+        if e.name == "input" && e.attributes.get("semantic").map(|s| s.as_str()) == Some(semantic) {
+            if let Some(id) = e.attributes.get("source").and_then(|s| utils::url_id(s)) {
+                ids.insert(id.to_string());
+            }
+        }
+        for c in &e.children {
+            inputs(c, semantic, ids);
+        }
+    }
+
+    fn sources(e: &mut Element, ids: &HashSet<String>, stride: usize, indices: &[usize], f: f64) {
+        if e.name == "source" && e.attributes.get("id").map(|id| ids.contains(id)) == Some(true) {
+            if let Some(a) = e.get_mut_child("float_array") {
+                scale_text(a, stride, indices, f);
+            }
+            return;
+        }
+        for c in &mut e.children {
+            sources(c, ids, stride, indices, f);
+        }
+    }
+
+    let mut ids = HashSet::new();
+    inputs(e, semantic, &mut ids);
+    sources(e, &ids, stride, indices, f);
+}
+
+#[cfg(test)]
+mod tests {
+    use xmltree::{Element};
+    use collada::{Collada};
+    use error::{ColladaError};
+    use physics::{ShapeGeometry};
+
+    const DATA: &'static str = r##"<?xml version="1.0" encoding="utf-8"?>
+<COLLADA xmlns="http://www.collada.org/2008/03/COLLADASchema" version="1.5.0">
+  <asset>
+    <created>2017-01-01T00:00:00Z</created>
+    <modified>2017-01-01T00:00:00Z</modified>
+    <unit meter="0.01" name="centimeter"/>
+  </asset>
+  <library_animations>
+    <animation id="move">
+      <source id="move-input">
+        <float_array id="move-input-array" count="2">0 1</float_array>
+        <technique_common>
+          <accessor source="#move-input-array" count="2"><param name="TIME" type="float"/></accessor>
+        </technique_common>
+      </source>
+      <source id="move-output">
+        <float_array id="move-output-array" count="6">100 0 -50 200 0 -50</float_array>
+        <technique_common>
+          <accessor source="#move-output-array" count="2" stride="3">
+            <param name="X" type="float"/><param name="Y" type="float"/><param name="Z" type="float"/>
+          </accessor>
+        </technique_common>
+      </source>
+      <source id="move-x-output">
+        <float_array id="move-x-output-array" count="2">100 300</float_array>
+        <technique_common>
+          <accessor source="#move-x-output-array" count="2"><param name="X" type="float"/></accessor>
+        </technique_common>
+      </source>
+      <source id="spin-output">
+        <float_array id="spin-output-array" count="2">45 90</float_array>
+        <technique_common>
+          <accessor source="#spin-output-array" count="2"><param name="ANGLE" type="float"/></accessor>
+        </technique_common>
+      </source>
+      <sampler id="move-sampler">
+        <input semantic="INPUT" source="#move-input"/>
+        <input semantic="OUTPUT" source="#move-output"/>
+      </sampler>
+      <sampler id="move-x-sampler">
+        <input semantic="INPUT" source="#move-input"/>
+        <input semantic="OUTPUT" source="#move-x-output"/>
+      </sampler>
+      <sampler id="spin-sampler">
+        <input semantic="INPUT" source="#move-input"/>
+        <input semantic="OUTPUT" source="#spin-output"/>
+      </sampler>
+      <channel source="#move-sampler" target="node/move"/>
+      <channel source="#move-x-sampler" target="node/move.X"/>
+      <channel source="#spin-sampler" target="node/spin.ANGLE"/>
+    </animation>
+  </library_animations>
+  <library_cameras>
+    <camera id="camera">
+      <optics>
+        <technique_common>
+          <perspective>
+            <yfov>45</yfov>
+            <znear>10</znear>
+            <zfar>1000</zfar>
+          </perspective>
+        </technique_common>
+      </optics>
+    </camera>
+  </library_cameras>
+  <library_geometries>
+    <geometry id="tri">
+      <asset>
+        <created>2017-01-01T00:00:00Z</created>
+        <modified>2017-01-01T00:00:00Z</modified>
+        <unit meter="1" name="meter"/>
+      </asset>
+      <mesh>
+        <source id="tri-pos">
+          <float_array id="tri-pos-array" count="9">0 0 0 1 0 0 0 1 0</float_array>
+          <technique_common>
+            <accessor source="#tri-pos-array" count="3" stride="3"/>
+          </technique_common>
+        </source>
+        <source id="tri-uv">
+          <float_array id="tri-uv-array" count="2">0.5 1</float_array>
+        </source>
+        <vertices id="tri-vtx">
+          <input semantic="POSITION" source="#tri-pos"/>
+        </vertices>
+        <triangles count="1">
+          <input semantic="VERTEX" source="#tri-vtx" offset="0"/>
+          <input semantic="TEXCOORD" source="#tri-uv" offset="0"/>
+          <p>0 1 2</p>
+        </triangles>
+      </mesh>
+    </geometry>
+  </library_geometries>
+  <library_lights>
+    <light id="light">
+      <technique_common>
+        <point>
+          <color>1 1 1</color>
+          <linear_attenuation>0.5</linear_attenuation>
+          <quadratic_attenuation>0.25</quadratic_attenuation>
+        </point>
+      </technique_common>
+    </light>
+  </library_lights>
+  <library_physics_models>
+    <physics_model id="model">
+      <rigid_body sid="body">
+        <technique_common>
+          <shape>
+            <box>
+              <half_extents>1 2 3</half_extents>
+            </box>
+            <translate>0 0 100</translate>
+            <rotate>0 0 1 90</rotate>
+          </shape>
+        </technique_common>
+      </rigid_body>
+    </physics_model>
+  </library_physics_models>
+  <library_physics_scenes>
+    <physics_scene id="physics">
+      <technique_common>
+        <gravity>0 -981 0</gravity>
+      </technique_common>
+    </physics_scene>
+  </library_physics_scenes>
+  <library_visual_scenes>
+    <visual_scene id="scene">
+      <node id="node">
+        <translate sid="move">100 0 -50</translate>
+        <rotate sid="spin">0 1 0 45</rotate>
+        <matrix>1 0 0 200 0 1 0 0 0 0 1 0 0 0 0 1</matrix>
+      </node>
+    </visual_scene>
+  </library_visual_scenes>
+</COLLADA>"##;
+
+    fn find<'a>(e: &'a Element, name: &str) -> Option<&'a Element> {
+        if e.name == name {
+            return Some(e);
+        }
+        e.children.iter().filter_map(|c| find(c, name)).next()
+    }
+
+    fn text<'a>(c: &'a Collada, library: &str, name: &str) -> String {
+        let l = c.other_libraries.iter().find(|l| l.name == library).unwrap();
+        find(l, name).unwrap().text.clone().unwrap()
+    }
+
+    #[test]
+    fn test_convert_units() {
+        let mut c = Collada::read(DATA.as_bytes()).unwrap();
+        c.convert_units(1.0).unwrap();
+
+        let u = c.asset.unit.as_ref().unwrap();
+        assert_eq!(u.meter, Some(1.0));
+        assert_eq!(u.name, Some("meter".to_string()));
+
+        // The geometry was in meters already, the rest in centimeters
+        assert_eq!(text(&c, "library_geometries", "float_array"), "0 0 0 1 0 0 0 1 0");
+        let g = c.other_libraries.iter().find(|l| l.name == "library_geometries").unwrap();
+        assert!(find(g, "unit").is_none());
+        assert_eq!(find(g, "mesh").unwrap().children[1].children[0].text, Some("0.5 1".to_string()));
+        assert_eq!(text(&c, "library_cameras", "znear"), "0.1");
+        assert_eq!(text(&c, "library_cameras", "zfar"), "10");
+        assert_eq!(text(&c, "library_cameras", "yfov"), "45");
+        assert_eq!(text(&c, "library_lights", "linear_attenuation"), "50");
+        assert_eq!(text(&c, "library_lights", "quadratic_attenuation"), "2500");
+        assert_eq!(text(&c, "library_visual_scenes", "translate"), "1 0 -0.5");
+        assert_eq!(text(&c, "library_visual_scenes", "rotate"), "0 1 0 45");
+        assert_eq!(text(&c, "library_visual_scenes", "matrix"), "1 0 0 2 0 1 0 0 0 0 1 0 0 0 0 1");
+
+        // Animated translations move with the node, angles don't change
+        let a = c.other_libraries.iter().find(|l| l.name == "library_animations").unwrap();
+        let arrays: Vec<&str> = a.children[0].children.iter()
+            .filter(|s| s.name == "source")
+            .map(|s| s.get_child("float_array").unwrap().text.as_ref().unwrap().as_str())
+            .collect();
+        assert_eq!(arrays, vec!["0 1", "1 0 -0.5 2 0 -0.5", "1 3", "45 90"]);
+
+        let shape = &c.library_physics_models[0].physics_models[0].rigid_bodies[0].common.shapes[0];
+        match shape.geometry {
+            ShapeGeometry::Box{half_extents} => assert_eq!(half_extents, [0.01, 0.02, 0.03]),
+            _ => assert!(false),
+        }
+        assert_eq!(shape.transforms[0].values, vec![0.0, 0.0, 1.0]);
+        assert_eq!(shape.transforms[1].values, vec![0.0, 0.0, 1.0, 90.0]);
+        let g = c.library_physics_scenes[0].physics_scenes[0].common.gravity.unwrap();
+        assert!((g[1] + 9.81).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_convert_units_back() {
+        let mut c = Collada::read(DATA.as_bytes()).unwrap();
+        c.convert_units(0.001).unwrap();
+        assert_eq!(c.asset.unit.as_ref().unwrap().name, Some("millimeter".to_string()));
+        assert_eq!(text(&c, "library_geometries", "float_array"), "0 0 0 1000 0 0 0 1000 0");
+        assert_eq!(text(&c, "library_cameras", "znear"), "100");
+        assert!(c.validate().is_empty());
+
+        c.convert_units(0.01).unwrap();
+        assert_eq!(text(&c, "library_cameras", "znear"), "10");
+        assert_eq!(text(&c, "library_geometries", "float_array"), "0 0 0 100 0 0 0 100 0");
+
+        for meter in &[0.0, -1.0, ::std::f32::NAN, ::std::f32::INFINITY] {
+            assert!(c.convert_units(*meter).is_err());
+        }
+        assert_eq!(c.asset.unit.as_ref().unwrap().meter, Some(0.01));
+        assert_eq!(text(&c, "library_cameras", "znear"), "10");
+    }
+
+    #[test]
+    fn test_convert_units_invalid_source() {
+        let root = r#"<unit meter="0.01" name="centimeter"/>"#;
+        let nested = r#"<unit meter="1" name="meter"/>"#;
+        for &(from, to, data) in &[
+            (root, r#"<unit meter="0"/>"#, "0"),
+            (root, r#"<unit meter="NaN"/>"#, "NaN"),
+            (nested, r#"<unit meter="-1"/>"#, "-1"),
+            (nested, r#"<unit meter="one"/>"#, "one"),
+        ] {
+            let mut c = Collada::read(DATA.replace(from, to).as_bytes()).unwrap();
+            match c.convert_units(1.0) {
+                Err(ColladaError::InvalidAttrData{ref elem, ref attr, data: ref d}) => {
+                    assert_eq!((elem.as_str(), attr.as_str(), d.as_str()), ("unit", "meter", data));
+                },
+                r => panic!("unexpected result {:?} for {}", r, to),
+            }
+            assert_eq!(text(&c, "library_geometries", "float_array"), "0 0 0 1 0 0 0 1 0");
+            assert_eq!(text(&c, "library_cameras", "znear"), "10");
+        }
+    }
+}
@@ -0,0 +1,305 @@
+use std::collections::{HashMap};
+use std::fmt::{Display};
+use std::io::{self, Write};
+use std::str::{FromStr};
+use xmltree::{Element};
+use error::{ColladaError};
+
+/// Get the value of an optional attribute
+pub fn get_attr(e: &Element, attr: &str) -> Option<String> {
+    e.attributes.get(attr).cloned()
+}
+
+/// Get the value of a required attribute
+pub fn require_attr(e: &Element, attr: &str) -> Result<String, ColladaError> {
+    match e.attributes.get(attr) {
+        Some(v) => Ok(v.clone()),
+        None => Err(ColladaError::MissingAttr{
+            elem: e.name.clone(),
+            attr: attr.to_string(),
+        }),
+    }
+}
+
+/// Parse the value of an optional attribute
+pub fn parse_attr<T: FromStr>(e: &Element, attr: &str) -> Result<Option<T>, ColladaError> {
+    match e.attributes.get(attr) {
+        Some(v) => match v.trim().parse::<T>() {
+            Ok(x) => Ok(Some(x)),
+            Err(_) => Err(ColladaError::InvalidAttrData{
+                elem: e.name.clone(),
+                attr: attr.to_string(),
+                data: v.clone(),
+            }),
+        },
+        None => Ok(None),
+    }
+}
+
+/// Get the text of an element that must contain data
+pub fn require_text(e: &Element) -> Result<String, ColladaError> {
+    match e.text {
+        Some(ref t) => Ok(t.clone()),
+        None => Err(ColladaError::MissingData{
+            elem: e.name.clone(),
+        }),
+    }
+}
+
+/// Create an element containing only text
+pub fn text_element(name: &str, text: String) -> Element {
+    Element {
+        name: name.to_string(),
+        attributes: HashMap::new(),
+        children: Vec::new(),
+        text: Some(text),
+    }
+}
+
+/// Insert an attribute if it has a value
+pub fn set_attr(e: &mut Element, attr: &str, value: &Option<String>) {
+    if let Some(ref v) = *value {
+        e.attributes.insert(attr.to_string(), v.clone());
+    }
+}
+
+/// Get the namespaces an element declares as `(prefix, uri)` pairs sorted by
+/// prefix, the default namespace having an empty prefix
+pub fn get_namespaces(e: &Element) -> Vec<(String, String)> {
+    let mut namespaces: Vec<(String, String)> = e.attributes.iter()
+        .filter_map(|(name, uri)| match name.as_str() {
+            "xmlns" => Some((String::new(), uri.clone())),
+            _ if name.starts_with("xmlns:") => Some((name[6..].to_string(), uri.clone())),
+            _ => None,
+        })
+        .collect();
+    namespaces.sort();
+    namespaces
+}
+
+/// Declare namespaces on an element as `xmlns` and `xmlns:<prefix>`
+/// attributes
+pub fn set_namespaces(e: &mut Element, namespaces: &[(String, String)]) {
+    for &(ref prefix, ref uri) in namespaces {
+        let attr = match prefix.as_str() {
+            "" => "xmlns".to_string(),
+            _ => format!("xmlns:{}", prefix),
+        };
+        e.attributes.insert(attr, uri.clone());
+    }
+}
+
+/// Get the id referenced by a local url, e.g. `#some-id` gives `some-id`
+pub fn url_id(url: &str) -> Option<&str> {
+    if url.starts_with('#') {
+        Some(&url[1..])
+    } else {
+        None
+    }
+}
+
+/// The urls an element refers to other elements with, e.g. the `url` of an
+/// instance or the `source` of an input
+pub fn element_urls(e: &Element) -> Vec<String> {
+    let mut urls = Vec::new();
+    for &attr in ["url", "source", "target"].iter() {
+        // The target of a channel or a bind is a sid path
+        if attr == "target" && e.name != "instance_material" {
+            continue;
+        }
+        if let Some(url) = e.attributes.get(attr) {
+            urls.push(url.clone());
+        }
+    }
+    if e.name == "skeleton" {
+        urls.extend(e.text.iter().map(|t| t.trim().to_string()));
+    }
+    urls
+}
+
+/// Whether a URI starts with a scheme such as `http:`. Single letter schemes
+/// are treated as windows drive letters.
+pub fn has_scheme(uri: &str) -> bool {
+    match uri.find(':') {
+        Some(i) if i > 1 => {
+            let s = &uri[..i];
+            s.chars().all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.')
+        },
+        _ => false,
+    }
+}
+
+/// Decode the `%XX` escapes of a URI
+pub fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = ::std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or("");
+            if let Ok(b) = u8::from_str_radix(hex, 16) {
+                out.push(b);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Parse the text of an element as hex binary data, ignoring whitespace
+pub fn parse_hex(e: &Element) -> Result<Vec<u8>, ColladaError> {
+    let t = try!(require_text(e));
+    let digits: Vec<u8> = t.bytes().filter(|b| !(*b as char).is_whitespace()).collect();
+    let invalid = ColladaError::InvalidData{
+        elem: e.name.clone(),
+        data: t.clone(),
+    };
+    if digits.len() % 2 != 0 {
+        return Err(invalid);
+    }
+
+    let mut data = Vec::with_capacity(digits.len() / 2);
+    for pair in digits.chunks(2) {
+        let hi = (pair[0] as char).to_digit(16);
+        let lo = (pair[1] as char).to_digit(16);
+        match (hi, lo) {
+            (Some(h), Some(l)) => data.push((h * 16 + l) as u8),
+            _ => return Err(invalid),
+        }
+    }
+    Ok(data)
+}
+
+/// Format binary data as hex
+pub fn hex_string(data: &[u8]) -> String {
+    let mut s = String::with_capacity(data.len() * 2);
+    for b in data {
+        s.push_str(&format!("{:02X}", b));
+    }
+    s
+}
+
+/// Parse an xs:boolean value
+pub fn parse_bool(s: &str) -> Option<bool> {
+    match s.trim() {
+        "true" | "1" => Some(true),
+        "false" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+/// Parse the text of an element as a whitespace separated list of values.
+/// An element without text is an empty list.
+pub fn parse_list<T: FromStr>(e: &Element) -> Result<Vec<T>, ColladaError> {
+    let mut v = Vec::new();
+    if let Some(ref t) = e.text {
+        for s in t.split_whitespace() {
+            match s.parse::<T>() {
+                Ok(x) => v.push(x),
+                Err(_) => return Err(ColladaError::InvalidData{
+                    elem: e.name.clone(),
+                    data: s.to_string(),
+                }),
+            }
+        }
+    }
+    Ok(v)
+}
+
+/// Parse the text of an element as a list with an exact number of values
+pub fn parse_array<T: FromStr>(e: &Element, len: usize) -> Result<Vec<T>, ColladaError> {
+    let v = try!(parse_list(e));
+    if v.len() != len {
+        return Err(ColladaError::InvalidData{
+            elem: e.name.clone(),
+            data: e.text.clone().unwrap_or(String::new()),
+        });
+    }
+    Ok(v)
+}
+
+/// Parse the text of an element as a single value
+pub fn parse_text<T: FromStr>(e: &Element) -> Result<T, ColladaError> {
+    let t = try!(require_text(e));
+    match t.trim().parse::<T>() {
+        Ok(x) => Ok(x),
+        Err(_) => Err(ColladaError::InvalidData{
+            elem: e.name.clone(),
+            data: t.clone(),
+        }),
+    }
+}
+
+/// Create an element containing a whitespace separated list of values
+pub fn list_element<T: Display>(name: &str, values: &[T]) -> Element {
+    let strs: Vec<String> = values.iter().map(|x| x.to_string()).collect();
+    text_element(name, strs.join(" "))
+}
+
+/// Parse the text of an element as three floats
+pub fn parse_float3(e: &Element) -> Result<[f32; 3], ColladaError> {
+    let v = try!(parse_array::<f32>(e, 3));
+    Ok([v[0], v[1], v[2]])
+}
+
+/// Parse the text of an element as four floats
+pub fn parse_float4(e: &Element) -> Result<[f32; 4], ColladaError> {
+    let v = try!(parse_array::<f32>(e, 4));
+    Ok([v[0], v[1], v[2], v[3]])
+}
+
+/// Parse the text of an element as an xs:boolean
+pub fn parse_bool_text(e: &Element) -> Result<bool, ColladaError> {
+    let t = try!(require_text(e));
+    match parse_bool(&t) {
+        Some(b) => Ok(b),
+        None => Err(ColladaError::InvalidData{
+            elem: e.name.clone(),
+            data: t,
+        }),
+    }
+}
+
+/// Write an element and its children as indented xml. Attributes are written
+/// sorted by name so the output doesn't depend on hash order.
+pub fn write_element<W: Write>(w: &mut W, e: &Element, depth: usize) -> io::Result<()> {
+    let indent = "  ".repeat(depth);
+    try!(write!(w, "{}<{}", indent, e.name));
+    let mut attrs: Vec<(&String, &String)> = e.attributes.iter().collect();
+    attrs.sort();
+    for (k, v) in attrs {
+        try!(write!(w, " {}=\"{}\"", k, escape_xml(v)));
+    }
+
+    let text = e.text.as_ref().map(|t| escape_xml(t)).unwrap_or(String::new());
+    if e.children.is_empty() {
+        if text.is_empty() {
+            return writeln!(w, "/>");
+        }
+        return writeln!(w, ">{}</{}>", text, e.name);
+    }
+    try!(writeln!(w, ">{}", text));
+    for c in &e.children {
+        try!(write_element(w, c, depth + 1));
+    }
+    writeln!(w, "{}</{}>", indent, e.name)
+}
+
+/// Escape the characters that can't appear as is in xml text or attribute
+/// values
+pub fn escape_xml(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
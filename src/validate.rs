@@ -0,0 +1,1597 @@
+use std::collections::{HashSet};
+use xmltree::{Element};
+use context::{ParseContext};
+use core::{DateTime};
+use error::{ColladaError};
+use version::{Version};
+
+/// Kind of data in an attribute or in the text of an element
+#[derive(Copy, Clone, Debug)]
+enum Data {
+    Any,
+    Id,
+    Sid,
+    NCName,
+    NMToken,
+    UInt,
+    Int,
+    Float,
+    Bool,
+    DateTime,
+    Enum(&'static [&'static str]),
+
+    /// A list of floats, with an exact length unless it is 0
+    Floats(usize),
+    Ints,
+    UInts,
+    Bools,
+    Tokens,
+}
+
+/// What an element may contain
+#[derive(Copy, Clone, Debug)]
+enum Content {
+    /// Only the listed children, in order. Each particle is a choice of
+    /// element names with a minimum and maximum count.
+    Seq(&'static [(&'static [&'static str], usize, usize)]),
+
+    /// Any well-formed xml, which is not checked
+    Any,
+
+    /// Children that aren't described yet, checked only by their own rules
+    Open,
+}
+
+/// Schema rule for an element
+struct Rule {
+    name: &'static str,
+
+    /// Parents the rule applies to, any parent when empty
+    parents: &'static [&'static str],
+
+    /// Allowed attributes with their data and whether they are required
+    attrs: &'static [(&'static str, Data, bool)],
+    content: Content,
+
+    /// Data of the text, no text is allowed when None
+    text: Option<Data>,
+}
+
+const N: usize = ::std::usize::MAX;
+
+const ID_NAME: &'static [(&'static str, Data, bool)] = &[
+    ("id", Data::Id, false),
+    ("name", Data::Any, false),
+];
+
+const INSTANCE: &'static [(&'static str, Data, bool)] = &[
+    ("sid", Data::Sid, false),
+    ("name", Data::Any, false),
+    ("url", Data::Any, true),
+];
+
+const SID: &'static [(&'static str, Data, bool)] = &[
+    ("sid", Data::Sid, false),
+];
+
+const ARRAY: &'static [(&'static str, Data, bool)] = &[
+    ("id", Data::Id, false),
+    ("name", Data::Any, false),
+    ("count", Data::UInt, true),
+];
+
+const UNSHARED_INPUT: &'static [(&'static str, Data, bool)] = &[
+    ("semantic", Data::NMToken, true),
+    ("source", Data::Any, true),
+];
+
+const SHARED_INPUT: &'static [(&'static str, Data, bool)] = &[
+    ("offset", Data::UInt, true),
+    ("semantic", Data::NMToken, true),
+    ("source", Data::Any, true),
+    ("set", Data::UInt, false),
+];
+
+const PRIMITIVE: &'static [(&'static str, Data, bool)] = &[
+    ("name", Data::Any, false),
+    ("count", Data::UInt, true),
+    ("material", Data::NCName, false),
+];
+
+const LIBRARIES_141: &'static [&'static str] = &[
+    "library_animation_clips", "library_animations", "library_cameras",
+    "library_controllers", "library_effects", "library_force_fields",
+    "library_geometries", "library_images", "library_lights", "library_materials",
+    "library_nodes", "library_physics_materials", "library_physics_models",
+    "library_physics_scenes", "library_visual_scenes",
+];
+
+const LIBRARIES_150: &'static [&'static str] = &[
+    "library_animation_clips", "library_animations", "library_articulated_systems",
+    "library_cameras", "library_controllers", "library_effects", "library_force_fields",
+    "library_formulas", "library_geometries", "library_images", "library_joints",
+    "library_kinematics_models", "library_kinematics_scenes", "library_lights",
+    "library_materials", "library_nodes", "library_physics_materials",
+    "library_physics_models", "library_physics_scenes", "library_visual_scenes",
+];
+
+/// The item each library holds
+const LIBRARY_ITEMS: &'static [(&'static str, &'static str)] = &[
+    ("library_animation_clips", "animation_clip"),
+    ("library_animations", "animation"),
+    ("library_articulated_systems", "articulated_system"),
+    ("library_cameras", "camera"),
+    ("library_controllers", "controller"),
+    ("library_effects", "effect"),
+    ("library_force_fields", "force_field"),
+    ("library_formulas", "formula"),
+    ("library_geometries", "geometry"),
+    ("library_images", "image"),
+    ("library_joints", "joint"),
+    ("library_kinematics_models", "kinematics_model"),
+    ("library_kinematics_scenes", "kinematics_scene"),
+    ("library_lights", "light"),
+    ("library_materials", "material"),
+    ("library_nodes", "node"),
+    ("library_physics_materials", "physics_material"),
+    ("library_physics_models", "physics_model"),
+    ("library_physics_scenes", "physics_scene"),
+    ("library_visual_scenes", "visual_scene"),
+];
+
+const PRIMITIVES: &'static [&'static str] = &[
+    "lines", "linestrips", "polygons", "polylist", "triangles", "trifans", "tristrips",
+];
+
+const ARRAYS: &'static [&'static str] = &[
+    "bool_array", "float_array", "IDREF_array", "int_array", "Name_array",
+    "SIDREF_array", "token_array",
+];
+
+const TRANSFORMS: &'static [&'static str] = &[
+    "lookat", "matrix", "rotate", "scale", "skew", "translate",
+];
+
+const EXTRA: (&'static [&'static str], usize, usize) = (&["extra"], 0, N);
+const ASSET: (&'static [&'static str], usize, usize) = (&["asset"], 0, 1);
+const TECHNIQUE_COMMON: (&'static [&'static str], usize, usize) = (&["technique_common"], 1, 1);
+const TECHNIQUE: (&'static [&'static str], usize, usize) = (&["technique"], 0, N);
+
+/// Rule of every `<library_*>`, which holds an `<asset>`, one or more of
+/// its items and any `<extra>`
+const LIBRARY: Rule = Rule {
+    name: "library",
+    parents: &[],
+    attrs: ID_NAME,
+    content: Content::Seq(&[]),
+    text: None,
+};
+
+/// Rules that differ in COLLADA 1.4.1, looked up before `RULES`
+const RULES_141: &'static [Rule] = &[
+    Rule {
+        name: "COLLADA",
+        parents: &[],
+        attrs: &[("version", Data::Enum(&["1.4.0", "1.4.1"]), true), ("base", Data::Any, false)],
+        content: Content::Seq(&[(&["asset"], 1, 1), (LIBRARIES_141, 0, N), (&["scene"], 0, 1), EXTRA]),
+        text: None,
+    },
+    Rule {
+        name: "asset",
+        parents: &[],
+        attrs: &[],
+        content: Content::Seq(&[
+            (&["contributor"], 0, N), (&["created"], 1, 1), (&["keywords"], 0, 1),
+            (&["modified"], 1, 1), (&["revision"], 0, 1), (&["subject"], 0, 1),
+            (&["title"], 0, 1), (&["unit"], 0, 1), (&["up_axis"], 0, 1),
+        ]),
+        text: None,
+    },
+    Rule {
+        name: "contributor",
+        parents: &[],
+        attrs: &[],
+        content: Content::Seq(&[
+            (&["author"], 0, 1), (&["authoring_tool"], 0, 1), (&["comments"], 0, 1),
+            (&["copyright"], 0, 1), (&["source_data"], 0, 1),
+        ]),
+        text: None,
+    },
+    Rule {
+        name: "effect",
+        parents: &[],
+        attrs: &[("id", Data::Id, true), ("name", Data::Any, false)],
+        content: Content::Seq(&[
+            ASSET, (&["annotate"], 0, N), (&["image"], 0, N), (&["newparam"], 0, N),
+            (&["profile_CG", "profile_COMMON", "profile_GLES", "profile_GLSL"], 1, N), EXTRA,
+        ]),
+        text: None,
+    },
+    Rule {
+        name: "image",
+        parents: &[],
+        attrs: &[
+            ("id", Data::Id, false), ("name", Data::Any, false), ("format", Data::Any, false),
+            ("height", Data::UInt, false), ("width", Data::UInt, false), ("depth", Data::UInt, false),
+        ],
+        content: Content::Seq(&[ASSET, (&["data", "init_from"], 1, 1), EXTRA]),
+        text: None,
+    },
+    Rule {
+        name: "init_from",
+        parents: &["image"],
+        attrs: &[],
+        content: Content::Seq(&[]),
+        text: Some(Data::Any),
+    },
+    Rule {
+        name: "scene",
+        parents: &[],
+        attrs: &[],
+        content: Content::Seq(&[(&["instance_physics_scene"], 0, N), (&["instance_visual_scene"], 0, 1), EXTRA]),
+        text: None,
+    },
+];
+
+/// Rules of COLLADA 1.5.0 elements. Elements without a rule are checked for
+/// the syntax of their `id` and `sid` only.
+const RULES: &'static [Rule] = &[
+    // Core
+    Rule {
+        name: "COLLADA",
+        parents: &[],
+        attrs: &[("version", Data::Enum(&["1.5.0"]), true), ("base", Data::Any, false)],
+        content: Content::Seq(&[(&["asset"], 1, 1), (LIBRARIES_150, 0, N), (&["scene"], 0, 1), EXTRA]),
+        text: None,
+    },
+    Rule {
+        name: "asset",
+        parents: &[],
+        attrs: &[],
+        content: Content::Seq(&[
+            (&["contributor"], 0, N), (&["coverage"], 0, 1), (&["created"], 1, 1),
+            (&["keywords"], 0, 1), (&["modified"], 1, 1), (&["revision"], 0, 1),
+            (&["subject"], 0, 1), (&["title"], 0, 1), (&["unit"], 0, 1),
+            (&["up_axis"], 0, 1), EXTRA,
+        ]),
+        text: None,
+    },
+    Rule {
+        name: "contributor",
+        parents: &[],
+        attrs: &[],
+        content: Content::Seq(&[
+            (&["author"], 0, 1), (&["author_email"], 0, 1), (&["author_website"], 0, 1),
+            (&["authoring_tool"], 0, 1), (&["comments"], 0, 1), (&["copyright"], 0, 1),
+            (&["source_data"], 0, 1),
+        ]),
+        text: None,
+    },
+    Rule {
+        name: "created",
+        parents: &["asset"],
+        attrs: &[],
+        content: Content::Seq(&[]),
+        text: Some(Data::DateTime),
+    },
+    Rule {
+        name: "modified",
+        parents: &["asset"],
+        attrs: &[],
+        content: Content::Seq(&[]),
+        text: Some(Data::DateTime),
+    },
+    Rule {
+        name: "unit",
+        parents: &["asset"],
+        attrs: &[("meter", Data::Float, false), ("name", Data::NMToken, false)],
+        content: Content::Seq(&[]),
+        text: None,
+    },
+    Rule {
+        name: "up_axis",
+        parents: &["asset"],
+        attrs: &[],
+        content: Content::Seq(&[]),
+        text: Some(Data::Enum(&["X_UP", "Y_UP", "Z_UP"])),
+    },
+    Rule {
+        name: "coverage",
+        parents: &["asset"],
+        attrs: &[],
+        content: Content::Seq(&[(&["geographic_location"], 0, 1)]),
+        text: None,
+    },
+    Rule {
+        name: "geographic_location",
+        parents: &[],
+        attrs: &[],
+        content: Content::Seq(&[(&["longitude"], 1, 1), (&["latitude"], 1, 1), (&["altitude"], 1, 1)]),
+        text: None,
+    },
+    Rule {
+        name: "longitude",
+        parents: &["geographic_location"],
+        attrs: &[],
+        content: Content::Seq(&[]),
+        text: Some(Data::Float),
+    },
+    Rule {
+        name: "latitude",
+        parents: &["geographic_location"],
+        attrs: &[],
+        content: Content::Seq(&[]),
+        text: Some(Data::Float),
+    },
+    Rule {
+        name: "altitude",
+        parents: &["geographic_location"],
+        attrs: &[("mode", Data::Enum(&["absolute", "relativeToGround"]), true)],
+        content: Content::Seq(&[]),
+        text: Some(Data::Float),
+    },
+    Rule {
+        name: "extra",
+        parents: &[],
+        attrs: &[("id", Data::Id, false), ("name", Data::Any, false), ("type", Data::NMToken, false)],
+        content: Content::Seq(&[ASSET, (&["technique"], 1, N)]),
+        text: None,
+    },
+    Rule {
+        name: "technique",
+        parents: &["extra", "source", "optics", "imager", "light", "bind_material"],
+        attrs: &[("profile", Data::NMToken, true), ("xmlns", Data::Any, false)],
+        content: Content::Any,
+        text: None,
+    },
+    Rule {
+        name: "scene",
+        parents: &[],
+        attrs: &[],
+        content: Content::Seq(&[
+            (&["instance_physics_scene"], 0, N), (&["instance_visual_scene"], 0, 1),
+            (&["instance_kinematics_scene"], 0, 1), EXTRA,
+        ]),
+        text: None,
+    },
+    Rule {
+        name: "instance_visual_scene",
+        parents: &["scene"],
+        attrs: INSTANCE,
+        content: Content::Seq(&[EXTRA]),
+        text: None,
+    },
+    Rule {
+        name: "instance_physics_scene",
+        parents: &["scene"],
+        attrs: INSTANCE,
+        content: Content::Seq(&[EXTRA]),
+        text: None,
+    },
+    Rule {
+        name: "lookat",
+        parents: &[],
+        attrs: SID,
+        content: Content::Seq(&[]),
+        text: Some(Data::Floats(9)),
+    },
+    Rule {
+        name: "matrix",
+        parents: &[],
+        attrs: SID,
+        content: Content::Seq(&[]),
+        text: Some(Data::Floats(16)),
+    },
+    Rule {
+        name: "rotate",
+        parents: &[],
+        attrs: SID,
+        content: Content::Seq(&[]),
+        text: Some(Data::Floats(4)),
+    },
+    Rule {
+        name: "scale",
+        parents: &[],
+        attrs: SID,
+        content: Content::Seq(&[]),
+        text: Some(Data::Floats(3)),
+    },
+    Rule {
+        name: "skew",
+        parents: &[],
+        attrs: SID,
+        content: Content::Seq(&[]),
+        text: Some(Data::Floats(7)),
+    },
+    Rule {
+        name: "translate",
+        parents: &[],
+        attrs: SID,
+        content: Content::Seq(&[]),
+        text: Some(Data::Floats(3)),
+    },
+
+    // Data flow
+    Rule {
+        name: "source",
+        parents: &[],
+        attrs: &[("id", Data::Id, true), ("name", Data::Any, false)],
+        content: Content::Seq(&[ASSET, (ARRAYS, 0, 1), (&["technique_common"], 0, 1), TECHNIQUE]),
+        text: None,
+    },
+    Rule {
+        name: "technique_common",
+        parents: &["source"],
+        attrs: &[],
+        content: Content::Seq(&[(&["accessor"], 1, 1)]),
+        text: None,
+    },
+    Rule {
+        name: "accessor",
+        parents: &[],
+        attrs: &[
+            ("count", Data::UInt, true), ("offset", Data::UInt, false),
+            ("source", Data::Any, true), ("stride", Data::UInt, false),
+        ],
+        content: Content::Seq(&[(&["param"], 0, N)]),
+        text: None,
+    },
+    Rule {
+        name: "param",
+        parents: &["accessor"],
+        attrs: &[
+            ("name", Data::Any, false), ("sid", Data::Sid, false),
+            ("type", Data::NMToken, true), ("semantic", Data::NMToken, false),
+        ],
+        content: Content::Seq(&[]),
+        text: None,
+    },
+    Rule {
+        name: "bool_array",
+        parents: &[],
+        attrs: ARRAY,
+        content: Content::Seq(&[]),
+        text: Some(Data::Bools),
+    },
+    Rule {
+        name: "float_array",
+        parents: &[],
+        attrs: &[
+            ("id", Data::Id, false), ("name", Data::Any, false), ("count", Data::UInt, true),
+            ("digits", Data::UInt, false), ("magnitude", Data::Int, false),
+        ],
+        content: Content::Seq(&[]),
+        text: Some(Data::Floats(0)),
+    },
+    Rule {
+        name: "int_array",
+        parents: &[],
+        attrs: &[
+            ("id", Data::Id, false), ("name", Data::Any, false), ("count", Data::UInt, true),
+            ("minInclusive", Data::Int, false), ("maxInclusive", Data::Int, false),
+        ],
+        content: Content::Seq(&[]),
+        text: Some(Data::Ints),
+    },
+    Rule {
+        name: "IDREF_array",
+        parents: &[],
+        attrs: ARRAY,
+        content: Content::Seq(&[]),
+        text: Some(Data::Tokens),
+    },
+    Rule {
+        name: "Name_array",
+        parents: &[],
+        attrs: ARRAY,
+        content: Content::Seq(&[]),
+        text: Some(Data::Tokens),
+    },
+    Rule {
+        name: "SIDREF_array",
+        parents: &[],
+        attrs: ARRAY,
+        content: Content::Seq(&[]),
+        text: Some(Data::Tokens),
+    },
+    Rule {
+        name: "token_array",
+        parents: &[],
+        attrs: ARRAY,
+        content: Content::Seq(&[]),
+        text: Some(Data::Tokens),
+    },
+    Rule {
+        name: "input",
+        parents: &["vertices", "joints", "targets", "sampler", "control_vertices"],
+        attrs: UNSHARED_INPUT,
+        content: Content::Seq(&[]),
+        text: None,
+    },
+    Rule {
+        name: "input",
+        parents: &[
+            "lines", "linestrips", "polygons", "polylist", "triangles", "trifans",
+            "tristrips", "vertex_weights",
+        ],
+        attrs: SHARED_INPUT,
+        content: Content::Seq(&[]),
+        text: None,
+    },
+
+    // Geometry
+    Rule {
+        name: "geometry",
+        parents: &[],
+        attrs: ID_NAME,
+        content: Content::Seq(&[ASSET, (&["brep", "convex_mesh", "mesh", "spline"], 1, 1), EXTRA]),
+        text: None,
+    },
+    Rule {
+        name: "mesh",
+        parents: &[],
+        attrs: &[],
+        content: Content::Seq(&[(&["source"], 1, N), (&["vertices"], 1, 1), (PRIMITIVES, 0, N), EXTRA]),
+        text: None,
+    },
+    Rule {
+        name: "vertices",
+        parents: &[],
+        attrs: &[("id", Data::Id, true), ("name", Data::Any, false)],
+        content: Content::Seq(&[(&["input"], 1, N), EXTRA]),
+        text: None,
+    },
+    Rule {
+        name: "lines",
+        parents: &[],
+        attrs: PRIMITIVE,
+        content: Content::Seq(&[(&["input"], 0, N), (&["p"], 0, 1), EXTRA]),
+        text: None,
+    },
+    Rule {
+        name: "triangles",
+        parents: &[],
+        attrs: PRIMITIVE,
+        content: Content::Seq(&[(&["input"], 0, N), (&["p"], 0, 1), EXTRA]),
+        text: None,
+    },
+    Rule {
+        name: "polylist",
+        parents: &[],
+        attrs: PRIMITIVE,
+        content: Content::Seq(&[(&["input"], 0, N), (&["vcount"], 0, 1), (&["p"], 0, 1), EXTRA]),
+        text: None,
+    },
+    Rule {
+        name: "polygons",
+        parents: &[],
+        attrs: PRIMITIVE,
+        content: Content::Seq(&[(&["input"], 0, N), (&["p", "ph"], 0, N), EXTRA]),
+        text: None,
+    },
+    Rule {
+        name: "linestrips",
+        parents: &[],
+        attrs: PRIMITIVE,
+        content: Content::Seq(&[(&["input"], 0, N), (&["p"], 0, N), EXTRA]),
+        text: None,
+    },
+    Rule {
+        name: "trifans",
+        parents: &[],
+        attrs: PRIMITIVE,
+        content: Content::Seq(&[(&["input"], 0, N), (&["p"], 0, N), EXTRA]),
+        text: None,
+    },
+    Rule {
+        name: "tristrips",
+        parents: &[],
+        attrs: PRIMITIVE,
+        content: Content::Seq(&[(&["input"], 0, N), (&["p"], 0, N), EXTRA]),
+        text: None,
+    },
+    Rule {
+        name: "ph",
+        parents: &[],
+        attrs: &[],
+        content: Content::Seq(&[(&["p"], 1, 1), (&["h"], 1, N)]),
+        text: None,
+    },
+    Rule {
+        name: "p",
+        parents: &[],
+        attrs: &[],
+        content: Content::Seq(&[]),
+        text: Some(Data::UInts),
+    },
+    Rule {
+        name: "h",
+        parents: &["ph"],
+        attrs: &[],
+        content: Content::Seq(&[]),
+        text: Some(Data::UInts),
+    },
+    Rule {
+        name: "vcount",
+        parents: &[],
+        attrs: &[],
+        content: Content::Seq(&[]),
+        text: Some(Data::UInts),
+    },
+
+    // Controllers
+    Rule {
+        name: "controller",
+        parents: &[],
+        attrs: ID_NAME,
+        content: Content::Seq(&[ASSET, (&["morph", "skin"], 1, 1), EXTRA]),
+        text: None,
+    },
+    Rule {
+        name: "skin",
+        parents: &[],
+        attrs: &[("source", Data::Any, true)],
+        content: Content::Seq(&[
+            (&["bind_shape_matrix"], 0, 1), (&["source"], 3, N), (&["joints"], 1, 1),
+            (&["vertex_weights"], 1, 1), EXTRA,
+        ]),
+        text: None,
+    },
+    Rule {
+        name: "bind_shape_matrix",
+        parents: &["skin"],
+        attrs: &[],
+        content: Content::Seq(&[]),
+        text: Some(Data::Floats(16)),
+    },
+    Rule {
+        name: "joints",
+        parents: &["skin"],
+        attrs: &[],
+        content: Content::Seq(&[(&["input"], 2, N), EXTRA]),
+        text: None,
+    },
+    Rule {
+        name: "vertex_weights",
+        parents: &[],
+        attrs: &[("count", Data::UInt, true)],
+        content: Content::Seq(&[(&["input"], 2, N), (&["vcount"], 0, 1), (&["v"], 0, 1), EXTRA]),
+        text: None,
+    },
+    Rule {
+        name: "v",
+        parents: &["vertex_weights"],
+        attrs: &[],
+        content: Content::Seq(&[]),
+        text: Some(Data::Ints),
+    },
+    Rule {
+        name: "morph",
+        parents: &[],
+        attrs: &[("method", Data::Enum(&["NORMALIZED", "RELATIVE"]), false), ("source", Data::Any, true)],
+        content: Content::Seq(&[(&["source"], 2, N), (&["targets"], 1, 1), EXTRA]),
+        text: None,
+    },
+    Rule {
+        name: "targets",
+        parents: &["morph"],
+        attrs: &[],
+        content: Content::Seq(&[(&["input"], 2, N), EXTRA]),
+        text: None,
+    },
+
+    // Animation
+    Rule {
+        name: "animation",
+        parents: &[],
+        attrs: ID_NAME,
+        content: Content::Open,
+        text: None,
+    },
+    Rule {
+        name: "animation_clip",
+        parents: &[],
+        attrs: &[
+            ("id", Data::Id, false), ("name", Data::Any, false),
+            ("start", Data::Float, false), ("end", Data::Float, false),
+        ],
+        content: Content::Seq(&[ASSET, (&["instance_animation"], 1, N), (&["instance_formula"], 0, N), EXTRA]),
+        text: None,
+    },
+    Rule {
+        name: "instance_animation",
+        parents: &[],
+        attrs: INSTANCE,
+        content: Content::Seq(&[EXTRA]),
+        text: None,
+    },
+    Rule {
+        name: "sampler",
+        parents: &["animation"],
+        attrs: &[
+            ("id", Data::Id, false),
+            ("pre_behavior", Data::Enum(&["UNDEFINED", "CONSTANT", "GRADIENT", "CYCLE", "OSCILLATE", "CYCLE_RELATIVE"]), false),
+            ("post_behavior", Data::Enum(&["UNDEFINED", "CONSTANT", "GRADIENT", "CYCLE", "OSCILLATE", "CYCLE_RELATIVE"]), false),
+        ],
+        content: Content::Seq(&[(&["input"], 1, N)]),
+        text: None,
+    },
+    Rule {
+        name: "channel",
+        parents: &["animation"],
+        attrs: &[("source", Data::Any, true), ("target", Data::Any, true)],
+        content: Content::Seq(&[]),
+        text: None,
+    },
+
+    // Cameras and lights
+    Rule {
+        name: "camera",
+        parents: &[],
+        attrs: ID_NAME,
+        content: Content::Seq(&[ASSET, (&["optics"], 1, 1), (&["imager"], 0, 1), EXTRA]),
+        text: None,
+    },
+    Rule {
+        name: "optics",
+        parents: &["camera"],
+        attrs: &[],
+        content: Content::Seq(&[TECHNIQUE_COMMON, TECHNIQUE, EXTRA]),
+        text: None,
+    },
+    Rule {
+        name: "technique_common",
+        parents: &["optics"],
+        attrs: &[],
+        content: Content::Seq(&[(&["orthographic", "perspective"], 1, 1)]),
+        text: None,
+    },
+    Rule {
+        name: "imager",
+        parents: &["camera"],
+        attrs: &[],
+        content: Content::Seq(&[(&["technique"], 1, N), EXTRA]),
+        text: None,
+    },
+    Rule {
+        name: "light",
+        parents: &[],
+        attrs: ID_NAME,
+        content: Content::Seq(&[ASSET, TECHNIQUE_COMMON, TECHNIQUE, EXTRA]),
+        text: None,
+    },
+    Rule {
+        name: "technique_common",
+        parents: &["light"],
+        attrs: &[],
+        content: Content::Seq(&[(&["ambient", "directional", "point", "spot"], 1, 1)]),
+        text: None,
+    },
+
+    // Scenes
+    Rule {
+        name: "visual_scene",
+        parents: &[],
+        attrs: ID_NAME,
+        content: Content::Seq(&[ASSET, (&["node"], 1, N), (&["evaluate_scene"], 0, N), EXTRA]),
+        text: None,
+    },
+    Rule {
+        name: "node",
+        parents: &[],
+        attrs: &[
+            ("id", Data::Id, false), ("name", Data::Any, false), ("sid", Data::Sid, false),
+            ("type", Data::Enum(&["JOINT", "NODE"]), false), ("layer", Data::Any, false),
+        ],
+        content: Content::Seq(&[
+            ASSET, (TRANSFORMS, 0, N), (&["instance_camera"], 0, N),
+            (&["instance_controller"], 0, N), (&["instance_geometry"], 0, N),
+            (&["instance_light"], 0, N), (&["instance_node"], 0, N), (&["node"], 0, N), EXTRA,
+        ]),
+        text: None,
+    },
+    Rule {
+        name: "instance_camera",
+        parents: &["node"],
+        attrs: INSTANCE,
+        content: Content::Seq(&[EXTRA]),
+        text: None,
+    },
+    Rule {
+        name: "instance_light",
+        parents: &["node"],
+        attrs: INSTANCE,
+        content: Content::Seq(&[EXTRA]),
+        text: None,
+    },
+    Rule {
+        name: "instance_node",
+        parents: &["node"],
+        attrs: &[
+            ("sid", Data::Sid, false), ("name", Data::Any, false),
+            ("url", Data::Any, true), ("proxy", Data::Any, false),
+        ],
+        content: Content::Seq(&[EXTRA]),
+        text: None,
+    },
+    Rule {
+        name: "instance_geometry",
+        parents: &["node"],
+        attrs: INSTANCE,
+        content: Content::Seq(&[(&["bind_material"], 0, 1), EXTRA]),
+        text: None,
+    },
+    Rule {
+        name: "instance_controller",
+        parents: &["node"],
+        attrs: INSTANCE,
+        content: Content::Seq(&[(&["skeleton"], 0, N), (&["bind_material"], 0, 1), EXTRA]),
+        text: None,
+    },
+    Rule {
+        name: "bind_material",
+        parents: &[],
+        attrs: &[],
+        content: Content::Seq(&[(&["param"], 0, N), TECHNIQUE_COMMON, TECHNIQUE, EXTRA]),
+        text: None,
+    },
+    Rule {
+        name: "technique_common",
+        parents: &["bind_material"],
+        attrs: &[],
+        content: Content::Seq(&[(&["instance_material"], 1, N)]),
+        text: None,
+    },
+    Rule {
+        name: "instance_material",
+        parents: &[],
+        attrs: &[
+            ("sid", Data::Sid, false), ("name", Data::Any, false),
+            ("target", Data::Any, true), ("symbol", Data::Any, true),
+        ],
+        content: Content::Seq(&[(&["bind"], 0, N), (&["bind_vertex_input"], 0, N), EXTRA]),
+        text: None,
+    },
+    Rule {
+        name: "bind_vertex_input",
+        parents: &["instance_material"],
+        attrs: &[
+            ("semantic", Data::NCName, true), ("input_semantic", Data::NCName, true),
+            ("input_set", Data::UInt, false),
+        ],
+        content: Content::Seq(&[]),
+        text: None,
+    },
+
+    // Effects and materials
+    Rule {
+        name: "effect",
+        parents: &[],
+        attrs: &[("id", Data::Id, true), ("name", Data::Any, false)],
+        content: Content::Seq(&[
+            ASSET, (&["annotate"], 0, N), (&["newparam"], 0, N),
+            (&["profile_BRIDGE", "profile_CG", "profile_COMMON", "profile_GLES", "profile_GLES2", "profile_GLSL"], 1, N),
+            EXTRA,
+        ]),
+        text: None,
+    },
+    Rule {
+        name: "material",
+        parents: &[],
+        attrs: ID_NAME,
+        content: Content::Seq(&[ASSET, (&["instance_effect"], 1, 1), EXTRA]),
+        text: None,
+    },
+    Rule {
+        name: "instance_effect",
+        parents: &["material"],
+        attrs: INSTANCE,
+        content: Content::Seq(&[(&["technique_hint"], 0, N), (&["setparam"], 0, N), EXTRA]),
+        text: None,
+    },
+    Rule {
+        name: "image",
+        parents: &[],
+        attrs: &[("id", Data::Id, false), ("sid", Data::Sid, false), ("name", Data::Any, false)],
+        content: Content::Seq(&[
+            ASSET, (&["renderable"], 0, 1),
+            (&["create_2d", "create_3d", "create_cube", "init_from"], 0, 1), EXTRA,
+        ]),
+        text: None,
+    },
+    Rule {
+        name: "renderable",
+        parents: &["image"],
+        attrs: &[("share", Data::Bool, true)],
+        content: Content::Seq(&[]),
+        text: None,
+    },
+    Rule {
+        name: "init_from",
+        parents: &["image"],
+        attrs: &[("mips_generate", Data::Bool, false)],
+        content: Content::Seq(&[(&["hex", "ref"], 1, 1)]),
+        text: None,
+    },
+
+    // Physics
+    Rule {
+        name: "physics_material",
+        parents: &[],
+        attrs: ID_NAME,
+        content: Content::Seq(&[ASSET, TECHNIQUE_COMMON, TECHNIQUE, EXTRA]),
+        text: None,
+    },
+    Rule {
+        name: "physics_model",
+        parents: &[],
+        attrs: ID_NAME,
+        content: Content::Seq(&[
+            ASSET, (&["rigid_body"], 0, N), (&["rigid_constraint"], 0, N),
+            (&["instance_physics_model"], 0, N), EXTRA,
+        ]),
+        text: None,
+    },
+    Rule {
+        name: "physics_scene",
+        parents: &[],
+        attrs: ID_NAME,
+        content: Content::Seq(&[
+            ASSET, (&["instance_force_field"], 0, N), (&["instance_physics_model"], 0, N),
+            TECHNIQUE_COMMON, TECHNIQUE, EXTRA,
+        ]),
+        text: None,
+    },
+    Rule {
+        name: "rigid_body",
+        parents: &["physics_model"],
+        attrs: &[("id", Data::Id, false), ("sid", Data::Sid, true), ("name", Data::Any, false)],
+        content: Content::Seq(&[TECHNIQUE_COMMON, TECHNIQUE, EXTRA]),
+        text: None,
+    },
+    Rule {
+        name: "rigid_constraint",
+        parents: &["physics_model"],
+        attrs: &[("sid", Data::Sid, true), ("name", Data::Any, false)],
+        content: Content::Seq(&[
+            (&["ref_attachment"], 1, 1), (&["attachment"], 1, 1), TECHNIQUE_COMMON, TECHNIQUE, EXTRA,
+        ]),
+        text: None,
+    },
+    Rule {
+        name: "force_field",
+        parents: &[],
+        attrs: ID_NAME,
+        content: Content::Seq(&[ASSET, (&["technique"], 1, N), EXTRA]),
+        text: None,
+    },
+
+    // Kinematics
+    Rule {
+        name: "joint",
+        parents: &["library_joints", "technique_common"],
+        attrs: &[("id", Data::Id, false), ("name", Data::Any, false), ("sid", Data::Sid, false)],
+        content: Content::Seq(&[(&["prismatic", "revolute"], 1, N), EXTRA]),
+        text: None,
+    },
+    Rule {
+        name: "prismatic",
+        parents: &["joint"],
+        attrs: SID,
+        content: Content::Seq(&[(&["axis"], 1, 1), (&["limits"], 0, 1)]),
+        text: None,
+    },
+    Rule {
+        name: "revolute",
+        parents: &["joint"],
+        attrs: SID,
+        content: Content::Seq(&[(&["axis"], 1, 1), (&["limits"], 0, 1)]),
+        text: None,
+    },
+    Rule {
+        name: "axis",
+        parents: &["prismatic", "revolute"],
+        attrs: SID,
+        content: Content::Seq(&[]),
+        text: Some(Data::Floats(3)),
+    },
+    Rule {
+        name: "instance_joint",
+        parents: &[],
+        attrs: INSTANCE,
+        content: Content::Seq(&[EXTRA]),
+        text: None,
+    },
+    Rule {
+        name: "kinematics_model",
+        parents: &[],
+        attrs: ID_NAME,
+        content: Content::Seq(&[ASSET, TECHNIQUE_COMMON, TECHNIQUE, EXTRA]),
+        text: None,
+    },
+    Rule {
+        name: "technique_common",
+        parents: &["kinematics_model"],
+        attrs: &[],
+        content: Content::Seq(&[
+            (&["newparam"], 0, N), (&["instance_joint", "joint"], 1, N), (&["link"], 1, N),
+            (&["formula", "instance_formula"], 0, N),
+        ]),
+        text: None,
+    },
+    Rule {
+        name: "link",
+        parents: &[],
+        attrs: &[("sid", Data::Sid, false), ("name", Data::Any, false)],
+        content: Content::Seq(&[
+            (&["rotate", "translate"], 0, N),
+            (&["attachment_end", "attachment_full", "attachment_start"], 0, N),
+        ]),
+        text: None,
+    },
+    Rule {
+        name: "attachment_full",
+        parents: &["link"],
+        attrs: &[("joint", Data::Any, true)],
+        content: Content::Seq(&[(&["rotate", "translate"], 0, N), (&["link"], 1, 1)]),
+        text: None,
+    },
+    Rule {
+        name: "attachment_start",
+        parents: &["link"],
+        attrs: &[("joint", Data::Any, true)],
+        content: Content::Seq(&[(&["rotate", "translate"], 0, N)]),
+        text: None,
+    },
+    Rule {
+        name: "attachment_end",
+        parents: &["link"],
+        attrs: &[("joint", Data::Any, true)],
+        content: Content::Seq(&[(&["rotate", "translate"], 0, N)]),
+        text: None,
+    },
+    Rule {
+        name: "articulated_system",
+        parents: &[],
+        attrs: ID_NAME,
+        content: Content::Seq(&[ASSET, (&["kinematics", "motion"], 1, 1), EXTRA]),
+        text: None,
+    },
+    Rule {
+        name: "kinematics_scene",
+        parents: &[],
+        attrs: ID_NAME,
+        content: Content::Seq(&[
+            ASSET, (&["instance_kinematics_model"], 0, N),
+            (&["instance_articulated_system"], 0, N), EXTRA,
+        ]),
+        text: None,
+    },
+    Rule {
+        name: "formula",
+        parents: &["library_formulas", "technique_common"],
+        attrs: &[("id", Data::Id, false), ("name", Data::Any, false), ("sid", Data::Sid, false)],
+        content: Content::Seq(&[(&["newparam"], 0, N), (&["target"], 1, 1), TECHNIQUE_COMMON, TECHNIQUE]),
+        text: None,
+    },
+    Rule {
+        name: "technique_common",
+        parents: &["formula"],
+        attrs: &[],
+        content: Content::Any,
+        text: None,
+    },
+];
+
+/// Find the rule of an element with a parent
+fn find_rule(name: &str, parent: &str, version: Version) -> Option<&'static Rule> {
+    let applies = |r: &&'static Rule| {
+        r.name == name && (r.parents.is_empty() || r.parents.contains(&parent))
+    };
+    if version == Version::V141 {
+        if let Some(r) = RULES_141.iter().find(&applies) {
+            return Some(r);
+        }
+    }
+    RULES.iter().find(&applies)
+}
+
+/// Whether a string is an xs:NCName, e.g. an `id`
+fn is_ncname(s: &str) -> bool {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(c) if c.is_alphabetic() || c == '_' => {},
+        _ => return false,
+    }
+    chars.all(|c| c.is_alphanumeric() || c == '_' || c == '-' || c == '.' || c == '\u{b7}')
+}
+
+/// Whether a string is an xs:NMTOKEN
+fn is_nmtoken(s: &str) -> bool {
+    !s.is_empty() && s.chars().all(|c| {
+        c.is_alphanumeric() || c == '_' || c == '-' || c == '.' || c == ':' || c == '\u{b7}'
+    })
+}
+
+/// Whether a string is a scoped identifier. COLLADA 1.5 doesn't allow the
+/// characters used by sid paths, i.e. `.`, `/`, `(` and `)`.
+fn is_sid(s: &str, version: Version) -> bool {
+    is_ncname(s) && (version == Version::V141 || !s.contains('.'))
+}
+
+/// Whether a string is an xs:float or xs:double, e.g. `-1.5E3` or `INF`.
+/// Unlike Rust's float parsing, `inf`, `infinity` and `nan` aren't allowed.
+fn is_float(s: &str) -> bool {
+    fn unsigned(s: &str) -> &str {
+        if s.starts_with('+') || s.starts_with('-') { &s[1..] } else { s }
+    }
+    fn digits(s: &str) -> bool {
+        s.chars().all(|c| c.is_ascii_digit())
+    }
+    if s == "INF" || s == "-INF" || s == "NaN" {
+        return true;
+    }
+    let (mantissa, exponent) = match s.find(|c| c == 'e' || c == 'E') {
+        Some(i) => (&s[..i], Some(unsigned(&s[i + 1..]))),
+        None => (s, None),
+    };
+    let mut parts = unsigned(mantissa).splitn(2, '.');
+    let whole = parts.next().unwrap_or("");
+    let fraction = parts.next().unwrap_or("");
+    (!whole.is_empty() || !fraction.is_empty()) && digits(whole) && digits(fraction)
+        && exponent.map(|e| !e.is_empty() && digits(e)).unwrap_or(true)
+}
+
+/// Whether a string is an xs:dateTime, e.g. `2017-01-01T12:30:00Z`
+pub fn is_date_time(s: &str) -> bool {
+    s.parse::<DateTime>().is_ok()
+}
+
+/// Checks an element tree against the schema rules
+struct Validator {
+    ctx: ParseContext,
+    version: Version,
+    ids: HashSet<String>,
+    errors: Vec<ColladaError>,
+}
+
+impl Validator {
+    fn error(&mut self, e: &Element, error: ColladaError) {
+        let error = match self.ctx.position(e) {
+            Some(p) => ColladaError::Located{position: p.clone(), error: Box::new(error)},
+            None => error,
+        };
+        self.errors.push(error);
+    }
+
+    fn check_data(&self, data: Data, s: &str) -> bool {
+        let t = s.trim();
+        match data {
+            Data::Any | Data::Tokens => true,
+            Data::Id | Data::NCName => is_ncname(t),
+            Data::Sid => is_sid(t, self.version),
+            Data::NMToken => is_nmtoken(t),
+            Data::UInt => t.parse::<u64>().is_ok(),
+            Data::Int => t.parse::<i64>().is_ok(),
+            Data::Float => is_float(t),
+            Data::Bool => ::utils::parse_bool(t).is_some(),
+            Data::DateTime => is_date_time(t),
+            Data::Enum(values) => values.contains(&t),
+            Data::Floats(n) => {
+                let v: Vec<&str> = t.split_whitespace().collect();
+                (n == 0 || v.len() == n) && v.iter().all(|x| is_float(x))
+            },
+            Data::Ints => t.split_whitespace().all(|x| x.parse::<i64>().is_ok()),
+            Data::UInts => t.split_whitespace().all(|x| x.parse::<u64>().is_ok()),
+            Data::Bools => t.split_whitespace().all(|x| ::utils::parse_bool(x).is_some()),
+        }
+    }
+
+    /// Check that an `id` is unique, its syntax is checked with the other
+    /// attributes
+    fn check_id(&mut self, e: &Element) {
+        if let Some(id) = e.attributes.get("id") {
+            if is_ncname(id) && !self.ids.insert(id.clone()) {
+                self.error(e, ColladaError::Invalid{msg: format!("duplicate id '{}'", id)});
+            }
+        }
+    }
+
+    /// Check the `id` and `sid` syntax of an element without a rule
+    fn check_ids(&mut self, e: &Element) {
+        for &(name, data) in [("id", Data::Id), ("sid", Data::Sid)].iter() {
+            if let Some(v) = e.attributes.get(name) {
+                if !self.check_data(data, v) {
+                    self.error(e, ColladaError::InvalidAttrData{
+                        elem: e.name.clone(),
+                        attr: name.to_string(),
+                        data: v.clone(),
+                    });
+                }
+            }
+        }
+    }
+
+    fn check_attrs(&mut self, e: &Element, rule: &Rule) {
+        for &(name, data, required) in rule.attrs {
+            match e.attributes.get(name) {
+                Some(v) => if !self.check_data(data, v) {
+                    self.error(e, ColladaError::InvalidAttrData{
+                        elem: e.name.clone(),
+                        attr: name.to_string(),
+                        data: v.clone(),
+                    });
+                },
+                None => if required {
+                    self.error(e, ColladaError::MissingAttr{
+                        elem: e.name.clone(),
+                        attr: name.to_string(),
+                    });
+                },
+            }
+        }
+
+        let mut names: Vec<&String> = e.attributes.keys().collect();
+        names.sort();
+        for name in names {
+            let known = rule.attrs.iter().any(|a| a.0 == name.as_str());
+            let namespace = name.as_str() == "xmlns" || name.starts_with("xmlns:") || name.starts_with("xsi:");
+            if !known && !namespace {
+                self.error(e, ColladaError::InvalidAttr{
+                    elem: e.name.clone(),
+                    attr: name.clone(),
+                });
+            }
+        }
+    }
+
+    fn check_text(&mut self, e: &Element, rule: &Rule) {
+        let text = e.text.as_ref().map(|t| t.trim()).unwrap_or("");
+        match rule.text {
+            None => if text != "" {
+                self.error(e, ColladaError::InvalidData{
+                    elem: e.name.clone(),
+                    data: text.to_string(),
+                });
+            },
+            Some(data) => {
+                let optional = match data {
+                    Data::Any | Data::Tokens | Data::Floats(0) | Data::Ints |
+                    Data::UInts | Data::Bools => true,
+                    _ => false,
+                };
+                if text == "" && !optional {
+                    self.error(e, ColladaError::MissingData{elem: e.name.clone()});
+                } else if !self.check_data(data, text) {
+                    self.error(e, ColladaError::InvalidData{
+                        elem: e.name.clone(),
+                        data: text.to_string(),
+                    });
+                }
+            },
+        }
+
+        if ARRAYS.contains(&e.name.as_str()) {
+            let count = e.attributes.get("count").and_then(|c| c.trim().parse::<usize>().ok());
+            let n = text.split_whitespace().count();
+            if let Some(c) = count {
+                if c != n {
+                    self.error(e, ColladaError::Invalid{
+                        msg: format!("<{}> has count {} but {} values", e.name, c, n),
+                    });
+                }
+            }
+        }
+    }
+
+    /// Check the order and number of children against a content model
+    fn check_children(&mut self, e: &Element, particles: &[(&[&str], usize, usize)]) {
+        let mut current = 0;
+        let mut count = 0;
+        for c in &e.children {
+            let name = c.name.as_str();
+            match particles.iter().skip(current).position(|p| p.0.contains(&name)) {
+                Some(0) => {
+                    count += 1;
+                    if count - 1 == particles[current].2 {
+                        self.error(c, ColladaError::Invalid{
+                            msg: format!("<{}> has too many <{}> elements", e.name, name),
+                        });
+                    }
+                },
+                Some(skip) => {
+                    self.check_min(e, &particles[current], count);
+                    for p in &particles[current + 1..current + skip] {
+                        self.check_min(e, p, 0);
+                    }
+                    current += skip;
+                    count = 1;
+                },
+                None => if particles[..current].iter().any(|p| p.0.contains(&name)) {
+                    self.error(c, ColladaError::Invalid{
+                        msg: format!("<{}> is out of order in <{}>", name, e.name),
+                    });
+                } else {
+                    self.error(c, ColladaError::InvalidChild{
+                        child: c.name.clone(),
+                        parent: e.name.clone(),
+                    });
+                },
+            }
+        }
+
+        if current < particles.len() {
+            self.check_min(e, &particles[current], count);
+            for p in &particles[current + 1..] {
+                self.check_min(e, p, 0);
+            }
+        }
+    }
+
+    fn check_min(&mut self, e: &Element, particle: &(&[&str], usize, usize), count: usize) {
+        // A misplaced child is reported as out of order instead
+        let misplaced = e.children.iter().any(|c| particle.0.contains(&c.name.as_str()));
+        if count < particle.1 && !misplaced {
+            self.error(e, ColladaError::MissingElement{
+                structure: e.name.clone(),
+                elem: particle.0.join(" or "),
+            });
+        }
+    }
+
+    /// Check the children of an element against a content model, then
+    /// check the children it allows. Unexpected children are only reported.
+    fn check_content(&mut self, e: &Element, particles: &[(&[&str], usize, usize)]) {
+        self.check_children(e, particles);
+        for c in &e.children {
+            if particles.iter().any(|p| p.0.contains(&c.name.as_str())) {
+                self.check(c, &e.name);
+            }
+        }
+    }
+
+    fn check(&mut self, e: &Element, parent: &str) {
+        self.check_id(e);
+        if let Some(&(_, item)) = LIBRARY_ITEMS.iter().find(|l| l.0 == e.name) {
+            let items = [item];
+            self.check_attrs(e, &LIBRARY);
+            self.check_text(e, &LIBRARY);
+            self.check_content(e, &[ASSET, (&items, 1, N), EXTRA]);
+            return;
+        }
+
+        let rule = match find_rule(&e.name, parent, self.version) {
+            Some(r) => r,
+            None => {
+                self.check_ids(e);
+                for c in &e.children {
+                    self.check(c, &e.name);
+                }
+                return;
+            },
+        };
+
+        self.check_attrs(e, rule);
+        match rule.content {
+            Content::Any => {},
+            Content::Open => for c in &e.children {
+                self.check(c, &e.name);
+            },
+            Content::Seq(particles) => {
+                self.check_text(e, rule);
+                self.check_content(e, particles);
+            },
+        }
+    }
+}
+
+/// Validate a `<COLLADA>` element tree against the rules of the COLLADA
+/// schema, e.g. the order and number of children, required attributes,
+/// enumerations, `id` and `sid` syntax and array counts. Every violation is
+/// returned, located by its element path.
+///
+/// This is a subset of the schema, not an equivalent of it. The content of
+/// the asset, scene, data flow, geometry, controller, camera, light, visual
+/// scene, material and instance elements is checked, and the outline of
+/// animations, effects, images, physics, kinematics and formulas. Elements
+/// without a rule, e.g. the content of `profile_COMMON`, `profile_GLSL`,
+/// `profile_CG` and `profile_GLES` or physics shapes, only have their `id`
+/// and `sid` checked.
+pub fn validate_element(root: &Element) -> Vec<ColladaError> {
+    let version = match root.attributes.get("version").map(|v| v.as_str()) {
+        Some("1.4.0") | Some("1.4.1") => Version::V141,
+        _ => Version::V150,
+    };
+    let mut v = Validator {
+        ctx: ParseContext::for_element(root),
+        version: version,
+        ids: HashSet::new(),
+        errors: Vec::new(),
+    };
+    if root.name != "COLLADA" {
+        v.error(root, ColladaError::MissingElement{
+            structure: "document".to_string(),
+            elem: "COLLADA".to_string(),
+        });
+    }
+    v.check(root, "");
+    v.errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use xmltree::{Element};
+    use error::{ColladaError};
+
+    const VALID: &'static str = r##"<COLLADA version="1.5.0">
+  <asset>
+    <created>2017-01-01T00:00:00Z</created>
+    <modified>2017-01-01T12:30:00.5+02:00</modified>
+    <unit meter="0.01" name="centimeter"/>
+    <up_axis>Z_UP</up_axis>
+  </asset>
+  <library_geometries>
+    <geometry id="box">
+      <mesh>
+        <source id="box-positions">
+          <float_array id="box-positions-array" count="9">0 0 0 1 0 0 0 1 0</float_array>
+          <technique_common>
+            <accessor source="#box-positions-array" count="3" stride="3">
+              <param name="X" type="float"/>
+              <param name="Y" type="float"/>
+              <param name="Z" type="float"/>
+            </accessor>
+          </technique_common>
+        </source>
+        <vertices id="box-vertices">
+          <input semantic="POSITION" source="#box-positions"/>
+        </vertices>
+        <triangles count="1" material="white">
+          <input offset="0" semantic="VERTEX" source="#box-vertices"/>
+          <p>0 1 2</p>
+        </triangles>
+      </mesh>
+    </geometry>
+  </library_geometries>
+  <library_visual_scenes>
+    <visual_scene id="scene">
+      <node id="n" sid="n" type="NODE">
+        <translate sid="t">0 0 1</translate>
+        <instance_geometry url="#box">
+          <bind_material>
+            <technique_common>
+              <instance_material symbol="white" target="#white"/>
+            </technique_common>
+          </bind_material>
+        </instance_geometry>
+      </node>
+    </visual_scene>
+  </library_visual_scenes>
+  <scene>
+    <instance_visual_scene url="#scene"/>
+  </scene>
+  <extra>
+    <technique profile="vendor"><anything id="1 not checked"/></technique>
+  </extra>
+</COLLADA>"##;
+
+    #[test]
+    fn test_validate_valid() {
+        let e = Element::parse(VALID.as_bytes()).unwrap();
+        let errors: Vec<String> = validate_element(&e).iter().map(|e| e.to_string()).collect();
+        assert_eq!(errors, Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_validate_violations() {
+        let data = r##"<COLLADA version="1.5.0">
+  <library_geometries>
+    <geometry id="box">
+      <mesh>
+        <vertices id="box">
+          <input semantic="POSITION" source="#p"/>
+        </vertices>
+        <source id="p">
+          <float_array count="4">0 0 x</float_array>
+        </source>
+        <triangles>
+          <p>0 1 2</p>
+        </triangles>
+      </mesh>
+    </geometry>
+  </library_geometries>
+  <asset>
+    <created>yesterday</created>
+    <modified>2017-01-01T00:00:00Z</modified>
+    <up_axis>W_UP</up_axis>
+  </asset>
+  <library_visual_scenes>
+    <visual_scene id="1scene">
+      <node type="BONE" bogus="x"><matrix>1 0 0</matrix></node>
+    </visual_scene>
+  </library_visual_scenes>
+  <extra/>
+</COLLADA>"##;
+        let e = Element::parse(data.as_bytes()).unwrap();
+        let errors = validate_element(&e);
+        let found: Vec<(String, String)> = errors.iter()
+            .map(|e| (e.position().unwrap().path.clone(), e.inner().to_string()))
+            .collect();
+        let expected = vec![
+            ("/COLLADA/asset", "Invalid collada: <asset> is out of order in <COLLADA>"),
+            ("/COLLADA/library_geometries/geometry/mesh/source", "Invalid collada: <source> is out of order in <mesh>"),
+            ("/COLLADA/library_geometries/geometry/mesh/vertices", "Invalid collada: duplicate id 'box'"),
+            ("/COLLADA/library_geometries/geometry/mesh/source/float_array", "Element <float_array> has invalid data: 0 0 x"),
+            ("/COLLADA/library_geometries/geometry/mesh/source/float_array", "Invalid collada: <float_array> has count 4 but 3 values"),
+            ("/COLLADA/library_geometries/geometry/mesh/triangles", "Element <triangles> is missing required attribute: count"),
+            ("/COLLADA/asset/created", "Element <created> has invalid data: yesterday"),
+            ("/COLLADA/asset/up_axis", "Element <up_axis> has invalid data: W_UP"),
+            ("/COLLADA/library_visual_scenes/visual_scene", "Element <visual_scene> has attribute 'id' with invalid data: 1scene"),
+            ("/COLLADA/library_visual_scenes/visual_scene/node", "Element <node> has attribute 'type' with invalid data: BONE"),
+            ("/COLLADA/library_visual_scenes/visual_scene/node", "Element <node> has invalid attribute 'bogus'"),
+            ("/COLLADA/library_visual_scenes/visual_scene/node/matrix", "Element <matrix> has invalid data: 1 0 0"),
+            ("/COLLADA/extra", "Parsing 'extra' but required <technique> element not found"),
+        ];
+        let expected: Vec<(String, String)> = expected.iter()
+            .map(|x| (x.0.to_string(), x.1.to_string()))
+            .collect();
+        assert_eq!(found, expected);
+    }
+
+    #[test]
+    fn test_validate_version() {
+        let data = r##"<COLLADA version="1.4.1">
+  <asset>
+    <coverage/>
+    <created>2017-01-01T00:00:00Z</created>
+    <modified>2017-01-01T00:00:00Z</modified>
+  </asset>
+  <library_images>
+    <image id="tex" width="256" height="128"><init_from>tex.png</init_from></image>
+  </library_images>
+  <library_joints/>
+</COLLADA>"##;
+        let e = Element::parse(data.as_bytes()).unwrap();
+        let errors = validate_element(&e);
+        assert_eq!(errors.len(), 2);
+        match *errors[0].inner() {
+            ColladaError::InvalidChild{ref child, ..} => assert_eq!(child.as_str(), "library_joints"),
+            _ => assert!(false),
+        }
+        match *errors[1].inner() {
+            ColladaError::InvalidChild{ref child, ..} => assert_eq!(child.as_str(), "coverage"),
+            _ => assert!(false),
+        }
+
+        let mut e = e;
+        e.attributes.insert("version".to_string(), "1.5.0".to_string());
+        let errors = validate_element(&e);
+        assert!(errors.len() > 2);
+    }
+
+    #[test]
+    fn test_validate_syntax() {
+        assert!(is_date_time("2017-01-01T00:00:00Z"));
+        assert!(is_date_time("-0044-03-15T12:00:00"));
+        assert!(is_date_time("2017-12-31T23:59:59.125-05:30"));
+        assert!(!is_date_time("2017-01-01"));
+        assert!(!is_date_time("2017-13-01T00:00:00Z"));
+        assert!(!is_date_time("2017-01-01T00:00:00z"));
+        assert!(!is_date_time("2017-01-01T00:00:00+5"));
+
+        assert!(is_ncname("_a-b.c"));
+        assert!(!is_ncname("1a"));
+        assert!(!is_ncname("a:b"));
+        assert!(!is_ncname(""));
+        assert!(is_sid("joint.1", Version::V141));
+        assert!(!is_sid("joint.1", Version::V150));
+
+        for x in &["1", "-1.5", "+.5", "1.", "1e3", "-1.5E-3", "INF", "-INF", "NaN"] {
+            assert!(is_float(x), "{}", x);
+        }
+        for x in &["", ".", "e3", "1e", "--1", "1.2.3", "inf", "+INF", "infinity", "nan", "0x10", "1_000"] {
+            assert!(!is_float(x), "{}", x);
+        }
+    }
+}
@@ -0,0 +1,20 @@
+use std::fmt;
+
+/// Version of the COLLADA schema a document or element follows
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Version {
+    /// COLLADA 1.4.1
+    V141,
+
+    /// COLLADA 1.5.0
+    V150,
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &Version::V141 => write!(f, "1.4.1"),
+            &Version::V150 => write!(f, "1.5.0"),
+        }
+    }
+}